@@ -0,0 +1,39 @@
+//Example: communities.rs
+//Detect communities with Louvain, print the largest ones and save the partition as "node community" lines with the ids from the edge list
+//  cargo run --example communities -- data/facebook_combined.txt communities.txt [RESOLUTION]
+use std::io::Write;
+use facebook_graph_analysis::community::{community_sizes, louvain_seeded, with_original_ids, write_partition};
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::graph::Graph;
+use facebook_graph_analysis::pipeline::COMMUNITY_SEED;
+
+const USAGE: &str = "usage: communities <EDGE_LIST> <OUT_FILE> [RESOLUTION]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args, &mut std::io::stdout()) {
+        eprintln!("Error: {}\n{}", e, USAGE);
+        std::process::exit(e.exit_code());
+    }
+}
+
+pub fn run(args: &[String], out: &mut impl Write) -> Result<(), GraphError> { //args without the program name: edge list, output file, optional Louvain resolution (default 1.0)
+    let (Some(path), Some(out_file)) = (args.first(), args.get(1)) else {
+        return Err(GraphError::InvalidParameter(String::from("communities needs an edge list and an output file")));
+    };
+    let resolution: f64 = match args.get(2) {
+        Some(r) => r.parse().map_err(|_| GraphError::InvalidParameter(format!("resolution must be a number, got {:?}", r)))?,
+        None => 1.0,
+    };
+
+    let graph = Graph::load_from_file(path)?;
+    let (partition, modularity) = louvain_seeded(&graph, resolution, COMMUNITY_SEED);
+    let sizes = community_sizes(&partition);
+    writeln!(out, "{} communities, modularity {:.4}", sizes.len(), modularity)?;
+    for (community, size) in sizes.iter().take(10) {
+        writeln!(out, "  Community {}: {} people", community, size)?;
+    }
+    write_partition(out_file, &with_original_ids(&graph, &partition))?;
+    writeln!(out, "Wrote the partition to {}", out_file)?;
+    Ok(())
+}
@@ -0,0 +1,37 @@
+//Example: compare_snapshots.rs
+//Diff two edge lists of the same network: overall overlap, then the people whose friend lists changed the most
+//  cargo run --example compare_snapshots -- old.txt new.txt [K]
+use std::io::Write;
+use facebook_graph_analysis::analysis::{graph_jaccard, node_churn, top_churners};
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::graph::Graph;
+
+const USAGE: &str = "usage: compare_snapshots <OLD_EDGE_LIST> <NEW_EDGE_LIST> [K]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args, &mut std::io::stdout()) {
+        eprintln!("Error: {}\n{}", e, USAGE);
+        std::process::exit(e.exit_code());
+    }
+}
+
+pub fn run(args: &[String], out: &mut impl Write) -> Result<(), GraphError> { //args without the program name: old and new edge lists, optional number of churners to list (default 10)
+    let (Some(old_path), Some(new_path)) = (args.first(), args.get(1)) else {
+        return Err(GraphError::InvalidParameter(String::from("compare_snapshots needs an old and a new edge list")));
+    };
+    let k: usize = match args.get(2) {
+        Some(k) => k.parse().map_err(|_| GraphError::InvalidParameter(format!("K must be a whole number, got {:?}", k)))?,
+        None => 10,
+    };
+
+    let (old, new) = (Graph::load_from_file(old_path)?, Graph::load_from_file(new_path)?);
+    let overlap = graph_jaccard(&old, &new);
+    writeln!(out, "Node Jaccard {:.4}, edge Jaccard {:.4}, degree correlation {:.4} over {} shared nodes", overlap.node_jaccard, overlap.edge_jaccard, overlap.degree_correlation, overlap.common_nodes)?;
+    let churn = node_churn(&old, &new);
+    writeln!(out, "Top {} churners (gained / lost friends, neighborhood similarity):", k)?;
+    for (id, stats) in top_churners(&churn, k) {
+        writeln!(out, "  {}: +{} / -{}, {:.4}", id, stats.gained.len(), stats.lost.len(), stats.similarity)?;
+    }
+    Ok(())
+}
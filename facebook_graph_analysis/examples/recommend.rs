@@ -0,0 +1,38 @@
+//Example: recommend.rs
+//Load an edge list and print friend recommendations for one person, using only the library's public API
+//  cargo run --example recommend -- data/facebook_combined.txt 2817 10
+use std::io::Write;
+use facebook_graph_analysis::analysis::{recommend_friends, SimilarityMetric};
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::graph::{Graph, GraphRead};
+use facebook_graph_analysis::labels::NodeId;
+use facebook_graph_analysis::labels::InternalId;
+
+const USAGE: &str = "usage: recommend <EDGE_LIST> <NODE_ID> [K]";
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = run(&args, &mut std::io::stdout()) {
+        eprintln!("Error: {}\n{}", e, USAGE);
+        std::process::exit(e.exit_code());
+    }
+}
+
+pub fn run(args: &[String], out: &mut impl Write) -> Result<(), GraphError> { //args without the program name: edge list, node id as in the file, optional number of recommendations (default 5)
+    let (Some(path), Some(id)) = (args.first(), args.get(1)) else {
+        return Err(GraphError::InvalidParameter(String::from("recommend needs an edge list and a node id")));
+    };
+    let id: usize = id.parse().map_err(|_| GraphError::InvalidParameter(format!("node id must be a whole number, got {:?}", id)))?;
+    let k: usize = match args.get(2) {
+        Some(k) => k.parse().map_err(|_| GraphError::InvalidParameter(format!("K must be a whole number, got {:?}", k)))?,
+        None => 5,
+    };
+
+    let graph = Graph::load_from_file(path)?;
+    let node = graph.internal_id(NodeId(id)).ok_or(GraphError::UnknownNode(id))?;
+    writeln!(out, "Node {} has {} friends; top {} suggestions by Jaccard similarity:", id, graph.degree(node.index()), k)?;
+    for (candidate, score) in recommend_friends(&graph, node, k, SimilarityMetric::Jaccard) {
+        writeln!(out, "  {} ({:.4})", graph.original_id(InternalId(candidate)).0, score)?;
+    }
+    Ok(())
+}
@@ -1,150 +1,5394 @@
 //Module: analysis.rs
 //Here we implement graph algorithms that will analyze social connectivity and structural similarity
-use std::collections::{HashMap, HashSet, VecDeque};
-use crate::graph::Graph;
-
-pub fn average_distance(graph: &Graph) -> f64 { // Computes the average distance between all reachable node pairs in the graph.
-    let mut total_distance = 0usize;
-    let mut count = 0usize;
-
-    for &start in graph.adj_list.keys() { //For each node, use BFS (defined in the function after this) to compute distances to all others. 
-        let distances = bfs_distances(graph, start);
-        for &dist in distances.values() {
-            if dist > 0 {
-                total_distance += dist;
-                count += 1;
-            }
+//Graphs with fewer than two nodes have no pairs to measure, and every function handles them the same way:
+//per-node results (maps and rankings) have one entry per node, so they are empty for n = 0 and score the lone node 0.0 for n = 1;
+//averages over pairs or edges (distance, density, clustering, transitivity, connected-pair fraction...) are 0.0; counts are 0;
+//pair lists (similar pairs, recommendations, bridges) are empty; correlations with nothing to correlate (assortativity) are NaN;
+//functions asking about one node return UnknownNode for ids that are not in the graph. Callers should say "graph too small" rather than print these
+use std::collections::{hash_map::Entry, BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::{Arc, Mutex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::budget::{AnalysisKind, MemoryBudget};
+use crate::community::Partition;
+use crate::error::GraphError;
+use crate::graph::sampling::{reservoir_sample, sample_nodes, StratifiedSample};
+use crate::graph::{parse_numbers, Graph, GraphRead};
+use crate::labels::{InternalId, NodeId};
+use crate::parallel::ParallelismConfig;
+use crate::pipeline::AnalysisResults;
+use crate::view::{EdgeOverlay, GraphView};
+use crate::report::{MetricFamily, NumberFormat};
+use crate::sketch::{mix, HyperLogLog};
+use crate::temporal::TemporalGraph;
+use crate::traversal::Bfs;
+use crate::weighted::WeightedGraph;
+
+pub fn average_distance<G: GraphRead + Sync + ?Sized>(graph: &G) -> f64 { // Computes the average distance between all reachable node pairs in the graph.
+    average_distance_with(graph, &ParallelismConfig::default())
+}
+
+pub fn average_distance_with<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig) -> f64 { //Same as average_distance, with control over how many threads run the BFS passes
+    histogram_mean(&distance_histogram_with(graph, parallelism))
+}
+
+pub fn average_distance_with_progress<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig, progress: &(dyn Fn(usize, usize) + Sync)) -> f64 { //average_distance_with, calling progress with (sources done, sources to do) every PROGRESS_EVERY sources and once at the end
+    histogram_mean(&histogram_from_sweeps(&all_pairs_bfs_with_progress(graph, parallelism, progress)))
+}
+
+pub fn distance_histogram<G: GraphRead + Sync + ?Sized>(graph: &G) -> Vec<usize> { //histogram[d] = ordered pairs (source, target) with target reachable at exactly d hops (histogram[0] is always 0)
+    distance_histogram_with(graph, &ParallelismConfig::default())
+}
+
+pub fn distance_histogram_with<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig) -> Vec<usize> { //Same as distance_histogram, with control over how many threads run the BFS passes
+    //One BFS per node, like average_distance; the mean, median and trimmed mean are then all read off this histogram without another sweep
+    histogram_from_sweeps(&all_pairs_bfs(graph, parallelism))
+}
+
+pub fn distance_distribution<G: GraphRead + Sync + ?Sized>(graph: &G) -> BTreeMap<usize, usize> { //Hop plot: hop count -> unordered pairs {u, v} exactly that many hops apart (pairs in different components are left out), from one BFS per node
+    distribution_from_histogram(&distance_histogram(graph))
+}
+
+pub fn distribution_from_histogram(histogram: &[usize]) -> BTreeMap<usize, usize> { //distance_distribution from a distance_histogram, which counts each pair once from each end
+    histogram.iter().enumerate().filter(|&(d, &count)| d > 0 && count > 0).map(|(d, &count)| (d, count / 2)).collect()
+}
+
+pub fn distance_distribution_sampled(graph: &Graph, num_samples: usize, seed: u64) -> BTreeMap<usize, f64> { //distance_distribution estimated from BFS runs out of num_samples random sources (same seed = same estimate):
+    //each sampled source stands for n / num_samples sources, so a connected graph's estimates add up to exactly n (n - 1) / 2. With num_samples >= num_nodes it is the exact distribution
+    distance_distribution_sampled_with(graph, num_samples, seed, &ParallelismConfig::default())
+}
+
+pub fn distance_distribution_sampled_with(graph: &Graph, num_samples: usize, seed: u64, parallelism: &ParallelismConfig) -> BTreeMap<usize, f64> { //Same as distance_distribution_sampled, with control over how many threads run the BFS passes
+    let (histogram, sources) = sampled_histogram(graph, Some(num_samples), seed, parallelism);
+    let scale = if sources == 0 { 0.0 } else { graph.num_nodes() as f64 / sources as f64 / 2.0 };
+    histogram.iter().enumerate().filter(|&(d, &count)| d > 0 && count > 0).map(|(d, &count)| (d, count as f64 * scale)).collect()
+}
+
+fn sampled_histogram(graph: &Graph, sample: Option<usize>, seed: u64, parallelism: &ParallelismConfig) -> (Vec<usize>, usize) { //distance_histogram over every source (sample None, or at least num_nodes) or that many random ones, and the number of sources used
+    match sample {
+        Some(sources) if sources < graph.num_nodes() => {
+            let nodes = sample_nodes(graph, sources, seed);
+            (histogram_from_sweeps(&bfs_sweeps(graph, &nodes, parallelism, &|_, _| {})), nodes.len())
         }
+        _ => (distance_histogram_with(graph, parallelism), graph.num_nodes()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceDistances { //What one BFS from `node` found, kept small so a sweep over every source fits in memory
+    pub node: usize,
+    pub reached: usize,         //Nodes reachable from `node`, not counting itself
+    pub total_distance: usize,  //Sum of the distances to them
+    pub histogram: Vec<usize>,  //histogram[d] = nodes at exactly d hops (histogram[0] is always 0)
+}
+
+pub fn all_pairs_bfs<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig) -> Vec<SourceDistances> { //One BFS per node (in parallel unless the config is sequential), in graph.nodes() order. Both closeness and the distance histogram are read off this, so a caller wanting both runs n traversals instead of 2n
+    all_pairs_bfs_with_progress(graph, parallelism, &|_, _| {})
+}
+
+pub fn all_pairs_bfs_with_progress<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig, progress: &(dyn Fn(usize, usize) + Sync)) -> Vec<SourceDistances> { //all_pairs_bfs with a progress callback (see PROGRESS_EVERY), possibly called from several threads
+    let nodes: Vec<usize> = graph.nodes().collect();
+    bfs_sweeps(graph, &nodes, parallelism, progress)
+}
+
+pub const PROGRESS_EVERY: usize = 500; //Sources between two progress calls in the all-sources sweeps: a handful of calls on the Facebook graph, nothing next to the BFS work
+
+pub(crate) fn tick(progress: &(dyn Fn(usize, usize) + Sync), done: &AtomicUsize, total: usize) { //Counts one more source done, calling progress on every PROGRESS_EVERY-th and on the last
+    tick_by(progress, done, 1, total);
+}
+
+pub(crate) fn tick_by(progress: &(dyn Fn(usize, usize) + Sync), done: &AtomicUsize, count: usize, total: usize) { //tick for count sources at once, calling progress when they pass a multiple of PROGRESS_EVERY or finish the sweep
+    let before = done.fetch_add(count, AtomicOrdering::Relaxed);
+    let after = before + count;
+    if after / PROGRESS_EVERY > before / PROGRESS_EVERY || after == total {
+        progress(after, total);
     }
+}
+
+pub fn bfs_sweeps<G: GraphRead + Sync + ?Sized>(graph: &G, sources: &[usize], parallelism: &ParallelismConfig, progress: &(dyn Fn(usize, usize) + Sync)) -> Vec<SourceDistances> { //all_pairs_bfs restricted to the given sources, in their order
+    let done = AtomicUsize::new(0);
+    parallelism.map_nodes(sources, |start| {
+        let mut sweep = SourceDistances { node: start, reached: 0, total_distance: 0, histogram: Vec::new() };
+        for d in bfs_distances(graph, InternalId(start)).into_values().filter(|&d| d > 0) {
+            if sweep.histogram.len() <= d {
+                sweep.histogram.resize(d + 1, 0);
+            }
+            sweep.histogram[d] += 1;
+            sweep.reached += 1;
+            sweep.total_distance += d;
+        }
+        tick(progress, &done, sources.len());
+        sweep
+    })
+}
+
+pub fn histogram_from_sweeps(sweeps: &[SourceDistances]) -> Vec<usize> { //Adds up the per-source histograms of all_pairs_bfs into distance_histogram's
+    sweeps.iter().fold(Vec::new(), |mut total, sweep| {
+        if total.len() < sweep.histogram.len() {
+            total.resize(sweep.histogram.len(), 0);
+        }
+        total.iter_mut().zip(&sweep.histogram).for_each(|(t, c)| *t += c);
+        total
+    })
+}
+
+pub fn closeness_from_sweeps(sweeps: &[SourceDistances]) -> Vec<(usize, f64)> { //closeness_centrality's scores (and order) from the results of all_pairs_bfs
+    let mut result: Vec<(usize, f64)> = sweeps
+        .iter()
+        .map(|sweep| (sweep.node, if sweep.total_distance > 0 { sweep.reached as f64 / sweep.total_distance as f64 } else { 0.0 })) //Avoiding division by 0
+        .collect();
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    result
+}
 
+pub fn histogram_mean(histogram: &[usize]) -> f64 { //Mean distance over the pairs in a distance histogram (0.0 if there are none)
+    let (total_distance, count) = histogram.iter().enumerate().fold((0u128, 0u128), |(t, c), (d, &n)| (t + d as u128 * n as u128, c + n as u128)); //Summed over every pair, which can pass u64 on very large graphs
     if count == 0 { 0.0 } else { total_distance as f64 / count as f64 }
 }
 
-pub fn bfs_distances(graph: &Graph, start: usize) -> HashMap<usize, usize> { //Performs Breadth-First Search (BFS) from a start node.
-    let mut visited = HashSet::new();
+pub fn histogram_median(histogram: &[usize]) -> f64 { //Median distance over the pairs in a distance histogram: the middle value, or the mean of the two middle values for an even count (0.0 if there are none)
+    let count: usize = histogram.iter().sum();
+    if count == 0 {
+        return 0.0;
+    }
+    let value_at = |position: usize| { //Distance of the pair at this position once all pairs are sorted by distance
+        let mut seen = 0;
+        histogram.iter().position(|&n| {
+            seen += n;
+            seen > position
+        }).unwrap()
+    };
+    (value_at((count - 1) / 2) + value_at(count / 2)) as f64 / 2.0
+}
+
+pub fn histogram_trimmed_mean(histogram: &[usize], trim_fraction: f64) -> Result<f64, GraphError> { //Mean distance after dropping floor(trim_fraction * pairs) of the shortest and as many of the longest pairs. trim_fraction must be in [0, 0.5)
+    if !(0.0..0.5).contains(&trim_fraction) {
+        return Err(GraphError::InvalidParameter(format!("trim fraction must be at least 0 and below 0.5, got {}", trim_fraction)));
+    }
+    let count: usize = histogram.iter().sum();
+    let dropped = (trim_fraction * count as f64) as usize;
+    let (keep_from, keep_to) = (dropped, count - dropped);
+    let (mut start, mut total_distance) = (0, 0);
+    for (d, &n) in histogram.iter().enumerate() {
+        let kept = (start + n).min(keep_to).saturating_sub(start.max(keep_from)); //Overlap of this distance's positions with the kept range
+        total_distance += d * kept;
+        start += n;
+    }
+    let kept = keep_to - keep_from;
+    Ok(if kept == 0 { 0.0 } else { total_distance as f64 / kept as f64 })
+}
+
+pub fn median_distance(graph: &Graph) -> f64 { //Median shortest path length over all reachable pairs, less pulled up than the mean by a few far-flung nodes
+    histogram_median(&distance_histogram(graph))
+}
+
+pub fn trimmed_average_distance(graph: &Graph, trim_fraction: f64) -> Result<f64, GraphError> { //Average distance ignoring the trim_fraction shortest and trim_fraction longest reachable pairs (0.0 = average_distance)
+    histogram_trimmed_mean(&distance_histogram(graph), trim_fraction)
+}
+
+pub fn bfs_distances<G: GraphRead + ?Sized>(graph: &G, InternalId(start): InternalId) -> HashMap<usize, usize> { //Performs Breadth-First Search (BFS) from a start node. An unknown start node gives an empty map (see bfs_distances_checked)
+    Bfs::new(graph, start).collect()
+}
+
+pub fn bfs_predecessors<G: GraphRead + ?Sized>(graph: &G, InternalId(start): InternalId) -> HashMap<usize, usize> { //BFS tree from start: each reachable node (other than start) maps to the node before it on a shortest path. Among several such nodes the smallest id is kept, so the tree does not depend on neighbor order
     let mut distance = HashMap::new();
+    let mut predecessor = HashMap::new();
     let mut queue = VecDeque::new();
-
-    visited.insert(start);
+    if !graph.contains_node(start) {
+        return predecessor;
+    }
     distance.insert(start, 0);
     queue.push_back(start);
-
-    while let Some(current) = queue.pop_front() { // Uses a queue and a visited set to explore each level of the graph
-        let current_dist = distance[&current];
-        if let Some(neighbors) = graph.adj_list.get(&current) {
-            for &neighbor in neighbors {
-                if !visited.contains(&neighbor) {
-                    visited.insert(neighbor);
-                    distance.insert(neighbor, current_dist + 1);
+    while let Some(current) = queue.pop_front() {
+        let next = distance[&current] + 1;
+        for neighbor in graph.neighbors(current) {
+            match distance.entry(neighbor) {
+                Entry::Vacant(slot) => {
+                    slot.insert(next);
+                    predecessor.insert(neighbor, current);
                     queue.push_back(neighbor);
                 }
+                Entry::Occupied(seen) if *seen.get() == next => {
+                    let best = predecessor.get_mut(&neighbor).unwrap();
+                    *best = (*best).min(current);
+                }
+                Entry::Occupied(_) => {}
             }
         }
     }
+    predecessor
+}
 
-    distance
+pub fn path_from_predecessors(predecessor: &HashMap<usize, usize>, start: usize, end: usize) -> Option<Vec<usize>> { //Walks a bfs_predecessors tree back from end; None if end was not reached from start
+    let mut path = vec![end];
+    let mut current = end;
+    while current != start {
+        current = *predecessor.get(&current)?;
+        path.push(current);
+    }
+    path.reverse();
+    Some(path)
 }
 
-pub fn closeness_centrality(graph: &Graph) -> Vec<(usize, f64)> { //Computes closeness centrality for all nodes in the graph.
-    let mut result = vec![];
-    for &node in graph.adj_list.keys() { //For each node, sum shortest path distances through bfs, then compute inverse. if theres higher closeness then its a more central node.
-        let dist = bfs_distances(graph, node);
-        let sum: usize = dist.values().sum();
-        let closeness = if sum > 0 { //Avoiding division by 0
-            (dist.len() - 1) as f64 / sum as f64
-        } else {
-            0.0
-        };
-        result.push((node, closeness));
+pub fn bfs_path<G: GraphRead + ?Sized>(graph: &G, InternalId(start): InternalId, InternalId(end): InternalId) -> Option<Vec<usize>> { //A shortest path start, ..., end (vec![start] when they are equal). None if either node is unknown or end is unreachable
+    if !graph.contains_node(start) || !graph.contains_node(end) {
+        return None;
+    }
+    path_from_predecessors(&bfs_predecessors(graph, InternalId(start)), start, end)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DijkstraEntry { //Tentative distance to a node; ordered so the BinaryHeap pops the closest node first (ties by node id)
+    distance: f64,
+    node: usize,
+}
+
+impl Eq for DijkstraEntry {}
+
+impl Ord for DijkstraEntry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.distance.total_cmp(&self.distance).then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for DijkstraEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub fn dijkstra_distances<G: GraphRead + ?Sized>(graph: &G, InternalId(start): InternalId) -> HashMap<usize, f64> { //Shortest weighted distance from start to every reachable node (edge weights must be >= 0; unweighted graphs give the BFS hop counts)
+    let mut distances: HashMap<usize, f64> = HashMap::new();
+    if !graph.contains_node(start) {
+        return distances;
+    }
+    let mut heap = BinaryHeap::from([DijkstraEntry { distance: 0.0, node: start }]);
+    while let Some(DijkstraEntry { distance, node }) = heap.pop() {
+        if distances.contains_key(&node) {
+            continue; //Already settled through a shorter path
+        }
+        distances.insert(node, distance);
+        for neighbor in graph.neighbors(node) {
+            if !distances.contains_key(&neighbor) {
+                heap.push(DijkstraEntry { distance: distance + graph.edge_weight(node, neighbor), node: neighbor });
+            }
+        }
+    }
+    distances
+}
+
+pub fn temporal_reachability(graph: &TemporalGraph, source: usize, t_start: u64, t_end: u64) -> HashMap<usize, u64> { //Earliest arrival time at every node reachable from source along time-respecting paths (source itself at t_start)
+    //Only contacts in [t_start, t_end] are used, and a contact at time t passes information on only if one end already had it by t.
+    //Contacts are replayed in time order; those sharing a timestamp are spread by a BFS among themselves, so a chain of same-time contacts still counts
+    let mut arrival: HashMap<usize, u64> = HashMap::new();
+    if !graph.adj_list.contains_key(&source) || t_start > t_end {
+        return arrival;
+    }
+    arrival.insert(source, t_start);
+    let contacts: Vec<(u64, usize, usize)> = graph.contacts_by_time().into_iter().filter(|&(t, _, _)| (t_start..=t_end).contains(&t)).collect();
+    for group in contacts.chunk_by(|a, b| a.0 == b.0) {
+        let time = group[0].0;
+        let mut together: HashMap<usize, Vec<usize>> = HashMap::new();
+        for &(_, u, v) in group {
+            together.entry(u).or_default().push(v);
+            together.entry(v).or_default().push(u);
+        }
+        let mut queue: VecDeque<usize> = together.keys().filter(|node| arrival.contains_key(node)).copied().collect();
+        while let Some(node) = queue.pop_front() {
+            for &friend in &together[&node] {
+                if let Entry::Vacant(entry) = arrival.entry(friend) {
+                    entry.insert(time);
+                    queue.push_back(friend);
+                }
+            }
+        }
     }
-    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+    arrival
+}
+
+pub fn temporal_distance(graph: &TemporalGraph, source: usize, target: usize) -> Option<u64> { //Earliest time information starting at source can reach target over the whole timeline (None without a time-respecting path)
+    temporal_reachability(graph, source, 0, u64::MAX).get(&target).copied()
+}
+
+pub fn average_distance_weighted<G: GraphRead + Sync + ?Sized>(graph: &G) -> f64 { //average_distance with path lengths summed from edge weights (Dijkstra instead of BFS)
+    average_distance_weighted_with(graph, &ParallelismConfig::default())
+}
+
+pub fn average_distance_weighted_with<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig) -> f64 { //Same as average_distance_weighted, with control over how many threads run the Dijkstra passes
+    let nodes: Vec<usize> = graph.nodes().collect();
+    let per_source = parallelism.map_nodes(&nodes, |start| {
+        let distances = dijkstra_distances(graph, InternalId(start));
+        (distances.len() - 1, ordered_sum(distances.into_values()))
+    });
+    let (total, count) = per_source.into_iter().fold((0.0, 0usize), |(t, c), (dc, dt)| (t + dt, c + dc));
+    if count == 0 { 0.0 } else { total / count as f64 }
+}
+
+pub fn closeness_centrality_weighted<G: GraphRead + Sync + ?Sized>(graph: &G) -> Vec<(usize, f64)> { //closeness_centrality with weighted distances: reachable nodes divided by the sum of weighted distances to them
+    closeness_centrality_weighted_with(graph, &ParallelismConfig::default())
+}
+
+pub fn closeness_centrality_weighted_with<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig) -> Vec<(usize, f64)> { //Same as closeness_centrality_weighted, with control over how many threads run the Dijkstra passes
+    let nodes: Vec<usize> = graph.nodes().collect();
+    let mut result = parallelism.map_nodes(&nodes, |node| {
+        let distances = dijkstra_distances(graph, InternalId(node));
+        let reached = distances.len();
+        let sum = ordered_sum(distances.into_values());
+        (node, if sum > 0.0 { (reached - 1) as f64 / sum } else { 0.0 })
+    });
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
     result
 }
 
-pub fn jaccard_similarity(graph: &Graph, u: usize, v: usize) -> f64 { //Computes the Jaccard similarity between two nodes in the graph - measures social similarity based on mutual friends
-    let a = graph.adj_list.get(&u);
-    let b = graph.adj_list.get(&v);
+fn ordered_sum(values: impl Iterator<Item = f64>) -> f64 { //Sum in increasing order, so the HashMap iteration order cannot change the last bits of the result
+    let mut values: Vec<f64> = values.collect();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    values.iter().sum()
+}
 
-    match (a, b) {
-        (Some(set1), Some(set2)) => {
-            let intersection = set1.intersection(set2).count() as f64;
-            let union = set1.union(set2).count() as f64;
-            if union == 0.0 { 0.0 } else { intersection / union }
+pub fn bfs_distances_within<G: GraphRead + ?Sized>(graph: &G, InternalId(start): InternalId, max_depth: usize) -> HashMap<usize, usize> { //bfs_distances that stops after max_depth hops, so only the ball of that radius is explored
+    let mut distance = HashMap::new();
+    if !graph.contains_node(start) {
+        return distance;
+    }
+    distance.insert(start, 0);
+    let mut frontier = vec![start];
+    for depth in 1..=max_depth {
+        let mut next = Vec::new();
+        for &current in &frontier {
+            for neighbor in graph.neighbors(current) {
+                if let Entry::Vacant(slot) = distance.entry(neighbor) {
+                    slot.insert(depth);
+                    next.push(neighbor);
+                }
+            }
         }
-        _ => 0.0,
+        if next.is_empty() {
+            break;
+        }
+        frontier = next;
     }
+    distance
 }
 
-pub fn most_similar_pairs(graph: &Graph, top_n: usize) -> Vec<((usize, usize), f64)> { //Computes top N most similar node pairs based on Jaccard similarity.
-    let mut results = Vec::new();
-    let nodes: Vec<usize> = graph.adj_list.keys().copied().collect();
+pub fn bfs_distances_checked<G: GraphRead + ?Sized>(graph: &G, InternalId(start): InternalId) -> Result<HashMap<usize, usize>, GraphError> { //Same as bfs_distances, but an unknown start node is an UnknownNode error instead of an empty result
+    if !graph.contains_node(start) {
+        return Err(GraphError::UnknownNode(start));
+    }
+    Ok(bfs_distances(graph, InternalId(start)))
+}
 
-    for i in 0..nodes.len() { //For all unique node pairs it will compute similarity, skip sparse nodes, and sort them
-        for j in i + 1..nodes.len() {
-            let u = nodes[i];
-            let v = nodes[j];
-            let neighbors_u = graph.adj_list.get(&u);
-            let neighbors_v = graph.adj_list.get(&v);
-            if neighbors_u.map_or(true, |n| n.len() <= 1) || neighbors_v.map_or(true, |n| n.len() <= 1) {
-                continue;
+pub fn closeness_of<G: GraphRead + ?Sized>(graph: &G, InternalId(node): InternalId) -> Result<f64, GraphError> { //Closeness centrality of a single node (same formula as closeness_centrality)
+    let dist = bfs_distances_checked(graph, InternalId(node))?;
+    let sum: usize = dist.values().sum();
+    Ok(if sum > 0 { (dist.len() - 1) as f64 / sum as f64 } else { 0.0 })
+}
+
+pub fn without_hubs(graph: &Graph, hub_exclusion: Option<usize>) -> (GraphView<'_>, usize) { //Zero-copy view that treats nodes with more friends than the cap as absent, plus how many were left out (None keeps everyone).
+    //The view still filters every step, so callers with no cap run on the graph itself instead, as the *_excluding_hubs functions below do
+    let cap = hub_exclusion.unwrap_or(usize::MAX);
+    let excluded = graph.adjacency().values().filter(|friends| friends.len() > cap).count();
+    (GraphView::degree_range(graph, 0, cap), excluded)
+}
+
+pub fn bfs_distances_excluding_hubs(graph: &Graph, InternalId(start): InternalId, hub_exclusion: Option<usize>) -> HashMap<usize, usize> { //bfs_distances that never steps on a hub (empty if start itself is one)
+    match hub_exclusion {
+        None => bfs_distances(graph, InternalId(start)),
+        Some(_) => bfs_distances(&without_hubs(graph, hub_exclusion).0, InternalId(start)),
+    }
+}
+
+pub fn average_distance_excluding_hubs(graph: &Graph, hub_exclusion: Option<usize>, parallelism: &ParallelismConfig) -> (f64, usize) { //average_distance over paths that avoid hubs, and the number of hubs excluded
+    let (histogram, excluded) = distance_histogram_excluding_hubs(graph, hub_exclusion, parallelism);
+    (histogram_mean(&histogram), excluded)
+}
+
+pub fn distance_histogram_excluding_hubs(graph: &Graph, hub_exclusion: Option<usize>, parallelism: &ParallelismConfig) -> (Vec<usize>, usize) { //distance_histogram over paths that avoid hubs, and the number of hubs excluded
+    match hub_exclusion {
+        None => (distance_histogram_with(graph, parallelism), 0),
+        Some(_) => {
+            let (view, excluded) = without_hubs(graph, hub_exclusion);
+            (distance_histogram_with(&view, parallelism), excluded)
+        }
+    }
+}
+
+pub fn closeness_centrality_excluding_hubs(graph: &Graph, hub_exclusion: Option<usize>, parallelism: &ParallelismConfig) -> (Vec<(usize, f64)>, usize) { //closeness_centrality over paths that avoid hubs (hubs get no score), and the number of hubs excluded
+    match hub_exclusion {
+        None => (closeness_centrality_with(graph, parallelism), 0),
+        Some(_) => {
+            let (view, excluded) = without_hubs(graph, hub_exclusion);
+            (closeness_centrality_with(&view, parallelism), excluded)
+        }
+    }
+}
+
+pub fn closeness_delta_with_edge(graph: &Graph, node: InternalId, new_edge: (InternalId, InternalId)) -> Result<(f64, f64), GraphError> { //Closeness of node (before, after) if new_edge existed, from two BFS runs; the graph is not modified or copied
+    Ok(closeness_deltas_with_edges(graph, node, &[new_edge])?[0])
+}
+
+pub fn closeness_deltas_with_edges(graph: &Graph, InternalId(node): InternalId, new_edges: &[(InternalId, InternalId)]) -> Result<Vec<(f64, f64)>, GraphError> { //Same as closeness_delta_with_edge for many hypothetical edges, each considered on its own (the "before" BFS runs once)
+    let new_edges: Vec<(usize, usize)> = new_edges.iter().map(|&(u, v)| (u.index(), v.index())).collect();
+    if let Some(&(u, v)) = new_edges.iter().find(|&&(u, v)| !graph.contains_node(u) || !graph.contains_node(v)) {
+        return Err(GraphError::UnknownNode(if graph.contains_node(u) { v } else { u }));
+    }
+    let before = closeness_of(graph, InternalId(node))?;
+    new_edges
+        .iter()
+        .map(|&edge| Ok((before, closeness_of(&EdgeOverlay::new(graph, &[edge]), InternalId(node))?)))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistanceProfile { //How many people a node reaches at each distance, as a compact feature vector
+    pub counts: Vec<usize>,     //counts[d - 1] = nodes at distance exactly d, for d = 1..=max_depth
+    pub beyond: usize,          //Reachable nodes farther than max_depth
+    pub median: Option<usize>,  //Median distance to the reachable nodes (nearest rank); None if nothing is reachable or it lies beyond max_depth
+}
+
+pub fn distance_profiles(graph: &Graph, max_depth: usize) -> HashMap<usize, DistanceProfile> { //Distance profile of every node, from one BFS per node that stops at max_depth
+    distance_profiles_with(graph, max_depth, &ParallelismConfig::default())
+}
+
+pub fn distance_profiles_with(graph: &Graph, max_depth: usize, parallelism: &ParallelismConfig) -> HashMap<usize, DistanceProfile> { //Same as distance_profiles, with control over how many threads run the BFS passes
+    //The BFS stops early, so the number of nodes beyond max_depth comes from the size of the node's component instead
+    let mut component_size = HashMap::new();
+    for component in connected_components(graph) {
+        for &node in &component {
+            component_size.insert(node, component.len());
+        }
+    }
+    let nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    let profiles = parallelism.map_nodes(&nodes, |node| {
+        let mut counts = vec![0; max_depth];
+        let mut seen = HashSet::from([node]);
+        let mut frontier = vec![node];
+        for count in counts.iter_mut() {
+            let mut next = Vec::new();
+            for &current in &frontier {
+                for &neighbor in &graph.adjacency()[&current] {
+                    if seen.insert(neighbor) {
+                        next.push(neighbor);
+                    }
+                }
+            }
+            *count = next.len();
+            frontier = next;
+        }
+        let reachable = component_size[&node] - 1;
+        let rank = reachable.div_ceil(2).max(1);
+        let median = counts
+            .iter()
+            .scan(0, |total, &c| {
+                *total += c;
+                Some(*total)
+            })
+            .position(|total| reachable > 0 && total >= rank)
+            .map(|depth| depth + 1);
+        (node, DistanceProfile { beyond: reachable - (seen.len() - 1), counts, median })
+    });
+    profiles.into_iter().collect()
+}
+
+pub fn distance_between(graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId) -> Result<usize, GraphError> { //Shortest path length (number of hops) between two nodes, by the bidirectional search of path_between
+    for node in [u, v] {
+        if !graph.adjacency().contains_key(&node) {
+            return Err(GraphError::UnknownNode(node));
+        }
+    }
+    path_between(graph, InternalId(u), InternalId(v)).map(|path| path.len() - 1).ok_or(GraphError::Disconnected)
+}
+
+pub fn path_between(graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId) -> Option<Vec<usize>> { //A shortest path u, ..., v (vec![u] when they are equal). None if either node is unknown or they are not connected
+    //One BFS grows from each end, a whole level at a time and always on the side with the smaller frontier, until the two touch.
+    //Of the edges joining them at that level the one giving the fewest hops is kept, and the two parent chains are stitched through it.
+    //On a small-world graph each side only goes about half the distance, so far fewer nodes are touched than by bfs_path
+    if !graph.adjacency().contains_key(&u) || !graph.adjacency().contains_key(&v) {
+        return None;
+    }
+    if u == v {
+        return Some(vec![u]);
+    }
+    let mut reached = [HashMap::from([(u, (0, None))]), HashMap::from([(v, (0, None))])]; //Per side: node -> (depth, parent)
+    let mut frontiers = [vec![u], vec![v]];
+    loop {
+        let side = if frontiers[0].len() <= frontiers[1].len() { 0 } else { 1 };
+        if frontiers[side].is_empty() {
+            return None; //This side's whole component was searched without meeting the other
+        }
+        let mut meeting: Option<(usize, usize, usize)> = None; //(hops, node on this side, its friend reached by the other side)
+        let mut next = Vec::new();
+        for &node in &frontiers[side] {
+            let depth = reached[side][&node].0;
+            for &friend in graph.sorted_neighbors(InternalId(node)) {
+                if let Some(&(other_depth, _)) = reached[1 - side].get(&friend) {
+                    let hops = depth + 1 + other_depth;
+                    if meeting.is_none_or(|(best, _, _)| hops < best) {
+                        meeting = Some((hops, node, friend));
+                    }
+                }
+                if let Entry::Vacant(slot) = reached[side].entry(friend) {
+                    slot.insert((depth + 1, Some(node)));
+                    next.push(friend);
+                }
             }
-            let sim = jaccard_similarity(graph, u, v);
-            if sim > 0.0 {
-                results.push(((u, v), sim));
+        }
+        if let Some((_, near, far)) = meeting {
+            let chain = |parents: &HashMap<usize, (usize, Option<usize>)>, mut node: usize| {
+                let mut chain = vec![node];
+                while let Some(parent) = parents[&node].1 {
+                    chain.push(parent);
+                    node = parent;
+                }
+                chain
+            };
+            let mut path = chain(&reached[side], near);
+            path.reverse();
+            path.extend(chain(&reached[1 - side], far));
+            if side == 1 {
+                path.reverse(); //Built from v's end
             }
+            return Some(path);
         }
+        frontiers[side] = next;
     }
+}
 
-    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-    results.truncate(top_n);
-    results
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistStats { //Distribution of the distances from each member of group A to its nearest member of group B
+    pub reachable: usize, //Members of A (after sampling) with a path to B
+    pub unreachable: usize, //Members of A (after sampling) with no path to any member of B
+    pub mean: f64, //The statistics cover reachable members only (NaN if there are none)
+    pub median: f64, //Nearest-rank median, like the percentiles used for roles
+    pub p90: f64,
 }
 
-//TESTS for the algorithms in analysis.rs
-#[cfg(test)] //To include our tests in cargo test
-mod tests { //Submodule to put our tests in
-    use super::*;
-    use crate::graph::Graph;
-    use std::collections::HashSet;
-    fn small_graph() -> Graph { //Creates a simple triangle graph to test our algorithms on
-        let mut graph = Graph::new();
-        graph.adj_list.insert(0, HashSet::from([1, 2]));
-        graph.adj_list.insert(1, HashSet::from([0, 2]));
-        graph.adj_list.insert(2, HashSet::from([0, 1]));
-        graph.num_nodes = 3;
-        graph.num_edges = 3;
-        graph
+pub fn group_distance_stats(graph: &Graph, a: &[usize], b: &[usize], sample: Option<usize>, seed: u64) -> Result<DistStats, GraphError> { //One multi-source BFS from all of B, then a lookup per member of A. With `sample`, only that many random members of A are used (same seed = same sample)
+    let unknown: Vec<usize> = a.iter().chain(b).copied().filter(|n| !graph.adjacency().contains_key(n)).collect();
+    if !unknown.is_empty() {
+        return Err(GraphError::UnknownNodes(unknown));
     }
+    let members = reservoir_sample(a.iter().copied(), sample.unwrap_or(a.len()), seed);
 
-    #[test]
-    fn test_bfs_distances() { //Verifies that BFS correctly computes the shortest distances from node 0.
-        let graph = small_graph();
-        let distances = bfs_distances(&graph, 0);
-        assert_eq!(distances.get(&0), Some(&0));
-        assert_eq!(distances.get(&1), Some(&1));
-        assert_eq!(distances.get(&2), Some(&1));
+    let nearest = multi_source_bfs(graph, b);
+    let distances: Vec<f64> = members.iter().filter_map(|n| nearest.get(n)).map(|&d| d as f64).collect();
+    let (mean, median, p90) = if distances.is_empty() {
+        (f64::NAN, f64::NAN, f64::NAN)
+    } else {
+        (distances.iter().sum::<f64>() / distances.len() as f64, percentile_threshold(distances.clone(), 0.5), percentile_threshold(distances.clone(), 0.9))
+    };
+    Ok(DistStats { reachable: distances.len(), unreachable: members.len() - distances.len(), mean, median, p90 })
+}
+
+fn multi_source_bfs(graph: &Graph, sources: &[usize]) -> HashMap<usize, usize> { //Distance from every reachable node to the closest of the sources (BFS started from all of them at once)
+    let mut distance: HashMap<usize, usize> = sources.iter().map(|&s| (s, 0)).collect();
+    let mut queue: VecDeque<usize> = distance.keys().copied().collect();
+    while let Some(current) = queue.pop_front() {
+        let next = distance[&current] + 1;
+        for &neighbor in &graph.adjacency()[&current] {
+            if let Entry::Vacant(slot) = distance.entry(neighbor) {
+                slot.insert(next);
+                queue.push_back(neighbor);
+            }
+        }
     }
+    distance
+}
 
-    #[test]
-    fn test_average_distance() { //Checks that the average distance computed is accurate for a fully connected triangle graph
-        let graph = small_graph();
-        let avg_dist = average_distance(&graph);
-        assert!((avg_dist - 1.0).abs() < 0.0001);
+pub fn two_hop_reach(graph: &Graph) -> HashMap<usize, usize> { //Counts, for every node, the distinct people exactly two hops away (friends of friends who are not already friends)
+    //Instead of building a new set per node we keep one map from node to the last source that reached it, so hubs don't cause large reallocations
+    let mut seen_by: HashMap<usize, usize> = HashMap::with_capacity(graph.adjacency().len());
+    graph
+        .adjacency()
+        .keys()
+        .map(|&node| (node, count_two_hop(graph, node, &mut seen_by)))
+        .collect()
+}
+
+pub fn two_hop_reach_of(graph: &Graph, InternalId(node): InternalId) -> Result<usize, GraphError> { //Two-hop reach of a single node
+    if !graph.adjacency().contains_key(&node) {
+        return Err(GraphError::UnknownNode(node));
     }
+    Ok(count_two_hop(graph, node, &mut HashMap::new()))
+}
 
-    #[test]
-    fn test_closeness_centrality() { //Ensures closeness centrality returns 1.0 for all nodes in our closed trianlge
-        let graph = small_graph();
-        let closeness = closeness_centrality(&graph);
-        for &(_, centrality) in &closeness {
-            assert!((centrality - 1.0).abs() < 0.0001);
+pub fn two_hop_reach_approx(graph: &Graph, precision: u8) -> Result<HashMap<usize, f64>, GraphError> { //Estimated two-hop reach from HyperLogLog sketches: memory is 2^precision bytes per node however large the neighborhoods get
+    //Each node's sketch starts as its closed neighborhood; one synchronous sweep merges the friends' sketches, giving the ball of radius 2
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let mut radius_one: HashMap<usize, HyperLogLog> = HashMap::with_capacity(nodes.len());
+    for &node in &nodes {
+        let mut sketch = HyperLogLog::new(precision)?;
+        sketch.insert(node);
+        graph.adjacency()[&node].iter().for_each(|&friend| sketch.insert(friend));
+        radius_one.insert(node, sketch);
+    }
+    let mut reach = HashMap::with_capacity(nodes.len());
+    for &node in &nodes {
+        let mut ball = radius_one[&node].clone();
+        for friend in &graph.adjacency()[&node] {
+            ball.merge(&radius_one[friend]);
         }
+        let closed_neighborhood = (graph.adjacency()[&node].len() + 1) as f64; //Known exactly, so only the two-hop part carries sketch error
+        let estimate = if graph.adjacency()[&node].is_empty() { 0.0 } else { (ball.estimate() - closed_neighborhood).max(0.0) }; //Nobody to reach: exactly 0, not sketch noise
+        reach.insert(node, estimate);
     }
+    Ok(reach)
+}
 
-    #[test]
-    fn test_jaccard_similarity() { //Validates Jaccard similarity between two nodes with shared neighbors in the triangle.
-        let graph = small_graph();
-        let sim = jaccard_similarity(&graph, 0, 1);
-        assert!((sim - (1.0 / 3.0)).abs() < 0.0001);
+fn count_two_hop(graph: &Graph, node: usize, seen_by: &mut HashMap<usize, usize>) -> usize {
+    let friends = &graph.adjacency()[&node];
+    let mut count = 0;
+    for friend in friends {
+        for &candidate in &graph.adjacency()[friend] {
+            if candidate == node || friends.contains(&candidate) {
+                continue; //Part of the closed neighborhood, so not two hops away
+            }
+            if seen_by.insert(candidate, node) != Some(node) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+pub fn closeness_centrality<G: GraphRead + Sync + ?Sized>(graph: &G) -> Vec<(usize, f64)> { //Computes closeness centrality for all nodes in the graph.
+    closeness_centrality_with(graph, &ParallelismConfig::default())
+}
+
+pub fn closeness_centrality_with<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig) -> Vec<(usize, f64)> { //Same as closeness_centrality, with control over how many threads run the BFS passes
+    //For each node, sum shortest path distances through bfs, then compute inverse. if theres higher closeness then its a more central node.
+    closeness_from_sweeps(&all_pairs_bfs(graph, parallelism))
+}
+
+pub fn closeness_centrality_with_progress<G: GraphRead + Sync + ?Sized>(graph: &G, parallelism: &ParallelismConfig, progress: &(dyn Fn(usize, usize) + Sync)) -> Vec<(usize, f64)> { //closeness_centrality_with, calling progress with (sources done, sources to do) every PROGRESS_EVERY sources and once at the end
+    closeness_from_sweeps(&all_pairs_bfs_with_progress(graph, parallelism, progress))
+}
+
+pub fn top_k_closeness(graph: &Graph, k: usize) -> Vec<(usize, f64)> { //Exactly closeness_centrality(graph) cut to its first k rows (same scores, same tie order), usually with far less BFS work:
+    //nodes are tried in descending degree order, and a BFS stops as soon as the node provably cannot beat the current k-th best score
+    pruned_top_k_closeness(graph, k).0
+}
+
+fn pruned_top_k_closeness(graph: &Graph, k: usize) -> (Vec<(usize, f64)>, usize) { //top_k_closeness and the number of nodes its BFS runs visited.
+    //Closeness is reached / total distance. After visiting `seen` of the `reach` nodes in its component at BFS depth d, the others are at least d away,
+    //so reach / (total + (reach - seen) * d) bounds the score from above; once that is below the k-th best the node is dropped.
+    //Only strictly lower bounds prune, so a node tying the k-th score is still scored and the tie broken by id as in the full ranking
+    if k == 0 {
+        return (Vec::new(), 0);
+    }
+    let mut reachable: HashMap<usize, usize> = HashMap::new();
+    for component in connected_components(graph) {
+        for &node in &component {
+            reachable.insert(node, component.len() - 1);
+        }
+    }
+    let mut order: Vec<usize> = graph.adjacency().keys().copied().collect();
+    order.sort_by(|&a, &b| graph.degree(b).cmp(&graph.degree(a)).then(a.cmp(&b))); //Hubs first: they tend to score high, which raises the threshold early
+    let mut best: Vec<(usize, f64)> = Vec::with_capacity(k + 1);
+    let mut visited = 0;
+    'nodes: for node in order {
+        let reach = reachable[&node];
+        let threshold = if best.len() == k { best[k - 1].1 } else { f64::NEG_INFINITY };
+        let (mut seen, mut total) = (0usize, 0usize);
+        for (_, depth) in Bfs::new(graph, node) {
+            visited += 1;
+            if depth == 0 {
+                continue;
+            }
+            seen += 1;
+            total += depth;
+            if (reach as f64 / (total + (reach - seen) * depth) as f64) < threshold {
+                continue 'nodes;
+            }
+        }
+        best.push((node, if total > 0 { seen as f64 / total as f64 } else { 0.0 })); //Same formula as closeness_from_sweeps, so the scores are bit-for-bit equal
+        best.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        best.truncate(k);
+    }
+    (best, visited)
+}
+
+pub const ISOLATED_SENTINEL: f64 = -1.0; //Score given to isolated nodes under IsolatedNodes::Sentinel (below every real centrality, which are all >= 0)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IsolatedNodes { //How centrality rankings treat nodes with no friends, whose score is undefined rather than truly low
+    #[default]
+    IncludeAsZero, //Keep them with a score of 0.0
+    Sentinel, //Keep them with ISOLATED_SENTINEL so they always sort last and are easy to spot
+    Exclude, //Leave them out of the ranking
+}
+
+impl IsolatedNodes {
+    pub fn from_name(name: &str) -> Result<Self, GraphError> { //Parses the names used on the command line
+        match name {
+            "zero" => Ok(IsolatedNodes::IncludeAsZero),
+            "sentinel" => Ok(IsolatedNodes::Sentinel),
+            "exclude" => Ok(IsolatedNodes::Exclude),
+            other => Err(GraphError::InvalidParameter(format!("unknown isolated-node mode {:?}, expected zero, sentinel or exclude", other))),
+        }
+    }
+}
+
+pub fn apply_isolated<G: GraphRead + ?Sized>(graph: &G, scores: Vec<(usize, f64)>, treatment: IsolatedNodes) -> (Vec<(usize, f64)>, usize) { //Applies the treatment to a ranking and re-sorts it (highest first, ties by node id). Also returns how many nodes were excluded
+    let before = scores.len();
+    let mut result: Vec<(usize, f64)> = match treatment {
+        IsolatedNodes::IncludeAsZero => scores.into_iter().map(|(node, score)| (node, if graph.degree(node) == 0 { 0.0 } else { score })).collect(),
+        IsolatedNodes::Sentinel => scores.into_iter().map(|(node, score)| (node, if graph.degree(node) == 0 { ISOLATED_SENTINEL } else { score })).collect(),
+        IsolatedNodes::Exclude => scores.into_iter().filter(|&(node, _)| graph.degree(node) > 0).collect(),
+    };
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    let excluded = before - result.len();
+    (result, excluded)
+}
+
+pub fn harmonic_centrality(graph: &Graph) -> Vec<(usize, f64)> { //Sum of 1/distance to every other reachable node, divided by n - 1. Unlike closeness it stays meaningful when the graph is disconnected
+    harmonic_centrality_with(graph, &ParallelismConfig::default())
+}
+
+pub fn harmonic_centrality_with(graph: &Graph, parallelism: &ParallelismConfig) -> Vec<(usize, f64)> { //Same as harmonic_centrality, with control over how many threads run the BFS passes
+    let n = graph.node_count();
+    let nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    let mut result = parallelism.map_nodes(&nodes, |node| {
+        let sum = ordered_sum(bfs_distances(graph, InternalId(node)).into_values().filter(|&d| d > 0).map(|d| 1.0 / d as f64));
+        (node, if n > 1 { sum / (n - 1) as f64 } else { 0.0 })
+    });
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceRing { //The people at one distance from a node and their share of its centrality scores
+    pub distance: usize,
+    pub nodes: usize,
+    pub closeness: f64, //nodes / (sum of all distances): every reachable person adds the same 1/sum, so the rings add up to closeness_of
+    pub harmonic: f64,  //nodes / (distance * (n - 1)): the rings add up to the node's harmonic_centrality
+}
+
+pub fn closeness_breakdown(graph: &Graph, InternalId(node): InternalId) -> Result<Vec<DistanceRing>, GraphError> { //Closeness and harmonic centrality of node split by distance ring (1, 2, 3, ...) from one BFS, to show where a central node's score comes from
+    let dist = bfs_distances_checked(graph, InternalId(node))?;
+    let mut counts: Vec<usize> = Vec::new();
+    for &d in dist.values().filter(|&&d| d > 0) {
+        if counts.len() < d {
+            counts.resize(d, 0);
+        }
+        counts[d - 1] += 1;
+    }
+    let sum: usize = dist.values().sum();
+    let n = graph.node_count();
+    Ok(counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, nodes)| DistanceRing {
+            distance: i + 1,
+            nodes,
+            closeness: nodes as f64 / sum as f64, //sum > 0 whenever there is a ring
+            harmonic: nodes as f64 / ((i + 1) * (n - 1)) as f64,
+        })
+        .collect())
+}
+
+pub fn percentile_ranks<G: GraphRead + ?Sized>(graph: &G, scores: &[(usize, f64)], treatment: IsolatedNodes) -> HashMap<usize, f64> { //Share of ranked nodes scoring at or below each node, in (0, 1]. Sentinel nodes keep ISOLATED_SENTINEL and are not counted; excluded nodes are missing
+    let (ranked, _) = apply_isolated(graph, scores.to_vec(), treatment);
+    let is_sentinel = |node: usize| treatment == IsolatedNodes::Sentinel && graph.degree(node) == 0;
+    let mut values: Vec<f64> = ranked.iter().filter(|&&(node, _)| !is_sentinel(node)).map(|&(_, s)| s).collect();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    ranked
+        .into_iter()
+        .map(|(node, score)| {
+            if is_sentinel(node) {
+                return (node, ISOLATED_SENTINEL);
+            }
+            let at_or_below = values.partition_point(|&v| v <= score);
+            (node, at_or_below as f64 / values.len() as f64)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApproxCloseness {
+    pub scores: Vec<(usize, f64)>, //Estimated closeness, sorted like closeness_centrality (highest first, ties by node id)
+    pub pivots: usize,             //Number of BFS runs actually used
+    pub error_bound: f64,          //With 95% probability every node's estimated average distance is within this many hops of the true one
+}
+
+pub fn closeness_centrality_approx(graph: &Graph, num_samples: usize, seed: u64) -> ApproxCloseness { //Eppstein–Wang: BFS from a few random pivots and estimate each node's average distance from its distances to them
+    //Each pivot BFS gives every node one more sample distance, so k pivots cost k BFS runs instead of n.
+    //Hoeffding's inequality with a union bound over all n nodes gives |error| <= D * sqrt(ln(2n / 0.05) / (2k)), where D bounds the diameter
+    //(twice the largest eccentricity seen from a pivot). With k = n every node is a pivot and the scores equal the exact ones
+    closeness_centrality_approx_with(graph, num_samples, seed, &ParallelismConfig::default())
+}
+
+pub fn closeness_centrality_approx_with(graph: &Graph, num_samples: usize, seed: u64, parallelism: &ParallelismConfig) -> ApproxCloseness { //Same as closeness_centrality_approx, with control over how many threads run the pivot BFS passes
+    let nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    let pivots = sample_nodes(graph, num_samples, seed);
+
+    let per_pivot = parallelism.map_nodes(&pivots, |pivot| bfs_distances(graph, InternalId(pivot)));
+    let mut sums: HashMap<usize, (usize, usize)> = HashMap::new(); //node -> (sum of distances to reachable pivots, number of such pivots other than itself)
+    let mut max_eccentricity = 0;
+    for distances in &per_pivot {
+        for (&node, &d) in distances {
+            if d > 0 {
+                let entry = sums.entry(node).or_insert((0, 0));
+                entry.0 += d;
+                entry.1 += 1;
+            }
+            max_eccentricity = max_eccentricity.max(d);
+        }
+    }
+
+    let mut scores: Vec<(usize, f64)> = nodes
+        .iter()
+        .map(|&node| match sums.get(&node) {
+            Some(&(sum, count)) if sum > 0 => (node, count as f64 / sum as f64), //Same form as the exact (reachable - 1) / sum, so k = n gives identical values
+            _ => (node, 0.0),
+        })
+        .collect();
+    scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    let error_bound = if pivots.is_empty() {
+        f64::INFINITY
+    } else {
+        2.0 * max_eccentricity as f64 * ((2.0 * nodes.len() as f64 / 0.05).ln() / (2.0 * pivots.len() as f64)).sqrt()
+    };
+    ApproxCloseness { scores, pivots: pivots.len(), error_bound }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ApproxDistance {
+    pub mean: f64,    //Estimated average distance over reachable pairs
+    pub std_err: f64, //Standard error of the estimate (0.0 when every node was a source, as the value is then exact)
+    pub samples: usize, //Number of BFS sources actually used
+}
+
+pub fn average_distance_sampled(graph: &Graph, num_samples: usize, seed: u64) -> ApproxDistance { //average_distance from BFS runs out of a few random sources (same seed = same estimate); with num_samples >= num_nodes it is the exact value
+    //The estimate is a ratio: total distance over reachable pairs seen from the sampled sources, like the exact mean over all sources.
+    //Its standard error uses the usual ratio-estimator formula sqrt(sum (t_i - mean * r_i)^2 / (k (k - 1))) / mean(r_i), where source i reaches r_i nodes at total distance t_i
+    average_distance_sampled_with(graph, num_samples, seed, &ParallelismConfig::default())
+}
+
+pub fn average_distance_sampled_with(graph: &Graph, num_samples: usize, seed: u64, parallelism: &ParallelismConfig) -> ApproxDistance { //Same as average_distance_sampled, with control over how many threads run the BFS passes
+    if num_samples >= graph.num_nodes() {
+        return ApproxDistance { mean: average_distance_with(graph, parallelism), std_err: 0.0, samples: graph.num_nodes() };
+    }
+    let sweeps = bfs_sweeps(graph, &sample_nodes(graph, num_samples, seed), parallelism, &|_, _| {});
+    let k = sweeps.len() as f64;
+    let (total, reached) = sweeps.iter().fold((0usize, 0usize), |(t, r), s| (t + s.total_distance, r + s.reached));
+    if reached == 0 {
+        return ApproxDistance { mean: 0.0, std_err: 0.0, samples: sweeps.len() };
+    }
+    let mean = total as f64 / reached as f64;
+    let std_err = if sweeps.len() < 2 {
+        f64::INFINITY
+    } else {
+        let residuals: f64 = sweeps.iter().map(|s| (s.total_distance as f64 - mean * s.reached as f64).powi(2)).sum();
+        (residuals / (k * (k - 1.0))).sqrt() / (reached as f64 / k)
+    };
+    ApproxDistance { mean, std_err, samples: sweeps.len() }
+}
+
+pub const SEPARATION_SOURCES: usize = 1000; //BFS sources the main report samples for degrees_of_separation_table once the graph is too big for exact distances (pipeline::EXACT_DISTANCES_MAX_NODES)
+
+pub fn degrees_of_separation_table(graph: &Graph, max_k: usize, sample: Option<usize>, seed: u64) -> Vec<(usize, f64)> { //(k, share of connected pairs at most k hops apart) for k = 1..=max_k, from every BFS (sample None, or at least num_nodes sources) or from that many random sources (same seed = same table)
+    degrees_of_separation_table_with(graph, max_k, sample, seed, &ParallelismConfig::default())
+}
+
+pub fn degrees_of_separation_table_with(graph: &Graph, max_k: usize, sample: Option<usize>, seed: u64, parallelism: &ParallelismConfig) -> Vec<(usize, f64)> { //Same as degrees_of_separation_table, with control over how many threads run the BFS passes
+    let (histogram, _) = sampled_histogram(graph, sample, seed, parallelism);
+    separation_table(histogram.into_iter().enumerate(), max_k)
+}
+
+pub fn separation_table(counts: impl IntoIterator<Item = (usize, usize)>, max_k: usize) -> Vec<(usize, f64)> { //degrees_of_separation_table from (hops, pairs) counts already at hand, e.g. GraphStats::distance_distribution
+    let counts: Vec<(usize, usize)> = counts.into_iter().filter(|&(hops, _)| hops > 0).collect();
+    let pairs: usize = counts.iter().map(|&(_, count)| count).sum();
+    (1..=max_k)
+        .map(|k| {
+            let within: usize = counts.iter().filter(|&&(hops, _)| hops <= k).map(|&(_, count)| count).sum();
+            (k, if pairs == 0 { 0.0 } else { within as f64 / pairs as f64 })
+        })
+        .collect()
+}
+
+pub fn separation_threshold(table: &[(usize, f64)], coverage: f64) -> Option<usize> { //Smallest k in a degrees_of_separation_table whose share reaches coverage (None if max_k was too small)
+    table.iter().find(|&&(_, share)| share >= coverage).map(|&(k, _)| k)
+}
+
+pub fn average_distance_stratified(graph: &Graph, sample: &StratifiedSample) -> ApproxDistance { //average_distance_sampled over a sampling::stratified_by_degree sample, each source reweighted by its bucket weight
+    average_distance_stratified_with(graph, sample, &ParallelismConfig::default())
+}
+
+pub fn average_distance_stratified_with(graph: &Graph, sample: &StratifiedSample, parallelism: &ParallelismConfig) -> ApproxDistance { //Same as average_distance_stratified, with control over how many threads run the BFS passes
+    let sweeps = bfs_sweeps(graph, &sample.nodes, parallelism, &|_, _| {});
+    let totals: Vec<f64> = sweeps.iter().map(|s| s.total_distance as f64).collect();
+    let reached: Vec<f64> = sweeps.iter().map(|s| s.reached as f64).collect();
+    let (mean, std_err) = stratified_ratio(sample, &totals, &reached);
+    ApproxDistance { mean, std_err, samples: sweeps.len() }
+}
+
+fn stratified_ratio(sample: &StratifiedSample, numerators: &[f64], denominators: &[f64]) -> (f64, f64) { //Weighted ratio sum(w y) / sum(w x) over the sampled nodes, and its standard error
+    //The error is the stratified variance of the linearized residuals y - ratio x, with the finite population correction, so a sample of every node has error 0.
+    //A bucket that contributes a single node has no variance estimate and makes the error infinite
+    let weighted = |values: &[f64]| values.iter().zip(&sample.weights).map(|(v, w)| v * w).sum::<f64>();
+    let denominator = weighted(denominators);
+    if denominator == 0.0 {
+        return (0.0, 0.0);
+    }
+    let ratio = weighted(numerators) / denominator;
+    let mut variance = 0.0;
+    let mut start = 0;
+    for bucket in &sample.buckets {
+        let residuals: Vec<f64> = (start..start + bucket.sampled).map(|i| numerators[i] - ratio * denominators[i]).collect();
+        start += bucket.sampled;
+        if bucket.sampled == bucket.population || bucket.sampled == 0 {
+            continue;
+        }
+        if bucket.sampled < 2 {
+            return (ratio, f64::INFINITY);
+        }
+        let n = bucket.sampled as f64;
+        let mean = residuals.iter().sum::<f64>() / n;
+        let spread = residuals.iter().map(|e| (e - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let population = bucket.population as f64;
+        variance += population * population * (1.0 - n / population) * spread / n;
+    }
+    (ratio, variance.sqrt() / denominator)
+}
+
+pub fn degree_histogram(graph: &Graph) -> Vec<(usize, usize)> { //(degree, number of nodes with it), sorted by degree; degrees nobody has are left out
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for friends in graph.adjacency().values() {
+        *counts.entry(friends.len()).or_insert(0) += 1;
+    }
+    let mut histogram: Vec<(usize, usize)> = counts.into_iter().collect();
+    histogram.sort();
+    histogram
+}
+
+pub fn degree_histogram_log_binned(graph: &Graph, bins: usize) -> Vec<(f64, f64)> { //(bin center, density) over `bins` log-spaced bins, for log-log degree plots; empty bins and isolated nodes (log 0) are left out
+    //Bin i covers degrees in [e_i, e_(i+1)) with e_i = min * ((max + 1) / min)^(i / bins), centered at the geometric mean of its edges.
+    //Density is the bin's count over its width in whole degrees and over the nodes with a friend, so it is comparable across bins of different widths and sums (times width) to 1
+    let histogram: Vec<(usize, usize)> = degree_histogram(graph).into_iter().filter(|&(d, _)| d > 0).collect();
+    let (Some(&(min, _)), Some(&(max, _))) = (histogram.first(), histogram.last()) else {
+        return Vec::new();
+    };
+    if bins == 0 {
+        return Vec::new();
+    }
+    let total: usize = histogram.iter().map(|&(_, c)| c).sum();
+    let edge = |i: usize| min as f64 * ((max + 1) as f64 / min as f64).powf(i as f64 / bins as f64);
+    let mut result = Vec::new();
+    let mut rest = histogram.iter().peekable();
+    for i in 0..bins {
+        let (low, high) = (edge(i), if i + 1 == bins { (max + 1) as f64 } else { edge(i + 1) }); //The last edge is exactly max + 1, whatever the rounding
+        let mut count = 0;
+        while let Some(&&(degree, c)) = rest.peek() {
+            if degree as f64 >= high {
+                break;
+            }
+            count += c;
+            rest.next();
+        }
+        if count > 0 {
+            let width = (high.ceil() - low.ceil()) as usize; //Whole degrees in [low, high)
+            result.push(((low * high).sqrt(), count as f64 / (width * total) as f64));
+        }
+    }
+    result
+}
+
+pub fn density(graph: &Graph) -> f64 { //Fraction of all possible friendships that exist (0.0 for graphs with fewer than two nodes)
+    let n = graph.num_nodes() as f64;
+    if graph.num_nodes() < 2 {
+        return 0.0;
+    }
+    2.0 * graph.num_edges() as f64 / (n * (n - 1.0))
+}
+
+pub fn local_clustering(graph: &Graph) -> HashMap<usize, f64> { //For each node, the fraction of pairs of its friends that are also friends with each other (0.0 for nodes with fewer than two friends)
+    graph.adjacency().keys().map(|&node| (node, clustering_at(graph, node))).collect()
+}
+
+pub fn local_clustering_of(graph: &Graph, InternalId(node): InternalId) -> Result<f64, GraphError> { //Local clustering of a single node
+    if !graph.adjacency().contains_key(&node) {
+        return Err(GraphError::UnknownNode(node));
+    }
+    Ok(clustering_at(graph, node))
+}
+
+fn clustering_at(graph: &Graph, node: usize) -> f64 {
+    let neighbors = &graph.adjacency()[&node];
+    let k = neighbors.len();
+    if k < 2 {
+        return 0.0;
+    }
+    let links: usize = neighbors.iter().map(|n| graph.adjacency()[n].iter().filter(|w| neighbors.contains(w)).count()).sum(); //HashSet lookups, so each node costs the sum of its friends' degrees
+    links as f64 / (k * (k - 1)) as f64 //Each link between two friends was counted from both ends, matching the k(k-1) ordered pairs
+}
+
+pub fn average_clustering(graph: &Graph) -> f64 { //Mean of the local clustering coefficients over all nodes
+    if graph.adjacency().is_empty() {
+        return 0.0;
+    }
+    local_clustering(graph).values().sum::<f64>() / graph.adjacency().len() as f64
+}
+
+pub const SMALL_WORLD_SOURCES: usize = 500; //BFS sources per graph for the average distances in small_world_sigma
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmallWorld { //The parts of sigma = (c / c_rand) / (l / l_rand): well above 1 means much more clustered than chance at about the same distances
+    pub c: f64,      //average_clustering of the graph
+    pub c_rand: f64, //Mean average_clustering of the random graphs
+    pub l: f64,      //Sampled average distance of the graph
+    pub l_rand: f64, //Mean sampled average distance of the random graphs
+    pub sigma: f64,  //NaN when a ratio is undefined (random graphs without triangles, or no paths at all)
+}
+
+pub fn small_world_sigma(graph: &Graph, num_random: usize, seed: u64) -> Result<SmallWorld, GraphError> { //Compares the graph with num_random Erdős–Rényi graphs of the same size and density (seeds seed, seed + 1, ...)
+    if num_random == 0 {
+        return Err(GraphError::InvalidParameter(String::from("small_world_sigma needs at least one random graph")));
+    }
+    let distance = |g: &Graph| average_distance_sampled(g, SMALL_WORLD_SOURCES, seed).mean;
+    let (mut c_rand, mut l_rand) = (0.0, 0.0);
+    for i in 0..num_random as u64 {
+        let random = crate::generate::erdos_renyi(graph.num_nodes(), density(graph), seed.wrapping_add(i))?;
+        c_rand += average_clustering(&random) / num_random as f64;
+        l_rand += distance(&random) / num_random as f64;
+    }
+    let (c, l) = (average_clustering(graph), distance(graph));
+    let sigma = if c_rand > 0.0 && l > 0.0 && l_rand > 0.0 { (c / c_rand) / (l / l_rand) } else { f64::NAN };
+    Ok(SmallWorld { c, c_rand, l, l_rand, sigma })
+}
+
+pub fn transitivity(graph: &Graph) -> Result<f64, GraphError> { //Global clustering: fraction of wedges (paths u-w-v centered on w) that are closed into triangles, or Overflow if the wedges cannot be counted
+    let wedges = wedge_count(graph)?;
+    let mut closed = 0; //Closed wedges are a subset of the wedges, so once those fit this cannot overflow
+    for neighbors in graph.adjacency().values() {
+        for &u in neighbors {
+            closed += graph.adjacency()[&u].iter().filter(|&w| u < *w && neighbors.contains(w)).count();
+        }
+    }
+    Ok(if wedges == 0 { 0.0 } else { closed as f64 / wedges as f64 })
+}
+
+pub fn wedge_count<G: GraphRead + ?Sized>(graph: &G) -> Result<usize, GraphError> { //Number of wedges (paths u-w-v centered on w), the sum of d(d-1)/2: transitivity's denominator counted exactly, or Overflow
+    graph.nodes().try_fold(0usize, |total, node| {
+        pairs_among(graph.degree(node)).and_then(|wedges| total.checked_add(wedges)).ok_or(GraphError::Overflow { what: "wedge count" })
+    })
+}
+
+pub(crate) fn pairs_among(count: usize) -> Option<usize> { //count(count - 1)/2 unordered pairs, computed in u128 so only a result too large for usize is None
+    usize::try_from(count as u128 * count.saturating_sub(1) as u128 / 2).ok()
+}
+
+pub fn count_triangles(graph: &Graph) -> usize { //Number of triangles (sets of three people who are all friends with each other)
+    triangles_per_node(graph).values().sum::<usize>() / 3
+}
+
+pub fn triangles_per_node(graph: &Graph) -> HashMap<usize, usize> { //How many triangles each node is part of (every node appears, 0 if none); the counts sum to 3 x count_triangles
+    //Orient every edge from the lower to the higher (degree, id) end, so each triangle is found exactly once, from its lowest-ranked corner,
+    //and a hub only keeps the few friends ranked above it. Intersecting two sorted out-lists then costs O(m^1.5) overall instead of one triple loop
+    let rank = |node: usize| (graph.adjacency()[&node].len(), node);
+    let higher: HashMap<usize, Vec<usize>> = graph
+        .adjacency()
+        .iter()
+        .map(|(&u, friends)| {
+            let mut out: Vec<usize> = friends.iter().copied().filter(|&v| rank(v) > rank(u)).collect();
+            out.sort_unstable();
+            (u, out)
+        })
+        .collect();
+    let mut counts: HashMap<usize, usize> = graph.adjacency().keys().map(|&node| (node, 0)).collect();
+    for (&u, out_u) in &higher {
+        for &v in out_u {
+            for_each_common(out_u, &higher[&v], |w| {
+                for node in [u, v, w] {
+                    *counts.get_mut(&node).unwrap() += 1;
+                }
+            });
+        }
+    }
+    counts
+}
+
+fn for_each_common(a: &[usize], b: &[usize], mut visit: impl FnMut(usize)) { //Calls visit on every value in both sorted lists, merging them in one pass
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                visit(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+}
+
+pub fn edge_embeddedness(graph: &Graph) -> HashMap<(usize, usize), usize> { //Mutual friends of the two ends of every friendship, keyed (u, v) with u < v so each edge appears once. Embedded ties are the strong ones; 0 means no friend in common
+    let mut embeddedness = HashMap::new();
+    for (&u, friends) in graph.adjacency() {
+        for &v in friends.iter().filter(|&&v| u < v) {
+            embeddedness.insert((u, v), mutual_friend_count(graph, InternalId(u), InternalId(v)));
+        }
+    }
+    embeddedness
+}
+
+pub fn edge_overlap(graph: &Graph, embeddedness: &HashMap<(usize, usize), usize>) -> HashMap<(usize, usize), f64> { //Neighborhood overlap of every friendship (Onnela et al.) from its edge_embeddedness:
+    //mutual friends over the friends either end has besides the other. 0.0 when neither end has another friend (a pendant pair), 1.0 when both ends have exactly the same other friends
+    embeddedness
+        .iter()
+        .map(|(&(u, v), &common)| {
+            let others = graph.adjacency()[&u].len() + graph.adjacency()[&v].len() - 2 - common; //Union of N(u) - {v} and N(v) - {u}
+            ((u, v), if others == 0 { 0.0 } else { common as f64 / others as f64 })
+        })
+        .collect()
+}
+
+pub fn k_core_decomposition(graph: &Graph) -> HashMap<usize, usize> { //Core number of every node: the largest k such that it belongs to a subgraph where everyone has at least k friends
+    degeneracy_order(graph).into_iter().collect()
+}
+
+fn degeneracy_order(graph: &Graph) -> Vec<(usize, usize)> { //(node, core number) in the order the nodes are peeled off: each node has at most its core number of friends later in the list
+    //Batagelj-Zaversnik peeling: nodes are kept sorted by current degree in one array with bucket starts, so removing the lowest-degree node
+    //and moving each of its remaining friends down one bucket are O(1) swaps, O(n + m) overall
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let index: HashMap<usize, usize> = nodes.iter().enumerate().map(|(i, &node)| (node, i)).collect();
+    let mut degree: Vec<usize> = nodes.iter().map(|node| graph.adjacency()[node].len()).collect();
+    let max_degree = degree.iter().copied().max().unwrap_or(0);
+    let mut bucket_start = vec![0; max_degree + 2];
+    for &d in &degree {
+        bucket_start[d + 1] += 1;
+    }
+    for d in 1..bucket_start.len() {
+        bucket_start[d] += bucket_start[d - 1];
+    }
+    let mut order = vec![0; nodes.len()]; //Node indices sorted by current degree
+    let mut position = vec![0; nodes.len()];
+    let mut next_free = bucket_start.clone();
+    for (i, &d) in degree.iter().enumerate() {
+        position[i] = next_free[d];
+        order[position[i]] = i;
+        next_free[d] += 1;
+    }
+    for processed in 0..order.len() {
+        let v = order[processed]; //Lowest current degree left: that degree is its core number
+        for friend in graph.sorted_neighbors(InternalId(nodes[v])) { //In id order so the peeling order is the same on every run
+            let u = index[friend];
+            if degree[u] > degree[v] { //Move u to the front of its bucket, then shrink the bucket so u falls into the one below
+                let d = degree[u];
+                let front = bucket_start[d].max(processed + 1);
+                let w = order[front];
+                order.swap(position[u], front);
+                position.swap(u, w);
+                bucket_start[d] = front + 1;
+                degree[u] -= 1;
+            }
+        }
+    }
+    order.into_iter().map(|i| (nodes[i], degree[i])).collect()
+}
+
+pub fn k_core_subgraph(graph: &Graph, k: usize) -> Graph { //Subgraph induced by the nodes with core number at least k (empty if k is above every core number)
+    let core = k_core_decomposition(graph);
+    let mut subgraph = GraphView::new(graph, |node| core[&node] >= k).to_graph();
+    subgraph.id_map = graph.id_map.clone();
+    subgraph
+}
+
+pub fn maximal_cliques(graph: &Graph, min_size: usize) -> Vec<Vec<usize>> { //Every maximal clique with at least min_size members, each sorted, the list sorted by size (largest first) then by members
+    let mut cliques = Vec::new();
+    for_each_maximal_clique(graph, min_size, |clique| {
+        cliques.push(clique.to_vec());
+        ControlFlow::<()>::Continue(())
+    });
+    cliques.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    cliques.dedup();
+    cliques
+}
+
+pub fn for_each_maximal_clique<B>(graph: &Graph, min_size: usize, mut visit: impl FnMut(&[usize]) -> ControlFlow<B>) -> Option<B> { //Hands each maximal clique of at least min_size members (sorted) to visit as it is found,
+    //without keeping them, until visit breaks; returns the break value. Each clique comes exactly once, in a fixed order for a given graph.
+    //The count can grow exponentially with density: the Facebook data has over forty million maximal cliques, so break once you have enough
+    let mut stopped = None;
+    enumerate_cliques(graph, &mut |clique| match visit(clique) {
+        ControlFlow::Continue(()) => min_size,
+        ControlFlow::Break(value) => {
+            stopped = Some(value);
+            usize::MAX //No clique is that large, so every remaining branch is cut
+        }
+    }, min_size);
+    stopped
+}
+
+pub fn largest_clique(graph: &Graph) -> Vec<usize> { //A maximum clique, sorted (the smallest members among equally large ones); empty for an empty graph
+    let mut largest: Vec<usize> = Vec::new();
+    enumerate_cliques(graph, &mut |clique| {
+        if clique.len() > largest.len() || (clique.len() == largest.len() && clique < largest.as_slice()) {
+            largest = clique.to_vec();
+        }
+        largest.len() //Branches that cannot beat it are cut; ties still come through for the smallest members
+    }, 1);
+    largest
+}
+
+fn enumerate_cliques(graph: &Graph, visit: &mut dyn FnMut(&[usize]) -> usize, min_size: usize) { //Bron-Kerbosch with pivoting, started once per node in degeneracy order:
+    //node v only looks for cliques among its later friends (at most its core number of them) and skips those containing an earlier friend,
+    //so every maximal clique is found once, from its earliest member. visit returns the smallest size still wanted, so searches can raise the bar.
+    //Each start works on bitsets over v's friends, so the set operations of the search are a few word ANDs instead of hash lookups
+    let order = degeneracy_order(graph);
+    let position: HashMap<usize, usize> = order.iter().enumerate().map(|(i, &(node, _))| (node, i)).collect();
+    let mut min_size = min_size;
+    for (i, &(node, _)) in order.iter().enumerate() {
+        let (later, earlier): (Vec<usize>, Vec<usize>) = graph.sorted_neighbors(InternalId(node)).iter().filter(|&&friend| friend != node).partition(|&&friend| position[&friend] > i);
+        if 1 + later.len() < min_size {
+            continue;
+        }
+        let local: Vec<usize> = later.iter().chain(&earlier).copied().collect(); //Bit j stands for local[j]: the later friends, then the earlier ones
+        let index: HashMap<usize, usize> = local.iter().enumerate().map(|(j, &friend)| (friend, j)).collect();
+        let words = local.len().div_ceil(64);
+        let mut adjacent = vec![vec![0u64; words]; local.len()]; //Only edges touching a later friend are needed: the excluded set is only ever intersected with them
+        for (a, &friend) in later.iter().enumerate() {
+            for b in graph.sorted_neighbors(InternalId(friend)).iter().filter_map(|other| index.get(other)).copied().filter(|&b| b != a) {
+                adjacent[a][b / 64] |= 1 << (b % 64);
+                adjacent[b][a / 64] |= 1 << (a % 64);
+            }
+        }
+        let mut candidates = vec![0u64; words];
+        let mut excluded = vec![0u64; words];
+        (0..later.len()).for_each(|j| candidates[j / 64] |= 1 << (j % 64));
+        (later.len()..local.len()).for_each(|j| excluded[j / 64] |= 1 << (j % 64));
+        let mut clique = vec![node];
+        bron_kerbosch(&local, &adjacent, &mut clique, candidates, excluded, visit, &mut min_size);
+    }
+}
+
+fn set_bits(set: &[u64]) -> impl Iterator<Item = usize> + '_ { //Positions of the 1 bits, in increasing order
+    set.iter().enumerate().flat_map(|(w, &word)| (0..64).filter(move |bit| word >> bit & 1 == 1).map(move |bit| w * 64 + bit))
+}
+
+fn bron_kerbosch(local: &[usize], adjacent: &[Vec<u64>], clique: &mut Vec<usize>, mut candidates: Vec<u64>, mut excluded: Vec<u64>, visit: &mut dyn FnMut(&[usize]) -> usize, min_size: &mut usize) { //Extends clique by the candidates
+    //(friends of all its members); excluded are such friends already tried, so a clique that could still take one is not maximal
+    let count = |set: &[u64]| set.iter().map(|word| word.count_ones() as usize).sum::<usize>();
+    let num_candidates = count(&candidates);
+    if num_candidates == 0 {
+        if count(&excluded) == 0 && clique.len() >= *min_size {
+            let mut sorted = clique.clone();
+            sorted.sort();
+            *min_size = visit(&sorted);
+        }
+        return;
+    }
+    if clique.len() + num_candidates < *min_size {
+        return; //Even taking every candidate would be too small
+    }
+    let both: Vec<u64> = candidates.iter().zip(&excluded).map(|(c, x)| c | x).collect();
+    let shared = |u: usize| candidates.iter().zip(&adjacent[u]).map(|(c, a)| (c & a).count_ones() as usize).sum::<usize>();
+    let pivot = set_bits(&both).max_by_key(|&u| (shared(u), std::cmp::Reverse(u))).unwrap();
+    let branches: Vec<usize> = set_bits(&candidates).filter(|&v| adjacent[pivot][v / 64] >> (v % 64) & 1 == 0).collect(); //A maximal clique holds the pivot or one of its non-friends
+    for v in branches {
+        let within = |set: &[u64]| set.iter().zip(&adjacent[v]).map(|(s, a)| s & a).collect();
+        clique.push(local[v]);
+        bron_kerbosch(local, adjacent, clique, within(&candidates), within(&excluded), visit, min_size);
+        clique.pop();
+        candidates[v / 64] &= !(1 << (v % 64));
+        excluded[v / 64] |= 1 << (v % 64);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransitivityEstimate {
+    pub estimate: f64, //Fraction of sampled wedges that were closed
+    pub lower: f64,    //95% Wilson confidence interval for the true transitivity
+    pub upper: f64,
+    pub wedges_sampled: usize,
+}
+
+pub fn transitivity_sampled(graph: &Graph, num_wedges: usize, seed: u64) -> TransitivityEstimate { //Estimates transitivity from random wedges, for graphs too large for exact counting (same seed = same estimate)
+    //A uniformly random wedge is a center node chosen with weight d(d-1)/2 (alias table, O(1) per draw) plus two distinct random friends of it
+    let mut centers: Vec<(usize, Vec<usize>)> = graph
+        .adjacency()
+        .iter()
+        .filter(|(_, n)| n.len() >= 2)
+        .map(|(&node, n)| {
+            let mut friends: Vec<usize> = n.iter().copied().collect();
+            friends.sort();
+            (node, friends)
+        })
+        .collect();
+    centers.sort_by_key(|(node, _)| *node); //Sorted so the draws do not depend on HashMap order
+    if centers.is_empty() || num_wedges == 0 {
+        return TransitivityEstimate { estimate: 0.0, lower: 0.0, upper: 0.0, wedges_sampled: 0 };
+    }
+    let weights: Vec<f64> = centers.iter().map(|(_, f)| (f.len() * (f.len() - 1) / 2) as f64).collect();
+    let (probability, alias) = alias_table(&weights);
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut closed = 0;
+    for _ in 0..num_wedges {
+        let slot = rng.gen_range(0..centers.len());
+        let center = if rng.gen::<f64>() < probability[slot] { slot } else { alias[slot] };
+        let friends = &centers[center].1;
+        let a = rng.gen_range(0..friends.len());
+        let mut b = rng.gen_range(0..friends.len() - 1);
+        if b >= a {
+            b += 1;
+        }
+        if graph.adjacency()[&friends[a]].contains(&friends[b]) {
+            closed += 1;
+        }
+    }
+
+    let n = num_wedges as f64;
+    let p = closed as f64 / n;
+    let z = 1.96;
+    let denominator = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / denominator;
+    let margin = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+    TransitivityEstimate { estimate: p, lower: (center - margin).max(0.0), upper: (center + margin).min(1.0), wedges_sampled: num_wedges }
+}
+
+pub fn transitivity_stratified(graph: &Graph, sample: &StratifiedSample) -> TransitivityEstimate { //Estimates transitivity from the wedges centered on a sampling::stratified_by_degree sample, each center reweighted by its bucket weight
+    //Closed and total wedges at every sampled center are counted exactly; the interval is the ratio estimate +/- 1.96 standard errors, clamped to [0, 1]
+    let (mut closed, mut wedges) = (Vec::new(), Vec::new());
+    for &node in &sample.nodes {
+        let neighbors = &graph.adjacency()[&node];
+        let k = neighbors.len() as f64;
+        wedges.push(k * (k - 1.0) / 2.0);
+        closed.push(neighbors.iter().map(|u| graph.adjacency()[u].iter().filter(|&w| u < w && neighbors.contains(w)).count()).sum::<usize>() as f64);
+    }
+    let (estimate, std_err) = stratified_ratio(sample, &closed, &wedges);
+    let margin = 1.96 * std_err;
+    TransitivityEstimate { estimate, lower: (estimate - margin).max(0.0), upper: (estimate + margin).min(1.0), wedges_sampled: wedges.iter().sum::<f64>() as usize }
+}
+
+fn alias_table(weights: &[f64]) -> (Vec<f64>, Vec<usize>) { //Vose's alias method: slot i keeps itself with probability[i], otherwise it gives alias[i]
+    let n = weights.len();
+    let total: f64 = weights.iter().sum();
+    let mut scaled: Vec<f64> = weights.iter().map(|w| w * n as f64 / total).collect();
+    let (mut probability, mut alias) = (vec![1.0; n], vec![0; n]);
+    let (mut small, mut large): (Vec<usize>, Vec<usize>) = (0..n).partition(|&i| scaled[i] < 1.0);
+    while let (Some(s), Some(&l)) = (small.pop(), large.last()) {
+        probability[s] = scaled[s];
+        alias[s] = l;
+        scaled[l] -= 1.0 - scaled[s];
+        if scaled[l] < 1.0 {
+            large.pop();
+            small.push(l);
+        }
+    }
+    (probability, alias) //Leftover slots (only from rounding) keep probability 1.0
+}
+
+pub fn connected_components(graph: &Graph) -> Vec<Vec<usize>> { //Groups of nodes connected by some path, largest first (members sorted, ties by smallest member)
+    let mut seen = HashSet::new();
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let mut components = Vec::new();
+    for node in nodes {
+        if seen.contains(&node) {
+            continue;
+        }
+        let mut members: Vec<usize> = bfs_distances(graph, InternalId(node)).into_keys().collect();
+        members.sort();
+        seen.extend(members.iter().copied());
+        components.push(members);
+    }
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+    components
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bipartiteness {
+    TwoColoring(HashMap<usize, bool>), //Side of every node (false for the BFS root of each component and everyone an even number of hops from it)
+    OddCycle(Vec<usize>),              //Nodes of an odd cycle in order; the last is a friend of the first. Proof that no two-coloring exists
+}
+
+pub fn is_bipartite(graph: &Graph) -> Option<HashMap<usize, bool>> { //A two-coloring where every friendship joins the two sides, or None if the graph has an odd cycle (see bipartite_check for one)
+    match bipartite_check(graph) {
+        Bipartiteness::TwoColoring(sides) => Some(sides),
+        Bipartiteness::OddCycle(_) => None,
+    }
+}
+
+pub fn bipartite_check(graph: &Graph) -> Bipartiteness { //Colors each component by BFS depth parity (components in order of smallest member); an edge between two nodes at the same depth closes an odd cycle
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let (mut depth, mut parent): (HashMap<usize, usize>, HashMap<usize, usize>) = (HashMap::new(), HashMap::new());
+    for &root in &nodes {
+        if depth.contains_key(&root) {
+            continue;
+        }
+        let mut bfs = Bfs::new(graph, root);
+        let mut component = Vec::new();
+        while let Some((node, d)) = bfs.next() {
+            depth.insert(node, d);
+            if let Some(p) = bfs.parent(node) {
+                parent.insert(node, p);
+            }
+            component.push(node);
+        }
+        for &u in &component {
+            if let Some(&v) = graph.sorted_neighbors(InternalId(u)).iter().find(|&v| depth[v] == depth[&u]) {
+                //u and v are equally deep, so climbing both BFS branches in step meets at their closest common ancestor: the two branches plus u-v are a cycle of odd length
+                let (mut up_u, mut up_v) = (vec![u], vec![v]);
+                while up_u.last() != up_v.last() {
+                    up_u.push(parent[up_u.last().unwrap()]);
+                    up_v.push(parent[up_v.last().unwrap()]);
+                }
+                up_v.pop();
+                up_u.extend(up_v.into_iter().rev());
+                return Bipartiteness::OddCycle(up_u);
+            }
+        }
+    }
+    Bipartiteness::TwoColoring(depth.into_iter().map(|(node, d)| (node, d % 2 == 1)).collect())
+}
+
+pub fn articulation_points(graph: &Graph) -> HashSet<usize> { //People whose removal splits their component into more pieces
+    cut_structure(graph).0
+}
+
+pub fn bridges(graph: &Graph) -> Vec<(usize, usize)> { //Friendships whose removal splits their component, as (u, v) with u < v, sorted
+    cut_structure(graph).1
+}
+
+fn cut_structure(graph: &Graph) -> (HashSet<usize>, Vec<(usize, usize)>) { //Tarjan's low-link DFS, with an explicit stack so long chains cannot overflow the call stack
+    //low[u] = earliest discovery time reachable from u's DFS subtree using at most one back edge. A child v with low[v] > disc[u] means u-v is a bridge,
+    //low[v] >= disc[u] means u separates v's subtree (for the root: it needs two or more DFS children)
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let (mut disc, mut low): (HashMap<usize, usize>, HashMap<usize, usize>) = (HashMap::new(), HashMap::new());
+    let (mut points, mut bridges) = (HashSet::new(), Vec::new());
+    for &root in &nodes {
+        if disc.contains_key(&root) {
+            continue;
+        }
+        disc.insert(root, disc.len());
+        low.insert(root, disc[&root]);
+        let mut root_children = 0;
+        let mut stack: Vec<(usize, Option<usize>, usize)> = vec![(root, None, 0)]; //(node, DFS parent, next neighbor to look at)
+        while let Some((u, parent, next)) = stack.last_mut() {
+            let (u, parent) = (*u, *parent);
+            let friends = graph.sorted_neighbors(InternalId(u));
+            if let Some(&v) = friends.get(*next) {
+                *next += 1;
+                if Some(v) == parent || v == u {
+                    continue;
+                }
+                match disc.get(&v) {
+                    Some(&seen) => {
+                        let l = low.get_mut(&u).unwrap();
+                        *l = (*l).min(seen);
+                    }
+                    None => {
+                        disc.insert(v, disc.len());
+                        low.insert(v, disc[&v]);
+                        stack.push((v, Some(u), 0));
+                    }
+                }
+                continue;
+            }
+            stack.pop();
+            let Some(p) = parent else { continue };
+            let low_u = low[&u];
+            let l = low.get_mut(&p).unwrap();
+            *l = (*l).min(low_u);
+            if low_u > disc[&p] {
+                bridges.push((p.min(u), p.max(u)));
+            }
+            if p == root {
+                root_children += 1;
+            } else if low_u >= disc[&p] {
+                points.insert(p);
+            }
+        }
+        if root_children >= 2 {
+            points.insert(root);
+        }
+    }
+    bridges.sort();
+    (points, bridges)
+}
+
+pub fn geodesic_subgraph(graph: &Graph, s: usize, t: usize) -> Option<Graph> { //Union of all shortest paths from s to t: every node and edge on at least one of them. None if either node is unknown or t is unreachable
+    //A node v lies on a geodesic when d(s, v) + d(v, t) = d(s, t); an edge u-v does when d(s, u) + 1 + d(v, t) = d(s, t)
+    let from_s = bfs_distances(graph, InternalId(s));
+    let &length = from_s.get(&t)?;
+    let from_t = bfs_distances(graph, InternalId(t));
+    let on_path = |v: &usize| from_s.get(v).zip(from_t.get(v)).is_some_and(|(a, b)| a + b == length);
+    let mut geodesic = Graph::new();
+    geodesic.add_node(InternalId(s)); //Keeps s when s == t
+    for u in graph.adjacency().keys().filter(|u| on_path(u)) {
+        for v in graph.adjacency()[u].iter().filter(|v| on_path(v)) {
+            if from_s[u] + 1 + from_t[v] == length {
+                geodesic.add_edge(InternalId(*u), InternalId(*v));
+            }
+        }
+    }
+    geodesic.id_map = graph.id_map.clone();
+    Some(geodesic)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemovalStrategy { //Which nodes robustness_profile takes out next
+    RandomRemoval,           //Failures: a seeded shuffle of every node
+    HighestDegreeFirst,      //Attack on the hubs, degrees recomputed on what is left after every batch (ties by node id)
+    HighestBetweennessFirst, //Attack on the bridges between groups, betweenness recomputed after every batch: one Brandes run per step, so slow on large graphs
+}
+
+pub fn robustness_profile(graph: &Graph, strategy: RemovalStrategy, fraction_steps: usize, seed: u64) -> Vec<(f64, f64)> { //Removes the nodes in fraction_steps equal batches and records (fraction removed, largest remaining component / original node count)
+    //after each, starting from (0.0, ...) for the intact graph. Random failure of a scale-free network shrinks it slowly; removing its hubs first breaks it up fast
+    let n = graph.num_nodes();
+    let giant = |g: &Graph| connected_components(g).first().map_or(0, Vec::len) as f64 / n.max(1) as f64;
+    let mut working = graph.induced_subgraph(&graph.adjacency().keys().copied().collect());
+    let mut profile = vec![(0.0, giant(&working))];
+    let mut failures: Vec<usize> = graph.adjacency().keys().copied().collect();
+    failures.sort();
+    failures.shuffle(&mut StdRng::seed_from_u64(seed));
+    let mut removed = 0;
+    for step in 1..=fraction_steps {
+        let batch = n * step / fraction_steps - removed;
+        let targets: Vec<usize> = match strategy {
+            RemovalStrategy::RandomRemoval => failures[removed..removed + batch].to_vec(),
+            RemovalStrategy::HighestDegreeFirst => {
+                let mut degrees = working.all_degrees();
+                degrees.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                degrees.into_iter().take(batch).map(|(node, _)| node).collect()
+            }
+            RemovalStrategy::HighestBetweennessFirst => betweenness_centrality(&working).into_iter().take(batch).map(|(node, _)| node).collect(),
+        };
+        for node in targets {
+            working.remove_node(InternalId(node));
+        }
+        removed += batch;
+        profile.push((removed as f64 / n as f64, giant(&working)));
+    }
+    profile
+}
+
+pub fn num_components(graph: &Graph) -> usize { //How many connected components the graph has (isolated nodes count as their own)
+    connected_components(graph).len()
+}
+
+pub fn is_connected(graph: &Graph) -> bool { //Whether every pair of nodes is joined by some path (an empty graph counts as connected)
+    num_components(graph) <= 1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphOverlap { //How much two graphs over (partly) the same people agree; nodes are matched by their input-file ids
+    pub node_jaccard: f64,       //Shared nodes / nodes in either graph
+    pub edge_jaccard: f64,       //Shared friendships / friendships in either graph (each undirected edge counted once)
+    pub degree_correlation: f64, //Pearson correlation of degrees over the shared nodes (0.0 with fewer than two of them)
+    pub common_nodes: usize,
+}
+
+pub fn graph_jaccard(a: &Graph, b: &Graph) -> GraphOverlap { //Intersection over union of the node and edge sets of two graphs, e.g. two snapshots of one network
+    let nodes = |g: &Graph| -> HashSet<NodeId> { g.adjacency().keys().map(|&n| g.original_id(InternalId(n))).collect() };
+    let edges = |g: &Graph| -> HashSet<(NodeId, NodeId)> {
+        g.adjacency()
+            .iter()
+            .flat_map(|(&u, friends)| friends.iter().map(move |&v| (g.original_id(InternalId(u)), g.original_id(InternalId(v)))))
+            .filter(|(u, v)| u < v)
+            .collect()
+    };
+    let ratio = |shared: usize, total: usize| if total == 0 { 0.0 } else { shared as f64 / total as f64 };
+    let (nodes_a, nodes_b) = (nodes(a), nodes(b));
+    let (edges_a, edges_b) = (edges(a), edges(b));
+    let mut common: Vec<NodeId> = nodes_a.intersection(&nodes_b).copied().collect();
+    common.sort();
+    let degree = |g: &Graph, id: NodeId| g.adjacency()[&g.internal_id(id).unwrap().index()].len() as f64;
+    let pairs: Vec<(f64, f64)> = common.iter().map(|&id| (degree(a, id), degree(b, id))).collect();
+    GraphOverlap {
+        node_jaccard: ratio(common.len(), nodes_a.union(&nodes_b).count()),
+        edge_jaccard: ratio(edges_a.intersection(&edges_b).count(), edges_a.union(&edges_b).count()),
+        degree_correlation: pearson(&pairs),
+        common_nodes: common.len(),
+    }
+}
+
+fn pearson(pairs: &[(f64, f64)]) -> f64 { //Correlation of the two coordinates. If either side is constant it is 1.0 when both sides match exactly, 0.0 otherwise
+    if pairs.len() < 2 {
+        return 0.0;
+    }
+    let n = pairs.len() as f64;
+    let (mean_x, mean_y) = (pairs.iter().map(|p| p.0).sum::<f64>() / n, pairs.iter().map(|p| p.1).sum::<f64>() / n);
+    let covariance: f64 = pairs.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let var_x: f64 = pairs.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let var_y: f64 = pairs.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if var_x == 0.0 || var_y == 0.0 {
+        return if pairs.iter().all(|(x, y)| x == y) { 1.0 } else { 0.0 };
+    }
+    covariance / (var_x * var_y).sqrt()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChurnStats { //How one person's friend list changed between two snapshots; all ids are input-file ids
+    pub similarity: f64,   //Jaccard of the old and new friend sets (1.0 when both are empty, 0.0 when the node is in only one snapshot)
+    pub gained: Vec<usize>, //Friends in the new snapshot but not the old one, sorted
+    pub lost: Vec<usize>,   //Friends in the old snapshot but not the new one, sorted
+}
+
+impl ChurnStats {
+    pub fn churn(&self) -> usize { //Total number of friendships that appeared or disappeared
+        self.gained.len() + self.lost.len()
+    }
+}
+
+pub fn node_churn(old: &Graph, new: &Graph) -> HashMap<usize, ChurnStats> { //Per-node neighborhood change between two snapshots, keyed by input-file id. A node in only one snapshot gains or loses all of its friends
+    let friends = |g: &Graph, id: usize| -> HashSet<usize> {
+        g.internal_id(NodeId(id)).map_or_else(HashSet::new, |node| g.adjacency()[&node.index()].iter().map(|&f| g.original_id(InternalId(f)).0).collect())
+    };
+    let ids: HashSet<usize> = old.adjacency().keys().map(|&n| old.original_id(InternalId(n)).0).chain(new.adjacency().keys().map(|&n| new.original_id(InternalId(n)).0)).collect();
+    ids.into_iter()
+        .map(|id| {
+            let (before, after) = (friends(old, id), friends(new, id));
+            let mut gained: Vec<usize> = after.difference(&before).copied().collect();
+            let mut lost: Vec<usize> = before.difference(&after).copied().collect();
+            gained.sort();
+            lost.sort();
+            let in_both = old.internal_id(NodeId(id)).is_some() && new.internal_id(NodeId(id)).is_some();
+            let union = before.union(&after).count();
+            let similarity = match (in_both, union) {
+                (false, _) => 0.0,
+                (true, 0) => 1.0,
+                (true, _) => before.intersection(&after).count() as f64 / union as f64,
+            };
+            (id, ChurnStats { similarity, gained, lost })
+        })
+        .collect()
+}
+
+pub fn top_churners(churn: &HashMap<usize, ChurnStats>, k: usize) -> Vec<(usize, &ChurnStats)> { //The k nodes whose friend lists changed the most (then lowest similarity, then smallest id)
+    let mut ranked: Vec<(usize, &ChurnStats)> = churn.iter().map(|(&id, stats)| (id, stats)).collect();
+    ranked.sort_by(|a, b| b.1.churn().cmp(&a.1.churn()).then(a.1.similarity.total_cmp(&b.1.similarity)).then(a.0.cmp(&b.0)));
+    ranked.truncate(k);
+    ranked
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankChange { //Where one node sits in two rankings of the same nodes (ranks start at 1)
+    pub node: usize,
+    pub rank_before: usize,
+    pub rank_after: usize,
+    pub score_before: f64,
+    pub score_after: f64,
+}
+
+impl RankChange {
+    pub fn rank_shift(&self) -> i64 { //Places gained (negative when the node fell)
+        self.rank_before as i64 - self.rank_after as i64
+    }
+
+    pub fn score_shift(&self) -> f64 {
+        self.score_after - self.score_before
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankingDiff { //How a ranking changed, e.g. closeness with and without hub exclusion
+    pub top_k: usize,
+    pub rank_movers: Vec<RankChange>,  //The top_k largest rank changes among nodes in both rankings (ties by node id)
+    pub score_movers: Vec<RankChange>, //The top_k largest score changes among nodes in both rankings (ties by node id)
+    pub entered: Vec<usize>,           //In the top_k after but not before, in their new order
+    pub left: Vec<usize>,              //In the top_k before but not after, in their old order
+    pub kendall_tau: f64,              //Rank agreement over the nodes in both rankings: 1.0 same order, -1.0 reversed (1.0 with fewer than two such nodes)
+    pub common: usize,                 //Number of nodes in both rankings
+}
+
+pub fn ranking_diff(before: &[(usize, f64)], after: &[(usize, f64)], top_k: usize) -> RankingDiff { //Compares two rankings given best first, as every centrality function returns them
+    let after_rank: HashMap<usize, (usize, f64)> = after.iter().enumerate().map(|(i, &(node, score))| (node, (i + 1, score))).collect();
+    let changes: Vec<RankChange> = before
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(node, score_before))| {
+            let &(rank_after, score_after) = after_rank.get(&node)?;
+            Some(RankChange { node, rank_before: i + 1, rank_after, score_before, score_after })
+        })
+        .collect();
+
+    let mut rank_movers = changes.clone();
+    rank_movers.sort_by(|a, b| b.rank_shift().abs().cmp(&a.rank_shift().abs()).then(a.node.cmp(&b.node)));
+    rank_movers.truncate(top_k);
+    let mut score_movers = changes.clone();
+    score_movers.sort_by(|a, b| b.score_shift().abs().total_cmp(&a.score_shift().abs()).then(a.node.cmp(&b.node)));
+    score_movers.truncate(top_k);
+
+    let top = |ranking: &[(usize, f64)]| ranking.iter().take(top_k).map(|&(node, _)| node).collect::<Vec<usize>>();
+    let (top_before, top_after) = (top(before), top(after));
+    let entered = top_after.iter().filter(|n| !top_before.contains(n)).copied().collect();
+    let left = top_before.iter().filter(|n| !top_after.contains(n)).copied().collect();
+
+    //Kendall's tau-a over the common nodes: they are listed in their old order, so a pair is concordant exactly when the later one also ranks lower after
+    let (mut concordant, mut discordant) = (0u64, 0u64);
+    for (i, a) in changes.iter().enumerate() {
+        for b in &changes[i + 1..] {
+            if a.rank_after < b.rank_after {
+                concordant += 1;
+            } else {
+                discordant += 1;
+            }
+        }
+    }
+    let pairs = concordant + discordant;
+    let kendall_tau = if pairs == 0 { 1.0 } else { (concordant as f64 - discordant as f64) / pairs as f64 };
+    RankingDiff { top_k, rank_movers, score_movers, entered, left, kendall_tau, common: changes.len() }
+}
+
+impl fmt::Display for RankingDiff { //A short table, e.g. "  Node 12: #7 -> #2 (+5), score 0.3100 -> 0.3350"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Kendall tau: {:.4} over {} nodes in both rankings", self.kendall_tau, self.common)?;
+        for (title, movers) in [("rank", &self.rank_movers), ("score", &self.score_movers)] {
+            writeln!(f, "Largest {} changes:", title)?;
+            for m in movers {
+                writeln!(f, "  Node {}: #{} -> #{} ({:+}), score {:.4} -> {:.4} ({:+.4})", m.node, m.rank_before, m.rank_after, m.rank_shift(), m.score_before, m.score_after, m.score_shift())?;
+            }
+        }
+        let list = |nodes: &[usize]| if nodes.is_empty() { String::from("none") } else { nodes.iter().map(usize::to_string).collect::<Vec<_>>().join(" ") };
+        writeln!(f, "Entered the top {}: {}", self.top_k, list(&self.entered))?;
+        writeln!(f, "Left the top {}: {}", self.top_k, list(&self.left))
+    }
+}
+
+pub fn component_labels(graph: &Graph) -> HashMap<usize, usize> { //node -> index of its component in connected_components, so component 0 is the largest
+    connected_components(graph)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(label, members)| members.into_iter().map(move |node| (node, label)))
+        .collect()
+}
+
+pub fn component_of(graph: &Graph, InternalId(node): InternalId) -> Result<usize, GraphError> { //Component label of a single node (labels every node, so reuse component_labels when asking about many)
+    if !graph.adjacency().contains_key(&node) {
+        return Err(GraphError::UnknownNode(node));
+    }
+    Ok(component_labels(graph)[&node])
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PeripheryMetric { //Rankings least_central can read from the bottom
+    Closeness,
+    Degree,
+    PageRank,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeripheryOptions { //Which nodes least_central may return: the lowest scores otherwise belong to isolated nodes and stray pairs, which are disconnected rather than peripheral
+    pub giant_component_only: bool, //Only nodes of the largest component
+    pub min_component_size: usize,  //Only nodes of components at least this large (applies to the largest one too)
+}
+
+impl Default for PeripheryOptions {
+    fn default() -> Self {
+        Self { giant_component_only: true, min_component_size: 3 }
+    }
+}
+
+pub fn least_central(graph: &Graph, metric: PeripheryMetric, k: usize, options: &PeripheryOptions) -> Result<Vec<(usize, f64)>, GraphError> { //The k lowest-scoring nodes that options allow, lowest first (ties by node id). Closeness is only computed for those nodes
+    least_central_with(graph, metric, k, options, &ParallelismConfig::default())
+}
+
+pub fn least_central_with(graph: &Graph, metric: PeripheryMetric, k: usize, options: &PeripheryOptions, parallelism: &ParallelismConfig) -> Result<Vec<(usize, f64)>, GraphError> { //Same as least_central, with control over how many threads compute closeness
+    let eligible = periphery_candidates(graph, options);
+    let scores: Vec<(usize, f64)> = match metric {
+        PeripheryMetric::Closeness => {
+            let mut nodes: Vec<usize> = eligible.iter().copied().collect();
+            nodes.sort();
+            let closeness = parallelism.map_nodes(&nodes, |node| closeness_of(graph, InternalId(node)).unwrap_or(0.0));
+            nodes.into_iter().zip(closeness).collect()
+        }
+        PeripheryMetric::Degree => eligible.iter().map(|&node| (node, graph.degree(node) as f64)).collect(),
+        PeripheryMetric::PageRank => pagerank(graph, 0.85, PAGERANK_MAX_ITERATIONS, 1e-8)?,
+    };
+    Ok(bottom_of(scores, &eligible, k))
+}
+
+pub fn least_central_among(graph: &Graph, scores: &[(usize, f64)], k: usize, options: &PeripheryOptions) -> Vec<(usize, f64)> { //least_central over a ranking already computed (e.g. the pipeline's closeness)
+    bottom_of(scores.to_vec(), &periphery_candidates(graph, options), k)
+}
+
+fn periphery_candidates(graph: &Graph, options: &PeripheryOptions) -> HashSet<usize> {
+    let components = connected_components(graph);
+    let keep = if options.giant_component_only { components.len().min(1) } else { components.len() };
+    components.into_iter().take(keep).filter(|members| members.len() >= options.min_component_size).flatten().collect()
+}
+
+fn bottom_of(mut scores: Vec<(usize, f64)>, eligible: &HashSet<usize>, k: usize) -> Vec<(usize, f64)> {
+    scores.retain(|(node, _)| eligible.contains(node));
+    scores.sort_by(|a, b| a.1.total_cmp(&b.1).then(a.0.cmp(&b.0)));
+    scores.truncate(k);
+    scores
+}
+
+pub fn giant_component_fraction(graph: &Graph) -> f64 { //Share of all nodes that sit in the largest connected component
+    if graph.adjacency().is_empty() {
+        return 0.0;
+    }
+    connected_components(graph)[0].len() as f64 / graph.adjacency().len() as f64
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComponentShare { //How much of a graph largest_connected_component kept (fractions are 0.0 when the graph had no nodes or no edges)
+    pub nodes: usize,
+    pub edges: usize,
+    pub node_fraction: f64,
+    pub edge_fraction: f64,
+}
+
+pub fn largest_connected_component(graph: &Graph) -> (Graph, ComponentShare) { //The induced subgraph on the biggest component (ties go to the one with the smallest node id, as in connected_components) and how much of the graph it kept.
+    //An empty graph gives an empty graph. Node ids and the id map are kept, so reports still print the ids from the file
+    let Some(largest) = connected_components(graph).into_iter().next() else {
+        return (Graph::new(), ComponentShare { nodes: 0, edges: 0, node_fraction: 0.0, edge_fraction: 0.0 });
+    };
+    let giant = graph.induced_subgraph(&largest.into_iter().collect());
+    let fraction = |kept: usize, total: usize| if total == 0 { 0.0 } else { kept as f64 / total as f64 };
+    let share = ComponentShare {
+        nodes: giant.num_nodes(),
+        edges: giant.num_edges(),
+        node_fraction: fraction(giant.num_nodes(), graph.num_nodes()),
+        edge_fraction: fraction(giant.num_edges(), graph.num_edges()),
+    };
+    (giant, share)
+}
+
+pub fn connected_pair_fraction(graph: &Graph) -> f64 { //Share of all node pairs joined by some path, computed exactly from the component sizes (0.0 for fewer than two nodes)
+    let sizes: Vec<usize> = connected_components(graph).iter().map(Vec::len).collect();
+    pair_fraction(&sizes)
+}
+
+pub fn connected_pair_fraction_from_file(path: &str) -> Result<f64, GraphError> { //Same as connected_pair_fraction, streaming the edge file through a union-find instead of loading the graph (memory grows with the nodes, not the edges)
+    let reader = BufReader::new(File::open(path)?);
+    let mut parent: HashMap<usize, usize> = HashMap::new();
+    let mut size: HashMap<usize, usize> = HashMap::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if let [u, v] = parse_numbers(&line, index)?[..] {
+            let (root_u, root_v) = (find_root(&mut parent, u), find_root(&mut parent, v));
+            if root_u != root_v { //Union by size: hang the smaller tree under the larger one
+                let (small, large) = if size.get(&root_u).unwrap_or(&1) < size.get(&root_v).unwrap_or(&1) { (root_u, root_v) } else { (root_v, root_u) };
+                let merged = size.get(&small).unwrap_or(&1) + size.get(&large).unwrap_or(&1);
+                parent.insert(small, large);
+                size.insert(large, merged);
+                size.remove(&small);
+            }
+        }
+    }
+    let nodes: Vec<usize> = parent.keys().copied().collect();
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for node in nodes {
+        *sizes.entry(find_root(&mut parent, node)).or_insert(0) += 1;
+    }
+    Ok(pair_fraction(&sizes.into_values().collect::<Vec<_>>()))
+}
+
+fn find_root(parent: &mut HashMap<usize, usize>, node: usize) -> usize { //Union-find lookup with path halving; unseen nodes become their own root
+    let mut current = *parent.entry(node).or_insert(node);
+    let mut child = node;
+    while current != child {
+        let grandparent = parent[&current];
+        parent.insert(child, grandparent);
+        child = current;
+        current = grandparent;
+    }
+    current
+}
+
+fn pair_fraction(component_sizes: &[usize]) -> f64 { //Sum of s(s-1)/2 over the components, divided by n(n-1)/2
+    let n: usize = component_sizes.iter().sum();
+    if n < 2 {
+        return 0.0;
+    }
+    let pairs = |s: usize| s as u128 * s.saturating_sub(1) as u128 / 2; //n(n - 1) passes u64 past about 4 billion nodes
+    let connected: u128 = component_sizes.iter().map(|&s| pairs(s)).sum();
+    connected as f64 / pairs(n) as f64
+}
+
+pub fn eccentricities(graph: &Graph) -> HashMap<usize, usize> { //Distance from each node to the farthest node it can reach (0 for isolated nodes)
+    eccentricities_with(graph, &ParallelismConfig::default())
+}
+
+pub fn eccentricities_with(graph: &Graph, parallelism: &ParallelismConfig) -> HashMap<usize, usize> { //Same as eccentricities, with control over how many threads run the BFS passes
+    let nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    parallelism
+        .map_nodes(&nodes, |node| (node, bfs_distances(graph, InternalId(node)).into_values().max().unwrap_or(0)))
+        .into_iter()
+        .collect()
+}
+
+pub fn diameter(graph: &Graph) -> usize { //Longest shortest path between any two connected nodes, i.e. the largest diameter of any component (pairs in different components are ignored)
+    eccentricities(graph).into_values().max().unwrap_or(0)
+}
+
+pub fn diameter_double_sweep(graph: &Graph) -> usize { //Lower bound on diameter from two BFS per component (from its smallest node to the farthest node found, then from there); exact on trees and usually close on social networks
+    let mut best = 0;
+    for component in connected_components(graph) {
+        let farthest = |start: usize| {
+            bfs_distances(graph, InternalId(start)).into_iter().max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0))).unwrap() //Ties go to the smallest id, for repeatable results
+        };
+        let (far, _) = farthest(component[0]);
+        best = best.max(farthest(far).1);
+    }
+    best
+}
+
+pub fn radius(graph: &Graph) -> Result<usize, GraphError> { //Smallest eccentricity (0 for an empty or one-node graph). Disconnected error unless the graph is connected: the minimum over several components would just pick out the smallest one
+    if !is_connected(graph) {
+        return Err(GraphError::Disconnected);
+    }
+    Ok(eccentricities(graph).into_values().min().unwrap_or(0))
+}
+
+pub const PAGERANK_MAX_ITERATIONS: usize = 1000; //Default cap on power iterations (pagerank_weighted always uses it)
+
+pub fn pagerank(graph: &Graph, damping: f64, max_iter: usize, tol: f64) -> Result<Vec<(usize, f64)>, GraphError> { //PageRank with every friendship as two directed links, highest first (ties by node id). Stops once the L1 change is below tol or after max_iter rounds
+    pagerank_from(graph, damping, max_iter, tol, None)
+}
+
+pub fn pagerank_weighted(graph: &WeightedGraph, damping: f64, tol: f64) -> Result<Vec<(usize, f64)>, GraphError> { //PageRank where each node passes its rank to its friends in proportion to the edge weights (highest first, ties by node id)
+    pagerank_from(graph, damping, PAGERANK_MAX_ITERATIONS, tol, None)
+}
+
+fn pagerank_from<G: GraphRead + ?Sized>(graph: &G, damping: f64, max_iter: usize, tol: f64, teleport: Option<&HashMap<usize, f64>>) -> Result<Vec<(usize, f64)>, GraphError> { //Power iteration shared by every PageRank variant: rank flows along edge_weight / strength, so unweighted graphs split it evenly.
+    //The walk restarts (and dangling rank goes) by the teleport vector: node -> share, summing to 1, missing nodes 0 (None: every node alike). The iteration starts from it too
+    if !(0.0..1.0).contains(&damping) || tol.is_nan() || tol <= 0.0 {
+        return Err(GraphError::InvalidParameter(format!("PageRank needs 0 <= damping < 1 and tol > 0, got damping {} and tol {}", damping, tol)));
+    }
+    let mut nodes: Vec<usize> = graph.nodes().collect();
+    nodes.sort();
+    let n = nodes.len();
+    if n == 0 {
+        return Ok(Vec::new());
+    }
+    let index: HashMap<usize, usize> = nodes.iter().enumerate().map(|(i, &node)| (node, i)).collect();
+    let strength: Vec<f64> = nodes.iter().map(|&node| graph.strength(node)).collect();
+    let teleport: Vec<f64> = match teleport {
+        Some(shares) => nodes.iter().map(|node| shares.get(node).copied().unwrap_or(0.0)).collect(),
+        None => vec![1.0 / n as f64; n],
+    };
+    let mut rank = teleport.clone();
+    for _ in 0..max_iter {
+        //Dangling nodes (no friends, or only zero-weight edges) have nowhere to send their rank, so it is spread by the teleport vector
+        let dangling: f64 = (0..n).filter(|&i| strength[i] <= 0.0).map(|i| rank[i]).sum();
+        let restart = (1.0 - damping) + damping * dangling;
+        let mut next: Vec<f64> = teleport.iter().map(|share| restart * share).collect();
+        for (i, &node) in nodes.iter().enumerate() {
+            if strength[i] <= 0.0 {
+                continue;
+            }
+            for friend in graph.neighbors(node) {
+                next[index[&friend]] += damping * rank[i] * graph.edge_weight(node, friend) / strength[i];
+            }
+        }
+        let change: f64 = rank.iter().zip(&next).map(|(a, b)| (a - b).abs()).sum();
+        rank = next;
+        if change < tol {
+            break;
+        }
+    }
+    let mut result: Vec<(usize, f64)> = nodes.into_iter().zip(rank).collect();
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    Ok(result)
+}
+
+pub const PPR_RESTART: f64 = 0.15; //Default alpha for similar_by_ppr: the walk jumps back to the source 15% of the time, the usual 1 - 0.85 damping
+
+pub fn personalized_pagerank(graph: &Graph, InternalId(source): InternalId, alpha: f64, max_iter: usize, tol: f64) -> Result<HashMap<usize, f64>, GraphError> { //Random walk with restart: the long-run share of time a walk from source spends at each node,
+    //returning to source with probability alpha at every step (0 < alpha <= 1). Scores sum to 1; nodes outside source's component get 0. Stops once the L1 change is below tol or after max_iter rounds
+    if !graph.contains_node(source) {
+        return Err(GraphError::UnknownNode(source));
+    }
+    if !(alpha > 0.0 && alpha <= 1.0) || tol.is_nan() || tol <= 0.0 {
+        return Err(GraphError::InvalidParameter(format!("personalized PageRank needs 0 < alpha <= 1 and tol > 0, got alpha {} and tol {}", alpha, tol)));
+    }
+    let scores = pagerank_from(graph, 1.0 - alpha, max_iter, tol, Some(&HashMap::from([(source, 1.0)])))?; //A dangling node sends its walkers home rather than to everyone, so the mass stays personal
+    Ok(scores.into_iter().collect())
+}
+
+pub fn similar_by_ppr(graph: &Graph, InternalId(source): InternalId, top_k: usize) -> Result<Vec<(usize, f64)>, GraphError> { //The top_k people source is not yet friends with, by personalized PageRank from source (PPR_RESTART), highest first (ties by node id).
+    //A diffusion-based alternative to Jaccard recommendations: it sees past friends of friends, and a tie through a small tight group counts more than one through a hub
+    let scores = personalized_pagerank(graph, InternalId(source), PPR_RESTART, PAGERANK_MAX_ITERATIONS, 1e-10)?;
+    let mut candidates: Vec<(usize, f64)> = scores
+        .into_iter()
+        .filter(|&(node, score)| node != source && score > 0.0 && !graph.has_edge(source, node))
+        .collect();
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    candidates.truncate(top_k);
+    Ok(candidates)
+}
+
+pub fn eigenvector_centrality(graph: &Graph, max_iter: usize, tol: f64) -> Vec<(usize, f64)> { //Power iteration on the adjacency matrix, L2-normalized each round, highest first (ties by node id). Stops once the L2 change is below tol or after max_iter rounds
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let index: HashMap<usize, usize> = nodes.iter().enumerate().map(|(i, &node)| (node, i)).collect();
+    //Isolated nodes start (and so stay) at 0. On a disconnected graph the vector settles on the component with the largest eigenvalue and the others fade towards 0
+    let mut score: Vec<f64> = nodes.iter().map(|node| if graph.adjacency()[node].is_empty() { 0.0 } else { 1.0 }).collect();
+    normalize_l2(&mut score);
+    for _ in 0..max_iter {
+        //Multiplying by A + I rather than A: same eigenvectors, but bipartite graphs (stars, trees) converge instead of flipping between their two sides
+        let mut next = score.clone();
+        for (i, node) in nodes.iter().enumerate() {
+            next[i] += graph.adjacency()[node].iter().map(|friend| score[index[friend]]).sum::<f64>();
+        }
+        normalize_l2(&mut next);
+        let change = score.iter().zip(&next).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt();
+        score = next;
+        if change < tol {
+            break;
+        }
+    }
+    let mut result: Vec<(usize, f64)> = nodes.into_iter().zip(score).collect();
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    result
+}
+
+fn normalize_l2(vector: &mut [f64]) { //Scales to unit length; an all-zero vector (no edges at all) is left alone
+    let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+pub fn betweenness_centrality(graph: &Graph) -> Vec<(usize, f64)> { //Computes betweenness centrality for all nodes using Brandes' algorithm
+    finish_betweenness(raw_betweenness(graph))
+}
+
+fn raw_betweenness(graph: &Graph) -> HashMap<usize, f64> { //Brandes accumulation from every source (in id order), before halving
+    let mut centrality: HashMap<usize, f64> = graph.adjacency().keys().map(|&n| (n, 0.0)).collect();
+    let adjacency = graph.sorted_adjacency();
+    let mut sources: Vec<usize> = graph.adjacency().keys().copied().collect();
+    sources.sort();
+    for source in sources {
+        accumulate_betweenness(adjacency, source, &mut centrality);
+    }
+    centrality
+}
+
+pub fn betweenness_centrality_sampled(graph: &Graph, num_sources: usize, seed: u64) -> Vec<(usize, f64)> { //Brandes accumulation from num_sources random sources, scaled up by n / num_sources (same seed = same estimate).
+    //Every source's dependencies are an unbiased sample of the full sum, so the estimate costs num_sources BFS runs instead of n; with num_sources >= n it is betweenness_centrality
+    if num_sources >= graph.num_nodes() {
+        return betweenness_centrality(graph);
+    }
+    let mut centrality: HashMap<usize, f64> = graph.adjacency().keys().map(|&n| (n, 0.0)).collect();
+    let adjacency = graph.sorted_adjacency();
+    let mut sources = sample_nodes(graph, num_sources, seed);
+    sources.sort();
+    for &source in &sources {
+        accumulate_betweenness(adjacency, source, &mut centrality);
+    }
+    if !sources.is_empty() {
+        let scale = graph.num_nodes() as f64 / sources.len() as f64;
+        centrality.values_mut().for_each(|c| *c *= scale);
+    }
+    finish_betweenness(centrality)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SparseBetweennessOptions { //Which nodes betweenness_centrality_sparse keeps; with both set, a node must pass the threshold and be in the top k
+    pub min_normalized: Option<f64>, //Keep nodes whose score divided by the number of other pairs, (n-1)(n-2)/2, is above this
+    pub top_k: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseBetweenness {
+    pub scores: Vec<(usize, f64)>, //Kept nodes with the same (unnormalized) scores and order as betweenness_centrality
+    pub suppressed: usize,         //Nodes left out
+}
+
+pub fn betweenness_centrality_sparse(graph: &Graph, options: &SparseBetweennessOptions) -> SparseBetweenness { //betweenness_centrality keeping only the nodes that matter: every source is still accumulated, but only kept nodes are collected and sorted
+    let centrality = raw_betweenness(graph);
+    let n = centrality.len();
+    let pairs = (n.saturating_sub(1) * n.saturating_sub(2) / 2).max(1) as f64;
+    let mut scores: Vec<(usize, f64)> = centrality
+        .into_iter()
+        .map(|(node, c)| (node, c / 2.0))
+        .filter(|&(_, score)| options.min_normalized.is_none_or(|min| score / pairs > min))
+        .collect();
+    let by_rank = |a: &(usize, f64), b: &(usize, f64)| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0));
+    if let Some(k) = options.top_k.filter(|&k| k < scores.len()) {
+        if k > 0 {
+            scores.select_nth_unstable_by(k - 1, by_rank); //Partition around the k-th best so only k entries get sorted
+        }
+        scores.truncate(k);
+    }
+    scores.sort_by(by_rank);
+    SparseBetweenness { suppressed: n - scores.len(), scores }
+}
+
+pub(crate) fn accumulate_betweenness(adjacency: &HashMap<usize, Vec<usize>>, source: usize, centrality: &mut HashMap<usize, f64>) { //From one source, count shortest paths with BFS, then walk back up the BFS order adding each node's dependency to its score
+    let (stack, predecessors, sigma) = shortest_path_counts(adjacency, source);
+    add_dependencies(source, stack, &predecessors, &sigma, Some(centrality), None);
+}
+
+type PathCounts = (Vec<usize>, HashMap<usize, Vec<usize>>, HashMap<usize, f64>);
+
+fn shortest_path_counts(adjacency: &HashMap<usize, Vec<usize>>, source: usize) -> PathCounts { //Brandes' first phase with BFS: the nodes reached from source by nondecreasing distance,
+    //the nodes just before each one on its shortest paths, and sigma, the number of those paths
+    let mut stack = Vec::new();
+    let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut sigma: HashMap<usize, f64> = HashMap::from([(source, 1.0)]);
+    let mut distance: HashMap<usize, usize> = HashMap::from([(source, 0)]);
+    let mut queue = VecDeque::from([source]);
+
+    while let Some(current) = queue.pop_front() {
+        stack.push(current);
+        let current_dist = distance[&current];
+        for &neighbor in &adjacency[&current] {
+            if let Entry::Vacant(entry) = distance.entry(neighbor) {
+                entry.insert(current_dist + 1);
+                queue.push_back(neighbor);
+            }
+            if distance[&neighbor] == current_dist + 1 {
+                *sigma.entry(neighbor).or_insert(0.0) += sigma[&current];
+                predecessors.entry(neighbor).or_default().push(current);
+            }
+        }
+    }
+    (stack, predecessors, sigma)
+}
+
+fn add_dependencies(
+    source: usize,
+    mut stack: Vec<usize>,
+    predecessors: &HashMap<usize, Vec<usize>>,
+    sigma: &HashMap<usize, f64>,
+    mut nodes: Option<&mut HashMap<usize, f64>>,
+    mut edges: Option<&mut HashMap<(usize, usize), f64>>,
+) { //Brandes' second phase, shared by the BFS and Dijkstra versions and by edge betweenness: stack, predecessors and sigma as from shortest_path_counts.
+    //Each node's dependency is added to its score in `nodes`, and the share of paths each predecessor link carries to the (u < v) entry in `edges`
+    let mut delta: HashMap<usize, f64> = HashMap::new();
+    while let Some(w) = stack.pop() {
+        let delta_w = delta.get(&w).copied().unwrap_or(0.0);
+        if let Some(preds) = predecessors.get(&w) {
+            for &v in preds {
+                let carried = sigma[&v] / sigma[&w] * (1.0 + delta_w);
+                if let Some(edges) = edges.as_deref_mut() {
+                    *edges.entry((v.min(w), v.max(w))).or_insert(0.0) += carried;
+                }
+                *delta.entry(v).or_insert(0.0) += carried;
+            }
+        }
+        if let Some(nodes) = nodes.as_deref_mut().filter(|_| w != source) {
+            *nodes.entry(w).or_insert(0.0) += delta_w;
+        }
+    }
+}
+
+pub fn edge_betweenness(graph: &Graph) -> HashMap<(usize, usize), f64> { //Shortest paths through every friendship (Brandes adapted to edges), keyed (u, v) with u < v. A bridge carries every path between its two sides
+    edge_betweenness_of(graph.sorted_adjacency())
+}
+
+pub(crate) fn edge_betweenness_of(adjacency: &HashMap<usize, Vec<usize>>) -> HashMap<(usize, usize), f64> { //edge_betweenness over a bare adjacency map, so Girvan–Newman can rerun it on its working copy as edges go
+    let mut centrality: HashMap<(usize, usize), f64> = HashMap::new();
+    for (&u, friends) in adjacency {
+        centrality.extend(friends.iter().filter(|&&v| u < v).map(|&v| ((u, v), 0.0)));
+    }
+    let mut sources: Vec<usize> = adjacency.keys().copied().collect();
+    sources.sort();
+    for source in sources {
+        let (stack, predecessors, sigma) = shortest_path_counts(adjacency, source);
+        add_dependencies(source, stack, &predecessors, &sigma, None, Some(&mut centrality));
+    }
+    centrality.values_mut().for_each(|c| *c /= 2.0); //Each pair was counted from both ends
+    centrality
+}
+
+pub(crate) fn finish_betweenness(centrality: HashMap<usize, f64>) -> Vec<(usize, f64)> { //Every unordered pair was counted once from each endpoint, so we halve the scores for an undirected graph and sort them (ties by node id)
+    let mut result: Vec<(usize, f64)> = centrality.into_iter().map(|(n, c)| (n, c / 2.0)).collect();
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    result
+}
+
+pub const PATH_LENGTH_EPSILON: f64 = 1e-9; //Relative tolerance under which two weighted path lengths count as equal (0.1 + 0.2 and 0.3 are both shortest)
+
+fn same_length(a: f64, b: f64) -> bool {
+    (a - b).abs() <= PATH_LENGTH_EPSILON * a.abs().max(b.abs()).max(1.0)
+}
+
+pub fn betweenness_centrality_weighted(graph: &WeightedGraph, normalized: bool) -> Result<Vec<(usize, f64)>, GraphError> { //Brandes' algorithm with Dijkstra instead of BFS, so shortest means lightest; sorted like betweenness_centrality.
+    //normalized divides by the number of pairs of other nodes, (n-1)(n-2)/2. A negative weight is an InvalidParameter error
+    if let Some((u, v, w)) = graph.adj_list.iter().flat_map(|(&u, friends)| friends.iter().map(move |(&v, &w)| (u, v, w))).find(|&(_, _, w)| w < 0.0 || w.is_nan()) {
+        return Err(GraphError::InvalidParameter(format!("edge {}-{} has weight {}, shortest paths need weights >= 0", u, v, w)));
+    }
+    let adjacency: HashMap<usize, Vec<(usize, f64)>> = graph
+        .adj_list
+        .iter()
+        .map(|(&node, friends)| {
+            let mut sorted: Vec<(usize, f64)> = friends.iter().map(|(&v, &w)| (v, w)).collect();
+            sorted.sort_by_key(|&(v, _)| v);
+            (node, sorted)
+        })
+        .collect();
+    let mut centrality: HashMap<usize, f64> = graph.adj_list.keys().map(|&n| (n, 0.0)).collect();
+    let mut sources: Vec<usize> = graph.adj_list.keys().copied().collect();
+    sources.sort();
+    for source in sources {
+        let mut stack = Vec::new();
+        let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut sigma: HashMap<usize, f64> = HashMap::from([(source, 1.0)]);
+        let mut tentative: HashMap<usize, f64> = HashMap::from([(source, 0.0)]);
+        let mut settled: HashSet<usize> = HashSet::new();
+        let mut heap = BinaryHeap::from([DijkstraEntry { distance: 0.0, node: source }]);
+        while let Some(DijkstraEntry { distance, node }) = heap.pop() {
+            if !settled.insert(node) {
+                continue; //A stale entry: the node was settled through a shorter path
+            }
+            stack.push(node);
+            for &(neighbor, weight) in &adjacency[&node] {
+                if settled.contains(&neighbor) {
+                    continue;
+                }
+                let through = distance + weight;
+                match tentative.get(&neighbor) {
+                    Some(&best) if same_length(through, best) => {
+                        *sigma.get_mut(&neighbor).unwrap() += sigma[&node];
+                        predecessors.get_mut(&neighbor).unwrap().push(node);
+                    }
+                    Some(&best) if best < through => {}
+                    _ => {
+                        tentative.insert(neighbor, through);
+                        sigma.insert(neighbor, sigma[&node]);
+                        predecessors.insert(neighbor, vec![node]);
+                        heap.push(DijkstraEntry { distance: through, node: neighbor });
+                    }
+                }
+            }
+        }
+        add_dependencies(source, stack, &predecessors, &sigma, Some(&mut centrality), None);
+    }
+    let n = centrality.len();
+    let scale = if normalized { (n.saturating_sub(1) * n.saturating_sub(2) / 2).max(1) as f64 } else { 1.0 };
+    Ok(finish_betweenness(centrality).into_iter().map(|(node, score)| (node, score / scale)).collect())
+}
+
+fn for_each_mutual_friend<G: GraphRead + ?Sized>(graph: &G, u: usize, v: usize, mut visit: impl FnMut(usize)) { //Calls visit on every friend u and v have in common, in no particular order (none if either node is unknown)
+    if !graph.contains_node(u) || !graph.contains_node(v) {
+        return;
+    }
+    let (small, large) = if graph.degree(u) <= graph.degree(v) { (u, v) } else { (v, u) }; //Walk the shorter friend list, look up in the other
+    graph.for_each_neighbor(small, |w| if graph.has_edge(large, w) { visit(w) });
+}
+
+pub fn mutual_friends<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> Vec<usize> { //The friends u and v have in common, sorted (empty if either node is unknown)
+    let mut common = Vec::new();
+    for_each_mutual_friend(graph, u, v, |w| common.push(w));
+    common.sort();
+    common
+}
+
+pub fn mutual_friend_count<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> usize { //mutual_friends(graph, u, v).len() without building the list
+    let mut count = 0;
+    for_each_mutual_friend(graph, u, v, |_| count += 1);
+    count
+}
+
+pub fn top_pairs_by_mutual_friends<G: GraphRead + ?Sized>(graph: &G, top_n: usize) -> Vec<((usize, usize), usize)> { //The top_n pairs (u < v) with the most friends in common, most first (ties by pair). Friends and non-friends alike;
+    //raw counts favor hubs, which is the point: for them an absolute number reads better than a Jaccard ratio diluted by a long friend list
+    let mut nodes: Vec<usize> = graph.nodes().collect();
+    nodes.sort();
+    let mut pairs: Vec<((usize, usize), usize)> = Vec::new();
+    for (i, &u) in nodes.iter().enumerate() {
+        pairs.extend(two_hop_candidates(graph, u, &nodes[i + 1..], |_| true).into_iter().map(|v| ((u, v), mutual_friend_count(graph, InternalId(u), InternalId(v)))));
+        if pairs.len() > 2 * top_n.max(1024) { //Keep memory at O(top_n) rather than one entry per two-hop pair
+            pairs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            pairs.truncate(top_n);
+        }
+    }
+    pairs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    pairs.truncate(top_n);
+    pairs
+}
+
+pub fn jaccard_similarity<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Computes the Jaccard similarity between two nodes in the graph - measures social similarity based on mutual friends
+    if !graph.contains_node(u) || !graph.contains_node(v) {
+        return 0.0;
+    }
+    let intersection = mutual_friend_count(graph, InternalId(u), InternalId(v)) as f64;
+    let union = (graph.degree(u) + graph.degree(v)) as f64 - intersection;
+    if union == 0.0 { 0.0 } else { intersection / union }
+}
+
+pub fn cosine_similarity<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Common friends over the geometric mean of the two degrees, |N(u) ∩ N(v)| / sqrt(|N(u)| |N(v)|); 0.0 if either node is unknown or has no friends
+    SimilarityMetric::Cosine.score_within(graph, InternalId(u), InternalId(v), None)
+}
+
+pub fn overlap_coefficient<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Common friends over the smaller degree, so 1.0 whenever one friend list contains the other; 0.0 if either node is unknown or has no friends
+    SimilarityMetric::Overlap.score_within(graph, InternalId(u), InternalId(v), None)
+}
+
+fn neighbor_overlap<G: GraphRead + ?Sized>(graph: &G, u: usize, v: usize, allowed: Option<&HashSet<usize>>) -> Option<(usize, usize, usize)> { //(common friends, degree of u, degree of v), counted inside `allowed` when given; None if u or v is unknown or outside it
+    let inside = |n: &usize| allowed.is_none_or(|a| a.contains(n));
+    if !inside(&u) || !inside(&v) || !graph.contains_node(u) || !graph.contains_node(v) {
+        return None;
+    }
+    if allowed.is_none() {
+        return Some((mutual_friend_count(graph, InternalId(u), InternalId(v)), graph.degree(u), graph.degree(v)));
+    }
+    let count = |n: usize, shared_with_v: bool| {
+        let mut count = 0;
+        graph.for_each_neighbor(n, |w| count += usize::from(inside(&w) && (!shared_with_v || graph.has_edge(v, w))));
+        count
+    };
+    Some((count(u, true), count(u, false), count(v, false)))
+}
+
+pub fn friends_of_friends_similarity(graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Jaccard similarity of the people exactly two hops from u and from v (friends and the nodes themselves left out); 0.0 if either node is unknown
+    //u and v are dropped from both sets, so two people two hops apart are not penalized for not being in their own neighborhoods
+    if !graph.adjacency().contains_key(&u) || !graph.adjacency().contains_key(&v) {
+        return 0.0;
+    }
+    let second_ring = |node: usize| -> HashSet<usize> {
+        bfs_distances_within(graph, InternalId(node), 2).into_iter().filter(|&(w, d)| d == 2 && w != u && w != v).map(|(w, _)| w).collect()
+    };
+    let (ring_u, ring_v) = (second_ring(u), second_ring(v));
+    let intersection = ring_u.intersection(&ring_v).count() as f64;
+    let union = (ring_u.len() + ring_v.len()) as f64 - intersection;
+    if union == 0.0 { 0.0 } else { intersection / union }
+}
+
+pub fn jaccard_similarity_within<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId, allowed: &HashSet<usize>) -> f64 { //Jaccard similarity counting only friends inside `allowed` (e.g. a community or a k-hop ball); 0.0 if u or v is outside it
+    //Same as jaccard_similarity on the subgraph induced by `allowed`, without building that subgraph
+    let (common, degree_u, degree_v) = neighbor_overlap(graph, u, v, Some(allowed)).unwrap_or((0, 0, 0));
+    let (intersection, union) = (common as f64, (degree_u + degree_v - common) as f64);
+    if union == 0.0 { 0.0 } else { intersection / union }
+}
+
+const MINHASH_MAGIC: &[u8; 8] = b"FGAMINH1"; //First bytes of a sketch file; the 1 is the format version
+
+pub fn minhash_standard_error(num_hashes: usize) -> f64 { //Worst-case standard error of a MinHash Jaccard estimate: sqrt(J (1 - J) / k) is largest at J = 0.5
+    0.5 / (num_hashes as f64).sqrt()
+}
+
+pub fn minhash_signature(friends: impl Iterator<Item = usize>, num_hashes: usize, seed: u64) -> Vec<u64> { //For each of num_hashes seeded hash functions, the smallest hash over the friends (u64::MAX for nobody)
+    let keys: Vec<u64> = (0..num_hashes as u64).map(|i| mix(seed ^ mix(i))).collect();
+    let mut minima = vec![u64::MAX; num_hashes];
+    for friend in friends {
+        for (minimum, key) in minima.iter_mut().zip(&keys) {
+            *minimum = (*minimum).min(mix(friend as u64 ^ key));
+        }
+    }
+    minima
+}
+
+pub fn minhash_jaccard(a: &[u64], b: &[u64]) -> f64 { //Share of hash functions whose minima agree: an unbiased estimate of the Jaccard similarity of the two friend lists (0.0 when both are empty, as in jaccard_similarity)
+    if a.is_empty() || a.iter().all(|&m| m == u64::MAX) {
+        return 0.0;
+    }
+    a.iter().zip(b).filter(|(x, y)| x == y).count() as f64 / a.len() as f64
+}
+
+pub fn export_minhash_sketches(graph: &Graph, num_hashes: usize, seed: u64, path: &str) -> Result<(), GraphError> { //Writes a MinHash signature of every node's friend list, keyed by input-file id, so Jaccard can be estimated without the edge list
+    //Layout, little-endian: the 8 magic bytes, num_hashes and the node count as u64, then per node (by id) its id and num_hashes u64 minima.
+    //Estimates are off by at most minhash_standard_error(num_hashes) on average (0.031 at 256 hashes). The seed is not written: anyone holding it
+    //could hash every id and see which friend produced each minimum. The hash is not cryptographic, so this keeps friend lists from casual view, not from a determined attacker
+    if num_hashes == 0 {
+        return Err(GraphError::InvalidParameter(String::from("a MinHash sketch needs at least one hash function")));
+    }
+    let mut nodes: Vec<(usize, usize)> = graph.adjacency().keys().map(|&node| (graph.original_id(InternalId(node)).0, node)).collect();
+    nodes.sort();
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MINHASH_MAGIC)?;
+    for value in [num_hashes as u64, nodes.len() as u64] {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    for (id, node) in nodes {
+        let friends = graph.adjacency()[&node].iter().map(|&friend| graph.original_id(InternalId(friend)).0); //File ids, so the signatures do not depend on compaction
+        writer.write_all(&(id as u64).to_le_bytes())?;
+        for minimum in minhash_signature(friends, num_hashes, seed) {
+            writer.write_all(&minimum.to_le_bytes())?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn read_minhash_sketches(path: &str) -> Result<HashMap<usize, Vec<u64>>, GraphError> { //Signatures written by export_minhash_sketches, by input-file id
+    let bytes = std::fs::read(path)?;
+    let bad = |why: &str| GraphError::Parse { line: 0, content: format!("{} is not a MinHash sketch file: {}", path, why) };
+    let (header, body) = bytes.split_at_checked(24).ok_or_else(|| bad("too short"))?;
+    if &header[..8] != MINHASH_MAGIC {
+        return Err(bad("wrong magic bytes"));
+    }
+    let values: Vec<u64> = body.chunks_exact(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect();
+    let field = |i: usize| u64::from_le_bytes(header[8 * i..8 * i + 8].try_into().unwrap()) as usize;
+    let (num_hashes, count) = (field(1), field(2));
+    if num_hashes == 0 || body.len() % 8 != 0 || values.len() != count.saturating_mul(num_hashes + 1) {
+        return Err(bad("its length does not match the header"));
+    }
+    Ok(values.chunks_exact(num_hashes + 1).map(|record| (record[0] as usize, record[1..].to_vec())).collect())
+}
+
+pub fn similarity_from_sketches(path: &str, u: usize, v: usize) -> Result<f64, GraphError> { //Estimated Jaccard similarity of u and v (input-file ids) from a sketch file alone; UnknownNode if either has no signature
+    sketch_similarity(&read_minhash_sketches(path)?, u, v)
+}
+
+pub fn sketch_similarity(sketches: &HashMap<usize, Vec<u64>>, u: usize, v: usize) -> Result<f64, GraphError> { //similarity_from_sketches over signatures already read with read_minhash_sketches, for looking up many pairs
+    let signature = |node: usize| sketches.get(&node).ok_or(GraphError::UnknownNode(node));
+    Ok(minhash_jaccard(signature(u)?, signature(v)?))
+}
+
+pub fn minhash_signatures(graph: &Graph, num_hashes: usize, seed: u64) -> HashMap<usize, Vec<u64>> { //minhash_signature of every node's friend list, by internal id
+    graph.adjacency().iter().map(|(&node, friends)| (node, minhash_signature(friends.iter().copied(), num_hashes, seed))).collect()
+}
+
+pub fn most_similar_pairs_approx(graph: &Graph, top_n: usize, num_hashes: usize, bands: usize, seed: u64) -> Result<RankedPairs, GraphError> { //most_similar_pairs from LSH candidates: only pairs whose signatures agree on a whole band are scored (exactly, with jaccard_similarity)
+    //With r = num_hashes / bands rows per band, a pair of Jaccard J becomes a candidate with probability 1 - (1 - J^r)^bands, so similar pairs are
+    //almost always found and dissimilar ones rarely scored. It is approximate: a pair that misses every band is left out even if it belongs in the top N.
+    //Same seed, same candidates; nodes with fewer than two friends are skipped as in most_similar_pairs
+    if bands == 0 || num_hashes == 0 || !num_hashes.is_multiple_of(bands) {
+        return Err(GraphError::InvalidParameter(format!("num_hashes must be a positive multiple of bands, got {} hashes in {} bands", num_hashes, bands)));
+    }
+    let rows = num_hashes / bands;
+    let mut signatures: Vec<(usize, Vec<u64>)> = minhash_signatures(graph, num_hashes, seed).into_iter().filter(|(node, _)| graph.adjacency()[node].len() > 1).collect();
+    signatures.sort(); //Bucket contents in id order, so pairs come out as (smaller id, larger id)
+    let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+    for band in 0..bands {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (node, signature) in &signatures {
+            let key = signature[band * rows..(band + 1) * rows].iter().fold(mix(band as u64), |hash, &minimum| mix(hash ^ minimum));
+            buckets.entry(key).or_default().push(*node);
+        }
+        for bucket in buckets.values() {
+            for (i, &u) in bucket.iter().enumerate() {
+                candidates.extend(bucket[i + 1..].iter().map(|&v| (u, v)));
+            }
+        }
+    }
+    let mut results: RankedPairs = candidates.into_iter().map(|(u, v)| ((u, v), jaccard_similarity(graph, InternalId(u), InternalId(v)))).filter(|&(_, sim)| sim > 0.0).collect();
+    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    results.truncate(top_n);
+    Ok(results)
+}
+
+#[derive(Debug)]
+pub struct AdaptiveJaccard { //Settings for jaccard_similarity_adaptive, plus the MinHash signatures it has built so far (one graph only: make a new one after changing the graph)
+    pub exact_below: usize, //Pairs whose degrees add up to less than this are compared exactly, the rest through signatures
+    pub num_hashes: usize,
+    pub seed: u64,
+    signatures: Mutex<HashMap<usize, Arc<Vec<u64>>>>, //Built the first time a node is in a sketched pair
+}
+
+impl AdaptiveJaccard {
+    pub fn new(exact_below: usize, num_hashes: usize, seed: u64) -> Self {
+        Self { exact_below, num_hashes, seed, signatures: Mutex::default() }
+    }
+
+    pub fn error_bound(&self) -> f64 { //Four worst-case standard errors: a sketched estimate is further than this from the exact value for about one pair in 15,000
+        4.0 * minhash_standard_error(self.num_hashes)
+    }
+
+    pub fn cached_signatures(&self) -> usize {
+        self.signatures.lock().unwrap().len()
+    }
+
+    fn signature<G: GraphRead + ?Sized>(&self, graph: &G, node: usize) -> Arc<Vec<u64>> {
+        let mut signatures = self.signatures.lock().unwrap();
+        signatures.entry(node).or_insert_with(|| Arc::new(minhash_signature(graph.neighbors(node), self.num_hashes, self.seed))).clone()
+    }
+}
+
+impl Default for AdaptiveJaccard {
+    fn default() -> Self { //Exact up to 2,000 combined friends (every pair in the Facebook data), 256 hashes above
+        Self::new(2000, 256, 42)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JaccardMethod {
+    Exact,
+    Sketched { error_bound: f64 }, //See AdaptiveJaccard::error_bound
+}
+
+pub fn jaccard_similarity_adaptive<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId, config: &AdaptiveJaccard) -> (f64, JaccardMethod) { //jaccard_similarity, estimated from cached MinHash signatures when the two friend lists are large together, and which way it was computed
+    if graph.degree(u) + graph.degree(v) < config.exact_below || !graph.contains_node(u) || !graph.contains_node(v) {
+        return (jaccard_similarity(graph, InternalId(u), InternalId(v)), JaccardMethod::Exact);
+    }
+    let estimate = minhash_jaccard(&config.signature(graph, u), &config.signature(graph, v));
+    (estimate, JaccardMethod::Sketched { error_bound: config.error_bound() })
+}
+
+pub fn most_similar_pairs<G: GraphRead + ?Sized>(graph: &G, top_n: usize) -> Vec<((usize, usize), f64)> { //Computes top N most similar node pairs based on Jaccard similarity.
+    most_similar_pairs_with(graph, &SimilarPairsOptions::top(top_n))
+}
+
+pub fn most_similar_pairs_by<G: GraphRead + ?Sized>(graph: &G, top_n: usize, metric: SimilarityMetric) -> Vec<((usize, usize), f64)> { //most_similar_pairs scored with another metric (same candidates: pairs of nodes with at least two friends that share one)
+    most_similar_pairs_with(graph, &SimilarPairsOptions { metric, ..SimilarPairsOptions::top(top_n) })
+}
+
+pub fn jaccard_lift(graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Observed Jaccard divided by the Jaccard expected if u and v had picked their friends at random (0.0 when either is 0)
+    //Null model: u keeps a = deg(u) friends other than v and v keeps b = deg(v) friends other than u, each set drawn uniformly
+    //from the N = n - 2 remaining people. The overlap I is then hypergeometric, P(I = k) = C(a, k) C(N - a, b - k) / C(N, b),
+    //and since the union is deg(u) + deg(v) - I, the expected Jaccard is the sum over k of P(I = k) * k / (deg(u) + deg(v) - k)
+    let observed = jaccard_similarity(graph, InternalId(u), InternalId(v));
+    let n = graph.adjacency().len();
+    if observed == 0.0 || n < 3 {
+        return 0.0;
+    }
+    let (deg_u, deg_v) = (graph.degree(u), graph.degree(v));
+    let linked = usize::from(graph.has_edge(u, v));
+    let (a, b, total) = (deg_u - linked, deg_v - linked, n - 2);
+
+    //P(k + 1) / P(k) = (a - k)(b - k) / ((k + 1)(N - a - b + k + 1)), so the weights are built up from the smallest possible overlap and normalized at the end
+    let first = (a + b).saturating_sub(total);
+    let mut weight = 1.0;
+    let (mut mass, mut expected) = (0.0, 0.0);
+    for k in first..=a.min(b) {
+        mass += weight;
+        expected += weight * k as f64 / (deg_u + deg_v - k) as f64;
+        weight *= ((a - k) * (b - k)) as f64 / ((k + 1) * (total + k + 1 - a - b)) as f64;
+    }
+    if expected == 0.0 { 0.0 } else { observed / (expected / mass) }
+}
+
+pub fn most_similar_pairs_by_lift(graph: &Graph, top_n: usize) -> Vec<((usize, usize), f64)> { //Same candidates as most_similar_pairs (overlapping pairs of nodes with at least two friends), ranked by jaccard_lift
+    let mut results: Vec<((usize, usize), f64)> = co_neighbor_counts(graph, 1)
+        .into_keys()
+        .filter(|&(u, v)| graph.degree(u) > 1 && graph.degree(v) > 1)
+        .map(|(u, v)| ((u, v), jaccard_lift(graph, InternalId(u), InternalId(v))))
+        .collect();
+    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    results.truncate(top_n);
+    results
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimilarPairsOptions<'a> { //Settings for the most similar pairs scan
+    pub top_n: usize,
+    pub partition: Option<&'a Partition>, //Community of each node, needed for cross_community_only
+    pub cross_community_only: bool,       //Drop pairs whose nodes are in the same community before scoring them
+    pub collapse_equivalent: bool,        //Score one representative per structural equivalence class and expand afterwards (same result, fewer pairs; ignored with `within`)
+    pub within: Option<&'a HashSet<usize>>, //Keep the whole scan inside the subgraph induced by these nodes (see jaccard_similarity_within)
+    pub metric: SimilarityMetric,           //How pairs are scored (Jaccard by default)
+    pub min_combined_degree: Option<usize>, //Drop pairs whose two degrees add up to less than this (leaf-leaf noise)
+    pub max_degree: Option<usize>,          //Drop pairs where either node has more friends than this (hubs)
+    pub max_degree_ratio: Option<f64>,      //Drop pairs whose larger degree is more than this many times the smaller one
+    pub exact_ranking: bool,                //Rank Jaccard pairs by the exact fraction common / union (see JaccardRatio), so ties never depend on float rounding; other metrics ignore it
+    pub adaptive: Option<&'a AdaptiveJaccard>, //Score Jaccard pairs with jaccard_similarity_adaptive (not with `within`, and exact_ranking rescores exactly); other metrics ignore it
+}
+
+impl SimilarPairsOptions<'_> {
+    fn degrees_allowed(&self, degree_u: usize, degree_v: usize) -> bool { //The degree filters, checked before a pair is scored
+        let (low, high) = (degree_u.min(degree_v), degree_u.max(degree_v));
+        self.min_combined_degree.is_none_or(|min| low + high >= min)
+            && self.max_degree.is_none_or(|max| high <= max)
+            && self.max_degree_ratio.is_none_or(|ratio| high as f64 <= ratio * low as f64)
+    }
+}
+
+pub type RankedPairs = Vec<((usize, usize), f64)>; //Scored node pairs, best first (ties by pair)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SimilarPairsStats {
+    pub scored: usize,             //Candidate pairs (sharing a friend, both with two or more friends) that were scored
+    pub filtered_by_degree: usize, //Candidate pairs dropped by the degree filters before scoring
+}
+
+impl SimilarPairsOptions<'_> {
+    pub fn top(top_n: usize) -> Self {
+        Self { top_n, ..Self::default() }
+    }
+}
+
+pub fn most_similar_pairs_with<G: GraphRead + ?Sized>(graph: &G, options: &SimilarPairsOptions) -> Vec<((usize, usize), f64)> { //Same as most_similar_pairs, with extra candidate filters
+    most_similar_pairs_with_stats(graph, options).0
+}
+
+pub fn most_similar_pairs_with_progress<G: GraphRead + ?Sized>(graph: &G, options: &SimilarPairsOptions, progress: &(dyn Fn(usize, usize) + Sync)) -> Vec<((usize, usize), f64)> { //most_similar_pairs_with, calling progress with (nodes scanned, nodes to scan) every PROGRESS_EVERY nodes and once at the end
+    similar_pairs_scan(graph, options, progress).0
+}
+
+pub fn most_similar_pairs_with_stats<G: GraphRead + ?Sized>(graph: &G, options: &SimilarPairsOptions) -> (RankedPairs, SimilarPairsStats) { //most_similar_pairs_with, also counting the pairs scored and the ones the degree filters dropped (per class representative with collapse_equivalent)
+    similar_pairs_scan(graph, options, &|_, _| {})
+}
+
+pub(crate) fn similar_pairs_scan<G: GraphRead + ?Sized>(graph: &G, options: &SimilarPairsOptions, progress: &(dyn Fn(usize, usize) + Sync)) -> (RankedPairs, SimilarPairsStats) { //most_similar_pairs_with_stats with a progress callback (the collapsed scan only reports its end)
+    let mut stats = SimilarPairsStats::default();
+    if options.collapse_equivalent && options.within.is_none() {
+        let results = collapsed_similar_pairs(graph, options, &mut stats);
+        let total = graph.nodes().count();
+        progress(total, total);
+        return (results, stats);
+    }
+    let mut results = Vec::new();
+    let mut nodes: Vec<usize> = graph.nodes().filter(|n| options.within.is_none_or(|allowed| allowed.contains(n))).collect();
+    nodes.sort(); //Sorted so every pair is reported as (smaller id, larger id) whatever the HashMap order
+
+    let done = AtomicUsize::new(0);
+    for i in 0..nodes.len() { //For all unique node pairs it will compute similarity, skip sparse nodes, and sort them
+        results.extend(similar_pairs_from(graph, nodes[i], &nodes[i + 1..], options, &mut stats));
+        tick(progress, &done, nodes.len());
+    }
+
+    rank_pairs(graph, &mut results, options);
+    (results, stats)
+}
+
+fn rank_pairs<G: GraphRead + ?Sized>(graph: &G, results: &mut RankedPairs, options: &SimilarPairsOptions) { //Sorts best first (equal scores by pair, so the top N is reproducible) and keeps the top N
+    if options.exact_ranking && options.metric == SimilarityMetric::Jaccard {
+        let mut exact: Vec<(JaccardRatio, (usize, usize))> = results.iter().map(|&((u, v), _)| (jaccard_ratio(graph, InternalId(u), InternalId(v), options.within), (u, v))).collect();
+        exact.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        *results = exact.into_iter().take(options.top_n).map(|(ratio, pair)| (pair, ratio.to_f64())).collect(); //Converted to f64 only once the order is fixed
+        return;
+    }
+    results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    results.truncate(options.top_n);
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct JaccardRatio { //Jaccard similarity as the exact fraction intersection / union; compared by cross-multiplying, so 1/3 and 2/6 are equal and no two different fractions ever tie
+    pub intersection: usize,
+    pub union: usize,
+}
+
+impl JaccardRatio {
+    pub fn to_f64(self) -> f64 { //The value jaccard_similarity returns (0.0 for an empty union)
+        if self.union == 0 { 0.0 } else { self.intersection as f64 / self.union as f64 }
+    }
+}
+
+impl Ord for JaccardRatio {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let (a, b) = (self.intersection as u128 * other.union.max(1) as u128, other.intersection as u128 * self.union.max(1) as u128);
+        a.cmp(&b)
+    }
+}
+
+impl PartialOrd for JaccardRatio {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for JaccardRatio {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for JaccardRatio {}
+
+pub fn jaccard_ratio<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId, allowed: Option<&HashSet<usize>>) -> JaccardRatio { //Exact Jaccard of u and v (friends counted inside `allowed` when given); 0/0 if either node is unknown or outside it
+    let (common, degree_u, degree_v) = neighbor_overlap(graph, u, v, allowed).unwrap_or((0, 0, 0));
+    JaccardRatio { intersection: common, union: degree_u + degree_v - common }
+}
+
+pub fn structural_equivalence_classes<G: GraphRead + ?Sized>(graph: &G) -> Vec<Vec<usize>> { //Groups nodes with exactly the same set of friends (every node is in one class, singletons included), sorted by smallest member
+    let mut by_neighbors: HashMap<Vec<usize>, Vec<usize>> = HashMap::new();
+    for node in graph.nodes() {
+        let mut key: Vec<usize> = graph.neighbors(node).collect();
+        key.sort(); //Canonical form of the neighbor set
+        by_neighbors.entry(key).or_default().push(node);
+    }
+    let mut classes: Vec<Vec<usize>> = by_neighbors
+        .into_values()
+        .map(|mut class| {
+            class.sort();
+            class
+        })
+        .collect();
+    classes.sort();
+    classes
+}
+
+fn collapsed_similar_pairs<G: GraphRead + ?Sized>(graph: &G, options: &SimilarPairsOptions, stats: &mut SimilarPairsStats) -> Vec<((usize, usize), f64)> { //most_similar_pairs_with over one representative per equivalence class
+    //Members of a class have identical friend sets, so they score exactly like their representative against everyone else,
+    //and two members of the same class (never friends with each other) have Jaccard 1.0
+    let classes = structural_equivalence_classes(graph);
+    let representatives: Vec<usize> = classes.iter().map(|c| c[0]).collect();
+    let members: HashMap<usize, &Vec<usize>> = classes.iter().map(|c| (c[0], c)).collect();
+    let unfiltered = SimilarPairsOptions { cross_community_only: false, ..*options }; //Members of one class can sit in different communities, so filter after expanding
+
+    let mut results = Vec::new();
+    for (i, &u) in representatives.iter().enumerate() {
+        for ((a, b), sim) in similar_pairs_from(graph, u, &representatives[i + 1..], &unfiltered, stats) {
+            for &x in members[&a] {
+                for &y in members[&b] {
+                    results.push(((x.min(y), x.max(y)), sim));
+                }
+            }
+        }
+        if graph.degree(u) > 1 && members[&u].len() > 1 && options.degrees_allowed(graph.degree(u), graph.degree(u)) { //Sparse nodes are skipped by the normal scan too
+            let sim = options.metric.score_within(graph, InternalId(u), InternalId(members[&u][1]), None); //The same for every two members (1.0 for Jaccard)
+            for (j, &x) in members[&u].iter().enumerate() {
+                results.extend(members[&u][j + 1..].iter().map(|&y| ((x, y), sim)));
+            }
+        }
+    }
+    if options.cross_community_only {
+        if let Some(partition) = options.partition {
+            results.retain(|&((x, y), _)| !matches!((partition.get(&x), partition.get(&y)), (Some(a), Some(b)) if a == b));
+        }
+    }
+    rank_pairs(graph, &mut results, options);
+    results
+}
+
+pub(crate) fn similar_pairs_from<G: GraphRead + ?Sized>(graph: &G, u: usize, candidates: &[usize], options: &SimilarPairsOptions, stats: &mut SimilarPairsStats) -> Vec<((usize, usize), f64)> { //Scores u against each (sorted) candidate, skipping sparse nodes (at most one friend), filtered pairs and pairs with no overlap
+    let mut pairs = Vec::new();
+    let inside = |n: &usize| options.within.is_none_or(|allowed| allowed.contains(n));
+    let degree = |n: usize| match options.within { //Friends counted inside the allowed set when the scan is restricted
+        None => graph.degree(n),
+        Some(_) => {
+            let mut count = 0;
+            graph.for_each_neighbor(n, |w| count += usize::from(inside(&w)));
+            count
+        }
+    };
+    let degree_u = degree(u);
+    if degree_u <= 1 {
+        return pairs;
+    }
+    //Only candidates sharing a friend with u can score above 0, so they are found through u's friends instead of trying every candidate
+    let two_hop = two_hop_candidates(graph, u, candidates, inside);
+    let community_u = options.partition.and_then(|p| p.get(&u));
+    for v in two_hop {
+        let degree_v = degree(v);
+        if degree_v <= 1 {
+            continue;
+        }
+        if !options.degrees_allowed(degree_u, degree_v) {
+            stats.filtered_by_degree += 1;
+            continue;
+        }
+        if options.cross_community_only && community_u.is_some() && community_u == options.partition.and_then(|p| p.get(&v)) {
+            continue;
+        }
+        stats.scored += 1;
+        let sim = match options.adaptive {
+            Some(adaptive) if options.metric == SimilarityMetric::Jaccard && options.within.is_none() => jaccard_similarity_adaptive(graph, InternalId(u), InternalId(v), adaptive).0,
+            _ => options.metric.score_within(graph, InternalId(u), InternalId(v), options.within),
+        };
+        if sim > 0.0 {
+            pairs.push(((u, v), sim));
+        }
+    }
+    pairs
+}
+
+fn two_hop_candidates<G: GraphRead + ?Sized>(graph: &G, u: usize, candidates: &[usize], inside: impl Fn(&usize) -> bool) -> Vec<usize> { //The (sorted) candidates sharing at least one friend with u, only friends passing inside counted; sorted, each once
+    let mut two_hop = Vec::new();
+    graph.for_each_neighbor(u, |w| {
+        if inside(&w) {
+            graph.for_each_neighbor(w, |v| if v != u && candidates.binary_search(&v).is_ok() { two_hop.push(v) });
+        }
+    });
+    two_hop.sort();
+    two_hop.dedup();
+    two_hop
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SimilarityMetric { //Neighbor-based similarity measures that can be used to compare two nodes
+    #[default]
+    Jaccard,
+    Cosine,             //Common friends over sqrt(deg(u) deg(v)), see cosine_similarity
+    Overlap,            //Common friends over the smaller degree, see overlap_coefficient
+    AdamicAdar,         //Common friends weighted by 1 / ln(their degree), see adamic_adar
+    ResourceAllocation, //Common friends weighted by 1 / their degree, see resource_allocation
+}
+
+impl SimilarityMetric {
+    pub fn from_name(name: &str) -> Result<Self, GraphError> { //Parses the names used on the command line
+        match name {
+            "jaccard" => Ok(SimilarityMetric::Jaccard),
+            "cosine" => Ok(SimilarityMetric::Cosine),
+            "overlap" => Ok(SimilarityMetric::Overlap),
+            "adamic-adar" => Ok(SimilarityMetric::AdamicAdar),
+            "resource-allocation" => Ok(SimilarityMetric::ResourceAllocation),
+            other => Err(GraphError::InvalidParameter(format!("unknown similarity metric '{}' (expected jaccard, cosine, overlap, adamic-adar or resource-allocation)", other))),
+        }
+    }
+
+    pub fn score(&self, graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Dispatches to the function that computes the chosen metric for the pair (u, v)
+        self.score_within(graph, InternalId(u), InternalId(v), None)
+    }
+
+    pub fn score_within<G: GraphRead + ?Sized>(&self, graph: &G, InternalId(u): InternalId, InternalId(v): InternalId, allowed: Option<&HashSet<usize>>) -> f64 { //score, counting only friends inside `allowed` when it is given (as on the induced subgraph)
+        match (self, allowed) {
+            (SimilarityMetric::Jaccard, None) => jaccard_similarity(graph, InternalId(u), InternalId(v)),
+            (SimilarityMetric::Jaccard, Some(allowed)) => jaccard_similarity_within(graph, InternalId(u), InternalId(v), allowed),
+            (SimilarityMetric::Cosine | SimilarityMetric::Overlap, _) => neighbor_overlap(graph, u, v, allowed).and_then(|(common, du, dv)| self.score_from_count(common, du, dv)).unwrap_or(0.0),
+            (SimilarityMetric::AdamicAdar, _) => weighted_common_neighbors(graph, u, v, allowed, adamic_adar_weight),
+            (SimilarityMetric::ResourceAllocation, _) => weighted_common_neighbors(graph, u, v, allowed, |degree| 1.0 / degree as f64),
+        }
+    }
+
+    pub fn score_from_count(&self, common: usize, degree_u: usize, degree_v: usize) -> Option<f64> { //Same score from an already known number of common neighbors (see co_neighbor_counts); None for metrics that also need each common friend's degree
+        match self {
+            SimilarityMetric::Jaccard => {
+                let union = degree_u + degree_v - common;
+                Some(if union == 0 { 0.0 } else { common as f64 / union as f64 })
+            }
+            SimilarityMetric::Cosine => Some(if degree_u == 0 || degree_v == 0 { 0.0 } else { common as f64 / ((degree_u * degree_v) as f64).sqrt() }),
+            SimilarityMetric::Overlap => Some(if degree_u.min(degree_v) == 0 { 0.0 } else { common as f64 / degree_u.min(degree_v) as f64 }),
+            SimilarityMetric::AdamicAdar | SimilarityMetric::ResourceAllocation => None,
+        }
+    }
+}
+
+pub fn adamic_adar<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Sum of 1 / ln(deg(w)) over the common friends w of u and v: a friend shared with few others counts for more
+    weighted_common_neighbors(graph, u, v, None, adamic_adar_weight)
+}
+
+pub fn resource_allocation<G: GraphRead + ?Sized>(graph: &G, InternalId(u): InternalId, InternalId(v): InternalId) -> f64 { //Sum of 1 / deg(w) over the common friends w of u and v: like adamic_adar but punishing popular friends harder
+    weighted_common_neighbors(graph, u, v, None, |degree| 1.0 / degree as f64)
+}
+
+fn adamic_adar_weight(degree: usize) -> f64 { //1 / ln(degree). A common friend of two different people has at least two friends, so ln is never 0 for a real pair;
+    //a degree below 2 can only come from u == v, and such a friend is skipped (weight 0.0) rather than dividing by ln(1) = 0
+    if degree < 2 { 0.0 } else { 1.0 / (degree as f64).ln() }
+}
+
+fn weighted_common_neighbors<G: GraphRead + ?Sized>(graph: &G, u: usize, v: usize, allowed: Option<&HashSet<usize>>, weight: impl Fn(usize) -> f64) -> f64 { //Sum of weight(degree of w) over the common friends w, degrees and friends counted inside `allowed` when given
+    let inside = |n: &usize| allowed.is_none_or(|a| a.contains(n));
+    if !inside(&u) || !inside(&v) || !graph.contains_node(u) || !graph.contains_node(v) {
+        return 0.0;
+    }
+    let degree = |w: usize| if allowed.is_some() { graph.neighbors(w).filter(inside).count() } else { graph.degree(w) };
+    let mut common: Vec<usize> = graph.neighbors(u).filter(|w| inside(w) && graph.has_edge(v, *w)).collect();
+    common.sort(); //Summed in a fixed order so the float result does not depend on HashSet order
+    common.into_iter().map(|w| weight(degree(w))).sum()
+}
+
+pub(crate) fn mutual_friend_counts(graph: &Graph, u: usize) -> HashMap<usize, usize> { //Every non-friend of u who shares a friend with u, with the number of friends they share
+    let mut counts = HashMap::new();
+    let friends = match graph.adjacency().get(&u) {
+        Some(friends) => friends,
+        None => return counts,
+    };
+    for friend in friends {
+        for &v in &graph.adjacency()[friend] {
+            if v != u && !friends.contains(&v) {
+                *counts.entry(v).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CoNeighborOptions { //Settings for co_neighbor_counts_with
+    pub min_count: u32,           //Drop pairs with fewer common neighbors than this
+    pub hub_cap: Option<usize>,   //Nodes with more friends than this are not expanded (a hub of degree d alone adds d(d-1)/2 pairs)
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CoNeighborCounts {
+    pub counts: HashMap<(usize, usize), u32>, //(u, v) with u < v -> number of common neighbors (friends or not)
+    pub skipped_hubs: Vec<usize>,             //Nodes over the hub cap, sorted; they are not counted as common neighbors of anyone
+    pub skipped_pairs: usize,                 //Neighbor pairs those hubs would have contributed
+}
+
+pub fn co_neighbor_counts(graph: &Graph, min_count: u32) -> HashMap<(usize, usize), u32> { //Number of common neighbors of every pair that has at least min_count of them, in one pass over the graph
+    co_neighbor_counts_with(graph, &CoNeighborOptions { min_count, hub_cap: None }).counts
+}
+
+pub fn co_neighbor_counts_with(graph: &Graph, options: &CoNeighborOptions) -> CoNeighborCounts { //Same as co_neighbor_counts, with hub mitigation
+    //Every pair of friends of w has w as a common neighbor, so enumerating the pairs inside each adjacency list and
+    //counting how often each pair appears gives all intersection sizes at once (like a hash join of the edge list with itself)
+    let mut result = CoNeighborCounts::default();
+    for (&middle, friends) in graph.adjacency() {
+        if options.hub_cap.is_some_and(|cap| friends.len() > cap) {
+            result.skipped_hubs.push(middle);
+            result.skipped_pairs = result.skipped_pairs.saturating_add(pairs_among(friends.len()).unwrap_or(usize::MAX)); //Only a statistic, so it saturates instead of failing the scan
+            continue;
+        }
+        let mut friends: Vec<usize> = friends.iter().copied().collect();
+        friends.sort();
+        for (i, &u) in friends.iter().enumerate() {
+            for &v in &friends[i + 1..] {
+                *result.counts.entry((u, v)).or_insert(0) += 1;
+            }
+        }
+    }
+    result.counts.retain(|_, count| *count >= options.min_count);
+    result.skipped_hubs.sort();
+    result
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation { //One suggested friend for a node
+    pub node: usize,
+    pub rank: usize, //1 = most similar candidate for this node
+    pub candidate: usize,
+    pub score: f64,
+    pub mutual_count: usize,
+}
+
+pub fn recommendations_for_all(graph: &Graph, k: usize, min_degree: usize, metric: SimilarityMetric) -> Vec<Recommendation> { //For every node with at least min_degree friends, its k most similar non-friends (rows grouped by node id, best first)
+    recommendations_for_all_with(graph, k, min_degree, metric, &ParallelismConfig::default(), &|_, _| {})
+}
+
+pub fn recommendations_for_all_with(
+    graph: &Graph,
+    k: usize,
+    min_degree: usize,
+    metric: SimilarityMetric,
+    parallelism: &ParallelismConfig,
+    progress: &(dyn Fn(usize, usize) + Sync), //Called with (nodes done, nodes to do) after each node, possibly from several threads
+) -> Vec<Recommendation> { //Same as recommendations_for_all, with control over threads and a progress callback
+    let mut nodes: Vec<usize> = graph.adjacency().iter().filter(|(_, n)| n.len() >= min_degree).map(|(&u, _)| u).collect();
+    nodes.sort();
+    let done = AtomicUsize::new(0);
+    let per_node = parallelism.map_nodes(&nodes, |u| {
+        let mut scored = ranked_candidates(graph, u, metric);
+        scored.truncate(k);
+        progress(done.fetch_add(1, AtomicOrdering::Relaxed) + 1, nodes.len());
+        scored
+            .into_iter()
+            .enumerate()
+            .map(|(i, (candidate, score, mutual_count))| Recommendation { node: u, rank: i + 1, candidate, score, mutual_count })
+            .collect::<Vec<_>>()
+    });
+    per_node.into_iter().flatten().collect()
+}
+
+pub fn recommend_friends(graph: &Graph, InternalId(node): InternalId, top_k: usize, metric: SimilarityMetric) -> Vec<(usize, f64)> { //The top_k people node should befriend next: friends of friends who are not yet friends, best score first (empty for unknown or isolated nodes)
+    let mut scored = ranked_candidates(graph, node, metric);
+    scored.truncate(top_k);
+    scored.into_iter().map(|(candidate, score, _)| (candidate, score)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairExplanation { //Everything explain_pair knows about why two people are (or are not) suggested to each other, with the input file's ids
+    pub u: NodeId,
+    pub v: NodeId,
+    pub distance: Option<usize>,       //Hops on a shortest path; None if they are not connected
+    pub path: Option<Vec<NodeId>>,     //One shortest path u, ..., v (see bfs_path)
+    pub mutual_friends: Vec<NodeId>,   //Sorted; their number is the common-neighbors score
+    pub jaccard: f64,
+    pub adamic_adar: f64,
+    pub same_community: Option<bool>, //None when no community partition was given
+}
+
+pub fn explain_pair(graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId, context: Option<&AnalysisResults>) -> Result<PairExplanation, GraphError> { //Distance, a shortest path, mutual friends, similarity scores and (if context holds communities) whether u and v share one
+    match (graph.adjacency().contains_key(&u), graph.adjacency().contains_key(&v)) {
+        (false, false) => return Err(GraphError::UnknownNodes(vec![u, v])),
+        (false, true) => return Err(GraphError::UnknownNode(u)),
+        (true, false) => return Err(GraphError::UnknownNode(v)),
+        (true, true) => {}
+    }
+    let path = bfs_path(graph, InternalId(u), InternalId(v));
+    let mut mutual: Vec<usize> = graph.adjacency()[&u].intersection(&graph.adjacency()[&v]).copied().collect();
+    mutual.sort();
+    let same_community = context.and_then(|results| results.communities.as_ref()).map(|partition| partition.contains_key(&u) && partition.get(&u) == partition.get(&v));
+    Ok(PairExplanation {
+        u: graph.original_id(InternalId(u)),
+        v: graph.original_id(InternalId(v)),
+        distance: path.as_ref().map(|p| p.len() - 1),
+        path: path.map(|p| p.into_iter().map(|n| graph.original_id(InternalId(n))).collect()),
+        mutual_friends: mutual.into_iter().map(|n| graph.original_id(InternalId(n))).collect(),
+        jaccard: jaccard_similarity(graph, InternalId(u), InternalId(v)),
+        adamic_adar: adamic_adar(graph, InternalId(u), InternalId(v)),
+        same_community,
+    })
+}
+
+impl fmt::Display for PairExplanation { //A few indented lines, e.g. "  Distance: 2 (1 → 2 → 4)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Nodes {} and {}:", self.u, self.v)?;
+        match &self.path {
+            Some(path) => {
+                let chain: Vec<String> = path.iter().map(NodeId::to_string).collect();
+                writeln!(f, "  Distance: {} ({})", path.len() - 1, chain.join(" → "))?;
+            }
+            None => writeln!(f, "  Distance: not connected")?,
+        }
+        let mutual: Vec<String> = self.mutual_friends.iter().map(NodeId::to_string).collect();
+        writeln!(f, "  Mutual friends ({}): {}", mutual.len(), if mutual.is_empty() { String::from("none") } else { mutual.join(" ") })?;
+        writeln!(f, "  Common neighbors: {}, Jaccard: {:.4}, Adamic-Adar: {:.4}", self.mutual_friends.len(), self.jaccard, self.adamic_adar)?;
+        let community = match self.same_community {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "unknown (communities not computed)",
+        };
+        writeln!(f, "  Same community: {}", community)
+    }
+}
+
+pub const INSPECTOR_CAPACITY: usize = 64; //NodeReports a NodeInspector keeps by default
+pub const INSPECT_TOP_SIMILAR: usize = 10; //Suggestions listed in a NodeReport
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeReport { //Everything a dashboard shows for one pinned node (internal ids)
+    pub node: usize,
+    pub degree: usize,
+    pub clustering: f64,
+    pub closeness: f64,              //Same value as closeness_of: the rings' closeness shares added up
+    pub rings: Vec<DistanceRing>,    //closeness_breakdown of the node
+    pub similar: Vec<(usize, f64)>,  //recommend_friends by Jaccard, top INSPECT_TOP_SIMILAR
+    pub community: Option<usize>,    //None when the results hold no communities
+}
+
+pub struct NodeInspector<'a> { //Answers repeated per-node questions cheaply: clustering and community come from the stored results when they are fresh,
+    //the rest from one BFS and the node's friends of friends, and the last few reports are kept (least recently inspected dropped first)
+    graph: &'a Graph,
+    results: &'a AnalysisResults,
+    stale: Vec<&'static str>, //Stored results computed against another version of the graph, never reused
+    capacity: usize,
+    recent: VecDeque<NodeReport>, //Most recently inspected last
+    computed: usize,
+}
+
+impl<'a> NodeInspector<'a> {
+    pub fn new(graph: &'a Graph, results: &'a AnalysisResults) -> Self {
+        Self::with_capacity(graph, results, INSPECTOR_CAPACITY)
+    }
+
+    pub fn with_capacity(graph: &'a Graph, results: &'a AnalysisResults, capacity: usize) -> Self { //Keeps up to capacity reports (at least one)
+        NodeInspector { graph, results, stale: results.stale(graph), capacity: capacity.max(1), recent: VecDeque::new(), computed: 0 }
+    }
+
+    pub fn computed(&self) -> usize { //Reports built from scratch so far (inspecting a kept node does not count)
+        self.computed
+    }
+
+    pub fn inspect(&mut self, InternalId(node): InternalId) -> Result<NodeReport, GraphError> { //The node's report, from the kept ones if it was inspected recently. UnknownNode for ids not in the graph
+        if let Some(index) = self.recent.iter().position(|report| report.node == node) {
+            let report = self.recent.remove(index).unwrap();
+            self.recent.push_back(report.clone());
+            return Ok(report);
+        }
+        let report = self.build(node)?;
+        self.computed += 1;
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(report.clone());
+        Ok(report)
+    }
+
+    fn build(&self, node: usize) -> Result<NodeReport, GraphError> {
+        let rings = closeness_breakdown(self.graph, InternalId(node))?;
+        let fresh = |name: &str| !self.stale.contains(&name);
+        let clustering = match self.results.clustering.as_ref().filter(|_| fresh("clustering")).and_then(|c| c.get(&node)) {
+            Some(&value) => value,
+            None => local_clustering_of(self.graph, InternalId(node))?,
+        };
+        Ok(NodeReport {
+            node,
+            degree: self.graph.degree(node),
+            clustering,
+            closeness: rings.iter().map(|ring| ring.closeness).sum(),
+            similar: recommend_friends(self.graph, InternalId(node), INSPECT_TOP_SIMILAR, SimilarityMetric::Jaccard),
+            community: self.results.communities.as_ref().filter(|_| fresh("communities")).and_then(|p| p.get(&node).copied()),
+            rings,
+        })
+    }
+}
+
+fn ranked_candidates(graph: &Graph, u: usize, metric: SimilarityMetric) -> Vec<(usize, f64, usize)> { //Every friend of a friend of u who is not u or already a friend, as (candidate, score, mutual friends), best first
+    let mut scored: Vec<(usize, f64, usize)> = mutual_friend_counts(graph, u).into_iter().map(|(v, mutual)| (v, metric.score(graph, InternalId(u), InternalId(v)), mutual)).collect();
+    scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0))); //Ties go to the smaller id so the result is the same on every run
+    scored
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimilarityGraphOptions {
+    pub max_candidates: Option<usize>, //Stop with BudgetExceeded if more candidate pairs than this would have to be scored
+    pub drop_isolated: bool,           //Leave out nodes that end up with no similar partner (by default every node is kept)
+    pub memory: MemoryBudget,          //Checked against the estimated size of the candidate table before it is built
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SimilarityGraphStats {
+    pub candidate_pairs: usize, //Pairs that share a friend and were scored
+    pub edges: usize,           //Pairs whose similarity passed the threshold
+}
+
+pub fn similarity_graph(graph: &Graph, threshold: f64, metric: SimilarityMetric, options: &SimilarityGraphOptions) -> Result<(WeightedGraph, SimilarityGraphStats), GraphError> { //Builds a graph on the same people where an edge joins two nodes whose similarity is above threshold, weighted by that similarity
+    //Only pairs that share a friend are scored: every other pair has zero overlap, so this scales with the number of two-hop paths instead of n²
+    let mut similar = WeightedGraph::new();
+    if !options.drop_isolated {
+        for &u in graph.adjacency().keys() {
+            similar.add_node(u);
+        }
+    }
+    options.memory.check(graph.estimate_analysis_memory(AnalysisKind::CoNeighborCounts))?;
+    let mut pairs: Vec<((usize, usize), u32)> = co_neighbor_counts(graph, 1).into_iter().collect();
+    pairs.sort();
+    let mut stats = SimilarityGraphStats { candidate_pairs: pairs.len(), edges: 0 };
+    if let Some(cap) = options.max_candidates.filter(|&cap| stats.candidate_pairs > cap) {
+        return Err(GraphError::BudgetExceeded { needed: stats.candidate_pairs, budget: cap });
+    }
+    for ((u, v), common) in pairs {
+        let sim = metric.score_from_count(common as usize, graph.degree(u), graph.degree(v)).unwrap_or_else(|| metric.score(graph, InternalId(u), InternalId(v)));
+        if sim > threshold {
+            similar.add_edge(u, v, sim);
+            stats.edges += 1;
+        }
+    }
+    Ok((similar, stats))
+}
+
+pub const MAX_SIMRANK_NODES: usize = 2000; //Default cap on the graph size for simrank: it keeps a score for every pair of nodes
+pub const SIMRANK_DECAY: f64 = 0.8; //The decay C of the SimRank paper: how much a pair's score owes to its neighbors' scores
+
+pub fn simrank(graph: &Graph, decay: f64, max_iter: usize, tol: f64) -> Result<HashMap<(usize, usize), f64>, GraphError> { //simrank_with under the MAX_SIMRANK_NODES cap
+    simrank_with(graph, decay, max_iter, tol, MAX_SIMRANK_NODES)
+}
+
+pub fn simrank_with(graph: &Graph, decay: f64, max_iter: usize, tol: f64, max_nodes: usize) -> Result<HashMap<(usize, usize), f64>, GraphError> { //SimRank of every pair (u, v) with u <= v:
+    //two people are similar if their friends are. s(u, u) = 1 and s(u, v) = decay * (average of s(a, b) over friends a of u and b of v), found by iterating from
+    //the identity until no score moves by more than tol (or max_iter rounds). Scores lie in [0, 1]; isolated nodes score 0 with everyone else.
+    //Memory and each round grow with the square of the node count, so graphs above max_nodes are refused with BudgetExceeded: run it on an ego network
+    check_decay(decay)?;
+    if graph.num_nodes() > max_nodes {
+        return Err(GraphError::BudgetExceeded { needed: graph.num_nodes(), budget: max_nodes });
+    }
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let index: HashMap<usize, usize> = nodes.iter().enumerate().map(|(i, &node)| (node, i)).collect();
+    let friends: Vec<Vec<usize>> = nodes.iter().map(|&node| graph.sorted_neighbors(InternalId(node)).iter().map(|friend| index[friend]).collect()).collect();
+    let n = nodes.len();
+    let mut scores: Vec<Vec<f64>> = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect()).collect();
+    for _ in 0..max_iter {
+        //partial[a][j] = sum of s(a, b) over the friends b of j, so each new score is one sum over the friends of the other node
+        let partial: Vec<Vec<f64>> = scores.iter().map(|row| friends.iter().map(|of_j| of_j.iter().map(|&b| row[b]).sum()).collect()).collect();
+        let mut change: f64 = 0.0;
+        let mut next = vec![vec![0.0; n]; n];
+        for i in 0..n {
+            next[i][i] = 1.0;
+            for j in i + 1..n {
+                if friends[i].is_empty() || friends[j].is_empty() {
+                    continue;
+                }
+                let total: f64 = friends[i].iter().map(|&a| partial[a][j]).sum();
+                let score = decay * total / (friends[i].len() * friends[j].len()) as f64;
+                (next[i][j], next[j][i]) = (score, score);
+                change = change.max((score - scores[i][j]).abs());
+            }
+        }
+        scores = next;
+        if change < tol {
+            break;
+        }
+    }
+    Ok((0..n).flat_map(|i| (i..n).map(move |j| (i, j))).map(|(i, j)| ((nodes[i], nodes[j]), scores[i][j])).collect())
+}
+
+pub fn simrank_single(graph: &Graph, InternalId(u): InternalId, InternalId(v): InternalId, decay: f64, max_iter: usize, tol: f64) -> Result<f64, GraphError> { //SimRank of one pair without the all-pairs table,
+    //from the random-surfer view of the paper: two walkers start at u and v and step to random friends together; the score is the sum of decay^t times the chance
+    //they first meet at step t. We push the joint distribution of the unmet walkers forward, so the cost depends on how many pairs they can reach, not on the graph size.
+    //After k steps this equals k rounds of simrank; it stops after max_iter steps or once the unmet mass can add no more than tol
+    check_decay(decay)?;
+    for node in [u, v] {
+        if !graph.adjacency().contains_key(&node) {
+            return Err(GraphError::UnknownNode(node));
+        }
+    }
+    if u == v {
+        return Ok(1.0);
+    }
+    let mut walkers: HashMap<(usize, usize), f64> = HashMap::from([((u.min(v), u.max(v)), 1.0)]); //Chance the walkers are at this pair, unmet; (a, b) and (b, a) are merged since s is symmetric
+    let (mut score, mut weight) = (0.0, 1.0);
+    for _ in 0..max_iter {
+        weight *= decay;
+        let mut next: HashMap<(usize, usize), f64> = HashMap::new();
+        for (&(a, b), &mass) in &walkers {
+            let (of_a, of_b) = (graph.sorted_neighbors(InternalId(a)), graph.sorted_neighbors(InternalId(b)));
+            let step = mass / (of_a.len() * of_b.len()) as f64;
+            for &x in of_a {
+                for &y in of_b {
+                    if x == y {
+                        score += weight * step;
+                    } else {
+                        *next.entry((x.min(y), x.max(y))).or_insert(0.0) += step;
+                    }
+                }
+            }
+        }
+        walkers = next;
+        if walkers.values().sum::<f64>() * weight * decay < tol {
+            break;
+        }
+    }
+    Ok(score)
+}
+
+fn check_decay(decay: f64) -> Result<(), GraphError> { //SimRank's decay must lie strictly between 0 and 1 for the scores to converge below 1
+    if !(decay > 0.0 && decay < 1.0) {
+        return Err(GraphError::InvalidParameter(format!("SimRank decay must be between 0 and 1 (exclusive), got {}", decay)));
+    }
+    Ok(())
+}
+
+pub const MAX_MATRIX_NODES: usize = 2000; //Default cap on the subset size for similarity_matrix, since the matrix grows quadratically
+
+pub fn similarity_matrix(graph: &Graph, nodes: &[usize], metric: SimilarityMetric) -> Result<Vec<Vec<f64>>, GraphError> { //Computes the full pairwise similarity matrix for a subset of nodes (rows/columns follow the order of `nodes`)
+    let default_budget = MemoryBudget::bytes(graph.estimate_analysis_memory(AnalysisKind::SimilarityMatrix(MAX_MATRIX_NODES)));
+    similarity_matrix_with(graph, nodes, metric, &default_budget)
+}
+
+pub fn similarity_matrix_with(graph: &Graph, nodes: &[usize], metric: SimilarityMetric, memory: &MemoryBudget) -> Result<Vec<Vec<f64>>, GraphError> { //Same as similarity_matrix with an explicit memory budget instead of the MAX_MATRIX_NODES cap
+    memory.check(graph.estimate_analysis_memory(AnalysisKind::SimilarityMatrix(nodes.len())))?;
+    let unknown: Vec<usize> = nodes.iter().copied().filter(|n| !graph.adjacency().contains_key(n)).collect();
+    if !unknown.is_empty() {
+        return Err(GraphError::UnknownNodes(unknown));
+    }
+
+    //The matrix is symmetric, so we only score the upper triangle and mirror it. By convention a node is fully similar to itself, so the diagonal is 1.0
+    let n = nodes.len();
+    let mut matrix = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        matrix[i][i] = 1.0;
+        for j in i + 1..n {
+            let sim = metric.score(graph, InternalId(nodes[i]), InternalId(nodes[j]));
+            matrix[i][j] = sim;
+            matrix[j][i] = sim;
+        }
+    }
+    Ok(matrix)
+}
+
+pub fn write_matrix_csv(path: &str, labels: &[usize], matrix: &[Vec<f64>], format: &NumberFormat) -> Result<(), GraphError> { //Writes a labeled square matrix to a CSV file: header row of node ids, then one row per node starting with its id
+    let mut writer = csv::Writer::from_path(path)?;
+    let mut header = vec![String::from("node")];
+    header.extend(labels.iter().map(|l| l.to_string()));
+    writer.write_record(&header)?;
+    for (label, row) in labels.iter().zip(matrix) {
+        let mut record = vec![label.to_string()];
+        record.extend(row.iter().map(|&x| format.format(MetricFamily::Similarity, x)));
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Role { //Interpretable label for the structural position of a node
+    Hub,
+    Broker,
+    Peripheral,
+    Ordinary,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Hub => "hub",
+            Role::Broker => "broker",
+            Role::Peripheral => "peripheral",
+            Role::Ordinary => "ordinary",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RoleOptions<'a> { //Thresholds for classify_roles, plus optional precomputed metrics so they are not computed twice
+    pub hub_percentile: f64,        //Nodes with degree at or above this percentile are hubs (0.9 = top decile)
+    pub broker_percentile: f64,     //Non-hub nodes with betweenness at or above this percentile are brokers
+    pub peripheral_max_degree: usize, //Nodes with at most this many friends are peripheral
+    pub degrees: Option<&'a [(usize, usize)]>,
+    pub betweenness: Option<&'a [(usize, f64)]>,
+}
+
+impl Default for RoleOptions<'_> {
+    fn default() -> Self {
+        Self {
+            hub_percentile: 0.9,
+            broker_percentile: 0.9,
+            peripheral_max_degree: 2,
+            degrees: None,
+            betweenness: None,
+        }
+    }
+}
+
+fn percentile_threshold(mut values: Vec<f64>, percentile: f64) -> f64 { //Returns the value at the given percentile (nearest-rank method) of a list of values
+    if values.is_empty() {
+        return f64::INFINITY;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let rank = ((percentile * values.len() as f64).ceil() as usize).clamp(1, values.len());
+    values[rank - 1]
+}
+
+pub fn classify_roles(graph: &Graph, options: &RoleOptions) -> HashMap<usize, Role> { //Labels every node as hub, broker, peripheral or ordinary (checked in that order)
+    let computed_degrees;
+    let degrees = match options.degrees {
+        Some(d) => d,
+        None => {
+            computed_degrees = graph.all_degrees();
+            &computed_degrees
+        }
+    };
+    let computed_betweenness;
+    let betweenness = match options.betweenness {
+        Some(b) => b,
+        None => {
+            computed_betweenness = betweenness_centrality(graph);
+            &computed_betweenness
+        }
+    };
+
+    let hub_cutoff = percentile_threshold(degrees.iter().map(|&(_, d)| d as f64).collect(), options.hub_percentile);
+    let broker_cutoff = percentile_threshold(betweenness.iter().map(|&(_, b)| b).collect(), options.broker_percentile);
+    let betweenness_of: HashMap<usize, f64> = betweenness.iter().copied().collect();
+
+    degrees
+        .iter()
+        .map(|&(node, degree)| {
+            let between = betweenness_of.get(&node).copied().unwrap_or(0.0);
+            let role = if degree > 0 && degree as f64 >= hub_cutoff {
+                Role::Hub
+            } else if between > 0.0 && between >= broker_cutoff {
+                Role::Broker
+            } else if degree <= options.peripheral_max_degree {
+                Role::Peripheral
+            } else {
+                Role::Ordinary
+            };
+            (node, role)
+        })
+        .collect()
+}
+
+pub fn neighborhood_diversity(graph: &Graph, partition: &Partition) -> HashMap<usize, usize> { //Counts how many different communities each node's friends belong to (a simple bridging indicator)
+    //Isolated nodes are skipped, and friends missing from the partition are ignored
+    graph
+        .adjacency()
+        .iter()
+        .filter(|(_, neighbors)| !neighbors.is_empty())
+        .map(|(&node, neighbors)| {
+            let communities: HashSet<usize> = neighbors.iter().filter_map(|n| partition.get(n).copied()).collect();
+            (node, communities.len())
+        })
+        .collect()
+}
+
+pub fn neighborhood_entropy(graph: &Graph, partition: &Partition) -> HashMap<usize, f64> { //Normalized Shannon entropy of the communities among each node's friends
+    //0.0 means all friends are in one community, 1.0 means every friend is in a different community (entropy divided by ln(degree))
+    graph
+        .adjacency()
+        .iter()
+        .filter(|(_, neighbors)| !neighbors.is_empty())
+        .map(|(&node, neighbors)| {
+            let mut counts: HashMap<usize, usize> = HashMap::new();
+            for community in neighbors.iter().filter_map(|n| partition.get(n)) {
+                *counts.entry(*community).or_insert(0) += 1;
+            }
+            let total: usize = counts.values().sum();
+            if total <= 1 {
+                return (node, 0.0);
+            }
+            let entropy: f64 = counts
+                .values()
+                .map(|&c| {
+                    let p = c as f64 / total as f64;
+                    -p * p.ln()
+                })
+                .sum();
+            (node, entropy / (total as f64).ln())
+        })
+        .collect()
+}
+
+pub fn degree_assortativity(graph: &Graph) -> f64 { //Newman's degree assortativity: do people with many friends tend to be friends with people with many friends? (NaN if there are no edges or every edge joins equal degrees)
+    let (mut sum_product, mut sum_half, mut sum_half_squares, mut m) = (0.0, 0.0, 0.0, 0.0);
+    for (&u, neighbors) in graph.adjacency() {
+        for &v in neighbors.iter().filter(|&&v| u < v) {
+            let (j, k) = (neighbors.len() as f64, graph.adjacency()[&v].len() as f64);
+            sum_product += j * k;
+            sum_half += 0.5 * (j + k);
+            sum_half_squares += 0.5 * (j * j + k * k);
+            m += 1.0;
+        }
+    }
+    let mean_sq = (sum_half / m).powi(2);
+    (sum_product / m - mean_sq) / (sum_half_squares / m - mean_sq)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssortativityReport {
+    pub coefficient: f64,     //Pearson correlation across edge endpoints, NaN when the metric does not vary over the edges used
+    pub edges_used: usize,
+    pub edges_skipped: usize, //Edges where at least one endpoint has no value
+}
+
+pub fn numeric_assortativity(graph: &Graph, values: &HashMap<usize, f64>) -> f64 { //Do nodes with similar values of a metric (closeness, PageRank, core number...) tend to be connected?
+    numeric_assortativity_report(graph, values).coefficient
+}
+
+pub fn numeric_assortativity_report(graph: &Graph, values: &HashMap<usize, f64>) -> AssortativityReport { //Same as numeric_assortativity, also reporting how many edges were used and skipped
+    //Each undirected edge is visited once and contributes both orderings (x_u, x_v) and (x_v, x_u), so the result does not depend on edge direction
+    let (mut n, mut sum, mut sum_sq, mut sum_product) = (0.0, 0.0, 0.0, 0.0);
+    let (mut edges_used, mut edges_skipped) = (0, 0);
+    for (&u, neighbors) in graph.adjacency() {
+        for &v in neighbors.iter().filter(|&&v| u < v) {
+            let (Some(&x), Some(&y)) = (values.get(&u), values.get(&v)) else {
+                edges_skipped += 1;
+                continue;
+            };
+            edges_used += 1;
+            n += 2.0;
+            sum += x + y;
+            sum_sq += x * x + y * y;
+            sum_product += 2.0 * x * y;
+        }
+    }
+    let mean = sum / n;
+    let variance = sum_sq / n - mean * mean;
+    let coefficient = if edges_used == 0 || variance.abs() < 1e-12 { f64::NAN } else { (sum_product / n - mean * mean) / variance };
+    AssortativityReport { coefficient, edges_used, edges_skipped }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlapEval { //Agreement between a predicted cover (possibly overlapping communities) and a ground-truth cover
+    pub f1: f64,                //Average of the two directional scores below
+    pub f1_pred_to_truth: f64,  //Mean over predicted communities of the best F1 against any true community
+    pub f1_truth_to_pred: f64,  //Mean over true communities of the best F1 against any predicted community
+    pub omega: f64,             //Omega index: pair agreement on how many communities two nodes share, corrected for chance
+}
+
+pub fn evaluate_overlapping(pred: &[HashSet<usize>], truth: &[HashSet<usize>]) -> OverlapEval { //Compares two covers; empty communities are ignored and nodes present in only one cover count as shared with nobody in the other
+    let pred: Vec<&HashSet<usize>> = pred.iter().filter(|c| !c.is_empty()).collect();
+    let truth: Vec<&HashSet<usize>> = truth.iter().filter(|c| !c.is_empty()).collect();
+    let f1_pred_to_truth = average_best_f1(&pred, &truth);
+    let f1_truth_to_pred = average_best_f1(&truth, &pred);
+
+    //Omega over every pair of nodes appearing in either cover. Only pairs that share a community somewhere are stored,
+    //the remaining pairs share zero communities in both covers
+    let universe: HashSet<usize> = pred.iter().chain(truth.iter()).flat_map(|c| c.iter().copied()).collect();
+    let total_pairs = (universe.len() * universe.len().saturating_sub(1) / 2) as f64;
+    let omega = if total_pairs == 0.0 {
+        1.0
+    } else {
+        let (pred_shared, truth_shared) = (shared_community_counts(&pred), shared_community_counts(&truth));
+        let mut disagreements = 0;
+        for (pair, &count) in &pred_shared {
+            if truth_shared.get(pair).copied().unwrap_or(0) != count {
+                disagreements += 1;
+            }
+        }
+        disagreements += truth_shared.keys().filter(|pair| !pred_shared.contains_key(pair)).count();
+        let observed = 1.0 - disagreements as f64 / total_pairs;
+
+        let (pred_hist, truth_hist) = (shared_histogram(&pred_shared, total_pairs), shared_histogram(&truth_shared, total_pairs));
+        let expected: f64 = pred_hist.iter().map(|(j, n)| n * truth_hist.get(j).copied().unwrap_or(0.0)).sum::<f64>() / (total_pairs * total_pairs);
+        if (1.0 - expected).abs() < 1e-12 { 1.0 } else { (observed - expected) / (1.0 - expected) }
+    };
+
+    OverlapEval { f1: (f1_pred_to_truth + f1_truth_to_pred) / 2.0, f1_pred_to_truth, f1_truth_to_pred, omega }
+}
+
+fn average_best_f1(from: &[&HashSet<usize>], to: &[&HashSet<usize>]) -> f64 { //For each community in `from`, the best F1 = 2|A∩B| / (|A|+|B|) against `to`, averaged (0.0 when either side is empty)
+    if from.is_empty() || to.is_empty() {
+        return 0.0;
+    }
+    let best = |a: &HashSet<usize>| {
+        to.iter()
+            .map(|b| 2.0 * a.intersection(b).count() as f64 / (a.len() + b.len()) as f64)
+            .fold(0.0, f64::max)
+    };
+    from.iter().map(|a| best(a)).sum::<f64>() / from.len() as f64
+}
+
+fn shared_community_counts(cover: &[&HashSet<usize>]) -> HashMap<(usize, usize), usize> { //For every pair (u < v) in at least one common community, how many communities contain both
+    let mut counts = HashMap::new();
+    for community in cover {
+        let mut members: Vec<usize> = community.iter().copied().collect();
+        members.sort();
+        for (i, &u) in members.iter().enumerate() {
+            for &v in &members[i + 1..] {
+                *counts.entry((u, v)).or_insert(0) += 1;
+            }
+        }
+    }
+    counts
+}
+
+fn shared_histogram(counts: &HashMap<(usize, usize), usize>, total_pairs: f64) -> HashMap<usize, f64> { //Number of pairs sharing exactly j communities, for every j including 0
+    let mut histogram: HashMap<usize, f64> = HashMap::new();
+    for &count in counts.values() {
+        *histogram.entry(count).or_insert(0.0) += 1.0;
+    }
+    histogram.insert(0, total_pairs - counts.len() as f64);
+    histogram
+}
+
+pub fn normalized_mutual_information(a: &Partition, b: &Partition) -> f64 { //NMI = 2 I(A;B) / (H(A) + H(B)) over the nodes both partitions assign: 1.0 for identical groupings (up to renaming), near 0.0 for unrelated ones
+    let shared: Vec<usize> = a.keys().copied().filter(|n| b.contains_key(n)).collect();
+    let n = shared.len() as f64;
+    let mut joint: HashMap<(usize, usize), f64> = HashMap::new();
+    let (mut size_a, mut size_b): (HashMap<usize, f64>, HashMap<usize, f64>) = (HashMap::new(), HashMap::new());
+    for node in &shared {
+        *joint.entry((a[node], b[node])).or_insert(0.0) += 1.0;
+        *size_a.entry(a[node]).or_insert(0.0) += 1.0;
+        *size_b.entry(b[node]).or_insert(0.0) += 1.0;
+    }
+    let entropy = |sizes: &HashMap<usize, f64>| -sizes.values().map(|&s| s / n * (s / n).ln()).sum::<f64>();
+    let (h_a, h_b) = (entropy(&size_a), entropy(&size_b));
+    if h_a + h_b == 0.0 {
+        return 1.0; //Both put everyone together (or nothing is shared): identical groupings
+    }
+    let mutual: f64 = joint.iter().map(|(&(ca, cb), &count)| count / n * (count * n / (size_a[&ca] * size_b[&cb])).ln()).sum();
+    (2.0 * mutual / (h_a + h_b)).clamp(0.0, 1.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommunityAlgorithm { //Seeded community detection methods that partition_stability can rerun
+    LabelPropagation,
+    Louvain, //Standard resolution 1.0, nodes visited in a seeded order
+}
+
+impl CommunityAlgorithm {
+    pub fn run(&self, graph: &Graph, seed: u64) -> Partition {
+        match self {
+            CommunityAlgorithm::LabelPropagation => crate::community::label_propagation(graph, seed),
+            CommunityAlgorithm::Louvain => crate::community::louvain_seeded(graph, 1.0, seed).0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StabilityReport {
+    pub runs: usize,
+    pub mean_nmi: f64, //Over every pair of runs (1.0 with fewer than two runs)
+    pub min_nmi: f64,
+    pub consensus: Partition, //Nodes joined when their friendship fell inside one community in at least half of the runs
+}
+
+pub fn partition_stability(graph: &Graph, algorithm: CommunityAlgorithm, num_runs: usize, seed: u64, progress: &dyn Fn(usize, usize)) -> StabilityReport { //Runs the algorithm num_runs times (seeds seed, seed + 1, ...) and measures how much the partitions agree. progress gets (runs done, num_runs)
+    let mut partitions = Vec::with_capacity(num_runs);
+    for run in 0..num_runs {
+        partitions.push(algorithm.run(graph, seed.wrapping_add(run as u64)));
+        progress(run + 1, num_runs);
+    }
+    let mut scores = Vec::new();
+    for (i, a) in partitions.iter().enumerate() {
+        for b in &partitions[i + 1..] {
+            scores.push(normalized_mutual_information(a, b));
+        }
+    }
+    let (mean_nmi, min_nmi) = if scores.is_empty() {
+        (1.0, 1.0)
+    } else {
+        (scores.iter().sum::<f64>() / scores.len() as f64, scores.iter().copied().fold(f64::INFINITY, f64::min))
+    };
+
+    //Consensus: the co-assignment matrix is only read on the graph's edges (two strangers are never linked directly anyway),
+    //and the components of the edges co-assigned in at least half of the runs become the consensus communities
+    let mut parent: HashMap<usize, usize> = HashMap::new();
+    for (&u, friends) in graph.adjacency() {
+        find_root(&mut parent, u);
+        for &v in friends.iter().filter(|&&v| u < v) {
+            let together = partitions.iter().filter(|p| p.get(&u) == p.get(&v)).count();
+            if num_runs > 0 && 2 * together >= num_runs {
+                let (ru, rv) = (find_root(&mut parent, u), find_root(&mut parent, v));
+                parent.insert(ru.max(rv), ru.min(rv));
+            }
+        }
+    }
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    let consensus = nodes
+        .iter()
+        .map(|&node| {
+            let root = find_root(&mut parent, node);
+            let next = ids.len();
+            (node, *ids.entry(root).or_insert(next))
+        })
+        .collect();
+    StabilityReport { runs: num_runs, mean_nmi, min_nmi, consensus }
+}
+
+//TESTS for the algorithms in analysis.rs
+#[cfg(test)] //To include our tests in cargo test
+mod tests { //Submodule to put our tests in
+    use super::*;
+    use crate::generate::two_cliques_with_bridge;
+    use crate::graph::Graph;
+    use std::collections::HashSet;
+    fn small_graph() -> Graph { //Creates a simple triangle graph to test our algorithms on
+        Graph::from_edges([(0, 1), (1, 2), (2, 0)])
+    }
+
+    fn triangle_plus_pendant() -> Graph { //Triangle 0-1-2 with a pendant node 3 hanging off node 2
+        Graph::from_edges([(0, 1), (1, 2), (2, 0), (2, 3)])
+    }
+
+    fn cliques_with_connector() -> Graph { //Cliques {0,1,2,3} and {4,5,6,7}, with connector 8 linked to 3 and 4 so that 3 and 4 share a neighbor
+        let mut edges = vec![(3, 8), (8, 4)];
+        for clique in [[0, 1, 2, 3], [4, 5, 6, 7]] {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    edges.push((clique[i], clique[j]));
+                }
+            }
+        }
+        Graph::from_edges(&edges)
+    }
+
+    fn barbell_graph() -> Graph { //Two 4-cliques {0,1,2,3} and {7,8,9,10} joined by the path 3-4-5-6-7, with pendants 11 (on 0) and 12 (on 10)
+        let mut edges = vec![(3, 4), (4, 5), (5, 6), (6, 7), (0, 11), (10, 12)];
+        for clique in [[0, 1, 2, 3], [7, 8, 9, 10]] {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    edges.push((clique[i], clique[j]));
+                }
+            }
+        }
+        Graph::from_edges(&edges)
+    }
+
+    #[test]
+    fn test_bfs_distances() { //Verifies that BFS correctly computes the shortest distances from node 0.
+        let graph = small_graph();
+        let distances = bfs_distances(&graph, InternalId(0));
+        assert_eq!(distances.get(&0), Some(&0));
+        assert_eq!(distances.get(&1), Some(&1));
+        assert_eq!(distances.get(&2), Some(&1));
+    }
+
+    #[test]
+    fn test_bfs_path() { //Every path is as long as the BFS distance, runs from start to end and only steps between friends. On a 4-cycle the lower-id route is taken
+        let graph = crate::generate::barabasi_albert(200, 2, 4).unwrap();
+        let distances = bfs_distances(&graph, InternalId(0));
+        let predecessors = bfs_predecessors(&graph, InternalId(0));
+        for (&end, &distance) in &distances {
+            let path = bfs_path(&graph, InternalId(0), InternalId(end)).unwrap();
+            assert_eq!(path.len(), distance + 1);
+            assert_eq!((path[0], path[path.len() - 1]), (0, end));
+            assert!(path.windows(2).all(|step| graph.has_edge(step[0], step[1])));
+            assert_eq!(path_from_predecessors(&predecessors, 0, end), Some(path));
+        }
+        assert_eq!(bfs_path(&graph, InternalId(7), InternalId(7)), Some(vec![7]));
+        assert_eq!(bfs_path(&graph, InternalId(0), InternalId(999)), None);
+
+        let mut cycle = Graph::new();
+        for (u, v) in [(0, 3), (3, 2), (2, 1), (1, 0), (10, 11)] {
+            cycle.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(bfs_path(&cycle, InternalId(0), InternalId(2)), Some(vec![0, 1, 2]));
+        assert_eq!(bfs_path(&cycle, InternalId(0), InternalId(10)), None);
+    }
+
+    #[test]
+    fn test_average_distance() { //Checks that the average distance computed is accurate for a fully connected triangle graph
+        let graph = small_graph();
+        let avg_dist = average_distance(&graph);
+        assert!((avg_dist - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_degrees_of_separation_table() { //Path 0-1-2-3-4: of the 10 connected pairs 4 are 1 hop apart, 3 are 2, 2 are 3 and 1 is 4. The sampled table closes in on the exact one
+        let path = Graph::from_edges(&[(0, 1), (1, 2), (2, 3), (3, 4)]);
+        let table = degrees_of_separation_table(&path, 5, None, 0);
+        assert_eq!(table, vec![(1, 0.4), (2, 0.7), (3, 0.9), (4, 1.0), (5, 1.0)]);
+        assert_eq!(degrees_of_separation_table(&path, 5, Some(10), 0), table); //More sources than nodes is exact
+        assert_eq!(separation_table(distance_distribution(&path), 5), table);
+        assert_eq!((separation_threshold(&table, 0.9), separation_threshold(&table, 0.99)), (Some(3), Some(4)));
+        assert_eq!(separation_threshold(&table[..2], 0.9), None);
+
+        let graph = crate::generate::watts_strogatz(600, 6, 0.1, 2).unwrap();
+        let exact = degrees_of_separation_table(&graph, 10, None, 0);
+        let error = |sources: usize| degrees_of_separation_table(&graph, 10, Some(sources), 7).iter().zip(&exact).map(|(a, b)| (a.1 - b.1).abs()).fold(0.0, f64::max);
+        assert!(error(300) < 0.02, "300 sources: {}", error(300));
+        assert!(error(300) <= error(10));
+        assert_eq!(degrees_of_separation_table(&graph, 10, Some(50), 7), degrees_of_separation_table(&graph, 10, Some(50), 7));
+    }
+
+    struct Hubs { //Mock graph: `count` nodes each claiming `degree` friends, far more than could ever be stored
+        count: usize,
+        degree: usize,
+    }
+
+    impl GraphRead for Hubs {
+        fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+            Box::new(0..self.count)
+        }
+        fn neighbors(&self, _node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+            Box::new(std::iter::empty())
+        }
+        fn contains_node(&self, node: usize) -> bool {
+            node < self.count
+        }
+        fn has_edge(&self, _u: usize, _v: usize) -> bool {
+            false
+        }
+        fn degree(&self, _node: usize) -> usize {
+            self.degree
+        }
+    }
+
+    #[test]
+    fn test_counter_overflow_is_an_error() { //Two u32::MAX hubs still fit (2^64 - 3 * 2^32 + 2 wedges), a third overflows; huge component sizes keep an exact fraction
+        let big = u32::MAX as usize;
+        assert_eq!(wedge_count(&Hubs { count: 2, degree: big }).unwrap(), big * (big - 1));
+        assert!(matches!(wedge_count(&Hubs { count: 3, degree: big }), Err(GraphError::Overflow { what: "wedge count" })));
+        assert!(matches!(wedge_count(&Hubs { count: 1, degree: usize::MAX }), Err(GraphError::Overflow { .. }))); //A single d(d - 1)/2 too large
+        assert_eq!(wedge_count(&small_graph()).unwrap(), 3);
+        assert_eq!(GraphError::Overflow { what: "wedge count" }.exit_code(), 8);
+
+        let fraction = pair_fraction(&[5_000_000_000, 5_000_000_000]); //n(n - 1) = 10^20 does not fit in u64
+        assert!((fraction - 0.5).abs() < 1e-9, "{}", fraction);
+        assert_eq!(histogram_mean(&[0, usize::MAX, usize::MAX]), 1.5);
+    }
+
+    #[test]
+    fn test_average_distance_sampled() { //Triangle: exact whether sampled or not. A larger graph: same seed, same estimate, within a few standard errors of the truth
+        let triangle = small_graph();
+        assert_eq!(average_distance_sampled(&triangle, 3, 1), ApproxDistance { mean: 1.0, std_err: 0.0, samples: 3 });
+        assert_eq!(average_distance_sampled(&triangle, 2, 1), ApproxDistance { mean: 1.0, std_err: 0.0, samples: 2 });
+
+        let graph = crate::generate::barabasi_albert(400, 2, 3).unwrap();
+        let estimate = average_distance_sampled(&graph, 40, 7);
+        assert_eq!(estimate, average_distance_sampled(&graph, 40, 7));
+        assert_eq!(estimate.samples, 40);
+        assert!(estimate.std_err > 0.0 && (estimate.mean - average_distance(&graph)).abs() < 4.0 * estimate.std_err);
+    }
+
+    #[test]
+    fn test_degree_histogram() { //Star with hub 0 and leaves 1-5: five nodes of degree 1, one of degree 5
+        let mut graph = Graph::new();
+        for leaf in 1..=5 {
+            graph.add_edge(InternalId(0), InternalId(leaf));
+        }
+        graph.add_node(InternalId(9));
+        assert_eq!(degree_histogram(&graph), vec![(0, 1), (1, 5), (5, 1)]);
+
+        //Three bins over degrees 1-5 with edges 1, 6^(1/3), 6^(2/3), 6: {1} holds the leaves, {2, 3} is empty and skipped, {4, 5} holds the hub
+        let binned = degree_histogram_log_binned(&graph, 3);
+        assert_eq!(binned.len(), 2);
+        assert!((binned[0].0 - 6f64.powf(1.0 / 6.0)).abs() < 1e-9 && (binned[1].0 - 6f64.powf(5.0 / 6.0)).abs() < 1e-9);
+        assert!((binned[0].1 - 5.0 / 6.0).abs() < 1e-12 && (binned[1].1 - 1.0 / 12.0).abs() < 1e-12);
+        assert!(degree_histogram_log_binned(&graph, 0).is_empty() && degree_histogram_log_binned(&Graph::new(), 4).is_empty());
+        let many = degree_histogram_log_binned(&graph, 50); //Bins narrower than one degree hold it whole, so nothing is counted twice
+        assert_eq!(many.len(), 2);
+    }
+
+    #[test]
+    fn test_stratified_estimates_are_reweighted() { //60 triangles, 30 squares and 4 stars of 30 leaves: the 4 hubs hold most wedges, so small uniform samples (a single bucket) that miss them overestimate transitivity several times over on average. Reweighted degree buckets always catch them and stay on the truth
+        use crate::graph::sampling::{stratified_by_degree, Allocation};
+        let mut graph = Graph::new();
+        for t in 0..60 {
+            for (u, v) in [(0, 1), (1, 2), (2, 0)] {
+                graph.add_edge(InternalId(3 * t + u), InternalId(3 * t + v));
+            }
+        }
+        for q in 0..30 {
+            for (u, v) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+                graph.add_edge(InternalId(1000 + 4 * q + u), InternalId(1000 + 4 * q + v));
+            }
+        }
+        for hub in 0..4 {
+            for leaf in 1..=30 {
+                graph.add_edge(InternalId(2000 + 100 * hub), InternalId(2000 + 100 * hub + leaf));
+            }
+        }
+        let (true_distance, true_transitivity) = (average_distance(&graph), transitivity(&graph).unwrap());
+        let full = stratified_by_degree(&graph, graph.num_nodes(), 4, Allocation::Equal, 1);
+        assert_eq!(average_distance_stratified(&graph, &full), ApproxDistance { mean: true_distance, std_err: 0.0, samples: graph.num_nodes() });
+        assert!((transitivity_stratified(&graph, &full).estimate - true_transitivity).abs() < 1e-12);
+
+        let runs = 300;
+        let (mut distance, mut clustering, mut uniform_clustering) = (0.0, 0.0, 0.0);
+        for seed in 0..runs {
+            let stratified = stratified_by_degree(&graph, 30, 4, Allocation::Equal, seed); //Quartiles fall inside the ties, leaving buckets of degree 1, 2 and 30
+            let uniform = stratified_by_degree(&graph, 30, 1, Allocation::Equal, seed);
+            distance += average_distance_stratified(&graph, &stratified).mean / runs as f64;
+            clustering += transitivity_stratified(&graph, &stratified).estimate / runs as f64;
+            uniform_clustering += transitivity_stratified(&graph, &uniform).estimate / runs as f64;
+        }
+        assert!((distance - true_distance).abs() < 0.01 * true_distance);
+        assert!((clustering - true_transitivity).abs() < 0.05 * true_transitivity);
+        assert!(uniform_clustering > 2.0 * true_transitivity);
+    }
+
+    #[test]
+    fn test_closeness_centrality() { //Ensures closeness centrality returns 1.0 for all nodes in our closed trianlge
+        let graph = small_graph();
+        let closeness = closeness_centrality(&graph);
+        for &(_, centrality) in &closeness {
+            assert!((centrality - 1.0).abs() < 0.0001);
+        }
+    }
+
+    #[test]
+    fn test_median_and_trimmed_distance() { //Star 0 with leaves 1-3 and a tail 0-4-5-6: unordered pairs at distance 1..4 number 6, 8, 4, 3
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (0, 2), (0, 3), (0, 4), (4, 5), (5, 6)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(distance_histogram(&graph), vec![0, 12, 16, 8, 6]); //Ordered pairs, so twice the unordered counts
+        assert!((average_distance(&graph) - 46.0 / 21.0).abs() < 1e-9);
+        assert_eq!(median_distance(&graph), 2.0);
+        assert!((trimmed_average_distance(&graph, 0.0).unwrap() - 46.0 / 21.0).abs() < 1e-9);
+        //42 ordered pairs, 10 dropped from each end: 2 at distance 1, 16 at 2 and 4 at 3 remain
+        assert!((trimmed_average_distance(&graph, 0.25).unwrap() - 46.0 / 22.0).abs() < 1e-9);
+        assert!(matches!(trimmed_average_distance(&graph, 0.5), Err(GraphError::InvalidParameter(_))));
+        assert_eq!(histogram_median(&[0, 1, 0, 1]), 2.0); //Even count: mean of the two middle values
+    }
+
+    fn triangle_with_isolated() -> Graph { //Triangle plus pendant (nodes 0-3) and two people with no friends (4 and 5)
+        let mut graph = triangle_plus_pendant();
+        graph.add_node(InternalId(4));
+        graph.add_node(InternalId(5));
+        graph
+    }
+
+    #[test]
+    fn test_isolated_node_modes() { //Zero keeps them at 0.0, sentinel marks them and sorts them last, exclude drops them and reports the count
+        let graph = triangle_with_isolated();
+        for scores in [closeness_centrality(&graph), harmonic_centrality(&graph)] {
+            let (zero, excluded) = apply_isolated(&graph, scores.clone(), IsolatedNodes::IncludeAsZero);
+            assert_eq!(excluded, 0);
+            assert_eq!(&zero[4..], &[(4, 0.0), (5, 0.0)]);
+            let (sentinel, excluded) = apply_isolated(&graph, scores.clone(), IsolatedNodes::Sentinel);
+            assert_eq!(excluded, 0);
+            assert_eq!(&sentinel[4..], &[(4, ISOLATED_SENTINEL), (5, ISOLATED_SENTINEL)]);
+            let (kept, excluded) = apply_isolated(&graph, scores, IsolatedNodes::Exclude);
+            assert_eq!(excluded, 2);
+            assert_eq!(kept, &zero[..4]);
+        }
+
+        let closeness = closeness_centrality(&graph);
+        let zero = percentile_ranks(&graph, &closeness, IsolatedNodes::IncludeAsZero);
+        assert_eq!((zero[&4], zero[&2]), (2.0 / 6.0, 1.0));
+        let sentinel = percentile_ranks(&graph, &closeness, IsolatedNodes::Sentinel);
+        assert_eq!((sentinel[&4], sentinel[&3]), (ISOLATED_SENTINEL, 0.25));
+        let excluded = percentile_ranks(&graph, &closeness, IsolatedNodes::Exclude);
+        assert_eq!((excluded.len(), excluded[&3]), (4, 0.25));
+    }
+
+    #[test]
+    fn test_jaccard_similarity() { //Validates Jaccard similarity between two nodes with shared neighbors in the triangle.
+        let graph = small_graph();
+        let sim = jaccard_similarity(&graph, InternalId(0), InternalId(1));
+        assert!((sim - (1.0 / 3.0)).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_friends_of_friends_similarity() { //0 and 4 share no friends but both reach 2 in two hops; 0 also reaches 6, so the two-hop overlap is 1/2
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (4, 3), (3, 2), (0, 5), (5, 6)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(jaccard_similarity(&graph, InternalId(0), InternalId(4)), 0.0);
+        assert!((friends_of_friends_similarity(&graph, InternalId(0), InternalId(4)) - 0.5).abs() < 1e-9);
+        assert_eq!(friends_of_friends_similarity(&graph, InternalId(0), InternalId(99)), 0.0);
+        assert_eq!(bfs_distances_within(&graph, InternalId(0), 2).len(), 5); //0, its friends 1 and 5, then 2 and 6
+    }
+
+    #[test]
+    fn test_similarity_matrix() { //Checks symmetry, the 1.0 diagonal and a couple of exact Jaccard entries on the triangle-plus-pendant graph
+        let graph = triangle_plus_pendant();
+        let nodes = [0, 1, 2, 3];
+        let matrix = similarity_matrix(&graph, &nodes, SimilarityMetric::Jaccard).unwrap();
+        for (i, row) in matrix.iter().enumerate() {
+            assert_eq!(row[i], 1.0);
+            for (j, &value) in row.iter().enumerate() {
+                assert_eq!(value, matrix[j][i]);
+            }
+        }
+        assert!((matrix[0][1] - 1.0 / 3.0).abs() < 0.0001); //N(0)={1,2}, N(1)={0,2}
+        assert!((matrix[0][3] - 0.5).abs() < 0.0001); //N(0)={1,2}, N(3)={2}
+        assert_eq!(matrix[2][3], 0.0); //Node 2 and its pendant share no friends
+    }
+
+    #[test]
+    fn test_similarity_matrix_unknown_ids() { //Unknown ids should be reported back in the error
+        let graph = triangle_plus_pendant();
+        let err = similarity_matrix(&graph, &[0, 7, 9], SimilarityMetric::Jaccard).unwrap_err();
+        assert!(matches!(err, GraphError::UnknownNodes(ref ids) if ids == &vec![7, 9]));
+        let too_many: Vec<usize> = (0..=MAX_MATRIX_NODES).collect();
+        assert!(matches!(similarity_matrix(&graph, &too_many, SimilarityMetric::Jaccard), Err(GraphError::BudgetExceeded { .. })));
+        let needed = graph.estimate_analysis_memory(AnalysisKind::SimilarityMatrix(3));
+        assert_eq!(needed, 9 * 8);
+        let tight = MemoryBudget::bytes(needed - 1);
+        assert!(matches!(similarity_matrix_with(&graph, &[0, 1, 2], SimilarityMetric::Jaccard, &tight), Err(GraphError::BudgetExceeded { needed: 72, budget: 71 })));
+        assert!(similarity_matrix_with(&graph, &[0, 1, 2], SimilarityMetric::Jaccard, &MemoryBudget::bytes(needed)).is_ok());
+    }
+
+    #[test]
+    fn test_write_matrix_csv() { //Writes a small matrix and reads the file back
+        let path = std::env::temp_dir().join(format!("fga_test_matrix_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_matrix_csv(path, &[0, 3], &[vec![1.0, 0.5], vec![0.5, 1.0]], &NumberFormat::with_precision(1)).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert_eq!(contents, "node,0,3\n0,1.0,0.5\n3,0.5,1.0\n");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_betweenness_centrality() { //Middle of the barbell path lies on 6 * 6 = 36 shortest paths, triangle nodes lie on none
+        let graph = barbell_graph();
+        let betweenness: HashMap<usize, f64> = betweenness_centrality(&graph).into_iter().collect();
+        assert!((betweenness[&5] - 36.0).abs() < 0.0001);
+        assert!((betweenness[&4] - 35.0).abs() < 0.0001);
+        assert_eq!(betweenness[&1], 0.0);
+        let triangle: HashMap<usize, f64> = betweenness_centrality(&small_graph()).into_iter().collect();
+        assert!(triangle.values().all(|&b| b == 0.0));
+
+        assert_eq!(betweenness_centrality_sampled(&graph, graph.num_nodes(), 1), betweenness_centrality(&graph)); //Every node a source: exact
+        let sampled: HashMap<usize, f64> = betweenness_centrality_sampled(&graph, 6, 1).into_iter().collect(); //Half the sources, scaled back up: the bridge still carries about 36 paths
+        assert!((sampled[&5] - 36.0).abs() < 12.0 && sampled[&1] == 0.0);
+    }
+
+    #[test]
+    fn test_top_k_closeness_matches_full_ranking() { //Same rows as the full ranking for several k on a random graph with stray components, while visiting fewer nodes than n BFS runs
+        let graph = crate::generate::erdos_renyi(300, 0.012, 8).unwrap();
+        let full = closeness_centrality(&graph);
+        let full_visits: usize = connected_components(&graph).iter().map(|c| c.len() * c.len()).sum();
+        for k in [1, 5, 20, 300, 1000] {
+            let (top, visited) = pruned_top_k_closeness(&graph, k);
+            assert_eq!(top, full[..k.min(full.len())]);
+            if k <= 20 {
+                assert!(visited < full_visits, "k = {}: {} visits, {} for the full ranking", k, visited, full_visits);
+            }
+        }
+        assert!(top_k_closeness(&graph, 0).is_empty() && top_k_closeness(&Graph::new(), 5).is_empty());
+    }
+
+    #[test]
+    fn test_edge_embeddedness_and_overlap() { //Triangle 0-1-2 with a pendant 2-3: triangle edges share one friend, the pendant none; keys are u < v and overlap never NaN
+        let graph = Graph::from_edges(&[(1, 0), (1, 2), (2, 0), (3, 2)]);
+        let embeddedness = edge_embeddedness(&graph);
+        assert_eq!(embeddedness, HashMap::from([((0, 1), 1), ((0, 2), 1), ((1, 2), 1), ((2, 3), 0)]));
+        let overlap = edge_overlap(&graph, &embeddedness);
+        assert_eq!(overlap.len(), 4);
+        assert_eq!((overlap[&(0, 1)], overlap[&(0, 2)], overlap[&(2, 3)]), (1.0, 0.5, 0.0)); //0-2: 2 also knows 3, so 1 mutual of 2 others
+        let pair = Graph::from_edges([(0, 1)]);
+        let pair = edge_overlap(&pair, &edge_embeddedness(&pair));
+        assert_eq!(pair, HashMap::from([((0, 1), 0.0)])); //No other friends at all: 0.0, not 0/0
+        assert_eq!(count_triangles(&graph), 1);
+    }
+
+    #[test]
+    fn test_bipartite_check() { //Even cycle: alternating sides; triangle: a 3-node odd cycle as witness; a square next to a triangle: the triangle still spoils it
+        let square = Graph::from_edges(&[(0, 1), (1, 2), (2, 3), (3, 0), (5, 6)]);
+        let sides = is_bipartite(&square).unwrap();
+        assert_eq!(sides.len(), 6);
+        assert!(square.adjacency().iter().all(|(u, friends)| friends.iter().all(|v| sides[u] != sides[v])));
+        assert_eq!((sides[&0], sides[&1], sides[&2], sides[&3]), (false, true, false, true));
+
+        let triangle = Graph::from_edges(&[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(is_bipartite(&triangle), None);
+        let Bipartiteness::OddCycle(cycle) = bipartite_check(&triangle) else { panic!("a triangle is not bipartite") };
+        assert_eq!(cycle.len(), 3);
+        assert!((0..3).all(|i| triangle.has_edge(cycle[i], cycle[(i + 1) % 3])));
+
+        let mixed = Graph::from_edges(&[(0, 1), (1, 2), (2, 3), (3, 0), (10, 11), (11, 12), (12, 10), (12, 13)]);
+        assert_eq!(is_bipartite(&mixed), None);
+        let Bipartiteness::OddCycle(cycle) = bipartite_check(&mixed) else { panic!("the triangle is an odd cycle") };
+        let mut members: Vec<NodeId> = cycle.iter().map(|&n| mixed.original_id(InternalId(n))).collect();
+        members.sort();
+        assert_eq!(members, vec![NodeId(10), NodeId(11), NodeId(12)]);
+        assert_eq!(bipartite_check(&Graph::new()), Bipartiteness::TwoColoring(HashMap::new()));
+    }
+
+    #[test]
+    fn test_personalized_pagerank() { //Barbell of two 5-cliques joined by 4-5: a walk from 0 stays mostly in its own clique; mass sums to 1 and never reaches another component
+        let mut edges = Vec::new();
+        for side in [0, 5] {
+            for a in side..side + 5 {
+                edges.extend((a + 1..side + 5).map(|b| (a, b)));
+            }
+        }
+        edges.extend([(4, 5), (20, 21)]);
+        let graph = Graph::from_edges(&edges);
+        let ppr = personalized_pagerank(&graph, InternalId(0), 0.15, PAGERANK_MAX_ITERATIONS, 1e-12).unwrap();
+        assert!((ppr.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        let own: f64 = (0..5).map(|n| ppr[&n]).sum();
+        assert!(own > 0.75, "only {} stayed in the source's clique", own);
+        assert!((0..5).all(|a| (5..10).all(|b| ppr[&a] > ppr[&b])));
+        assert_eq!(ppr.values().filter(|&&score| score == 0.0).count(), 2); //The separate pair 20-21
+
+        let suggestions = similar_by_ppr(&graph, InternalId(0), 3).unwrap();
+        assert_eq!(suggestions.iter().map(|&(node, _)| node).collect::<Vec<_>>(), vec![5, 6, 7]); //Non-friends only: the bridge end first, then its clique by id
+        assert!(matches!(personalized_pagerank(&graph, InternalId(0), 0.0, 10, 1e-9), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(similar_by_ppr(&graph, InternalId(99), 3), Err(GraphError::UnknownNode(99))));
+    }
+
+    #[test]
+    fn test_edge_betweenness() { //Two triangles joined by 2-3: the bridge carries all 9 cross pairs, 0-2 its own pair plus 0's three paths across
+        let graph = Graph::from_edges(&[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3)]);
+        let scores = edge_betweenness(&graph);
+        assert_eq!(scores.len(), 7);
+        assert_eq!((scores[&(2, 3)], scores[&(0, 2)], scores[&(0, 1)]), (9.0, 4.0, 1.0));
+        let pairs = graph.num_nodes() * (graph.num_nodes() - 1) / 2;
+        let total_hops: usize = graph.nodes().map(|n| bfs_distances(&graph, InternalId(n)).values().sum::<usize>()).sum::<usize>() / 2;
+        assert!((scores.values().sum::<f64>() - total_hops as f64).abs() < 1e-9 && total_hops > pairs); //Every shortest path spreads one unit over each hop
+    }
+
+    #[test]
+    fn test_robustness_profile() { //Star with 49 leaves in 10 steps: taking the hub first leaves only singletons, random failures wear it down a few leaves at a time
+        let star = Graph::from_edges(&(1..50).map(|leaf| (0, leaf)).collect::<Vec<_>>());
+        for strategy in [RemovalStrategy::HighestDegreeFirst, RemovalStrategy::HighestBetweennessFirst] {
+            let attack = robustness_profile(&star, strategy, 10, 1);
+            assert_eq!((attack.len(), attack[0], attack[1]), (11, (0.0, 1.0), (0.1, 1.0 / 50.0)), "{:?}", strategy);
+            assert_eq!(attack[10], (1.0, 0.0));
+        }
+        let failure = robustness_profile(&star, RemovalStrategy::RandomRemoval, 10, 1);
+        assert_eq!(failure.len(), 11);
+        assert!(failure.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 >= pair[1].1));
+        assert!(failure[1].1 >= 0.8); //Seed 1 spares the hub in the first batch
+        assert_eq!(failure, robustness_profile(&star, RemovalStrategy::RandomRemoval, 10, 1));
+        assert_eq!(robustness_profile(&star, RemovalStrategy::RandomRemoval, 0, 1), vec![(0.0, 1.0)]);
+    }
+
+    #[test]
+    fn test_mutual_friends() { //In a triangle 0 and 1 share exactly 2; nodes in different components or unknown share nothing; the pair ranking counts friends in common
+        let triangle = Graph::from_edges(&[(0, 1), (1, 2), (2, 0), (5, 6)]);
+        assert_eq!((mutual_friends(&triangle, InternalId(0), InternalId(1)), mutual_friend_count(&triangle, InternalId(0), InternalId(1))), (vec![2], 1));
+        assert_eq!((mutual_friends(&triangle, InternalId(0), InternalId(5)), mutual_friend_count(&triangle, InternalId(2), InternalId(6))), (vec![], 0));
+        assert_eq!((mutual_friends(&triangle, InternalId(0), InternalId(99)), mutual_friend_count(&triangle, InternalId(99), InternalId(0))), (vec![], 0));
+
+        let graph = crate::generate::erdos_renyi(60, 0.15, 4).unwrap();
+        let top = top_pairs_by_mutual_friends(&graph, 15);
+        let mut every: Vec<((usize, usize), usize)> = Vec::new();
+        for u in 0..60 {
+            for v in u + 1..60 {
+                assert_eq!(mutual_friends(&graph, InternalId(u), InternalId(v)).len(), mutual_friend_count(&graph, InternalId(u), InternalId(v)));
+                every.push(((u, v), mutual_friend_count(&graph, InternalId(u), InternalId(v))));
+            }
+        }
+        every.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        assert_eq!(top, every[..15]);
+        assert_eq!(top_pairs_by_mutual_friends(&triangle, 10), vec![((0, 1), 1), ((0, 2), 1), ((1, 2), 1)]);
+    }
+
+    #[test]
+    fn test_distance_distribution() { //Unordered pairs: a triangle is {1: 3}, a path 0-1-2-3 {1: 3, 2: 2, 3: 1} with a separate edge adding one more 1-hop pair; sampled estimates scale up to every pair
+        let triangle = Graph::from_edges(&[(0, 1), (1, 2), (2, 0)]);
+        assert_eq!(distance_distribution(&triangle), BTreeMap::from([(1, 3)]));
+        let path = Graph::from_edges(&[(0, 1), (1, 2), (2, 3), (8, 9)]);
+        assert_eq!(distance_distribution(&path), BTreeMap::from([(1, 4), (2, 2), (3, 1)]));
+        assert_eq!(distance_distribution_sampled(&path, 50, 1), BTreeMap::from([(1, 4.0), (2, 2.0), (3, 1.0)]));
+        assert!(distance_distribution(&Graph::new()).is_empty());
+
+        let graph = crate::generate::watts_strogatz(200, 6, 0.1, 3).unwrap();
+        let exact = distance_distribution(&graph);
+        assert_eq!(exact.values().sum::<usize>(), 200 * 199 / 2);
+        let total_distance: usize = exact.iter().map(|(d, count)| d * count).sum();
+        assert!((total_distance as f64 / (200 * 199 / 2) as f64 - average_distance(&graph)).abs() < 1e-12);
+        let sampled = distance_distribution_sampled(&graph, 40, 9);
+        assert!((sampled.values().sum::<f64>() - (200 * 199 / 2) as f64).abs() < 1e-6);
+        assert!(sampled.keys().all(|d| exact.contains_key(d)));
+    }
+
+    #[test]
+    fn test_largest_connected_component() { //A 4-node cycle beats a 3-node path; between two triangles the one with the smaller node id (3) wins; an empty graph stays empty
+        let graph = Graph::from_edges(&[(10, 11), (11, 12), (12, 13), (13, 10), (0, 1), (1, 2)]);
+        let (giant, share) = largest_connected_component(&graph);
+        let mut nodes: Vec<usize> = giant.adjacency().keys().copied().collect();
+        nodes.sort();
+        assert_eq!(nodes, vec![10, 11, 12, 13]);
+        assert_eq!(share, ComponentShare { nodes: 4, edges: 4, node_fraction: 4.0 / 7.0, edge_fraction: 4.0 / 6.0 });
+        assert!(giant.validate().is_ok() && giant.has_edge(13, 10));
+
+        let (giant, _) = largest_connected_component(&Graph::from_edges(&[(5, 6), (6, 7), (7, 5), (3, 8), (8, 9), (9, 3)]));
+        assert!(giant.contains_node(3) && !giant.contains_node(5));
+        let (empty, share) = largest_connected_component(&Graph::new());
+        assert_eq!((empty.num_nodes(), share.nodes, share.node_fraction), (0, 0, 0.0));
+    }
+
+    #[test]
+    fn test_least_central_on_lollipop() { //Clique 0-4 with the tail 4-5-6-7-8-9, an isolated node 20 and a lone pair 30-31: the tail end comes first, the strays only when allowed
+        let mut edges: Vec<(usize, usize)> = (0..5).flat_map(|u| (u + 1..5).map(move |v| (u, v))).collect();
+        edges.extend([(4, 5), (5, 6), (6, 7), (7, 8), (8, 9), (30, 31), (20, 20)]);
+        let graph = Graph::from_edges(&edges);
+        let nodes = |ranked: Vec<(usize, f64)>| ranked.into_iter().map(|(node, _)| node).collect::<Vec<_>>();
+        let giant = PeripheryOptions::default();
+        assert_eq!(nodes(least_central(&graph, PeripheryMetric::Closeness, 4, &giant).unwrap()), vec![9, 8, 7, 0]); //Then clique member 0 (distance sum 24), which is farther from the tail than tail node 6 (21)
+        assert_eq!(nodes(least_central(&graph, PeripheryMetric::Degree, 3, &giant).unwrap()), vec![9, 5, 6]);
+        assert_eq!(nodes(least_central(&graph, PeripheryMetric::PageRank, 1, &giant).unwrap()), vec![9]);
+        let closeness = least_central(&graph, PeripheryMetric::Closeness, 1, &giant).unwrap();
+        assert!((closeness[0].1 - closeness_of(&graph, InternalId(9)).unwrap()).abs() < 1e-12);
+        assert_eq!(least_central_among(&graph, &closeness_centrality(&graph), 4, &giant), least_central(&graph, PeripheryMetric::Closeness, 4, &giant).unwrap());
+
+        let pairs_too = PeripheryOptions { giant_component_only: false, min_component_size: 2 };
+        assert_eq!(nodes(least_central(&graph, PeripheryMetric::Degree, 4, &pairs_too).unwrap()), vec![9, 30, 31, 5]);
+        assert!(least_central(&graph, PeripheryMetric::Degree, 20, &PeripheryOptions { giant_component_only: true, min_component_size: 20 }).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_betweenness_centrality_weighted() { //Hub 0 joined to ring 1-2-3-4 by weight-10 edges: by hops the hub is on 1-3 and 2-4 (a third of each), by weight the ring carries everything.
+        //1-3 ties at 0.1 + 0.2 and 0.15 + 0.15 (not equal as floats), so 2 and 4 get half each; 2-4 is shortest through 1 (0.25 against 0.35)
+        let mut graph = WeightedGraph::new();
+        for (u, v, w) in [(0, 1, 10.0), (0, 2, 10.0), (0, 3, 10.0), (0, 4, 10.0), (1, 2, 0.1), (2, 3, 0.2), (3, 4, 0.15), (4, 1, 0.15)] {
+            graph.add_edge(u, v, w);
+        }
+        let by_hops = betweenness_centrality(&Graph::from_edges(&graph.adj_list.iter().flat_map(|(&u, f)| f.keys().map(move |&v| (u, v))).collect::<Vec<_>>()));
+        assert_eq!(by_hops[0].0, 0);
+        assert!((by_hops[0].1 - 2.0 / 3.0).abs() < 1e-12);
+        let weighted = betweenness_centrality_weighted(&graph, false).unwrap();
+        let expected = [(1, 1.0), (2, 0.5), (4, 0.5), (0, 0.0), (3, 0.0)];
+        assert_eq!(weighted.iter().map(|&(node, _)| node).collect::<Vec<_>>(), expected.map(|(node, _)| node));
+        assert!(weighted.iter().zip(expected).all(|(&(_, got), (_, want))| (got - want).abs() < 1e-12));
+        let normalized = betweenness_centrality_weighted(&graph, true).unwrap();
+        assert!((normalized[0].1 - 1.0 / 6.0).abs() < 1e-12); //6 pairs of the other four nodes
+
+        let mut unit = WeightedGraph::new(); //With every weight 1 the weighted version is the unweighted one
+        for (&u, friends) in barbell_graph().adjacency() {
+            for &v in friends {
+                unit.add_edge(u, v, 1.0);
+            }
+        }
+        assert_eq!(betweenness_centrality_weighted(&unit, false).unwrap(), betweenness_centrality(&barbell_graph()));
+        graph.add_edge(3, 5, -1.0);
+        assert!(matches!(betweenness_centrality_weighted(&graph, false), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_classify_roles() { //Clique members are hubs or ordinary, the connecting path is brokers and pendants are peripheral
+        let graph = barbell_graph();
+        let roles = classify_roles(&graph, &RoleOptions::default());
+        for node in [0, 3, 7, 10] {
+            assert_eq!(roles[&node], Role::Hub);
+        }
+        for node in [1, 2, 8, 9] {
+            assert_eq!(roles[&node], Role::Ordinary);
+        }
+        for node in [4, 5, 6] {
+            assert_eq!(roles[&node], Role::Broker);
+        }
+        assert_eq!(roles[&11], Role::Peripheral);
+        assert_eq!(roles[&12], Role::Peripheral);
+    }
+
+    #[test]
+    fn test_classify_roles_reuses_supplied_metrics() { //Supplied degrees/betweenness are used instead of being recomputed
+        let graph = barbell_graph();
+        let degrees = graph.all_degrees();
+        let betweenness: Vec<(usize, f64)> = graph.adjacency().keys().map(|&n| (n, if n == 1 { 1.0 } else { 0.0 })).collect();
+        let options = RoleOptions { degrees: Some(&degrees), betweenness: Some(&betweenness), ..RoleOptions::default() };
+        let roles = classify_roles(&graph, &options);
+        assert_eq!(roles[&1], Role::Broker);
+        assert_eq!(roles[&5], Role::Peripheral);
+    }
+
+    #[test]
+    fn test_neighborhood_diversity() { //Only the bridge endpoints have friends in two communities
+        let graph = two_cliques_with_bridge();
+        let partition: Partition = (0..8).map(|n| (n, if n < 4 { 0 } else { 1 })).collect();
+        let diversity = neighborhood_diversity(&graph, &partition);
+        for node in 0..8 {
+            let expected = if node == 3 || node == 4 { 2 } else { 1 };
+            assert_eq!(diversity[&node], expected);
+        }
+        let entropy = neighborhood_entropy(&graph, &partition);
+        assert_eq!(entropy[&0], 0.0);
+        let p: f64 = 1.0 / 4.0; //Node 3 has 3 friends in its own clique and 1 across the bridge
+        let expected = -(p * p.ln() + (1.0 - p) * (1.0 - p).ln()) / 4f64.ln();
+        assert!((entropy[&3] - expected).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_cross_community_pairs() { //With the flag set, only pairs spanning two communities are scored
+        let graph = cliques_with_connector();
+        let partition: Partition = (0..9).map(|n| (n, if n < 4 || n == 8 { 0 } else { 1 })).collect();
+        let all = most_similar_pairs(&graph, 100);
+        assert!(all.iter().any(|((u, v), _)| partition[u] == partition[v]));
+
+        let options = SimilarPairsOptions { top_n: 100, partition: Some(&partition), cross_community_only: true, ..SimilarPairsOptions::default() };
+        let cross = most_similar_pairs_with(&graph, &options);
+        assert!(!cross.is_empty());
+        assert!(cross.iter().all(|((u, v), _)| partition[u] != partition[v]));
+        assert!(cross.iter().any(|&((u, v), _)| (u, v) == (3, 4) || (u, v) == (4, 3)));
+    }
+
+    #[test]
+    fn test_cosine_and_overlap() { //N(0) = {10, 11} is a strict subset of N(1) = {10, 11, 12, 13}: overlap 1.0, cosine 2/sqrt(8), Jaccard 0.5
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 10), (0, 11), (1, 10), (1, 11), (1, 12), (1, 13), (12, 13)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph.add_node(InternalId(5));
+        assert_eq!(overlap_coefficient(&graph, InternalId(0), InternalId(1)), 1.0);
+        assert!((cosine_similarity(&graph, InternalId(0), InternalId(1)) - 2.0 / 8f64.sqrt()).abs() < 1e-12);
+        assert_eq!(jaccard_similarity(&graph, InternalId(0), InternalId(1)), 0.5);
+        for metric in [SimilarityMetric::Cosine, SimilarityMetric::Overlap] {
+            assert_eq!((metric.score(&graph, InternalId(0), InternalId(5)), metric.score(&graph, InternalId(0), InternalId(99))), (0.0, 0.0)); //No friends, unknown node
+        }
+        let pairs = |metric| most_similar_pairs_by(&graph, 10, metric);
+        assert_eq!(pairs(SimilarityMetric::Overlap)[0], ((0, 1), 1.0));
+        assert_eq!(pairs(SimilarityMetric::Jaccard).len(), pairs(SimilarityMetric::Cosine).len());
+
+        let allowed: HashSet<usize> = HashSet::from([0, 1, 10, 12]);
+        assert_eq!(SimilarityMetric::Cosine.score_within(&graph, InternalId(0), InternalId(1), Some(&allowed)), 1.0 / 2f64.sqrt()); //Inside the set: N(0) = {10}, N(1) = {10, 12}
+    }
+
+    #[test]
+    fn test_adamic_adar_and_resource_allocation() { //0 and 1 share four friends of degree 10; 2 and 3 share one friend of degree 2. Adamic-Adar ranks 0-1 first, resource allocation 2-3
+        let mut graph = Graph::new();
+        for common in 10..14 {
+            graph.add_edge(InternalId(0), InternalId(common));
+            graph.add_edge(InternalId(1), InternalId(common));
+            for leaf in 0..8 {
+                graph.add_edge(InternalId(common), InternalId(100 + 10 * common + leaf));
+            }
+        }
+        for leaf in 0..4 {
+            graph.add_edge(InternalId(0), InternalId(300 + leaf));
+            graph.add_edge(InternalId(1), InternalId(310 + leaf));
+        }
+        for (u, v) in [(2, 20), (3, 20), (2, 30), (3, 31)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        assert!((adamic_adar(&graph, InternalId(0), InternalId(1)) - 4.0 / 10f64.ln()).abs() < 1e-12);
+        assert!((adamic_adar(&graph, InternalId(2), InternalId(3)) - 1.0 / 2f64.ln()).abs() < 1e-12);
+        assert!((resource_allocation(&graph, InternalId(0), InternalId(1)) - 0.4).abs() < 1e-12);
+        assert_eq!(resource_allocation(&graph, InternalId(2), InternalId(3)), 0.5);
+        assert_eq!((adamic_adar(&graph, InternalId(0), InternalId(2)), resource_allocation(&graph, InternalId(0), InternalId(99))), (0.0, 0.0));
+
+        let top_two = |metric| most_similar_pairs_by(&graph, 2, metric).into_iter().map(|(pair, _)| pair).collect::<Vec<_>>();
+        assert_eq!(top_two(SimilarityMetric::AdamicAdar), vec![(0, 1), (2, 3)]);
+        assert_eq!(top_two(SimilarityMetric::ResourceAllocation), vec![(2, 3), (0, 1)]);
+        let collapsed = SimilarPairsOptions { collapse_equivalent: true, metric: SimilarityMetric::AdamicAdar, ..SimilarPairsOptions::top(20) };
+        assert_eq!(most_similar_pairs_with(&graph, &collapsed), most_similar_pairs_by(&graph, 20, SimilarityMetric::AdamicAdar));
+    }
+
+    #[test]
+    fn test_similar_pairs_degree_filters() { //Four pairs sharing one friend: leaves 1-2 (degrees 2, 2), moderate 3-4 (5, 5), lopsided 5-6 (2, 8) and hubs 7-8 (20, 20)
+        let mut graph = Graph::new();
+        let mut next_leaf = 1000;
+        for (u, v, degree_u, degree_v, shared) in [(1, 2, 2, 2, 100), (3, 4, 5, 5, 101), (5, 6, 2, 8, 102), (7, 8, 20, 20, 103)] {
+            for (node, degree) in [(u, degree_u), (v, degree_v)] {
+                graph.add_edge(InternalId(node), InternalId(shared));
+                for _ in 1..degree {
+                    graph.add_edge(InternalId(node), InternalId(next_leaf));
+                    next_leaf += 1;
+                }
+            }
+        }
+        let pairs_with = |options: SimilarPairsOptions| {
+            let (pairs, stats) = most_similar_pairs_with_stats(&graph, &SimilarPairsOptions { top_n: 10, ..options });
+            let mut kept: Vec<(usize, usize)> = pairs.into_iter().map(|(pair, _)| pair).collect();
+            kept.sort();
+            (kept, stats.filtered_by_degree)
+        };
+        assert_eq!(pairs_with(SimilarPairsOptions::default()), (vec![(1, 2), (3, 4), (5, 6), (7, 8)], 0));
+        assert_eq!(pairs_with(SimilarPairsOptions { min_combined_degree: Some(6), ..SimilarPairsOptions::default() }), (vec![(3, 4), (5, 6), (7, 8)], 1));
+        assert_eq!(pairs_with(SimilarPairsOptions { max_degree: Some(10), ..SimilarPairsOptions::default() }), (vec![(1, 2), (3, 4), (5, 6)], 1));
+        assert_eq!(pairs_with(SimilarPairsOptions { max_degree_ratio: Some(2.0), ..SimilarPairsOptions::default() }), (vec![(1, 2), (3, 4), (7, 8)], 1));
+        let all = SimilarPairsOptions { min_combined_degree: Some(6), max_degree: Some(10), max_degree_ratio: Some(2.0), ..SimilarPairsOptions::default() };
+        assert_eq!(pairs_with(all), (vec![(3, 4)], 3));
+        assert_eq!(most_similar_pairs_with_stats(&graph, &all).1.scored, 1);
+    }
+
+    #[test]
+    fn test_exact_ranking() { //Near-tied pairs 100/201 > 99/199 and exact ties 1/3 = 2/6 keep one order through every scan path, and that order is the exact one
+        let (huge, third) = (JaccardRatio { intersection: (1 << 60) + 1, union: 3 << 60 }, JaccardRatio { intersection: 1, union: 3 });
+        assert_eq!(huge.to_f64(), third.to_f64()); //Different fractions that round to the same f64
+        assert!(huge > third);
+        assert_eq!(JaccardRatio { intersection: 2, union: 6 }, third);
+        assert_eq!(JaccardRatio { intersection: 0, union: 0 }, JaccardRatio { intersection: 0, union: 5 });
+
+        let mut graph = Graph::new();
+        let mut next = 100;
+        for ((u, v), common, private) in [((0, 1), 100, 101), ((2, 3), 99, 100), ((4, 5), 1, 2), ((6, 7), 2, 4)] { //Private friends are split between u and v
+            for i in 0..common + private {
+                let ends: &[usize] = if i < common { &[u, v] } else if i % 2 == 0 { &[u] } else { &[v] };
+                for &end in ends {
+                    graph.add_edge(InternalId(end), InternalId(next));
+                }
+                next += 1;
+            }
+        }
+        assert_eq!(jaccard_ratio(&graph, InternalId(0), InternalId(1), None), JaccardRatio { intersection: 100, union: 201 });
+        let everyone: HashSet<usize> = graph.adjacency().keys().copied().collect();
+        let exact = SimilarPairsOptions { exact_ranking: true, ..SimilarPairsOptions::top(usize::MAX) };
+        let ranked = most_similar_pairs_with(&graph, &exact);
+        for options in [SimilarPairsOptions { collapse_equivalent: true, ..exact }, SimilarPairsOptions { within: Some(&everyone), ..exact }, SimilarPairsOptions::top(usize::MAX)] {
+            assert_eq!(most_similar_pairs_with(&graph, &options), ranked);
+        }
+        let hubs: Vec<(usize, usize)> = ranked.iter().map(|&(pair, _)| pair).filter(|&(u, _)| u < 8).collect();
+        assert_eq!(hubs, vec![(0, 1), (2, 3), (4, 5), (6, 7)]);
+        assert!(ranked.iter().all(|&((u, v), sim)| sim == jaccard_similarity(&graph, InternalId(u), InternalId(v))));
+        assert!(ranked.windows(2).all(|w| jaccard_ratio(&graph, InternalId(w[0].0 .0), InternalId(w[0].0 .1), None) >= jaccard_ratio(&graph, InternalId(w[1].0 .0), InternalId(w[1].0 .1), None)));
+    }
+
+    #[test]
+    fn test_similar_pairs_match_brute_force() { //Two-hop candidates give exactly the pairs, scores and tie order of trying every pair of nodes with at least two friends
+        let graph = crate::generate::erdos_renyi(200, 0.03, 11).unwrap();
+        let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+        nodes.sort();
+        let mut brute = Vec::new();
+        for (i, &u) in nodes.iter().enumerate() {
+            for &v in &nodes[i + 1..] {
+                let sim = jaccard_similarity(&graph, InternalId(u), InternalId(v));
+                if graph.degree(u) > 1 && graph.degree(v) > 1 && sim > 0.0 {
+                    brute.push(((u, v), sim));
+                }
+            }
+        }
+        brute.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        assert!(brute.len() > 100);
+        assert_eq!(most_similar_pairs(&graph, brute.len() + 1), brute);
+        assert_eq!(most_similar_pairs(&graph, 25), brute[..25]);
+    }
+
+    #[test]
+    fn test_connected_components() { //Triangle plus a separate edge: the triangle comes first and holds 3 of the 5 nodes
+        let mut graph = small_graph();
+        graph.add_edge(InternalId(7), InternalId(8));
+        assert_eq!(connected_components(&graph), vec![vec![0, 1, 2], vec![7, 8]]);
+        assert!((giant_component_fraction(&graph) - 0.6).abs() < 1e-9);
+        assert!(!is_connected(&graph) && is_connected(&small_graph()));
+
+        let mut triangles = small_graph(); //Two disjoint triangles: two components of three
+        for (u, v) in [(10, 11), (11, 12), (12, 10)] {
+            triangles.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(num_components(&triangles), 2);
+        assert!(connected_components(&triangles).iter().all(|c| c.len() == 3));
+    }
+
+    #[test]
+    fn test_connected_pair_fraction() { //Connected graph: every pair is reachable; two triangles: 2 * 3 of the 15 pairs; the streaming version agrees
+        assert_eq!(connected_pair_fraction(&barbell_graph()), 1.0);
+        let mut graph = small_graph();
+        for (u, v) in [(3, 4), (4, 5), (5, 3)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(connected_pair_fraction(&graph), 6.0 / 15.0);
+
+        let path = std::env::temp_dir().join(format!("fga_pair_fraction_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1\n1 2\n2 0\n3 4\n4 5\n10 11\n11 12\n12 13\n0 2\n").unwrap();
+        let from_file = connected_pair_fraction_from_file(path.to_str().unwrap()).unwrap();
+        let in_memory = connected_pair_fraction(&Graph::load_from_file(path.to_str().unwrap()).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(from_file, in_memory);
+        assert_eq!(from_file, 12.0 / 45.0);
+    }
+
+    #[test]
+    fn test_group_distance_stats() { //Path 0-1-...-9 plus a separate edge 20-21: A = {0,1,2,3,20} against B = {8,9}
+        let mut graph = Graph::new();
+        for i in 0..9 {
+            graph.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        graph.add_edge(InternalId(20), InternalId(21));
+        let stats = group_distance_stats(&graph, &[0, 1, 2, 3, 20], &[8, 9], None, 1).unwrap();
+        assert_eq!(stats, DistStats { reachable: 4, unreachable: 1, mean: 6.5, median: 6.0, p90: 8.0 });
+        let sampled = group_distance_stats(&graph, &[0, 1, 2, 3, 20], &[8, 9], Some(2), 1).unwrap();
+        assert_eq!(sampled.reachable + sampled.unreachable, 2);
+        assert_eq!(sampled, group_distance_stats(&graph, &[0, 1, 2, 3, 20], &[8, 9], Some(2), 1).unwrap());
+        assert!(matches!(group_distance_stats(&graph, &[0], &[42], None, 1), Err(GraphError::UnknownNodes(ref ids)) if ids == &vec![42]));
+    }
+
+    #[test]
+    fn test_co_neighbor_counts() { //One-pass counts equal brute-force intersections on random graphs; a hub cap skips exactly the hub's pairs
+        for seed in 0..5 {
+            let graph = crate::generate::erdos_renyi(30, 0.2, seed).unwrap();
+            let counts = co_neighbor_counts(&graph, 2);
+            for u in 0..30 {
+                for v in u + 1..30 {
+                    let common = graph.adjacency()[&u].intersection(&graph.adjacency()[&v]).count() as u32;
+                    assert_eq!(counts.get(&(u, v)).copied(), Some(common).filter(|&c| c >= 2));
+                }
+            }
+        }
+
+        let graph = barbell_graph(); //0, 3, 7 and 10 have four friends each, everyone else at most three
+        let capped = co_neighbor_counts_with(&graph, &CoNeighborOptions { min_count: 1, hub_cap: Some(3) });
+        assert_eq!(capped.skipped_hubs, vec![0, 3, 7, 10]);
+        assert_eq!(capped.skipped_pairs, 4 * 6);
+        assert!(!capped.counts.contains_key(&(1, 2))); //Their common friends 0 and 3 are both hubs
+        assert_eq!(capped.counts[&(4, 6)], 1); //Through 5, which is under the cap
+        assert!(!capped.counts.contains_key(&(2, 4))); //Only linked through hub 3
+    }
+
+    #[test]
+    fn test_hub_exclusion() { //Star 0 with leaves 1..=5, and the path 5-6-7-8 hanging off leaf 5: capping degree at 3 removes the star center
+        let mut graph = Graph::new();
+        for leaf in 1..=5 {
+            graph.add_edge(InternalId(0), InternalId(leaf));
+        }
+        for i in 5..8 {
+            graph.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        assert_eq!(bfs_distances(&graph, InternalId(1))[&8], 5);
+        assert_eq!(bfs_distances_excluding_hubs(&graph, InternalId(1), None), bfs_distances(&graph, InternalId(1)));
+        assert_eq!(bfs_distances_excluding_hubs(&graph, InternalId(1), Some(3)), HashMap::from([(1, 0)])); //Leaf 1 only knew the center
+        assert_eq!(bfs_distances_excluding_hubs(&graph, InternalId(8), Some(3)), HashMap::from([(8, 0), (7, 1), (6, 2), (5, 3)]));
+        assert!(bfs_distances_excluding_hubs(&graph, InternalId(0), Some(3)).is_empty());
+
+        let sequential = ParallelismConfig::sequential();
+        assert_eq!(average_distance_excluding_hubs(&graph, None, &sequential), (average_distance(&graph), 0));
+        assert_eq!(average_distance_excluding_hubs(&graph, Some(3), &sequential), (20.0 / 12.0, 1)); //Only the path 5-6-7-8 still has pairs
+        let (closeness, excluded) = closeness_centrality_excluding_hubs(&graph, Some(3), &sequential);
+        assert_eq!(excluded, 1);
+        assert_eq!(closeness.len(), 8);
+        assert!(closeness.iter().all(|&(node, _)| node != 0));
+    }
+
+    #[test]
+    fn test_lift_favors_surprising_overlap() { //Hubs 0 and 1 (10 friends each, 6 shared) beat leaves 20 and 21 (2 friends each, 1 shared) on raw Jaccard, but not on lift
+        let mut graph = Graph::new();
+        for f in 2..=7 {
+            graph.add_edge(InternalId(0), InternalId(f));
+            graph.add_edge(InternalId(1), InternalId(f));
+        }
+        for f in 8..=11 {
+            graph.add_edge(InternalId(0), InternalId(f));
+            graph.add_edge(InternalId(1), InternalId(f + 4));
+        }
+        for (u, v) in [(20, 22), (20, 23), (21, 23), (21, 24), (25, 26), (26, 27), (27, 28), (28, 29)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        assert!(jaccard_similarity(&graph, InternalId(0), InternalId(1)) > jaccard_similarity(&graph, InternalId(20), InternalId(21)));
+        assert!(jaccard_lift(&graph, InternalId(20), InternalId(21)) > jaccard_lift(&graph, InternalId(0), InternalId(1)));
+        assert!(jaccard_lift(&graph, InternalId(0), InternalId(1)) > 1.0); //Still more overlap than chance
+        assert_eq!(jaccard_lift(&graph, InternalId(0), InternalId(20)), 0.0);
+
+        let position = |ranking: &[((usize, usize), f64)], pair| ranking.iter().position(|&(p, _)| p == pair).unwrap();
+        let raw = most_similar_pairs(&graph, 1000);
+        let lifted = most_similar_pairs_by_lift(&graph, 1000);
+        assert_eq!(raw.len(), lifted.len());
+        assert!(position(&raw, (0, 1)) < position(&raw, (20, 21)));
+        assert!(position(&lifted, (20, 21)) < position(&lifted, (0, 1)));
+    }
+
+    #[test]
+    fn test_closeness_delta_matches_modified_copy() { //Every hypothetical edge on the barbell, checked against really adding it to a rebuilt graph
+        let graph = barbell_graph();
+        let edges = [(11, 12), (4, 6), (0, 1), (5, 9)].map(|(u, v)| (InternalId(u), InternalId(v)));
+        let deltas = closeness_deltas_with_edges(&graph, InternalId(11), &edges).unwrap();
+        for (&(u, v), &(before, after)) in edges.iter().zip(&deltas) {
+            let mut modified = barbell_graph();
+            modified.add_edge(u, v);
+            assert_eq!(before, closeness_of(&graph, InternalId(11)).unwrap());
+            assert_eq!(after, closeness_of(&modified, InternalId(11)).unwrap());
+        }
+        assert!(deltas[0].1 > deltas[0].0); //A shortcut between the two pendants helps 11 a lot
+        assert_eq!(deltas[2].0, deltas[2].1); //0-1 already exists
+        assert_eq!(closeness_delta_with_edge(&graph, InternalId(11), (InternalId(11), InternalId(12))).unwrap(), deltas[0]);
+        assert!(matches!(closeness_delta_with_edge(&graph, InternalId(11), (InternalId(11), InternalId(40))), Err(GraphError::UnknownNode(40))));
+    }
+
+    #[test]
+    fn test_distance_profiles() { //Path 0-1-2-3-4 and star 10 with leaves 11..=14, max_depth 2
+        let mut graph = Graph::new();
+        for i in 0..4 {
+            graph.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        for leaf in 11..=14 {
+            graph.add_edge(InternalId(10), InternalId(leaf));
+        }
+        let profiles = distance_profiles(&graph, 2);
+        assert_eq!(profiles[&0], DistanceProfile { counts: vec![1, 1], beyond: 2, median: Some(2) });
+        assert_eq!(profiles[&2], DistanceProfile { counts: vec![2, 2], beyond: 0, median: Some(1) });
+        assert_eq!(profiles[&10], DistanceProfile { counts: vec![4, 0], beyond: 0, median: Some(1) });
+        assert_eq!(profiles[&11], DistanceProfile { counts: vec![1, 3], beyond: 0, median: Some(2) });
+        assert_eq!(distance_profiles(&graph, 1)[&0], DistanceProfile { counts: vec![1], beyond: 3, median: None });
+    }
+
+    #[test]
+    fn test_sparse_betweenness_is_filtered_full_output() { //Threshold and top-k results must be exactly a prefix/filter of the full ranking
+        let graph = barbell_graph();
+        let full = betweenness_centrality(&graph);
+        let pairs = (12 * 11 / 2) as f64;
+        for min in [0.0, 0.05, 0.2, 0.6] {
+            let sparse = betweenness_centrality_sparse(&graph, &SparseBetweennessOptions { min_normalized: Some(min), top_k: None });
+            let expected: Vec<(usize, f64)> = full.iter().copied().filter(|&(_, s)| s / pairs > min).collect();
+            assert_eq!(sparse.suppressed, full.len() - expected.len());
+            assert_eq!(sparse.scores, expected);
+        }
+        for k in [0, 1, 4, 13, 50] {
+            let sparse = betweenness_centrality_sparse(&graph, &SparseBetweennessOptions { min_normalized: None, top_k: Some(k) });
+            assert_eq!(sparse.scores, full[..k.min(full.len())]);
+        }
+        let both = betweenness_centrality_sparse(&graph, &SparseBetweennessOptions { min_normalized: Some(0.0), top_k: Some(20) });
+        assert_eq!(both.scores, full.iter().copied().filter(|&(_, s)| s > 0.0).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_ranking_diff() { //Node 3 jumps from third to first, 2 and 4 lose the most score, 5 only exists after; the common order 1 2 3 4 -> 3 1 2 4 has 4 of 6 pairs agreeing
+        let before = [(1, 0.9), (2, 0.8), (3, 0.7), (4, 0.6)];
+        let after = [(3, 0.95), (1, 0.9), (2, 0.5), (5, 0.25), (4, 0.2)];
+        let diff = ranking_diff(&before, &after, 2);
+        assert_eq!(diff.rank_movers.iter().map(|m| (m.node, m.rank_shift())).collect::<Vec<_>>(), vec![(3, 2), (1, -1)]); //1, 2 and 4 all fell one place; the smallest id wins
+        assert_eq!(diff.score_movers.iter().map(|m| m.node).collect::<Vec<_>>(), vec![4, 2]);
+        assert_eq!((diff.entered.clone(), diff.left.clone(), diff.common), (vec![3], vec![2], 4));
+        assert!((diff.kendall_tau - 1.0 / 3.0).abs() < 1e-12);
+        let text = diff.to_string();
+        assert!(text.contains("Node 3: #3 -> #1 (+2), score 0.7000 -> 0.9500 (+0.2500)") && text.contains("Left the top 2: 2"));
+
+        let same = ranking_diff(&before, &before, 3);
+        assert_eq!((same.kendall_tau, same.entered.len(), same.rank_movers[0].rank_shift()), (1.0, 0, 0));
+        let reversed: Vec<(usize, f64)> = before.iter().rev().map(|&(node, score)| (node, 1.0 - score)).collect();
+        assert_eq!(ranking_diff(&before, &reversed, 3).kendall_tau, -1.0);
+    }
+
+    #[test]
+    fn test_closeness_breakdown() { //Star 0 with leaves 1-4: the center has one ring holding its whole score; a leaf has 1 node at distance 1 and 3 at distance 2 (distance sum 7)
+        let mut star = Graph::new();
+        for leaf in 1..=4 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        assert_eq!(closeness_breakdown(&star, InternalId(0)).unwrap(), vec![DistanceRing { distance: 1, nodes: 4, closeness: 1.0, harmonic: 1.0 }]);
+        let leaf = closeness_breakdown(&star, InternalId(1)).unwrap();
+        assert_eq!(leaf.iter().map(|r| (r.distance, r.nodes)).collect::<Vec<_>>(), vec![(1, 1), (2, 3)]);
+        assert!((leaf[0].closeness - 1.0 / 7.0).abs() < 1e-12 && (leaf[1].closeness - 3.0 / 7.0).abs() < 1e-12);
+        assert!((leaf[0].harmonic - 0.25).abs() < 1e-12 && (leaf[1].harmonic - 0.375).abs() < 1e-12);
+
+        for graph in [small_graph(), triangle_plus_pendant(), barbell_graph(), triangle_with_isolated(), star] { //The rings add up to the node's overall scores
+            let harmonic: HashMap<usize, f64> = harmonic_centrality(&graph).into_iter().collect();
+            for &node in graph.adjacency().keys() {
+                let rings = closeness_breakdown(&graph, InternalId(node)).unwrap();
+                assert!((rings.iter().map(|r| r.closeness).sum::<f64>() - closeness_of(&graph, InternalId(node)).unwrap()).abs() < 1e-12);
+                assert!((rings.iter().map(|r| r.harmonic).sum::<f64>() - harmonic[&node]).abs() < 1e-12);
+            }
+        }
+        assert!(matches!(closeness_breakdown(&Graph::new(), InternalId(3)), Err(GraphError::UnknownNode(3))));
+    }
+
+    #[test]
+    fn test_explain_pair() { //Square 1-2-4-3 with 5 hanging off 4, plus a separate edge 6-7: 1 and 4 share friends 2 and 3, 1 and 6 are not connected
+        let mut graph = Graph::new();
+        for (u, v) in [(1, 2), (1, 3), (2, 4), (3, 4), (4, 5), (6, 7)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.communities = Some(HashMap::from([(1, 0), (2, 0), (3, 0), (4, 0), (5, 0), (6, 1), (7, 1)]));
+
+        let near = explain_pair(&graph, InternalId(1), InternalId(4), Some(&results)).unwrap();
+        assert_eq!((near.u, near.v, near.distance), (NodeId(1), NodeId(4), Some(2)));
+        assert_eq!(near.path, Some(vec![NodeId(1), NodeId(2), NodeId(4)])); //The smaller of the two middle friends
+        assert_eq!(near.mutual_friends, vec![NodeId(2), NodeId(3)]);
+        assert!((near.jaccard - 2.0 / 3.0).abs() < 1e-12 && (near.adamic_adar - 2.0 / 2f64.ln()).abs() < 1e-12);
+        assert_eq!(near.same_community, Some(true));
+        assert_eq!(near.to_string(), "Nodes 1 and 4:\n  Distance: 2 (1 → 2 → 4)\n  Mutual friends (2): 2 3\n  Common neighbors: 2, Jaccard: 0.6667, Adamic-Adar: 2.8854\n  Same community: yes\n");
+
+        let far = explain_pair(&graph, InternalId(1), InternalId(6), None).unwrap();
+        assert_eq!((far.distance, far.path.as_ref(), far.same_community), (None, None, None));
+        assert!(far.mutual_friends.is_empty() && far.jaccard == 0.0 && far.adamic_adar == 0.0);
+        assert!(far.to_string().contains("Distance: not connected\n  Mutual friends (0): none\n"));
+        assert_eq!(explain_pair(&graph, InternalId(1), InternalId(6), Some(&results)).unwrap().same_community, Some(false));
+
+        assert!(matches!(explain_pair(&graph, InternalId(1), InternalId(99), None), Err(GraphError::UnknownNode(99))));
+        assert!(matches!(explain_pair(&graph, InternalId(98), InternalId(99), None), Err(GraphError::UnknownNodes(ids)) if ids == vec![98, 99]));
+    }
+
+    #[test]
+    fn test_node_inspector_reuses_reports() { //Reports match the single-node functions; a repeat is served from the kept reports, and with room for two the least recently inspected is rebuilt
+        let graph = barbell_graph();
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.ensure_clustering(&graph);
+        results.ensure_communities(&graph);
+        let mut inspector = NodeInspector::with_capacity(&graph, &results, 2);
+        for node in [4, 0, 11] {
+            let report = inspector.inspect(InternalId(node)).unwrap();
+            assert_eq!((report.node, report.degree, report.clustering), (node, graph.degree(node), local_clustering_of(&graph, InternalId(node)).unwrap()));
+            assert!((report.closeness - closeness_of(&graph, InternalId(node)).unwrap()).abs() < 1e-12);
+            assert_eq!(report.rings, closeness_breakdown(&graph, InternalId(node)).unwrap());
+            assert_eq!(report.similar, recommend_friends(&graph, InternalId(node), INSPECT_TOP_SIMILAR, SimilarityMetric::Jaccard));
+            assert_eq!(report.community, results.communities.as_ref().unwrap().get(&node).copied());
+        }
+        assert_eq!(inspector.computed(), 3);
+        assert_eq!(inspector.inspect(InternalId(11)).unwrap(), inspector.inspect(InternalId(11)).unwrap());
+        assert_eq!(inspector.computed(), 3);
+        inspector.inspect(InternalId(4)).unwrap(); //Dropped when 11 came in
+        assert_eq!(inspector.computed(), 4);
+        assert!(matches!(inspector.inspect(InternalId(99)), Err(GraphError::UnknownNode(99))));
+        assert_eq!(NodeInspector::new(&graph, &AnalysisResults::new()).inspect(InternalId(0)).unwrap().community, None);
+    }
+
+    #[test]
+    fn test_recommend_friends() { //4 shares all three of 0's friends, 5 shares one: 4 first, then 5. A triangle has nobody left to suggest, nor has an isolated node
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (0, 2), (0, 3), (4, 1), (4, 2), (4, 3), (5, 3), (6, 7), (7, 8), (8, 6)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph.add_node(InternalId(9));
+        assert_eq!(recommend_friends(&graph, InternalId(0), 5, SimilarityMetric::Jaccard), vec![(4, 1.0), (5, 1.0 / 3.0)]);
+        assert_eq!(recommend_friends(&graph, InternalId(0), 1, SimilarityMetric::Jaccard), vec![(4, 1.0)]);
+        assert_eq!(recommend_friends(&graph, InternalId(5), 5, SimilarityMetric::Jaccard), vec![(0, 1.0 / 3.0), (4, 1.0 / 3.0)]); //Tie broken by id
+        assert!(recommend_friends(&graph, InternalId(6), 5, SimilarityMetric::Jaccard).is_empty());
+        assert!(recommend_friends(&graph, InternalId(9), 5, SimilarityMetric::Jaccard).is_empty());
+        assert!(recommend_friends(&graph, InternalId(99), 5, SimilarityMetric::Jaccard).is_empty());
+    }
+
+    #[test]
+    fn test_recommendations_for_all() { //Rows per node are sorted, capped at k, never suggest existing friends and match single-pair Jaccard
+        let graph = barbell_graph();
+        let calls = AtomicUsize::new(0);
+        let recs = recommendations_for_all_with(&graph, 2, 2, SimilarityMetric::Jaccard, &ParallelismConfig::with_threads(2), &|_, total| {
+            calls.fetch_add(1, AtomicOrdering::Relaxed);
+            assert_eq!(total, 11); //Pendants 11 and 12 have a single friend
+        });
+        assert_eq!(calls.load(AtomicOrdering::Relaxed), 11);
+        assert!(recs.iter().all(|r| r.node != 11 && r.node != 12));
+        for node in graph.adjacency().keys() {
+            let rows: Vec<&Recommendation> = recs.iter().filter(|r| r.node == *node).collect();
+            assert!(rows.len() <= 2);
+            for (i, row) in rows.iter().enumerate() {
+                assert_eq!(row.rank, i + 1);
+                assert!(!graph.adjacency()[node].contains(&row.candidate) && row.candidate != *node);
+                assert_eq!(row.score, jaccard_similarity(&graph, InternalId(*node), InternalId(row.candidate)));
+                assert_eq!(row.mutual_count, graph.adjacency()[node].intersection(&graph.adjacency()[&row.candidate]).count());
+                if i > 0 {
+                    assert!(rows[i - 1].score >= row.score);
+                }
+            }
+        }
+        //Node 4 (friends 3 and 5) scores 1/3 with 6, 1/4 with 1 and 2 (tie broken by id) and 1/5 with 0 (which also has pendant 11)
+        let rows: Vec<(usize, usize)> = recs.iter().filter(|r| r.node == 4).map(|r| (r.candidate, r.mutual_count)).collect();
+        assert_eq!(rows, vec![(6, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn test_progress_callbacks() { //600 separate edges (1200 sources, each BFS is one hop): every sweep reports at 500, 1000 and 1200 sources done, and the results do not change
+        let edges: Vec<(usize, usize)> = (0..600).map(|i| (2 * i, 2 * i + 1)).collect();
+        let graph = Graph::from_edges(&edges);
+        let seen = std::sync::Mutex::new(Vec::new());
+        let record = |done: usize, total: usize| seen.lock().unwrap().push((done, total));
+        let take = || {
+            let mut calls = std::mem::take(&mut *seen.lock().unwrap());
+            calls.sort();
+            calls
+        };
+        let expected = vec![(500, 1200), (1000, 1200), (1200, 1200)];
+
+        let closeness = closeness_centrality_with_progress(&graph, &ParallelismConfig::with_threads(2), &record);
+        assert_eq!(take(), expected);
+        assert_eq!(closeness, closeness_centrality(&graph));
+        assert_eq!(average_distance_with_progress(&graph, &ParallelismConfig::default(), &record), average_distance(&graph));
+        assert_eq!(take(), expected);
+        let options = SimilarPairsOptions::top(5);
+        assert_eq!(most_similar_pairs_with_progress(&graph, &options, &record), most_similar_pairs_with(&graph, &options));
+        assert_eq!(take(), expected);
+    }
+
+    #[test]
+    fn test_closeness_approx() { //All nodes as pivots reproduces the exact top 10; on a larger graph more pivots means smaller error
+        let graph = barbell_graph();
+        let exact = closeness_centrality(&graph);
+        let approx = closeness_centrality_approx(&graph, graph.num_nodes(), 3);
+        assert_eq!(approx.scores[..10], exact[..10]);
+
+        let mut ring = Graph::new(); //Ring of 300 with chords every 10 nodes, so distances vary a lot between nodes
+        for u in 0..300 {
+            ring.add_edge(InternalId(u), InternalId((u + 1) % 300));
+            if u % 10 == 0 {
+                ring.add_edge(InternalId(u), InternalId((u + 37) % 300));
+            }
+        }
+        let exact: HashMap<usize, f64> = closeness_centrality(&ring).into_iter().collect();
+        let mean_error = |samples: usize| {
+            let approx = closeness_centrality_approx(&ring, samples, 5);
+            approx.scores.iter().map(|(n, c)| (c - exact[n]).abs()).sum::<f64>() / 300.0
+        };
+        assert!(mean_error(100) < mean_error(10));
+        assert!(closeness_centrality_approx(&ring, 100, 5).error_bound < closeness_centrality_approx(&ring, 10, 5).error_bound);
+        assert_eq!(closeness_centrality_approx(&ring, 10, 5), closeness_centrality_approx(&ring, 10, 5));
+    }
+
+    #[test]
+    fn test_structural_equivalence() { //Hub 0 with leaf fans 1, 2, 3; nodes 4 and 5 both know exactly {0, 6}; 6 knows 4 and 5
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (0, 2), (0, 3), (0, 4), (0, 5), (4, 6), (5, 6)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(structural_equivalence_classes(&graph), vec![vec![0], vec![1, 2, 3], vec![4, 5], vec![6]]);
+
+        let full = most_similar_pairs_with(&graph, &SimilarPairsOptions::top(100));
+        let collapsed = most_similar_pairs_with(&graph, &SimilarPairsOptions { collapse_equivalent: true, ..SimilarPairsOptions::top(100) });
+        assert_eq!(collapsed, full);
+        assert_eq!(collapsed[0], ((4, 5), 1.0));
+        assert!(collapsed.iter().all(|&((u, _), _)| u != 1 && u != 2)); //The leaf fans have a single friend and are skipped like before
+
+        let partition: Partition = HashMap::from([(0, 0), (6, 1), (4, 0), (5, 0)]);
+        let options = SimilarPairsOptions { top_n: 100, partition: Some(&partition), cross_community_only: true, ..SimilarPairsOptions::default() };
+        let collapsed_options = SimilarPairsOptions { collapse_equivalent: true, ..options };
+        assert_eq!(most_similar_pairs_with(&graph, &collapsed_options), most_similar_pairs_with(&graph, &options));
+    }
+
+    #[test]
+    fn test_unknown_start_nodes() { //Every single-node entry point reports an unknown id instead of a silent zero or empty result
+        let graph = barbell_graph();
+        assert!(bfs_distances(&graph, InternalId(99)).is_empty());
+        assert!(matches!(bfs_distances_checked(&graph, InternalId(99)), Err(GraphError::UnknownNode(99))));
+        assert!(matches!(closeness_of(&graph, InternalId(99)), Err(GraphError::UnknownNode(99))));
+        assert!(matches!(distance_between(&graph, InternalId(0), InternalId(99)), Err(GraphError::UnknownNode(99))));
+        assert!(matches!(two_hop_reach_of(&graph, InternalId(99)), Err(GraphError::UnknownNode(99))));
+        let exact: HashMap<usize, f64> = closeness_centrality(&graph).into_iter().collect();
+        assert_eq!(closeness_of(&graph, InternalId(5)).unwrap(), exact[&5]);
+    }
+
+    #[test]
+    fn test_generic_analyses_agree_across_implementors() { //Graph, an unfiltered GraphView and a WeightedGraph with arbitrary weights describe the same friendships, so unweighted analyses must agree
+        let graph = barbell_graph();
+        let view = GraphView::new(&graph, |_| true);
+        let mut weighted = WeightedGraph::new();
+        for (&u, friends) in graph.adjacency() {
+            for &v in friends {
+                weighted.add_edge(u, v, (u + v) as f64);
+            }
+        }
+        assert_eq!(average_distance(&view), average_distance(&graph));
+        assert_eq!(average_distance(&weighted), average_distance(&graph));
+        assert_eq!(closeness_centrality(&view), closeness_centrality(&graph));
+        assert_eq!(closeness_centrality(&weighted), closeness_centrality(&graph));
+        assert_eq!(most_similar_pairs(&view, 10), most_similar_pairs(&graph, 10));
+        assert_eq!(most_similar_pairs(&weighted, 10), most_similar_pairs(&graph, 10));
+    }
+
+    #[test]
+    fn test_jaccard_within_matches_induced_subgraph() { //Restricting to {0..=6} drops the second clique and the pendants: scores must equal plain Jaccard on the copied subgraph
+        let graph = barbell_graph();
+        let allowed: HashSet<usize> = (0..=6).collect();
+        let induced = GraphView::of_nodes(&graph, &allowed).to_graph();
+        for u in 0..=12 {
+            for v in 0..=12 {
+                let expected = if allowed.contains(&u) && allowed.contains(&v) { jaccard_similarity(&induced, InternalId(u), InternalId(v)) } else { 0.0 };
+                assert_eq!(jaccard_similarity_within(&graph, InternalId(u), InternalId(v), &allowed), expected);
+            }
+        }
+        let restricted = most_similar_pairs_with(&graph, &SimilarPairsOptions { within: Some(&allowed), ..SimilarPairsOptions::top(50) });
+        assert_eq!(restricted, most_similar_pairs(&induced, 50));
+        assert!(restricted.iter().any(|&(pair, _)| pair == (0, 1))); //Without pendant 11, nodes 0 and 1 have the same friends apart from each other
+    }
+
+    #[test]
+    fn test_distance_between() { //Hop count for connected nodes, UnknownNode for missing ids and Disconnected across components
+        let mut graph = triangle_plus_pendant();
+        graph.add_edge(InternalId(10), InternalId(11));
+        assert_eq!(distance_between(&graph, InternalId(0), InternalId(3)).unwrap(), 2);
+        assert!(matches!(distance_between(&graph, InternalId(0), InternalId(99)), Err(GraphError::UnknownNode(99))));
+        assert!(matches!(distance_between(&graph, InternalId(0), InternalId(10)), Err(GraphError::Disconnected)));
+    }
+
+    #[test]
+    fn test_path_between_matches_bfs() { //On a random graph every pair's bidirectional path is a real path as long as the BFS distance; equal ends, friends and other components too
+        let mut graph = crate::generate::erdos_renyi(150, 0.02, 4).unwrap();
+        graph.add_edge(InternalId(900), InternalId(901));
+        for u in (0..150).step_by(11) {
+            let distances = bfs_distances(&graph, InternalId(u));
+            for v in (0..150).chain([900]) {
+                let path = path_between(&graph, InternalId(u), InternalId(v));
+                assert_eq!(path.as_ref().map(|path| path.len() - 1), distances.get(&v).copied(), "{}-{}", u, v);
+                if let Some(path) = path {
+                    assert_eq!((path[0], path[path.len() - 1]), (u, v));
+                    assert!(path.windows(2).all(|step| graph.has_edge(step[0], step[1])));
+                    assert_eq!(distance_between(&graph, InternalId(u), InternalId(v)).unwrap(), path.len() - 1);
+                }
+            }
+        }
+        assert_eq!((path_between(&graph, InternalId(900), InternalId(900)), path_between(&graph, InternalId(900), InternalId(901))), (Some(vec![900]), Some(vec![900, 901])));
+        assert_eq!(path_between(&graph, InternalId(0), InternalId(999)), None);
+    }
+
+    #[test]
+    fn test_write_matrix_csv_bad_path() { //Writing into a missing directory is an Io error
+        let result = write_matrix_csv("no/such/dir/matrix.csv", &[0], &[vec![1.0]], &NumberFormat::default());
+        assert!(matches!(result, Err(GraphError::Io(_))));
+    }
+
+    #[test]
+    fn test_dijkstra_prefers_light_path() { //The direct 0-2 edge is one hop but weighs 5; going through 1 costs 1 + 1
+        let mut graph = WeightedGraph::new();
+        for (u, v, w) in [(0, 1, 1.0), (1, 2, 1.0), (0, 2, 5.0)] {
+            graph.add_edge(u, v, w);
+        }
+        assert_eq!(bfs_distances(&graph, InternalId(0))[&2], 1);
+        assert_eq!(dijkstra_distances(&graph, InternalId(0))[&2], 2.0);
+        assert_eq!(average_distance_weighted(&graph), 8.0 / 6.0); //Ordered pairs: 0-1 and 1-2 cost 1, 0-2 costs 2
+        assert_eq!(closeness_centrality_weighted(&graph)[0], (1, 1.0));
+        let unweighted = small_graph();
+        assert_eq!(average_distance_weighted(&unweighted), average_distance(&unweighted));
+    }
+
+    #[test]
+    fn test_temporal_reachability() { //Fixture path 0-1 (t5), 1-2 (t3), 2-3 (t7), 3-4 (t7): statically connected, but 1-2 happens before 0 reaches 1
+        let graph = TemporalGraph::load_from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/temporal_edges.txt")).unwrap();
+        assert_eq!(bfs_distances(&graph, InternalId(0)).len(), 5);
+        assert_eq!(temporal_reachability(&graph, 0, 0, u64::MAX), HashMap::from([(0, 0), (1, 5)]));
+        assert_eq!((temporal_distance(&graph, 0, 1), temporal_distance(&graph, 0, 2)), (Some(5), None));
+        //Backwards the timestamps line up; 4 is reached through the same-time chain 2-3-4
+        assert_eq!(temporal_reachability(&graph, 2, 0, u64::MAX), HashMap::from([(2, 0), (1, 3), (0, 5), (3, 7), (4, 7)]));
+        assert_eq!(temporal_reachability(&graph, 2, 4, 6), HashMap::from([(2, 4)])); //The window starts after 1-2 and ends before 2-3
+        assert!(temporal_reachability(&graph, 9, 0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_minhash_sketches_estimate_jaccard() { //Export, drop the graph, estimate from the file: every pair within 4 standard errors of the exact Jaccard, and the mean error within one
+        let path = std::env::temp_dir().join(format!("fga_minhash_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        let graph = crate::generate::erdos_renyi(60, 0.2, 11).unwrap();
+        export_minhash_sketches(&graph, 256, 42, path).unwrap();
+        let exact: Vec<((usize, usize), f64)> = (0..60).flat_map(|u| (u + 1..60).map(move |v| (u, v))).map(|(u, v)| ((u, v), jaccard_similarity(&graph, InternalId(u), InternalId(v)))).collect();
+        drop(graph);
+        let sketches = read_minhash_sketches(path).unwrap();
+        let bound = minhash_standard_error(256);
+        let mut total_error = 0.0;
+        for &((u, v), jaccard) in &exact {
+            let estimate = minhash_jaccard(&sketches[&u], &sketches[&v]);
+            assert!((estimate - jaccard).abs() < 4.0 * bound, "{}-{}: estimate {} vs {}", u, v, estimate, jaccard);
+            total_error += (estimate - jaccard).abs();
+        }
+        assert!(total_error / (exact.len() as f64) < bound);
+        assert_eq!(similarity_from_sketches(path, 3, 3).unwrap(), 1.0);
+        assert!(matches!(similarity_from_sketches(path, 3, 60), Err(GraphError::UnknownNode(60))));
+
+        std::fs::write(path, b"FGAMINH1 truncated").unwrap();
+        assert!(matches!(read_minhash_sketches(path), Err(GraphError::Parse { .. })));
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(minhash_jaccard(&[u64::MAX; 4], &[u64::MAX; 4]), 0.0); //Two friendless nodes, as in jaccard_similarity
+    }
+
+    #[test]
+    fn test_adaptive_jaccard_dispatch() { //8 hubs over 400 leaves (each leaf joins hub i with probability 0.2 + 0.1 i): hub pairs are sketched and stay within the bound, leaf pairs are exact
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut graph = Graph::new();
+        let hubs: Vec<usize> = (1000..1008).collect();
+        for (i, &hub) in hubs.iter().enumerate() {
+            for leaf in 0..400 {
+                if rng.gen::<f64>() < 0.2 + 0.1 * i as f64 {
+                    graph.add_edge(InternalId(hub), InternalId(leaf));
+                }
+            }
+        }
+        let config = AdaptiveJaccard::new(100, 256, 7);
+        assert_eq!(jaccard_similarity_adaptive(&graph, InternalId(0), InternalId(1), &config), (jaccard_similarity(&graph, InternalId(0), InternalId(1)), JaccardMethod::Exact));
+        let combined = graph.degree(1000) + graph.degree(0);
+        for (exact_below, sketched) in [(combined, true), (combined + 1, false)] {
+            let (_, method) = jaccard_similarity_adaptive(&graph, InternalId(1000), InternalId(0), &AdaptiveJaccard::new(exact_below, 64, 7));
+            assert_eq!(matches!(method, JaccardMethod::Sketched { .. }), sketched, "threshold {}", exact_below);
+        }
+        for (i, &u) in hubs.iter().enumerate() {
+            for &v in &hubs[i + 1..] {
+                let (estimate, method) = jaccard_similarity_adaptive(&graph, InternalId(u), InternalId(v), &config);
+                assert_eq!(method, JaccardMethod::Sketched { error_bound: config.error_bound() });
+                assert!((estimate - jaccard_similarity(&graph, InternalId(u), InternalId(v))).abs() <= config.error_bound(), "{}-{}", u, v);
+            }
+        }
+        assert_eq!(config.cached_signatures(), hubs.len());
+
+        //Opted into by most_similar_pairs: every score within the bound of exact; a threshold nobody reaches gives the plain result
+        let adaptive = most_similar_pairs_with(&graph, &SimilarPairsOptions { adaptive: Some(&config), ..SimilarPairsOptions::top(usize::MAX) });
+        assert!(adaptive.iter().any(|&((u, _), _)| u >= 1000));
+        assert!(adaptive.iter().all(|&((u, v), score)| (score - jaccard_similarity(&graph, InternalId(u), InternalId(v))).abs() <= config.error_bound()));
+        let never = AdaptiveJaccard::new(usize::MAX, 256, 7);
+        assert_eq!(most_similar_pairs_with(&graph, &SimilarPairsOptions { adaptive: Some(&never), ..SimilarPairsOptions::top(50) }), most_similar_pairs(&graph, 50));
+    }
+
+    #[test]
+    fn test_small_world_sigma() { //A ring lattice with 5% of edges rewired keeps its clustering but gains shortcuts, the textbook small world
+        let graph = crate::generate::watts_strogatz(300, 6, 0.05, 3).unwrap();
+        let world = small_world_sigma(&graph, 2, 9).unwrap();
+        assert!(world.c > 5.0 * world.c_rand && world.l < 3.0 * world.l_rand, "{:?}", world);
+        assert!(world.sigma > 1.0, "{:?}", world);
+        assert!((small_world_sigma(&graph, 2, 9).unwrap().sigma - world.sigma).abs() < 1e-9); //Same seed, same random graphs (clustering sums in HashMap order, so only up to rounding)
+        assert!(small_world_sigma(&Graph::from_edges(&[(0, 1)]), 1, 9).unwrap().sigma.is_nan()); //Two nodes cannot close a triangle
+        assert!(small_world_sigma(&graph, 0, 9).is_err());
+    }
+
+    #[test]
+    fn test_most_similar_pairs_approx_finds_twins() { //In a sparse random graph, 500 and 501 share 20 of their 21 friends: LSH with 128 hashes must rank them first, as the exact scan does
+        let mut graph = crate::generate::erdos_renyi(300, 0.02, 8).unwrap();
+        for friend in 0..20 {
+            graph.add_edge(InternalId(500), InternalId(friend));
+            graph.add_edge(InternalId(501), InternalId(friend));
+        }
+        graph.add_edge(InternalId(500), InternalId(20));
+        let exact = most_similar_pairs(&graph, 5);
+        let approx = most_similar_pairs_approx(&graph, 5, 128, 32, 1).unwrap();
+        assert_eq!((exact[0], approx[0]), (((500, 501), 20.0 / 21.0), ((500, 501), 20.0 / 21.0)));
+        assert!(approx.iter().all(|&((u, v), sim)| u < v && sim == jaccard_similarity(&graph, InternalId(u), InternalId(v))));
+        assert_eq!(most_similar_pairs_approx(&graph, 5, 128, 32, 1).unwrap(), approx);
+        assert!(minhash_signatures(&graph, 16, 1).values().all(|s| s.len() == 16));
+        assert!(matches!(most_similar_pairs_approx(&graph, 5, 100, 32, 1), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_geodesic_subgraph() { //Opposite corners of a 4-cycle: both routes, so the whole cycle. On a tree: the unique path only
+        let mut cycle = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 3), (3, 0)] {
+            cycle.add_edge(InternalId(u), InternalId(v));
+        }
+        let geodesic = geodesic_subgraph(&cycle, 0, 2).unwrap();
+        assert_eq!((geodesic.num_nodes(), geodesic.num_edges()), (4, 4));
+
+        let mut tree = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (1, 3), (3, 4), (0, 5)] {
+            tree.add_edge(InternalId(u), InternalId(v));
+        }
+        let geodesic = geodesic_subgraph(&tree, 2, 4).unwrap();
+        let mut nodes: Vec<usize> = geodesic.adjacency().keys().copied().collect();
+        nodes.sort();
+        assert_eq!(nodes, vec![1, 2, 3, 4]);
+        assert_eq!(geodesic.num_edges(), 3);
+        tree.add_edge(InternalId(8), InternalId(9));
+        assert!(geodesic_subgraph(&tree, 0, 9).is_none());
+        assert!(geodesic_subgraph(&tree, 0, 99).is_none());
+    }
+
+    #[test]
+    fn test_graph_jaccard() { //Itself: all 1.0. Disjoint: all 0.0. Dropping 10% of the edges (no node lost): edge Jaccard 0.9
+        let graph = crate::generate::barabasi_albert(100, 3, 2).unwrap();
+        let same = graph_jaccard(&graph, &graph);
+        assert_eq!((same.node_jaccard, same.edge_jaccard, same.common_nodes), (1.0, 1.0, 100));
+        assert!((same.degree_correlation - 1.0).abs() < 1e-12);
+
+        let mut other = Graph::new();
+        for (u, v) in [(1000, 1001), (1001, 1002)] {
+            other.add_edge(InternalId(u), InternalId(v));
+        }
+        let disjoint = graph_jaccard(&graph, &other);
+        assert_eq!((disjoint.node_jaccard, disjoint.edge_jaccard, disjoint.degree_correlation), (0.0, 0.0, 0.0));
+
+        let mut thinned = crate::generate::barabasi_albert(100, 3, 2).unwrap();
+        let mut removed = 0;
+        for (u, v) in crate::graph::sampling::sample_edges(&graph, graph.num_edges(), 5) {
+            if removed * 10 < graph.num_edges() && thinned.adjacency()[&u].len() > 1 && thinned.adjacency()[&v].len() > 1 {
+                removed += thinned.remove_edge(InternalId(u), InternalId(v)) as usize;
+            }
+        }
+        let overlap = graph_jaccard(&graph, &thinned);
+        assert_eq!(overlap.node_jaccard, 1.0);
+        assert!((overlap.edge_jaccard - (graph.num_edges() - removed) as f64 / graph.num_edges() as f64).abs() < 1e-12);
+        assert!((overlap.edge_jaccard - 0.9).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_node_churn() { //Node 1 swaps friend 2 for friend 4, node 3 leaves, node 4 joins and node 0 keeps its friends
+        let (mut old, mut new) = (Graph::new(), Graph::new());
+        for (u, v) in [(0, 1), (0, 2), (1, 2), (1, 3)] {
+            old.add_edge(InternalId(u), InternalId(v));
+        }
+        for (u, v) in [(0, 1), (0, 2), (1, 4)] {
+            new.add_edge(InternalId(u), InternalId(v));
+        }
+        let churn = node_churn(&old, &new);
+        assert_eq!(churn.len(), 5);
+        assert_eq!(churn[&0], ChurnStats { similarity: 1.0, gained: vec![], lost: vec![] });
+        assert_eq!(churn[&1], ChurnStats { similarity: 0.25, gained: vec![4], lost: vec![2, 3] });
+        assert_eq!(churn[&2], ChurnStats { similarity: 0.5, gained: vec![], lost: vec![1] });
+        assert_eq!(churn[&3], ChurnStats { similarity: 0.0, gained: vec![], lost: vec![1] });
+        assert_eq!(churn[&4], ChurnStats { similarity: 0.0, gained: vec![1], lost: vec![] });
+        let top: Vec<usize> = top_churners(&churn, 3).iter().map(|(id, _)| *id).collect();
+        assert_eq!(top, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn test_component_labels() { //Components of sizes 2, 4 and 3 are labeled 2, 0 and 1, matching the order of connected_components
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (10, 11), (11, 12), (12, 13), (20, 21), (21, 22)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let labels = component_labels(&graph);
+        assert_eq!((labels[&0], labels[&10], labels[&20]), (2, 0, 1));
+        for (label, members) in connected_components(&graph).iter().enumerate() {
+            assert!(members.iter().all(|node| labels[node] == label));
+        }
+        assert_eq!(component_of(&graph, InternalId(13)).unwrap(), 0);
+        assert!(matches!(component_of(&graph, InternalId(5)), Err(GraphError::UnknownNode(5))));
+    }
+
+    #[test]
+    fn test_pagerank() { //Triangle: everyone 1/3. Star: the hub first. An isolated node is dangling and the ranks still sum to 1
+        for (node, rank) in pagerank(&small_graph(), 0.85, 100, 1e-12).unwrap() {
+            assert!((rank - 1.0 / 3.0).abs() < 1e-9, "node {} has {}", node, rank);
+        }
+        let mut star = Graph::new();
+        for leaf in 1..=5 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        star.add_node(InternalId(9));
+        let ranks = pagerank(&star, 0.85, 100, 1e-10).unwrap();
+        assert_eq!(ranks[0].0, 0);
+        assert_eq!(ranks.last().unwrap().0, 9);
+        assert!((ranks.iter().map(|&(_, r)| r).sum::<f64>() - 1.0).abs() < 1e-9);
+        let (ranked, excluded) = apply_isolated(&star, ranks, IsolatedNodes::Exclude);
+        assert_eq!((ranked.len(), excluded), (6, 1));
+    }
+
+    #[test]
+    fn test_eigenvector_centrality() { //Triangle: everyone 1/sqrt(3). Star: the hub first, leaves tied (hub at 1/sqrt(2) exactly). Isolated nodes score 0
+        for (node, score) in eigenvector_centrality(&small_graph(), 100, 1e-12) {
+            assert!((score - 1.0 / 3f64.sqrt()).abs() < 1e-9, "node {} has {}", node, score);
+        }
+        let mut star = Graph::new();
+        for leaf in 1..=4 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        star.add_node(InternalId(9));
+        let scores = eigenvector_centrality(&star, 1000, 1e-12);
+        assert_eq!(scores.iter().map(|&(n, _)| n).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 9]);
+        assert!((scores[0].1 - 0.5f64.sqrt()).abs() < 1e-6 && (scores[1].1 - scores[4].1).abs() < 1e-12);
+        assert_eq!(scores[5].1, 0.0);
+        assert!(eigenvector_centrality(&Graph::new(), 10, 1e-9).is_empty());
+    }
+
+    #[test]
+    fn test_pagerank_weighted() { //Unweighted, a triangle ranks everyone equally; a heavy 1-2 edge pulls rank to its ends. Zero-weight nodes are dangling
+        let mut triangle = WeightedGraph::new();
+        for (u, v, w) in [(0, 1, 1.0), (0, 2, 2.0), (1, 2, 6.0)] {
+            triangle.add_edge(u, v, w);
+        }
+        let mut unit = WeightedGraph::new();
+        for (u, v) in [(0, 1), (0, 2), (1, 2)] {
+            unit.add_edge(u, v, 1.0);
+        }
+        let flat = pagerank_weighted(&unit, 0.85, 1e-10).unwrap();
+        assert!(flat.iter().all(|&(_, r)| (r - 1.0 / 3.0).abs() < 1e-9));
+        assert_eq!(flat[0].0, 0); //All tied, so the lowest id comes first
+        let weighted = pagerank_weighted(&triangle, 0.85, 1e-10).unwrap();
+        assert_eq!(weighted.iter().map(|&(n, _)| n).collect::<Vec<_>>(), vec![2, 1, 0]);
+
+        triangle.add_edge(3, 4, 0.0); //Both ends of a zero-weight edge are dangling: they keep the teleport share and the sum stays 1
+        let ranks = pagerank_weighted(&triangle, 0.85, 1e-10).unwrap();
+        assert!((ranks.iter().map(|&(_, r)| r).sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(ranks.iter().all(|&(_, r)| r > 0.0));
+        assert!(pagerank_weighted(&triangle, 1.0, 1e-6).is_err());
+    }
+
+    #[test]
+    fn test_two_hop_reach_approx() { //Estimates stay within 3 standard errors of the radius-2 ball size; sketch memory depends only on the node count
+        let graph = crate::generate::barabasi_albert(400, 3, 11).unwrap();
+        let exact = two_hop_reach(&graph);
+        let approx = two_hop_reach_approx(&graph, 10).unwrap();
+        let bound = 3.0 * HyperLogLog::relative_error(10);
+        for (node, &reach) in &exact {
+            let ball = (reach + graph.adjacency()[node].len() + 1) as f64;
+            assert!((approx[node] - reach as f64).abs() <= bound * ball + 1.0, "node {}: {} vs {}", node, approx[node], reach);
+        }
+        let denser = crate::generate::barabasi_albert(400, 12, 11).unwrap();
+        let sketches = AnalysisKind::TwoHopSketches(10);
+        assert_eq!(graph.estimate_analysis_memory(sketches), denser.estimate_analysis_memory(sketches));
+        assert_eq!(graph.estimate_analysis_memory(sketches), 401 * 1024);
+        assert!(two_hop_reach_approx(&graph, 20).is_err());
+    }
+
+    #[test]
+    fn test_two_hop_reach() { //Star leaves reach the other n - 2 leaves; on a path each node reaches the nodes two steps away
+        let mut star = Graph::new();
+        for leaf in 1..=5 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        let reach = two_hop_reach(&star);
+        assert_eq!(reach[&0], 0);
+        for leaf in 1..=5 {
+            assert_eq!(reach[&leaf], 4);
+        }
+
+        let mut path = Graph::new(); //0-1-2-3-4
+        for i in 0..4 {
+            path.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        let reach = two_hop_reach(&path);
+        assert_eq!([reach[&0], reach[&1], reach[&2], reach[&3], reach[&4]], [1, 1, 2, 1, 1]);
+        assert_eq!(two_hop_reach_of(&path, InternalId(2)).unwrap(), 2);
+        assert!(matches!(two_hop_reach_of(&path, InternalId(9)), Err(GraphError::UnknownNode(9))));
+    }
+
+    #[test]
+    fn test_similarity_graph_groups_equivalent_nodes() { //0,1,2 all know exactly {10,11} and 3,4,5 all know exactly {12,13}: each set becomes one component
+        let mut graph = Graph::new();
+        let groups = [([0, 1, 2], [10, 11]), ([3, 4, 5], [12, 13])];
+        for (members, friends) in groups {
+            for m in members {
+                for f in friends {
+                    graph.add_edge(InternalId(m), InternalId(f));
+                }
+            }
+        }
+        let (similar, stats) = similarity_graph(&graph, 0.99, SimilarityMetric::Jaccard, &SimilarityGraphOptions::default()).unwrap();
+        assert_eq!(similar.num_nodes, graph.adjacency().len());
+        let component_of_0: HashSet<usize> = bfs_distances(&similar, InternalId(0)).into_keys().collect();
+        let component_of_3: HashSet<usize> = bfs_distances(&similar, InternalId(3)).into_keys().collect();
+        assert_eq!(component_of_0, HashSet::from([0, 1, 2]));
+        assert_eq!(component_of_3, HashSet::from([3, 4, 5]));
+        assert_eq!(similar.weight(0, 1), Some(1.0));
+        assert!(stats.candidate_pairs >= stats.edges);
+
+        let capped = SimilarityGraphOptions { max_candidates: Some(1), ..SimilarityGraphOptions::default() };
+        assert!(matches!(similarity_graph(&graph, 0.99, SimilarityMetric::Jaccard, &capped), Err(GraphError::BudgetExceeded { budget: 1, .. })));
+        let tiny = SimilarityGraphOptions { memory: MemoryBudget::bytes(64), ..SimilarityGraphOptions::default() };
+        assert!(matches!(similarity_graph(&graph, 0.99, SimilarityMetric::Jaccard, &tiny), Err(GraphError::BudgetExceeded { budget: 64, .. })));
+        let generous = SimilarityGraphOptions { memory: MemoryBudget::bytes(1 << 20), ..SimilarityGraphOptions::default() };
+        assert!(similarity_graph(&graph, 0.99, SimilarityMetric::Jaccard, &generous).is_ok());
+    }
+
+    #[test]
+    fn test_numeric_assortativity() { //Degree as the metric gives degree assortativity, a constant metric gives NaN, missing values are skipped
+        let graph = barbell_graph();
+        let degrees: HashMap<usize, f64> = graph.all_degrees().into_iter().map(|(n, d)| (n, d as f64)).collect();
+        let expected = degree_assortativity(&graph);
+        assert!((numeric_assortativity(&graph, &degrees) - expected).abs() < 1e-9);
+
+        let constant: HashMap<usize, f64> = graph.adjacency().keys().map(|&n| (n, 3.0)).collect();
+        assert!(numeric_assortativity(&graph, &constant).is_nan());
+
+        let mut partial = degrees.clone();
+        partial.remove(&11); //Pendant 11 has a single edge, to node 0
+        let report = numeric_assortativity_report(&graph, &partial);
+        assert_eq!(report.edges_skipped, 1);
+        assert_eq!(report.edges_used, graph.num_edges() - 1);
+    }
+
+    #[test]
+    fn test_clustering_and_diameter() { //Triangle nodes have clustering 1; adding a pendant to node 2 lowers it to 1/3 and stretches the diameter to 2
+        let triangle = small_graph();
+        assert!((average_clustering(&triangle) - 1.0).abs() < 0.0001);
+        assert_eq!(diameter(&triangle), 1);
+        let graph = triangle_plus_pendant();
+        let clustering = local_clustering(&graph);
+        assert!((clustering[&2] - 1.0 / 3.0).abs() < 0.0001);
+        assert_eq!(clustering[&3], 0.0);
+        assert!((average_clustering(&graph) - (2.0 + 1.0 / 3.0) / 4.0).abs() < 0.0001);
+        assert_eq!(diameter(&graph), 2);
+        assert!((density(&triangle) - 1.0).abs() < 0.0001);
+        assert!((density(&graph) - 4.0 / 6.0).abs() < 0.0001);
+
+        let mut star = Graph::new(); //No two leaves are friends: the hub's clustering is 0, leaves have a single friend
+        for leaf in 1..=4 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        assert_eq!(local_clustering_of(&star, InternalId(0)).unwrap(), 0.0);
+        assert_eq!(local_clustering_of(&star, InternalId(1)).unwrap(), 0.0);
+        assert_eq!((average_clustering(&star), transitivity(&star).unwrap()), (0.0, 0.0));
+        assert_eq!(local_clustering_of(&triangle, InternalId(0)).unwrap(), 1.0);
+        assert!(matches!(local_clustering_of(&star, InternalId(9)), Err(GraphError::UnknownNode(9))));
+    }
+
+    #[test]
+    fn test_triangle_counts() { //Triangle has 1, K4 has 4 (each node in 3), a star has none; per-node counts always sum to 3x the total
+        let triangle = small_graph();
+        assert_eq!(count_triangles(&triangle), 1);
+        assert!(triangles_per_node(&triangle).values().all(|&t| t == 1));
+
+        let mut k4 = Graph::new();
+        for u in 0..4 {
+            for v in u + 1..4 {
+                k4.add_edge(InternalId(u), InternalId(v));
+            }
+        }
+        assert_eq!(count_triangles(&k4), 4);
+        assert!(triangles_per_node(&k4).values().all(|&t| t == 3));
+
+        let mut star = Graph::new();
+        for leaf in 1..=5 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        assert_eq!(count_triangles(&star), 0);
+        assert_eq!(triangles_per_node(&star).len(), 6);
+
+        let graph = crate::generate::barabasi_albert(200, 3, 11).unwrap(); //Cross-check against closed wedges counted from local clustering
+        let per_node = triangles_per_node(&graph);
+        assert_eq!(per_node.values().sum::<usize>(), 3 * count_triangles(&graph));
+        for (node, clustering) in local_clustering(&graph) {
+            let k = graph.adjacency()[&node].len() as f64;
+            assert!((clustering * k * (k - 1.0) / 2.0 - per_node[&node] as f64).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_eccentricity_diameter_radius() { //Path 0-1-2-3-4: the ends are 4 hops from everything, the middle 2; a second component keeps the diameter but has no radius
+        let mut path = Graph::new();
+        for i in 0..4 {
+            path.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        let ecc = eccentricities(&path);
+        assert_eq!((ecc[&0], ecc[&1], ecc[&2], ecc[&4]), (4, 3, 2, 4));
+        assert_eq!(diameter(&path), 4);
+        assert_eq!(radius(&path).unwrap(), 2);
+        path.add_edge(InternalId(10), InternalId(11));
+        assert_eq!(eccentricities(&path)[&10], 1);
+        assert_eq!(diameter(&path), 4);
+        assert!(matches!(radius(&path), Err(GraphError::Disconnected)));
+    }
+
+    #[test]
+    fn test_simrank() { //1 and 2 share the friends 0 and 3, so they score alike against everyone and above an unrelated pair; simrank_single agrees with the table
+        let graph = Graph::from_edges([(0, 1), (0, 2), (1, 3), (2, 3), (3, 4), (4, 5)]);
+        let scores = simrank(&graph, SIMRANK_DECAY, 100, 1e-10).unwrap();
+        let s = |u: usize, v: usize| scores[&(u.min(v), u.max(v))];
+        assert_eq!(scores.len(), 6 * 7 / 2);
+        assert!(scores.values().all(|&score| (0.0..=1.0).contains(&score)));
+        assert!((0..6).all(|node| s(node, node) == 1.0));
+        for other in [0, 3, 4, 5] {
+            assert!((s(1, other) - s(2, other)).abs() < 1e-12, "{}", other);
+        }
+        assert!(s(1, 2) > s(1, 5) && s(1, 2) > s(0, 4));
+        for (u, v) in [(1, 2), (0, 3), (1, 5), (2, 4), (3, 3)] {
+            assert!((simrank_single(&graph, InternalId(u), InternalId(v), SIMRANK_DECAY, 100, 1e-12).unwrap() - s(u, v)).abs() < 1e-9, "{}-{}", u, v);
+        }
+
+        assert!(matches!(simrank_with(&graph, 0.8, 10, 1e-4, 5), Err(GraphError::BudgetExceeded { needed: 6, budget: 5 })));
+        assert!(matches!(simrank(&graph, 1.0, 10, 1e-4), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(simrank_single(&graph, InternalId(0), InternalId(99), 0.8, 10, 1e-4), Err(GraphError::UnknownNode(99))));
+    }
+
+    #[test]
+    fn test_maximal_cliques() { //K4 is one clique; the triangle plus pendant has the triangle and the edge 2-3; on a random graph every clique is complete and maximal
+        let k4 = Graph::from_edges([(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)]);
+        assert_eq!(maximal_cliques(&k4, 1), vec![vec![0, 1, 2, 3]]);
+        assert_eq!(maximal_cliques(&triangle_plus_pendant(), 1), vec![vec![0, 1, 2], vec![2, 3]]);
+        assert_eq!(maximal_cliques(&triangle_plus_pendant(), 3), vec![vec![0, 1, 2]]);
+        assert_eq!(largest_clique(&cliques_with_connector()), vec![0, 1, 2, 3]);
+        assert!(largest_clique(&Graph::new()).is_empty());
+
+        let graph = crate::generate::erdos_renyi(60, 0.3, 2).unwrap();
+        let cliques = maximal_cliques(&graph, 1);
+        for clique in &cliques {
+            assert!(clique.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(clique.iter().all(|&u| clique.iter().all(|&v| u == v || graph.has_edge(u, v))));
+            assert!(graph.adjacency().keys().all(|&w| clique.contains(&w) || clique.iter().any(|&u| !graph.has_edge(u, w))), "{:?} is not maximal", clique);
+        }
+        let edges_covered: HashSet<(usize, usize)> = cliques.iter().flat_map(|clique| clique.iter().flat_map(|&u| clique.iter().map(move |&v| (u, v)))).filter(|(u, v)| u < v).collect();
+        assert_eq!(edges_covered.len(), graph.num_edges());
+        assert_eq!(largest_clique(&graph).len(), cliques[0].len());
+        assert_eq!(maximal_cliques(&graph, 4), cliques.iter().filter(|clique| clique.len() >= 4).cloned().collect::<Vec<_>>());
+        let mut streamed = 0;
+        assert_eq!(for_each_maximal_clique(&graph, 1, |_| {
+            streamed += 1;
+            ControlFlow::<()>::Continue(())
+        }), None);
+        assert_eq!(streamed, cliques.len()); //Each clique is found once, so nothing was removed as a duplicate
+        let mut seen = 0;
+        let fifth = for_each_maximal_clique(&graph, 1, |clique| {
+            seen += 1;
+            if seen == 5 { ControlFlow::Break(clique.to_vec()) } else { ControlFlow::Continue(()) }
+        });
+        assert_eq!((seen, fifth.is_some_and(|clique| cliques.contains(&clique))), (5, true));
+    }
+
+    #[test]
+    fn test_k_core_decomposition() { //Triangle nodes are in the 2-core, the pendant only in the 1-core; K4 plus a tail checks the bucket moves on a bigger case
+        let graph = triangle_plus_pendant();
+        let core = k_core_decomposition(&graph);
+        assert_eq!((core[&0], core[&1], core[&2], core[&3]), (2, 2, 2, 1));
+        let two_core = k_core_subgraph(&graph, 2);
+        assert_eq!(two_core.adjacency().keys().copied().collect::<HashSet<_>>(), HashSet::from([0, 1, 2]));
+        assert_eq!(two_core.num_edges(), 3);
+        assert_eq!(k_core_subgraph(&graph, 3).num_nodes(), 0);
+
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3), (3, 4), (4, 5), (5, 3)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph.add_edge(InternalId(5), InternalId(6));
+        let core = k_core_decomposition(&graph);
+        assert_eq!((0..7).map(|n| core[&n]).collect::<Vec<_>>(), vec![3, 3, 3, 3, 2, 2, 1]);
+
+        let graph = crate::generate::erdos_renyi(120, 0.08, 5).unwrap(); //Every k-core really has minimum degree k, and is the largest such subgraph (peeling it again removes nothing)
+        let core = k_core_decomposition(&graph);
+        for k in 1..=*core.values().max().unwrap() {
+            let sub = k_core_subgraph(&graph, k);
+            assert!(sub.adjacency().values().all(|f| f.len() >= k));
+            assert!(k_core_decomposition(&sub).values().all(|&c| c >= k));
+        }
+    }
+
+    #[test]
+    fn test_diameter_double_sweep() { //Exact on a path and on a tree with a long branch, never above the exact diameter on a random graph
+        let mut tree = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 3), (0, 4), (4, 5), (10, 11)] {
+            tree.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(diameter_double_sweep(&tree), 5);
+        let graph = crate::generate::watts_strogatz(60, 4, 0.1, 3).unwrap();
+        let bound = diameter_double_sweep(&graph);
+        assert!(bound <= diameter(&graph) && bound > 0);
+    }
+
+    #[test]
+    fn test_partition_stability() { //Two cliques split the same way on every seed; a sparse random graph does not. NMI is 1 for relabeled copies
+        let graph = two_cliques_with_bridge();
+        let calls = std::cell::Cell::new(0);
+        let report = partition_stability(&graph, CommunityAlgorithm::Louvain, 5, 1, &|done, total| {
+            assert_eq!(total, 5);
+            calls.set(done);
+        });
+        assert_eq!(calls.get(), 5);
+        assert!((report.mean_nmi - 1.0).abs() < 1e-9 && (report.min_nmi - 1.0).abs() < 1e-9);
+        assert_eq!(report.consensus, (0..8).map(|n| (n, n / 4)).collect::<Partition>());
+        assert!(partition_stability(&graph, CommunityAlgorithm::LabelPropagation, 5, 1, &|_, _| {}).mean_nmi > 0.99);
+
+        let random = crate::generate::erdos_renyi(150, 0.03, 9).unwrap();
+        let noisy = partition_stability(&random, CommunityAlgorithm::LabelPropagation, 5, 1, &|_, _| {});
+        assert!(noisy.mean_nmi < 0.9, "mean NMI {}", noisy.mean_nmi);
+        assert!(noisy.min_nmi <= noisy.mean_nmi);
+
+        let a: Partition = (0..6).map(|n| (n, n / 3)).collect();
+        let relabeled: Partition = (0..6).map(|n| (n, 7 - n / 3)).collect();
+        assert!((normalized_mutual_information(&a, &relabeled) - 1.0).abs() < 1e-12);
+        let crossed: Partition = (0..6).map(|n| (n, n % 2)).collect();
+        assert!(normalized_mutual_information(&a, &crossed) < 0.1);
+    }
+
+    #[test]
+    fn test_articulation_points_and_bridges() { //Path: every inner node cuts and every edge is a bridge; cycle: nothing; two triangles sharing node 2 plus a tail
+        let mut path = Graph::new();
+        for i in 0..5 {
+            path.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        assert_eq!(articulation_points(&path), HashSet::from([1, 2, 3, 4]));
+        assert_eq!(bridges(&path), (0..5).map(|i| (i, i + 1)).collect::<Vec<_>>());
+
+        let mut cycle = Graph::new();
+        for i in 0..6 {
+            cycle.add_edge(InternalId(i), InternalId((i + 1) % 6));
+        }
+        assert!(articulation_points(&cycle).is_empty() && bridges(&cycle).is_empty());
+
+        let mut bowtie = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 2), (4, 5), (20, 21)] {
+            bowtie.add_edge(InternalId(u), InternalId(v));
+        }
+        assert_eq!(articulation_points(&bowtie), HashSet::from([2, 4]));
+        assert_eq!(bridges(&bowtie), vec![(4, 5), (20, 21)]);
+
+        let mut long = Graph::new(); //Deep enough that a recursive DFS would risk the stack
+        for i in 0..200_000 {
+            long.add_edge(InternalId(i), InternalId(i + 1));
+        }
+        assert_eq!(bridges(&long).len(), 200_000);
+    }
+
+    #[test]
+    fn test_evaluate_overlapping() { //Identical covers agree perfectly, disjoint covers not at all, and a hand-computed partial case
+        let cover = vec![HashSet::from([0, 1, 2]), HashSet::from([2, 3, 4]), HashSet::new()];
+        let same = evaluate_overlapping(&cover, &cover);
+        assert!((same.f1 - 1.0).abs() < 1e-9);
+        assert!((same.omega - 1.0).abs() < 1e-9);
+
+        let other = vec![HashSet::from([5, 6]), HashSet::from([7, 8])];
+        assert!(evaluate_overlapping(&cover, &other).f1 < 1e-9);
+
+        //{0,1,2} vs {0,1}: F1 0.8, vs {2,3}: F1 0.4. Pred->truth = 0.8, truth->pred = (0.8 + 0.4) / 2 = 0.6
+        //Omega: 3 of the 6 pairs agree and chance agreement is (3*4 + 3*2) / 36 = 0.5, so omega = 0
+        let pred = vec![HashSet::from([0, 1, 2])];
+        let truth = vec![HashSet::from([0, 1]), HashSet::from([2, 3])];
+        let eval = evaluate_overlapping(&pred, &truth);
+        assert!((eval.f1_pred_to_truth - 0.8).abs() < 1e-9);
+        assert!((eval.f1_truth_to_pred - 0.6).abs() < 1e-9);
+        assert!((eval.f1 - 0.7).abs() < 1e-9);
+        assert!(eval.omega.abs() < 1e-9);
+        assert_eq!(evaluate_overlapping(&[], &truth).f1, 0.0);
+    }
+
+    #[test]
+    fn test_transitivity_sampled_within_interval() { //Ring lattice (each node linked to its 3 nearest on both sides) with 10% of edges rewired at random
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut graph = Graph::new();
+        let n = 600;
+        for u in 0..n {
+            for step in 1..=3 {
+                let v = if rng.gen::<f64>() < 0.1 { rng.gen_range(0..n) } else { (u + step) % n };
+                if v != u {
+                    graph.add_edge(InternalId(u), InternalId(v));
+                }
+            }
+        }
+
+        let exact = transitivity(&graph).unwrap();
+        let sampled = transitivity_sampled(&graph, 20_000, 11);
+        assert!(sampled.lower <= exact && exact <= sampled.upper, "exact {} outside {:?}", exact, sampled);
+        assert!(sampled.upper - sampled.lower < 0.02);
+        assert_eq!(sampled, transitivity_sampled(&graph, 20_000, 11));
+        assert!((transitivity(&small_graph()).unwrap() - 1.0).abs() < 1e-9);
     }
 }
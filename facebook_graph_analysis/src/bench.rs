@@ -0,0 +1,74 @@
+//Module: bench.rs
+//Here we time the stages of a run for "--timing", to report how long each algorithm takes and how that grows with the graph.
+//Timings::time_stage wraps a stage and hands back its value unchanged, record adds a stage timed by someone else (e.g. the scheduled analyses),
+//and printing a Timings gives the summary table: one row per stage in the order they ran, with its share of the total
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timings {
+    stages: Vec<(String, Duration)>, //(stage name, wall time) in the order the stages ran
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn time_stage<T>(&mut self, name: &str, stage: impl FnOnce() -> T) -> T { //Runs the stage and records its wall time; errors are returned like any other value (and the time still counts)
+        let started = Instant::now();
+        let value = stage();
+        self.record(name, started.elapsed());
+        value
+    }
+
+    pub fn record(&mut self, name: &str, elapsed: Duration) {
+        self.stages.push((name.to_string(), elapsed));
+    }
+
+    pub fn stages(&self) -> &[(String, Duration)] {
+        &self.stages
+    }
+
+    pub fn total(&self) -> Duration {
+        self.stages.iter().map(|(_, elapsed)| *elapsed).sum()
+    }
+
+    pub fn seconds(&self) -> Vec<(String, f64)> { //As AnalysisReport::timings
+        self.stages.iter().map(|(name, elapsed)| (name.clone(), elapsed.as_secs_f64())).collect()
+    }
+}
+
+impl fmt::Display for Timings { //e.g. "closeness           1.234s   41.0%", then the total
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total().as_secs_f64();
+        writeln!(f, "{:<18} {:>9} {:>7}", "Stage", "Time", "Share")?;
+        for (name, elapsed) in &self.stages {
+            let share = if total > 0.0 { 100.0 * elapsed.as_secs_f64() / total } else { 0.0 };
+            writeln!(f, "{:<18} {:>8.3}s {:>6.1}%", name, elapsed.as_secs_f64(), share)?;
+        }
+        writeln!(f, "{:<18} {:>8.3}s", "total", total)
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_stage_is_transparent() { //The closure's value comes back unchanged and its (nonzero) time is recorded under its name
+        let mut timings = Timings::new();
+        let value = timings.time_stage("sleep", || {
+            std::thread::sleep(Duration::from_millis(5));
+            vec![1, 2, 3]
+        });
+        let failed: Result<u8, String> = timings.time_stage("fail", || Err(String::from("bad input")));
+        assert_eq!((value, failed), (vec![1, 2, 3], Err(String::from("bad input"))));
+        assert_eq!(timings.stages().iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>(), vec!["sleep", "fail"]);
+        assert!(timings.stages()[0].1 >= Duration::from_millis(5) && timings.total() >= timings.stages()[0].1);
+        timings.record("external", Duration::from_millis(20));
+        let table = timings.to_string();
+        assert!(table.lines().nth(3).unwrap().starts_with("external") && table.lines().last().unwrap().starts_with("total"));
+    }
+}
@@ -0,0 +1,89 @@
+//Module: budget.rs
+//Here we define MemoryBudget: a cap on how much memory the analyses with large tables (similarity matrix, similarity graph, distance matrix) may allocate
+//They estimate what they need before allocating and return GraphError::BudgetExceeded instead of running the machine out of memory
+use std::mem::size_of;
+use crate::error::GraphError;
+use crate::graph::Graph;
+
+pub const MEMORY_BUDGET_ENV: &str = "FGA_MEMORY_BUDGET"; //Environment variable read by MemoryBudget::from_env (same syntax as the --memory-budget flag)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryBudget { //Bytes an analysis may allocate; None means unlimited
+    pub bytes: Option<usize>,
+}
+
+impl MemoryBudget {
+    pub fn unlimited() -> Self {
+        Self { bytes: None }
+    }
+
+    pub fn bytes(bytes: usize) -> Self {
+        Self { bytes: Some(bytes) }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, GraphError> { //Reads sizes like "500000", "512K", "64M" or "2G" (powers of 1024)
+        let text = text.trim();
+        let (digits, multiplier) = match text.char_indices().last() {
+            Some((i, 'K' | 'k')) => (&text[..i], 1 << 10),
+            Some((i, 'M' | 'm')) => (&text[..i], 1 << 20),
+            Some((i, 'G' | 'g')) => (&text[..i], 1 << 30),
+            _ => (text, 1),
+        };
+        let amount: usize = digits
+            .parse()
+            .map_err(|_| GraphError::InvalidParameter(format!("memory budget {:?} is not a size like 512M", text)))?;
+        Ok(Self::bytes(amount.saturating_mul(multiplier)))
+    }
+
+    pub fn from_env() -> Result<Self, GraphError> { //Budget from FGA_MEMORY_BUDGET, unlimited if it is not set
+        match std::env::var(MEMORY_BUDGET_ENV) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Ok(Self::unlimited()),
+        }
+    }
+
+    pub fn check(&self, needed: usize) -> Result<(), GraphError> { //Err(BudgetExceeded) if an allocation of `needed` bytes does not fit
+        match self.bytes {
+            Some(budget) if needed > budget => Err(GraphError::BudgetExceeded { needed, budget }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnalysisKind { //Analyses whose memory use can be estimated up front
+    SimilarityMatrix(usize), //Pairwise matrix over a subset of this many nodes
+    DistanceMatrix,          //All-pairs shortest distances over the whole graph
+    CoNeighborCounts,        //Common-neighbor count of every pair sharing a friend (also what similarity_graph builds)
+    TwoHopSketches(u8),      //HyperLogLog sketches of two_hop_reach_approx at this precision
+}
+
+impl Graph {
+    pub fn estimate_analysis_memory(&self, kind: AnalysisKind) -> usize { //Rough upper bound in bytes of the main table the analysis allocates
+        match kind {
+            AnalysisKind::SimilarityMatrix(nodes) => nodes.saturating_mul(nodes).saturating_mul(size_of::<f64>()),
+            AnalysisKind::DistanceMatrix => self.num_nodes().saturating_mul(self.num_nodes()).saturating_mul(size_of::<usize>()),
+            AnalysisKind::CoNeighborCounts => { //Every friend pair of every node may be a new entry; HashMaps keep spare capacity, hence the factor 2
+                let pairs: usize = self.adjacency().values().map(|f| f.len() * f.len().saturating_sub(1) / 2).sum();
+                pairs.saturating_mul(2 * (size_of::<((usize, usize), u32)>() + 1))
+            }
+            AnalysisKind::TwoHopSketches(precision) => self.num_nodes().saturating_add(1).saturating_mul(1 << precision), //Radius-1 sketch per node plus the ball being merged
+        }
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_check() { //Suffixes multiply by powers of 1024; a budget only fails allocations larger than itself
+        assert_eq!(MemoryBudget::parse("64M").unwrap(), MemoryBudget::bytes(64 << 20));
+        assert_eq!(MemoryBudget::parse("1500").unwrap(), MemoryBudget::bytes(1500));
+        assert!(MemoryBudget::parse("lots").is_err());
+        assert!(MemoryBudget::bytes(100).check(100).is_ok());
+        assert!(matches!(MemoryBudget::bytes(100).check(101), Err(GraphError::BudgetExceeded { needed: 101, budget: 100 })));
+        assert!(MemoryBudget::unlimited().check(usize::MAX).is_ok());
+    }
+}
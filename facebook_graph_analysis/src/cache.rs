@@ -0,0 +1,154 @@
+//Module: cache.rs
+//Here we keep finished analysis results on disk between runs (opt-in with --cache-dir), so re-running on an unchanged input file loads them instead of recomputing
+//Each entry is a small text file keyed by (graph fingerprint, analysis name, parameters); an entry that is corrupt, from another format version
+//or for other parameters is treated as a miss and overwritten by the recomputed result
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use crate::error::GraphError;
+use crate::graph::Graph;
+
+pub const CACHE_FORMAT_VERSION: u32 = 1; //Bump when the file layout or the meaning of a cached result changes, so old entries are ignored
+
+pub trait CacheEntry: Sized { //A result that can be written to and read back from the body of a cache file
+    fn to_lines(&self) -> Vec<String>;
+    fn from_lines(lines: &[&str]) -> Option<Self>; //None if any line is malformed
+}
+
+impl CacheEntry for Vec<(usize, f64)> { //Rankings: "node score" per line, order kept
+    fn to_lines(&self) -> Vec<String> {
+        self.iter().map(|(node, score)| format!("{} {}", node, score)).collect() //{} prints the shortest text that reads back as the same f64
+    }
+
+    fn from_lines(lines: &[&str]) -> Option<Self> {
+        lines.iter().map(|line| {
+            let (node, score) = line.split_once(' ')?;
+            Some((node.parse().ok()?, score.parse().ok()?))
+        }).collect()
+    }
+}
+
+impl CacheEntry for Vec<((usize, usize), f64)> { //Ranked pairs: "u v score" per line
+    fn to_lines(&self) -> Vec<String> {
+        self.iter().map(|((u, v), score)| format!("{} {} {}", u, v, score)).collect()
+    }
+
+    fn from_lines(lines: &[&str]) -> Option<Self> {
+        lines.iter().map(|line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [u, v, score] => Some(((u.parse().ok()?, v.parse().ok()?), score.parse().ok()?)),
+            _ => None,
+        }).collect()
+    }
+}
+
+impl CacheEntry for HashMap<usize, usize> { //Per-node labels such as a partition: "node label" per line, sorted by node
+    fn to_lines(&self) -> Vec<String> {
+        let mut entries: Vec<(&usize, &usize)> = self.iter().collect();
+        entries.sort();
+        entries.into_iter().map(|(node, label)| format!("{} {}", node, label)).collect()
+    }
+
+    fn from_lines(lines: &[&str]) -> Option<Self> {
+        lines.iter().map(|line| {
+            let (node, label) = line.split_once(' ')?;
+            Some((node.parse().ok()?, label.parse().ok()?))
+        }).collect()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ResultCache { //Cache directory plus a log of what this run loaded and computed
+    dir: PathBuf,
+    pub hits: Vec<String>,   //Analyses loaded from disk, in the order they were asked for
+    pub misses: Vec<String>, //Analyses computed (and then stored)
+}
+
+impl ResultCache {
+    pub fn new(dir: &str) -> Result<Self, GraphError> { //Opens (creating if needed) a cache directory
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: PathBuf::from(dir), ..Self::default() })
+    }
+
+    pub fn get_or_compute<T: CacheEntry>(&mut self, graph: &Graph, analysis: &str, params: &str, compute: impl FnOnce() -> T) -> T { //The stored result if there is a valid entry, otherwise compute() (stored for next time; a failed write only costs the cache)
+        let fingerprint = graph.fingerprint();
+        let header = format!("version {}\nanalysis {}\nfingerprint {}\nparams {}", CACHE_FORMAT_VERSION, analysis, fingerprint, params);
+        let path = self.dir.join(format!("{}_{:016x}_{:016x}.cache", analysis, fingerprint, fnv(params)));
+        if let Some(value) = fs::read_to_string(&path).ok().and_then(|text| read_entry(&text, &header)) {
+            self.hits.push(analysis.to_string());
+            return value;
+        }
+        let value = compute();
+        let mut text = header;
+        for line in value.to_lines() {
+            text.push('\n');
+            text.push_str(&line);
+        }
+        let tmp_path = path.with_extension("tmp"); //Written aside and renamed, so a crash never leaves half an entry
+        if fs::write(&tmp_path, text + "\n").and_then(|_| fs::rename(&tmp_path, &path)).is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        self.misses.push(analysis.to_string());
+        value
+    }
+}
+
+fn read_entry<T: CacheEntry>(text: &str, header: &str) -> Option<T> { //The body if the header matches exactly (same version, analysis, graph and parameters)
+    let lines: Vec<&str> = text.lines().collect();
+    let header_lines = header.lines().count();
+    if lines.len() < header_lines || lines[..header_lines].join("\n") != header {
+        return None;
+    }
+    T::from_lines(&lines[header_lines..])
+}
+
+fn fnv(text: &str) -> u64 { //FNV-1a, only used to keep file names short
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::AnalysisResults;
+    use crate::report::{write_json, NumberFormat};
+
+    fn run(graph: &Graph, dir: &str) -> (String, ResultCache) { //Full pipeline with a cache, returning the JSON report and the cache's log
+        let mut results = AnalysisResults::for_graph(graph);
+        results.cache = Some(ResultCache::new(dir).unwrap());
+        results.ensure_all(graph, 5);
+        let mut out = Vec::new();
+        write_json(&results, graph, crate::pipeline::StalePolicy::Warn, &mut out, &NumberFormat::default()).unwrap();
+        (String::from_utf8(out).unwrap(), results.cache.take().unwrap())
+    }
+
+    #[test]
+    fn test_second_run_loads_from_cache() { //First run computes and stores, second loads everything with identical output; corrupt or foreign entries are recomputed
+        let dir = std::env::temp_dir().join(format!("fga_cache_{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let _ = fs::remove_dir_all(dir);
+        let graph = crate::generate::barabasi_albert(80, 2, 3).unwrap();
+
+        let (first, cache) = run(&graph, dir);
+        assert!(cache.hits.is_empty());
+        assert_eq!(cache.misses, vec!["closeness", "betweenness", "similar_pairs", "communities"]);
+        let (second, cache) = run(&graph, dir);
+        assert_eq!(second, first);
+        assert_eq!(cache.hits, vec!["closeness", "betweenness", "similar_pairs", "communities"]);
+        assert!(cache.misses.is_empty());
+
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            if name.starts_with("betweenness") {
+                fs::write(&path, "version 1\nanalysis betweenness\ngarbage").unwrap();
+            } else if name.starts_with("closeness") {
+                let text = fs::read_to_string(&path).unwrap().replacen("version 1", "version 0", 1);
+                fs::write(&path, text).unwrap();
+            }
+        }
+        let (third, cache) = run(&graph, dir);
+        assert_eq!(third, first);
+        assert_eq!(cache.misses, vec!["closeness", "betweenness"]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
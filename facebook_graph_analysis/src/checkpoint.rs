@@ -0,0 +1,276 @@
+//Module: checkpoint.rs
+//Here we make the slow all-sources analyses (closeness, betweenness, similarity scan) resumable: progress is saved to a small text file every few sources,
+//and a restarted run skips the sources that were already finished, as long as the graph fingerprint still matches
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use crate::analysis::{accumulate_betweenness, bfs_distances, finish_betweenness, similar_pairs_from, RankedPairs, SimilarPairsOptions, SimilarPairsStats};
+use crate::error::GraphError;
+use crate::graph::Graph;
+use crate::labels::InternalId;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint { //Saved progress of one analysis on one graph
+    pub analysis: String,             //Which analysis (and parameters) this progress belongs to, e.g. "closeness" or "similar_pairs 5"
+    pub fingerprint: u64,             //Graph::fingerprint of the graph it was computed on
+    pub completed: HashSet<usize>,    //Source nodes that are fully processed
+    pub scores: HashMap<usize, f64>,  //Per-node values (closeness values, or partial betweenness sums)
+    pub pairs: RankedPairs,           //Best pairs so far for the similarity scan
+}
+
+impl Checkpoint {
+    pub fn new(analysis: &str, fingerprint: u64) -> Self {
+        Self {
+            analysis: analysis.to_string(),
+            fingerprint,
+            completed: HashSet::new(),
+            scores: HashMap::new(),
+            pairs: Vec::new(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), GraphError> { //Writes the checkpoint as plain text lines, going through a temporary file so a crash never leaves a half-written checkpoint
+        let mut text = format!("analysis {}\nfingerprint {}\n", self.analysis, self.fingerprint);
+        let mut completed: Vec<usize> = self.completed.iter().copied().collect();
+        completed.sort();
+        for node in completed {
+            text.push_str(&format!("done {}\n", node));
+        }
+        let mut scores: Vec<(usize, f64)> = self.scores.iter().map(|(&n, &v)| (n, v)).collect();
+        scores.sort_by_key(|&(n, _)| n);
+        for (node, value) in scores {
+            text.push_str(&format!("score {} {}\n", node, value));
+        }
+        for ((u, v), value) in &self.pairs {
+            text.push_str(&format!("pair {} {} {}\n", u, v, value));
+        }
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, text)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &str) -> Result<Self, GraphError> { //Reads a checkpoint written by save
+        let text = fs::read_to_string(path)?;
+        let mut checkpoint = Checkpoint::new("", 0);
+        for (index, line) in text.lines().enumerate() {
+            let bad = |line: &str| GraphError::Parse { line: index + 1, content: line.to_string() };
+            let (tag, rest) = line.split_once(' ').ok_or_else(|| bad(line))?;
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            match (tag, fields.as_slice()) {
+                ("analysis", _) => checkpoint.analysis = rest.to_string(),
+                ("fingerprint", [f]) => checkpoint.fingerprint = f.parse().map_err(|_| bad(line))?,
+                ("done", [n]) => {
+                    checkpoint.completed.insert(n.parse().map_err(|_| bad(line))?);
+                }
+                ("score", [n, v]) => {
+                    checkpoint.scores.insert(n.parse().map_err(|_| bad(line))?, v.parse().map_err(|_| bad(line))?);
+                }
+                ("pair", [u, v, s]) => checkpoint.pairs.push((
+                    (u.parse().map_err(|_| bad(line))?, v.parse().map_err(|_| bad(line))?),
+                    s.parse().map_err(|_| bad(line))?,
+                )),
+                _ => return Err(bad(line)),
+            }
+        }
+        Ok(checkpoint)
+    }
+
+    fn resume_or_new(path: &str, analysis: &str, graph: &Graph) -> Result<Self, GraphError> { //Loads the checkpoint at path if there is one, refusing to reuse progress from another graph or analysis
+        let fingerprint = graph.fingerprint();
+        if !Path::new(path).exists() {
+            return Ok(Checkpoint::new(analysis, fingerprint));
+        }
+        let checkpoint = Checkpoint::load(path)?;
+        if checkpoint.fingerprint != fingerprint || checkpoint.analysis != analysis {
+            return Err(GraphError::InvalidParameter(format!(
+                "checkpoint {} was made for \"{}\" on a different graph or analysis; delete it to start over",
+                path, checkpoint.analysis
+            )));
+        }
+        Ok(checkpoint)
+    }
+}
+
+fn sorted_nodes(graph: &Graph) -> Vec<usize> { //Sources are processed in id order so interrupted and uninterrupted runs do the same work in the same order
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    nodes
+}
+
+fn run_checkpointed( //Shared driver: processes every not-yet-completed source, saving every `interval` sources. Returns false if the budget ran out first
+    checkpoint: &mut Checkpoint,
+    path: &str,
+    interval: usize,
+    budget: Option<usize>,
+    nodes: &[usize],
+    mut process: impl FnMut(&mut Checkpoint, usize, usize),
+) -> Result<bool, GraphError> {
+    let interval = interval.max(1);
+    let mut since_save = 0;
+    let mut processed = 0;
+    for (index, &source) in nodes.iter().enumerate() {
+        if checkpoint.completed.contains(&source) {
+            continue;
+        }
+        if budget.is_some_and(|b| processed >= b) {
+            checkpoint.save(path)?;
+            return Ok(false);
+        }
+        process(checkpoint, index, source);
+        checkpoint.completed.insert(source);
+        processed += 1;
+        since_save += 1;
+        if since_save >= interval {
+            checkpoint.save(path)?;
+            since_save = 0;
+        }
+    }
+    checkpoint.save(path)?;
+    Ok(true)
+}
+
+pub fn closeness_centrality_checkpointed(graph: &Graph, checkpoint_path: &str, interval: usize) -> Result<Vec<(usize, f64)>, GraphError> { //Same result as closeness_centrality, but resumable
+    closeness_with_budget(graph, checkpoint_path, interval, None).map(|r| r.unwrap())
+}
+
+pub fn betweenness_centrality_checkpointed(graph: &Graph, checkpoint_path: &str, interval: usize) -> Result<Vec<(usize, f64)>, GraphError> { //Same result as betweenness_centrality, but resumable
+    betweenness_with_budget(graph, checkpoint_path, interval, None).map(|r| r.unwrap())
+}
+
+pub fn most_similar_pairs_checkpointed(graph: &Graph, top_n: usize, checkpoint_path: &str, interval: usize) -> Result<RankedPairs, GraphError> { //Same result as most_similar_pairs, but resumable
+    similar_pairs_with_budget(graph, top_n, checkpoint_path, interval, None).map(|r| r.unwrap())
+}
+
+fn closeness_with_budget(graph: &Graph, path: &str, interval: usize, budget: Option<usize>) -> Result<Option<Vec<(usize, f64)>>, GraphError> {
+    let mut checkpoint = Checkpoint::resume_or_new(path, "closeness", graph)?;
+    let nodes = sorted_nodes(graph);
+    let finished = run_checkpointed(&mut checkpoint, path, interval, budget, &nodes, |cp, _, source| {
+        let dist = bfs_distances(graph, InternalId(source));
+        let sum: usize = dist.values().sum();
+        let closeness = if sum > 0 { (dist.len() - 1) as f64 / sum as f64 } else { 0.0 };
+        cp.scores.insert(source, closeness);
+    })?;
+    if !finished {
+        return Ok(None);
+    }
+    let mut result: Vec<(usize, f64)> = nodes.iter().map(|n| (*n, checkpoint.scores[n])).collect();
+    result.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+    Ok(Some(result))
+}
+
+fn betweenness_with_budget(graph: &Graph, path: &str, interval: usize, budget: Option<usize>) -> Result<Option<Vec<(usize, f64)>>, GraphError> {
+    let mut checkpoint = Checkpoint::resume_or_new(path, "betweenness", graph)?;
+    let nodes = sorted_nodes(graph);
+    let adjacency = graph.sorted_adjacency();
+    let finished = run_checkpointed(&mut checkpoint, path, interval, budget, &nodes, |cp, _, source| {
+        accumulate_betweenness(adjacency, source, &mut cp.scores);
+    })?;
+    if !finished {
+        return Ok(None);
+    }
+    let mut centrality = checkpoint.scores;
+    for &node in &nodes {
+        centrality.entry(node).or_insert(0.0);
+    }
+    Ok(Some(finish_betweenness(centrality)))
+}
+
+fn similar_pairs_with_budget(graph: &Graph, top_n: usize, path: &str, interval: usize, budget: Option<usize>) -> Result<Option<RankedPairs>, GraphError> {
+    //Only the running top N is kept, so the checkpoint stays small. Ties are ordered by pair, so this gives the same answer as sorting every pair at the end
+    let mut checkpoint = Checkpoint::resume_or_new(path, &format!("similar_pairs {}", top_n), graph)?;
+    let nodes = sorted_nodes(graph);
+    let finished = run_checkpointed(&mut checkpoint, path, interval, budget, &nodes, |cp, index, source| {
+        cp.pairs.extend(similar_pairs_from(graph, source, &nodes[index + 1..], &SimilarPairsOptions::top(top_n), &mut SimilarPairsStats::default()));
+        cp.pairs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        cp.pairs.truncate(top_n);
+    })?;
+    Ok(if finished { Some(checkpoint.pairs) } else { None })
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{betweenness_centrality, closeness_centrality};
+
+    fn path_graph(n: usize) -> Graph { //A path 0-1-2-...-(n-1) with a chord 0-2 so there are some shared neighbors
+        let mut graph = Graph::new();
+        let mut edges: Vec<(usize, usize)> = (0..n - 1).map(|i| (i, i + 1)).collect();
+        edges.push((0, 2));
+        for (u, v) in edges {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph
+    }
+
+    fn temp_path(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!("fga_checkpoint_{}_{}.txt", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_closeness_resumes_after_interruption() { //Stop after 3 sources, restart, and compare with an uninterrupted run
+        let graph = path_graph(8);
+        let path = temp_path("closeness");
+        assert!(closeness_with_budget(&graph, &path, 2, Some(3)).unwrap().is_none());
+        assert_eq!(Checkpoint::load(&path).unwrap().completed.len(), 3);
+        let resumed = closeness_centrality_checkpointed(&graph, &path, 2).unwrap();
+        fs::remove_file(&path).unwrap();
+        let uninterrupted = closeness_centrality_checkpointed(&graph, &path, 2).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed, uninterrupted);
+        let plain: HashMap<usize, f64> = closeness_centrality(&graph).into_iter().collect();
+        for (node, value) in resumed {
+            assert!((plain[&node] - value).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_betweenness_resumes_after_interruption() {
+        let graph = path_graph(8);
+        let path = temp_path("betweenness");
+        assert!(betweenness_with_budget(&graph, &path, 1, Some(5)).unwrap().is_none());
+        let resumed = betweenness_centrality_checkpointed(&graph, &path, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        let uninterrupted = betweenness_centrality_checkpointed(&graph, &path, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed, uninterrupted);
+        let plain: HashMap<usize, f64> = betweenness_centrality(&graph).into_iter().collect();
+        for (node, value) in resumed {
+            assert!((plain[&node] - value).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_similar_pairs_resumes_after_interruption() {
+        let graph = path_graph(8);
+        let path = temp_path("pairs");
+        assert!(similar_pairs_with_budget(&graph, 3, &path, 1, Some(2)).unwrap().is_none());
+        let resumed = most_similar_pairs_checkpointed(&graph, 3, &path, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        let uninterrupted = most_similar_pairs_checkpointed(&graph, 3, &path, 1).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(resumed, uninterrupted);
+        assert_eq!(resumed.len(), 3);
+    }
+
+    #[test]
+    fn test_fingerprint_mismatch_is_rejected() { //Progress from one graph must not be reused on another
+        let path = temp_path("mismatch");
+        closeness_with_budget(&path_graph(6), &path, 1, Some(1)).unwrap();
+        let err = closeness_centrality_checkpointed(&path_graph(7), &path, 1).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(err, GraphError::InvalidParameter(_)));
+    }
+
+    #[test]
+    fn test_malformed_checkpoint_is_a_parse_error() {
+        let path = temp_path("malformed");
+        fs::write(&path, "analysis closeness\nfingerprint 12\nscore 1\n").unwrap();
+        let result = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result, Err(GraphError::Parse { line: 3, .. })));
+    }
+}
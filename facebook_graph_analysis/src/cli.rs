@@ -0,0 +1,160 @@
+//Module: cli.rs
+//Here we turn the command line into a Config before anything is loaded, so a typo in a flag stops the run with the usage text instead of being ignored
+//Subcommands read their own flags from the raw arguments; this module only needs to know every flag name and whether it takes a value
+use std::path::PathBuf;
+use crate::error::GraphError;
+use crate::labels::NodeId;
+
+pub const USAGE: &str = "\
+Usage: facebook_graph_analysis [SUBCOMMAND] [OPTIONS]
+
+Default run options:
+  --input FILE               Edge list to analyze (repeatable; default data/facebook_combined.txt)
+  --top N                    Similar pairs, suggestions and PageRank rows to print (default 5)
+  --pairs U:V,U:V            Pairs whose Jaccard similarity is printed (default 0:1,0:2,1:3)
+  --reference ID             Node whose friend list is printed at the end (default 2817)
+  --skip-average-distance    Leave out the all-pairs distance sweep (and the diameter printed with it)
+  --skip-similar-pairs       Leave out the all-pairs similarity scan
+  --exact-ranking            Rank similar pairs by exact fractions, so near ties keep one order on every platform and thread count
+  --bottom N                 Also print the N least central people of the giant component by closeness, degree and PageRank
+  --interactive              Load the graph, then answer queries typed at a prompt (degree, neighbors, distance, path, jaccard, recommend)
+  --largest-component        Analyze only the largest connected component (reports how much of the graph it kept)
+  --string-ids               The input names people by strings (e.g. usernames); reports print those names
+  --compare-random           Print average distance and clustering of a same-size random graph next to the real ones
+  --timing                   Print how long each stage took (and add it to report.json with --out-dir)
+  --progress                 Print how far the distance sweeps, closeness and the similarity scan have got
+  --threads N                Worker threads (1 = sequential, default every core)
+  --precision N              Decimal places for every metric
+
+Subcommands: generate, compare, growth, stress, batch, verify, memory, export-flat, show,
+  node, why-central, rank, explain, path, recommend-all, similarity-graph, stability, communities,
+  export-sketches, sketch-similarity";
+
+const SWITCHES: [&str; 12] = ["--all", "--compare-random", "--exact-ranking", "--interactive", "--largest-component", "--progress", "--streaming-stats", "--strict", "--string-ids", "--timing", "--skip-average-distance", "--skip-similar-pairs"]; //Flags without a value
+
+const VALUED_FLAGS: [&str; 56] = [ //Flags followed by a value, default run and subcommands alike
+    "--algorithm", "--analyses", "--beta", "--betweenness", "--bottom", "--budget", "--cache-dir", "--centrality", "--closeness", "--csv", "--degree-histogram", "--diameter", "--distance-distribution", "--distances",
+    "--dot", "--exclude-nodes", "--export", "--from", "--girvan-newman", "--graphml", "--hub-cap", "--include-only", "--input", "--isolated", "--k", "--limit", "--louvain", "--m",
+    "--max-nodes", "--memory-budget", "--metric", "--metrics", "--min-degree", "--min-size", "--model", "--nodes", "--offset", "--out", "--out-dir",
+    "--p", "--pairs", "--partition", "--precision", "--q", "--reference", "--run-first", "--runs", "--seed", "--similarity", "--steps", "--threads",
+    "--threshold", "--to", "--top", "--walk-length", "--walks-per-node",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config { //Settings of the default run; the defaults reproduce the output described in the write-up
+    pub inputs: Vec<PathBuf>,
+    pub top: usize,
+    pub pairs: Vec<(NodeId, NodeId)>,
+    pub reference: NodeId,
+    pub skip_average_distance: bool,
+    pub skip_similar_pairs: bool,
+    pub exact_ranking: bool, //Rank Jaccard pairs by the exact fraction common / union (SimilarPairsOptions::exact_ranking)
+    pub compare_random: bool, //Also analyze an Erdős–Rényi graph with the same nodes and density, as a null model
+    pub interactive: bool, //Answer queries from stdin instead of running the analyses
+    pub timing: bool, //Print per-stage wall times at the end
+    pub progress: bool, //Report how far the long analyses have got on stderr
+    pub analyze_largest_component: bool, //Drop every node outside the largest connected component before analyzing, so distances and closeness never mix components
+    pub threads: usize, //0 = every core
+    pub precision: Option<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            inputs: vec![PathBuf::from("data/facebook_combined.txt")],
+            top: 5,
+            pairs: vec![(NodeId(0), NodeId(1)), (NodeId(0), NodeId(2)), (NodeId(1), NodeId(3))],
+            reference: NodeId(2817),
+            skip_average_distance: false,
+            skip_similar_pairs: false,
+            exact_ranking: false,
+            compare_random: false,
+            interactive: false,
+            timing: false,
+            progress: false,
+            analyze_largest_component: false,
+            threads: 0,
+            precision: None,
+        }
+    }
+}
+
+pub fn parse_args(args: &[String]) -> Result<Config, GraphError> { //args as from std::env::args (program name first). Unknown flags, missing or malformed values are InvalidParameter
+    let mut config = Config::default();
+    let mut inputs = Vec::new();
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        if !arg.starts_with("--") { //Subcommand names and their positional arguments
+            continue;
+        }
+        if SWITCHES.contains(&arg.as_str()) {
+            config.skip_average_distance |= arg == "--skip-average-distance";
+            config.skip_similar_pairs |= arg == "--skip-similar-pairs";
+            config.exact_ranking |= arg == "--exact-ranking";
+            config.compare_random |= arg == "--compare-random";
+            config.interactive |= arg == "--interactive";
+            config.timing |= arg == "--timing";
+            config.progress |= arg == "--progress";
+            config.analyze_largest_component |= arg == "--largest-component";
+            continue;
+        }
+        if !VALUED_FLAGS.contains(&arg.as_str()) {
+            return Err(GraphError::InvalidParameter(format!("unknown flag {}", arg)));
+        }
+        let value = rest.next().ok_or_else(|| GraphError::InvalidParameter(format!("{} needs a value", arg)))?;
+        let number = || value.parse::<usize>().map_err(|_| GraphError::InvalidParameter(format!("{} needs a whole number, got {:?}", arg, value)));
+        match arg.as_str() {
+            "--input" => inputs.push(PathBuf::from(value)),
+            "--top" => config.top = number()?,
+            "--pairs" => config.pairs = parse_pairs(value)?,
+            "--reference" => config.reference = NodeId(number()?),
+            "--threads" => config.threads = number()?,
+            "--precision" => config.precision = Some(number()?),
+            _ => {} //Read later by the subcommand that uses it
+        }
+    }
+    if !inputs.is_empty() {
+        config.inputs = inputs;
+    }
+    Ok(config)
+}
+
+pub fn parse_pairs(text: &str) -> Result<Vec<(NodeId, NodeId)>, GraphError> { //"u:v,u:v" as input-file ids
+    text.split(',')
+        .map(|pair| {
+            let parsed = pair.split_once(':').and_then(|(u, v)| Some((NodeId(u.trim().parse().ok()?), NodeId(v.trim().parse().ok()?))));
+            parsed.ok_or_else(|| GraphError::InvalidParameter(format!("malformed pair {:?} in --pairs, expected u:v,u:v (e.g. 0:1,0:2)", pair)))
+        })
+        .collect()
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        std::iter::once("prog").chain(line.split_whitespace()).map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() { //No flags gives the write-up run; every flag is read, subcommand words and their flags are let through; bad input is an error
+        assert_eq!(parse_args(&args("")).unwrap(), Config::default());
+        let config = parse_args(&args("--input a.txt --top 10 --pairs 3:4,5:6 --skip-similar-pairs --input b.txt --threads 2 --reference 7")).unwrap();
+        assert_eq!(config.inputs, vec![PathBuf::from("a.txt"), PathBuf::from("b.txt")]);
+        assert_eq!((config.top, config.threads, config.reference), (10, 2, NodeId(7)));
+        assert_eq!(config.pairs, vec![(NodeId(3), NodeId(4)), (NodeId(5), NodeId(6))]);
+        assert!(config.skip_similar_pairs && !config.skip_average_distance && !config.compare_random);
+        assert!(parse_args(&args("--compare-random")).unwrap().compare_random);
+        assert!(parse_args(&args("--largest-component")).unwrap().analyze_largest_component && !config.analyze_largest_component);
+        assert!(parse_args(&args("--interactive")).unwrap().interactive && !config.interactive);
+        assert!(parse_args(&args("--timing")).unwrap().timing && !config.timing);
+        assert!(parse_args(&args("--exact-ranking")).unwrap().exact_ranking && !config.exact_ranking);
+        assert!(parse_args(&args("--progress")).unwrap().progress && !config.progress);
+        assert_eq!(parse_args(&args("path --from 1 --to 2 --dot out.dot --strict")).unwrap().top, 5);
+
+        for bad in ["--tpo 3", "--top", "--top many", "--pairs 1-2", "--pairs 1:2,3", "--pairs 1:x", "node 5 --verbose"] {
+            assert!(matches!(parse_args(&args(bad)), Err(GraphError::InvalidParameter(_))), "{} should be rejected", bad);
+        }
+    }
+}
@@ -0,0 +1,633 @@
+//Module: community.rs
+//Here we detect communities (groups of friends that are more connected to each other than to the rest of the network)
+//A partition is stored as a map from node id to community id, with community ids numbered 0, 1, 2, ...
+use std::cmp::Ordering;
+use std::collections::{hash_map::Entry, BinaryHeap, HashMap, HashSet};
+use std::fs;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::analysis::edge_betweenness_of;
+use crate::error::GraphError;
+use crate::graph::{Graph, GraphRead};
+use crate::weighted::WeightedGraph;
+use crate::labels::InternalId;
+
+pub type Partition = HashMap<usize, usize>;
+
+const MAX_LABEL_ROUNDS: usize = 100; //Safety cap, label propagation normally settles after a handful of rounds
+
+pub fn label_propagation(graph: &Graph, seed: u64) -> Partition { //Detects communities by repeatedly giving each node the most common label among its friends until nothing changes
+    propagate_labels(graph, seed)
+}
+
+pub fn label_propagation_weighted(graph: &WeightedGraph, seed: u64) -> Partition { //Same as label_propagation, but each neighbor's vote counts with the weight of its edge
+    propagate_labels(graph, seed)
+}
+
+fn propagate_labels<G: GraphRead>(graph: &G, seed: u64) -> Partition { //Shared label propagation: votes are edge weights, so an unweighted graph (all weights 1.0) gives plain majority voting
+    //Nodes are visited in a shuffled order each round and ties are broken at random, both driven by `seed` so a run can be repeated exactly
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut nodes: Vec<usize> = graph.nodes().collect();
+    nodes.sort();
+    let mut labels: HashMap<usize, usize> = nodes.iter().map(|&n| (n, n)).collect();
+    let mut order = nodes.clone();
+
+    for _ in 0..MAX_LABEL_ROUNDS {
+        let mut changed = false;
+        order.shuffle(&mut rng);
+        for &node in &order {
+            let mut votes: HashMap<usize, f64> = HashMap::new();
+            for neighbor in graph.neighbors(node) {
+                *votes.entry(labels[&neighbor]).or_insert(0.0) += graph.edge_weight(node, neighbor);
+            }
+            let Some(best) = votes.values().copied().reduce(f64::max) else {
+                continue; //Isolated node keeps its own label
+            };
+            let current = labels[&node];
+            if votes.get(&current) == Some(&best) {
+                continue; //Current label is already among the most common ones, keeping it helps the process settle
+            }
+            let mut winners: Vec<usize> = votes.iter().filter(|&(_, &v)| v == best).map(|(&l, _)| l).collect();
+            winners.sort(); //HashMap order is not deterministic, so sort before picking at random
+            labels.insert(node, *winners.choose(&mut rng).unwrap());
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    renumber(&nodes, &labels)
+}
+
+fn renumber(sorted_nodes: &[usize], labels: &HashMap<usize, usize>) -> Partition { //Turns arbitrary labels into community ids 0, 1, 2, ... in order of each community's smallest node
+    let mut ids: HashMap<usize, usize> = HashMap::new();
+    sorted_nodes
+        .iter()
+        .map(|node| {
+            let next = ids.len();
+            let id = *ids.entry(labels[node]).or_insert(next);
+            (*node, id)
+        })
+        .collect()
+}
+
+pub fn modularity(graph: &Graph, partition: &Partition) -> f64 { //Newman's modularity Q: fraction of edges inside communities minus what random wiring with the same degrees would give
+    modularity_of(graph, partition)
+}
+
+pub fn modularity_weighted(graph: &WeightedGraph, partition: &Partition) -> f64 { //Weighted modularity: edge weights replace edge counts and node strengths replace degrees
+    modularity_of(graph, partition)
+}
+
+fn modularity_of<G: GraphRead>(graph: &G, partition: &Partition) -> f64 { //Shared modularity computation over edge weights (1.0 per edge when unweighted)
+    let mut two_m = 0.0;
+    let mut inside: HashMap<usize, f64> = HashMap::new(); //community -> weight of edge endpoints inside it (each internal edge counted from both ends)
+    let mut degree_sum: HashMap<usize, f64> = HashMap::new();
+    for node in graph.nodes() {
+        let strength = graph.strength(node);
+        two_m += strength;
+        let Some(&c) = partition.get(&node) else { continue };
+        *degree_sum.entry(c).or_insert(0.0) += strength;
+        let internal: f64 = graph.neighbors(node).filter(|n| partition.get(n) == Some(&c)).map(|n| graph.edge_weight(node, n)).sum();
+        *inside.entry(c).or_insert(0.0) += internal;
+    }
+    if two_m == 0.0 {
+        return 0.0;
+    }
+    degree_sum
+        .iter()
+        .map(|(c, &d)| inside.get(c).copied().unwrap_or(0.0) / two_m - (d / two_m).powi(2))
+        .sum()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HeapEntry { //Candidate merge of communities i and j that would change modularity by dq
+    dq: f64,
+    i: usize,
+    j: usize,
+}
+
+impl Eq for HeapEntry {}
+
+impl Ord for HeapEntry { //Largest dq first, ties broken by smallest community ids so the merge order is deterministic
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dq
+            .total_cmp(&other.dq)
+            .then_with(|| other.i.cmp(&self.i))
+            .then_with(|| other.j.cmp(&self.j))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+pub fn greedy_modularity(graph: &Graph) -> (Vec<Partition>, Vec<f64>) { //Clauset–Newman–Moore: start from singletons and repeatedly merge the two connected communities with the best modularity gain
+    //Returns the partition after every merge step (step 0 = all singletons) together with its modularity, i.e. the full merge dendrogram
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let two_m: f64 = graph.adjacency().values().map(|n| n.len()).sum::<usize>() as f64;
+    let mut labels: HashMap<usize, usize> = nodes.iter().map(|&n| (n, n)).collect();
+    let mut partitions = vec![renumber(&nodes, &labels)];
+    if two_m == 0.0 {
+        return (partitions, vec![0.0]);
+    }
+
+    //a[c] = fraction of edge endpoints in community c, dq[c][d] = modularity change if c and d merged (only for connected pairs)
+    let mut a: HashMap<usize, f64> = nodes.iter().map(|&n| (n, graph.adjacency()[&n].len() as f64 / two_m)).collect();
+    let mut members: HashMap<usize, Vec<usize>> = nodes.iter().map(|&n| (n, vec![n])).collect();
+    let mut dq: HashMap<usize, HashMap<usize, f64>> = HashMap::new();
+    let mut heap = BinaryHeap::new();
+    for &u in &nodes {
+        let row = dq.entry(u).or_default();
+        for &v in &graph.adjacency()[&u] {
+            let gain = 2.0 * (1.0 / two_m - a[&u] * a[&v]);
+            row.insert(v, gain);
+            if u < v {
+                heap.push(HeapEntry { dq: gain, i: u, j: v });
+            }
+        }
+    }
+    let mut q: f64 = -a.values().map(|x| x * x).sum::<f64>();
+    let mut modularities = vec![q];
+
+    while let Some(HeapEntry { dq: gain, i, j }) = heap.pop() {
+        if dq.get(&i).and_then(|row| row.get(&j)) != Some(&gain) {
+            continue; //Stale entry: one of the communities was merged or the gain changed since it was pushed
+        }
+        //Merge the community with fewer neighbors into the other one
+        let (keep, gone) = if dq[&i].len() >= dq[&j].len() { (i, j) } else { (j, i) };
+        let keep_row = dq.remove(&keep).unwrap();
+        let gone_row = dq.remove(&gone).unwrap();
+        let (a_keep, a_gone) = (a[&keep], a[&gone]);
+        let mut merged_row: HashMap<usize, f64> = HashMap::new();
+        for (&k, &value) in keep_row.iter().filter(|(&k, _)| k != gone) {
+            let updated = match gone_row.get(&k) {
+                Some(&other) => value + other,
+                None => value - 2.0 * a_gone * a[&k],
+            };
+            merged_row.insert(k, updated);
+        }
+        for (&k, &value) in gone_row.iter().filter(|(&k, _)| k != keep && !keep_row.contains_key(&k)) {
+            merged_row.insert(k, value - 2.0 * a_keep * a[&k]);
+        }
+        for (&k, &value) in &merged_row {
+            let row = dq.get_mut(&k).unwrap();
+            row.remove(&gone);
+            row.insert(keep, value);
+            heap.push(HeapEntry { dq: value, i: keep.min(k), j: keep.max(k) });
+        }
+        dq.insert(keep, merged_row);
+        a.insert(keep, a_keep + a_gone);
+        a.remove(&gone);
+
+        let moved = members.remove(&gone).unwrap();
+        for &node in &moved {
+            labels.insert(node, keep);
+        }
+        members.get_mut(&keep).unwrap().extend(moved);
+
+        q += gain;
+        partitions.push(renumber(&nodes, &labels));
+        modularities.push(q);
+    }
+
+    (partitions, modularities)
+}
+
+pub fn girvan_newman(graph: &Graph, target_communities: usize) -> Partition { //Girvan–Newman: keep removing the friendship with the highest edge betweenness until the graph falls into target_communities components
+    //(or no edges are left), and return those components. Edge betweenness is recomputed after every removal, so this is O(m^2 n): meant for subgraphs of a few hundred nodes
+    girvan_newman_dendrogram(graph, target_communities).0.pop().unwrap()
+}
+
+pub fn girvan_newman_dendrogram(graph: &Graph, target_communities: usize) -> (Vec<Partition>, Vec<(usize, usize)>) { //girvan_newman's splits: the components before any removal and after each removal that split one,
+    //ending with the returned partition, plus every removed edge (u < v) in removal order. Ties between equally central edges go to the smallest pair
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let mut adjacency = graph.sorted_adjacency().clone();
+    let mut partitions = vec![components_of(&nodes, &adjacency)];
+    let mut removed = Vec::new();
+    while community_sizes(partitions.last().unwrap()).len() < target_communities {
+        let scores = edge_betweenness_of(&adjacency);
+        let Some((&(u, v), _)) = scores.iter().max_by(|a, b| a.1.partial_cmp(b.1).unwrap().then(b.0.cmp(a.0))) else {
+            break; //No edges left
+        };
+        adjacency.get_mut(&u).unwrap().retain(|&w| w != v);
+        adjacency.get_mut(&v).unwrap().retain(|&w| w != u);
+        removed.push((u, v));
+        let components = components_of(&nodes, &adjacency);
+        if community_sizes(&components).len() > community_sizes(partitions.last().unwrap()).len() {
+            partitions.push(components);
+        }
+    }
+    (partitions, removed)
+}
+
+fn components_of(sorted_nodes: &[usize], adjacency: &HashMap<usize, Vec<usize>>) -> Partition { //Connected components as a partition, numbered by smallest member
+    let mut labels: HashMap<usize, usize> = HashMap::new();
+    for &root in sorted_nodes {
+        if labels.contains_key(&root) {
+            continue;
+        }
+        labels.insert(root, root);
+        let mut stack = vec![root];
+        while let Some(node) = stack.pop() {
+            for &friend in &adjacency[&node] {
+                if let Entry::Vacant(slot) = labels.entry(friend) {
+                    slot.insert(root);
+                    stack.push(friend);
+                }
+            }
+        }
+    }
+    renumber(sorted_nodes, &labels)
+}
+
+const MODULARITY_EPSILON: f64 = 1e-12; //Louvain only moves a node for a gain above this, so rounding noise cannot make it cycle
+
+pub fn louvain(graph: &Graph, resolution: f64) -> (Partition, f64) { //Louvain method: move single nodes to the neighboring community with the best gain, merge each community into one node, repeat until no move helps
+    //Returns the partition and its modularity (plain Newman modularity, as modularity() would score it). Resolution above 1 favors smaller communities,
+    //below 1 larger ones. Nodes are always visited in id order and ties keep the current community or go to the lowest id, so the result is deterministic
+    louvain_from(graph, resolution, None)
+}
+
+pub fn louvain_seeded(graph: &Graph, resolution: f64, seed: u64) -> (Partition, f64) { //Same as louvain, visiting the nodes of every level in a shuffled order (same seed = same partition)
+    louvain_from(graph, resolution, Some(StdRng::seed_from_u64(seed)))
+}
+
+fn louvain_from(graph: &Graph, resolution: f64, mut rng: Option<StdRng>) -> (Partition, f64) {
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+    let index: HashMap<usize, usize> = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+    //The graph being optimized: level 0 is the input, each later level has one node per community of the previous one
+    let mut adjacency: Vec<Vec<(usize, f64)>> = nodes.iter().map(|n| graph.adjacency()[n].iter().map(|f| (index[f], 1.0)).collect()).collect();
+    let mut self_loops = vec![0.0; nodes.len()]; //Weight of the edges already merged inside each node
+    let mut membership: Vec<usize> = (0..nodes.len()).collect(); //Input node -> node of the current level
+    let two_m = graph.adjacency().values().map(|f| f.len()).sum::<usize>() as f64;
+    if two_m == 0.0 { //No edges: every node stays on its own
+        return (renumber(&nodes, &nodes.iter().map(|&n| (n, n)).collect()), 0.0);
+    }
+
+    loop {
+        let n = adjacency.len();
+        let degree: Vec<f64> = (0..n).map(|i| adjacency[i].iter().map(|&(_, w)| w).sum::<f64>() + 2.0 * self_loops[i]).collect();
+        let mut community: Vec<usize> = (0..n).collect();
+        let mut total = degree.clone(); //Sum of member degrees per community
+        let mut moved = false;
+        let mut order: Vec<usize> = (0..n).collect();
+        if let Some(rng) = rng.as_mut() {
+            order.shuffle(rng);
+        }
+        loop { //Local moves: sweep until a full pass changes nothing
+            let mut changed = false;
+            for &i in &order {
+                let current = community[i];
+                total[current] -= degree[i];
+                let mut links: HashMap<usize, f64> = HashMap::new(); //Edge weight from i into each neighboring community
+                for &(j, w) in &adjacency[i] {
+                    *links.entry(community[j]).or_insert(0.0) += w;
+                }
+                let gain = |c: usize, w: f64| w - resolution * total[c] * degree[i] / two_m;
+                let (mut best, mut best_gain) = (current, gain(current, links.get(&current).copied().unwrap_or(0.0)));
+                let mut candidates: Vec<(usize, f64)> = links.into_iter().collect();
+                candidates.sort_by_key(|&(c, _)| c);
+                for (c, w) in candidates {
+                    if gain(c, w) > best_gain + MODULARITY_EPSILON {
+                        (best, best_gain) = (c, gain(c, w));
+                    }
+                }
+                total[best] += degree[i];
+                if best != current {
+                    community[i] = best;
+                    changed = true;
+                    moved = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        if !moved {
+            break;
+        }
+        //Aggregation: every community becomes one node, edges between communities are summed, edges inside them become self-loop weight
+        let mut renumbered: HashMap<usize, usize> = HashMap::new();
+        for &c in &community {
+            let next = renumbered.len();
+            renumbered.entry(c).or_insert(next);
+        }
+        let level_of = |i: usize| renumbered[&community[i]];
+        let mut merged: Vec<HashMap<usize, f64>> = vec![HashMap::new(); renumbered.len()];
+        let mut merged_loops = vec![0.0; renumbered.len()];
+        for i in 0..n {
+            let ci = level_of(i);
+            merged_loops[ci] += self_loops[i];
+            for &(j, w) in &adjacency[i] {
+                let cj = level_of(j);
+                if ci == cj {
+                    merged_loops[ci] += w / 2.0; //Seen once from each end
+                } else {
+                    *merged[ci].entry(cj).or_insert(0.0) += w;
+                }
+            }
+        }
+        adjacency = merged.into_iter().map(|row| {
+            let mut row: Vec<(usize, f64)> = row.into_iter().collect();
+            row.sort_by_key(|&(j, _)| j);
+            row
+        }).collect();
+        self_loops = merged_loops;
+        membership.iter_mut().for_each(|m| *m = level_of(*m));
+    }
+
+    let labels: HashMap<usize, usize> = nodes.iter().zip(&membership).map(|(&node, &m)| (node, m)).collect();
+    let partition = renumber(&nodes, &labels);
+    let score = modularity(graph, &partition);
+    (partition, score)
+}
+
+pub fn community_sizes(partition: &Partition) -> Vec<(usize, usize)> { //Returns (community id, number of members), largest communities first
+    let mut sizes: HashMap<usize, usize> = HashMap::new();
+    for &community in partition.values() {
+        *sizes.entry(community).or_insert(0) += 1;
+    }
+    let mut sizes: Vec<(usize, usize)> = sizes.into_iter().collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    sizes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionFormat {
+    NodeCommunity, //One "node community" pair per line
+    Circles,       //SNAP circles: one community per line, "name<TAB>member<TAB>member..."
+    Auto,          //Guess from the first data line: a non-numeric first token or more than two tokens means circles
+}
+
+pub fn read_circles(path: &str) -> Result<Vec<HashSet<usize>>, GraphError> { //Reads SNAP circles; a node may belong to several circles. A leading non-numeric token on a line is the circle name and is skipped
+    let text = fs::read_to_string(path)?;
+    let mut circles = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut tokens = line.split_whitespace().peekable();
+        if tokens.peek().is_some_and(|t| t.parse::<usize>().is_err()) {
+            tokens.next();
+        }
+        let members = tokens
+            .map(|t| t.parse::<usize>())
+            .collect::<Result<HashSet<usize>, _>>()
+            .map_err(|_| GraphError::Parse { line: index + 1, content: line.to_string() })?;
+        circles.push(members);
+    }
+    Ok(circles)
+}
+
+pub fn read_partition(path: &str, format: PartitionFormat) -> Result<Partition, GraphError> { //Reads a partition produced by another tool. With circles, a node in several circles is assigned to the first one listed
+    let text = fs::read_to_string(path)?;
+    let format = match format {
+        PartitionFormat::Auto => detect_format(&text),
+        other => other,
+    };
+    if format == PartitionFormat::Circles {
+        let mut partition = Partition::new();
+        for (circle, members) in read_circles(path)?.into_iter().enumerate() {
+            for node in members {
+                partition.entry(node).or_insert(circle);
+            }
+        }
+        return Ok(partition);
+    }
+
+    let mut partition = Partition::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = || GraphError::Parse { line: index + 1, content: line.to_string() };
+        let fields: Vec<usize> = line.split_whitespace().map(|t| t.parse()).collect::<Result<_, _>>().map_err(|_| bad())?;
+        match fields.as_slice() {
+            [node, community] => {
+                partition.insert(*node, *community);
+            }
+            _ => return Err(bad()),
+        }
+    }
+    Ok(partition)
+}
+
+fn detect_format(text: &str) -> PartitionFormat {
+    let first = text.lines().find(|l| !l.trim().is_empty() && !l.starts_with('#')).unwrap_or("");
+    let tokens: Vec<&str> = first.split_whitespace().collect();
+    if tokens.len() == 2 && tokens.iter().all(|t| t.parse::<usize>().is_ok()) {
+        PartitionFormat::NodeCommunity
+    } else {
+        PartitionFormat::Circles
+    }
+}
+
+pub fn unknown_partition_nodes(graph: &Graph, partition: &Partition) -> Vec<usize> { //Node ids in a partition that are not in the graph. They are kept in the partition, this only reports them
+    let mut unknown: Vec<usize> = partition.keys().copied().filter(|n| !graph.adjacency().contains_key(n)).collect();
+    unknown.sort();
+    unknown
+}
+
+pub fn with_original_ids(graph: &Graph, partition: &Partition) -> Partition { //The partition keyed by input-file ids instead of internal ids, e.g. before write_partition so the file matches the edge list
+    partition.iter().map(|(&node, &community)| (graph.original_id(InternalId(node)).0, community)).collect()
+}
+
+pub fn write_partition(path: &str, partition: &Partition) -> Result<(), GraphError> { //Writes "node community" lines sorted by node id
+    let mut entries: Vec<(usize, usize)> = partition.iter().map(|(&n, &c)| (n, c)).collect();
+    entries.sort();
+    let text: String = entries.iter().map(|(n, c)| format!("{} {}\n", n, c)).collect();
+    fs::write(path, text)?;
+    Ok(())
+}
+
+pub fn write_circles(path: &str, circles: &[HashSet<usize>]) -> Result<(), GraphError> { //Writes SNAP circles, named circle0, circle1, ... with members sorted
+    let mut text = String::new();
+    for (i, circle) in circles.iter().enumerate() {
+        let mut members: Vec<usize> = circle.iter().copied().collect();
+        members.sort();
+        text.push_str(&format!("circle{}", i));
+        for m in members {
+            text.push_str(&format!("\t{}", m));
+        }
+        text.push('\n');
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::two_cliques_with_bridge;
+
+    fn weighted_copy(graph: &Graph, heavy: (usize, usize), weight: f64) -> WeightedGraph { //Same edges with weight 1.0, except one heavy edge
+        let mut weighted = WeightedGraph::new();
+        for (&u, neighbors) in graph.adjacency() {
+            for &v in neighbors.iter().filter(|&&v| u < v) {
+                weighted.add_edge(u, v, if (u, v) == heavy { weight } else { 1.0 });
+            }
+        }
+        weighted
+    }
+
+    #[test]
+    fn test_heavy_edge_pulls_nodes_together_only_when_weighted() { //The bridge 3-4 outweighs each clique's three votes when it carries weight 10
+        let graph = two_cliques_with_bridge();
+        let unweighted = label_propagation(&graph, 7);
+        assert_ne!(unweighted[&3], unweighted[&4]);
+        let weighted = label_propagation_weighted(&weighted_copy(&graph, (3, 4), 10.0), 7);
+        assert_eq!(weighted[&3], weighted[&4]);
+
+        //With every weight 1.0 the weighted modularity is the ordinary one; a heavy bridge makes the clique split worse
+        assert!((modularity_weighted(&weighted_copy(&graph, (3, 4), 1.0), &unweighted) - modularity(&graph, &unweighted)).abs() < 1e-12);
+        assert!(modularity_weighted(&weighted_copy(&graph, (3, 4), 10.0), &unweighted) < modularity(&graph, &unweighted));
+    }
+
+    #[test]
+    fn test_label_propagation_finds_cliques() { //Each clique should become one community
+        let partition = label_propagation(&two_cliques_with_bridge(), 7);
+        for node in 0..4 {
+            assert_eq!(partition[&node], 0);
+        }
+        for node in 4..8 {
+            assert_eq!(partition[&node], 1);
+        }
+        assert_eq!(community_sizes(&partition), vec![(0, 4), (1, 4)]);
+    }
+
+    #[test]
+    fn test_label_propagation_is_reproducible() { //Same seed, same partition
+        let graph = two_cliques_with_bridge();
+        assert_eq!(label_propagation(&graph, 42), label_propagation(&graph, 42));
+    }
+
+    fn ring_of_cliques(cliques: usize, size: usize) -> Graph { //`cliques` complete graphs of `size` nodes, consecutive cliques joined by one edge into a ring
+        let mut graph = Graph::new();
+        let mut add = |u: usize, v: usize| {
+            graph.add_edge(InternalId(u), InternalId(v));
+        };
+        for c in 0..cliques {
+            let base = c * size;
+            for i in 0..size {
+                for j in i + 1..size {
+                    add(base + i, base + j);
+                }
+            }
+            add(base + size - 1, ((c + 1) % cliques) * size);
+        }
+        graph
+    }
+
+    #[test]
+    fn test_greedy_modularity_recovers_cliques() { //The best step of the dendrogram is exactly the cliques, and the curve rises then falls
+        let graph = ring_of_cliques(4, 4);
+        let (partitions, modularities) = greedy_modularity(&graph);
+        assert_eq!(partitions.len(), modularities.len());
+        assert_eq!(partitions.len(), 16); //15 merges take 16 singletons down to one community
+        let best = (0..modularities.len()).max_by(|&x, &y| modularities[x].total_cmp(&modularities[y])).unwrap();
+        let best_partition = &partitions[best];
+        for node in 0..16 {
+            assert_eq!(best_partition[&node], best_partition[&(node / 4 * 4)]);
+        }
+        assert_eq!(community_sizes(best_partition).len(), 4);
+        for step in 1..modularities.len() {
+            if step <= best {
+                assert!(modularities[step] >= modularities[step - 1] - 1e-12);
+            } else {
+                assert!(modularities[step] <= modularities[step - 1] + 1e-12);
+            }
+        }
+        assert!((modularities[best] - modularity(&graph, best_partition)).abs() < 1e-9);
+
+        let barbell = two_cliques_with_bridge(); //The best step is Louvain's partition, with the same modularity
+        let (partitions, modularities) = greedy_modularity(&barbell);
+        let best = (0..modularities.len()).max_by(|&x, &y| modularities[x].total_cmp(&modularities[y])).unwrap();
+        let (partition, q) = louvain(&barbell, 1.0);
+        assert_eq!(community_sizes(&partitions[best]), community_sizes(&partition));
+        assert!((0..8).all(|u| (0..8).all(|v| (partitions[best][&u] == partitions[best][&v]) == (partition[&u] == partition[&v]))));
+        assert!((modularities[best] - q).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_louvain_separates_cliques() { //Barbell of two 4-cliques: one community per clique; a ring of cliques needs the aggregation phase to settle on whole cliques
+        let graph = two_cliques_with_bridge();
+        let (partition, q) = louvain(&graph, 1.0);
+        assert_eq!(partition, (0..8).map(|n| (n, n / 4)).collect::<Partition>());
+        assert!(q > 0.0);
+        assert!((q - modularity(&graph, &partition)).abs() < 1e-12);
+        assert_eq!(louvain(&graph, 1.0), (partition, q)); //Deterministic
+
+        let ring = ring_of_cliques(6, 5);
+        let (partition, q) = louvain(&ring, 1.0);
+        assert_eq!(community_sizes(&partition).len(), 6);
+        assert!(q >= greedy_modularity(&ring).1.into_iter().fold(f64::MIN, f64::max) - 1e-9);
+        assert_eq!(community_sizes(&louvain(&ring, 0.01).0).len(), 1); //A tiny resolution rewards one big community
+        assert_eq!(louvain(&Graph::new(), 1.0), (Partition::new(), 0.0));
+    }
+
+    #[test]
+    fn test_girvan_newman_cuts_the_bridge_first() { //Two triangles joined by 2-3: the bridge goes first and leaves exactly the two triangles; asking for more keeps cutting
+        let graph = Graph::from_edges(&[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3)]);
+        let (partitions, removed) = girvan_newman_dendrogram(&graph, 2);
+        assert_eq!(removed, vec![(2, 3)]);
+        assert_eq!(partitions.len(), 2);
+        assert_eq!(girvan_newman(&graph, 2), (0..6).map(|n| (n, n / 3)).collect::<Partition>());
+        assert_eq!(girvan_newman(&graph, 1), (0..6).map(|n| (n, 0)).collect::<Partition>());
+        let (partitions, removed) = girvan_newman_dendrogram(&graph, 100); //More than there are nodes: stops once every edge is gone
+        assert_eq!((removed.len(), community_sizes(partitions.last().unwrap()).len()), (7, 6));
+        assert!(partitions.windows(2).all(|pair| community_sizes(&pair[0]).len() < community_sizes(&pair[1]).len()));
+    }
+
+    #[test]
+    fn test_modularity_of_singletons_and_whole_graph() {
+        let graph = two_cliques_with_bridge();
+        let whole: Partition = (0..8).map(|n| (n, 0)).collect();
+        assert!(modularity(&graph, &whole).abs() < 1e-12);
+        let cliques: Partition = (0..8).map(|n| (n, n / 4)).collect();
+        assert!(modularity(&graph, &cliques) > 0.4);
+    }
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir().join(format!("fga_partition_{}_{}.txt", name, std::process::id())).to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_partition_round_trip() { //Written partitions and circles read back unchanged (also through auto-detection)
+        let partition: Partition = (0..8).map(|n| (n, n / 4)).collect();
+        let path = temp_path("pairs");
+        write_partition(&path, &partition).unwrap();
+        assert_eq!(read_partition(&path, PartitionFormat::NodeCommunity).unwrap(), partition);
+        assert_eq!(read_partition(&path, PartitionFormat::Auto).unwrap(), partition);
+        let sparse = Graph::from_edges(&[(100_000, 300_000), (300_000, 500_000)]); //Compacted on build, so internal ids are 0, 1, 2
+        let by_id = with_original_ids(&sparse, &HashMap::from([(0, 0), (1, 0), (2, 1)]));
+        assert_eq!(by_id, HashMap::from([(100_000, 0), (300_000, 0), (500_000, 1)]));
+
+        let circles = vec![HashSet::from([1, 2, 3]), HashSet::from([3, 4])];
+        write_circles(&path, &circles).unwrap();
+        assert_eq!(read_circles(&path).unwrap(), circles);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_circles_fixture_with_overlap() { //Node 3 is in both circles; as a partition it goes to the first circle
+        let path = temp_path("circles");
+        fs::write(&path, "circle0\t1\t2\t3\ncircle1\t3\t4\t99\n").unwrap();
+        let circles = read_circles(&path).unwrap();
+        let partition = read_partition(&path, PartitionFormat::Auto).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(circles, vec![HashSet::from([1, 2, 3]), HashSet::from([3, 4, 99])]);
+        assert_eq!(partition[&3], 0);
+        assert_eq!(partition[&4], 1);
+        let graph = two_cliques_with_bridge();
+        assert_eq!(unknown_partition_nodes(&graph, &partition), vec![99]);
+    }
+}
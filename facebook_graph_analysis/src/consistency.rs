@@ -0,0 +1,169 @@
+//Module: consistency.rs
+//Test support: checks that alternative implementations of the same metric (parallel, checkpointed, approximate with exact settings, generic over views...)
+//agree on a battery of generated graphs. On the first divergence the offending graph is saved as an edge list so the failure can be reproduced
+use std::fmt::Debug;
+use crate::generate::{barabasi_albert, erdos_renyi, watts_strogatz};
+use crate::graph::Graph;
+use crate::labels::InternalId;
+
+pub(crate) type Implementation<'a, T> = (&'a str, &'a dyn Fn(&Graph) -> T); //Name shown in failure messages, and the code path to run
+
+pub(crate) fn graph_battery() -> Vec<(&'static str, Graph)> { //Random models plus the shapes that tend to break shortcuts: long paths, hubs and several components
+    let mut graphs = vec![
+        ("erdos_renyi", erdos_renyi(40, 0.12, 3).unwrap()),
+        ("barabasi_albert", barabasi_albert(60, 2, 5).unwrap()),
+        ("watts_strogatz", watts_strogatz(50, 4, 0.2, 7).unwrap()),
+    ];
+    let mut path = Graph::new();
+    let mut star = Graph::new();
+    let mut disconnected = Graph::new();
+    for i in 0..12 {
+        path.add_edge(InternalId(i), InternalId(i + 1));
+        star.add_edge(InternalId(0), InternalId(i + 1));
+    }
+    for (u, v) in [(0, 1), (1, 2), (2, 0), (10, 11), (11, 12), (12, 13), (13, 10), (10, 12), (20, 21)] {
+        disconnected.add_edge(InternalId(u), InternalId(v));
+    }
+    graphs.extend([("path", path), ("star", star), ("disconnected", disconnected)]);
+    for (_, graph) in &mut graphs { //Edge lists cannot store isolated nodes, so drop them to make every saved graph load back identically
+        let isolated: Vec<usize> = graph.adjacency().iter().filter(|(_, friends)| friends.is_empty()).map(|(&node, _)| node).collect();
+        isolated.into_iter().for_each(|node| _ = graph.remove_node(InternalId(node)));
+    }
+    graphs
+}
+
+pub(crate) fn assert_equivalent_implementations<T: PartialEq + Debug>(metric: &str, implementations: &[Implementation<T>]) { //Runs every implementation on every battery graph and panics at the first one that disagrees with the first implementation
+    let Some(((reference_name, reference), others)) = implementations.split_first() else {
+        return;
+    };
+    for (graph_name, graph) in graph_battery() {
+        let expected = reference(&graph);
+        for (name, implementation) in others {
+            let actual = implementation(&graph);
+            if actual != expected {
+                let path = std::env::temp_dir().join(format!("fga_divergence_{}_{}.txt", metric, graph_name));
+                let saved = match graph.write_edge_list(&path.to_string_lossy()) {
+                    Ok(()) => path.display().to_string(),
+                    Err(e) => format!("<could not save: {}>", e),
+                };
+                panic!("{}: {} and {} disagree on the {} graph (saved to {})\n{:?}\nvs\n{:?}", metric, reference_name, name, graph_name, saved, expected, actual);
+            }
+        }
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::InternalId;
+    use std::collections::HashMap;
+    use crate::analysis::*;
+    use crate::checkpoint::{betweenness_centrality_checkpointed, closeness_centrality_checkpointed, most_similar_pairs_checkpointed};
+    use crate::community::{label_propagation, label_propagation_weighted};
+    use crate::csr::{average_distance_csr, closeness_centrality_csr, CsrGraph};
+    use crate::parallel::ParallelismConfig;
+    use crate::view::GraphView;
+    use crate::weighted::WeightedGraph;
+
+    fn by_node(mut scores: Vec<(usize, f64)>) -> Vec<(usize, f64)> { //Rankings sorted by node id, so per-node implementations can be compared with ranked ones
+        scores.sort_by_key(|&(node, _)| node);
+        scores
+    }
+
+    fn temp_file(name: &str) -> String { //Fresh checkpoint or edge-list path for one run (removed before returning)
+        let path = std::env::temp_dir().join(format!("fga_consistency_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    fn unit_weights(graph: &Graph) -> WeightedGraph {
+        let mut weighted = WeightedGraph::new();
+        for (&u, friends) in graph.adjacency() {
+            for &v in friends {
+                weighted.add_edge(u, v, 1.0);
+            }
+        }
+        weighted
+    }
+
+    #[test]
+    fn test_every_fast_path_matches_reference() { //One check per metric that has more than one implementation in the crate
+        let sequential = ParallelismConfig::sequential();
+        let four = ParallelismConfig::with_threads(4);
+
+        assert_equivalent_implementations::<Vec<(usize, f64)>>("closeness", &[
+            ("sequential", &|g| closeness_centrality_with(g, &sequential)),
+            ("4 threads", &|g| closeness_centrality_with(g, &four)),
+            ("view", &|g| closeness_centrality(&GraphView::new(g, |_| true))),
+            ("csr", &|g| closeness_centrality_csr(&CsrGraph::from(g), &four)),
+            ("generic over csr", &|g| closeness_centrality(&CsrGraph::from(g))),
+            ("approx, every node a pivot", &|g| closeness_centrality_approx(g, g.num_nodes(), 1).scores),
+            ("checkpointed", &|g| {
+                let path = temp_file("closeness");
+                let scores = closeness_centrality_checkpointed(g, &path, 5).unwrap();
+                let _ = std::fs::remove_file(&path);
+                scores
+            }),
+        ]);
+        assert_equivalent_implementations::<Vec<(usize, f64)>>("closeness_by_node", &[
+            ("ranking", &|g| by_node(closeness_centrality(g))),
+            ("single node", &|g| by_node(g.adjacency().keys().map(|&n| (n, closeness_of(g, InternalId(n)).unwrap())).collect())),
+        ]);
+        assert_equivalent_implementations::<f64>("average_distance", &[
+            ("sequential", &|g| average_distance_with(g, &sequential)),
+            ("4 threads", &|g| average_distance_with(g, &four)),
+            ("csr", &|g| average_distance_csr(&CsrGraph::from(g), &sequential)),
+            ("no hub cap", &|g| average_distance_excluding_hubs(g, None, &sequential).0),
+            ("dijkstra", &|g| average_distance_weighted(g)),
+        ]);
+        assert_equivalent_implementations::<Vec<(usize, f64)>>("betweenness", &[
+            ("in memory", &|g| betweenness_centrality(g)),
+            ("checkpointed", &|g| {
+                let path = temp_file("betweenness");
+                let scores = betweenness_centrality_checkpointed(g, &path, 5).unwrap();
+                let _ = std::fs::remove_file(&path);
+                scores
+            }),
+        ]);
+        assert_equivalent_implementations::<Vec<((usize, usize), f64)>>("similar_pairs", &[
+            ("pair scan", &|g| most_similar_pairs(g, 25)),
+            ("equivalence classes collapsed", &|g| most_similar_pairs_with(g, &SimilarPairsOptions { collapse_equivalent: true, ..SimilarPairsOptions::top(25) })),
+            ("checkpointed", &|g| {
+                let path = temp_file("pairs");
+                let pairs = most_similar_pairs_checkpointed(g, 25, &path, 5).unwrap();
+                let _ = std::fs::remove_file(&path);
+                pairs
+            }),
+        ]);
+        assert_equivalent_implementations::<HashMap<(usize, usize), f64>>("jaccard", &[
+            ("set intersection", &|g| co_neighbor_counts(g, 1).into_keys().map(|(u, v)| ((u, v), jaccard_similarity(g, InternalId(u), InternalId(v)))).collect()),
+            ("from co-neighbor counts", &|g| {
+                co_neighbor_counts(g, 1)
+                    .into_iter()
+                    .map(|((u, v), c)| ((u, v), SimilarityMetric::Jaccard.score_from_count(c as usize, g.adjacency()[&u].len(), g.adjacency()[&v].len()).unwrap()))
+                    .collect()
+            }),
+        ]);
+        assert_equivalent_implementations::<f64>("connected_pair_fraction", &[
+            ("components", &|g| connected_pair_fraction(g)),
+            ("streamed union-find", &|g| {
+                let path = temp_file("pairs_file");
+                g.write_edge_list(&path).unwrap();
+                let fraction = connected_pair_fraction_from_file(&path).unwrap();
+                let _ = std::fs::remove_file(&path);
+                fraction
+            }),
+        ]);
+        assert_equivalent_implementations::<crate::community::Partition>("label_propagation", &[
+            ("unweighted", &|g| label_propagation(g, 42)),
+            ("unit weights", &|g| label_propagation_weighted(&unit_weights(g), 42)),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "saved to")]
+    fn test_divergence_is_reported_with_saved_graph() { //A deliberately wrong implementation must be caught
+        assert_equivalent_implementations::<usize>("edge_count", &[("counter", &|g| g.num_edges()), ("off by one", &|g| g.num_edges() + 1)]);
+    }
+}
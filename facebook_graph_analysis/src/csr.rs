@@ -0,0 +1,290 @@
+//Module: csr.rs
+//Here we define CsrGraph, a read-only compressed sparse row copy of a Graph for the all-sources BFS sweeps:
+//row r's friends are neighbors[offsets[r]..offsets[r + 1]] (as rows), so a BFS walks two flat Vecs and fills a Vec<u32> of distances instead of hashing every step.
+//Rows are NodeIndex values following the graph's internal ids in increasing order; node and row convert between the two, and original_id gives the id from the input file
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::AtomicUsize;
+use crate::analysis::{closeness_from_sweeps, distribution_from_histogram, histogram_from_sweeps, histogram_mean, tick_by, SourceDistances};
+use crate::graph::{Graph, GraphRead};
+use crate::labels::{IdMap, NodeId, NodeIndex};
+use crate::parallel::ParallelismConfig;
+use crate::labels::InternalId;
+
+pub const UNREACHED: u32 = u32::MAX; //Distance bfs_distances_csr gives rows it could not reach
+
+#[derive(Debug, Clone, Default)]
+pub struct CsrGraph {
+    pub offsets: Vec<u32>,   //num_nodes + 1 entries, starting at 0 and ending at neighbors.len()
+    pub neighbors: Vec<u32>, //Every friendship twice (once from each end) as rows, each row sorted
+    pub nodes: Vec<usize>,   //Internal id of each row, increasing
+    rows: HashMap<usize, u32>, //Internal id -> row
+    id_map: Option<IdMap>,   //The graph's id map, for original_id
+}
+
+impl From<&Graph> for CsrGraph {
+    fn from(graph: &Graph) -> Self {
+        let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+        nodes.sort();
+        let mut csr = CsrGraph::from_rows(nodes, 2 * graph.num_edges(), |node| Cow::Borrowed(graph.sorted_neighbors(InternalId(node))));
+        csr.id_map = graph.id_map.clone();
+        csr
+    }
+}
+
+impl CsrGraph {
+    pub fn from_read<G: GraphRead + ?Sized>(graph: &G) -> Self { //CSR copy of any GraphRead, e.g. a hub-excluding GraphView. There is no id map, so original_id gives internal ids
+        let mut nodes: Vec<usize> = graph.nodes().collect();
+        nodes.sort();
+        CsrGraph::from_rows(nodes, 0, |node| {
+            let mut friends: Vec<usize> = graph.neighbors(node).collect();
+            friends.sort();
+            Cow::Owned(friends)
+        })
+    }
+
+    fn from_rows<'a>(nodes: Vec<usize>, links: usize, friends: impl Fn(usize) -> Cow<'a, [usize]>) -> Self { //Rows for the given sorted nodes; friends gives a node's sorted friends, links is a capacity hint
+        let row = |i: usize| u32::try_from(i).expect("CsrGraph stores rows and offsets as u32");
+        let rows: HashMap<usize, u32> = nodes.iter().enumerate().map(|(i, &node)| (node, row(i))).collect();
+        let mut offsets = Vec::with_capacity(nodes.len() + 1);
+        let mut neighbors = Vec::with_capacity(links);
+        offsets.push(0);
+        for &node in &nodes {
+            neighbors.extend(friends(node).iter().map(|friend| rows[friend])); //Rows are in id order, so sorted ids give sorted rows
+            offsets.push(row(neighbors.len()));
+        }
+        CsrGraph { offsets, neighbors, nodes, rows, id_map: None }
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = NodeIndex> { //Every row, in order
+        let count = u32::try_from(self.num_nodes()).expect("from_rows checked that every row fits in u32");
+        (0..count).map(NodeIndex)
+    }
+
+    pub fn row(&self, node: usize) -> Option<NodeIndex> { //Row of an internal id, if the node exists
+        self.rows.get(&node).copied().map(NodeIndex)
+    }
+
+    pub fn node(&self, row: NodeIndex) -> usize { //Internal id of a row (the id every other analysis reports)
+        self.nodes[row.index()]
+    }
+
+    pub fn original_id(&self, row: NodeIndex) -> NodeId { //Id of a row's node as it appears in the input file
+        let node = self.node(row);
+        self.id_map.as_ref().map_or(NodeId(node), |map| map.original_or_internal(node))
+    }
+
+    pub fn row_neighbors(&self, row: NodeIndex) -> &[u32] { //Friends of a row, as sorted rows
+        self.friends_at(row.index())
+    }
+
+    pub(crate) fn friends_at(&self, row: usize) -> &[u32] { //row_neighbors for the sweeps that index their per-row arrays by usize
+        &self.neighbors[self.offsets[row] as usize..self.offsets[row + 1] as usize]
+    }
+}
+
+impl GraphRead for CsrGraph { //In internal ids, so the generic analyses run on a CsrGraph unchanged (the *_csr functions below are the fast paths)
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.nodes.iter().copied())
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self.row(node) {
+            Some(row) => Box::new(self.row_neighbors(row).iter().map(|&friend| self.nodes[friend as usize])),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.rows.contains_key(&node)
+    }
+
+    fn for_each_neighbor(&self, node: usize, visit: impl FnMut(usize)) {
+        self.row(node).into_iter().flat_map(|row| self.row_neighbors(row)).map(|&friend| self.nodes[friend as usize]).for_each(visit);
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool {
+        match (self.row(u), self.row(v)) {
+            (Some(u), Some(v)) => self.row_neighbors(u).binary_search(&v.0).is_ok(),
+            _ => false,
+        }
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.row(node).map_or(0, |row| self.row_neighbors(row).len())
+    }
+}
+
+pub fn bfs_distances_csr(graph: &CsrGraph, start: NodeIndex) -> Vec<u32> { //bfs_distances by row: distances[r] is the number of hops from row start to row r, UNREACHED if there is no path
+    let mut distances = vec![UNREACHED; graph.num_nodes()];
+    let mut queue = VecDeque::new();
+    distances[start.index()] = 0;
+    queue.push_back(start.index());
+    while let Some(current) = queue.pop_front() {
+        let next = distances[current] + 1;
+        for &friend in graph.friends_at(current) {
+            if distances[friend as usize] == UNREACHED {
+                distances[friend as usize] = next;
+                queue.push_back(friend as usize);
+            }
+        }
+    }
+    distances
+}
+
+pub fn all_pairs_bfs_csr(graph: &CsrGraph, parallelism: &ParallelismConfig) -> Vec<SourceDistances> { //all_pairs_bfs over the CSR copy, in row order (nodes reported by internal id)
+    let rows: Vec<NodeIndex> = graph.rows().collect();
+    parallelism.map_nodes(&rows, |row| {
+        let mut sweep = SourceDistances { node: graph.node(row), reached: 0, total_distance: 0, histogram: Vec::new() };
+        for d in bfs_distances_csr(graph, row).into_iter().filter(|&d| d > 0 && d != UNREACHED).map(|d| d as usize) {
+            if sweep.histogram.len() <= d {
+                sweep.histogram.resize(d + 1, 0);
+            }
+            sweep.histogram[d] += 1;
+            sweep.reached += 1;
+            sweep.total_distance += d;
+        }
+        sweep
+    })
+}
+
+pub fn closeness_centrality_csr(graph: &CsrGraph, parallelism: &ParallelismConfig) -> Vec<(usize, f64)> { //closeness_centrality_with on the CSR copy: same scores and order, by internal id
+    closeness_from_sweeps(&all_pairs_bfs_csr(graph, parallelism))
+}
+
+pub fn average_distance_csr(graph: &CsrGraph, parallelism: &ParallelismConfig) -> f64 { //average_distance_with on the CSR copy
+    histogram_mean(&histogram_from_sweeps(&all_pairs_bfs_csr(graph, parallelism)))
+}
+
+pub fn distance_histogram_bitparallel(graph: &CsrGraph, parallelism: &ParallelismConfig) -> Vec<usize> { //distance_histogram (ordered pairs per hop count) from bitparallel_batch's 64-source sweeps
+    let n = graph.num_nodes();
+    let batches: Vec<usize> = (0..n).step_by(64).collect();
+    let per_batch = parallelism.map_nodes(&batches, |first| {
+        let mut histogram = vec![0];
+        bitparallel_batch(graph, first, |d, _, mask| {
+            if histogram.len() <= d {
+                histogram.resize(d + 1, 0);
+            }
+            histogram[d] += mask.count_ones() as usize;
+        });
+        histogram
+    });
+    let mut histogram = vec![0; per_batch.iter().map(Vec::len).max().unwrap_or(1)];
+    for batch in per_batch {
+        for (d, count) in batch.into_iter().enumerate() {
+            histogram[d] += count;
+        }
+    }
+    histogram
+}
+
+pub fn all_pairs_bfs_bitparallel(graph: &CsrGraph, parallelism: &ParallelismConfig, progress: &(dyn Fn(usize, usize) + Sync)) -> Vec<SourceDistances> { //all_pairs_bfs_csr (the same sweeps in the same row order) from bitparallel_batch's 64-source sweeps:
+    //a row newly reached at distance d counts once for every source whose bit is set. Progress is reported per batch of 64
+    let n = graph.num_nodes();
+    let batches: Vec<usize> = (0..n).step_by(64).collect();
+    let done = AtomicUsize::new(0);
+    let per_batch = parallelism.map_nodes(&batches, |first| {
+        let mut sweeps: Vec<SourceDistances> = graph.nodes[first..n.min(first + 64)].iter().map(|&node| SourceDistances { node, reached: 0, total_distance: 0, histogram: Vec::new() }).collect();
+        bitparallel_batch(graph, first, |d, _, mut mask| {
+            while mask != 0 {
+                let sweep = &mut sweeps[mask.trailing_zeros() as usize];
+                mask &= mask - 1;
+                if sweep.histogram.len() <= d {
+                    sweep.histogram.resize(d + 1, 0);
+                }
+                sweep.histogram[d] += 1;
+                sweep.reached += 1;
+                sweep.total_distance += d;
+            }
+        });
+        tick_by(progress, &done, sweeps.len(), n);
+        sweeps
+    });
+    per_batch.into_iter().flatten().collect()
+}
+
+fn bitparallel_batch(graph: &CsrGraph, first: usize, mut reached: impl FnMut(usize, usize, u64)) { //BFS from the (up to) 64 rows first.. at once: bit i of a row's mask says whether source first + i has reached it,
+    //so one pass over the edges advances all 64 frontiers by a level. Calls reached(d, row, mask) with the sources reaching row for the first time at distance d >= 1
+    let n = graph.num_nodes();
+    let (mut visited, mut frontier) = (vec![0u64; n], vec![0u64; n]);
+    for (bit, row) in (first..n.min(first + 64)).enumerate() {
+        visited[row] = 1 << bit;
+        frontier[row] = 1 << bit;
+    }
+    for d in 1.. {
+        let mut next = vec![0u64; n];
+        let mut any = false;
+        for (row, mask) in next.iter_mut().enumerate() {
+            let incoming = graph.friends_at(row).iter().fold(0, |acc, &friend| acc | frontier[friend as usize]);
+            *mask = incoming & !visited[row];
+            visited[row] |= *mask;
+            if *mask != 0 {
+                any = true;
+                reached(d, row, *mask);
+            }
+        }
+        if !any {
+            break;
+        }
+        frontier = next;
+    }
+}
+
+pub fn average_distance_exact_fast(graph: &Graph, parallelism: &ParallelismConfig) -> f64 { //average_distance (same value: the same pair counts, averaged the same way) through distance_histogram_bitparallel on a CSR copy
+    histogram_mean(&distance_histogram_bitparallel(&CsrGraph::from(graph), parallelism))
+}
+
+pub fn distance_distribution_fast(graph: &Graph, parallelism: &ParallelismConfig) -> BTreeMap<usize, usize> { //distance_distribution (unordered pairs per hop count) through distance_histogram_bitparallel on a CSR copy
+    distribution_from_histogram(&distance_histogram_bitparallel(&CsrGraph::from(graph), parallelism))
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{average_distance, bfs_distances, distance_distribution, distance_histogram};
+    use crate::generate::erdos_renyi;
+
+    #[test]
+    fn test_csr_distances_match_hashmap_bfs() { //Every source of a random graph (several components) gives the same distances both ways; rows map back to file ids
+        let graph = erdos_renyi(200, 0.012, 4).unwrap();
+        let csr = CsrGraph::from(&graph);
+        assert_eq!((csr.num_nodes(), csr.neighbors.len()), (graph.num_nodes(), 2 * graph.num_edges()));
+        for row in csr.rows() {
+            let by_node: HashMap<usize, usize> = bfs_distances_csr(&csr, row)
+                .into_iter()
+                .zip(csr.rows())
+                .filter(|&(d, _)| d != UNREACHED)
+                .map(|(d, r)| (csr.node(r), d as usize))
+                .collect();
+            assert_eq!(by_node, bfs_distances(&graph, InternalId(csr.node(row))), "row {:?}", row);
+        }
+
+        let sparse = Graph::from_edges(&[(100_000, 300_000), (300_000, 500_000)]); //Compacted on build, so rows go through the id map
+        let csr = CsrGraph::from(&sparse);
+        assert_eq!(csr.rows().map(|row| csr.original_id(row)).collect::<Vec<_>>(), vec![NodeId(100_000), NodeId(300_000), NodeId(500_000)]);
+        assert_eq!(bfs_distances_csr(&csr, NodeIndex(0)), vec![0, 1, 2]);
+        assert!(csr.has_edge(csr.node(NodeIndex(0)), csr.node(NodeIndex(1))) && !csr.has_edge(csr.node(NodeIndex(0)), csr.node(NodeIndex(2))));
+    }
+
+    #[test]
+    fn test_bitparallel_matches_naive_sweeps() { //500 nodes (not a multiple of 64) in several components: the same histogram, average and hop plot as one BFS per node
+        let graph = erdos_renyi(500, 0.005, 8).unwrap();
+        assert!(crate::analysis::num_components(&graph) > 1);
+        let csr = CsrGraph::from(&graph);
+        for parallelism in [ParallelismConfig::sequential(), ParallelismConfig::default()] {
+            assert_eq!(distance_histogram_bitparallel(&csr, &parallelism), distance_histogram(&graph));
+        }
+        let parallelism = ParallelismConfig::default();
+        assert_eq!(average_distance_exact_fast(&graph, &parallelism), average_distance(&graph));
+        assert_eq!(distance_distribution_fast(&graph, &parallelism), distance_distribution(&graph));
+        assert_eq!((average_distance_exact_fast(&Graph::new(), &parallelism), distance_distribution_fast(&Graph::new(), &parallelism)), (0.0, BTreeMap::new()));
+        for parallelism in [ParallelismConfig::sequential(), ParallelismConfig::default()] { //Per-source sweeps too, so closeness can come out of the same pass
+            assert_eq!(all_pairs_bfs_bitparallel(&csr, &parallelism, &|_, _| {}), all_pairs_bfs_csr(&csr, &parallelism));
+        }
+    }
+}
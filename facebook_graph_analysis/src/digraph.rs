@@ -0,0 +1,132 @@
+//Module: digraph.rs
+//Here we define DiGraph, a directed graph for data where a link only goes one way (follower lists, citations, replies)
+//It implements GraphRead with neighbors() meaning out-neighbors, so the BFS-based analyses (bfs_distances, average_distance, closeness_centrality)
+//follow edge direction automatically: a node's distances and closeness are about who it can reach, and unreachable pairs are skipped as before
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use crate::error::GraphError;
+use crate::graph::{parse_numbers, Graph, GraphRead};
+use crate::labels::InternalId;
+
+#[derive(Debug, Default, Clone)]
+pub struct DiGraph { //Out- and in-adjacency lists kept in sync, so both directions can be queried without scanning every node
+    pub out_list: HashMap<usize, HashSet<usize>>,
+    pub in_list: HashMap<usize, HashSet<usize>>,
+    pub num_nodes: usize,
+    pub num_edges: usize, //Each u -> v counts once; u -> v and v -> u are two edges
+}
+
+impl DiGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn load_from_file_directed(path: &str) -> Result<Self, GraphError> { //Same edge-list format as Graph::load_from_file, but each line "u v" only adds u -> v
+        let reader = BufReader::new(File::open(path)?);
+        let mut graph = DiGraph::new();
+        for (index, line) in reader.lines().enumerate() {
+            if let [u, v] = parse_numbers(&line?, index)?[..] {
+                graph.add_edge(u, v);
+            }
+        }
+        if graph.num_nodes == 0 {
+            return Err(GraphError::EmptyFile(path.to_string()));
+        }
+        Ok(graph)
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize) -> bool { //Adds u -> v. Returns false (and changes nothing) for self-loops and edges that already exist
+        if u == v || self.out_list.get(&u).is_some_and(|n| n.contains(&v)) {
+            return false;
+        }
+        for node in [u, v] {
+            if let Entry::Vacant(entry) = self.out_list.entry(node) {
+                entry.insert(HashSet::new());
+                self.in_list.insert(node, HashSet::new());
+                self.num_nodes += 1;
+            }
+        }
+        self.out_list.get_mut(&u).unwrap().insert(v);
+        self.in_list.get_mut(&v).unwrap().insert(u);
+        self.num_edges += 1;
+        true
+    }
+
+    pub fn out_degrees(&self) -> Vec<(usize, usize)> { //Edges leaving each node (people it follows), sorted by node id
+        degrees(&self.out_list)
+    }
+
+    pub fn in_degrees(&self) -> Vec<(usize, usize)> { //Edges arriving at each node (its followers), sorted by node id
+        degrees(&self.in_list)
+    }
+
+    pub fn to_undirected(&self) -> Graph { //Forgets directions: u and v become friends if either points to the other
+        let mut graph = Graph::new();
+        for (&u, targets) in &self.out_list {
+            graph.add_node(InternalId(u));
+            for &v in targets {
+                graph.add_edge(InternalId(u), InternalId(v));
+            }
+        }
+        graph
+    }
+}
+
+fn degrees(list: &HashMap<usize, HashSet<usize>>) -> Vec<(usize, usize)> {
+    let mut degrees: Vec<(usize, usize)> = list.iter().map(|(&node, n)| (node, n.len())).collect();
+    degrees.sort();
+    degrees
+}
+
+impl GraphRead for DiGraph { //neighbors() and degree() are the outgoing side
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.out_list.keys().copied())
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self.out_list.get(&node) {
+            Some(targets) => Box::new(targets.iter().copied()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.out_list.contains_key(&node)
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool { //Whether u -> v exists (v -> u does not count)
+        self.out_list.get(&u).is_some_and(|n| n.contains(&v))
+    }
+
+    fn edge_count(&self) -> usize { //Directed edges are only seen from their source, so no halving
+        self.num_edges
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{average_distance, bfs_distances, closeness_centrality};
+
+    #[test]
+    fn test_directed_chain() { //0 -> 1 -> 2: 2 is reachable from 0 but nothing is reachable from 2
+        let path = std::env::temp_dir().join(format!("fga_directed_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1\n1 2\n").unwrap();
+        let graph = DiGraph::load_from_file_directed(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((graph.num_nodes, graph.num_edges, graph.edge_count()), (3, 2, 2));
+        assert_eq!(bfs_distances(&graph, InternalId(0)).get(&2), Some(&2));
+        assert_eq!(bfs_distances(&graph, InternalId(2)).get(&0), None);
+        assert_eq!(graph.out_degrees(), vec![(0, 1), (1, 1), (2, 0)]);
+        assert_eq!(graph.in_degrees(), vec![(0, 0), (1, 1), (2, 1)]);
+        assert!(graph.has_edge(0, 1) && !graph.has_edge(1, 0));
+
+        assert_eq!(average_distance(&graph), 4.0 / 3.0); //Reachable ordered pairs: 0->1, 0->2, 1->2
+        let closeness: HashMap<usize, f64> = closeness_centrality(&graph).into_iter().collect();
+        assert_eq!((closeness[&0], closeness[&1], closeness[&2]), (2.0 / 3.0, 1.0, 0.0));
+        assert_eq!(graph.to_undirected().num_edges(), 2);
+    }
+}
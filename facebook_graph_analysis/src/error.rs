@@ -0,0 +1,71 @@
+//Module: error.rs
+//Here we define the single error type returned by every fallible function in the crate, so callers can match on what went wrong
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum GraphError {
+    Io(io::Error),                             //Reading or writing a file failed
+    Parse { line: usize, content: String },    //A line of an input file could not be understood (line numbers start at 1)
+    EmptyFile(String),                         //An input file had no usable edges (the path is kept for the message)
+    UnknownNode(usize),                        //A node id that is not in the graph was asked for
+    UnknownNodes(Vec<usize>),                  //Several unknown node ids at once (e.g. in a requested subset)
+    Disconnected,                              //The requested nodes are not connected by any path
+    BudgetExceeded { needed: usize, budget: usize }, //The requested work is larger than the configured cap (in the cap's unit: bytes for a MemoryBudget, items for count caps)
+    InvalidParameter(String),                  //An argument or setting is out of range or inconsistent
+    Overflow { what: &'static str },           //A count grew past what its integer type holds (returned instead of a wrapped, wrong number)
+    Corrupt(String),                           //A graph broke one of its own invariants (see Graph::validate); always a bug, never bad input
+}
+
+impl GraphError {
+    pub fn exit_code(&self) -> i32 { //Process exit code main uses for each kind of failure
+        match self {
+            GraphError::InvalidParameter(_) => 2,
+            GraphError::Io(_) => 3,
+            GraphError::Parse { .. } | GraphError::EmptyFile(_) => 4,
+            GraphError::UnknownNode(_) | GraphError::UnknownNodes(_) => 5,
+            GraphError::Disconnected => 6,
+            GraphError::BudgetExceeded { .. } => 7,
+            GraphError::Overflow { .. } => 8,
+            GraphError::Corrupt(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Io(e) => write!(f, "I/O error: {}", e),
+            GraphError::Parse { line, content } => write!(f, "Could not parse line {}: {:?}", line, content),
+            GraphError::EmptyFile(path) => write!(f, "{} contains no edges", path),
+            GraphError::UnknownNode(node) => write!(f, "Node {} is not in the graph", node),
+            GraphError::UnknownNodes(nodes) => write!(f, "Nodes not in the graph: {:?}", nodes),
+            GraphError::Disconnected => write!(f, "The nodes are not connected"),
+            GraphError::BudgetExceeded { needed, budget } => write!(f, "The requested computation needs {} but the budget is {}", needed, budget),
+            GraphError::InvalidParameter(message) => write!(f, "Invalid parameter: {}", message),
+            GraphError::Overflow { what } => write!(f, "The {} is too large to count", what),
+            GraphError::Corrupt(problem) => write!(f, "The graph is inconsistent: {}", problem),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GraphError {
+    fn from(e: io::Error) -> Self {
+        GraphError::Io(e)
+    }
+}
+
+impl From<csv::Error> for GraphError {
+    fn from(e: csv::Error) -> Self {
+        GraphError::Io(e.into())
+    }
+}
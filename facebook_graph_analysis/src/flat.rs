@@ -0,0 +1,126 @@
+//Module: flat.rs
+//Here we export a loaded (and possibly scoped) graph as flat CSR arrays so other languages can use it without our loader:
+//offsets[i]..offsets[i + 1] is the slice of targets holding row i's friends (as row numbers), and ids[i] is row i's id in the input file.
+//write_flat_npy_like stores each array as raw little-endian u64 next to a small JSON header, so numpy can memory-map them directly, e.g.
+//  np.memmap("out.targets.bin", dtype="<u8", mode="r")
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use crate::csr::CsrGraph;
+use crate::error::GraphError;
+use crate::graph::Graph;
+
+pub const FLAT_FORMAT_VERSION: u32 = 1; //Written to the header; bump if the layout of the arrays changes
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FlatGraph {
+    pub offsets: Vec<u64>, //num_nodes + 1 entries, starting at 0 and ending at targets.len()
+    pub targets: Vec<u64>, //Every friendship twice (once from each end), each row sorted
+    pub ids: Vec<u64>,     //Input-file id of each row; rows follow the internal ids in increasing order
+}
+
+impl Graph {
+    pub fn to_flat_arrays(&self) -> FlatGraph { //CSR copy of the graph (see CsrGraph) with the original ids kept in `ids`
+        let csr = CsrGraph::from(self);
+        FlatGraph {
+            offsets: csr.offsets.iter().map(|&offset| offset as u64).collect(),
+            targets: csr.neighbors.iter().map(|&row| row as u64).collect(),
+            ids: csr.rows().map(|row| csr.original_id(row).0 as u64).collect(),
+        }
+    }
+}
+
+impl FlatGraph {
+    pub fn write_flat_npy_like(&self, path: &str) -> Result<(), GraphError> { //Writes <path>.offsets.bin, <path>.targets.bin, <path>.ids.bin and the header <path>.json
+        let name = std::path::Path::new(path).file_name().map_or_else(|| path.to_string(), |n| n.to_string_lossy().into_owned()); //The header refers to the arrays by file name so the files can be moved together
+        let mut arrays = Vec::new();
+        for (array, values) in self.arrays() {
+            let mut writer = BufWriter::new(File::create(format!("{}.{}.bin", path, array))?);
+            for value in values {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+            writer.flush()?;
+            arrays.push(format!("\"{}\": {{\"file\": \"{}.{}.bin\", \"dtype\": \"<u8\", \"length\": {}}}", array, name, array, values.len()));
+        }
+        let header = format!(
+            "{{\"format_version\": {}, \"num_nodes\": {}, \"num_edges\": {}, \"arrays\": {{{}}}}}\n",
+            FLAT_FORMAT_VERSION,
+            self.ids.len(),
+            self.targets.len() / 2,
+            arrays.join(", ")
+        );
+        fs::write(format!("{}.json", path), header)?;
+        Ok(())
+    }
+
+    pub fn read_flat_npy_like(path: &str) -> Result<Self, GraphError> { //Reads back the arrays written by write_flat_npy_like, checking that they fit together
+        let read = |array: &str| -> Result<Vec<u64>, GraphError> {
+            let file = format!("{}.{}.bin", path, array);
+            let bytes = fs::read(&file)?;
+            if bytes.len() % 8 != 0 {
+                return Err(GraphError::Parse { line: 0, content: format!("{} is {} bytes, not a whole number of u64 values", file, bytes.len()) });
+            }
+            Ok(bytes.chunks_exact(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap())).collect())
+        };
+        let flat = FlatGraph { offsets: read("offsets")?, targets: read("targets")?, ids: read("ids")? };
+        let rows = flat.ids.len() as u64;
+        let consistent = flat.offsets.len() == flat.ids.len() + 1
+            && flat.offsets.first() == Some(&0)
+            && flat.offsets.last() == Some(&(flat.targets.len() as u64))
+            && flat.offsets.windows(2).all(|w| w[0] <= w[1])
+            && flat.targets.iter().all(|&t| t < rows);
+        if !consistent {
+            return Err(GraphError::Parse { line: 0, content: format!("{}.*.bin do not form a valid CSR graph", path) });
+        }
+        Ok(flat)
+    }
+
+    fn arrays(&self) -> [(&'static str, &[u64]); 3] {
+        [("offsets", &self.offsets), ("targets", &self.targets), ("ids", &self.ids)]
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::InternalId;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn test_flat_round_trip() { //Sparse ids get compacted on load; the flat arrays keep the file's ids, survive a write/read unchanged and rebuild the same friendships
+        let input = std::env::temp_dir().join(format!("fga_flat_input_{}.txt", std::process::id()));
+        let prefix = std::env::temp_dir().join(format!("fga_flat_{}", std::process::id()));
+        let (input, prefix) = (input.to_str().unwrap(), prefix.to_str().unwrap());
+        fs::write(input, "7000000000 12\n12 40\n40 7000000000\n40 5\n").unwrap();
+        let graph = Graph::load_from_file(input).unwrap();
+
+        let flat = graph.to_flat_arrays();
+        assert_eq!(flat.offsets.len(), graph.num_nodes() + 1);
+        assert_eq!(flat.targets.len(), 2 * graph.num_edges());
+        flat.write_flat_npy_like(prefix).unwrap();
+        let bytes: Vec<Vec<u8>> = flat.arrays().iter().map(|(array, _)| fs::read(format!("{}.{}.bin", prefix, array)).unwrap()).collect();
+        let expected: Vec<u8> = flat.targets.iter().flat_map(|t| t.to_le_bytes()).collect();
+        assert_eq!(bytes[1], expected);
+        let header = fs::read_to_string(format!("{}.json", prefix)).unwrap();
+        assert!(header.contains("\"targets\": {\"file\": \"fga_flat_"));
+        assert!(header.contains(&format!("\"dtype\": \"<u8\", \"length\": {}", flat.targets.len())));
+
+        let back = FlatGraph::read_flat_npy_like(prefix).unwrap();
+        assert_eq!(back, flat);
+        let friends: HashMap<u64, HashSet<u64>> = (0..back.ids.len())
+            .map(|i| (back.ids[i], back.targets[back.offsets[i] as usize..back.offsets[i + 1] as usize].iter().map(|&t| back.ids[t as usize]).collect()))
+            .collect();
+        for (&node, neighbors) in graph.adjacency() {
+            let original: HashSet<u64> = neighbors.iter().map(|&n| graph.original_id(InternalId(n)).0 as u64).collect();
+            assert_eq!(friends[&(graph.original_id(InternalId(node)).0 as u64)], original);
+        }
+        assert_eq!(friends[&7_000_000_000], HashSet::from([12, 40]));
+
+        fs::write(format!("{}.targets.bin", prefix), [0u8; 7]).unwrap();
+        assert!(matches!(FlatGraph::read_flat_npy_like(prefix), Err(GraphError::Parse { .. })));
+        for file in ["offsets.bin", "targets.bin", "ids.bin", "json"] {
+            fs::remove_file(format!("{}.{}", prefix, file)).unwrap();
+        }
+        fs::remove_file(input).unwrap();
+    }
+}
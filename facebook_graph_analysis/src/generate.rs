@@ -0,0 +1,213 @@
+//Module: generate.rs
+//Here we build random graphs from the classic models (Erdős–Rényi, Watts–Strogatz, Barabási–Albert, configuration model)
+//They are used for experiments and benchmarks, and to compare the Facebook network with graphs that have no social structure
+//Every generator takes a seed, so the same arguments always give the same graph
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use crate::error::GraphError;
+use crate::graph::{Graph, GraphRead};
+use crate::labels::InternalId;
+
+pub fn erdos_renyi(nodes: usize, p: f64, seed: u64) -> Result<Graph, GraphError> { //G(n, p): every pair of nodes is connected independently with probability p
+    if !(0.0..=1.0).contains(&p) {
+        return Err(GraphError::InvalidParameter(format!("edge probability must be between 0 and 1, got {}", p)));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut graph = with_nodes(nodes);
+    for u in 0..nodes {
+        for v in u + 1..nodes {
+            if rng.gen::<f64>() < p {
+                graph.add_edge(InternalId(u), InternalId(v));
+            }
+        }
+    }
+    Ok(graph)
+}
+
+pub fn watts_strogatz(nodes: usize, k: usize, beta: f64, seed: u64) -> Result<Graph, GraphError> { //Ring where each node links to its k nearest neighbors (k/2 per side), then each edge is rewired to a random node with probability beta
+    if !k.is_multiple_of(2) || k >= nodes {
+        return Err(GraphError::InvalidParameter(format!("k must be even and smaller than the number of nodes, got k = {} for {} nodes", k, nodes)));
+    }
+    if !(0.0..=1.0).contains(&beta) {
+        return Err(GraphError::InvalidParameter(format!("rewiring probability must be between 0 and 1, got {}", beta)));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut graph = with_nodes(nodes);
+    for u in 0..nodes {
+        for step in 1..=k / 2 {
+            graph.add_edge(InternalId(u), InternalId((u + step) % nodes));
+        }
+    }
+    for step in 1..=k / 2 {
+        for u in 0..nodes {
+            let v = (u + step) % nodes;
+            if rng.gen::<f64>() >= beta || !graph.adjacency()[&u].contains(&v) || graph.adjacency()[&u].len() >= nodes - 1 {
+                continue;
+            }
+            let target = loop { //Redraw until the new endpoint is neither u nor already a friend of u
+                let w = rng.gen_range(0..nodes);
+                if w != u && !graph.adjacency()[&u].contains(&w) {
+                    break w;
+                }
+            };
+            graph.remove_edge(InternalId(u), InternalId(v));
+            graph.add_edge(InternalId(u), InternalId(target));
+        }
+    }
+    Ok(graph)
+}
+
+pub fn barabasi_albert(nodes: usize, m: usize, seed: u64) -> Result<Graph, GraphError> { //Preferential attachment: each new node links to m existing nodes chosen with probability proportional to their degree
+    if m == 0 || m >= nodes {
+        return Err(GraphError::InvalidParameter(format!("m must be between 1 and the number of nodes - 1, got m = {} for {} nodes", m, nodes)));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut graph = with_nodes(nodes);
+    let mut endpoints: Vec<usize> = Vec::new(); //Every node appears once per edge it has, so a uniform pick from here is degree-proportional
+    let mut targets: Vec<usize> = (0..m).collect(); //The first new node links to the m seed nodes
+    for new in m..nodes {
+        for &target in &targets {
+            graph.add_edge(InternalId(new), InternalId(target));
+            endpoints.extend([new, target]);
+        }
+        targets.clear();
+        while targets.len() < m {
+            let candidate = endpoints[rng.gen_range(0..endpoints.len())];
+            if !targets.contains(&candidate) {
+                targets.push(candidate);
+            }
+        }
+    }
+    Ok(graph)
+}
+
+pub fn configuration_model(degrees: &[usize], seed: u64) -> Result<Graph, GraphError> { //Random graph with (close to) the given degree sequence: node i gets degrees[i] stubs which are paired at random
+    //Self-loops and repeated edges produced by the pairing are dropped (the "erased" configuration model), so some nodes end up with slightly lower degrees
+    if !degrees.iter().sum::<usize>().is_multiple_of(2) {
+        return Err(GraphError::InvalidParameter(String::from("the degrees must add up to an even number")));
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut graph = with_nodes(degrees.len());
+    let mut stubs: Vec<usize> = degrees.iter().enumerate().flat_map(|(node, &d)| std::iter::repeat_n(node, d)).collect();
+    stubs.shuffle(&mut rng);
+    for pair in stubs.chunks(2) {
+        graph.add_edge(InternalId(pair[0]), InternalId(pair[1]));
+    }
+    Ok(graph)
+}
+
+pub const MAX_SWAP_ATTEMPTS_PER_SWAP: usize = 100; //rewire_preserving_degrees gives up after this many tries per requested swap (a star, for one, has no legal swap at all)
+
+pub fn rewire_preserving_degrees(graph: &Graph, num_swaps: usize, seed: u64) -> Graph { //Degree-preserving null model by double edge swaps: two random edges a-b and c-d become a-d and c-b unless that makes a self-loop or a repeat.
+    //Every node keeps its degree, so whatever clustering or assortativity survives many swaps is explained by the degree sequence alone. Stops after num_swaps successful swaps
+    //(about 10 per edge mixes well) or num_swaps * MAX_SWAP_ATTEMPTS_PER_SWAP tries. Keeps the id map; same seed = same graph
+    let mut rewired = Graph::new();
+    for (&u, friends) in graph.adjacency() {
+        rewired.add_node(InternalId(u));
+        friends.iter().for_each(|&v| _ = rewired.add_edge(InternalId(u), InternalId(v)));
+    }
+    rewired.id_map = graph.id_map.clone();
+    let mut edges: Vec<(usize, usize)> = graph.adjacency().iter().flat_map(|(&u, friends)| friends.iter().filter(move |&&v| u < v).map(move |&v| (u, v))).collect();
+    edges.sort(); //Sampling by position, so the order must not depend on HashMap iteration
+    if edges.len() < 2 {
+        return rewired;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (mut done, mut attempts) = (0, 0);
+    while done < num_swaps && attempts < num_swaps.saturating_mul(MAX_SWAP_ATTEMPTS_PER_SWAP) {
+        attempts += 1;
+        let (i, j) = (rng.gen_range(0..edges.len()), rng.gen_range(0..edges.len()));
+        let (a, b) = edges[i];
+        let (c, d) = if rng.gen::<bool>() { edges[j] } else { (edges[j].1, edges[j].0) }; //Either orientation, so both possible swaps of the pair get drawn
+        if i == j || a == d || c == b || rewired.has_edge(a, d) || rewired.has_edge(c, b) {
+            continue;
+        }
+        rewired.remove_edge(InternalId(a), InternalId(b));
+        rewired.remove_edge(InternalId(c), InternalId(d));
+        rewired.add_edge(InternalId(a), InternalId(d));
+        rewired.add_edge(InternalId(c), InternalId(b));
+        (edges[i], edges[j]) = ((a.min(d), a.max(d)), (c.min(b), c.max(b)));
+        done += 1;
+    }
+    rewired
+}
+
+fn with_nodes(nodes: usize) -> Graph { //Graph with nodes 0..nodes and no edges
+    let mut graph = Graph::new();
+    for node in 0..nodes {
+        graph.add_node(InternalId(node));
+    }
+    graph
+}
+
+#[cfg(test)]
+pub(crate) fn two_cliques_with_bridge() -> Graph { //Test fixture shared by the analysis and community tests: cliques {0,1,2,3} and {4,5,6,7} joined by the single edge 3-4
+    let mut edges = vec![(3, 4)];
+    for clique in [[0, 1, 2, 3], [4, 5, 6, 7]] {
+        for i in 0..4 {
+            for j in i + 1..4 {
+                edges.push((clique[i], clique[j]));
+            }
+        }
+    }
+    Graph::from_edges(&edges)
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_graphs_round_trip_through_edge_list() { //Every model's output is written with each edge once and loads back with exactly the same edges
+        let degrees: Vec<usize> = (0..200).map(|i| 1 + i % 5).collect();
+        let graphs = [
+            ("er", erdos_renyi(200, 0.05, 7).unwrap()),
+            ("ws", watts_strogatz(200, 6, 0.2, 7).unwrap()),
+            ("ba", barabasi_albert(200, 3, 7).unwrap()),
+            ("config", configuration_model(&degrees, 7).unwrap()),
+        ];
+        for (name, graph) in graphs {
+            let path = std::env::temp_dir().join(format!("fga_generate_{}_{}.txt", name, std::process::id()));
+            graph.write_edge_list(path.to_str().unwrap()).unwrap();
+            let text = std::fs::read_to_string(&path).unwrap();
+            let loaded = Graph::load_from_file(path.to_str().unwrap()).unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(text.lines().count(), graph.num_edges(), "{}", name);
+            assert_eq!(loaded.num_edges(), graph.num_edges(), "{}", name);
+            for (node, neighbors) in loaded.adjacency() {
+                assert_eq!(neighbors, &graph.adjacency()[node], "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rewiring_keeps_degrees() { //50-node random graph: every degree survives, some friendships move, the same seed repeats; a star has no legal swap and stays put
+        let graph = erdos_renyi(50, 0.1, 4).unwrap();
+        let rewired = rewire_preserving_degrees(&graph, 10 * graph.num_edges(), 11);
+        assert_eq!(rewired.num_edges(), graph.num_edges());
+        assert!(graph.adjacency().iter().all(|(node, friends)| rewired.adjacency()[node].len() == friends.len()));
+        assert!(rewired.adjacency().iter().all(|(node, friends)| !friends.contains(node) && friends.iter().all(|f| rewired.adjacency()[f].contains(node))));
+        assert!(graph.adjacency().iter().any(|(node, friends)| rewired.adjacency()[node] != *friends));
+        assert_eq!(rewired.fingerprint(), rewire_preserving_degrees(&graph, 10 * graph.num_edges(), 11).fingerprint());
+        let star = Graph::from_edges(&[(0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_eq!(rewire_preserving_degrees(&star, 5, 1).adjacency(), star.adjacency());
+    }
+
+    #[test]
+    fn test_model_shapes() { //Quick structural checks per model, plus determinism per seed
+        let ws = watts_strogatz(100, 4, 0.0, 1).unwrap();
+        assert_eq!(ws.num_edges(), 200);
+        assert!(ws.adjacency().values().all(|n| n.len() == 4));
+        let ba = barabasi_albert(100, 2, 1).unwrap();
+        assert_eq!(ba.num_edges(), 2 * 98); //Every node after the m seed nodes adds exactly m edges
+        assert_eq!(ba.fingerprint(), barabasi_albert(100, 2, 1).unwrap().fingerprint());
+        assert_eq!(erdos_renyi(30, 1.0, 3).unwrap().num_edges(), 30 * 29 / 2);
+        let empty = erdos_renyi(30, 0.0, 3).unwrap();
+        assert_eq!((empty.num_nodes(), empty.num_edges()), (30, 0));
+        assert_eq!(erdos_renyi(50, 0.1, 9).unwrap().fingerprint(), erdos_renyi(50, 0.1, 9).unwrap().fingerprint());
+        assert!(matches!(watts_strogatz(10, 3, 0.1, 1), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(configuration_model(&[1, 1, 1], 1), Err(GraphError::InvalidParameter(_))));
+    }
+}
@@ -1,49 +1,635 @@
 //Module: graph.rs
 //Here we define the graph struct and build the graph to prepare us for analysis methods
 
-use std::collections::{HashMap, HashSet};
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::borrow::Borrow;
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use flate2::bufread::MultiGzDecoder;
+use crate::community::{read_circles, Partition};
+use crate::error::GraphError;
+use crate::labels::{IdMap, InternalId, NodeId};
+use builder::{BadLine, GraphBuilder, LoadOptions};
 
-#[derive(Debug)]
+pub mod sampling; //Uniform, seeded samplers of nodes, edges and node pairs
+pub mod external; //Degree and component counts streamed from edge lists too large to load
+pub mod builder; //GraphBuilder: edge-by-edge construction with policies for duplicates, self-loops and bad lines
+pub mod binary; //save_binary / load_binary snapshots of a loaded graph, and load_cached which keeps one next to the edge list
+
+pub type EgoNetwork = (Graph, Vec<HashSet<usize>>, HashMap<usize, Vec<bool>>); //Merged graph, ground-truth circles and per-node feature vectors
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadReport { //What loading one edge list added to the graph
+    pub path: PathBuf,
+    pub edges: usize,      //Friendships not seen before (in this file or an earlier one)
+    pub duplicates: usize, //Lines repeating a friendship already loaded, in either direction
+    pub self_loops: usize, //"u u" lines: the person is kept, the line adds no friendship
+    pub skipped: usize,    //Lines without exactly two ids
+}
+
+impl LoadReport {
+    pub fn combined(reports: &[LoadReport]) -> LoadReport { //Totals over several files (path left empty)
+        reports.iter().fold(LoadReport::default(), |total, r| LoadReport {
+            path: PathBuf::new(),
+            edges: total.edges + r.edges,
+            duplicates: total.duplicates + r.duplicates,
+            self_loops: total.self_loops + r.self_loops,
+            skipped: total.skipped + r.skipped,
+        })
+    }
+}
+
+#[macro_export]
+macro_rules! assert_graph_eq { //Like assert_eq! for two Graphs (same nodes and edges by original id), but a failure prints the differing nodes and edges and both graphs via Graph::pretty
+    ($left:expr, $right:expr $(,)?) => {
+        if let Some(diff) = $crate::graph::Graph::pretty_diff(&$left, &$right) {
+            panic!("graphs differ\n{}", diff);
+        }
+    };
+}
+
+// Graph is shared as &Graph by the parallel analyses, so it must stay Send + Sync. The invariants that keep that true:
+// every field is owned plain data or a OnceLock cache; &self methods only read (a cache is filled at most once, atomically);
+// adj_list and its counters are private, so anything that changes the edges is a &mut self method here that calls touch(), which bumps version and drops the caches
+#[derive(Debug, Default)]
 pub struct Graph { // We represent an undirected graph using an adjacency list to model our social network
-    pub adj_list: HashMap<usize, HashSet<usize>>,
-    pub num_nodes: usize,
-    pub num_edges: usize,
+    adj_list: HashMap<usize, HashSet<usize>>, //Read it through adjacency(); edit it through add_edge, remove_edge, add_node and remove_node
+    num_nodes: usize, //Read these three through num_nodes(), num_edges() and version(); only the mutators write them
+    num_edges: usize,
+    pub id_map: Option<IdMap>, //Set when the file's ids were compacted on load (see labels.rs); None means internal ids are the file's ids
+    version: u64, //Bumped by every mutator (add_edge, remove_edge, ...) so stored results can tell they are out of date
+    cache: GraphCache,
+}
+
+#[derive(Debug, Default)]
+struct GraphCache { //Data derived from adj_list on first use; valid until the next touch()
+    sorted_adjacency: OnceLock<HashMap<usize, Vec<usize>>>,
+    fingerprint: OnceLock<u64>,
+}
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b]; //First two bytes of every gzip member
+
+pub const MAX_COMPLEMENT_EDGES: usize = 10_000_000; //complement refuses to build more edges than this: the complement of a sparse graph is nearly complete
+
+const DOT_PALETTE: [&str; 8] = ["lightblue", "lightpink", "palegreen", "khaki", "plum", "lightsalmon", "paleturquoise", "wheat"]; //Fill colors for communities (id modulo 8); gold is kept for highlighted nodes
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DotStyle<'a> { //Optional decorations for to_dot_with; nodes are keyed by internal id
+    pub labels: Option<&'a HashMap<usize, String>>, //Printed instead of the node's id (nodes without one keep the id)
+    pub communities: Option<&'a Partition>,         //Each community gets its own fill color
+    pub highlight: &'a [usize],                     //Filled in gold, over any community color
 }
 
+const _: () = { //Compile-time check: sharing &Graph between threads must keep compiling
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Graph>();
+};
+
 impl Graph {
     pub fn new() -> Self { //Creates a new empty graph with zero nodes and edges
         Self {
             adj_list: HashMap::new(),
             num_nodes: 0,
             num_edges: 0,
+            id_map: None,
+            version: 0,
+            cache: GraphCache::default(),
         }
     }
 
-    pub fn load_from_file(path: &str) -> Self { //We load a graph from a file where each line represents an edge as "u", "v" It reads each file line by line, parses each edge, and builds the adjacency list
-        let file = File::open(path).expect("Failed to open graph file.");
-        let reader = BufReader::new(file);
-        let mut graph = Graph::new();
+    pub fn load_from_file(path: &str) -> Result<Self, GraphError> { //We load a graph from a file where each line represents an edge as "u", "v" It reads each file line by line, parses each edge, and builds the adjacency list (gzipped files are decompressed on the fly)
+        Ok(Self::load_from_file_with(path, false)?.0)
+    }
+
+    pub fn load_from_file_with(path: &str, strict: bool) -> Result<(Self, usize), GraphError> { //Like load_from_file, also returning how many lines were skipped for not having exactly two ids; with strict such a line is a Parse error instead. Blank lines are always ignored
+        let (graph, reports) = Self::load_from_files_with(&[PathBuf::from(path)], strict)?;
+        Ok((graph, reports[0].skipped))
+    }
+
+    pub fn load_from_files(paths: &[PathBuf]) -> Result<Self, GraphError> { //One graph from several edge lists (e.g. shards of one network), as if they had been concatenated
+        Ok(Self::load_from_files_with(paths, false)?.0)
+    }
+
+    pub fn load_from_files_with(paths: &[PathBuf], strict: bool) -> Result<(Self, Vec<LoadReport>), GraphError> { //load_from_files with one LoadReport per file (LoadReport::combined adds them up). An edge already read from an earlier file counts as a duplicate, just like a repeat within one file
+        let mut builder = GraphBuilder::new().bad_lines(if strict { BadLine::Error } else { BadLine::Skip });
+        for path in paths {
+            builder.read_file(path)?;
+        }
+        builder.build_graph()
+    }
+
+    pub fn load_with_options(path: &str, options: &LoadOptions) -> Result<Self, GraphError> { //load_from_file for other layouts: comma- or tab-separated, '#' comments, a header row, extra columns
+        let mut builder = GraphBuilder::new().format(*options);
+        builder.read_file(Path::new(path))?;
+        Ok(builder.build_graph()?.0)
+    }
+
+    pub fn load_from_reader<R: BufRead>(reader: R) -> Result<Self, GraphError> { //load_from_file for text that is already open or in memory (not decompressed: see open_edge_list)
+        let mut builder = GraphBuilder::new();
+        builder.read(reader, PathBuf::new())?;
+        Ok(builder.build_graph()?.0)
+    }
+
+    pub fn from_edges(edges: impl IntoIterator<Item = impl Borrow<(usize, usize)>>) -> Self { //A graph from "u v" pairs (a slice, a Vec or any iterator of them) under the loaders' default policies
+        //(repeats ignored, a self-loop keeps only the node); sparse ids are compacted like a loaded file's
+        edges.into_iter().fold(GraphBuilder::new(), |builder, edge| builder.edge(edge.borrow().0, edge.borrow().1)).graph().expect("the default policies accept every edge")
+    }
+
+    fn compact_ids(&mut self) -> Result<(), GraphError> { //Renumbers nodes to 0..n in order of their original ids and remembers the originals
+        let map = IdMap::from_ids(self.adj_list.keys().copied())?;
+        self.adj_list = self
+            .adj_list
+            .drain()
+            .map(|(node, neighbors)| (map.compact(NodeId(node)).unwrap().index(), neighbors.into_iter().map(|n| map.compact(NodeId(n)).unwrap().index()).collect()))
+            .collect();
+        self.id_map = Some(map);
+        self.cache = GraphCache::default();
+        Ok(())
+    }
+
+    fn touch(&mut self) { //Marks the graph as changed: bumps version and drops cached data. Every mutator calls it
+        self.version += 1;
+        self.cache = GraphCache::default();
+    }
+
+    pub fn adjacency(&self) -> &HashMap<usize, HashSet<usize>> { //Every node's friend set, read-only
+        &self.adj_list
+    }
 
-        for line in reader.lines() { //Reading edges line by line
-            if let Ok(edge_line) = line {
-                let parts: Vec<usize> = edge_line
-                    .split_whitespace()
-                    .map(|x| x.parse::<usize>().unwrap())
-                    .collect();
-                if parts.len() != 2 {
-                    continue;
+    pub fn num_nodes(&self) -> usize {
+        self.num_nodes
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.num_edges
+    }
+
+    pub fn version(&self) -> u64 { //Changes on every mutation, see AnalysisResults::stale
+        self.version
+    }
+
+    pub fn sorted_adjacency(&self) -> &HashMap<usize, Vec<usize>> { //Every node's neighbors in increasing id order, sorted once for the whole graph on first use. Floating-point sums depend on the order they are added in, so betweenness walks these instead of the HashSets to give bit-identical scores on every run
+        self.cache.sorted_adjacency.get_or_init(|| {
+            self.adj_list
+                .iter()
+                .map(|(&node, neighbors)| {
+                    let mut sorted: Vec<usize> = neighbors.iter().copied().collect();
+                    sorted.sort();
+                    (node, sorted)
+                })
+                .collect()
+        })
+    }
+
+    pub fn sorted_neighbors(&self, InternalId(node): InternalId) -> &[usize] { //Neighbors in increasing id order (empty for unknown nodes), from sorted_adjacency
+        self.sorted_adjacency().get(&node).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn original_id(&self, InternalId(node): InternalId) -> NodeId { //Id of a node as it appears in the input file
+        self.id_map.as_ref().map_or(NodeId(node), |map| map.original_or_internal(node))
+    }
+
+    pub fn internal_id(&self, original: NodeId) -> Option<InternalId> { //Internal id for an id from the input file, if the node exists
+        let node = match &self.id_map {
+            Some(map) => map.internal(original)?,
+            None => original.0,
+        };
+        self.adj_list.contains_key(&node).then_some(InternalId(node))
+    }
+
+    pub fn add_edge(&mut self, InternalId(u): InternalId, InternalId(v): InternalId) -> bool { //Adds an undirected edge, keeping the counts up to date. Returns false (and changes nothing) for self-loops and edges that already exist
+        if u == v || self.adj_list.get(&u).is_some_and(|n| n.contains(&v)) {
+            return false;
+        }
+        for node in [u, v] {
+            if let Entry::Vacant(entry) = self.adj_list.entry(node) {
+                entry.insert(HashSet::new());
+                self.num_nodes += 1;
+                if let Some(map) = &mut self.id_map { //A new node of a compacted graph needs an original id of its own
+                    map.assign(node);
+                }
+            }
+        }
+        self.adj_list.get_mut(&u).unwrap().insert(v);
+        self.adj_list.get_mut(&v).unwrap().insert(u);
+        self.num_edges += 1;
+        self.touch();
+        true
+    }
+
+    pub fn remove_edge(&mut self, InternalId(u): InternalId, InternalId(v): InternalId) -> bool { //Removes an undirected edge (both nodes stay, possibly isolated). Returns false if there was no such edge
+        if !self.adj_list.get_mut(&u).is_some_and(|n| n.remove(&v)) {
+            return false;
+        }
+        self.adj_list.get_mut(&v).unwrap().remove(&u);
+        self.num_edges -= 1;
+        self.touch();
+        true
+    }
+
+    pub fn add_node(&mut self, InternalId(node): InternalId) -> bool { //Adds a person with no friendships yet. Returns false (and changes nothing) if the node already exists
+        if self.adj_list.contains_key(&node) {
+            return false;
+        }
+        self.adj_list.insert(node, HashSet::new());
+        self.num_nodes += 1;
+        if let Some(map) = &mut self.id_map {
+            map.assign(node);
+        }
+        self.touch();
+        true
+    }
+
+    pub fn remove_node(&mut self, InternalId(node): InternalId) -> bool { //Removes a person and every friendship they had. Returns false if there was no such node
+        let Some(mut friends) = self.adj_list.remove(&node) else {
+            return false;
+        };
+        friends.remove(&node); //A self-loop (only in a graph edited by hand, see validate) is not a friendship to undo
+        for friend in &friends {
+            self.adj_list.get_mut(friend).unwrap().remove(&node);
+        }
+        self.num_edges -= friends.len();
+        self.num_nodes -= 1;
+        self.touch();
+        true
+    }
+
+    pub fn friends(&self, InternalId(node): InternalId) -> Option<&HashSet<usize>> { //A node's friend set, or None for unknown nodes (GraphRead::neighbors iterates the same set)
+        self.adj_list.get(&node)
+    }
+
+    pub fn merge_nodes(&mut self, keep: usize, absorb: usize) -> Result<(), GraphError> { //Moves absorb's friendships onto keep (no self-loops, no duplicates) and removes absorb, e.g. for two accounts of one person
+        for node in [keep, absorb] {
+            if !self.adj_list.contains_key(&node) {
+                return Err(GraphError::UnknownNode(node));
+            }
+        }
+        if keep == absorb {
+            return Err(GraphError::InvalidParameter(format!("cannot merge node {} into itself", keep)));
+        }
+        for friend in self.adj_list.remove(&absorb).unwrap() {
+            self.adj_list.get_mut(&friend).unwrap().remove(&absorb);
+            self.num_edges -= 1;
+            if friend != keep && self.adj_list.get_mut(&keep).unwrap().insert(friend) {
+                self.adj_list.get_mut(&friend).unwrap().insert(keep);
+                self.num_edges += 1;
+            }
+        }
+        self.num_nodes -= 1;
+        self.touch();
+        Ok(())
+    }
+
+    pub fn merge_many(&mut self, merges: &[(usize, usize)]) -> Result<(), GraphError> { //Applies (from, into) merges, following chains: with (a, b) and (b, c) both a and b end up in c
+        let mut into: HashMap<usize, usize> = HashMap::new();
+        for &(from, target) in merges {
+            if into.insert(from, target).is_some_and(|previous| previous != target) {
+                return Err(GraphError::InvalidParameter(format!("node {} is merged into two different nodes", from)));
+            }
+        }
+        let mut order: Vec<usize> = into.keys().copied().collect();
+        order.sort();
+        let mut resolved = Vec::new();
+        for &from in &order {
+            let mut root = into[&from];
+            let mut steps = 0;
+            while let Some(&next) = into.get(&root) {
+                root = next;
+                steps += 1;
+                if steps > into.len() {
+                    return Err(GraphError::InvalidParameter(format!("merges starting at node {} form a cycle", from)));
                 }
-                let (u, v) = (parts[0], parts[1]);
+            }
+            resolved.push((from, root));
+        }
+        for (from, root) in resolved {
+            self.merge_nodes(root, from)?;
+        }
+        Ok(())
+    }
+
+    pub fn restrict_to(&self, include_only: Option<&HashSet<NodeId>>, exclude: &HashSet<NodeId>) -> (Graph, usize) { //Induced subgraph scoping a run: keeps the nodes in include_only (all if None) minus those in exclude, both given as input-file ids. Returns it with the number of nodes removed
+        let keep = |node: &usize| {
+            let original = self.original_id(InternalId(*node));
+            include_only.is_none_or(|allowed| allowed.contains(&original)) && !exclude.contains(&original)
+        };
+        let scoped = self.induced_subgraph(&self.adj_list.keys().copied().filter(keep).collect());
+        let removed = self.num_nodes - scoped.num_nodes;
+        (scoped, removed)
+    }
+
+    pub fn induced_subgraph(&self, nodes: &HashSet<usize>) -> Graph { //The given nodes (internal ids, unknown ones ignored) with every friendship between them; friendships leaving the set are dropped from both ends. Keeps the id map
+        let mut subgraph = Graph::new();
+        for node in nodes.iter().filter(|node| self.adj_list.contains_key(node)) {
+            subgraph.adj_list.insert(*node, self.adj_list[node].iter().copied().filter(|f| nodes.contains(f)).collect());
+        }
+        subgraph.num_nodes = subgraph.adj_list.len();
+        subgraph.num_edges = subgraph.adj_list.values().map(|f| f.len()).sum::<usize>() / 2;
+        subgraph.id_map = self.id_map.clone();
+        subgraph
+    }
+
+    pub fn complement(&self) -> Result<Graph, GraphError> { //Same nodes, with a friendship exactly where this graph has none. Errors with BudgetExceeded if it would hold more than MAX_COMPLEMENT_EDGES edges (Overflow if that count does not even fit a usize). Keeps the id map
+        let n = self.num_nodes;
+        let pairs = crate::analysis::pairs_among(n).ok_or(GraphError::Overflow { what: "complement edge count" })?;
+        let needed = pairs.saturating_sub(self.num_edges); //Every pair minus the existing edges
+        if needed > MAX_COMPLEMENT_EDGES {
+            return Err(GraphError::BudgetExceeded { needed, budget: MAX_COMPLEMENT_EDGES });
+        }
+        let mut complement = Graph::new();
+        for (&node, friends) in &self.adj_list {
+            complement.adj_list.insert(node, self.adj_list.keys().copied().filter(|&other| other != node && !friends.contains(&other)).collect());
+        }
+        complement.num_nodes = n;
+        complement.num_edges = needed;
+        complement.id_map = self.id_map.clone();
+        Ok(complement)
+    }
+
+    pub fn ego_network(&self, InternalId(center): InternalId, radius: usize) -> Graph { //Induced subgraph on everyone within radius hops of center, center included (empty if center is unknown)
+        let ball: HashSet<usize> = crate::analysis::bfs_distances_within(self, InternalId(center), radius).into_keys().collect();
+        self.induced_subgraph(&ball)
+    }
+
+    pub fn filter_nodes(&self, predicate: impl Fn(usize, &HashSet<usize>) -> bool) -> Graph { //Induced subgraph on the nodes for which predicate(node, its friends here) holds, e.g. a degree threshold. Keeps the id map
+        self.induced_subgraph(&self.adj_list.iter().filter(|&(&node, friends)| predicate(node, friends)).map(|(&node, _)| node).collect())
+    }
+
+    pub fn filter_edges(&self, predicate: impl Fn(usize, usize) -> bool, keep_isolated: bool) -> Graph { //Only the friendships u-v (asked once, with u < v) for which predicate holds, e.g. both ends in one community.
+        //With keep_isolated every node stays, otherwise nodes left without friends (or that had none) are dropped. Keeps the id map
+        let mut filtered = Graph::new();
+        if keep_isolated {
+            filtered.adj_list = self.adj_list.keys().map(|&node| (node, HashSet::new())).collect();
+        }
+        for (&u, friends) in &self.adj_list {
+            for &v in friends.iter().filter(|&&v| u < v && predicate(u, v)) { //Each friendship decided once, from its smaller end, and kept from both
+                filtered.adj_list.entry(u).or_default().insert(v);
+                filtered.adj_list.entry(v).or_default().insert(u);
+                filtered.num_edges += 1;
+            }
+        }
+        filtered.num_nodes = filtered.adj_list.len();
+        filtered.id_map = self.id_map.clone();
+        filtered
+    }
+
+    pub fn prune_by_degree(&self, min_degree: usize) -> Graph { //Repeatedly drops nodes with fewer than min_degree friends, since each removal can push friends below it, until none is left: the min_degree-core (see k_core_subgraph)
+        crate::analysis::k_core_subgraph(self, min_degree)
+    }
+
+    pub fn write_edge_list(&self, path: &str) -> Result<(), GraphError> { //Writes every edge once as "u v" (u < v, original ids), sorted, in the format load_from_file reads. Isolated nodes cannot be represented and are left out
+        let mut nodes: Vec<usize> = self.adj_list.keys().copied().collect();
+        nodes.sort();
+        let mut writer = BufWriter::new(File::create(path)?);
+        for u in nodes {
+            for &v in self.sorted_neighbors(InternalId(u)).iter().filter(|&&v| u < v) {
+                writeln!(writer, "{} {}", self.original_id(InternalId(u)), self.original_id(InternalId(v)))?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn to_dot(&self) -> String { //The graph in Graphviz DOT format (draw it with e.g. `dot -Tpng`): every node, then every edge once as "u -- v" with u < v, all sorted and by original id
+        self.to_dot_with(&DotStyle::default())
+    }
+
+    pub fn to_dot_with(&self, style: &DotStyle) -> String { //to_dot with labels, community colors and highlighted nodes
+        let mut nodes: Vec<usize> = self.adj_list.keys().copied().collect();
+        nodes.sort();
+        let mut dot = String::from("graph G {\n");
+        for &node in &nodes {
+            let mut attributes = Vec::new();
+            if let Some(label) = style.labels.and_then(|labels| labels.get(&node)) {
+                attributes.push(format!("label=\"{}\"", label.replace('\\', "\\\\").replace('"', "\\\"")));
+            }
+            let fill = if style.highlight.contains(&node) {
+                Some("gold")
+            } else {
+                style.communities.and_then(|partition| partition.get(&node)).map(|&c| DOT_PALETTE[c % DOT_PALETTE.len()])
+            };
+            if let Some(color) = fill {
+                attributes.push(format!("style=filled, fillcolor={}", color));
+            }
+            let attributes = if attributes.is_empty() { String::new() } else { format!(" [{}]", attributes.join(", ")) };
+            dot.push_str(&format!("  {}{};\n", self.original_id(InternalId(node)), attributes));
+        }
+        for &u in &nodes {
+            for &v in self.sorted_neighbors(InternalId(u)).iter().filter(|&&v| u < v) {
+                dot.push_str(&format!("  {} -- {};\n", self.original_id(InternalId(u)), self.original_id(InternalId(v))));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn write_dot(&self, path: &str) -> Result<(), GraphError> { //Writes to_dot to a file
+        self.write_dot_with(path, &DotStyle::default())
+    }
+
+    pub fn write_dot_with(&self, path: &str, style: &DotStyle) -> Result<(), GraphError> { //Writes to_dot_with to a file
+        fs::write(path, self.to_dot_with(style))?;
+        Ok(())
+    }
+
+    pub fn write_graphml(&self, path: &str, node_attrs: Option<&HashMap<usize, HashMap<String, String>>>) -> Result<(), GraphError> { //The graph as GraphML for Gephi or Cytoscape, written as it goes: a string <key> per attribute name in node_attrs
+        //(keyed by internal id, e.g. "community" or "closeness"; nodes without a value leave it out), then every node and every edge once (u < v), sorted and by original id
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">")?;
+        let mut names: Vec<&String> = node_attrs.into_iter().flat_map(|attrs| attrs.values().flat_map(|values| values.keys())).collect();
+        names.sort();
+        names.dedup();
+        for (i, name) in names.iter().enumerate() {
+            writeln!(writer, "  <key id=\"d{}\" for=\"node\" attr.name=\"{}\" attr.type=\"string\"/>", i, xml_escape(name))?;
+        }
+        writeln!(writer, "  <graph id=\"G\" edgedefault=\"undirected\">")?;
+        let mut nodes: Vec<usize> = self.adj_list.keys().copied().collect();
+        nodes.sort();
+        for &node in &nodes {
+            let values = node_attrs.and_then(|attrs| attrs.get(&node));
+            let data: Vec<(usize, &String)> = names.iter().enumerate().filter_map(|(i, name)| Some((i, values?.get(*name)?))).collect();
+            if data.is_empty() {
+                writeln!(writer, "    <node id=\"{}\"/>", self.original_id(InternalId(node)))?;
+                continue;
+            }
+            writeln!(writer, "    <node id=\"{}\">", self.original_id(InternalId(node)))?;
+            for (i, value) in data {
+                writeln!(writer, "      <data key=\"d{}\">{}</data>", i, xml_escape(value))?;
+            }
+            writeln!(writer, "    </node>")?;
+        }
+        for &u in &nodes {
+            for &v in self.sorted_neighbors(InternalId(u)).iter().filter(|&&v| u < v) {
+                writeln!(writer, "    <edge source=\"{}\" target=\"{}\"/>", self.original_id(InternalId(u)), self.original_id(InternalId(v)))?;
+            }
+        }
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Loads a directory of SNAP ego networks (<ego>.edges, <ego>.circles, <ego>.feat, <ego>.egofeat) into one graph
+    // Each ego is connected to every node listed in its own files, friend-friend edges are unioned across egos (duplicates count once),
+    // circles from all egos are returned as overlapping communities, and 0/1 feature vectors are attached per node.
+    // Every ego has its own feature columns, so a node that appears in several egos keeps the vector of the smallest ego id
+    pub fn load_snap_ego_dir(dir: &str) -> Result<EgoNetwork, GraphError> {
+        let mut egos: Vec<usize> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|e| e == "edges"))
+            .filter_map(|path| path.file_stem().and_then(|s| s.to_str()).and_then(|s| s.parse().ok()))
+            .collect();
+        egos.sort();
+
+        let mut graph = Graph::new();
+        let mut circles = Vec::new();
+        let mut features: HashMap<usize, Vec<bool>> = HashMap::new();
+        for ego in egos {
+            let file = |ext: &str| Path::new(dir).join(format!("{}.{}", ego, ext));
+            let mut friends = HashSet::new();
+            for (u, v) in read_edge_pairs(&file("edges"))? {
                 graph.adj_list.entry(u).or_default().insert(v);
                 graph.adj_list.entry(v).or_default().insert(u);
-                graph.num_edges += 1;
+                friends.extend([u, v]);
+            }
+            if file("feat").exists() {
+                for (index, line) in fs::read_to_string(file("feat"))?.lines().enumerate() {
+                    let values = parse_numbers(line, index)?;
+                    if let Some((&node, flags)) = values.split_first() {
+                        friends.insert(node);
+                        features.entry(node).or_insert_with(|| flags.iter().map(|&f| f != 0).collect());
+                    }
+                }
+            }
+            if file("egofeat").exists() {
+                let text = fs::read_to_string(file("egofeat"))?;
+                let flags = parse_numbers(text.lines().next().unwrap_or(""), 0)?;
+                features.entry(ego).or_insert_with(|| flags.iter().map(|&f| f != 0).collect());
+            }
+            graph.adj_list.entry(ego).or_default();
+            for friend in friends {
+                if friend != ego {
+                    graph.adj_list.entry(ego).or_default().insert(friend);
+                    graph.adj_list.entry(friend).or_default().insert(ego);
+                }
+            }
+            if file("circles").exists() {
+                circles.extend(read_circles(&file("circles").to_string_lossy())?);
             }
         }
 
         graph.num_nodes = graph.adj_list.len();
-        graph
+        graph.num_edges = graph.adj_list.values().map(|n| n.len()).sum::<usize>() / 2;
+        Ok((graph, circles, features))
+    }
+
+    // Computes a stable 64-bit fingerprint of the graph structure (FNV-1a over the sorted adjacency lists)
+    // Two graphs with the same nodes and edges always get the same fingerprint, regardless of insertion order or platform
+    pub fn fingerprint(&self) -> u64 { //FNV-1a hash of the sorted adjacency, cached until the graph is touched
+        *self.cache.fingerprint.get_or_init(|| {
+            let mut hash: u64 = 0xcbf29ce484222325;
+            let mut feed = |value: usize| {
+                for byte in (value as u64).to_le_bytes() {
+                    hash ^= byte as u64;
+                    hash = hash.wrapping_mul(0x100000001b3);
+                }
+            };
+            let mut nodes: Vec<usize> = self.adj_list.keys().copied().collect();
+            nodes.sort();
+            for node in nodes {
+                let neighbors = self.sorted_neighbors(InternalId(node));
+                feed(node);
+                feed(neighbors.len());
+                for &neighbor in neighbors {
+                    feed(neighbor);
+                }
+            }
+            hash
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), GraphError> { //Checks the invariants every constructor and mutator keeps: counts match the adjacency, friendships are symmetric with no self-loops, and the id map covers every node
+        let corrupt = |problem: String| Err(GraphError::Corrupt(problem));
+        if self.num_nodes != self.adj_list.len() {
+            return corrupt(format!("num_nodes is {} but there are {} nodes", self.num_nodes, self.adj_list.len()));
+        }
+        let mut degree_sum = 0usize;
+        for (&node, friends) in &self.adj_list {
+            if friends.contains(&node) {
+                return corrupt(format!("node {} is its own friend", node));
+            }
+            if let Some(&friend) = friends.iter().find(|friend| !self.adj_list.get(friend).is_some_and(|back| back.contains(&node))) {
+                return corrupt(format!("node {} lists {} as a friend but not the other way round", node, friend));
+            }
+            if self.id_map.as_ref().is_some_and(|map| node >= map.len()) {
+                return corrupt(format!("node {} has no entry in the id map", node));
+            }
+            degree_sum += friends.len();
+        }
+        if degree_sum != 2 * self.num_edges {
+            return corrupt(format!("num_edges is {} but the degrees add up to {}", self.num_edges, degree_sum));
+        }
+        Ok(())
+    }
+
+    pub fn pretty(&self) -> String { //One "node: friend friend ..." line per node, by original id, everything sorted. Meant for small graphs in tests and on screen
+        let mut rows: Vec<(NodeId, Vec<NodeId>)> = self
+            .adj_list
+            .iter()
+            .map(|(&node, friends)| {
+                let mut friends: Vec<NodeId> = friends.iter().map(|&f| self.original_id(InternalId(f))).collect();
+                friends.sort();
+                (self.original_id(InternalId(node)), friends)
+            })
+            .collect();
+        rows.sort();
+        let mut out = String::new();
+        for (node, friends) in rows {
+            out.push_str(&format!("{}:", node));
+            friends.iter().for_each(|f| out.push_str(&format!(" {}", f)));
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn diff(&self, other: &Graph) -> GraphDiff { //What changed from this snapshot to other, matching nodes by input-file id (the two need not share nodes or internal ids).
+        //A node in only one graph brings all its friendships along as added or removed edges
+        let nodes = |g: &Graph| -> HashSet<NodeId> { g.adj_list.keys().map(|&n| g.original_id(InternalId(n))).collect() };
+        let edges = |g: &Graph| -> HashSet<(NodeId, NodeId)> {
+            g.adj_list.iter().flat_map(|(&u, friends)| friends.iter().map(move |&v| (g.original_id(InternalId(u)), g.original_id(InternalId(v))))).filter(|(u, v)| u < v).collect()
+        };
+        fn sorted<T: Ord>(mut items: Vec<T>) -> Vec<T> {
+            items.sort();
+            items
+        }
+        let (nodes_old, nodes_new, edges_old, edges_new) = (nodes(self), nodes(other), edges(self), edges(other));
+        let (common_edges, all_edges) = (edges_old.intersection(&edges_new).count(), edges_old.union(&edges_new).count());
+        GraphDiff {
+            added_nodes: sorted(nodes_new.difference(&nodes_old).copied().collect()),
+            removed_nodes: sorted(nodes_old.difference(&nodes_new).copied().collect()),
+            added_edges: sorted(edges_new.difference(&edges_old).copied().collect()),
+            removed_edges: sorted(edges_old.difference(&edges_new).copied().collect()),
+            common_nodes: nodes_old.intersection(&nodes_new).count(),
+            common_edges,
+            edge_jaccard: if all_edges == 0 { 1.0 } else { common_edges as f64 / all_edges as f64 },
+        }
+    }
+
+    pub fn pretty_diff(&self, other: &Graph) -> Option<String> { //None if both graphs have the same nodes and edges (by original id); otherwise what only one side has, then both pretty listings
+        let diff = self.diff(other);
+        if diff.is_empty() {
+            return None;
+        }
+        let only = |nodes: &[NodeId], edges: &[(NodeId, NodeId)]| -> String {
+            let nodes = nodes.iter().map(|n| format!("node {}", n));
+            nodes.chain(edges.iter().map(|(u, v)| format!("{}-{}", u, v))).collect::<Vec<_>>().join(", ")
+        };
+        let (left, right) = (only(&diff.removed_nodes, &diff.removed_edges), only(&diff.added_nodes, &diff.added_edges));
+        Some(format!("only in left: {}\nonly in right: {}\nleft:\n{}right:\n{}", left, right, self.pretty(), other.pretty()))
     }
 
     // Computes the degree (number of neighbors) for each node in the graph
@@ -54,6 +640,176 @@ impl Graph {
             .map(|(&node, neighbors)| (node, neighbors.len()))
             .collect()
     }
+
+    pub fn stats(&self) -> GraphSummary { //Size, density and degree summary in one pass over the adjacency lists (all zeros for the empty graph)
+        let mut degrees: Vec<usize> = self.adj_list.values().map(HashSet::len).collect();
+        degrees.sort();
+        let n = degrees.len();
+        let median_degree = match n {
+            0 => 0.0,
+            _ if n % 2 == 1 => degrees[n / 2] as f64,
+            _ => (degrees[n / 2 - 1] + degrees[n / 2]) as f64 / 2.0,
+        };
+        GraphSummary {
+            num_nodes: self.num_nodes,
+            num_edges: self.num_edges,
+            density: crate::analysis::density(self),
+            min_degree: degrees.first().copied().unwrap_or(0),
+            max_degree: degrees.last().copied().unwrap_or(0),
+            mean_degree: if n == 0 { 0.0 } else { degrees.iter().sum::<usize>() as f64 / n as f64 },
+            median_degree,
+            isolated_nodes: degrees.iter().take_while(|&&d| d == 0).count(),
+            self_loops: self.adj_list.iter().filter(|(node, friends)| friends.contains(node)).count(), //Never added by the loaders or add_edge, only by editing adj_list directly
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphSummary { //What Graph::stats reports (named apart from pipeline::GraphStats, which holds the distance results of a run)
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    pub density: f64, //2E / N(N - 1), 0.0 with fewer than two nodes
+    pub min_degree: usize,
+    pub max_degree: usize,
+    pub mean_degree: f64,
+    pub median_degree: f64, //Mean of the two middle degrees when the node count is even
+    pub isolated_nodes: usize,
+    pub self_loops: usize,
+}
+
+impl fmt::Display for GraphSummary { //Multi-line report, e.g. "Nodes: 4039 (0 isolated)" then edges, density, degrees and self-loops
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Nodes: {} ({} isolated)", self.num_nodes, self.isolated_nodes)?;
+        writeln!(f, "Edges: {}", self.num_edges)?;
+        writeln!(f, "Density: {:.6}", self.density)?;
+        writeln!(f, "Degree: min {}, max {}, mean {:.2}, median {:.1}", self.min_degree, self.max_degree, self.mean_degree, self.median_degree)?;
+        writeln!(f, "Self-loops: {}", self.self_loops)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphDiff { //What Graph::diff found between an old and a new snapshot, by input-file id; every list sorted, edges as (u, v) with u < v
+    pub added_nodes: Vec<NodeId>,
+    pub removed_nodes: Vec<NodeId>,
+    pub added_edges: Vec<(NodeId, NodeId)>,
+    pub removed_edges: Vec<(NodeId, NodeId)>,
+    pub common_nodes: usize,
+    pub common_edges: usize,
+    pub edge_jaccard: f64, //Shared friendships / friendships in either snapshot (1.0 when neither has any)
+}
+
+impl GraphDiff {
+    pub fn is_empty(&self) -> bool { //True when both snapshots have exactly the same nodes and friendships
+        self.added_nodes.is_empty() && self.removed_nodes.is_empty() && self.added_edges.is_empty() && self.removed_edges.is_empty()
+    }
+}
+
+impl fmt::Display for GraphDiff { //One line, e.g. "+2 / -1 nodes, +5 / -3 edges (1200 nodes and 8000 edges kept, edge Jaccard 0.9990)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{} / -{} nodes, +{} / -{} edges ({} nodes and {} edges kept, edge Jaccard {:.4})",
+            self.added_nodes.len(), self.removed_nodes.len(), self.added_edges.len(), self.removed_edges.len(), self.common_nodes, self.common_edges, self.edge_jaccard)
+    }
+}
+
+fn xml_escape(text: &str) -> String { //Text safe inside XML content and double-quoted attributes
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\'', "&apos;")
+}
+
+pub fn open_edge_list(path: &Path) -> Result<Box<dyn BufRead>, GraphError> { //Opens an edge list for line-by-line reading, decompressing it when it is gzipped (a .gz extension or the gzip magic bytes)
+    let mut reader = BufReader::new(File::open(path)?);
+    let gzipped = path.extension().is_some_and(|ext| ext == "gz") || reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+    Ok(if gzipped { Box::new(BufReader::new(MultiGzDecoder::new(reader))) } else { Box::new(reader) })
+}
+
+pub fn fuzz_load(bytes: &[u8]) -> Result<Graph, GraphError> { //Fuzz target for the loader: reads bytes as an edge-list file under the default policies and validates the result. Any input may be an error, none may panic
+    let mut builder = GraphBuilder::new();
+    builder.read(bytes, PathBuf::from("<fuzz input>"))?;
+    let (graph, _) = builder.build_graph()?;
+    graph.validate()?;
+    Ok(graph)
+}
+
+pub(crate) fn parse_numbers(line: &str, index: usize) -> Result<Vec<usize>, GraphError> { //Parses every whitespace-separated token of a line as a number (index is the 0-based line index, for the error)
+    line.split_whitespace()
+        .map(|x| x.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| GraphError::Parse { line: index + 1, content: line.to_string() })
+}
+
+fn read_edge_pairs(path: &Path) -> Result<Vec<(usize, usize)>, GraphError> { //Reads "u v" lines, skipping lines that do not have exactly two numbers
+    let mut pairs = Vec::new();
+    for (index, line) in fs::read_to_string(path)?.lines().enumerate() {
+        if let [u, v] = parse_numbers(line, index)?[..] {
+            pairs.push((u, v));
+        }
+    }
+    Ok(pairs)
+}
+
+// Read-only access to an undirected graph. Analyses written against this trait work on a Graph
+// as well as on lightweight views of one (see view.rs) without copying any adjacency lists
+pub trait GraphRead {
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_>; //Every node, in no particular order
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_>; //Neighbors of a node (empty for unknown nodes)
+    fn contains_node(&self, node: usize) -> bool;
+    fn has_edge(&self, u: usize, v: usize) -> bool;
+
+    fn for_each_neighbor(&self, node: usize, visit: impl FnMut(usize)) { //neighbors without the boxed iterator, for per-pair hot loops such as Jaccard; the concrete graphs walk their own lists
+        self.neighbors(node).for_each(visit);
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.neighbors(node).count()
+    }
+
+    fn node_count(&self) -> usize {
+        self.nodes().count()
+    }
+
+    fn edge_count(&self) -> usize { //Each undirected edge is seen from both endpoints, hence the halving
+        self.nodes().map(|n| self.degree(n)).sum::<usize>() / 2
+    }
+
+    fn edge_weight(&self, _u: usize, _v: usize) -> f64 { //Weight of the edge u-v; unweighted graphs count every edge as 1.0 (only meaningful when has_edge(u, v))
+        1.0
+    }
+
+    fn strength(&self, node: usize) -> f64 { //Weighted degree: sum of the weights of a node's edges (equals the degree when unweighted)
+        self.neighbors(node).map(|n| self.edge_weight(node, n)).sum()
+    }
+}
+
+impl GraphRead for Graph {
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.adj_list.keys().copied())
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self.adj_list.get(&node) {
+            Some(neighbors) => Box::new(neighbors.iter().copied()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.adj_list.contains_key(&node)
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.adj_list.get(&u).is_some_and(|n| n.contains(&v))
+    }
+
+    fn for_each_neighbor(&self, node: usize, visit: impl FnMut(usize)) {
+        self.adj_list.get(&node).into_iter().flatten().copied().for_each(visit);
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.adj_list.get(&node).map_or(0, |n| n.len())
+    }
+
+    fn node_count(&self) -> usize {
+        self.adj_list.len()
+    }
 }
 
 
@@ -61,6 +817,7 @@ impl Graph {
 #[cfg(test)] //Attribute that allows Rust to include this module only during cargo test
 mod tests { //Submodule that contains our tests
     use super::*;
+    use crate::labels::NodeIndex;
     use std::collections::HashSet;
 
     #[test]
@@ -80,4 +837,436 @@ mod tests { //Submodule that contains our tests
         assert!(degrees.contains(&(2, 1)));
         assert!(degrees.contains(&(3, 1))); 
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_stats() { //Triangle: everyone has degree 2. Star with 4 leaves: density 8/20, median 1. Even node counts average the middle degrees
+        let mut triangle = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 0)] {
+            triangle.add_edge(InternalId(u), InternalId(v));
+        }
+        let stats = triangle.stats();
+        assert_eq!((stats.num_nodes, stats.num_edges, stats.density), (3, 3, 1.0));
+        assert_eq!((stats.min_degree, stats.max_degree, stats.mean_degree, stats.median_degree), (2, 2, 2.0, 2.0));
+        assert_eq!((stats.isolated_nodes, stats.self_loops), (0, 0));
+        assert_eq!(stats.to_string(), "Nodes: 3 (0 isolated)\nEdges: 3\nDensity: 1.000000\nDegree: min 2, max 2, mean 2.00, median 2.0\nSelf-loops: 0\n");
+
+        let mut star = Graph::new();
+        for leaf in 1..=4 {
+            star.add_edge(InternalId(0), InternalId(leaf));
+        }
+        let stats = star.stats();
+        assert_eq!((stats.num_nodes, stats.num_edges, stats.density), (5, 4, 0.4));
+        assert_eq!((stats.min_degree, stats.max_degree, stats.mean_degree, stats.median_degree), (1, 4, 1.6, 1.0));
+        star.adj_list.insert(9, HashSet::new());
+        star.num_nodes += 1;
+        assert_eq!((star.stats().isolated_nodes, star.stats().median_degree), (1, 1.0));
+        star.remove_edge(InternalId(0), InternalId(4));
+        star.remove_edge(InternalId(0), InternalId(3));
+        assert_eq!(star.stats().median_degree, 0.5); //Degrees 0, 0, 0, 1, 1, 2
+
+        let mut single = Graph::new();
+        single.adj_list.insert(5, HashSet::from([5])); //A self-loop put in by hand
+        single.num_nodes = 1;
+        let stats = single.stats();
+        assert_eq!((stats.density, stats.self_loops), (0.0, 1));
+        assert_eq!(Graph::new().stats().median_degree, 0.0);
+    }
+
+    #[test]
+    fn test_ego_network_and_induced_subgraph() { //Star 0 with leaves 1-4, plus a tail 4-5-6 hanging off leaf 4
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (0, 2), (0, 3), (0, 4), (4, 5), (5, 6)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let star = graph.induced_subgraph(&HashSet::from([0, 1, 2, 3, 4]));
+        assert_eq!((star.num_nodes, star.num_edges), (5, 4));
+        assert!(!star.adj_list[&4].contains(&5)); //Dropped from both ends
+        crate::assert_graph_eq!(graph.ego_network(InternalId(0), 1), star);
+
+        let mut leaf = Graph::new();
+        leaf.add_edge(InternalId(1), InternalId(0));
+        crate::assert_graph_eq!(graph.ego_network(InternalId(1), 1), leaf);
+        assert_eq!(graph.ego_network(InternalId(4), 2).num_edges, 6); //Reaches 1-3 through the hub and 6 through 5
+        assert_eq!(graph.ego_network(InternalId(6), 0).pretty(), "6:\n");
+        assert_eq!(graph.ego_network(InternalId(99), 3).num_nodes, 0);
+        assert_eq!(graph.induced_subgraph(&HashSet::from([1, 2, 99])).pretty(), "1:\n2:\n");
+    }
+
+    #[test]
+    fn test_filter_and_prune() { //Pruning a star at degree 2 empties it (the hub falls once its leaves are gone); a triangle filtered to one edge keeps its ends, or all three nodes when asked
+        let star = Graph::from_edges([(0, 1), (0, 2), (0, 3), (0, 4)]);
+        assert_eq!(star.prune_by_degree(2).num_nodes, 0);
+        assert_eq!(star.filter_nodes(|_, friends| friends.len() >= 2).pretty(), "0:\n");
+        let triangle_with_tail = Graph::from_edges([(0, 1), (1, 2), (2, 0), (2, 3), (3, 4)]);
+        crate::assert_graph_eq!(triangle_with_tail.prune_by_degree(2), Graph::from_edges([(0, 1), (1, 2), (2, 0)]));
+
+        let triangle = Graph::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let one_edge = triangle.filter_edges(|u, v| (u, v) == (0, 1), false);
+        assert_eq!((one_edge.num_nodes, one_edge.num_edges), (2, 1));
+        assert_eq!(one_edge.pretty(), "0: 1\n1: 0\n");
+        let kept = triangle.filter_edges(|u, v| (u, v) == (0, 1), true);
+        assert_eq!((kept.num_nodes, kept.num_edges), (3, 1));
+        let calls = std::cell::Cell::new(0);
+        triangle.filter_edges(|_, _| { calls.set(calls.get() + 1); true }, false);
+        assert_eq!(calls.get(), 3); //Once per friendship, not once from each end
+        for graph in [one_edge, kept, star.filter_nodes(|node, _| node != 0)] {
+            graph.validate().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_complement() { //Triangle 0-1-2 plus isolated node 3: the complement is the star joining 3 to the others
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 0)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph.adj_list.insert(3, HashSet::new());
+        graph.num_nodes = 4;
+        let complement = graph.complement().unwrap();
+        assert_eq!((complement.num_nodes, complement.num_edges), (4, 3));
+        assert_eq!(complement.pretty(), "0: 3\n1: 3\n2: 3\n3: 0 1 2\n");
+        assert_eq!(complement.complement().unwrap().pretty(), graph.pretty());
+
+        let mut sparse = Graph::new();
+        for node in 0..5000 {
+            sparse.add_edge(InternalId(node), InternalId(node + 1));
+        }
+        assert!(matches!(sparse.complement(), Err(GraphError::BudgetExceeded { budget: MAX_COMPLEMENT_EDGES, .. })));
+        sparse.num_nodes = usize::MAX; //n(n - 1)/2 wraps long before this
+        assert!(matches!(sparse.complement(), Err(GraphError::Overflow { .. })));
+    }
+
+    #[test]
+    fn test_to_dot() { //Edges given in both directions and out of order still come out once each, u < v, sorted; labels are quoted and communities colored
+        let mut graph = Graph::new();
+        for (u, v) in [(3, 1), (1, 3), (2, 1), (0, 2), (2, 3), (3, 0)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("graph G {\n") && dot.ends_with("}\n"));
+        let edges: Vec<(usize, usize)> = dot
+            .lines()
+            .filter_map(|line| line.trim().strip_suffix(';')?.split_once(" -- "))
+            .map(|(u, v)| (u.parse().unwrap(), v.parse().unwrap()))
+            .collect();
+        assert_eq!(edges.len(), graph.num_edges);
+        assert_eq!(edges.iter().collect::<HashSet<_>>().len(), edges.len());
+        assert!(edges.iter().all(|&(u, v)| u < v) && edges.windows(2).all(|w| w[0] < w[1]));
+
+        let labels = HashMap::from([(0, String::from("Ann \"A\"")), (1, String::from("Bo"))]);
+        let communities = HashMap::from([(0, 0), (1, 0), (2, 1), (3, 9)]);
+        let styled = graph.to_dot_with(&DotStyle { labels: Some(&labels), communities: Some(&communities), highlight: &[3] });
+        assert!(styled.contains("  0 [label=\"Ann \\\"A\\\"\", style=filled, fillcolor=lightblue];\n  1 [label=\"Bo\", style=filled, fillcolor=lightblue];\n"));
+        assert!(styled.contains("  2 [style=filled, fillcolor=lightpink];\n  3 [style=filled, fillcolor=gold];\n"));
+        assert_eq!(styled.lines().filter(|l| l.contains(" -- ")).count(), 5);
+    }
+
+    #[test]
+    fn test_write_graphml() { //Triangle plus isolated node 3: one <node> each and one <edge> per friendship; attribute names become keys and values are escaped
+        let graph = GraphBuilder::new().edge(1, 0).edge(1, 2).edge(2, 0).node(3).graph().unwrap();
+        let attrs: HashMap<usize, HashMap<String, String>> = HashMap::from([
+            (0, HashMap::from([(String::from("community"), String::from("0")), (String::from("label"), String::from("Tom & \"Jerry\" <3"))])),
+            (2, HashMap::from([(String::from("community"), String::from("1"))])),
+        ]);
+        let path = std::env::temp_dir().join(format!("fga_graphml_{}.graphml", std::process::id()));
+        graph.write_graphml(path.to_str().unwrap(), Some(&attrs)).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(text.starts_with("<?xml") && text.trim_end().ends_with("</graphml>"));
+        assert_eq!((text.matches("<node ").count(), text.matches("<edge ").count(), text.matches("<key ").count()), (4, 3, 2));
+        assert!(text.contains("<key id=\"d0\" for=\"node\" attr.name=\"community\" attr.type=\"string\"/>"));
+        assert!(text.contains("<data key=\"d1\">Tom &amp; &quot;Jerry&quot; &lt;3</data>"));
+        assert!(text.contains("<node id=\"3\"/>") && text.contains("<edge source=\"0\" target=\"1\"/>"));
+        assert_eq!(text.matches("<data ").count(), 3);
+        let nodes: Vec<&str> = text.lines().filter(|line| line.contains("<node ")).collect();
+        assert!(nodes.windows(2).all(|pair| pair[0] < pair[1])); //Sorted ids (single digits here)
+    }
+
+    #[test]
+    fn test_diff() { //Triangle 0-1-2 against the triangle minus node 2 plus the edge 1-3: node 2 leaves with both its friendships, node 3 arrives with one
+        let old = Graph::from_edges(&[(0, 1), (1, 2), (2, 0)]);
+        let new = Graph::from_edges(&[(0, 1), (1, 3)]);
+        let diff = old.diff(&new);
+        assert_eq!((diff.added_nodes, diff.removed_nodes), (vec![NodeId(3)], vec![NodeId(2)]));
+        assert_eq!((diff.added_edges, diff.removed_edges), (vec![(NodeId(1), NodeId(3))], vec![(NodeId(0), NodeId(2)), (NodeId(1), NodeId(2))]));
+        assert_eq!((diff.common_nodes, diff.common_edges, diff.edge_jaccard), (2, 1, 0.25));
+        assert_eq!(old.diff(&new).to_string(), "+1 / -1 nodes, +1 / -2 edges (2 nodes and 1 edges kept, edge Jaccard 0.2500)");
+        assert!(!old.diff(&new).is_empty() && old.diff(&Graph::from_edges(&[(2, 1), (0, 2), (1, 0)])).is_empty());
+        let back = new.diff(&old);
+        assert_eq!((back.added_nodes, back.removed_edges.len()), (vec![NodeId(2)], 1));
+    }
+
+    #[test]
+    fn test_pretty_and_assert_graph_eq() { //The triangle (plus an isolated node) prints sorted; the macro accepts the same edges in another order and names the missing one
+        let mut triangle = Graph::new();
+        for (u, v) in [(2, 0), (0, 1), (1, 2)] {
+            triangle.add_edge(InternalId(u), InternalId(v));
+        }
+        triangle.adj_list.insert(7, HashSet::new());
+        assert_eq!(triangle.pretty(), "0: 1 2\n1: 0 2\n2: 0 1\n7:\n");
+
+        let mut same = Graph::new();
+        for (u, v) in [(1, 2), (1, 0), (0, 2)] {
+            same.add_edge(InternalId(u), InternalId(v));
+        }
+        same.adj_list.insert(7, HashSet::new());
+        crate::assert_graph_eq!(triangle, same);
+
+        same.remove_edge(InternalId(1), InternalId(2));
+        let failure = std::panic::catch_unwind(|| crate::assert_graph_eq!(triangle, same)).unwrap_err();
+        let message = failure.downcast_ref::<String>().unwrap();
+        assert!(message.contains("only in left: 1-2\nonly in right: \n"), "{}", message);
+    }
+
+    #[test]
+    fn test_fingerprint() { //Same edges in a different order give the same fingerprint, an extra edge changes it
+        let mut a = Graph::new();
+        a.adj_list.insert(1, HashSet::from([2, 3]));
+        a.adj_list.insert(2, HashSet::from([1]));
+        a.adj_list.insert(3, HashSet::from([1]));
+        let mut b = Graph::new();
+        b.adj_list.insert(3, HashSet::from([1]));
+        b.adj_list.insert(2, HashSet::from([1]));
+        b.adj_list.insert(1, HashSet::from([3, 2]));
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        b.adj_list.get_mut(&2).unwrap().insert(3);
+        b.adj_list.get_mut(&3).unwrap().insert(2);
+        b.touch(); //Direct edits must drop the cached fingerprint
+        assert_ne!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[test]
+    fn test_concurrent_readers_match_single_thread() { //Several threads share one &Graph, filling its caches and running analyses at once; every result matches a sequential run
+        use crate::analysis::{bfs_distances, closeness_centrality_with, jaccard_similarity};
+        use crate::parallel::ParallelismConfig;
+        let build = || crate::generate::barabasi_albert(300, 3, 4).unwrap();
+        let sequential = ParallelismConfig::sequential();
+        let pairs: Vec<(usize, usize)> = (0..200).map(|i| (i, (i * 7 + 3) % 300)).collect();
+        let run = |graph: &Graph| {
+            let jaccard: Vec<f64> = pairs.iter().map(|&(u, v)| jaccard_similarity(graph, InternalId(u), InternalId(v))).collect();
+            let from_zero = bfs_distances(graph, InternalId(0));
+            let distances: Vec<usize> = (0..300).map(|n| from_zero.get(&n).copied().unwrap_or(usize::MAX)).collect();
+            (graph.fingerprint(), graph.sorted_neighbors(InternalId(5)).to_vec(), closeness_centrality_with(graph, &sequential), jaccard, distances)
+        };
+        let expected = run(&build());
+        let shared = build();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4).map(|_| scope.spawn(|| run(&shared))).collect();
+            for handle in handles {
+                assert!(handle.join().unwrap() == expected);
+            }
+        });
+    }
+
+    #[test]
+    fn test_load_errors() { //A missing file is an Io error, a non-numeric token is a Parse error with its line number
+        assert!(matches!(Graph::load_from_file("no/such/file.txt"), Err(GraphError::Io(_))));
+        let path = std::env::temp_dir().join(format!("fga_bad_edges_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1\n1 x\n").unwrap();
+        let result = Graph::load_from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        match result {
+            Err(GraphError::Parse { line, content }) => {
+                assert_eq!(line, 2);
+                assert_eq!(content, "1 x");
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_strictness() { //Lines with the wrong number of ids are counted and skipped, or rejected with strict; negative ids and empty files are errors
+        let path = std::env::temp_dir().join(format!("fga_loose_edges_{}.txt", std::process::id()));
+        let path_str = path.to_str().unwrap();
+        std::fs::write(&path, "0 1\n\n1 2 3\n4\n2 0\n").unwrap();
+        let (graph, skipped) = Graph::load_from_file_with(path_str, false).unwrap();
+        assert_eq!((graph.num_edges, skipped), (2, 2));
+        assert!(matches!(Graph::load_from_file_with(path_str, true), Err(GraphError::Parse { line: 3, .. })));
+        std::fs::write(&path, "0 1\n-1 2\n").unwrap();
+        assert!(matches!(Graph::load_from_file(path_str), Err(GraphError::Parse { line: 2, .. })));
+        std::fs::write(&path, "\n\n").unwrap();
+        assert!(matches!(Graph::load_from_file(path_str), Err(GraphError::EmptyFile(_))));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_load_duplicates_and_self_loops() { //0-1 listed three times (once reversed) and a "7 7" line: each friendship counts once, the self-loop adds node 7 with no friends
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/messy_edges.txt");
+        let (graph, reports) = Graph::load_from_files_with(&[PathBuf::from(path)], false).unwrap();
+        assert_eq!((graph.num_nodes, graph.num_edges), (6, 3));
+        assert_eq!(graph.adj_list.values().map(|f| f.len()).sum::<usize>(), 2 * graph.num_edges);
+        assert_eq!((graph.adj_list[&0].len(), graph.adj_list[&7].len()), (1, 0));
+        assert_eq!((reports[0].duplicates, reports[0].self_loops, reports[0].skipped), (2, 1, 2));
+    }
+
+    #[test]
+    fn test_load_from_files() { //Two shards sharing the 1-2 friendship (written the other way round in the second) load like their concatenation
+        let shard = |name: &str| std::env::temp_dir().join(format!("fga_shard_{}_{}.txt", name, std::process::id()));
+        let (first, second, joined) = (shard("a"), shard("b"), shard("joined"));
+        std::fs::write(&first, "0 1\n1 2\n0 1\n").unwrap();
+        std::fs::write(&second, "2 1\n2 3\n3 3\n4\n").unwrap();
+        std::fs::write(&joined, "0 1\n1 2\n0 1\n2 1\n2 3\n3 3\n4\n").unwrap();
+
+        let (graph, reports) = Graph::load_from_files_with(&[first.clone(), second.clone()], false).unwrap();
+        let (concatenated, skipped) = Graph::load_from_file_with(joined.to_str().unwrap(), false).unwrap();
+        assert_eq!((graph.num_nodes, graph.num_edges), (concatenated.num_nodes, concatenated.num_edges));
+        assert_eq!(graph.adj_list, concatenated.adj_list);
+        assert_eq!(graph.fingerprint(), concatenated.fingerprint());
+        assert_eq!((reports[0].edges, reports[0].duplicates), (2, 1));
+        assert_eq!((reports[1].edges, reports[1].duplicates, reports[1].self_loops, reports[1].skipped), (1, 1, 1, 1));
+        let total = LoadReport::combined(&reports);
+        assert_eq!((total.edges, total.duplicates, total.skipped), (graph.num_edges, 2, skipped));
+        for path in [first, second, joined] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_load_gzipped() { //The same edge list plain, gzipped as .txt.gz, gzipped under a .txt name (found by its magic bytes) and from memory loads the same graph and report
+        let text = "0 1\n1 2\n2 0\n1 0\n3 3\n4 5 6\n\n";
+        let file = |name: &str| std::env::temp_dir().join(format!("fga_gzip_{}_{}", std::process::id(), name));
+        let (plain, gz, disguised) = (file("plain.txt"), file("edges.txt.gz"), file("disguised.txt"));
+        std::fs::write(&plain, text).unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(text.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        std::fs::write(&gz, &compressed).unwrap();
+        std::fs::write(&disguised, &compressed).unwrap();
+
+        let (expected, reports) = Graph::load_from_files_with(std::slice::from_ref(&plain), false).unwrap();
+        assert_eq!((expected.num_nodes, expected.num_edges, reports[0].duplicates, reports[0].skipped), (4, 3, 1, 1));
+        for path in [&gz, &disguised] {
+            let (graph, gz_reports) = Graph::load_from_files_with(std::slice::from_ref(path), false).unwrap();
+            assert_graph_eq!(graph, expected);
+            assert_eq!((graph.num_edges, gz_reports[0].duplicates, gz_reports[0].self_loops, gz_reports[0].skipped), (3, 1, 1, 1));
+        }
+        assert_graph_eq!(Graph::load_from_reader(text.as_bytes()).unwrap(), expected);
+        std::fs::write(&gz, &compressed[..compressed.len() / 2]).unwrap();
+        assert!(matches!(Graph::load_from_file(gz.to_str().unwrap()), Err(GraphError::Io(_))));
+        for path in [plain, gz, disguised] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_mutation_api() { //Double adds and removals of missing nodes or edges change nothing; the counters always match the adjacency
+        let mut graph = Graph::new();
+        let consistent = |g: &Graph| g.num_nodes == g.adj_list.len() && g.num_edges == g.adj_list.values().map(|f| f.len()).sum::<usize>() / 2;
+        assert!(graph.add_edge(InternalId(0), InternalId(1)) && graph.add_edge(InternalId(1), InternalId(2)) && graph.add_edge(InternalId(2), InternalId(0)));
+        assert!(!graph.add_edge(InternalId(1), InternalId(0)) && !graph.add_edge(InternalId(0), InternalId(1)) && !graph.add_edge(InternalId(3), InternalId(3))); //Already there either way round; self-loops are refused
+        assert!(graph.add_node(InternalId(3)) && !graph.add_node(InternalId(3)) && !graph.add_node(InternalId(0)));
+        assert_eq!((graph.num_nodes, graph.num_edges), (4, 3));
+        assert!(consistent(&graph) && graph.has_edge(1, 0) && graph.friends(InternalId(3)) == Some(&HashSet::new()) && graph.friends(InternalId(9)).is_none());
+
+        assert!(!graph.remove_edge(InternalId(0), InternalId(3)) && !graph.remove_edge(InternalId(5), InternalId(6)));
+        assert!(graph.remove_edge(InternalId(1), InternalId(0)) && !graph.remove_edge(InternalId(0), InternalId(1)));
+        assert!(graph.remove_node(InternalId(2)) && !graph.remove_node(InternalId(2))); //Takes 1-2 and 2-0 with it
+        assert_eq!((graph.num_nodes, graph.num_edges), (3, 0));
+        assert!(consistent(&graph) && graph.adj_list.values().all(|friends| !friends.contains(&2)));
+        assert!(!graph.remove_edge(InternalId(1), InternalId(2)) && graph.num_edges == 0);
+        let version = graph.version;
+        assert!(graph.remove_node(InternalId(0)) && !graph.add_node(InternalId(1)));
+        assert_eq!(graph.version, version + 1);
+
+        graph.adj_list.get_mut(&1).unwrap().insert(1); //A self-loop put in by hand goes with its node
+        assert!(graph.remove_node(InternalId(1)) && consistent(&graph));
+    }
+
+    #[test]
+    fn test_validate_reports_broken_invariants() { //A loaded graph passes; each hand-made inconsistency is a Corrupt error naming it
+        let graph = Graph::from_edges(&[(0, 1), (1, 2), (5, 5)]);
+        assert!(graph.validate().is_ok() && Graph::new().validate().is_ok());
+        type Breaker = fn(&mut Graph);
+        let broken: [(&str, Breaker); 4] = [
+            ("num_nodes", |g| g.num_nodes += 1),
+            ("own friend", |g| _ = g.adj_list.get_mut(&1).unwrap().insert(1)),
+            ("other way round", |g| _ = g.adj_list.get_mut(&2).unwrap().remove(&1)),
+            ("degrees add up", |g| g.num_edges = 5),
+        ];
+        for (problem, breaking) in broken {
+            let mut copy = Graph::from_edges(&[(0, 1), (1, 2), (5, 5)]);
+            breaking(&mut copy);
+            assert!(matches!(copy.validate(), Err(GraphError::Corrupt(m)) if m.contains(problem)), "{}", problem);
+        }
+    }
+
+    #[test]
+    fn test_merge_nodes() { //1 and 2 share friend 3 and are friends with each other; merging 2 into 1 must not add a self-loop or a second 1-3 edge
+        let mut graph = Graph::new();
+        for (u, v) in [(1, 2), (1, 3), (2, 3), (2, 4), (4, 5)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph.merge_nodes(1, 2).unwrap();
+        assert_eq!(graph.adj_list[&1], HashSet::from([3, 4]));
+        assert_eq!(graph.adj_list[&3], HashSet::from([1]));
+        assert!(!graph.adj_list.contains_key(&2));
+        assert!(graph.adj_list.values().all(|friends| !friends.contains(&2)));
+        assert_eq!((graph.num_nodes, graph.num_edges), (4, 3));
+        assert_eq!(graph.num_edges, graph.adj_list.values().map(|f| f.len()).sum::<usize>() / 2);
+        assert!(matches!(graph.merge_nodes(1, 2), Err(GraphError::UnknownNode(2))));
+
+        graph.merge_many(&[(3, 4), (4, 5)]).unwrap(); //Chain: 3 -> 4 -> 5, so both end up in 5
+        assert_eq!(graph.adj_list.keys().copied().collect::<HashSet<_>>(), HashSet::from([1, 5]));
+        assert_eq!((graph.num_nodes, graph.num_edges), (2, 1));
+        assert!(matches!(graph.merge_many(&[(1, 5), (5, 1)]), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_load_snap_ego_dir() { //Two egos (0 and 10) sharing friend 2: ego edges are added, the shared friend-friend edge counts once
+        let dir = std::env::temp_dir().join(format!("fga_ego_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("0.edges"), "1 2\n2 3\n").unwrap();
+        fs::write(dir.join("0.circles"), "circle0\t1\t2\ncircle1\t3\n").unwrap();
+        fs::write(dir.join("0.feat"), "1 1 0\n2 0 1\n3 0 0\n4 1 1\n").unwrap();
+        fs::write(dir.join("0.egofeat"), "1 0\n").unwrap();
+        fs::write(dir.join("10.edges"), "2 3\n11 12\n").unwrap();
+        fs::write(dir.join("10.circles"), "circle0\t2\t11\t12\n").unwrap();
+
+        let (graph, circles, features) = Graph::load_snap_ego_dir(dir.to_str().unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        //Nodes 0-4 and 10-12; edges: 1-2, 2-3, 11-12, ego 0 to 1,2,3,4 (node 4 only appears in 0.feat), ego 10 to 2,3,11,12
+        assert_eq!(graph.num_nodes, 8);
+        assert_eq!(graph.num_edges, 3 + 4 + 4);
+        assert!(graph.adj_list[&0].contains(&4));
+        assert!(graph.adj_list[&10].contains(&2));
+        assert_eq!(circles, vec![HashSet::from([1, 2]), HashSet::from([3]), HashSet::from([2, 11, 12])]);
+        assert_eq!(features[&4], vec![true, true]);
+        assert_eq!(features[&0], vec![true, false]);
+        assert!(!features.contains_key(&11));
+    }
+
+    #[test]
+    fn test_huge_ids_are_compacted() { //64-bit style ids are stored as 0..n but written back out unchanged
+        let path = std::env::temp_dir().join(format!("fga_huge_ids_{}.txt", std::process::id()));
+        fs::write(&path, "1438292017341 7\n7 900000000000\n1438292017341 900000000000\n7 42\n").unwrap();
+        let graph = Graph::load_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!(graph.num_nodes, 4);
+        assert_eq!(graph.num_edges, 4);
+        assert!(graph.adj_list.keys().all(|&n| n < 4));
+        assert_eq!(graph.internal_id(NodeId(1438292017341)), Some(InternalId(3))); //Compact ids follow the order of the originals
+        assert_eq!(graph.original_id(InternalId(0)), NodeId(7));
+        assert_eq!(graph.internal_id(5.into()), None);
+        assert!(matches!(NodeIndex::try_from(u32::MAX as usize), Ok(NodeIndex(u32::MAX))));
+        assert!(matches!(NodeIndex::try_from(u32::MAX as usize + 1), Err(GraphError::Overflow { .. }))); //Refused instead of wrapping onto node 0
+        let mut grown = Graph::load_from_file(path.to_str().unwrap()).unwrap();
+        grown.add_edge(InternalId(3), InternalId(10)); //A node added after compaction gets an original id above every id in the file
+        grown.add_node(InternalId(2)); //Already there: keeps its id
+        assert_eq!((grown.original_id(InternalId(10)), grown.original_id(InternalId(3)), grown.original_id(InternalId(2))), (NodeId(1438292017342), NodeId(1438292017341), NodeId(900000000000)));
+        assert_eq!(grown.internal_id(NodeId(1438292017342)), Some(InternalId(10)));
+        assert_eq!(grown.internal_id(NodeId(10)), None);
+        let grown_path = std::env::temp_dir().join(format!("fga_huge_ids_grown_{}.txt", std::process::id()));
+        grown.write_edge_list(grown_path.to_str().unwrap()).unwrap();
+        let reloaded = Graph::load_from_file(grown_path.to_str().unwrap()).unwrap();
+        fs::remove_file(&grown_path).unwrap();
+        let overlap = crate::analysis::graph_jaccard(&grown, &reloaded); //Same nodes and edges by original id after a write and reload
+        assert_eq!((overlap.node_jaccard, overlap.edge_jaccard, overlap.common_nodes), (1.0, 1.0, 5));
+        assert_eq!(crate::analysis::graph_jaccard(&grown, &Graph::from_edges([(10, 3)])).common_nodes, 0); //A file that really has a node 10 shares nothing with the added node
+
+        graph.write_edge_list(path.to_str().unwrap()).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written, "7 42\n7 900000000000\n7 1438292017341\n900000000000 1438292017341\n");
+        assert!(Graph::load_from_file("no/such/file.txt").is_err());
+    }
+}
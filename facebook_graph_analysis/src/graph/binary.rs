@@ -0,0 +1,144 @@
+//Module: graph/binary.rs
+//Here we save a loaded Graph in a compact binary form and read it back, so a large edge list is parsed once and later runs load the snapshot.
+//Layout, little-endian: the 7 magic bytes and a format version byte, then as u64 the node count, the edge count and the number of original ids
+//(0 when the ids were not compacted) followed by those ids, then per node (by internal id) its id, its degree and its friends' ids in increasing order.
+//Anything that does not fit this layout, or a graph that fails Graph::validate, is rejected; load_cached then simply parses the text again
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use crate::error::GraphError;
+use crate::labels::IdMap;
+use super::Graph;
+use crate::labels::InternalId;
+
+const BINARY_MAGIC: &[u8; 7] = b"FGAGRPH";
+pub const BINARY_FORMAT_VERSION: u8 = 1; //Bump if the layout changes: caches in an older layout are then reparsed instead of misread
+
+impl Graph {
+    pub fn save_binary(&self, path: &str) -> Result<(), GraphError> { //Writes the graph in the layout above (the version counter and caches are not saved)
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(BINARY_MAGIC)?;
+        writer.write_all(&[BINARY_FORMAT_VERSION])?;
+        let mut put = |value: usize| writer.write_all(&(value as u64).to_le_bytes());
+        let num_originals = self.id_map.as_ref().map_or(0, IdMap::len);
+        for value in [self.num_nodes, self.num_edges, num_originals] {
+            put(value)?;
+        }
+        for compact in 0..num_originals {
+            put(self.original_id(InternalId(compact)).0)?;
+        }
+        let mut nodes: Vec<usize> = self.adj_list.keys().copied().collect();
+        nodes.sort();
+        for node in nodes {
+            let friends = self.sorted_neighbors(InternalId(node));
+            put(node)?;
+            put(friends.len())?;
+            for &friend in friends {
+                put(friend)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load_binary(path: &str) -> Result<Self, GraphError> { //Reads a file written by save_binary. A file from another format version, cut short or otherwise malformed is a Parse error (line 0)
+        let bytes = fs::read(path)?;
+        let bad = |why: &str| GraphError::Parse { line: 0, content: format!("{} is not a graph cache: {}", path, why) };
+        let (header, body) = bytes.split_at_checked(8).ok_or_else(|| bad("too short"))?;
+        if &header[..7] != BINARY_MAGIC {
+            return Err(bad("wrong magic bytes"));
+        }
+        if header[7] != BINARY_FORMAT_VERSION {
+            return Err(bad(&format!("format version {}, expected {}", header[7], BINARY_FORMAT_VERSION)));
+        }
+        if body.len() % 8 != 0 {
+            return Err(bad("its length is not a whole number of values"));
+        }
+        let mut values = body.chunks_exact(8).map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()) as usize);
+        let mut next = || values.next().ok_or_else(|| bad("it ends early"));
+        let (num_nodes, num_edges, num_originals) = (next()?, next()?, next()?);
+        let originals = (0..num_originals).map(|_| next()).collect::<Result<Vec<usize>, _>>()?;
+        let mut adj_list: HashMap<usize, HashSet<usize>> = HashMap::new();
+        for _ in 0..num_nodes {
+            let (node, degree) = (next()?, next()?);
+            let friends = (0..degree).map(|_| next()).collect::<Result<HashSet<usize>, _>>()?;
+            adj_list.insert(node, friends);
+        }
+        if next().is_ok() {
+            return Err(bad("it has data after the last node"));
+        }
+        let mut graph = Graph::new();
+        graph.adj_list = adj_list;
+        (graph.num_nodes, graph.num_edges) = (num_nodes, num_edges);
+        graph.id_map = if originals.is_empty() { None } else { Some(IdMap::from_ids(originals)?) };
+        graph.validate().map_err(|problem| bad(&problem.to_string()))?;
+        Ok(graph)
+    }
+
+    pub fn load_cached(txt_path: &str) -> Result<Self, GraphError> { //load_from_file through a binary cache at "<txt_path>.bin": used when it is newer than the text file and loads cleanly,
+        //otherwise the text is parsed and the cache (re)written. A cache that cannot be written is skipped; the graph is still returned
+        let cache = format!("{}.bin", txt_path);
+        let modified = |path: &str| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        if let (Some(cached), Some(text)) = (modified(&cache), modified(txt_path)) {
+            if cached > text {
+                if let Ok(graph) = Self::load_binary(&cache) {
+                    return Ok(graph);
+                }
+            }
+        }
+        let graph = Self::load_from_file(txt_path)?;
+        if graph.save_binary(&cache).is_err() {
+            fs::remove_file(PathBuf::from(&cache)).ok(); //Never leave a half-written cache behind
+        }
+        Ok(graph)
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_round_trip_and_fallback() { //Plain and compacted graphs survive a round trip; a truncated, foreign or inconsistent cache is refused and load_cached reparses instead
+        let dir = std::env::temp_dir().join(format!("fga_binary_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_string_lossy().into_owned();
+        let plain = Graph::from_edges(&[(0, 1), (1, 2), (2, 0), (2, 3), (7, 7)]);
+        let compacted = Graph::from_edges(&[(1 << 40, 5), (5, 1 << 41)]);
+        assert!(compacted.id_map.is_some());
+        for graph in [&plain, &compacted] {
+            graph.save_binary(&file("graph.bin")).unwrap();
+            let loaded = Graph::load_binary(&file("graph.bin")).unwrap();
+            assert_eq!((&loaded.adj_list, loaded.num_nodes, loaded.num_edges, &loaded.id_map), (&graph.adj_list, graph.num_nodes, graph.num_edges, &graph.id_map));
+        }
+
+        plain.save_binary(&file("graph.bin")).unwrap();
+        let bytes = fs::read(file("graph.bin")).unwrap();
+        let mut other_version = bytes.clone();
+        other_version[7] = BINARY_FORMAT_VERSION + 1;
+        let mut asymmetric = bytes.clone();
+        let friend_of_3 = asymmetric.len() - 24; //The file ends with node 3's only friend 2, then node 7 and its degree 0
+        asymmetric[friend_of_3] = 0;
+        for broken in [&bytes[..bytes.len() - 8], &bytes[..5], &other_version, &asymmetric, b"FGAGRPH\x01 not a graph".as_slice()] {
+            fs::write(file("broken.bin"), broken).unwrap();
+            assert!(matches!(Graph::load_binary(&file("broken.bin")), Err(GraphError::Parse { line: 0, .. })));
+        }
+
+        let text = file("edges.txt");
+        fs::write(&text, "0 1\n1 2\n2 0\n2 3\n7 7\n").unwrap();
+        let first = Graph::load_cached(&text).unwrap(); //No cache yet: parses and writes one
+        assert!(fs::metadata(format!("{}.bin", text)).is_ok());
+        crate::assert_graph_eq!(first, plain);
+        compacted.save_binary(&format!("{}.bin", text)).unwrap(); //A newer cache is trusted without looking at the text...
+        crate::assert_graph_eq!(Graph::load_cached(&text).unwrap(), compacted);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&text, "0 1\n1 2\n2 0\n2 3\n7 7\n").unwrap(); //...until the text changes after it
+        crate::assert_graph_eq!(Graph::load_cached(&text).unwrap(), plain);
+        fs::write(format!("{}.bin", text), &other_version).unwrap(); //Written after the text, so it is tried, refused and replaced
+        crate::assert_graph_eq!(Graph::load_cached(&text).unwrap(), plain);
+        assert_eq!(fs::read(format!("{}.bin", text)).unwrap(), bytes);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
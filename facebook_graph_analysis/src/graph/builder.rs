@@ -0,0 +1,364 @@
+//Module: graph/builder.rs
+//Here we build graphs edge by edge under explicit policies for the messy parts of real edge lists: repeated friendships, self-loops and lines that are not "u v"
+//Every loader and Graph::from_edges goes through GraphBuilder; the default policies are exactly what load_from_file has always done
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use crate::error::GraphError;
+use crate::labels::IdMap;
+use crate::weighted::WeightedGraph;
+use super::{open_edge_list, parse_numbers, Graph, LoadReport};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateEdges { //A friendship listed again, in either direction
+    #[default]
+    Ignore, //Counted in LoadReport::duplicates, otherwise no effect
+    Error,
+    CountAsWeight, //Build a WeightedGraph whose weights are how often each friendship was listed
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelfLoops { //"u u" lines. A Graph has no self-loop edges, so even Keep only keeps the person
+    #[default]
+    Keep, //u joins the graph (possibly isolated), no friendship is added
+    Drop, //The line is ignored; u only joins through other lines
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BadLine { //Lines without exactly two ids. Blank lines are always ignored, and non-numeric or negative ids are always a Parse error
+    #[default]
+    Skip, //Counted in LoadReport::skipped
+    Error, //What --strict asks for
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Delimiter { //What separates the fields of a line
+    #[default]
+    Whitespace, //Any run of spaces and tabs, as in the Facebook file
+    Comma,      //Fields are trimmed, so "0, 1" works
+    Tab,
+    Char(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadOptions { //How lines are split into ids; the defaults are load_from_file's format
+    pub delimiter: Delimiter,
+    pub skip_comments: bool,        //Ignore lines starting with comment_char (after leading whitespace), like SNAP's "# Nodes: 4039" lines
+    pub comment_char: char,
+    pub has_header: bool,           //The first line that is neither blank nor a comment names the columns (e.g. "source,target") and is ignored, in every file read
+    pub ignore_extra_columns: bool, //Use the first two fields of longer lines (e.g. a timestamp column) instead of treating them as bad lines; the other fields are not parsed
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self { delimiter: Delimiter::Whitespace, skip_comments: false, comment_char: '#', has_header: false, ignore_extra_columns: false }
+    }
+}
+
+#[derive(Debug)]
+pub enum Built { //What GraphBuilder::build produces: weighted only with DuplicateEdges::CountAsWeight
+    Plain(Graph),
+    Weighted(WeightedGraph),
+}
+
+#[derive(Debug, Default)]
+pub struct GraphBuilder {
+    duplicates: DuplicateEdges,
+    self_loops: SelfLoops,
+    bad_lines: BadLine,
+    format: LoadOptions,
+    adj_list: HashMap<usize, HashSet<usize>>,
+    counts: HashMap<(usize, usize), usize>, //Times each friendship (smaller id first) was listed; only kept for CountAsWeight
+    num_edges: usize,
+    reports: Vec<LoadReport>, //One per file read; edges added directly land in a report with an empty path
+    rejected: Option<GraphError>, //First edge a policy refused through the chained edge(), returned by build
+}
+
+impl GraphBuilder {
+    pub fn new() -> Self { //Default policies, the behavior of Graph::load_from_file
+        Self::default()
+    }
+
+    pub fn duplicates(mut self, policy: DuplicateEdges) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    pub fn self_loops(mut self, policy: SelfLoops) -> Self {
+        self.self_loops = policy;
+        self
+    }
+
+    pub fn bad_lines(mut self, policy: BadLine) -> Self {
+        self.bad_lines = policy;
+        self
+    }
+
+    pub fn format(mut self, options: LoadOptions) -> Self {
+        self.format = options;
+        self
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize) -> Result<(), GraphError> { //Adds one "u v" edge under the policies; a rejected edge is an InvalidParameter naming it
+        if self.reports.is_empty() {
+            self.reports.push(LoadReport::default());
+        }
+        self.insert(u, v).map_err(|problem| GraphError::InvalidParameter(format!("{} {}-{}", problem, u, v)))
+    }
+
+    pub fn edge(mut self, u: usize, v: usize) -> Self { //add_edge for chaining, e.g. GraphBuilder::new().edge(0, 1).edge(1, 2).node(5).graph(). A rejected edge is kept and returned by build
+        if let Err(problem) = self.add_edge(u, v) {
+            self.rejected.get_or_insert(problem);
+        }
+        self
+    }
+
+    pub fn node(mut self, u: usize) -> Self { //Adds a person with no friendship (yet): an isolated node unless an edge names it too
+        self.adj_list.entry(u).or_default();
+        self
+    }
+
+    pub fn graph(self) -> Result<Graph, GraphError> { //build_graph without the reports, to end a chain of edge() and node(). A policy that rejected an edge makes this its InvalidParameter
+        self.build_graph().map(|(graph, _)| graph)
+    }
+
+    pub fn read_file(&mut self, path: &Path) -> Result<&LoadReport, GraphError> { //Reads a whitespace-separated edge list, plain or gzipped; an edge already read from an earlier file counts as a duplicate, just like a repeat within one file
+        self.read(open_edge_list(path)?, path.to_path_buf())
+    }
+
+    pub fn read(&mut self, reader: impl BufRead, path: PathBuf) -> Result<&LoadReport, GraphError> { //read_file for any reader (path is only recorded in the report); a line rejected by a policy is a Parse error, and a line that cannot be read stops the load with an Io error instead of being skipped
+        self.reports.push(LoadReport { path, ..LoadReport::default() });
+        let mut header_pending = self.format.has_header;
+        for (index, line) in reader.lines().enumerate() {
+            let edge_line = line?;
+            let content = edge_line.trim();
+            if self.format.skip_comments && content.starts_with(self.format.comment_char) {
+                continue;
+            }
+            if header_pending && !content.is_empty() {
+                header_pending = false;
+                continue;
+            }
+            let rejected = match self.fields(&edge_line, index)?[..] {
+                [u, v] => self.insert(u, v).is_err(),
+                [] => false,
+                _ if self.bad_lines == BadLine::Error => true,
+                _ => {
+                    self.report().skipped += 1;
+                    false
+                }
+            };
+            if rejected {
+                return Err(GraphError::Parse { line: index + 1, content: edge_line });
+            }
+        }
+        Ok(self.reports.last().unwrap())
+    }
+
+    fn fields(&self, line: &str, index: usize) -> Result<Vec<usize>, GraphError> { //The ids on a line under the format (none for a blank line); index is the 0-based line index, for the error
+        if self.format.delimiter == Delimiter::Whitespace && !self.format.ignore_extra_columns {
+            return parse_numbers(line, index);
+        }
+        let tokens: Vec<&str> = match self.format.delimiter {
+            _ if line.trim().is_empty() => Vec::new(),
+            Delimiter::Whitespace => line.split_whitespace().collect(),
+            Delimiter::Comma => line.split(',').map(str::trim).collect(),
+            Delimiter::Tab => line.split('\t').map(str::trim).collect(),
+            Delimiter::Char(c) => line.split(c).map(str::trim).collect(),
+        };
+        let keep = if self.format.ignore_extra_columns { 2 } else { tokens.len() };
+        tokens
+            .into_iter()
+            .take(keep)
+            .map(|token| token.parse::<usize>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| GraphError::Parse { line: index + 1, content: line.to_string() })
+    }
+
+    fn report(&mut self) -> &mut LoadReport {
+        self.reports.last_mut().unwrap()
+    }
+
+    fn insert(&mut self, u: usize, v: usize) -> Result<(), &'static str> { //Applies the policies to one edge; Err says which policy rejected it
+        if u == v {
+            match self.self_loops {
+                SelfLoops::Keep => {
+                    self.adj_list.entry(u).or_default();
+                }
+                SelfLoops::Drop => {}
+                SelfLoops::Error => return Err("self-loop"),
+            }
+            self.report().self_loops += 1;
+            return Ok(());
+        }
+        let new_edge = !self.adj_list.get(&u).is_some_and(|n| n.contains(&v));
+        if !new_edge && self.duplicates == DuplicateEdges::Error {
+            return Err("duplicate edge");
+        }
+        if self.duplicates == DuplicateEdges::CountAsWeight {
+            *self.counts.entry((u.min(v), u.max(v))).or_default() += 1;
+        }
+        if new_edge {
+            self.adj_list.entry(u).or_default().insert(v);
+            self.adj_list.entry(v).or_default().insert(u);
+            self.num_edges += 1;
+            self.report().edges += 1;
+        } else {
+            self.report().duplicates += 1;
+        }
+        Ok(())
+    }
+
+    pub fn build(self) -> Result<(Built, Vec<LoadReport>), GraphError> { //The graph and one report per file. If files were read and none of them had a node, that is an EmptyFile error naming them
+        if let Some(problem) = self.rejected {
+            return Err(problem);
+        }
+        let reports = self.reports;
+        let file_reports: Vec<&LoadReport> = reports.iter().filter(|r| !r.path.as_os_str().is_empty()).collect();
+        if self.adj_list.is_empty() && !file_reports.is_empty() {
+            let names: Vec<String> = file_reports.iter().map(|r| r.path.display().to_string()).collect();
+            return Err(GraphError::EmptyFile(names.join(", ")));
+        }
+
+        if self.duplicates == DuplicateEdges::CountAsWeight {
+            let mut weighted = WeightedGraph::new();
+            self.adj_list.keys().for_each(|&node| weighted.add_node(node));
+            self.counts.into_iter().for_each(|((u, v), count)| weighted.add_edge(u, v, count as f64));
+            return Ok((Built::Weighted(weighted), reports));
+        }
+
+        let mut graph = Graph::new();
+        graph.num_nodes = self.adj_list.len();
+        graph.num_edges = self.num_edges;
+        graph.adj_list = self.adj_list;
+        debug_assert_eq!(graph.adj_list.values().map(|f| f.len()).sum::<usize>(), 2 * graph.num_edges, "degree sum must be twice the edge count");
+        let max_id = graph.adj_list.keys().max().copied().unwrap_or(0);
+        if IdMap::is_sparse(max_id, graph.num_nodes) {
+            graph.compact_ids()?;
+        }
+        Ok((Built::Plain(graph), reports))
+    }
+
+    pub fn build_graph(self) -> Result<(Graph, Vec<LoadReport>), GraphError> { //build for callers that need an unweighted Graph; CountAsWeight is an InvalidParameter here
+        match self.build()? {
+            (Built::Plain(graph), reports) => Ok((graph, reports)),
+            (Built::Weighted(_), _) => Err(GraphError::InvalidParameter("DuplicateEdges::CountAsWeight builds a WeightedGraph, use build".to_string())),
+        }
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::GraphRead;
+
+    const MESSY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/messy_edges.txt"); //3 friendships (0-1 listed three times), one "7 7" self-loop, two bad lines, one blank line
+
+    fn load(builder: GraphBuilder) -> Result<(Built, LoadReport), GraphError> {
+        let mut builder = builder;
+        builder.read_file(Path::new(MESSY))?;
+        let (built, reports) = builder.build()?;
+        Ok((built, reports[0].clone()))
+    }
+
+    #[test]
+    fn test_builder_policies() { //Every policy combination against the same messy file: counts, weights or the line that failed
+        for duplicates in [DuplicateEdges::Ignore, DuplicateEdges::Error, DuplicateEdges::CountAsWeight] {
+            for self_loops in [SelfLoops::Keep, SelfLoops::Drop, SelfLoops::Error] {
+                for bad_lines in [BadLine::Skip, BadLine::Error] {
+                    let case = format!("{:?}/{:?}/{:?}", duplicates, self_loops, bad_lines);
+                    let result = load(GraphBuilder::new().duplicates(duplicates).self_loops(self_loops).bad_lines(bad_lines));
+                    let expected_error_line = match (duplicates, self_loops, bad_lines) { //The first line a policy rejects
+                        (DuplicateEdges::Error, _, _) => Some(2),
+                        (_, SelfLoops::Error, _) => Some(4),
+                        (_, _, BadLine::Error) => Some(6),
+                        _ => None,
+                    };
+                    if let Some(line) = expected_error_line {
+                        assert!(matches!(result, Err(GraphError::Parse { line: l, .. }) if l == line), "{}", case);
+                        continue;
+                    }
+                    let (built, report) = result.unwrap();
+                    assert_eq!((report.edges, report.duplicates, report.self_loops, report.skipped), (3, 2, 1, 2), "{}", case);
+                    let nodes = if self_loops == SelfLoops::Keep { 6 } else { 5 };
+                    match built {
+                        Built::Plain(graph) => {
+                            assert_ne!(duplicates, DuplicateEdges::CountAsWeight, "{}", case);
+                            assert_eq!((graph.num_nodes, graph.num_edges), (nodes, 3), "{}", case);
+                            assert!(graph.has_edge(0, 1) && graph.has_edge(4, 5) && !graph.has_edge(0, 2), "{}", case);
+                        }
+                        Built::Weighted(weighted) => {
+                            assert_eq!(duplicates, DuplicateEdges::CountAsWeight, "{}", case);
+                            assert_eq!((weighted.num_nodes, weighted.num_edges), (nodes, 3), "{}", case);
+                            assert_eq!((weighted.weight(1, 0), weighted.weight(1, 2), weighted.weight(4, 5)), (Some(3.0), Some(1.0), Some(1.0)), "{}", case);
+                        }
+                    }
+                }
+            }
+        }
+
+        //The defaults are load_from_file's behavior; build_graph refuses the weighted policy; direct edges report rejections by edge
+        let mut defaults = GraphBuilder::new();
+        defaults.read_file(Path::new(MESSY)).unwrap();
+        let (graph, _) = defaults.build_graph().unwrap();
+        crate::assert_graph_eq!(graph, Graph::load_from_file(MESSY).unwrap());
+        crate::assert_graph_eq!(Graph::from_edges(&[(0, 1), (1, 0), (7, 7), (1, 2), (5, 4)]), graph);
+        let mut weighted = GraphBuilder::new().duplicates(DuplicateEdges::CountAsWeight);
+        weighted.add_edge(0, 1).unwrap();
+        assert!(matches!(weighted.build_graph(), Err(GraphError::InvalidParameter(_))));
+        let mut strict = GraphBuilder::new().duplicates(DuplicateEdges::Error);
+        strict.add_edge(0, 1).unwrap();
+        assert!(matches!(strict.add_edge(1, 0), Err(GraphError::InvalidParameter(m)) if m == "duplicate edge 1-0"));
+    }
+
+    #[test]
+    fn test_chained_builder() { //Repeated friendships count once, a self-loop only adds the person, node() adds isolated people and a rejected edge surfaces at build
+        let graph = GraphBuilder::new().edge(0, 1).edge(1, 0).edge(1, 2).edge(0, 1).edge(3, 3).node(4).node(2).graph().unwrap();
+        assert_eq!((graph.num_nodes, graph.num_edges), (5, 2));
+        let mut degrees = graph.all_degrees();
+        degrees.sort();
+        assert_eq!(degrees, vec![(0, 1), (1, 2), (2, 1), (3, 0), (4, 0)]);
+        assert!(graph.validate().is_ok());
+        crate::assert_graph_eq!(Graph::from_edges(vec![(0, 1), (1, 0), (1, 2), (3, 3)].into_iter().chain([(0, 1)])), GraphBuilder::new().edge(0, 1).edge(1, 2).node(3).graph().unwrap());
+        let strict = GraphBuilder::new().duplicates(DuplicateEdges::Error).edge(0, 1).edge(1, 0).edge(5, 6);
+        assert!(matches!(strict.build(), Err(GraphError::InvalidParameter(m)) if m == "duplicate edge 1-0"));
+        let strict = GraphBuilder::new().duplicates(DuplicateEdges::Error).edge(0, 1).edge(1, 0);
+        assert!(matches!(strict.graph(), Err(GraphError::InvalidParameter(m)) if m == "duplicate edge 1-0")); //graph() reports the rejection too instead of panicking
+    }
+
+    #[test]
+    fn test_load_options() { //A CSV with a header, a SNAP file with '#' comments and a tab-separated file with a timestamp column all give the triangle 0-1-2
+        let read = |options: LoadOptions, text: &str| -> Result<(Graph, LoadReport), GraphError> {
+            let mut builder = GraphBuilder::new().format(options);
+            builder.read(text.as_bytes(), PathBuf::new())?;
+            builder.build_graph().map(|(graph, reports)| (graph, reports[0].clone()))
+        };
+        let triangle = Graph::from_edges(&[(0, 1), (1, 2), (2, 0)]);
+        let csv = LoadOptions { delimiter: Delimiter::Comma, has_header: true, ..LoadOptions::default() };
+        let snap = LoadOptions { skip_comments: true, ..LoadOptions::default() };
+        let timestamped = LoadOptions { delimiter: Delimiter::Tab, ignore_extra_columns: true, ..LoadOptions::default() };
+        let cases = [
+            (csv, "\nsource,target\n0,1\n1, 2\r\n\n2,0\n"),
+            (snap, "# Undirected graph: triangle\n# Nodes: 3 Edges: 3\n0 1\n  # indented comment\n1\t2\n2 0\n"),
+            (timestamped, "0\t1\t2009-01-04\n1\t2\t1600000000\n2\t0\n"),
+        ];
+        for (options, text) in cases {
+            let (graph, report) = read(options, text).unwrap();
+            crate::assert_graph_eq!(graph, triangle);
+            assert_eq!((report.edges, report.skipped), (3, 0), "{:?}", options);
+        }
+
+        let (_, report) = read(LoadOptions { delimiter: Delimiter::Tab, ..LoadOptions::default() }, "0\t1\t5\n1\t2\n").unwrap(); //Without ignore_extra_columns a third field makes a bad line
+        assert_eq!((report.edges, report.skipped), (1, 1));
+        assert!(matches!(read(LoadOptions::default(), "source target\n0 1\n"), Err(GraphError::Parse { line: 1, .. })));
+        assert!(matches!(read(snap, "0 1\n1 2 # trailing\n"), Err(GraphError::Parse { line: 2, .. })));
+        assert!(matches!(read(LoadOptions { delimiter: Delimiter::Char(';'), ..LoadOptions::default() }, "0;1\n1;;2\n"), Err(GraphError::Parse { line: 2, .. })));
+
+        let path = std::env::temp_dir().join(format!("fga_options_{}.csv", std::process::id()));
+        std::fs::write(&path, "from,to\n0,1\n1,2\n2,0\n").unwrap();
+        crate::assert_graph_eq!(Graph::load_with_options(path.to_str().unwrap(), &csv).unwrap(), triangle);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+//Module: graph/external.rs
+//Here we answer basic questions about edge lists too large to load as a Graph: the file is streamed line by line and no adjacency set is ever built.
+//Only one small entry per node is kept (a degree count, or a union-find slot), so memory grows with the number of nodes rather than edges.
+//Lines are read like load_from_file reads them (gzipped files included): self-loops keep the node without adding a friendship, lines without exactly two ids are skipped.
+//Without adjacency sets a repeated friendship cannot be recognized, so a duplicate line counts twice towards both degrees
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+use crate::error::GraphError;
+use super::{open_edge_list, parse_numbers};
+
+fn for_each_edge(path: &str, mut visit: impl FnMut(usize, usize)) -> Result<(), GraphError> { //Calls visit(u, v) for every two-id line, in file order
+    let reader = open_edge_list(Path::new(path))?;
+    let mut any = false;
+    for (index, line) in reader.lines().enumerate() {
+        if let [u, v] = parse_numbers(&line?, index)?[..] {
+            visit(u, v);
+            any = true;
+        }
+    }
+    if !any {
+        return Err(GraphError::EmptyFile(path.to_string()));
+    }
+    Ok(())
+}
+
+pub fn degree_count_from_file(path: &str) -> Result<HashMap<usize, u32>, GraphError> { //Degree of every node in the file (0 for a node only seen in a self-loop), by input-file id
+    let mut degrees: HashMap<usize, u32> = HashMap::new();
+    for_each_edge(path, |u, v| {
+        let step = u32::from(u != v);
+        *degrees.entry(u).or_insert(0) += step;
+        *degrees.entry(v).or_insert(0) += step;
+    })?;
+    Ok(degrees)
+}
+
+pub fn component_sizes_from_file(path: &str) -> Result<Vec<usize>, GraphError> { //Sizes of the connected components, largest first, found with a union-find over the node ids
+    //Ids are numbered densely as they appear so the union-find itself is two flat u32 arrays; only the id lookup is a HashMap
+    let mut slot: HashMap<usize, u32> = HashMap::new();
+    let mut parent: Vec<u32> = Vec::new();
+    let mut size: Vec<u32> = Vec::new();
+    let mut index_of = |id: usize, parent: &mut Vec<u32>, size: &mut Vec<u32>| -> u32 {
+        *slot.entry(id).or_insert_with(|| {
+            parent.push(parent.len() as u32);
+            size.push(1);
+            parent.len() as u32 - 1
+        })
+    };
+    for_each_edge(path, |u, v| {
+        let (a, b) = (index_of(u, &mut parent, &mut size), index_of(v, &mut parent, &mut size));
+        let (mut a, mut b) = (root(&mut parent, a), root(&mut parent, b));
+        if a != b {
+            if size[a as usize] < size[b as usize] { //Union by size keeps the trees shallow
+                std::mem::swap(&mut a, &mut b);
+            }
+            parent[b as usize] = a;
+            size[a as usize] += size[b as usize];
+        }
+    })?;
+    let mut sizes: Vec<usize> = (0..parent.len() as u32).filter(|&n| parent[n as usize] == n).map(|n| size[n as usize] as usize).collect();
+    sizes.sort_by(|a, b| b.cmp(a));
+    Ok(sizes)
+}
+
+fn root(parent: &mut [u32], mut node: u32) -> u32 { //Union-find lookup with path halving
+    while parent[node as usize] != node {
+        parent[node as usize] = parent[parent[node as usize] as usize];
+        node = parent[node as usize];
+    }
+    node
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::InternalId;
+    use crate::analysis::connected_components;
+    use crate::graph::Graph;
+
+    #[test]
+    fn test_streaming_matches_loaded_graph() { //Three components (one is a lone self-loop node), a skipped line and huge ids: same degrees and component sizes as the loaded Graph
+        let path = std::env::temp_dir().join(format!("fga_streaming_{}.txt", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "1 2\n2 3\n3 1\n3 4\n\n10 11\n7000000000 7000000000\n5\n11 12\n").unwrap();
+        let graph = Graph::load_from_file(path).unwrap();
+
+        let degrees = degree_count_from_file(path).unwrap();
+        assert_eq!(degrees.len(), graph.num_nodes);
+        for (&node, friends) in &graph.adj_list {
+            assert_eq!(degrees[&graph.original_id(InternalId(node)).0] as usize, friends.len());
+        }
+        let loaded: Vec<usize> = connected_components(&graph).iter().map(Vec::len).collect();
+        assert_eq!(component_sizes_from_file(path).unwrap(), loaded);
+        assert_eq!(loaded, vec![4, 3, 1]);
+
+        std::fs::write(path, "\n").unwrap();
+        assert!(matches!(degree_count_from_file(path), Err(GraphError::EmptyFile(_))));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+//Module: graph/sampling.rs
+//Here we keep the random samplers the estimators share (pivots, sampled groups, random pairs and non-edges, degree-stratified nodes), so the same seed means the same sample everywhere
+//Items are always visited in sorted order before sampling, so results never depend on HashMap iteration order
+use std::collections::HashSet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use super::Graph;
+
+pub fn reservoir_sample<T>(items: impl IntoIterator<Item = T>, k: usize, seed: u64) -> Vec<T> { //Algorithm R: k items chosen uniformly without replacement in one pass (everything if there are at most k)
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut reservoir = Vec::with_capacity(k);
+    for (i, item) in items.into_iter().enumerate() {
+        if i < k {
+            reservoir.push(item);
+        } else {
+            let slot = rng.gen_range(0..=i); //Item i replaces a kept item with probability k / (i + 1)
+            if slot < k {
+                reservoir[slot] = item;
+            }
+        }
+    }
+    reservoir
+}
+
+pub fn sample_nodes(graph: &Graph, k: usize, seed: u64) -> Vec<usize> { //k distinct nodes, uniformly at random
+    let mut nodes: Vec<usize> = graph.adj_list.keys().copied().collect();
+    nodes.sort();
+    reservoir_sample(nodes, k, seed)
+}
+
+pub fn sample_edges(graph: &Graph, k: usize, seed: u64) -> Vec<(usize, usize)> { //k distinct edges (u < v), uniformly at random; streams the edges instead of collecting them all
+    let mut nodes: Vec<usize> = graph.adj_list.keys().copied().collect();
+    nodes.sort();
+    let edges = nodes.into_iter().flat_map(|u| {
+        let mut higher: Vec<usize> = graph.adj_list[&u].iter().copied().filter(|&v| v > u).collect();
+        higher.sort();
+        higher.into_iter().map(move |v| (u, v))
+    });
+    reservoir_sample(edges, k, seed)
+}
+
+pub fn sample_node_pairs(graph: &Graph, k: usize, seed: u64, distinct: bool) -> Vec<(usize, usize)> { //k unordered pairs of different nodes (u < v), uniformly at random. With distinct no pair repeats (and at most every pair is returned), otherwise pairs are drawn independently
+    let mut nodes: Vec<usize> = graph.adj_list.keys().copied().collect();
+    nodes.sort();
+    let n = nodes.len();
+    let total_pairs = n * n.saturating_sub(1) / 2;
+    if n < 2 {
+        return Vec::new();
+    }
+    if distinct && k >= total_pairs { //Asking for every pair (or more)
+        return (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).map(|(i, j)| (nodes[i], nodes[j])).collect();
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::with_capacity(k);
+    while pairs.len() < k { //Rejection sampling: draw two different positions; with distinct, redraw pairs already taken
+        let i = rng.gen_range(0..n);
+        let j = rng.gen_range(0..n - 1);
+        let j = if j >= i { j + 1 } else { j };
+        let pair = (nodes[i.min(j)], nodes[i.max(j)]);
+        if !distinct || seen.insert(pair) {
+            pairs.push(pair);
+        }
+    }
+    pairs
+}
+
+const NON_EDGE_DRAWS_PER_SAMPLE: usize = 16; //sample_non_edges gives up on rejection after this many draws per requested pair (plus a few) and enumerates instead
+
+pub fn sample_non_edges(graph: &Graph, k: usize, seed: u64) -> Vec<(usize, usize)> { //k distinct pairs of different nodes that are not friends (u < v), uniformly at random, e.g. negatives for link prediction. Every non-edge if there are at most k
+    let mut nodes: Vec<usize> = graph.adj_list.keys().copied().collect();
+    nodes.sort();
+    let n = nodes.len();
+    let non_edges = (n * n.saturating_sub(1) / 2).saturating_sub(graph.num_edges);
+    let every_non_edge = || nodes.iter().enumerate().flat_map(|(i, &u)| nodes[i + 1..].iter().filter(move |&&v| !graph.adj_list[&u].contains(&v)).map(move |&v| (u, v)));
+    if k >= non_edges {
+        return every_non_edge().collect();
+    }
+    //Rejection sampling: draw two different positions, redraw friends and pairs already taken. In a dense graph almost every draw is rejected,
+    //so the number of draws is capped; past the cap the non-edges are enumerated (cheap then, as there are few) and sampled from directly. Either way the loop ends
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::with_capacity(k);
+    for _ in 0..NON_EDGE_DRAWS_PER_SAMPLE * k + 64 {
+        if pairs.len() == k {
+            return pairs;
+        }
+        let i = rng.gen_range(0..n);
+        let j = rng.gen_range(0..n - 1);
+        let j = if j >= i { j + 1 } else { j };
+        let pair = (nodes[i.min(j)], nodes[i.max(j)]);
+        if !graph.adj_list[&pair.0].contains(&pair.1) && seen.insert(pair) {
+            pairs.push(pair);
+        }
+    }
+    if pairs.len() == k {
+        return pairs;
+    }
+    reservoir_sample(every_non_edge(), k, seed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Allocation { //How stratified_by_degree splits the sample between degree buckets
+    #[default]
+    Proportional, //In proportion to bucket size (a uniform sample, but with every bucket represented)
+    Equal,        //The same number from every bucket, so the few hubs are sampled far more often than uniform sampling would
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegreeBucket {
+    pub min_degree: usize, //Degree range of the bucket, inclusive
+    pub max_degree: usize,
+    pub population: usize, //Nodes in the graph with a degree in the range
+    pub sampled: usize,
+    pub weight: f64, //population / sampled: how many nodes each sampled one stands for (0.0 if none was sampled)
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StratifiedSample {
+    pub nodes: Vec<usize>,   //The sample, bucket by bucket
+    pub weights: Vec<f64>,   //Weight of each sampled node (its bucket's weight), for reweighted estimates
+    pub buckets: Vec<DegreeBucket>,
+}
+
+pub fn stratified_by_degree(graph: &Graph, k: usize, num_buckets: usize, allocation: Allocation, seed: u64) -> StratifiedSample { //k distinct nodes drawn separately from degree-quantile buckets (same seed = same sample)
+    //Bucket boundaries sit at the degree quantiles, but nodes of equal degree always share a bucket, so heavy ties can leave fewer buckets than asked for.
+    //Every non-empty bucket gets at least one node when k allows it; the rest of k follows the allocation by largest remainder, capped at the bucket sizes
+    let mut nodes: Vec<(usize, usize)> = graph.adj_list.iter().map(|(&node, friends)| (friends.len(), node)).collect();
+    nodes.sort();
+    let n = nodes.len();
+    let mut strata: Vec<&[(usize, usize)]> = Vec::new();
+    let mut start = 0;
+    for b in 1..=num_buckets.max(1) {
+        let mut end = (b * n / num_buckets.max(1)).max(start);
+        while end < n && end > 0 && nodes[end].0 == nodes[end - 1].0 { //Keep equal degrees together
+            end += 1;
+        }
+        if end > start {
+            strata.push(&nodes[start..end]);
+            start = end;
+        }
+    }
+
+    let k = k.min(n);
+    let share = |stratum: &[(usize, usize)]| match allocation {
+        Allocation::Proportional => stratum.len() as f64,
+        Allocation::Equal => 1.0,
+    };
+    let total_share: f64 = strata.iter().map(|s| share(s)).sum();
+    let mut quota: Vec<usize> = strata.iter().map(|s| usize::from(k >= strata.len()).min(s.len())).collect();
+    while quota.iter().sum::<usize>() < k { //Largest remainder against the ideal split, among buckets with room left
+        let ideal = |i: usize| k as f64 * share(strata[i]) / total_share;
+        let open: Vec<usize> = (0..strata.len()).filter(|&i| quota[i] < strata[i].len()).collect();
+        let best = *open.iter().max_by(|&&a, &&b| (ideal(a) - quota[a] as f64).total_cmp(&(ideal(b) - quota[b] as f64)).then(b.cmp(&a))).unwrap();
+        quota[best] += 1;
+    }
+
+    let mut sample = StratifiedSample::default();
+    for (i, stratum) in strata.iter().enumerate() {
+        let members: Vec<usize> = stratum.iter().map(|&(_, node)| node).collect();
+        let chosen = reservoir_sample(members, quota[i], seed.wrapping_add(i as u64));
+        let weight = if chosen.is_empty() { 0.0 } else { stratum.len() as f64 / chosen.len() as f64 };
+        sample.weights.extend(std::iter::repeat_n(weight, chosen.len()));
+        sample.buckets.push(DegreeBucket { min_degree: stratum[0].0, max_degree: stratum[stratum.len() - 1].0, population: stratum.len(), sampled: chosen.len(), weight });
+        sample.nodes.extend(chosen);
+    }
+    sample
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::InternalId;
+    use std::collections::HashMap;
+
+    fn chi_square<T: std::hash::Hash + Eq>(draws: impl Iterator<Item = T>, categories: usize, total: usize) -> f64 { //Pearson statistic of the draw counts against an even spread over the categories
+        let mut counts: HashMap<T, usize> = HashMap::new();
+        for d in draws {
+            *counts.entry(d).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), categories);
+        let expected = total as f64 / categories as f64;
+        counts.values().map(|&c| (c as f64 - expected).powi(2) / expected).sum()
+    }
+
+    fn square_with_diagonal() -> Graph { //Nodes 0..4 in a cycle plus the chord 0-2: 4 nodes, 5 edges, 6 pairs
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 3), (3, 0), (0, 2)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph
+    }
+
+    #[test]
+    fn test_samplers_are_uniform() { //Single draws over many seeds spread evenly (chi-square far below the 0.1% critical values 16.3 / 18.5 / 20.5 for 3 / 4 / 5 degrees of freedom)
+        let graph = square_with_diagonal();
+        let runs = 6000;
+        assert!(chi_square((0..runs).map(|s| sample_nodes(&graph, 1, s as u64)[0]), 4, runs) < 16.3);
+        assert!(chi_square((0..runs).map(|s| sample_edges(&graph, 1, s as u64)[0]), 5, runs) < 18.5);
+        assert!(chi_square((0..runs).map(|s| sample_node_pairs(&graph, 1, s as u64, true)[0]), 6, runs) < 20.5);
+        let independent = sample_node_pairs(&graph, runs, 1, false);
+        assert!(chi_square(independent.into_iter(), 6, runs) < 20.5);
+    }
+
+    #[test]
+    fn test_samplers_are_deterministic_and_distinct() { //Same seed, same sample; distinct samples never repeat and stop at the population size
+        let graph = square_with_diagonal();
+        assert_eq!(sample_edges(&graph, 3, 9), sample_edges(&graph, 3, 9));
+        let pairs = sample_node_pairs(&graph, 5, 3, true);
+        assert_eq!(pairs.iter().collect::<HashSet<_>>().len(), 5);
+        assert_eq!(sample_node_pairs(&graph, 50, 3, true).len(), 6);
+        assert_eq!(sample_nodes(&graph, 10, 3).len(), 4);
+    }
+
+    #[test]
+    fn test_sample_non_edges() { //Path 0-1-2-3-4 (6 non-edges); in the 4-clique plus a pendant only the leaf's 3 of 10 pairs are non-edges, and every sample stays among them
+        let mut path = Graph::new();
+        for node in 0..4 {
+            path.add_edge(InternalId(node), InternalId(node + 1));
+        }
+        let sample = sample_non_edges(&path, 4, 7);
+        assert_eq!(sample, sample_non_edges(&path, 4, 7));
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 4);
+        assert!(sample.iter().all(|&(u, v)| u < v && !path.adj_list[&u].contains(&v)));
+        assert_eq!(sample_non_edges(&path, 10, 7), vec![(0, 2), (0, 3), (0, 4), (1, 3), (1, 4), (2, 4)]);
+
+        let mut dense = Graph::new();
+        for u in 0..4 {
+            for v in u + 1..4 {
+                dense.add_edge(InternalId(u), InternalId(v));
+            }
+        }
+        dense.add_edge(InternalId(3), InternalId(4));
+        for seed in 0..20 {
+            let sample = sample_non_edges(&dense, 2, seed);
+            assert_eq!(sample.len(), 2);
+            assert!(sample.iter().all(|&(u, v)| v == 4 && u < 3));
+        }
+        assert!(sample_non_edges(&Graph::new(), 3, 1).is_empty());
+    }
+
+    #[test]
+    fn test_stratified_by_degree() { //Star 0 with leaves 1-8 plus a separate triangle 9-11: degrees 1 (x8), 2 (x3), 8 (x1). Four quantile buckets collapse to three, as the ties cannot be split
+        let mut graph = Graph::new();
+        for (u, v) in (1..=8).map(|leaf| (0, leaf)).chain([(9, 10), (10, 11), (11, 9)]) {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let proportional = stratified_by_degree(&graph, 6, 4, Allocation::Proportional, 1);
+        let ranges: Vec<(usize, usize, usize)> = proportional.buckets.iter().map(|b| (b.min_degree, b.max_degree, b.population)).collect();
+        assert_eq!(ranges, vec![(1, 1, 8), (2, 2, 3), (8, 8, 1)]);
+        let counts = |sample: &StratifiedSample| sample.buckets.iter().map(|b| (b.sampled, b.weight)).collect::<Vec<_>>();
+        assert_eq!(counts(&proportional), vec![(4, 2.0), (1, 3.0), (1, 1.0)]); //Ideal split 4 / 1.5 / 0.5, but every bucket gets one
+        let equal = stratified_by_degree(&graph, 6, 4, Allocation::Equal, 1);
+        assert_eq!(counts(&equal), vec![(3, 8.0 / 3.0), (2, 1.5), (1, 1.0)]); //The hub bucket is full, so its share goes to the others
+
+        for sample in [&proportional, &equal] {
+            assert_eq!(sample.nodes.iter().collect::<HashSet<_>>().len(), 6);
+            let mut start = 0;
+            for bucket in &sample.buckets {
+                for node in &sample.nodes[start..start + bucket.sampled] {
+                    assert!((bucket.min_degree..=bucket.max_degree).contains(&graph.adj_list[node].len()));
+                }
+                assert_eq!(sample.weights[start..start + bucket.sampled], vec![bucket.weight; bucket.sampled]);
+                start += bucket.sampled;
+            }
+        }
+        assert_eq!(proportional, stratified_by_degree(&graph, 6, 4, Allocation::Proportional, 1));
+        assert_eq!(stratified_by_degree(&graph, 50, 4, Allocation::Equal, 1).nodes.len(), 12);
+        assert!(stratified_by_degree(&graph, 0, 4, Allocation::Equal, 1).nodes.is_empty());
+    }
+}
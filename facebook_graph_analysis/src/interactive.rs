@@ -0,0 +1,146 @@
+//Module: interactive.rs
+//Here we answer one-line questions about a loaded graph typed at a prompt ("--interactive"), so a big graph is loaded once and explored without rerunning the pipeline
+//parse_command turns a line into a Command without touching the graph, run_command answers one, and run_session loops over the input until "quit" or its end.
+//Nodes are typed and printed as in the input file (or by label for --string-ids graphs); a mistyped line prints the command list and the session carries on
+use std::io::{BufRead, Write};
+use crate::analysis::{jaccard_similarity, path_between, recommend_friends, SimilarityMetric};
+use crate::error::GraphError;
+use crate::graph::Graph;
+use crate::labels::NodeId;
+use crate::pipeline::AnalysisResults;
+use crate::report::{MetricFamily, NumberFormat};
+
+pub const COMMANDS: &str = "Commands: degree <node>, neighbors <node>, distance <u> <v>, path <u> <v>, jaccard <u> <v>, recommend <node> <k>, quit";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Degree(NodeId),
+    Neighbors(NodeId),
+    Distance(NodeId, NodeId),
+    Path(NodeId, NodeId),
+    Jaccard(NodeId, NodeId),
+    Recommend(NodeId, usize), //The node and how many suggestions to print
+    Quit,
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> { //One command per line, words separated by whitespace. The error says what was expected, e.g. "distance needs two node ids, e.g. distance 0 107"
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&name, rest)) = words.split_first() else {
+        return Err(String::from("empty command"));
+    };
+    let numbers: Option<Vec<usize>> = rest.iter().map(|word| word.parse().ok()).collect();
+    let usage = |what: &str, example: &str| Err(format!("{} needs {}, e.g. {} {}", name, what, name, example));
+    match (name, numbers.as_deref()) {
+        ("quit" | "exit", Some([])) => Ok(Command::Quit),
+        ("quit" | "exit", _) => Err(format!("{} takes no arguments", name)),
+        ("degree", Some(&[node])) => Ok(Command::Degree(NodeId(node))),
+        ("neighbors", Some(&[node])) => Ok(Command::Neighbors(NodeId(node))),
+        ("degree" | "neighbors", _) => usage("one node id", "107"),
+        ("distance", Some(&[u, v])) => Ok(Command::Distance(NodeId(u), NodeId(v))),
+        ("path", Some(&[u, v])) => Ok(Command::Path(NodeId(u), NodeId(v))),
+        ("jaccard", Some(&[u, v])) => Ok(Command::Jaccard(NodeId(u), NodeId(v))),
+        ("distance" | "path" | "jaccard", _) => usage("two node ids", "0 107"),
+        ("recommend", Some(&[node, k])) => Ok(Command::Recommend(NodeId(node), k)),
+        ("recommend", _) => usage("a node id and a count", "107 5"),
+        (other, _) => Err(format!("unknown command {:?}", other)),
+    }
+}
+
+pub fn run_command(graph: &Graph, results: &AnalysisResults, format: &NumberFormat, command: Command, out: &mut impl Write) -> Result<(), GraphError> { //Prints the answer to one command (nothing for Quit). An id the graph does not have prints "node N not found"
+    let find = |id: NodeId| graph.internal_id(id).ok_or(id);
+    let answer = match command {
+        Command::Quit => return Ok(()),
+        Command::Degree(id) => find(id).map(|node| format!("{} has {} friends", results.name(node.index()), graph.adjacency()[&node.index()].len())),
+        Command::Neighbors(id) => find(id).map(|node| {
+            let friends: Vec<String> = graph.sorted_neighbors(node).iter().map(|&friend| results.name(friend)).collect();
+            format!("{} ({} friends): {}", results.name(node.index()), friends.len(), friends.join(", "))
+        }),
+        Command::Distance(u, v) | Command::Path(u, v) => find(u).and_then(|a| Ok((a, find(v)?))).map(|(a, b)| match path_between(graph, a, b) {
+            None => format!("{} and {} are not connected", results.name(a.index()), results.name(b.index())),
+            Some(path) if matches!(command, Command::Distance(..)) => format!("{} and {} are {} steps apart", results.name(a.index()), results.name(b.index()), path.len() - 1),
+            Some(path) => path.iter().map(|&node| results.name(node)).collect::<Vec<_>>().join(" → "),
+        }),
+        Command::Jaccard(u, v) => find(u).and_then(|a| Ok((a, find(v)?))).map(|(a, b)| {
+            format!("Jaccard similarity of {} and {}: {}", results.name(a.index()), results.name(b.index()), format.format(MetricFamily::Similarity, jaccard_similarity(graph, a, b)))
+        }),
+        Command::Recommend(id, k) => find(id).map(|node| {
+            let suggestions: Vec<String> = recommend_friends(graph, node, k, SimilarityMetric::Jaccard)
+                .into_iter()
+                .map(|(candidate, score)| format!("{} ({})", results.name(candidate), format.format(MetricFamily::Similarity, score)))
+                .collect();
+            format!("{} → {}", results.name(node.index()), if suggestions.is_empty() { String::from("no suggestions") } else { suggestions.join(", ") })
+        }),
+    };
+    match answer {
+        Ok(text) => writeln!(out, "{}", text)?,
+        Err(missing) => writeln!(out, "node {} not found", missing)?,
+    }
+    Ok(())
+}
+
+pub fn run_session(graph: &Graph, results: &AnalysisResults, format: &NumberFormat, input: impl BufRead, out: &mut impl Write) -> Result<(), GraphError> { //Prompts with "> " and answers each line until "quit" or the end of the input; blank lines are ignored
+    writeln!(out, "{}", COMMANDS)?;
+    write!(out, "> ")?;
+    out.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            match parse_command(&line) {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => run_command(graph, results, format, command, out)?,
+                Err(problem) => writeln!(out, "{}\n{}", problem, COMMANDS)?,
+            }
+        }
+        write!(out, "> ")?;
+        out.flush()?;
+    }
+    writeln!(out)?; //End of input (e.g. Ctrl-D): finish the prompt line
+    Ok(())
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_command() { //Every command with its arguments, the synonyms of quit, and malformed lines naming what was expected
+        assert_eq!(parse_command("degree 107"), Ok(Command::Degree(NodeId(107))));
+        assert_eq!(parse_command("  neighbors\t0 "), Ok(Command::Neighbors(NodeId(0))));
+        assert_eq!(parse_command("distance 0 107"), Ok(Command::Distance(NodeId(0), NodeId(107))));
+        assert_eq!(parse_command("path 3 4"), Ok(Command::Path(NodeId(3), NodeId(4))));
+        assert_eq!(parse_command("jaccard 1 2"), Ok(Command::Jaccard(NodeId(1), NodeId(2))));
+        assert_eq!(parse_command("recommend 107 5"), Ok(Command::Recommend(NodeId(107), 5)));
+        assert_eq!((parse_command("quit"), parse_command("exit")), (Ok(Command::Quit), Ok(Command::Quit)));
+
+        assert_eq!(parse_command("distance 0"), Err(String::from("distance needs two node ids, e.g. distance 0 107")));
+        assert_eq!(parse_command("teleport 1"), Err(String::from("unknown command \"teleport\"")));
+        for bad in ["", "   ", "degree", "degree x", "degree 1 2", "degree -1", "path 1 two", "jaccard 1 2 3", "recommend 5", "recommend 5 many", "quit now"] {
+            assert!(parse_command(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn test_session_answers_and_recovers() { //A scripted session: answers, a missing node, a bad line that does not end the session, and quit stopping before the rest
+        let graph = Graph::from_edges(&[(0, 1), (1, 2), (2, 3), (0, 2), (7, 8)]);
+        let results = AnalysisResults::for_graph(&graph);
+        let input = "degree 2\nneighbors 0\n\ndistance 0 3\npath 0 3\ndistance 0 7\ndegree 99\nfly 1\njaccard 0 1\nrecommend 0 2\nquit\ndegree 0\n";
+        let mut out = Vec::new();
+        run_session(&graph, &results, &NumberFormat::default(), input.as_bytes(), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let answers: Vec<&str> = out.lines().skip(1).map(|line| line.trim_start_matches("> ")).collect();
+        assert_eq!(answers, vec![
+            "2 has 3 friends",
+            "0 (2 friends): 1, 2",
+            "0 and 3 are 2 steps apart",
+            "0 → 2 → 3",
+            "0 and 7 are not connected",
+            "node 99 not found",
+            "unknown command \"fly\"",
+            COMMANDS,
+            "Jaccard similarity of 0 and 1: 0.333",
+            "0 → 3 (0.500)",
+            "",
+        ]);
+    }
+}
@@ -0,0 +1,334 @@
+//Module: labels.rs
+//Here we map between the node ids found in an input file and the compact ids 0..n used inside the Graph
+//Edge lists with huge or very sparse ids (e.g. 64-bit account numbers) are renumbered on load so any per-node array stays small,
+//and every report translates back so users only ever see the ids from their own file. A node added to a compacted graph later gets a fresh id
+//above every id in use (IdMap::assign), so it still prints, writes out and reloads as a node of its own
+//
+//The id spaces have their own types so they cannot be mixed up: NodeId is an id from the input file, InternalId a node id of the Graph
+//(the compact id for a compacted graph, the file's id otherwise) and NodeIndex a compact id from IdMap / a CsrGraph row.
+//The Graph's methods and the analyses that take a node from the caller take an InternalId; the only ways between an InternalId and a NodeId
+//are Graph::internal_id and Graph::original_id (or AnalysisResults::label). Rankings and maps that analyses return still hold plain usize
+//internal ids, as does the GraphRead trait the analyses are written against. Migrating code written against the old usize signatures:
+//    jaccard_similarity(&g, u, v)  ->  jaccard_similarity(&g, InternalId(u), InternalId(v))   (or u.into())
+//    graph.add_edge(1, 2)          ->  graph.add_edge(InternalId(1), InternalId(2))
+//    graph.internal_id(107)        ->  graph.internal_id(NodeId(107))   (returns an InternalId; .index() for the usize)
+//    graph.original_id(n) + 1      ->  graph.original_id(InternalId(n)).0 + 1   (printing with {} is unchanged)
+//    IdMap::compact / original     ->  take and return NodeId / NodeIndex
+//    CsrGraph rows                 ->  NodeIndex (csr.rows() walks them; NodeIndex::try_from(usize) fails with Overflow past u32)
+//    read_node_list, restrict_to   ->  sets of NodeId
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::BufRead;
+use std::path::Path;
+use crate::error::GraphError;
+use crate::graph::builder::GraphBuilder;
+use crate::graph::{open_edge_list, Graph};
+
+pub const SPARSE_FACTOR: usize = 4; //Ids are compacted when the largest id is at least this many times the node count
+pub const MIN_COMPACT_ID: usize = 1 << 16; //...and at least this large, so small test graphs and dense files keep their ids
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId(pub usize); //A node id as written in the input file: what users type on the command line and see in every report
+
+/// A node id of the Graph, as the analyses take it. An id read from the input file has to go through the Graph's id map first,
+/// so passing one where an internal id is expected does not compile:
+///
+/// ```compile_fail
+/// use facebook_graph_analysis::{analysis::jaccard_similarity, graph::Graph, labels::NodeId};
+/// let graph = Graph::from_edges([(0, 1), (1, 2)]);
+/// jaccard_similarity(&graph, NodeId(0), NodeId(2));
+/// ```
+///
+/// ```
+/// use facebook_graph_analysis::{analysis::jaccard_similarity, graph::Graph, labels::{InternalId, NodeId}};
+/// let graph = Graph::from_edges([(0, 1), (1, 2)]);
+/// let (u, v) = (graph.internal_id(NodeId(0)).unwrap(), graph.internal_id(NodeId(2)).unwrap());
+/// assert_eq!(jaccard_similarity(&graph, u, v), jaccard_similarity(&graph, InternalId(0), InternalId(2)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct InternalId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeIndex(pub u32); //A compact id 0..n: handed out by IdMap (also the Graph's own id for a compacted graph) and used as a CsrGraph row
+
+impl From<usize> for NodeId {
+    fn from(id: usize) -> Self {
+        NodeId(id)
+    }
+}
+
+impl From<usize> for InternalId {
+    fn from(node: usize) -> Self {
+        InternalId(node)
+    }
+}
+
+impl InternalId {
+    pub fn index(self) -> usize { //The plain id, as rankings and the GraphRead trait use it
+        self.0
+    }
+}
+
+impl fmt::Display for InternalId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl fmt::Display for NodeId { //Prints the bare number, honoring width and alignment
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl NodeIndex {
+    pub fn index(self) -> usize { //As a Graph node id
+        self.0 as usize
+    }
+}
+
+impl TryFrom<usize> for NodeIndex { //Overflow past u32::MAX instead of silently wrapping onto another node
+    type Error = GraphError;
+    fn try_from(index: usize) -> Result<Self, GraphError> {
+        u32::try_from(index).map(NodeIndex).map_err(|_| GraphError::Overflow { what: "node index" })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdMap { //Compact id i stands for originals[i]; originals are sorted, so compact ids keep the same order as the originals
+    originals: Vec<usize>,
+    index: HashMap<usize, usize>, //Original id -> Graph node id, for the compacted nodes and the added ones
+    added: HashMap<usize, usize>, //Graph node id -> original handed out by assign, for nodes added to the graph after compaction
+}
+
+impl IdMap {
+    pub fn from_ids(ids: impl IntoIterator<Item = usize>) -> Result<Self, GraphError> { //Builds the map for a set of original ids (duplicates are ignored); Overflow past u32::MAX of them
+        let mut originals: Vec<usize> = ids.into_iter().collect();
+        originals.sort();
+        originals.dedup();
+        let index = originals.iter().enumerate().map(|(compact, &original)| Ok((original, NodeIndex::try_from(compact)?.index()))).collect::<Result<_, GraphError>>()?;
+        Ok(Self { originals, index, added: HashMap::new() })
+    }
+
+    pub fn is_sparse(max_id: usize, num_nodes: usize) -> bool { //Whether ids up to max_id for num_nodes nodes are worth compacting
+        max_id >= MIN_COMPACT_ID && max_id >= SPARSE_FACTOR * num_nodes
+    }
+
+    pub fn original(&self, compact: NodeIndex) -> Option<NodeId> { //Id from the input file for a compact id, if the map has one
+        self.originals.get(compact.index()).or_else(|| self.added.get(&compact.index())).copied().map(NodeId)
+    }
+
+    pub fn original_or_internal(&self, node: usize) -> NodeId { //original for a Graph node id; an id the map never saw (not a node of the graph) is returned unchanged
+        self.originals.get(node).or_else(|| self.added.get(&node)).copied().map_or(NodeId(node), NodeId)
+    }
+
+    pub fn compact(&self, original: NodeId) -> Option<NodeIndex> { //Compact id for an id from the input file, if that node exists
+        self.internal(original).and_then(|node| NodeIndex::try_from(node).ok())
+    }
+
+    pub fn internal(&self, original: NodeId) -> Option<usize> { //Graph node id for an original, including nodes added after compaction
+        self.index.get(&original.0).copied()
+    }
+
+    pub fn assign(&mut self, node: usize) -> NodeId { //Original id for a Graph node, handing a node added after compaction a fresh one above every id in use so it never prints like another node
+        if let Some(&original) = self.originals.get(node).or_else(|| self.added.get(&node)) {
+            return NodeId(original);
+        }
+        let largest = self.originals.last().into_iter().chain(self.added.values()).max();
+        let fresh = match largest.map_or(Some(0), |&id| id.checked_add(1)) {
+            Some(id) => id,
+            None => (0..).find(|id| !self.index.contains_key(id)).unwrap(), //usize::MAX is taken: any unused id will do
+        };
+        self.added.insert(node, fresh);
+        self.index.insert(fresh, node);
+        NodeId(fresh)
+    }
+
+    pub fn len(&self) -> usize {
+        self.originals.len() + self.added.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.originals.is_empty()
+    }
+}
+
+pub fn read_node_list(path: &str) -> Result<HashSet<NodeId>, GraphError> { //Reads one node id (as written in the input file) per line; blank lines and text after '#' are ignored
+    let text = fs::read_to_string(path)?;
+    let mut nodes = HashSet::new();
+    for (index, line) in text.lines().enumerate() {
+        let id = line.split('#').next().unwrap_or("").trim();
+        if id.is_empty() {
+            continue;
+        }
+        nodes.insert(NodeId(id.parse().map_err(|_| GraphError::Parse { line: index + 1, content: line.to_string() })?));
+    }
+    Ok(nodes)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateLabelPolicy { //What LabeledGraph::load_with_labels does when a second id carries a label it has already seen
+    #[default]
+    Merge, //The ids are the same person: they become one node (and are listed in LabeledGraph::duplicates)
+    Error, //A Parse error on the line that repeats the label
+}
+
+type Duplicates = Vec<(String, Vec<NodeId>)>;
+
+pub fn split_quoted(line: &str) -> Option<Vec<String>> { //Splits a line on whitespace; inside "double quotes" whitespace is part of the token, and a backslash takes the next character literally (\" \\ or an escaped space) anywhere. None for an unclosed quote or a trailing backslash
+    let mut tokens = Vec::new();
+    let (mut token, mut in_token, mut quoted) = (String::new(), false, false);
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                token.push(chars.next()?);
+                in_token = true;
+            }
+            '"' => {
+                quoted = !quoted;
+                in_token = true; //"" is an empty token, not nothing
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut token));
+                    in_token = false;
+                }
+            }
+            c => {
+                token.push(c);
+                in_token = true;
+            }
+        }
+    }
+    if quoted {
+        return None;
+    }
+    if in_token {
+        tokens.push(token);
+    }
+    Some(tokens)
+}
+
+fn read_labels(path: &str, policy: DuplicateLabelPolicy) -> Result<(HashMap<NodeId, String>, Duplicates), GraphError> { //Reads "id label" lines, the label quoted if it has spaces (e.g. 12 "Ada Lovelace"); blank lines and lines starting with '#' are ignored.
+    //Returns every id's label and the labels given to more than one id. Repeating a line is harmless, but giving one id two different labels is a Parse error whatever the policy
+    let text = fs::read_to_string(path)?;
+    let mut labels: HashMap<NodeId, String> = HashMap::new();
+    let mut ids: HashMap<String, NodeId> = HashMap::new(); //Label -> the first id seen with it
+    let mut duplicates: Duplicates = Vec::new();
+    let mut repeats: HashMap<String, usize> = HashMap::new(); //Label -> its entry in duplicates
+    for (index, line) in text.lines().enumerate() {
+        let parse_error = || GraphError::Parse { line: index + 1, content: line.to_string() };
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let tokens = split_quoted(trimmed).ok_or_else(parse_error)?;
+        let [id, label] = tokens.as_slice() else {
+            return Err(parse_error());
+        };
+        let id = NodeId(id.parse().map_err(|_| parse_error())?);
+        if label.is_empty() {
+            return Err(parse_error());
+        }
+        if let Some(existing) = labels.get(&id) {
+            if existing != label {
+                return Err(parse_error());
+            }
+            continue;
+        }
+        match ids.get(label) {
+            None => {
+                ids.insert(label.clone(), id);
+            }
+            Some(_) if policy == DuplicateLabelPolicy::Error => return Err(parse_error()),
+            Some(&first) => {
+                let entry = *repeats.entry(label.clone()).or_insert_with(|| {
+                    duplicates.push((label.clone(), vec![first]));
+                    duplicates.len() - 1
+                });
+                duplicates[entry].1.push(id);
+            }
+        }
+        labels.insert(id, label.clone());
+    }
+    Ok((labels, duplicates))
+}
+
+#[derive(Debug, Default)]
+pub struct LabeledGraph { //A graph whose input file names people by strings (usernames, hashes) instead of numbers: each label gets the dense
+    //node id 0, 1, 2, ... in the order it first appears, every analysis runs on those ids, and node_label / node_index translate back and forth
+    pub graph: Graph,
+    pub duplicates: Duplicates, //Mapping report of load_with_labels: every label given to more than one id, with those ids in file order (empty for load)
+    indices: HashMap<String, usize>,
+    labels: Vec<String>, //labels[i]: the label of node i
+}
+
+impl LabeledGraph {
+    pub fn load(path: &str) -> Result<Self, GraphError> { //Reads "label label" lines (quoted if a label has spaces, see split_quoted; .gz files are decompressed); blank lines and lines starting with '#' are ignored.
+        //A line without exactly two labels is a Parse error; a repeated edge is ignored and a self-loop keeps only the node, as in load_from_file
+        Self::load_named(path, |token| Some(token.to_string()))
+    }
+
+    pub fn load_with_labels(edges: &str, labels: &str, policy: DuplicateLabelPolicy) -> Result<Self, GraphError> { //A numeric edge list whose people are named by an "id label" file (see read_labels): the edge list is read as by load,
+        //each id standing for its label, or its own number if it has none. Under DuplicateLabelPolicy::Merge ids sharing a label are therefore one node; each such label is listed in duplicates
+        let (names, duplicates) = read_labels(labels, policy)?;
+        let mut result = Self::load_named(edges, |token| {
+            let id = NodeId(token.parse().ok()?);
+            Some(names.get(&id).cloned().unwrap_or_else(|| id.to_string()))
+        })?;
+        result.duplicates = duplicates;
+        Ok(result)
+    }
+
+    fn load_named(path: &str, name: impl Fn(&str) -> Option<String>) -> Result<Self, GraphError> { //load with each token turned into its label by name (None is a Parse error)
+        let mut result = LabeledGraph::default();
+        let mut builder = GraphBuilder::new();
+        for (index, line) in open_edge_list(Path::new(path))?.lines().enumerate() {
+            let line = line?;
+            let parse_error = || GraphError::Parse { line: index + 1, content: line.clone() };
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let tokens = split_quoted(trimmed).ok_or_else(parse_error)?;
+            let [u, v] = tokens.as_slice() else {
+                return Err(parse_error());
+            };
+            let (u, v) = (name(u).ok_or_else(parse_error)?, name(v).ok_or_else(parse_error)?);
+            let (u, v) = (result.intern(&u), result.intern(&v));
+            builder.add_edge(u, v)?;
+        }
+        if result.labels.is_empty() {
+            return Err(GraphError::EmptyFile(path.to_string()));
+        }
+        result.graph = builder.build_graph()?.0;
+        Ok(result)
+    }
+
+    fn intern(&mut self, label: &str) -> usize { //The node id of a label, handing out the next one the first time it is seen
+        if let Some(&node) = self.indices.get(label) {
+            return node;
+        }
+        self.labels.push(label.to_string());
+        self.indices.insert(label.to_string(), self.labels.len() - 1);
+        self.labels.len() - 1
+    }
+
+    pub fn node_index(&self, label: &str) -> Option<usize> {
+        self.indices.get(label).copied()
+    }
+
+    pub fn node_label(&self, node: usize) -> Option<&str> {
+        self.labels.get(node).map(String::as_str)
+    }
+
+    pub fn labels(&self) -> &[String] { //Every label, indexed by node id
+        &self.labels
+    }
+
+    pub fn into_parts(self) -> (Graph, Vec<String>) { //The graph and its labels (indexed by node id), e.g. for AnalysisResults::names
+        (self.graph, self.labels)
+    }
+}
@@ -0,0 +1,29 @@
+//Library root of our project: exposes the graph structure and analysis algorithms so main.rs (and tests) can use them
+#![cfg_attr(test, allow(clippy::needless_borrows_for_generic_args))] //Tests pass edge lists to Graph::from_edges as &[..] slices, which it also takes by value
+pub mod graph; //Module that defines and builds the Graph structure
+pub mod analysis; //Module that implements analysis algorithms
+pub mod pipeline; //Module that stores analysis results so they are computed once and reused
+pub mod checkpoint; //Module that makes long analyses resumable by saving their progress to disk
+pub mod cache; //Module that stores finished analysis results on disk, keyed by graph fingerprint
+pub mod parallel; //Module that controls thread usage for the parallel analyses
+pub mod community; //Module that detects communities and works with node partitions
+pub mod error; //Module that defines the crate-wide GraphError type
+pub mod report; //Module that writes results as text, Markdown, JSON and CSV with consistent number formatting
+pub mod view; //Module that provides filtered, zero-copy views of a graph
+pub mod weighted; //Module that defines the weighted graph type
+pub mod digraph; //Module that defines the directed graph type
+pub mod temporal; //Module that defines the graph of timestamped contacts
+pub mod generate; //Module that builds random graphs from classic models (ER, WS, BA, configuration)
+pub mod labels; //Module that maps large or sparse input node ids to compact internal ids and back
+pub mod budget; //Module that caps the memory of analyses with large tables
+pub mod sketch; //Module that provides HyperLogLog sketches for approximate distinct counts
+pub mod flat; //Module that exports a graph as flat CSR arrays for other languages
+pub mod csr; //Module that defines the compact CSR graph used for fast BFS sweeps
+pub mod traversal; //Module that provides the shared BFS walk as an iterator and a visitor with early exit
+pub mod oracle; //Module that answers distance queries from a few landmark BFS runs
+pub mod walks; //Module that generates random walk sequences for node embedding tools
+pub mod cli; //Module that parses the command line into the settings of a run
+pub mod interactive; //Module that answers one-line queries about a loaded graph at a prompt
+pub mod bench; //Module that times the stages of a run and prints them as a table
+#[cfg(test)]
+mod consistency; //Test-only harness that checks alternative implementations of a metric agree
@@ -1,54 +1,876 @@
 //Entry point of our project for analyzing the graph
 //Generally, we load our graph, and perform our algorithms on it
 //REMARK MY Write-Up goes over all the code very extensively and goes over the output there too! make sure to check that out
-mod graph; //Module that defines and builds the Graph structure
-mod analysis; //Module that implements analysis algorithms
-use graph::Graph;
-use analysis::{average_distance, closeness_centrality, jaccard_similarity, most_similar_pairs};
+use facebook_graph_analysis::graph::{external, DotStyle, Graph, LoadReport}; //Graph structure and analysis algorithms live in the library (lib.rs)
+use facebook_graph_analysis::analysis::{apply_isolated, articulation_points, betweenness_centrality, bfs_distances, bipartite_check, bfs_path, bridges, bfs_distances_checked, closeness_breakdown, closeness_of, degrees_of_separation_table_with, density, distance_distribution_sampled_with, edge_embeddedness, edge_overlap, eigenvector_centrality, explain_pair, export_minhash_sketches, for_each_maximal_clique, least_central_among, least_central_with, friends_of_friends_similarity, geodesic_subgraph, graph_jaccard, jaccard_similarity, largest_clique, largest_connected_component, local_clustering_of, minhash_standard_error, node_churn, pagerank, partition_stability, pagerank_weighted, ranking_diff, recommend_friends, recommendations_for_all_with, robustness_profile, separation_table, separation_threshold, similar_by_ppr, read_minhash_sketches, sketch_similarity, simrank, small_world_sigma, similarity_graph, top_churners, top_k_closeness, top_pairs_by_mutual_friends, transitivity, two_hop_reach_of, Bipartiteness, CommunityAlgorithm, IsolatedNodes, PeripheryMetric, PeripheryOptions, RemovalStrategy, SimilarityGraphOptions, SimilarityMetric, MAX_SIMRANK_NODES, PAGERANK_MAX_ITERATIONS, SEPARATION_SOURCES, SIMRANK_DECAY};
+use facebook_graph_analysis::csr::{closeness_centrality_csr, CsrGraph};
+use facebook_graph_analysis::bench::Timings;
+use facebook_graph_analysis::cache::ResultCache;
+use facebook_graph_analysis::cli::{self, Config};
+use facebook_graph_analysis::budget::{AnalysisKind, MemoryBudget};
+use facebook_graph_analysis::community::{community_sizes, girvan_newman_dendrogram, louvain, modularity, read_partition, PartitionFormat};
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::generate;
+use facebook_graph_analysis::interactive;
+use facebook_graph_analysis::labels::{read_node_list, LabeledGraph, NodeId};
+use facebook_graph_analysis::parallel::ParallelismConfig;
+use facebook_graph_analysis::pipeline::{growth_report, plan, planned_tasks, run_batch, stress, verify_against, AnalysisResults, BatchConfig, COMMUNITY_SEED, EXACT_DISTANCES_MAX_NODES, ExpectedStats, GraphSize, GrowthOptions, PlannedAnalysis, Section, StalePolicy, StressAnalysis, StressConfig, Variant};
+use facebook_graph_analysis::walks::{generate_biased_walks, generate_random_walks};
+use facebook_graph_analysis::report::{self, AnalysisReport, MetricFamily, NumberFormat, Provenance, RankedMetric, TableFormat};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::PathBuf;
+use std::time::Instant;
+use facebook_graph_analysis::labels::InternalId;
 
 fn main() {
+    //A command line that cannot be understood prints the usage text; any other error is printed and turned into an exit code that tells scripts what kind of failure happened (see GraphError::exit_code)
+    let args: Vec<String> = std::env::args().collect();
+    let config = match cli::parse_args(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, cli::USAGE);
+            std::process::exit(e.exit_code());
+        }
+    };
+    if let Err(e) = run(&args, &config) {
+        eprintln!("Error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &[String], config: &Config) -> Result<(), GraphError> {
+    let started = Instant::now(); //Output files record the time from here to when they were written
+    let options = args[1..].join(" ");
+    let threads = config.threads; //"--threads N": N = 1 runs everything sequentially, leaving it out uses every core
+    let format = config.precision.map_or_else(NumberFormat::default, NumberFormat::with_precision); //"--precision N": print every metric with N decimal places instead of the per-metric defaults
+
+    //"generate --model er|ws|ba|config --nodes N --seed S --out FILE" subcommand: write a random graph as an edge list
+    //Model flags: er --p P, ws --k K --beta B, ba --m M, config --from FILE (copies the degree sequence of an existing edge list)
+    if args.get(1).map(String::as_str) == Some("generate") {
+        let model: String = flag(args, "--model")?.ok_or_else(|| GraphError::InvalidParameter(String::from("generate needs --model er|ws|ba|config")))?;
+        let nodes: usize = flag(args, "--nodes")?.unwrap_or(1000);
+        let seed: u64 = flag(args, "--seed")?.unwrap_or(42);
+        let out: String = flag(args, "--out")?.ok_or_else(|| GraphError::InvalidParameter(String::from("generate needs --out FILE")))?;
+        let graph = match model.as_str() {
+            "er" => generate::erdos_renyi(nodes, flag(args, "--p")?.unwrap_or(0.01), seed)?,
+            "ws" => generate::watts_strogatz(nodes, flag(args, "--k")?.unwrap_or(4), flag(args, "--beta")?.unwrap_or(0.1), seed)?,
+            "ba" => generate::barabasi_albert(nodes, flag(args, "--m")?.unwrap_or(3), seed)?,
+            "config" => {
+                let from: String = flag(args, "--from")?.ok_or_else(|| GraphError::InvalidParameter(String::from("--model config needs --from FILE")))?;
+                let mut degrees = Graph::load_from_file(&from)?.all_degrees();
+                degrees.sort();
+                let degrees: Vec<usize> = degrees.into_iter().map(|(_, d)| d).collect();
+                generate::configuration_model(&degrees, seed)?
+            }
+            other => return Err(GraphError::InvalidParameter(format!("unknown model {:?}, expected er, ws, ba or config", other))),
+        };
+        graph.write_edge_list(&out)?;
+        println!("Wrote {} to {} (isolated nodes are not in the edge list)", model, out);
+        print!("{}", graph.stats());
+        return Ok(());
+    }
+
+    //"compare <OLD> <NEW> [--top K] [--csv OUT.csv] [--centrality closeness|betweenness|pagerank]" subcommand: how much two snapshots agree, whose friend lists changed the most and, if asked, how a centrality ranking moved
+    if args.get(1).map(String::as_str) == Some("compare") {
+        let (Some(old_path), Some(new_path)) = (args.get(2), args.get(3)) else {
+            return Err(GraphError::InvalidParameter(String::from("compare needs an old and a new snapshot file")));
+        };
+        let top: usize = flag(args, "--top")?.unwrap_or(10);
+        let csv_out: Option<String> = flag(args, "--csv")?;
+        let (old, new) = (Graph::load_from_file(old_path)?, Graph::load_from_file(new_path)?);
+        println!("Change: {}", old.diff(&new));
+        let overlap = graph_jaccard(&old, &new);
+        println!("Node Jaccard: {}  Edge Jaccard: {}  Degree correlation: {} ({} shared nodes)",
+            format.format(MetricFamily::Similarity, overlap.node_jaccard), format.format(MetricFamily::Similarity, overlap.edge_jaccard),
+            format.format(MetricFamily::Similarity, overlap.degree_correlation), overlap.common_nodes);
+        let churn = node_churn(&old, &new);
+        println!("\nTop {} churners (node: gained / lost, neighborhood similarity):", top);
+        for (node, stats) in top_churners(&churn, top) {
+            println!("  Node {}: +{} / -{}, similarity {}", node, stats.gained.len(), stats.lost.len(), format.format(MetricFamily::Similarity, stats.similarity));
+        }
+        if let Some(centrality) = flag::<String>(args, "--centrality")? {
+            let ranking = |graph: &Graph| -> Result<Vec<(usize, f64)>, GraphError> {
+                let scores = match centrality.as_str() {
+                    "closeness" => closeness_centrality_csr(&CsrGraph::from(graph), &ParallelismConfig::with_threads(threads)),
+                    "betweenness" => betweenness_centrality(graph),
+                    "pagerank" => pagerank(graph, 0.85, PAGERANK_MAX_ITERATIONS, 1e-8)?,
+                    other => return Err(GraphError::InvalidParameter(format!("unknown centrality {:?}, expected closeness, betweenness or pagerank", other))),
+                };
+                Ok(scores.into_iter().map(|(node, score)| (graph.original_id(InternalId(node)).0, score)).collect()) //Internal ids differ between snapshots
+            };
+            let diff = ranking_diff(&ranking(&old)?, &ranking(&new)?, top);
+            println!("\nChange in {} ranking:", centrality);
+            print!("{}", diff);
+        }
+        if let Some(out) = csv_out {
+            let provenance = Provenance::new(&format!("{} {}", old_path, new_path), &options, None, started);
+            report::write_churn_csv(&out, &churn, &format, Some(&provenance))?;
+        }
+        return Ok(());
+    }
+
+    //"sketch-similarity <SKETCHES> [--pairs U:V,...]" subcommand: estimated Jaccard similarities from an export-sketches file, without the edge list
+    if args.get(1).map(String::as_str) == Some("sketch-similarity") {
+        let path = args.get(2).filter(|a| !a.starts_with("--")).ok_or_else(|| GraphError::InvalidParameter(String::from("sketch-similarity needs a sketch file")))?;
+        let sketches = read_minhash_sketches(path)?;
+        for &(u, v) in &config.pairs {
+            println!("Estimated Jaccard similarity between {} and {}: {}", u.0, v.0, format.format(MetricFamily::Similarity, sketch_similarity(&sketches, u.0, v.0)?));
+        }
+        return Ok(());
+    }
+
+    //"growth <FILE>... [--csv OUT.csv]" subcommand: summarize edge-list snapshots in the order given and show how the network changed
+    if args.get(1).map(String::as_str) == Some("growth") {
+        let csv_out: Option<String> = flag(args, "--csv")?;
+        let paths: Vec<PathBuf> = args[2..].iter().take_while(|a| !a.starts_with("--")).map(PathBuf::from).collect();
+        if paths.is_empty() {
+            return Err(GraphError::InvalidParameter(String::from("growth needs at least one snapshot file")));
+        }
+        let growth = growth_report(&paths, &GrowthOptions::default())?;
+        report::write_growth_markdown(&growth, &mut std::io::stdout(), &format)?;
+        if let Some(out) = csv_out {
+            let inputs: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+            report::write_growth_csv(&growth, &out, &format, Some(&Provenance::new(&inputs.join(" "), &options, Some(GrowthOptions::default().seed), started)))?;
+        }
+        return Ok(());
+    }
+
+    //"stress [--budget SECS] [--max-nodes N] [--analyses a,b,...]" subcommand: find how large a graph this machine can analyze
+    if args.get(1).map(String::as_str) == Some("stress") {
+        let mut config = StressConfig { parallelism: ParallelismConfig::with_threads(threads), ..StressConfig::default() };
+        if let Some(seconds) = flag::<u64>(args, "--budget")? {
+            config.budget = std::time::Duration::from_secs(seconds);
+        }
+        config.max_nodes = flag(args, "--max-nodes")?.unwrap_or(config.max_nodes);
+        if let Some(names) = flag::<String>(args, "--analyses")? {
+            config.analyses = names.split(',').map(StressAnalysis::from_name).collect::<Result<_, _>>()?;
+        }
+        println!("{:>9} {:>10} {:>10} {:>12}  Status", "Nodes", "Edges", "Seconds", "Memory (MB)");
+        for row in stress(&config)? {
+            let status = match (row.valid, row.over_budget) {
+                (false, _) => "INVALID GRAPH",
+                (true, true) => "over budget",
+                (true, false) => "ok",
+            };
+            println!(
+                "{:>9} {:>10} {:>10.2} {:>12.1}  {}",
+                row.nodes,
+                row.edges,
+                row.elapsed.as_secs_f64(),
+                row.memory_bytes as f64 / 1e6,
+                status
+            );
+        }
+        return Ok(());
+    }
+
+    //"batch <DIR> [OUT.csv]" subcommand: analyze every .edges file in a directory and write one summary row per graph
+    if args.get(1).map(String::as_str) == Some("batch") {
+        let dir = args.get(2).ok_or_else(|| GraphError::InvalidParameter(String::from("batch needs a directory, e.g. batch data/facebook")))?;
+        let out = args.get(3).filter(|a| !a.starts_with("--")).map_or("batch_summary.csv", String::as_str);
+        let config = BatchConfig { extension: Some(String::from("edges")), parallelism: ParallelismConfig::with_threads(threads) };
+        let rows = run_batch(dir, &config)?;
+        for (file, _, summary) in &rows {
+            if let Some(error) = &summary.error {
+                eprintln!("Warning: skipped {}: {}", file.display(), error);
+            }
+        }
+        report::write_batch_csv(out, &rows, &format, Some(&Provenance::new(dir, &options, None, started)))?;
+        println!("Wrote {} rows to {}", rows.len(), out);
+        return Ok(());
+    }
+
     //Loading graph data
-    let path = "data/facebook_combined.txt";
-    let graph = Graph::load_from_file(path);
-    println!("Loaded {} nodes and {} edges.", graph.num_nodes, graph.num_edges);
+    let inputs = &config.inputs; //"--input FILE", repeatable: shards are merged into one graph
+    if args.iter().any(|a| a == "--streaming-stats") { //"--streaming-stats": degrees and component sizes streamed from the file, for edge lists too large to load
+        let [input] = &inputs[..] else {
+            return Err(GraphError::InvalidParameter(String::from("--streaming-stats reads a single --input file")));
+        };
+        let input = input.to_string_lossy();
+        let degrees = external::degree_count_from_file(&input)?;
+        let components = external::component_sizes_from_file(&input)?;
+        let degree_sum: u64 = degrees.values().map(|&d| d as u64).sum();
+        println!("Streamed {}: {} nodes, {} edge lines", input, degrees.len(), degree_sum / 2);
+        println!("  Average degree: {:.2}  Max degree: {}", degree_sum as f64 / degrees.len() as f64, degrees.values().max().unwrap_or(&0));
+        println!("  Components: {}  Largest: {} nodes", components.len(), components.first().unwrap_or(&0));
+        return Ok(());
+    }
+    let mut timings = Timings::new(); //Wall time of each stage, printed at the end with "--timing"
+    let mut names = None;
+    let (mut graph, load_reports) = timings.time_stage("loading", || {
+        if args.iter().any(|a| a == "--string-ids") { //"--string-ids": people are named by strings (usernames, hashes); they are numbered in order of appearance and printed by name
+            let [input] = &inputs[..] else {
+                return Err(GraphError::InvalidParameter(String::from("--string-ids reads a single --input file")));
+            };
+            let (graph, labels) = LabeledGraph::load(&input.to_string_lossy())?.into_parts();
+            names = Some(labels);
+            Ok((graph, Vec::new()))
+        } else {
+            Graph::load_from_files_with(inputs, args.iter().any(|a| a == "--strict")) //"--strict": a line without exactly two ids stops the run (with its line number) instead of being skipped
+        }
+    })?;
+    for report in &load_reports {
+        if report.skipped > 0 {
+            eprintln!("Warning: skipped {} lines of {} that did not have exactly two node ids", report.skipped, report.path.display());
+        }
+        if load_reports.len() > 1 {
+            eprintln!("  {}: {} new friendships, {} duplicates, {} self-loops", report.path.display(), report.edges, report.duplicates, report.self_loops);
+        }
+    }
+    if load_reports.len() > 1 {
+        let total = LoadReport::combined(&load_reports);
+        eprintln!("Merged {} files: {} friendships, {} duplicates, {} self-loops, {} lines skipped", load_reports.len(), total.edges, total.duplicates, total.self_loops, total.skipped);
+    }
+    let include_only = flag::<String>(args, "--include-only")?.map(|file| read_node_list(&file)).transpose()?; //"--include-only FILE": analyze only the ids listed (one per line)
+    let exclude = flag::<String>(args, "--exclude-nodes")?.map(|file| read_node_list(&file)).transpose()?.unwrap_or_default(); //"--exclude-nodes FILE": leave the listed ids out of every analysis and output
+    let mut excluded_nodes = 0;
+    if include_only.is_some() || !exclude.is_empty() {
+        (graph, excluded_nodes) = graph.restrict_to(include_only.as_ref(), &exclude);
+        eprintln!("Scoped the graph to {} nodes ({} excluded by the node lists)", graph.num_nodes(), excluded_nodes);
+    }
+    if config.analyze_largest_component { //"--largest-component": analyze only the giant component, so distances and closeness never mix components
+        let total = (graph.num_nodes(), graph.num_edges());
+        let (giant, share) = largest_connected_component(&graph);
+        eprintln!("Kept the largest component: {} of {} nodes ({:.1}%), {} of {} friendships ({:.1}%)",
+            share.nodes, total.0, 100.0 * share.node_fraction, share.edges, total.1, 100.0 * share.edge_fraction);
+        excluded_nodes += total.0 - share.nodes;
+        graph = giant;
+    }
+
+    let mut results = AnalysisResults::for_graph(&graph); //Reports print the ids from the file even if they were compacted on load
+    results.names = names;
+    results.excluded_nodes = excluded_nodes;
+    results.parallelism = ParallelismConfig::with_threads(threads);
+    if let Some(mode) = flag::<String>(args, "--isolated")? { //"--isolated zero|sentinel|exclude": how nodes with no friends appear in the closeness ranking
+        results.isolated = IsolatedNodes::from_name(&mode)?;
+    }
+    let input_names: Vec<String> = inputs.iter().map(|p| p.display().to_string()).collect();
+    results.record_provenance(&graph, &input_names.join(" "), &options, started);
+    if config.progress { //"--progress": e.g. "  closeness: 1000/4039" on stderr while the sweeps run
+        results.progress = Some(|analysis, done, total| eprintln!("  {}: {}/{}", analysis, done, total));
+    }
+    results.exact_ranking = config.exact_ranking; //"--exact-ranking": similar pairs sorted by exact fractions instead of floats
+    results.hub_exclusion = flag(args, "--hub-cap")?; //"--hub-cap N": distances and closeness ignore people with more than N friends
+    if let Some(dir) = flag::<String>(args, "--cache-dir")? { //"--cache-dir DIR": reuse closeness, betweenness, similar pairs and communities from an earlier run on the same graph
+        results.cache = Some(ResultCache::new(&dir)?);
+    }
+
+    //"--interactive": answer queries typed at a prompt about the loaded (and possibly scoped) graph, see interactive::COMMANDS
+    if config.interactive {
+        return interactive::run_session(&graph, &results, &format, std::io::stdin().lock(), &mut std::io::stdout());
+    }
+
+    //"verify [FILE]" subcommand: compare the loaded graph with published statistics (exit code 1 if any check fails)
+    if args.get(1).map(String::as_str) == Some("verify") {
+        let file = args.get(2).filter(|a| !a.starts_with("--")).map_or("data/facebook_expected.toml", String::as_str);
+        let checks = verify_against(&graph, &ExpectedStats::from_toml_file(file)?);
+        for check in &checks {
+            println!(
+                "{} {:<20} expected {} (± {}), got {}",
+                if check.passed { "PASS" } else { "FAIL" },
+                check.name,
+                check.expected,
+                check.tolerance,
+                check.actual
+            );
+        }
+        if checks.iter().any(|c| !c.passed) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    //"memory [--memory-budget SIZE]" subcommand: estimated memory of the analyses with large tables, checked against the budget
+    //(the budget comes from --memory-budget, else from the FGA_MEMORY_BUDGET environment variable, else it is unlimited)
+    if args.get(1).map(String::as_str) == Some("memory") {
+        let budget = match flag::<String>(args, "--memory-budget")? {
+            Some(text) => MemoryBudget::parse(&text)?,
+            None => MemoryBudget::from_env()?,
+        };
+        let kinds = [
+            ("similarity matrix (all nodes)", AnalysisKind::SimilarityMatrix(graph.num_nodes())),
+            ("distance matrix", AnalysisKind::DistanceMatrix),
+            ("co-neighbor counts / similarity graph", AnalysisKind::CoNeighborCounts),
+            ("two-hop reach sketches (precision 12)", AnalysisKind::TwoHopSketches(12)),
+        ];
+        for (name, kind) in kinds {
+            let needed = graph.estimate_analysis_memory(kind);
+            let status = if budget.check(needed).is_ok() { "fits" } else { "over budget" };
+            println!("{:<40} {:>14} bytes  {}", name, needed, status);
+        }
+        return Ok(());
+    }
+
+    //"export-flat [PREFIX]" subcommand: write the loaded (and scoped) graph as CSR arrays plus a JSON header that numpy can memory-map
+    if args.get(1).map(String::as_str) == Some("export-flat") {
+        let prefix = args.get(2).filter(|a| !a.starts_with("--")).map_or("facebook_flat", String::as_str);
+        graph.to_flat_arrays().write_flat_npy_like(prefix)?;
+        println!("Wrote {}.offsets.bin, {}.targets.bin, {}.ids.bin and {}.json", prefix, prefix, prefix, prefix);
+        return Ok(());
+    }
+
+    //"export-sketches [FILE] [--k HASHES] [--seed S]" subcommand: MinHash signatures of every friend list, for sharing similarity without the friendships
+    //Without --seed a random one is drawn (a fixed default would be the first seed anyone tries) and printed, so the export can be repeated
+    if args.get(1).map(String::as_str) == Some("export-sketches") {
+        let out = args.get(2).filter(|a| !a.starts_with("--")).map_or("facebook_sketches.bin", String::as_str);
+        let num_hashes: usize = flag(args, "--k")?.unwrap_or(256);
+        let seed = match flag::<u64>(args, "--seed")? {
+            Some(seed) => seed,
+            None => {
+                let seed = rand::random::<u64>();
+                println!("Using random seed {} (pass --seed {} to export the same sketches again)", seed, seed);
+                seed
+            }
+        };
+        export_minhash_sketches(&graph, num_hashes, seed, out)?;
+        println!("Wrote {} hashes per node to {} (estimates within about {:.3}; keep the seed private)", num_hashes, out, minhash_standard_error(num_hashes));
+        return Ok(());
+    }
+
+    //"show [--all]" subcommand: print the adjacency of a small (e.g. --input or scoped) graph, one sorted line per node
+    if args.get(1).map(String::as_str) == Some("show") {
+        if graph.num_nodes() > 200 && !args.iter().any(|a| a == "--all") {
+            return Err(GraphError::InvalidParameter(format!("show prints every node; {} nodes is a lot, pass --all to print anyway", graph.num_nodes())));
+        }
+        print!("{}", graph.pretty());
+        return Ok(());
+    }
+
+    //"node <id>" subcommand: quick facts about a single node
+    if args.get(1).map(String::as_str) == Some("node") {
+        let original: usize = args
+            .get(2)
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| GraphError::InvalidParameter(String::from("node needs a node id, e.g. node 107")))?;
+        let node = graph.internal_id(NodeId(original)).ok_or(GraphError::UnknownNode(original))?;
+        let reach = two_hop_reach_of(&graph, node)?;
+        let distances = bfs_distances_checked(&graph, node)?;
+        println!("Node {}:", original);
+        println!("  Friends: {}", graph.adjacency()[&node.index()].len());
+        println!("  Friends of friends (two-hop reach): {}", reach);
+        println!("  Reachable people: {}", distances.len() - 1);
+        println!("  Closeness centrality: {}", format.format(MetricFamily::Centrality, closeness_of(&graph, node)?));
+        println!("  Clustering coefficient: {}", format.format(MetricFamily::Centrality, local_clustering_of(&graph, node)?));
+        let nearby: Vec<String> = similar_by_ppr(&graph, node, 3)?
+            .into_iter()
+            .map(|(other, score)| format!("{} ({})", graph.original_id(InternalId(other)), format.format(MetricFamily::Centrality, score)))
+            .collect();
+        println!("  Closest non-friends by random walk: {}", if nearby.is_empty() { String::from("none") } else { nearby.join(", ") });
+        let ego = graph.ego_network(node, 1);
+        if ego.num_nodes() > MAX_SIMRANK_NODES { //SimRank keeps a score per pair, too many for a hub's ego network
+            println!("  Most alike friends by SimRank: skipped ({} people in the ego network, over {})", ego.num_nodes(), MAX_SIMRANK_NODES);
+            return Ok(());
+        }
+        let mut alike: Vec<(usize, f64)> = simrank(&ego, SIMRANK_DECAY, 5, 1e-4)?
+            .into_iter()
+            .filter_map(|((u, v), score)| match (u == node.index(), v == node.index()) {
+                (true, false) => Some((v, score)),
+                (false, true) => Some((u, score)),
+                _ => None,
+            })
+            .collect();
+        alike.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        let alike: Vec<String> = alike.into_iter().take(3).map(|(other, score)| format!("{} ({})", graph.original_id(InternalId(other)), format.format(MetricFamily::Similarity, score))).collect();
+        println!("  Most alike friends by SimRank (within the ego network): {}", if alike.is_empty() { String::from("none") } else { alike.join(", ") });
+        return Ok(());
+    }
+
+    //"why-central <id>" subcommand: a node's closeness and harmonic centrality split by how far away the people it reaches are
+    if args.get(1).map(String::as_str) == Some("why-central") {
+        let original: usize = args
+            .get(2)
+            .and_then(|n| n.parse().ok())
+            .ok_or_else(|| GraphError::InvalidParameter(String::from("why-central needs a node id, e.g. why-central 107")))?;
+        let node = graph.internal_id(NodeId(original)).ok_or(GraphError::UnknownNode(original))?;
+        let rings = closeness_breakdown(&graph, node)?;
+        let (closeness, harmonic): (f64, f64) = (rings.iter().map(|r| r.closeness).sum(), rings.iter().map(|r| r.harmonic).sum());
+        println!("Node {}: closeness {}, harmonic {}", original, format.format(MetricFamily::Centrality, closeness), format.format(MetricFamily::Centrality, harmonic));
+        println!("{:>8} {:>8} {:>12} {:>12}", "Distance", "People", "Closeness", "Harmonic");
+        for ring in rings {
+            println!("{:>8} {:>8} {:>12} {:>12}", ring.distance, ring.nodes, format.format(MetricFamily::Centrality, ring.closeness), format.format(MetricFamily::Centrality, ring.harmonic));
+        }
+        return Ok(());
+    }
+
+    //"rank <closeness|betweenness|pagerank|eigenvector|degree> [--offset N] [--limit N] [--out FILE.jsonl]" subcommand: one page of a full ranking (20 rows by default),
+    //or with --out the page (the whole ranking unless --limit is given) streamed to a JSON Lines file, so a million-node ranking never has to be printed
+    if args.get(1).map(String::as_str) == Some("rank") {
+        let metric = args.get(2).filter(|a| !a.starts_with("--")).ok_or_else(|| GraphError::InvalidParameter(String::from("rank needs a metric, e.g. rank closeness")))?;
+        let scores = match metric.as_str() {
+            "closeness" => closeness_centrality_csr(&CsrGraph::from(&graph), &ParallelismConfig::with_threads(threads)),
+            "betweenness" => betweenness_centrality(&graph),
+            "pagerank" => pagerank(&graph, 0.85, PAGERANK_MAX_ITERATIONS, 1e-8)?,
+            "eigenvector" => eigenvector_centrality(&graph, PAGERANK_MAX_ITERATIONS, 1e-8),
+            "degree" => {
+                let mut degrees: Vec<(usize, f64)> = graph.all_degrees().into_iter().map(|(node, degree)| (node, degree as f64)).collect();
+                degrees.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+                degrees
+            }
+            other => return Err(GraphError::InvalidParameter(format!("unknown metric {:?}, expected closeness, betweenness, pagerank, eigenvector or degree", other))),
+        };
+        let ranked = RankedMetric::new(metric, &scores);
+        let offset: usize = flag(args, "--offset")?.unwrap_or(0);
+        let out: Option<String> = flag(args, "--out")?;
+        let limit: usize = flag(args, "--limit")?.unwrap_or(if out.is_some() { usize::MAX } else { 20 });
+        if let Some(out) = out {
+            let lines = report::write_ranked_jsonl(&out, metric, ranked.entries(offset, limit), &graph)?;
+            println!("Wrote {} of {} {} scores to {}", lines, ranked.len(), metric, out);
+            return Ok(());
+        }
+        println!("{:>8} {:>10} {:>12}", "Rank", "Node", metric);
+        for entry in ranked.entries(offset, limit) {
+            println!("{:>8} {:>10} {:>12}", entry.rank, results.name(entry.node), format.format(MetricFamily::Centrality, entry.score));
+        }
+        let shown = ranked.page(offset, limit).len();
+        println!("({} of {} ranked nodes{})", shown, ranked.len(), if shown > 0 { format!(", ranks {}-{}", offset + 1, offset + shown) } else { String::new() });
+        return Ok(());
+    }
+
+    //"explain A B" subcommand: why two people would (or would not) be suggested to each other: distance, mutual friends, similarity and community
+    if args.get(1).map(String::as_str) == Some("explain") {
+        let mut ends = Vec::new();
+        for position in [2, 3] {
+            let original: usize = args
+                .get(position)
+                .and_then(|n| n.parse().ok())
+                .ok_or_else(|| GraphError::InvalidParameter(String::from("explain needs two node ids, e.g. explain 0 107")))?;
+            ends.push(graph.internal_id(NodeId(original)).ok_or(GraphError::UnknownNode(original))?);
+        }
+        results.ensure_communities(&graph);
+        print!("{}", explain_pair(&graph, ends[0], ends[1], Some(&results))?);
+        return Ok(());
+    }
+
+    //"path --from A --to B [--dot FILE]" subcommand: how two people are connected, as the union of every shortest path between them
+    if args.get(1).map(String::as_str) == Some("path") {
+        let mut ends = Vec::new();
+        for name in ["--from", "--to"] {
+            let original: usize = flag(args, name)?.ok_or_else(|| GraphError::InvalidParameter(format!("path needs {} ID", name)))?;
+            ends.push(graph.internal_id(NodeId(original)).ok_or(GraphError::UnknownNode(original))?);
+        }
+        let geodesic = geodesic_subgraph(&graph, ends[0].index(), ends[1].index()).ok_or(GraphError::Disconnected)?;
+        let length = bfs_distances(&geodesic, ends[0])[&ends[1].index()];
+        println!("Nodes {} and {} are {} steps apart; their shortest paths use {} people and {} friendships", results.name(ends[0].index()), results.name(ends[1].index()), length, geodesic.num_nodes(), geodesic.num_edges());
+        let chain: Vec<String> = bfs_path(&graph, ends[0], ends[1]).unwrap().iter().map(|&n| results.name(n)).collect();
+        println!("One such chain: {}", chain.join(" → "));
+        if let Some(out) = flag::<String>(args, "--dot")? {
+            report::write_dot(&out, &geodesic, &[ends[0].index(), ends[1].index()], results.provenance.as_ref())?;
+            println!("Wrote {}", out);
+        }
+        return Ok(());
+    }
+
+    //"recommend-all [--k K] [--min-degree D] [--out FILE]" subcommand: export the top K friend suggestions for every node as CSV
+    if args.get(1).map(String::as_str) == Some("recommend-all") {
+        let k = flag(args, "--k")?.unwrap_or(5);
+        let min_degree = flag(args, "--min-degree")?.unwrap_or(2);
+        let out: String = flag(args, "--out")?.unwrap_or_else(|| String::from("recommendations.csv"));
+        let progress = |done: usize, total: usize| {
+            if done.is_multiple_of(500) || done == total {
+                eprintln!("  {}/{} nodes", done, total);
+            }
+        };
+        let recommendations = recommendations_for_all_with(&graph, k, min_degree, SimilarityMetric::Jaccard, &results.parallelism, &progress);
+        report::write_recommendations_csv(&out, &recommendations, &graph, &format, results.provenance.as_ref())?;
+        println!("Wrote {} recommendations to {}", recommendations.len(), out);
+        return Ok(());
+    }
+
+    //"similarity-graph [--threshold T] [--metric jaccard|cosine|overlap|adamic-adar|resource-allocation]" subcommand: link people whose similarity is above T and list the strongest and most central of them
+    if args.get(1).map(String::as_str) == Some("similarity-graph") {
+        let threshold = flag(args, "--threshold")?.unwrap_or(0.3);
+        let metric = flag::<String>(args, "--metric")?.map(|name| SimilarityMetric::from_name(&name)).transpose()?.unwrap_or_default();
+        let options = SimilarityGraphOptions { drop_isolated: true, ..SimilarityGraphOptions::default() };
+        let (similar, _) = similarity_graph(&graph, threshold, metric, &options)?;
+        println!("Similarity graph ({:?} > {}): {} nodes and {} edges", metric, threshold, similar.num_nodes, similar.num_edges);
+        println!("\nTop 10 Nodes by Strength (sum of similarity weights):");
+        for (node, strength) in similar.strengths().into_iter().take(10) {
+            println!("Node {:>4}: {}", results.name(node), format.format(MetricFamily::Similarity, strength));
+        }
+        println!("\nTop 10 Nodes by Weighted PageRank:");
+        for (node, rank) in pagerank_weighted(&similar, 0.85, 1e-8)?.into_iter().take(10) {
+            println!("Node {:>4}: {}", results.name(node), format.format(MetricFamily::Centrality, rank));
+        }
+        return Ok(());
+    }
 
-    //Print the degree of the first 10 nodes
-    println!("\nDegree Distribution:");
-    for (node, degree) in graph.all_degrees().iter().take(10) {
-        println!("Node {:>4}: Degree {:>3}", node, degree);
+    //"robustness [--steps N] [--out FILE.csv]" subcommand: share of people still in the largest component as nodes fail at random vs as the hubs are removed first
+    if args.get(1).map(String::as_str) == Some("robustness") {
+        let steps = flag(args, "--steps")?.unwrap_or(10);
+        let failure = robustness_profile(&graph, RemovalStrategy::RandomRemoval, steps, COMMUNITY_SEED);
+        let attack = robustness_profile(&graph, RemovalStrategy::HighestDegreeFirst, steps, COMMUNITY_SEED);
+        println!("{:>8} {:>10} {:>10}", "Removed", "Random", "Hubs first");
+        for (&(fraction, random), &(_, hubs)) in failure.iter().zip(&attack) {
+            println!("{:>8} {:>10} {:>10}", format.format(MetricFamily::Similarity, fraction), format.format(MetricFamily::Similarity, random), format.format(MetricFamily::Similarity, hubs));
+        }
+        if let Some(out) = flag::<String>(args, "--out")? {
+            report::write_robustness_csv(&out, &[("random", &failure), ("degree", &attack)], results.provenance.as_ref())?;
+            println!("Wrote {}", out);
+        }
+        return Ok(());
+    }
+
+    //"cliques [--min-size K] [--limit N]" subcommand: count the maximal cliques of at least K people (default 3) by size, streamed and stopped after N of them
+    //(default a million; the Facebook data has over forty million), and show a largest one
+    if args.get(1).map(String::as_str) == Some("cliques") {
+        let (min_size, limit) = (flag(args, "--min-size")?.unwrap_or(3), flag(args, "--limit")?.unwrap_or(1_000_000));
+        let (mut by_size, mut found): (BTreeMap<usize, usize>, usize) = (BTreeMap::new(), 0);
+        let stopped = for_each_maximal_clique(&graph, min_size, |clique| {
+            *by_size.entry(clique.len()).or_insert(0) += 1;
+            found += 1;
+            if found >= limit { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        });
+        let note = if stopped.is_some() { " (stopped at --limit)" } else { "" };
+        println!("Maximal cliques of at least {} people: {}{}", min_size, found, note);
+        for (size, count) in &by_size {
+            println!("{:>4} people: {}", size, count);
+        }
+        let largest: Vec<String> = largest_clique(&graph).iter().map(|&n| results.name(n)).collect();
+        println!("Largest clique ({} people): {}", largest.len(), largest.join(", "));
+        return Ok(());
+    }
+
+    //"walks --out FILE [--walks-per-node N] [--walk-length L] [--p P --q Q] [--seed S]" subcommand: random walk "sentences" for node2vec/word2vec, uniform unless p or q is given
+    if args.get(1).map(String::as_str) == Some("walks") {
+        let out = flag::<String>(args, "--out")?.ok_or_else(|| GraphError::InvalidParameter(String::from("walks needs --out FILE")))?;
+        let (walks_per_node, walk_length) = (flag(args, "--walks-per-node")?.unwrap_or(10), flag(args, "--walk-length")?.unwrap_or(80));
+        let seed = flag(args, "--seed")?.unwrap_or(42);
+        let walks = match (flag::<f64>(args, "--p")?, flag::<f64>(args, "--q")?) {
+            (None, None) => generate_random_walks(&graph, walks_per_node, walk_length, seed),
+            (p, q) => generate_biased_walks(&graph, walks_per_node, walk_length, p.unwrap_or(1.0), q.unwrap_or(1.0), seed)?,
+        };
+        report::write_walks(&out, &walks, &graph)?;
+        println!("Wrote {} walks of {} nodes to {}", walks.len(), walk_length, out);
+        return Ok(());
+    }
+
+    //"stability [--runs N] [--algorithm louvain|label-propagation]" subcommand: rerun community detection with different seeds and report how much the partitions agree
+    if args.get(1).map(String::as_str) == Some("stability") {
+        let runs = flag(args, "--runs")?.unwrap_or(10);
+        let algorithm = match flag::<String>(args, "--algorithm")?.as_deref() {
+            None | Some("louvain") => CommunityAlgorithm::Louvain,
+            Some("label-propagation") => CommunityAlgorithm::LabelPropagation,
+            Some(other) => return Err(GraphError::InvalidParameter(format!("unknown algorithm {:?}, expected louvain or label-propagation", other))),
+        };
+        let report = partition_stability(&graph, algorithm, runs, COMMUNITY_SEED, &|done, total| eprintln!("  run {}/{}", done, total));
+        println!("{:?} over {} seeds: mean NMI {}, min NMI {}", algorithm, report.runs, format.format(MetricFamily::Similarity, report.mean_nmi), format.format(MetricFamily::Similarity, report.min_nmi));
+        println!("Consensus partition: {} communities", community_sizes(&report.consensus).len());
+        return Ok(());
+    }
+
+    //"communities" subcommand: detect communities and show which nodes have friends spread over the most of them
+    //With "--partition FILE" the communities are read from another tool's output (node/community pairs or SNAP circles) instead of being detected
+    if args.get(1).map(String::as_str) == Some("communities") {
+        if let Some(i) = args.iter().position(|a| a == "--partition") {
+            let file = args.get(i + 1).ok_or_else(|| GraphError::InvalidParameter(String::from("--partition needs a file path")))?;
+            let partition = read_partition(file, PartitionFormat::Auto)?;
+            //The file names nodes by their original ids: translate them and drop (after reporting) the ones the graph does not have
+            let mut unknown: Vec<usize> = partition.keys().copied().filter(|&n| graph.internal_id(NodeId(n)).is_none()).collect();
+            unknown.sort();
+            if !unknown.is_empty() {
+                eprintln!("Warning: {} node ids in {} are not in the graph: {:?}", unknown.len(), file, unknown);
+            }
+            results.communities = Some(partition.into_iter().filter_map(|(n, c)| graph.internal_id(NodeId(n)).map(|i| (i.index(), c))).collect());
+        } else if let Some(target) = flag::<usize>(args, "--girvan-newman")? { //"--girvan-newman N": cut the most central friendships until N communities remain (slow: for small graphs or --include-only subgraphs)
+            let (partitions, removed) = girvan_newman_dendrogram(&graph, target);
+            let partition = partitions.into_iter().last().unwrap();
+            println!("Girvan–Newman found {} communities after removing {} friendships (modularity {})", community_sizes(&partition).len(), removed.len(), format.format(MetricFamily::Similarity, modularity(&graph, &partition)));
+            results.communities = Some(partition);
+        } else if let Some(resolution) = flag::<f64>(args, "--louvain")? { //"--louvain RESOLUTION": Louvain modularity optimization instead of label propagation (1.0 = standard modularity)
+            let (partition, q) = louvain(&graph, resolution);
+            println!("Louvain found {} communities (modularity {})", community_sizes(&partition).len(), format.format(MetricFamily::Similarity, q));
+            results.communities = Some(partition);
+        }
+        results.ensure_communities(&graph);
+        results.ensure_cross_community_pairs(&graph, 5); //Structurally similar people in different communities
+        report::write_text(&results, &graph, StalePolicy::Warn, &mut std::io::stdout(), &format)?;
+        let mut diversity: Vec<(usize, usize)> = results.ensure_diversity(&graph).iter().map(|(&n, &d)| (n, d)).collect();
+        diversity.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        println!("\nTop 10 Most Diverse Nodes (friends in the most communities):");
+        for (node, count) in diversity.into_iter().take(10) {
+            println!("Node {:>4}: {} communities", results.name(node), count);
+        }
+        //"--export DIR [--min-size N]": also save every community of at least N people (default 3) as its own edge list, plus a manifest
+        if let Some(dir) = flag::<String>(args, "--export")? {
+            let min_size = flag(args, "--min-size")?.unwrap_or(3);
+            let partition = results.ensure_communities(&graph).clone();
+            let written = report::export_communities(&dir, &graph, &partition, min_size, &format, results.provenance.as_ref())?;
+            println!("\nWrote {} community edge lists and manifest.csv to {}", written, dir);
+        }
+        //"--dot FILE": the whole graph in Graphviz DOT format with one fill color per community
+        if let Some(out) = flag::<String>(args, "--dot")? {
+            graph.write_dot_with(&out, &DotStyle { communities: results.communities.as_ref(), ..DotStyle::default() })?;
+            println!("Wrote {}", out);
+        }
+        //"--graphml FILE": the same for Gephi or Cytoscape, with each node's community as a "community" attribute
+        if let Some(out) = flag::<String>(args, "--graphml")? {
+            let attrs: HashMap<usize, HashMap<String, String>> = results.ensure_communities(&graph).iter().map(|(&n, &c)| (n, HashMap::from([(String::from("community"), c.to_string())]))).collect();
+            graph.write_graphml(&out, Some(&attrs))?;
+            println!("Wrote {}", out);
+        }
+        return Ok(());
+    }
+
+    //Distances, centralities, similarities and communities all compare pairs of people; with fewer than two they would print misleading zeros
+    if graph.num_nodes() < 2 {
+        print!("{}", graph.stats());
+        for analysis in ["average distance", "diameter", "closeness", "betweenness", "PageRank", "similarity", "communities"] {
+            println!("Graph too small for {} (needs at least 2 nodes)", analysis);
+        }
+        return Ok(());
+    }
+
+    //Pick exact or cheaper variants of the expensive analyses from the graph size
+    //("--distances exact|sampled", "--closeness exact|sampled", "--diameter exact|double-sweep", "--betweenness exact|sampled|skip", "--similarity exact|approx|skip" override the choice)
+    let mut requested = Vec::new();
+    for (analysis, name) in [
+        (PlannedAnalysis::Distances, "--distances"),
+        (PlannedAnalysis::Closeness, "--closeness"),
+        (PlannedAnalysis::Diameter, "--diameter"),
+        (PlannedAnalysis::Betweenness, "--betweenness"),
+        (PlannedAnalysis::SimilarPairs, "--similarity"),
+    ] {
+        requested.push((analysis, flag::<String>(args, name)?.map(|v| Variant::from_name(&v)).transpose()?));
+    }
+    if config.skip_similar_pairs { //"--skip-similar-pairs" is "--similarity skip"
+        requested[4].1 = Some(Variant::Skip);
+    }
+    let mut plan = plan(GraphSize::from(&graph), &requested);
+    if let Some(first) = flag::<String>(args, "--run-first")? { //"--run-first roles,degrees": these sections run first, whatever their estimated cost
+        plan.priority = first.split(',').map(Section::from_name).collect::<Result<_, _>>()?;
+    }
+    print!("Execution plan:\n{}", plan);
+
+    //Run every analysis once, cheapest first, and keep the results in one container
+    //Each section goes to stderr as soon as it is ready; the full report below is printed once everything has run
+    let mut progress = std::io::stderr();
+    let mut tasks = planned_tasks(&plan, config.top);
+    if config.skip_average_distance { //"--skip-average-distance": no all-pairs sweep (closeness, if exact, then runs its own)
+        tasks.retain(|task| task.section != Section::Distances);
+    }
+    let mut section_started = Instant::now();
+    results.run_scheduled(&graph, &plan, tasks, |results, section| {
+        timings.record(section.name(), section_started.elapsed());
+        eprintln!("[{:.2}s] {} ready", started.elapsed().as_secs_f64(), section.name());
+        report::write_section(results, &graph, StalePolicy::Warn, section, &mut progress, &format)?;
+        progress.flush()?;
+        section_started = Instant::now(); //Printing the section is not part of its time
+        Ok(())
+    })?;
+    if let Some(cache) = results.cache.as_ref().filter(|c| !c.hits.is_empty()) {
+        eprintln!("Loaded from cache: {}", cache.hits.join(", "));
+    }
+    report::write_text(&results, &graph, StalePolicy::Warn, &mut std::io::stdout(), &format)?; //Nothing here mutates the graph, but the report would say so if a result were stale
+
+    //PageRank next to closeness: who collects links vs who is close to everyone (isolated nodes treated as with --isolated)
+    let (ranks, _) = apply_isolated(&graph, timings.time_stage("pagerank", || pagerank(&graph, 0.85, PAGERANK_MAX_ITERATIONS, 1e-8))?, results.isolated);
+    let top_closeness: Vec<(usize, f64)> = if let Some(scores) = &results.closeness {
+        scores.iter().take(config.top).copied().collect()
+    } else if results.hub_exclusion.is_none() { //"--closeness skip" drops the full ranking; the table only needs its top rows, and top_k_closeness prunes most of the BFS work
+        apply_isolated(&graph, top_k_closeness(&graph, config.top), results.isolated).0
+    } else { //Hub-excluding closeness only comes from the full sweep
+        results.ensure_closeness(&graph).iter().take(config.top).copied().collect()
+    };
+    println!("\nTop {} PageRank             | Top {} Closeness", config.top, config.top);
+    for (&(pr_node, pr), &(c_node, c)) in ranks.iter().zip(&top_closeness).take(config.top) {
+        println!("Node {:>4}: {:<14} | Node {:>4}: {}", results.name(pr_node), format.format(MetricFamily::Centrality, pr), results.name(c_node), format.format(MetricFamily::Centrality, c));
+    }
+    println!("_____________");
+
+    if let Some(bottom) = flag::<usize>(args, "--bottom")? { //"--bottom N": the N least central people who are still in the giant component, for outreach
+        let options = PeripheryOptions::default();
+        let closeness = match results.closeness.as_deref() {
+            Some(scores) => least_central_among(&graph, scores, bottom, &options),
+            None => least_central_with(&graph, PeripheryMetric::Closeness, bottom, &options, &results.parallelism)?,
+        };
+        let degrees = least_central_with(&graph, PeripheryMetric::Degree, bottom, &options, &results.parallelism)?;
+        let ranks = least_central_among(&graph, &ranks, bottom, &options);
+        println!("\nBottom {} Closeness         | Bottom {} Degree | Bottom {} PageRank (giant component only)", bottom, bottom, bottom);
+        for ((&(c_node, c), &(d_node, d)), &(pr_node, pr)) in closeness.iter().zip(&degrees).zip(&ranks) {
+            println!("Node {:>4}: {:<14} | Node {:>4}: {:<5} | Node {:>4}: {}", results.name(c_node), format.format(MetricFamily::Centrality, c), results.name(d_node), d, results.name(pr_node), format.format(MetricFamily::Centrality, pr));
+        }
+        println!("_____________");
+    }
+
+    //Eigenvector centrality: friends of well-connected people count for more, to set against closeness and the degree list above
+    println!("\nTop 5 Eigenvector Centrality:");
+    for (node, score) in eigenvector_centrality(&graph, PAGERANK_MAX_ITERATIONS, 1e-8).into_iter().take(5) {
+        println!("Node {:>4}: {}", results.name(node), format.format(MetricFamily::Centrality, score));
+    }
+    println!("_____________");
+
+    //Who and which friendships hold the network together: removing any of them splits a component
+    let (points, bridge_edges) = (articulation_points(&graph), bridges(&graph));
+    println!("\n{} articulation points and {} bridges", points.len(), bridge_edges.len());
+    let mut points: Vec<(usize, usize)> = points.into_iter().map(|n| (n, graph.adjacency()[&n].len())).collect();
+    points.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    for (node, degree) in points.into_iter().take(5) {
+        println!("Node {:>4}: articulation point with {} friends", results.name(node), degree);
     }
     println!("_____________");
 
-    //Compute and display the average shortest path length
-    let avg_dist = average_distance(&graph);
-    println!("\nAverage Distance (Six Degrees): {:.2}", avg_dist);
+    //Two-sided structure check, e.g. for user-item edge lists: a two-coloring, or an odd cycle showing why there is none
+    match bipartite_check(&graph) {
+        Bipartiteness::TwoColoring(sides) => {
+            let second = sides.values().filter(|&&side| side).count();
+            println!("\nBipartite: yes, sides of {} and {} nodes", sides.len() - second, second);
+        }
+        Bipartiteness::OddCycle(cycle) => {
+            let cycle: Vec<String> = cycle.iter().map(|&n| results.name(n)).collect();
+            println!("\nBipartite: no, odd cycle of {} nodes: {}", cycle.len(), cycle.join(" → "));
+        }
+    }
     println!("_____________");
 
-    //Compute and display top 5 nodes ranked by closeness centrality
-    println!("\nTop 5 Closeness Centrality Nodes:");
-    for (node, centrality) in closeness_centrality(&graph).into_iter().take(5) {
-        println!("Node {:>4}: Closeness Centrality {:.4}", node, centrality);
+    //Tie strength: friendships whose two ends share the most and the fewest friends (neighborhood overlap alongside)
+    let embeddedness = edge_embeddedness(&graph);
+    let overlap = edge_overlap(&graph, &embeddedness);
+    let mut edges: Vec<((usize, usize), usize)> = embeddedness.into_iter().collect();
+    edges.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    let print_edge = |&((u, v), common): &((usize, usize), usize)| println!("{} - {}: {} mutual friends (overlap {})", results.name(u), results.name(v), common, format.format(MetricFamily::Similarity, overlap[&(u, v)]));
+    println!("\nMost embedded friendships:");
+    edges.iter().take(5).for_each(print_edge);
+    println!("Least embedded friendships:");
+    edges.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0))); //Fewest first, ties still in edge order
+    edges.iter().take(5).for_each(print_edge);
+    println!("_____________");
+
+    //"Six degrees" checked directly: the share of connected pairs within k hops, with the k that first covers 90% and 99% marked.
+    //Read off the exact distance sweep when it ran; otherwise a fresh BFS from every node, sampled from SEPARATION_SOURCES sources on graphs too big for exact distances
+    if !config.skip_average_distance {
+        let table = match &results.stats {
+            Some(stats) if stats.distance_sources.is_none() => separation_table(stats.distance_distribution.iter().map(|(&hops, &pairs)| (hops, pairs)), 10),
+            _ => degrees_of_separation_table_with(&graph, 10, (graph.num_nodes() > EXACT_DISTANCES_MAX_NODES).then_some(SEPARATION_SOURCES), COMMUNITY_SEED, &results.parallelism),
+        };
+        let marks = [(separation_threshold(&table, 0.90), "90%"), (separation_threshold(&table, 0.99), "99%")];
+        println!("\nDegrees of Separation (share of connected pairs within k hops):");
+        for &(k, share) in &table {
+            let covered: Vec<&str> = marks.iter().filter(|(threshold, _)| *threshold == Some(k)).map(|&(_, label)| label).collect();
+            let note = if covered.is_empty() { String::new() } else { format!("  <- first k covering {}", covered.join(" and ")) };
+            println!("k = {:>2}: {}{}", k, format.format(MetricFamily::Centrality, share), note);
+        }
+        println!("_____________");
+    }
+
+    //"--compare-random": the same measures on Erdős–Rényi graphs with as many nodes and the same density, to show how much of them is social structure
+    if config.compare_random {
+        let world = small_world_sigma(&graph, 3, COMMUNITY_SEED)?;
+        println!("\nReal vs Random Graph (mean of 3 random graphs with {} nodes and density {}):", graph.num_nodes(), format.format(MetricFamily::Similarity, density(&graph)));
+        println!("Average distance:   {:<10} | {}", format.format(MetricFamily::Distance, world.l), format.format(MetricFamily::Distance, world.l_rand));
+        println!("Average clustering: {:<10} | {}", format.format(MetricFamily::Similarity, world.c), format.format(MetricFamily::Similarity, world.c_rand));
+        println!("Small-world sigma:  {}", format.format(MetricFamily::Distance, world.sigma));
+        let rewired = generate::rewire_preserving_degrees(&graph, 10 * graph.num_edges(), COMMUNITY_SEED); //Same degrees, random otherwise: clustering above this is not just the hubs
+        println!("Transitivity:       {:<10} | {} (degree-preserving rewiring)", format.format(MetricFamily::Similarity, transitivity(&graph)?), format.format(MetricFamily::Similarity, transitivity(&rewired)?));
+        println!("_____________");
+    }
+
+    //Optional "--metrics FILE": every per-node metric in one wide table (CSV if FILE ends in .csv, tab-separated otherwise)
+    if let Some(file) = flag::<String>(args, "--metrics")? {
+        let table = if file.ends_with(".csv") { TableFormat::Csv } else { TableFormat::Tsv };
+        report::write_wide_metrics(&file, &results, &graph, StalePolicy::Warn, table, &format)?;
+    }
+
+    //Optional "--degree-histogram FILE": degree,count rows for plotting the degree distribution
+    if let Some(file) = flag::<String>(args, "--degree-histogram")? {
+        report::write_degree_histogram_csv(&graph, &file, results.provenance.as_ref())?;
+    }
+
+    //Optional "--distance-distribution FILE": distance,pairs rows for the hop plot, from the distance sweep if it ran (else estimated from SEPARATION_SOURCES BFS sources)
+    if let Some(file) = flag::<String>(args, "--distance-distribution")? {
+        match &results.stats {
+            Some(stats) => report::write_distance_distribution_csv(&file, &stats.distance_distribution, results.provenance.as_ref())?,
+            None => report::write_distance_distribution_csv(&file, &distance_distribution_sampled_with(&graph, SEPARATION_SOURCES, COMMUNITY_SEED, &results.parallelism), results.provenance.as_ref())?,
+        }
+    }
+
+    //Optional "--out-dir DIR": the printed results again as files at full precision (closeness.csv, betweenness.csv, similar_pairs.csv, report.json)
+    if let Some(dir) = flag::<String>(args, "--out-dir")? {
+        std::fs::create_dir_all(&dir)?;
+        let file = |name: &str| PathBuf::from(&dir).join(name).to_string_lossy().into_owned();
+        let provenance = results.provenance.as_ref();
+        for (name, scores) in [("closeness.csv", &results.closeness), ("betweenness.csv", &results.betweenness)] {
+            if let Some(scores) = scores {
+                report::write_centrality_csv(&file(name), scores, &graph, provenance)?;
+            }
+        }
+        if let Some(pairs) = &results.similar_pairs {
+            report::write_pairs_csv(&file("similar_pairs.csv"), pairs, &graph, provenance)?;
+        }
+        let mut summary = AnalysisReport::from_results(&results, config.top);
+        if config.timing { //The stages run so far; the ones after this point are only in the printed table
+            summary.timings = timings.seconds();
+        }
+        report::write_report_json(&file("report.json"), &summary, provenance)?;
+        println!("Wrote results to {}", dir);
+    }
+
+    //Compute and print Jaccard similarity for selected pairs: over shared friends (1-hop) and over shared friends of friends (2-hop)
+    println!("\nJaccard Similarities (Friends | Friends of Friends):");
+    for &(u, v) in &config.pairs { //"--pairs U:V,U:V"
+        let (one_hop, two_hop) = match (graph.internal_id(u), graph.internal_id(v)) {
+            (Some(a), Some(b)) => (jaccard_similarity(&graph, a, b), friends_of_friends_similarity(&graph, a, b)),
+            _ => (0.0, 0.0),
+        };
+        println!("Nodes {} & {} → 1-hop: {} | 2-hop: {}", u, v, format.format(MetricFamily::Similarity, one_hop), format.format(MetricFamily::Similarity, two_hop));
     }
     println!("_____________");
 
-    //Compute and print Jaccard similarity for selected friend pairs
-    let pairs = vec![(0, 1), (0, 2), (1, 3)];
-    println!("\nJaccard Similarities (Friends of Friends):");
-    for (u, v) in pairs {
-        let sim = jaccard_similarity(&graph, u, v);
-        println!("Nodes {} & {} → Similarity: {:.3}", u, v, sim);
+    //Friend recommendations for a couple of people: friends of friends they are not yet friends with, most similar first
+    println!("\nSuggested Friends (Jaccard):");
+    for id in [0, 107] {
+        if let Some(node) = graph.internal_id(NodeId(id)) {
+            let suggestions: Vec<String> = recommend_friends(&graph, node, config.top, SimilarityMetric::Jaccard)
+                .into_iter()
+                .map(|(candidate, score)| format!("{} ({})", results.name(candidate), format.format(MetricFamily::Similarity, score)))
+                .collect();
+            println!("Node {} → {}", results.name(node.index()), if suggestions.is_empty() { String::from("no suggestions") } else { suggestions.join(", ") });
+        }
     }
     println!("_____________");
 
-    //Find and print the top 5 most similar node pairs in the entire graph
-    println!("\nTop Jaccard Similarities (Most Similar Friend Pairs):");
-    for ((u, v), sim) in most_similar_pairs(&graph, 5) {
-        println!("Nodes {} & {} → Similarity: {:.3}", u, v, sim);
+    //The same pairs question in absolute numbers: who shares the most friends (dominated by hubs, which Jaccard discounts); only when the similarity scan ran, as it costs the same
+    if results.similar_pairs.is_some() {
+        println!("\nMost Mutual Friends (pairs sharing the most friends):");
+        for ((u, v), count) in top_pairs_by_mutual_friends(&graph, config.top) {
+            println!("Nodes {} & {} → {} mutual friends", results.name(u), results.name(v), count);
+        }
+        println!("_____________");
+    }
+
+    //Chain of friends between two far-apart people: the first node and whoever is farthest from it (smallest id on ties)
+    if let Some(start) = graph.adjacency().keys().min().copied() {
+        let distances = bfs_distances(&graph, InternalId(start));
+        let (&end, _) = distances.iter().max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0))).unwrap();
+        let chain: Vec<String> = bfs_path(&graph, InternalId(start), InternalId(end)).unwrap().iter().map(|&n| results.name(n)).collect();
+        println!("\nChain of friends from {} to {} ({} steps): {}", results.name(start), results.name(end), chain.len() - 1, chain.join(" → "));
     }
 
-    //Debugging: Check friends of a reference node to verify similarity behavior (making sure jaccard is working)
-    let reference = 2817;
-    if let Some(friends) = graph.adj_list.get(&reference) {
+    //Debugging: Check friends of a reference node to verify similarity behavior (making sure jaccard is working); "--reference ID" picks another node
+    let reference = config.reference;
+    if let Some(friends) = graph.internal_id(reference).and_then(|n| graph.adjacency().get(&n.index())) {
+        let friends: Vec<usize> = friends.iter().map(|&f| graph.original_id(InternalId(f)).0).collect();
         println!("Node {} has {} friends: {:?}", reference, friends.len(), friends);
+    }
+
+    //"--timing": how long each stage took, and how many candidate pairs the similarity scan had to score
+    if config.timing {
+        print!("\nStage timings:\n{}", timings);
+        if let Some(stats) = results.similar_pairs_stats {
+            println!("Similarity candidates: {} pairs scored, {} dropped by the degree filters", stats.scored, stats.filtered_by_degree);
+        }
+    }
+    Ok(())
+}
+
+fn flag<T: std::str::FromStr>(args: &[String], name: &str) -> Result<Option<T>, GraphError> { //Value following "--name" on the command line, if the flag is present
+    match args.iter().position(|a| a == name) {
+        Some(i) => args
+            .get(i + 1)
+            .and_then(|v| v.parse().ok())
+            .map(Some)
+            .ok_or_else(|| GraphError::InvalidParameter(format!("{} needs a valid value", name))),
+        None => Ok(None),
+    }
 }
-}
\ No newline at end of file
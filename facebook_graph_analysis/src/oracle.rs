@@ -0,0 +1,90 @@
+//Module: oracle.rs
+//Here we answer "how far apart are u and v" without a BFS per question: a DistanceOracle runs one BFS from each of a few landmark nodes up front,
+//then bounds any distance from the stored vectors. Through landmark l, d(u, v) <= d(u, l) + d(l, v) (the estimate) and d(u, v) >= |d(u, l) - d(v, l)| (the lower bound)
+use std::collections::HashMap;
+use crate::analysis::bfs_distances;
+use crate::graph::sampling::sample_nodes;
+use crate::graph::Graph;
+use crate::parallel::ParallelismConfig;
+use crate::labels::InternalId;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LandmarkChoice {
+    #[default]
+    HighestDegree, //Hubs lie on many shortest paths, so the estimates are usually tight (ties by node id)
+    Random,        //Uniform sample (seeded), e.g. to compare against the hub choice
+}
+
+#[derive(Debug, Clone)]
+pub struct DistanceOracle {
+    landmarks: Vec<usize>,
+    distances: Vec<HashMap<usize, usize>>, //distances[i]: BFS distances from landmarks[i] (nodes it cannot reach are missing)
+}
+
+impl DistanceOracle {
+    pub fn new(graph: &Graph, num_landmarks: usize, seed: u64) -> Self { //with_choice with the highest-degree landmarks (seed is only used by LandmarkChoice::Random)
+        Self::with_choice(graph, num_landmarks, LandmarkChoice::default(), seed)
+    }
+
+    pub fn with_choice(graph: &Graph, num_landmarks: usize, choice: LandmarkChoice, seed: u64) -> Self { //One BFS per landmark; memory is num_landmarks distances per node
+        Self::with_parallelism(graph, num_landmarks, choice, seed, &ParallelismConfig::default())
+    }
+
+    pub fn with_parallelism(graph: &Graph, num_landmarks: usize, choice: LandmarkChoice, seed: u64, parallelism: &ParallelismConfig) -> Self { //Same as with_choice, with control over how many threads run the landmark BFS passes
+        let landmarks = match choice {
+            LandmarkChoice::HighestDegree => {
+                let mut degrees = graph.all_degrees();
+                degrees.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                degrees.into_iter().take(num_landmarks).map(|(node, _)| node).collect()
+            }
+            LandmarkChoice::Random => sample_nodes(graph, num_landmarks, seed),
+        };
+        let distances = parallelism.map_nodes(&landmarks, |landmark| bfs_distances(graph, InternalId(landmark)));
+        DistanceOracle { landmarks, distances }
+    }
+
+    pub fn landmarks(&self) -> &[usize] {
+        &self.landmarks
+    }
+
+    fn through_landmarks(&self, u: usize, v: usize) -> impl Iterator<Item = (usize, usize)> + '_ { //(d(u, l), d(v, l)) for every landmark l that reaches both
+        self.distances.iter().filter_map(move |d| Some((*d.get(&u)?, *d.get(&v)?)))
+    }
+
+    pub fn estimate(&self, u: usize, v: usize) -> Option<usize> { //Upper bound on d(u, v): the shortest detour through a landmark, exact when u or v is a landmark. None if no landmark reaches both
+        let detour = self.through_landmarks(u, v).map(|(du, dv)| du + dv).min()?;
+        Some(if u == v { 0 } else { detour })
+    }
+
+    pub fn lower_bound(&self, u: usize, v: usize) -> Option<usize> { //Lower bound on d(u, v) from the triangle inequality, also exact when u or v is a landmark. None if no landmark reaches both
+        self.through_landmarks(u, v).map(|(du, dv)| du.abs_diff(dv)).max()
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_oracle_brackets_exact_distance() { //Every pair of a random graph: lower_bound <= BFS distance <= estimate, both exact from a landmark; another component gives None
+        let mut graph = crate::generate::watts_strogatz(120, 4, 0.1, 6).unwrap();
+        graph.add_edge(InternalId(500), InternalId(501));
+        for choice in [LandmarkChoice::HighestDegree, LandmarkChoice::Random] {
+            let oracle = DistanceOracle::with_choice(&graph, 4, choice, 3);
+            assert_eq!(oracle.landmarks().len(), 4);
+            for u in (0..120).step_by(7) {
+                for (v, exact) in bfs_distances(&graph, InternalId(u)) {
+                    let (low, high) = (oracle.lower_bound(u, v).unwrap(), oracle.estimate(u, v).unwrap());
+                    assert!(low <= exact && exact <= high, "{:?} {}-{}: {} <= {} <= {}", choice, u, v, low, exact, high);
+                }
+            }
+            for &landmark in oracle.landmarks() {
+                for (v, exact) in bfs_distances(&graph, InternalId(landmark)) {
+                    assert_eq!((oracle.estimate(landmark, v), oracle.lower_bound(v, landmark)), (Some(exact), Some(exact)));
+                }
+            }
+            assert_eq!((oracle.estimate(0, 500), oracle.lower_bound(500, 501), oracle.estimate(7, 7)), (None, None, Some(0)));
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//Module: parallel.rs
+//Here we control how the per-node analyses are spread over threads. With the "parallel" feature (on by default) rayon is used;
+//with one thread, or without the feature, the plain sequential loop runs instead.
+//components_parallel and bfs_distances_parallel split a single traversal over threads for very large graphs; they give exactly the sequential answers
+use std::collections::HashMap;
+#[cfg(feature = "parallel")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "parallel")]
+use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use crate::csr::CsrGraph;
+use crate::analysis::{bfs_distances, connected_components};
+use crate::graph::Graph;
+use crate::labels::InternalId;
+
+pub const PARALLEL_MIN_EDGES: usize = 1_000_000; //Graphs with fewer edges run components_parallel and bfs_distances_parallel sequentially, as threads would cost more than they save
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParallelismConfig { //How many threads to use: 0 means "let rayon pick" (one per core), 1 forces the sequential code path
+    pub threads: usize,
+}
+
+impl ParallelismConfig {
+    pub fn with_threads(threads: usize) -> Self {
+        Self { threads }
+    }
+
+    pub fn sequential() -> Self {
+        Self { threads: 1 }
+    }
+
+    pub fn is_sequential(&self) -> bool { //True when work will run on the calling thread only
+        self.threads == 1 || cfg!(not(feature = "parallel"))
+    }
+
+    // Applies f to every node and returns the results in the same order as `nodes`, whichever code path runs
+    // Keeping the order fixed is what makes results identical for any thread count
+    pub fn map_nodes<N, T, F>(&self, nodes: &[N], f: F) -> Vec<T>
+    where
+        N: Copy + Sync,
+        T: Send,
+        F: Fn(N) -> T + Sync + Send,
+    {
+        if self.is_sequential() {
+            return nodes.iter().map(|&n| f(n)).collect();
+        }
+        self.map_nodes_parallel(nodes, f)
+    }
+
+    #[cfg(feature = "parallel")]
+    fn map_nodes_parallel<N, T, F>(&self, nodes: &[N], f: F) -> Vec<T>
+    where
+        N: Copy + Sync,
+        T: Send,
+        F: Fn(N) -> T + Sync + Send,
+    {
+        self.install(|| nodes.par_iter().map(|&n| f(n)).collect())
+    }
+
+    #[cfg(feature = "parallel")]
+    fn install<R: Send>(&self, work: impl FnOnce() -> R + Send) -> R { //Runs work inside a rayon pool with the configured number of threads; each pool is built once per thread count and reused
+        static POOLS: OnceLock<Mutex<HashMap<usize, Arc<rayon::ThreadPool>>>> = OnceLock::new();
+        let pool = {
+            let mut pools = POOLS.get_or_init(Default::default).lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let pool = pools.entry(self.threads).or_insert_with(|| {
+                Arc::new(rayon::ThreadPoolBuilder::new().num_threads(self.threads).build().expect("Failed to build thread pool."))
+            });
+            Arc::clone(pool)
+        };
+        pool.install(work)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn map_nodes_parallel<N, T, F>(&self, nodes: &[N], f: F) -> Vec<T>
+    where
+        N: Copy + Sync,
+        T: Send,
+        F: Fn(N) -> T + Sync + Send,
+    {
+        nodes.iter().map(|&n| f(n)).collect()
+    }
+}
+
+pub fn components_parallel(graph: &Graph) -> Vec<Vec<usize>> { //connected_components, with the labeling spread over every core on graphs of at least PARALLEL_MIN_EDGES edges
+    components_parallel_with(graph, &ParallelismConfig::default(), PARALLEL_MIN_EDGES)
+}
+
+pub fn components_parallel_with(graph: &Graph, parallelism: &ParallelismConfig, min_edges: usize) -> Vec<Vec<usize>> { //Same as components_parallel, with control over threads and the size below which the sequential version runs
+    if parallelism.is_sequential() || graph.num_edges() < min_edges {
+        return connected_components(graph);
+    }
+    components_by_min_label(graph, parallelism)
+}
+
+pub fn bfs_distances_parallel(graph: &Graph, start: usize) -> HashMap<usize, usize> { //bfs_distances, with each frontier expanded by every core on graphs of at least PARALLEL_MIN_EDGES edges
+    bfs_distances_parallel_with(graph, start, &ParallelismConfig::default(), PARALLEL_MIN_EDGES)
+}
+
+pub fn bfs_distances_parallel_with(graph: &Graph, start: usize, parallelism: &ParallelismConfig, min_edges: usize) -> HashMap<usize, usize> { //Same as bfs_distances_parallel, with control over threads and the size below which the sequential version runs
+    if parallelism.is_sequential() || graph.num_edges() < min_edges || !graph.adjacency().contains_key(&start) {
+        return bfs_distances(graph, InternalId(start));
+    }
+    level_synchronous_bfs(graph, start, parallelism)
+}
+
+#[cfg(feature = "parallel")]
+fn components_by_min_label(graph: &Graph, parallelism: &ParallelismConfig) -> Vec<Vec<usize>> {
+    //Rows of the CSR copy follow the internal ids in order. Every row starts labeled with itself and sweeps lower it to the smallest label among its friends (atomic fetch_min),
+    //followed by pointer jumping (a label is a row of the same component, so its own label is a valid smaller one), until a sweep changes nothing: each row then holds the smallest row of its component
+    let csr = CsrGraph::from(graph);
+    let nodes = &csr.nodes;
+    let labels: Vec<AtomicUsize> = (0..nodes.len()).map(AtomicUsize::new).collect();
+    parallelism.install(|| loop {
+        let changed = AtomicBool::new(false);
+        (0..nodes.len()).into_par_iter().for_each(|row| {
+            let smallest = csr.friends_at(row).iter().map(|&f| labels[f as usize].load(Ordering::Relaxed)).min().unwrap_or(row);
+            if labels[row].fetch_min(smallest, Ordering::Relaxed) > smallest {
+                changed.store(true, Ordering::Relaxed);
+            }
+        });
+        (0..nodes.len()).into_par_iter().for_each(|row| {
+            let label = labels[row].load(Ordering::Relaxed);
+            labels[row].fetch_min(labels[label].load(Ordering::Relaxed), Ordering::Relaxed);
+        });
+        if !changed.load(Ordering::Relaxed) {
+            break;
+        }
+    });
+
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (row, &node) in nodes.iter().enumerate() { //Rows in id order, so every member list comes out sorted
+        members.entry(labels[row].load(Ordering::Relaxed)).or_default().push(node);
+    }
+    let mut components: Vec<Vec<usize>> = members.into_values().collect();
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0]))); //Same order as connected_components
+    components
+}
+
+#[cfg(feature = "parallel")]
+fn level_synchronous_bfs(graph: &Graph, start: usize, parallelism: &ParallelismConfig) -> HashMap<usize, usize> {
+    //Each level claims unvisited friends of the frontier with a compare-and-swap on their distance, so every node joins exactly one next-frontier buffer (one per rayon task, concatenated afterwards)
+    let csr = CsrGraph::from(graph);
+    let distance: Vec<AtomicUsize> = (0..csr.num_nodes()).map(|_| AtomicUsize::new(usize::MAX)).collect();
+    let start_row = csr.row(start).unwrap().index();
+    distance[start_row].store(0, Ordering::Relaxed);
+    parallelism.install(|| {
+        let mut frontier = vec![start_row];
+        let mut level = 0;
+        while !frontier.is_empty() {
+            level += 1;
+            frontier = frontier
+                .par_iter()
+                .fold(Vec::new, |mut next, &row| {
+                    for &friend in csr.friends_at(row) {
+                        if distance[friend as usize].compare_exchange(usize::MAX, level, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+                            next.push(friend as usize);
+                        }
+                    }
+                    next
+                })
+                .reduce(Vec::new, |mut a, mut b| {
+                    a.append(&mut b);
+                    a
+                });
+        }
+    });
+    csr.nodes
+        .iter()
+        .zip(&distance)
+        .filter_map(|(&node, d)| Some(d.load(Ordering::Relaxed)).filter(|&d| d != usize::MAX).map(|d| (node, d)))
+        .collect()
+}
+
+#[cfg(not(feature = "parallel"))]
+fn components_by_min_label(graph: &Graph, _parallelism: &ParallelismConfig) -> Vec<Vec<usize>> {
+    connected_components(graph)
+}
+
+#[cfg(not(feature = "parallel"))]
+fn level_synchronous_bfs(graph: &Graph, start: usize, _parallelism: &ParallelismConfig) -> HashMap<usize, usize> {
+    bfs_distances(graph, start)
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{all_pairs_bfs, average_distance_sampled_with, average_distance_stratified_with, average_distance_weighted_with, average_distance_with, closeness_centrality_approx_with, closeness_centrality_weighted_with, closeness_centrality_with, closeness_from_sweeps, closeness_of, degrees_of_separation_table_with, distance_distribution_sampled_with, distance_histogram_with, harmonic_centrality_with, histogram_from_sweeps, least_central_with, PeripheryMetric, PeripheryOptions};
+    use crate::graph::sampling::{stratified_by_degree, Allocation};
+    use crate::graph::Graph;
+    use crate::oracle::{DistanceOracle, LandmarkChoice};
+
+    fn grid_graph(side: usize) -> Graph { //A side x side grid, big enough that work is actually split between threads
+        let mut graph = Graph::new();
+        for r in 0..side {
+            for c in 0..side {
+                let node = r * side + c;
+                let mut link = |other: usize| {
+                    graph.add_edge(InternalId(node), InternalId(other));
+                };
+                if c + 1 < side {
+                    link(node + 1);
+                }
+                if r + 1 < side {
+                    link(node + side);
+                }
+            }
+        }
+        graph
+    }
+
+    #[test]
+    fn test_same_results_at_1_and_4_threads() { //Thread count must never change the answers
+        let graph = grid_graph(12);
+        let one = ParallelismConfig::with_threads(1);
+        let four = ParallelismConfig::with_threads(4);
+        assert_eq!(closeness_centrality_with(&graph, &one), closeness_centrality_with(&graph, &four));
+        assert_eq!(average_distance_with(&graph, &one), average_distance_with(&graph, &four));
+
+        //The sampled, weighted and periphery paths take the same config
+        assert_eq!(closeness_centrality_approx_with(&graph, 20, 3, &one), closeness_centrality_approx_with(&graph, 20, 3, &four));
+        assert_eq!(harmonic_centrality_with(&graph, &one), harmonic_centrality_with(&graph, &four));
+        assert_eq!(average_distance_weighted_with(&graph, &one), average_distance_weighted_with(&graph, &four));
+        assert_eq!(closeness_centrality_weighted_with(&graph, &one), closeness_centrality_weighted_with(&graph, &four));
+        assert_eq!(average_distance_sampled_with(&graph, 20, 3, &one), average_distance_sampled_with(&graph, 20, 3, &four));
+        let sample = stratified_by_degree(&graph, 20, 3, Allocation::Proportional, 3);
+        assert_eq!(average_distance_stratified_with(&graph, &sample, &one), average_distance_stratified_with(&graph, &sample, &four));
+        assert_eq!(distance_distribution_sampled_with(&graph, 20, 3, &one), distance_distribution_sampled_with(&graph, 20, 3, &four));
+        assert_eq!(degrees_of_separation_table_with(&graph, 6, Some(20), 3, &one), degrees_of_separation_table_with(&graph, 6, Some(20), 3, &four));
+        let options = PeripheryOptions::default();
+        assert_eq!(least_central_with(&graph, PeripheryMetric::Closeness, 5, &options, &one).unwrap(), least_central_with(&graph, PeripheryMetric::Closeness, 5, &options, &four).unwrap());
+        let (oracle_one, oracle_four) = (DistanceOracle::with_parallelism(&graph, 4, LandmarkChoice::Random, 3, &one), DistanceOracle::with_parallelism(&graph, 4, LandmarkChoice::Random, 3, &four));
+        assert_eq!(oracle_one.landmarks(), oracle_four.landmarks());
+        assert!((0..144).all(|v| oracle_one.estimate(0, v) == oracle_four.estimate(0, v) && oracle_one.lower_bound(0, v) == oracle_four.lower_bound(0, v)));
+    }
+
+    #[test]
+    fn test_shared_sweeps_on_random_graph() { //A sparse G(n, p) with several components: one all_pairs_bfs gives the sequential closeness and histogram at any thread count
+        let graph = crate::generate::erdos_renyi(150, 0.015, 9).unwrap();
+        let sequential = ParallelismConfig::sequential();
+        let sweeps = all_pairs_bfs(&graph, &ParallelismConfig::with_threads(4));
+        assert_eq!(sweeps, all_pairs_bfs(&graph, &sequential));
+        assert_eq!(closeness_from_sweeps(&sweeps), closeness_centrality_with(&graph, &sequential));
+        assert_eq!(histogram_from_sweeps(&sweeps), distance_histogram_with(&graph, &sequential));
+        for (node, score) in closeness_from_sweeps(&sweeps) {
+            assert_eq!(score, closeness_of(&graph, InternalId(node)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_parallel_components_and_bfs_match_sequential() { //Sparse random graphs with many components and isolated nodes, forced onto the parallel path with min_edges 0
+        let four = ParallelismConfig::with_threads(4);
+        for seed in 0..5 {
+            let mut graph = crate::generate::erdos_renyi(300, 0.006, seed).unwrap();
+            graph.add_node(InternalId(1000)); //Isolated, and outside the generator's id range
+            assert_eq!(components_parallel_with(&graph, &four, 0), connected_components(&graph));
+            for start in [0, 17, 1000] {
+                assert_eq!(bfs_distances_parallel_with(&graph, start, &four, 0), bfs_distances(&graph, InternalId(start)));
+            }
+        }
+        let grid = grid_graph(20); //One component with a long diameter, so the label sweeps need several rounds
+        assert_eq!(components_parallel_with(&grid, &four, 0), connected_components(&grid));
+        assert_eq!(bfs_distances_parallel_with(&grid, 0, &four, 0), bfs_distances(&grid, InternalId(0)));
+        assert!(bfs_distances_parallel_with(&grid, 9999, &four, 0).is_empty());
+        assert_eq!(components_parallel(&grid), connected_components(&grid)); //Below PARALLEL_MIN_EDGES: the sequential fallback
+    }
+
+    #[test]
+    fn test_map_nodes_keeps_order() {
+        let nodes: Vec<usize> = (0..100).rev().collect();
+        let doubled = ParallelismConfig::with_threads(4).map_nodes(&nodes, |n| n * 2);
+        assert_eq!(doubled, nodes.iter().map(|n| n * 2).collect::<Vec<_>>());
+        assert!(ParallelismConfig::sequential().is_sequential());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_pools_keep_their_thread_count_across_calls() { //Reused pools must still run at the thread count each config asked for
+        for threads in [3, 2, 3] {
+            let counts = ParallelismConfig::with_threads(threads).map_nodes(&[0usize; 8], |_| rayon::current_num_threads());
+            assert!(counts.iter().all(|&c| c == threads));
+        }
+    }
+}
@@ -0,0 +1,1407 @@
+//Module: pipeline.rs
+//Here we keep the results of every analysis in one container, so each algorithm runs at most once and later features (reports, roles, comparisons) can reuse them
+//The writers that turn the container into output live in report.rs
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::analysis::{apply_isolated, bfs_sweeps, betweenness_centrality_sampled, average_clustering, component_labels, connected_components, local_clustering, giant_component_fraction, transitivity, transitivity_sampled, closeness_from_sweeps, distribution_from_histogram, histogram_from_sweeps, histogram_mean, histogram_median, diameter, betweenness_centrality, classify_roles, closeness_centrality_approx_with, density, diameter_double_sweep, eccentricities_with, without_hubs, distance_profiles_with, most_similar_pairs_approx, most_similar_pairs_with, neighborhood_diversity, similar_pairs_scan, two_hop_reach, DistanceProfile, IsolatedNodes, SimilarPairsOptions, SimilarPairsStats, Role, RoleOptions};
+use crate::cache::ResultCache;
+use crate::csr::{all_pairs_bfs_bitparallel, CsrGraph};
+use crate::community::{label_propagation, modularity, Partition};
+use crate::error::GraphError;
+use crate::generate::barabasi_albert;
+use crate::graph::sampling::sample_nodes;
+use crate::graph::{Graph, GraphRead};
+use crate::labels::{IdMap, NodeId};
+use crate::parallel::ParallelismConfig;
+use crate::report::Provenance;
+
+pub const COMMUNITY_SEED: u64 = 42; //Seed used for community detection so reports are reproducible
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphStats { //Graph-wide summary numbers
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    pub average_distance: f64,
+    pub median_distance: f64, //From the same distance histogram as the average (see analysis::distance_histogram)
+    pub distance_distribution: BTreeMap<usize, usize>, //Hop plot from the same sweep: hops -> unordered pairs that far apart (see analysis::distance_distribution)
+    pub excluded_nodes: usize, //Nodes removed before the run by --exclude-nodes / --include-only
+    pub distance_sources: Option<usize>, //BFS sources the distances were estimated from (ensure_stats_sampled), None when every node was one
+}
+
+#[derive(Debug, Default)]
+pub struct AnalysisResults { //Every field starts as None and is filled in the first time the matching analysis runs
+    pub degrees: Option<Vec<(usize, usize)>>,
+    pub closeness: Option<Vec<(usize, f64)>>,
+    pub betweenness: Option<Vec<(usize, f64)>>,
+    pub communities: Option<Partition>, //node -> community id
+    pub components: Option<HashMap<usize, usize>>, //node -> connected component label (0 = largest)
+    pub diversity: Option<HashMap<usize, usize>>, //node -> number of distinct communities among its friends
+    pub two_hop: Option<HashMap<usize, usize>>, //node -> number of friends of friends who are not friends
+    pub clustering: Option<HashMap<usize, f64>>, //node -> local clustering coefficient (their mean is average_clustering)
+    pub eccentricities: Option<HashMap<usize, usize>>, //node -> distance to the farthest node it can reach (the largest is the diameter)
+    pub diameter_bound: Option<usize>, //Double-sweep lower bound on the diameter, used instead of eccentricities on large graphs
+    pub similar_pairs: Option<Vec<((usize, usize), f64)>>,
+    pub similar_pairs_top_n: usize, //top_n the stored similar pairs were ranked for: a larger request ranks them again, a smaller one gets the first rows
+    pub cross_community_pairs: Option<Vec<((usize, usize), f64)>>, //Most similar pairs whose nodes sit in different communities
+    pub cross_community_top_n: usize, //Same as similar_pairs_top_n, for cross_community_pairs
+    pub roles: Option<HashMap<usize, Role>>,
+    pub distance_profiles: Option<HashMap<usize, DistanceProfile>>, //node -> number of people at each distance up to some depth
+    pub stats: Option<GraphStats>,
+    pub similar_pairs_stats: Option<SimilarPairsStats>, //Candidate pairs counted by the similar-pairs scan (None if it never ran, e.g. loaded from the cache)
+    pub exact_ranking: bool, //Similar pairs are ranked by exact fractions (SimilarPairsOptions::exact_ranking)
+    pub parallelism: ParallelismConfig, //Thread settings used by the parallel analyses (closeness, average distance)
+    pub id_map: Option<IdMap>, //Copy of the graph's id map so reports can show the ids from the input file
+    pub names: Option<Vec<String>>, //Labels of a LabeledGraph (names[node]), printed instead of ids by the text reports
+    pub isolated: IsolatedNodes, //How the closeness ranking treats nodes with no friends
+    pub isolated_excluded: usize, //Nodes left out of the closeness ranking by IsolatedNodes::Exclude
+    pub hub_exclusion: Option<usize>, //Distances and closeness treat nodes with more friends than this as absent
+    pub progress: Option<ProgressReport>, //Called during the all-sources sweeps and the similar-pairs scan (see PROGRESS_EVERY), so a long run shows it is still going
+    pub sampled_closeness: bool, //The plan chose sampled closeness, so ensure_stats does not store the exact closeness its sweeps would give for free
+    pub hubs_excluded: usize, //Nodes removed by hub_exclusion
+    pub excluded_nodes: usize, //Nodes the graph was scoped down by before analysis (see Graph::restrict_to), reported in GraphStats
+    pub versions: HashMap<&'static str, u64>, //Graph version each stored result was computed against (see stale)
+    pub cache: Option<ResultCache>, //When set, closeness, betweenness, similar pairs and communities are loaded from / saved to disk
+    pub provenance: Option<Provenance>, //When set, every file written from these results says which input, options and code produced it
+}
+
+pub type ProgressReport = fn(&str, usize, usize); //(analysis, sources or nodes done, to do), e.g. ("closeness", 1000, 4039)
+
+fn reporter(progress: Option<ProgressReport>, analysis: &'static str) -> impl Fn(usize, usize) + Sync { //The progress callback the analyses take, naming the analysis for AnalysisResults::progress
+    move |done, total| {
+        if let Some(report) = progress {
+            report(analysis, done, total);
+        }
+    }
+}
+
+fn distance_csr(graph: &Graph, hub_exclusion: Option<usize>) -> (CsrGraph, usize) { //CSR copy the distance sweeps run on (the graph, or its view without hubs) and the number of hubs left out
+    match hub_exclusion {
+        None => (CsrGraph::from(graph), 0),
+        Some(_) => {
+            let (view, excluded) = without_hubs(graph, hub_exclusion);
+            (CsrGraph::from_read(&view), excluded)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StalePolicy { //What check_fresh does when results were computed before the graph last changed
+    #[default]
+    Warn, //Return the names of the stale results so the caller can warn about them
+    Refuse, //Fail with InvalidParameter
+}
+
+impl AnalysisResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn for_graph(graph: &Graph) -> Self { //Empty results that report node ids the way the graph's input file wrote them
+        Self { id_map: graph.id_map.clone(), ..Self::default() }
+    }
+
+    pub fn record_provenance(&mut self, graph: &Graph, input: &str, options: &str, started: Instant) { //Attaches provenance for this run; the seed is COMMUNITY_SEED, which every seeded analysis here uses
+        self.provenance = Some(Provenance::new(input, options, Some(COMMUNITY_SEED), started).for_graph(graph));
+    }
+
+    pub fn label(&self, node: usize) -> NodeId { //Id to print for an internal node id
+        self.id_map.as_ref().map_or(NodeId(node), |map| map.original_or_internal(node))
+    }
+
+    pub fn name(&self, node: usize) -> String { //What the text reports print for a node: its label for a LabeledGraph, otherwise label(node)
+        match self.names.as_ref().and_then(|names| names.get(node)) {
+            Some(name) => name.clone(),
+            None => self.label(node).to_string(),
+        }
+    }
+
+    fn stamp(&mut self, name: &'static str, graph: &Graph) { //Records the graph version the first time a result is computed (already stamped results keep their version)
+        self.versions.entry(name).or_insert(graph.version());
+    }
+
+    pub fn stale(&self, graph: &Graph) -> Vec<&'static str> { //Names of the stored results computed against an older version of the graph, sorted
+        let mut names: Vec<&'static str> = self.versions.iter().filter(|&(_, &v)| v != graph.version()).map(|(&name, _)| name).collect();
+        names.sort();
+        names
+    }
+
+    pub fn check_fresh(&self, graph: &Graph, policy: StalePolicy) -> Result<Vec<&'static str>, GraphError> { //Run before writing a report: stale results are returned (Warn) or rejected (Refuse)
+        let stale = self.stale(graph);
+        if policy == StalePolicy::Refuse && !stale.is_empty() {
+            return Err(GraphError::InvalidParameter(format!("results computed before the graph changed: {}", stale.join(", "))));
+        }
+        Ok(stale)
+    }
+
+    pub fn refresh(&mut self, graph: &Graph) { //Drops every stale result so the next ensure_* call recomputes it, and picks up ids given to nodes added since
+        self.id_map = graph.id_map.clone();
+        for name in self.stale(graph) {
+            self.versions.remove(name);
+            match name {
+                "degrees" => self.degrees = None,
+                "closeness" => self.closeness = None,
+                "betweenness" => self.betweenness = None,
+                "two_hop" => self.two_hop = None,
+                "clustering" => self.clustering = None,
+                "eccentricities" => self.eccentricities = None,
+                "diameter_bound" => self.diameter_bound = None,
+                "similar_pairs" => self.similar_pairs = None,
+                "cross_community_pairs" => self.cross_community_pairs = None,
+                "stats" => self.stats = None,
+                "roles" => self.roles = None,
+                "communities" => self.communities = None,
+                "components" => self.components = None,
+                "diversity" => self.diversity = None,
+                "distance_profiles" => self.distance_profiles = None,
+                _ => {}
+            }
+        }
+    }
+
+    pub fn ensure_degrees(&mut self, graph: &Graph) -> &[(usize, usize)] { //Degrees sorted by node id, computed on first use
+        self.stamp("degrees", graph);
+        self.degrees.get_or_insert_with(|| {
+            let mut degrees = graph.all_degrees();
+            degrees.sort();
+            degrees
+        })
+    }
+
+    pub fn ensure_closeness(&mut self, graph: &Graph) -> &[(usize, f64)] {
+        let (parallelism, cap) = (self.parallelism, self.hub_exclusion);
+        let mut excluded = self.hubs_excluded;
+        let mut cache = self.cache.take();
+        let progress = reporter(self.progress, "closeness");
+        self.ensure_closeness_with(graph, |g| {
+            excluded = without_hubs(g, cap).1; //Cheap to recount, so it is not part of the cache entry
+            let compute = || closeness_from_sweeps(&all_pairs_bfs_bitparallel(&distance_csr(g, cap).0, &parallelism, &progress));
+            match cache.as_mut() {
+                Some(cache) => cache.get_or_compute(g, "closeness", &format!("hub_cap={:?}", cap), compute),
+                None => compute(),
+            }
+        });
+        self.cache = cache;
+        self.hubs_excluded = excluded;
+        self.closeness.as_ref().unwrap()
+    }
+
+    pub fn ensure_closeness_with(&mut self, graph: &Graph, compute: impl FnOnce(&Graph) -> Vec<(usize, f64)>) -> &[(usize, f64)] { //Same as ensure_closeness but with the algorithm passed in (used by tests and alternative implementations)
+        self.stamp("closeness", graph);
+        if self.closeness.is_none() {
+            let (scores, excluded) = apply_isolated(graph, compute(graph), self.isolated);
+            self.closeness = Some(scores);
+            self.isolated_excluded = excluded;
+        }
+        self.closeness.as_ref().unwrap()
+    }
+
+    pub fn ensure_betweenness(&mut self, graph: &Graph) -> &[(usize, f64)] {
+        let mut cache = self.cache.take();
+        self.ensure_betweenness_with(graph, |g| match cache.as_mut() {
+            Some(cache) => cache.get_or_compute(g, "betweenness", "", || betweenness_centrality(g)),
+            None => betweenness_centrality(g),
+        });
+        self.cache = cache;
+        self.betweenness.as_ref().unwrap()
+    }
+
+    pub fn ensure_betweenness_with(&mut self, graph: &Graph, compute: impl FnOnce(&Graph) -> Vec<(usize, f64)>) -> &[(usize, f64)] {
+        self.stamp("betweenness", graph);
+        self.betweenness.get_or_insert_with(|| compute(graph))
+    }
+
+    pub fn ensure_components(&mut self, graph: &Graph) -> &HashMap<usize, usize> {
+        self.stamp("components", graph);
+        self.components.get_or_insert_with(|| component_labels(graph))
+    }
+
+    pub fn ensure_eccentricities(&mut self, graph: &Graph) -> &HashMap<usize, usize> {
+        self.stamp("eccentricities", graph);
+        let parallelism = self.parallelism;
+        self.eccentricities.get_or_insert_with(|| eccentricities_with(graph, &parallelism))
+    }
+
+    pub fn ensure_two_hop(&mut self, graph: &Graph) -> &HashMap<usize, usize> {
+        self.stamp("two_hop", graph);
+        self.two_hop.get_or_insert_with(|| two_hop_reach(graph))
+    }
+
+    pub fn ensure_clustering(&mut self, graph: &Graph) -> &HashMap<usize, f64> {
+        self.stamp("clustering", graph);
+        self.clustering.get_or_insert_with(|| local_clustering(graph))
+    }
+
+    pub fn ensure_similar_pairs(&mut self, graph: &Graph, top_n: usize) -> &[((usize, usize), f64)] {
+        self.stamp("similar_pairs", graph);
+        if self.similar_pairs.is_none() || self.similar_pairs_top_n < top_n {
+            let stats = &mut self.similar_pairs_stats;
+            let progress = reporter(self.progress, "similar pairs");
+            let mut scan = || {
+                let (pairs, scanned) = similar_pairs_scan(graph, &SimilarPairsOptions { exact_ranking: self.exact_ranking, ..SimilarPairsOptions::top(top_n) }, &progress);
+                *stats = Some(scanned);
+                pairs
+            };
+            self.similar_pairs = Some(match self.cache.as_mut() {
+                Some(cache) => cache.get_or_compute(graph, "similar_pairs", &format!("top_n={} exact_ranking={}", top_n, self.exact_ranking), scan),
+                None => scan(),
+            });
+            self.similar_pairs_top_n = top_n;
+        }
+        let pairs = self.similar_pairs.as_ref().unwrap();
+        &pairs[..top_n.min(pairs.len())]
+    }
+
+    pub fn ensure_similar_pairs_approx(&mut self, graph: &Graph, top_n: usize) -> &[((usize, usize), f64)] { //ensure_similar_pairs scoring only MinHash LSH candidates (see most_similar_pairs_approx), for graphs too large for the full scan
+        self.stamp("similar_pairs", graph);
+        if self.similar_pairs.is_none() || self.similar_pairs_top_n < top_n {
+            let pairs = most_similar_pairs_approx(graph, top_n, SIMILAR_PAIRS_HASHES, SIMILAR_PAIRS_BANDS, COMMUNITY_SEED).expect("SIMILAR_PAIRS_HASHES is a multiple of SIMILAR_PAIRS_BANDS");
+            self.similar_pairs = Some(pairs);
+            self.similar_pairs_top_n = top_n;
+        }
+        let pairs = self.similar_pairs.as_ref().unwrap();
+        &pairs[..top_n.min(pairs.len())]
+    }
+
+    pub fn ensure_cross_community_pairs(&mut self, graph: &Graph, top_n: usize) -> &[((usize, usize), f64)] { //Most similar pairs whose nodes sit in different communities (detecting communities first if needed)
+        self.stamp("cross_community_pairs", graph);
+        if self.cross_community_pairs.is_none() || self.cross_community_top_n < top_n {
+            let exact_ranking = self.exact_ranking;
+            let partition = self.ensure_communities(graph);
+            let options = SimilarPairsOptions { top_n, partition: Some(partition), cross_community_only: true, exact_ranking, ..SimilarPairsOptions::default() };
+            self.cross_community_pairs = Some(most_similar_pairs_with(graph, &options));
+            self.cross_community_top_n = top_n;
+        }
+        let pairs = self.cross_community_pairs.as_ref().unwrap();
+        &pairs[..top_n.min(pairs.len())]
+    }
+
+    pub fn ensure_stats(&mut self, graph: &Graph) -> &GraphStats {
+        self.stamp("stats", graph);
+        if self.stats.is_none() {
+            let (csr, excluded) = distance_csr(graph, self.hub_exclusion);
+            let sweeps = all_pairs_bfs_bitparallel(&csr, &self.parallelism, &reporter(self.progress, "distances"));
+            let histogram = histogram_from_sweeps(&sweeps);
+            if self.closeness.is_none() && self.cache.is_none() && !self.sampled_closeness { //Same BFS passes as closeness, so store it now rather than sweeping again (a cache keeps its own closeness entry)
+                self.ensure_closeness_with(graph, |_| closeness_from_sweeps(&sweeps));
+            }
+            self.hubs_excluded = excluded;
+            self.stats = Some(self.stats_from_histogram(graph, &histogram, None));
+        }
+        self.stats.as_ref().unwrap()
+    }
+
+    pub fn ensure_stats_sampled(&mut self, graph: &Graph, num_sources: usize) -> &GraphStats { //ensure_stats from BFS runs out of num_sources random sources (COMMUNITY_SEED), for graphs too large for the all-pairs sweep.
+        //The mean and median are read off the sampled pairs; the distribution is scaled up by n / num_sources to estimated pair counts. With num_sources >= n it is ensure_stats
+        if num_sources >= graph.num_nodes() {
+            return self.ensure_stats(graph);
+        }
+        self.stamp("stats", graph);
+        if self.stats.is_none() {
+            let (view, excluded) = without_hubs(graph, self.hub_exclusion);
+            let sources: Vec<usize> = sample_nodes(graph, num_sources, COMMUNITY_SEED).into_iter().filter(|&node| view.contains_node(node)).collect();
+            let progress = reporter(self.progress, "distances");
+            let sweeps = match self.hub_exclusion {
+                None => bfs_sweeps(graph, &sources, &self.parallelism, &progress),
+                Some(_) => bfs_sweeps(&view, &sources, &self.parallelism, &progress),
+            };
+            let histogram = histogram_from_sweeps(&sweeps);
+            let scale = graph.num_nodes() as f64 / sources.len().max(1) as f64;
+            let scaled: Vec<usize> = histogram.iter().map(|&count| (count as f64 * scale).round() as usize).collect();
+            self.hubs_excluded = excluded;
+            let mut stats = self.stats_from_histogram(graph, &histogram, Some(sources.len()));
+            stats.distance_distribution = distribution_from_histogram(&scaled);
+            self.stats = Some(stats);
+        }
+        self.stats.as_ref().unwrap()
+    }
+
+    fn stats_from_histogram(&self, graph: &Graph, histogram: &[usize], distance_sources: Option<usize>) -> GraphStats {
+        GraphStats {
+            num_nodes: graph.num_nodes(),
+            num_edges: graph.num_edges(),
+            average_distance: histogram_mean(histogram),
+            median_distance: histogram_median(histogram),
+            distance_distribution: distribution_from_histogram(histogram),
+            excluded_nodes: self.excluded_nodes,
+            distance_sources,
+        }
+    }
+
+    pub fn ensure_betweenness_sampled(&mut self, graph: &Graph, num_sources: usize) -> &[(usize, f64)] { //Betweenness estimated from num_sources random sources (COMMUNITY_SEED), for graphs too large for exact Brandes
+        self.ensure_betweenness_with(graph, |g| betweenness_centrality_sampled(g, num_sources, COMMUNITY_SEED))
+    }
+
+    pub fn ensure_roles(&mut self, graph: &Graph, options: &RoleOptions) -> &HashMap<usize, Role> { //Classifies roles reusing the stored degrees and betweenness (computing them first if needed)
+        self.stamp("roles", graph);
+        if self.roles.is_none() {
+            self.ensure_degrees(graph);
+            self.ensure_betweenness(graph);
+            let options = RoleOptions {
+                degrees: self.degrees.as_deref(),
+                betweenness: self.betweenness.as_deref(),
+                ..options.clone()
+            };
+            self.roles = Some(classify_roles(graph, &options));
+        }
+        self.roles.as_ref().unwrap()
+    }
+
+    pub fn ensure_communities(&mut self, graph: &Graph) -> &Partition {
+        self.stamp("communities", graph);
+        if self.communities.is_none() {
+            self.communities = Some(match self.cache.as_mut() {
+                Some(cache) => cache.get_or_compute(graph, "communities", &format!("seed={}", COMMUNITY_SEED), || label_propagation(graph, COMMUNITY_SEED)),
+                None => label_propagation(graph, COMMUNITY_SEED),
+            });
+        }
+        self.communities.as_ref().unwrap()
+    }
+
+    pub fn ensure_distance_profiles(&mut self, graph: &Graph, max_depth: usize) -> &HashMap<usize, DistanceProfile> { //Distance profiles up to max_depth (the depth of the first call is kept)
+        self.stamp("distance_profiles", graph);
+        let parallelism = self.parallelism;
+        self.distance_profiles.get_or_insert_with(|| distance_profiles_with(graph, max_depth, &parallelism))
+    }
+
+    pub fn ensure_all(&mut self, graph: &Graph, top_n: usize) { //Runs the whole pipeline: every analysis above with default settings, top_n similar pairs
+        self.ensure_stats(graph);
+        self.ensure_degrees(graph);
+        self.ensure_closeness(graph);
+        self.ensure_betweenness(graph);
+        self.ensure_similar_pairs(graph, top_n);
+        self.ensure_roles(graph, &RoleOptions::default());
+        self.ensure_two_hop(graph);
+        self.ensure_communities(graph);
+        self.ensure_diversity(graph);
+        self.ensure_components(graph);
+    }
+
+    pub fn ensure_planned(&mut self, graph: &Graph, plan: &ExecutionPlan, top_n: usize) { //Runs the planned analyses with their chosen variants (skipped ones stay None)
+        self.sampled_closeness = plan.variant(PlannedAnalysis::Closeness) == Some(Variant::Approximate);
+        for step in &plan.steps {
+            match (step.analysis, step.variant) {
+                (_, Variant::Skip) => {}
+                (PlannedAnalysis::Distances, Variant::Exact) => {
+                    self.ensure_stats(graph);
+                }
+                (PlannedAnalysis::Distances, Variant::Approximate) => {
+                    self.ensure_stats_sampled(graph, SAMPLED_DISTANCE_SOURCES);
+                }
+                (PlannedAnalysis::Betweenness, Variant::Exact) => {
+                    self.ensure_betweenness(graph);
+                }
+                (PlannedAnalysis::Betweenness, Variant::Approximate) => {
+                    self.ensure_betweenness_sampled(graph, SAMPLED_BETWEENNESS_SOURCES);
+                }
+                (PlannedAnalysis::Closeness, Variant::Exact) => {
+                    self.ensure_closeness(graph);
+                }
+                (PlannedAnalysis::Closeness, Variant::Approximate) => {
+                    let parallelism = self.parallelism;
+                    self.ensure_closeness_with(graph, |g| closeness_centrality_approx_with(g, SAMPLED_CLOSENESS_PIVOTS, COMMUNITY_SEED, &parallelism).scores);
+                }
+                (PlannedAnalysis::Diameter, Variant::Exact) => {
+                    self.ensure_eccentricities(graph);
+                }
+                (PlannedAnalysis::Diameter, Variant::Approximate) => {
+                    self.ensure_diameter_bound(graph);
+                }
+                (PlannedAnalysis::SimilarPairs, Variant::Exact) => {
+                    self.ensure_similar_pairs(graph, top_n);
+                }
+                (PlannedAnalysis::SimilarPairs, Variant::Approximate) => {
+                    self.ensure_similar_pairs_approx(graph, top_n);
+                }
+            }
+        }
+    }
+
+    pub fn ensure_diameter_bound(&mut self, graph: &Graph) -> usize { //Double-sweep lower bound on the diameter, for graphs too large for every eccentricity
+        self.stamp("diameter_bound", graph);
+        *self.diameter_bound.get_or_insert_with(|| diameter_double_sweep(graph))
+    }
+
+    pub fn ensure_diversity(&mut self, graph: &Graph) -> &HashMap<usize, usize> { //Neighborhood diversity over the stored communities (detecting them first if needed)
+        self.stamp("diversity", graph);
+        if self.diversity.is_none() {
+            let partition = self.ensure_communities(graph);
+            self.diversity = Some(neighborhood_diversity(graph, partition));
+        }
+        self.diversity.as_ref().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExpectedStats { //Published statistics to check a loaded graph against; statistics left as None are not checked
+    pub num_nodes: Option<usize>,
+    pub num_edges: Option<usize>,
+    pub average_clustering: Option<f64>,
+    pub diameter: Option<usize>,
+    pub tolerances: HashMap<String, f64>, //Allowed absolute difference per statistic name (missing = exact match)
+}
+
+impl ExpectedStats {
+    pub fn from_toml_file(path: &str) -> Result<Self, GraphError> {
+        Self::from_toml_str(&std::fs::read_to_string(path)?)
+    }
+
+    // Reads top-level keys nodes, edges, average_clustering and diameter, plus an optional [tolerance] table with the same keys, e.g.
+    //   nodes = 4039
+    //   average_clustering = 0.6055
+    //   [tolerance]
+    //   average_clustering = 0.0005
+    pub fn from_toml_str(text: &str) -> Result<Self, GraphError> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| GraphError::InvalidParameter(format!("expected stats file: {}", e.message())))?;
+        let number = |key: &str| -> Result<Option<f64>, GraphError> {
+            match table.get(key) {
+                None => Ok(None),
+                Some(toml::Value::Integer(i)) => Ok(Some(*i as f64)),
+                Some(toml::Value::Float(f)) => Ok(Some(*f)),
+                Some(other) => Err(GraphError::InvalidParameter(format!("{} should be a number, found {}", key, other))),
+            }
+        };
+        let mut tolerances = HashMap::new();
+        if let Some(toml::Value::Table(tolerance)) = table.get("tolerance") {
+            for (key, value) in tolerance {
+                let value = value.as_float().or(value.as_integer().map(|i| i as f64));
+                let value = value.ok_or_else(|| GraphError::InvalidParameter(format!("tolerance for {} should be a number", key)))?;
+                tolerances.insert(key.clone(), value);
+            }
+        }
+        Ok(Self {
+            num_nodes: number("nodes")?.map(|n| n as usize),
+            num_edges: number("edges")?.map(|n| n as usize),
+            average_clustering: number("average_clustering")?,
+            diameter: number("diameter")?.map(|n| n as usize),
+            tolerances,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatCheck { //Outcome of checking one statistic
+    pub name: &'static str,
+    pub expected: f64,
+    pub actual: f64,
+    pub tolerance: f64,
+    pub passed: bool,
+}
+
+pub fn verify_against(graph: &Graph, expected: &ExpectedStats) -> Vec<StatCheck> { //Computes only the statistics that have an expected value and compares each within its tolerance
+    let mut checks = Vec::new();
+    let mut check = |name: &'static str, expected_value: Option<f64>, compute: &dyn Fn() -> f64| {
+        if let Some(expected_value) = expected_value {
+            let tolerance = expected.tolerances.get(name).copied().unwrap_or(0.0);
+            let actual = compute();
+            let passed = (actual - expected_value).abs() <= tolerance;
+            checks.push(StatCheck { name, expected: expected_value, actual, tolerance, passed });
+        }
+    };
+    check("nodes", expected.num_nodes.map(|n| n as f64), &|| graph.num_nodes() as f64);
+    check("edges", expected.num_edges.map(|n| n as f64), &|| graph.num_edges() as f64);
+    check("average_clustering", expected.average_clustering, &|| average_clustering(graph));
+    check("diameter", expected.diameter.map(|d| d as f64), &|| diameter(graph) as f64);
+    checks
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BatchConfig { //Settings for run_batch
+    pub extension: Option<String>, //Only files with this extension are analyzed (e.g. "edges"); None analyzes every file
+    pub parallelism: ParallelismConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnalysisSummary { //Per-graph numbers reported by batch mode next to GraphStats
+    pub density: f64,
+    pub average_clustering: f64,
+    pub modularity: f64, //Modularity of the communities found by label propagation
+    pub error: Option<String>, //Set when the file could not be analyzed; the numbers are then meaningless
+}
+
+pub fn run_batch(dir: &str, config: &BatchConfig) -> Result<Vec<(PathBuf, GraphStats, AnalysisSummary)>, GraphError> { //Loads and analyzes every matching file in dir (sorted by name). A file that fails is reported in its row instead of stopping the batch
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| config.extension.as_deref().is_none_or(|ext| path.extension().is_some_and(|e| e == ext)))
+        .collect();
+    paths.sort();
+
+    let mut rows = Vec::new();
+    for path in paths {
+        let row = match Graph::load_from_file(&path.to_string_lossy()) {
+            Ok(graph) => {
+                let mut results = AnalysisResults::for_graph(&graph);
+                results.parallelism = config.parallelism;
+                let stats = results.ensure_stats(&graph).clone();
+                let summary = AnalysisSummary {
+                    density: density(&graph),
+                    average_clustering: average_clustering(&graph),
+                    modularity: modularity(&graph, results.ensure_communities(&graph)),
+                    error: None,
+                };
+                (path, stats, summary)
+            }
+            Err(e) => {
+                let stats = GraphStats { num_nodes: 0, num_edges: 0, average_distance: f64::NAN, median_distance: f64::NAN, distance_distribution: BTreeMap::new(), excluded_nodes: 0, distance_sources: None };
+                (path, stats, AnalysisSummary { error: Some(e.to_string()), ..AnalysisSummary::default() })
+            }
+        };
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrowthOptions { //Settings for growth_report
+    pub sample_above_edges: Option<usize>, //Snapshots with more edges than this get sampled clustering instead of exact (None = always exact)
+    pub sampled_wedges: usize,
+    pub seed: u64,
+}
+
+impl Default for GrowthOptions {
+    fn default() -> Self {
+        Self { sample_above_edges: Some(10_000_000), sampled_wedges: 100_000, seed: COMMUNITY_SEED }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthRow { //Summary of one snapshot
+    pub path: PathBuf,
+    pub num_nodes: usize,
+    pub num_edges: usize,
+    pub average_degree: f64,
+    pub clustering: f64,         //Global clustering (transitivity)
+    pub clustering_sampled: bool, //True when clustering is a wedge-sampling estimate
+    pub giant_fraction: f64,     //Share of nodes in the largest connected component
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthDelta { //Change from one snapshot to the next
+    pub nodes: i64,
+    pub edges: i64,
+    pub average_degree: f64,
+    pub clustering: f64,
+    pub giant_fraction: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GrowthReport {
+    pub rows: Vec<GrowthRow>, //In the order the snapshots were given
+}
+
+impl GrowthReport {
+    pub fn deltas(&self) -> Vec<GrowthDelta> { //One entry per consecutive pair of snapshots (empty for fewer than two)
+        self.rows
+            .windows(2)
+            .map(|w| GrowthDelta {
+                nodes: w[1].num_nodes as i64 - w[0].num_nodes as i64,
+                edges: w[1].num_edges as i64 - w[0].num_edges as i64,
+                average_degree: w[1].average_degree - w[0].average_degree,
+                clustering: w[1].clustering - w[0].clustering,
+                giant_fraction: w[1].giant_fraction - w[0].giant_fraction,
+            })
+            .collect()
+    }
+}
+
+pub fn growth_report(paths: &[PathBuf], options: &GrowthOptions) -> Result<GrowthReport, GraphError> { //Loads each snapshot in order and summarizes it. Unlike batch mode a bad snapshot stops the report, since the deltas would be meaningless
+    let mut rows = Vec::new();
+    for path in paths {
+        let graph = Graph::load_from_file(&path.to_string_lossy())?;
+        let sampled = options.sample_above_edges.is_some_and(|limit| graph.num_edges() > limit);
+        let clustering = if sampled { transitivity_sampled(&graph, options.sampled_wedges, options.seed).estimate } else { transitivity(&graph)? };
+        rows.push(GrowthRow {
+            path: path.clone(),
+            num_nodes: graph.num_nodes(),
+            num_edges: graph.num_edges(),
+            average_degree: if graph.num_nodes() == 0 { 0.0 } else { 2.0 * graph.num_edges() as f64 / graph.num_nodes() as f64 },
+            clustering,
+            clustering_sampled: sampled,
+            giant_fraction: giant_component_fraction(&graph),
+        });
+    }
+    Ok(GrowthReport { rows })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StressAnalysis { //Analyses the stress test can time
+    AverageDistance,
+    Closeness,
+    Betweenness,
+    Communities,
+    Clustering,
+    SimilarPairs,
+}
+
+impl StressAnalysis {
+    pub fn from_name(name: &str) -> Result<Self, GraphError> { //Parses the names used on the command line
+        match name {
+            "distance" => Ok(StressAnalysis::AverageDistance),
+            "closeness" => Ok(StressAnalysis::Closeness),
+            "betweenness" => Ok(StressAnalysis::Betweenness),
+            "communities" => Ok(StressAnalysis::Communities),
+            "clustering" => Ok(StressAnalysis::Clustering),
+            "similarity" => Ok(StressAnalysis::SimilarPairs),
+            other => Err(GraphError::InvalidParameter(format!(
+                "unknown analysis {:?}, expected distance, closeness, betweenness, communities, clustering or similarity",
+                other
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressConfig { //Settings for stress
+    pub start_nodes: usize, //Size of the first graph; each next graph has twice as many nodes
+    pub max_nodes: usize,   //Never go beyond this size, even if everything stays within budget
+    pub m: usize,           //Barabási–Albert edges per new node
+    pub budget: Duration,   //Stop after the first size whose analyses take longer than this
+    pub analyses: Vec<StressAnalysis>,
+    pub seed: u64,
+    pub parallelism: ParallelismConfig,
+}
+
+impl Default for StressConfig {
+    fn default() -> Self {
+        Self {
+            start_nodes: 1000,
+            max_nodes: 1 << 20,
+            m: 5,
+            budget: Duration::from_secs(10),
+            analyses: vec![StressAnalysis::Clustering, StressAnalysis::Communities, StressAnalysis::AverageDistance],
+            seed: COMMUNITY_SEED,
+            parallelism: ParallelismConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StressRow { //Result for one graph size
+    pub nodes: usize,
+    pub edges: usize,
+    pub elapsed: Duration,     //Wall time of the selected analyses (generation not included)
+    pub memory_bytes: usize,   //Rough estimate of the graph plus per-node working memory, not a measurement
+    pub valid: bool,           //The generated graph had the expected edge count and symmetric adjacency
+    pub over_budget: bool,
+}
+
+pub fn stress(config: &StressConfig) -> Result<Vec<StressRow>, GraphError> { //Generates BA graphs of doubling size and times the selected analyses on each until one size exceeds the budget
+    let start = Instant::now();
+    stress_with_clock(config, &mut || start.elapsed())
+}
+
+pub fn stress_with_clock(config: &StressConfig, now: &mut dyn FnMut() -> Duration) -> Result<Vec<StressRow>, GraphError> { //Same as stress with the clock passed in, so tests can fake the timings
+    if config.start_nodes <= config.m {
+        return Err(GraphError::InvalidParameter(format!("start_nodes must be larger than m ({})", config.m)));
+    }
+    let mut rows = Vec::new();
+    let mut nodes = config.start_nodes;
+    while nodes <= config.max_nodes {
+        let graph = barabasi_albert(nodes, config.m, config.seed)?;
+        let valid = graph.num_edges() == config.m * (nodes - config.m)
+            && graph.adjacency().iter().all(|(u, neighbors)| neighbors.iter().all(|v| graph.adjacency()[v].contains(u)));
+
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.parallelism = config.parallelism;
+        let before = now();
+        for analysis in &config.analyses {
+            match analysis {
+                StressAnalysis::AverageDistance => {
+                    results.ensure_stats(&graph);
+                }
+                StressAnalysis::Closeness => {
+                    results.ensure_closeness(&graph);
+                }
+                StressAnalysis::Betweenness => {
+                    results.ensure_betweenness(&graph);
+                }
+                StressAnalysis::Communities => {
+                    results.ensure_communities(&graph);
+                }
+                StressAnalysis::Clustering => {
+                    average_clustering(&graph);
+                }
+                StressAnalysis::SimilarPairs => {
+                    results.ensure_similar_pairs(&graph, 10);
+                }
+            }
+        }
+        let elapsed = now().saturating_sub(before);
+
+        let over_budget = elapsed > config.budget;
+        rows.push(StressRow { nodes, edges: graph.num_edges(), elapsed, memory_bytes: estimated_memory_bytes(&graph), valid, over_budget });
+        if over_budget {
+            break;
+        }
+        nodes *= 2;
+    }
+    Ok(rows)
+}
+
+fn estimated_memory_bytes(graph: &Graph) -> usize { //Adjacency sets (each entry stored twice, hash tables kept at most ~7/8 full) plus one BFS's distance map and queue
+    let word = std::mem::size_of::<usize>();
+    let per_node = word + std::mem::size_of::<std::collections::HashSet<usize>>();
+    let adjacency = (graph.num_nodes() * per_node + 2 * graph.num_edges() * word) * 8 / 7;
+    let bfs = graph.num_nodes() * 3 * word;
+    adjacency + bfs
+}
+
+pub const EXACT_CLOSENESS_MAX_NODES: usize = 50_000; //plan: above this, closeness is estimated from pivots
+pub const EXACT_DIAMETER_MAX_NODES: usize = 10_000; //plan: above this, the diameter is a double-sweep lower bound
+pub const SIMILAR_PAIRS_MAX_NODES: usize = 20_000; //plan: above this, the all-pairs similarity scan is skipped
+pub const EXACT_DISTANCES_MAX_NODES: usize = 50_000; //plan: above this (or EXACT_DISTANCES_MAX_EDGES), average distance comes from sampled sources
+pub const EXACT_DISTANCES_MAX_EDGES: usize = 2_000_000;
+pub const EXACT_BETWEENNESS_MAX_NODES: usize = 20_000; //plan: above this (or EXACT_BETWEENNESS_MAX_EDGES), betweenness comes from sampled sources
+pub const EXACT_BETWEENNESS_MAX_EDGES: usize = 500_000;
+pub const SAMPLED_CLOSENESS_PIVOTS: usize = 1000; //BFS pivots used by sampled closeness
+pub const SAMPLED_DISTANCE_SOURCES: usize = 1000; //BFS sources used by sampled average distance
+pub const SAMPLED_BETWEENNESS_SOURCES: usize = 500; //Brandes sources used by sampled betweenness
+pub const SIMILAR_PAIRS_HASHES: usize = 128; //MinHash signature length for approximate similar pairs
+pub const SIMILAR_PAIRS_BANDS: usize = 32; //LSH bands the signature is split into (4 rows each)
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAnalysis { //Analyses whose cost grows fast enough that plan picks a variant for them
+    Distances, //Average and median distance (the all-pairs sweep)
+    Closeness,
+    Diameter,
+    Betweenness, //Also feeds the roles
+    SimilarPairs,
+}
+
+impl PlannedAnalysis {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlannedAnalysis::Distances => "distances",
+            PlannedAnalysis::Closeness => "closeness",
+            PlannedAnalysis::Diameter => "diameter",
+            PlannedAnalysis::Betweenness => "betweenness",
+            PlannedAnalysis::SimilarPairs => "similarity",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Exact,
+    Approximate, //Sampled distances, closeness and betweenness, double-sweep diameter, LSH candidate similar pairs
+    Skip,
+}
+
+impl Variant {
+    pub fn from_name(name: &str) -> Result<Self, GraphError> { //Parses the names used on the command line
+        match name {
+            "exact" => Ok(Variant::Exact),
+            "approx" | "sampled" | "double-sweep" => Ok(Variant::Approximate),
+            "skip" => Ok(Variant::Skip),
+            other => Err(GraphError::InvalidParameter(format!("unknown variant {:?}, expected exact, approx or skip", other))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphSize { //What plan needs to know about a graph, available right after loading
+    pub num_nodes: usize,
+    pub num_edges: usize,
+}
+
+impl From<&Graph> for GraphSize {
+    fn from(graph: &Graph) -> Self {
+        Self { num_nodes: graph.num_nodes(), num_edges: graph.num_edges() }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlanStep {
+    pub analysis: PlannedAnalysis,
+    pub variant: Variant,
+    pub reason: String, //Why this variant was chosen, printed with the plan
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ExecutionPlan {
+    pub steps: Vec<PlanStep>, //In the order requested
+    pub priority: Vec<Section>, //Sections scheduled first, in this order, whatever their cost (the rest follow cheapest first)
+}
+
+impl ExecutionPlan {
+    pub fn variant(&self, analysis: PlannedAnalysis) -> Option<Variant> { //Chosen variant, None if the analysis was not requested
+        self.steps.iter().find(|step| step.analysis == analysis).map(|step| step.variant)
+    }
+
+    pub fn estimated_cost(&self, section: Section, size: GraphSize) -> f64 { //Rough number of steps the section's analyses take with the planned variants; only the ordering matters
+        let (n, m) = (size.num_nodes as f64, size.num_edges as f64);
+        let sweep = n + m; //One BFS
+        let distances = match self.variant(PlannedAnalysis::Distances) {
+                Some(Variant::Approximate) => SAMPLED_DISTANCE_SOURCES as f64 * sweep,
+                _ => n * sweep,
+            }
+            + sweep
+            + match self.variant(PlannedAnalysis::Diameter) {
+                Some(Variant::Exact) => n * sweep,
+                Some(Variant::Approximate) => 2.0 * sweep,
+                _ => 0.0,
+            };
+        match section {
+            Section::Degrees => n,
+            Section::Distances => distances,
+            Section::Closeness => match self.variant(PlannedAnalysis::Closeness) {
+                Some(Variant::Approximate) => SAMPLED_CLOSENESS_PIVOTS as f64 * sweep,
+                _ => distances, //Exact closeness comes out of the distance sweeps, so it is costed like them and, as a tie, scheduled right after
+            },
+            Section::Betweenness | Section::Roles => match self.variant(PlannedAnalysis::Betweenness) { //Roles reuse betweenness and tie with it
+                Some(Variant::Approximate) => 2.0 * SAMPLED_BETWEENNESS_SOURCES as f64 * sweep,
+                _ => 2.0 * n * sweep,
+            },
+            Section::SimilarPairs => 4.0 * m * m / n.max(1.0), //Sum of squared degrees, for evenly spread degrees
+            Section::Communities => 10.0 * sweep, //A few label-propagation rounds
+            Section::CrossCommunityPairs => 10.0 * sweep + 4.0 * m * m / n.max(1.0), //Communities, then the similar-pairs scan
+        }
+    }
+
+    pub fn schedule(&self, size: GraphSize, sections: &[Section]) -> Vec<Section> { //Order to run the given sections in: the priority ones first, then cheapest first (ties keep the report order)
+        let mut order: Vec<Section> = self.priority.iter().copied().filter(|s| sections.contains(s)).collect();
+        let mut rest: Vec<Section> = Section::REPORT_ORDER.into_iter().filter(|s| sections.contains(s) && !order.contains(s)).collect();
+        rest.sort_by(|a, b| self.estimated_cost(*a, size).partial_cmp(&self.estimated_cost(*b, size)).unwrap());
+        order.extend(rest);
+        order
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section { //One section of the text report and the analyses that fill it
+    Degrees,
+    Distances, //Average and median distance, plus the diameter and component count when computed
+    Closeness,
+    Betweenness,
+    SimilarPairs,
+    Communities,
+    CrossCommunityPairs, //Most similar pairs across communities (needs the communities)
+    Roles,
+}
+
+impl Section {
+    pub const REPORT_ORDER: [Section; 8] = [Section::Degrees, Section::Distances, Section::Closeness, Section::Betweenness, Section::SimilarPairs, Section::Communities, Section::CrossCommunityPairs, Section::Roles];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Section::Degrees => "degrees",
+            Section::Distances => "distances",
+            Section::Closeness => "closeness",
+            Section::Betweenness => "betweenness",
+            Section::SimilarPairs => "similarity",
+            Section::Communities => "communities",
+            Section::CrossCommunityPairs => "cross-community",
+            Section::Roles => "roles",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, GraphError> { //Parses the names used on the command line
+        Section::REPORT_ORDER
+            .into_iter()
+            .find(|s| s.name() == name)
+            .ok_or_else(|| GraphError::InvalidParameter(format!("unknown section {:?}, expected one of degrees, distances, closeness, betweenness, similarity, communities, cross-community, roles", name)))
+    }
+}
+
+pub type TaskFn<'a> = Box<dyn FnOnce(&mut AnalysisResults, &Graph) + 'a>;
+
+pub struct Task<'a> { //One unit of work for run_scheduled: fills the results behind a report section
+    pub section: Section,
+    pub run: TaskFn<'a>,
+}
+
+pub fn planned_tasks(plan: &ExecutionPlan, top_n: usize) -> Vec<Task<'static>> { //The default run as tasks: degrees, planned distances (with components and the planned diameter), closeness, similar pairs and betweenness, and roles.
+    //Distances and betweenness the plan was not asked about run exactly
+    let mut tasks: Vec<Task<'static>> = vec![Task { section: Section::Degrees, run: Box::new(|results, graph| { results.ensure_degrees(graph); }) }];
+    let betweenness = plan.variant(PlannedAnalysis::Betweenness).unwrap_or(Variant::Exact);
+    let ensure_betweenness = move |results: &mut AnalysisResults, graph: &Graph| match betweenness {
+        Variant::Approximate => { results.ensure_betweenness_sampled(graph, SAMPLED_BETWEENNESS_SOURCES); }
+        _ => { results.ensure_betweenness(graph); }
+    };
+    if betweenness != Variant::Skip {
+        tasks.push(Task { section: Section::Betweenness, run: Box::new(ensure_betweenness) });
+        tasks.push(Task {
+            section: Section::Roles,
+            run: Box::new(move |results, graph| {
+                ensure_betweenness(results, graph); //So roles never fall back to exact betweenness when they run first
+                results.ensure_roles(graph, &RoleOptions::default());
+            }),
+        });
+    }
+    let distances = plan.variant(PlannedAnalysis::Distances).unwrap_or(Variant::Exact);
+    let diameter = plan.variant(PlannedAnalysis::Diameter);
+    tasks.push(Task {
+        section: Section::Distances,
+        run: Box::new(move |results, graph| {
+            match distances {
+                Variant::Exact => { results.ensure_stats(graph); }
+                Variant::Approximate => { results.ensure_stats_sampled(graph, SAMPLED_DISTANCE_SOURCES); }
+                Variant::Skip => {}
+            }
+            results.ensure_components(graph); //Printed with the average distance, which skips pairs in different components
+            match diameter {
+                Some(Variant::Exact) => { results.ensure_eccentricities(graph); }
+                Some(Variant::Approximate) => { results.ensure_diameter_bound(graph); }
+                _ => {}
+            }
+        }),
+    });
+    match plan.variant(PlannedAnalysis::Closeness) {
+        Some(Variant::Exact) => tasks.push(Task { section: Section::Closeness, run: Box::new(|results, graph| { results.ensure_closeness(graph); }) }),
+        Some(Variant::Approximate) => tasks.push(Task {
+            section: Section::Closeness,
+            run: Box::new(|results, graph| {
+                let parallelism = results.parallelism;
+                results.ensure_closeness_with(graph, |g| closeness_centrality_approx_with(g, SAMPLED_CLOSENESS_PIVOTS, COMMUNITY_SEED, &parallelism).scores);
+            }),
+        }),
+        _ => {}
+    }
+    match plan.variant(PlannedAnalysis::SimilarPairs) {
+        Some(Variant::Exact) => tasks.push(Task { section: Section::SimilarPairs, run: Box::new(move |results, graph| { results.ensure_similar_pairs(graph, top_n); }) }),
+        Some(Variant::Approximate) => tasks.push(Task { section: Section::SimilarPairs, run: Box::new(move |results, graph| { results.ensure_similar_pairs_approx(graph, top_n); }) }),
+        _ => {}
+    }
+    tasks
+}
+
+impl AnalysisResults {
+    pub fn run_scheduled(&mut self, graph: &Graph, plan: &ExecutionPlan, tasks: Vec<Task>, mut on_done: impl FnMut(&AnalysisResults, Section) -> Result<(), GraphError>) -> Result<(), GraphError> { //Runs the tasks in plan.schedule order, calling on_done after each so its section can be printed while slower ones are still to come
+        self.sampled_closeness = plan.variant(PlannedAnalysis::Closeness) == Some(Variant::Approximate);
+        let sections: Vec<Section> = tasks.iter().map(|t| t.section).collect();
+        let mut tasks: Vec<Option<Task>> = tasks.into_iter().map(Some).collect();
+        for section in plan.schedule(GraphSize::from(graph), &sections) {
+            for task in tasks.iter_mut().filter(|t| t.as_ref().is_some_and(|t| t.section == section)) { //Several tasks may fill one section
+                (task.take().unwrap().run)(self, graph);
+            }
+            on_done(self, section)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ExecutionPlan { //One line per step, e.g. "closeness: exact (4039 nodes <= 50000)"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for step in &self.steps {
+            let variant = match (step.analysis, step.variant) {
+                (_, Variant::Exact) => "exact",
+                (_, Variant::Skip) => "skipped",
+                (PlannedAnalysis::Distances | PlannedAnalysis::Closeness | PlannedAnalysis::Betweenness, Variant::Approximate) => "sampled",
+                (PlannedAnalysis::Diameter, Variant::Approximate) => "double-sweep lower bound",
+                (PlannedAnalysis::SimilarPairs, Variant::Approximate) => "MinHash LSH candidates",
+            };
+            writeln!(f, "{}: {} ({})", step.analysis.name(), variant, step.reason)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn plan(size: GraphSize, requested: &[(PlannedAnalysis, Option<Variant>)]) -> ExecutionPlan { //Picks exact or cheaper variants from the graph size; a Some(variant) next to an analysis is an explicit override and always wins
+    let steps = requested
+        .iter()
+        .map(|&(analysis, forced)| {
+            let (variant, reason) = match forced {
+                Some(variant) => (variant, String::from("chosen explicitly")),
+                None => {
+                    let (max_nodes, max_edges, cheaper) = match analysis {
+                        PlannedAnalysis::Distances => (EXACT_DISTANCES_MAX_NODES, Some(EXACT_DISTANCES_MAX_EDGES), Variant::Approximate),
+                        PlannedAnalysis::Closeness => (EXACT_CLOSENESS_MAX_NODES, None, Variant::Approximate),
+                        PlannedAnalysis::Diameter => (EXACT_DIAMETER_MAX_NODES, None, Variant::Approximate),
+                        PlannedAnalysis::Betweenness => (EXACT_BETWEENNESS_MAX_NODES, Some(EXACT_BETWEENNESS_MAX_EDGES), Variant::Approximate),
+                        PlannedAnalysis::SimilarPairs => (SIMILAR_PAIRS_MAX_NODES, None, Variant::Skip),
+                    };
+                    if size.num_nodes > max_nodes {
+                        (cheaper, format!("{} nodes > {}", size.num_nodes, max_nodes))
+                    } else if let Some(max_edges) = max_edges.filter(|&max| size.num_edges > max) {
+                        (cheaper, format!("{} edges > {}", size.num_edges, max_edges))
+                    } else {
+                        (Variant::Exact, format!("{} nodes <= {}", size.num_nodes, max_nodes))
+                    }
+                }
+            };
+            PlanStep { analysis, variant, reason }
+        })
+        .collect();
+    ExecutionPlan { steps, priority: Vec::new() }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentAnalysis { //Analyses whose answer for a node only depends on that node's connected component, so they can run on each component separately
+    Closeness,
+    Diameter, //Through the eccentricities
+    Clustering,
+    Communities,
+}
+
+pub fn per_component(graph: &Graph, analyses: &[ComponentAnalysis]) -> Vec<(usize, AnalysisResults)> { //Runs the analyses on every connected component on its own, one component per thread. Component ids are component_labels' (0 = largest)
+    per_component_with(graph, analyses, &ParallelismConfig::default())
+}
+
+pub fn per_component_with(graph: &Graph, analyses: &[ComponentAnalysis], parallelism: &ParallelismConfig) -> Vec<(usize, AnalysisResults)> { //Same as per_component, with control over the threads. A connected graph is analyzed in place with these threads instead
+    let components = connected_components(graph);
+    let run = |component: usize, subgraph: &Graph, parallelism: ParallelismConfig| {
+        let mut results = AnalysisResults { parallelism, ..AnalysisResults::for_graph(subgraph) };
+        results.stamp("components", subgraph);
+        results.components = Some(components[component].iter().map(|&node| (node, component)).collect());
+        for analysis in analyses {
+            match analysis {
+                ComponentAnalysis::Closeness => {
+                    results.ensure_closeness(subgraph);
+                }
+                ComponentAnalysis::Diameter => {
+                    results.ensure_eccentricities(subgraph);
+                }
+                ComponentAnalysis::Clustering => {
+                    results.ensure_clustering(subgraph);
+                }
+                ComponentAnalysis::Communities => {
+                    results.ensure_communities(subgraph);
+                }
+            }
+        }
+        results
+    };
+    if components.len() == 1 {
+        return vec![(0, run(0, graph, *parallelism))];
+    }
+    let ids: Vec<usize> = (0..components.len()).collect();
+    parallelism.map_nodes(&ids, |component| { //Threads go to components, so each component's own analyses run sequentially
+        let subgraph = graph.induced_subgraph(&components[component].iter().copied().collect());
+        (component, run(component, &subgraph, ParallelismConfig::sequential()))
+    })
+}
+
+pub fn merge_components(graph: &Graph, parts: &[(usize, AnalysisResults)]) -> AnalysisResults { //Global results from per_component's: closeness, eccentricities, clustering and components are unions (closeness re-sorted), community ids are renumbered so no two components share one. A result is only merged if every component has it
+    let mut merged = AnalysisResults::for_graph(graph);
+    let every = |field: fn(&AnalysisResults) -> bool| !parts.is_empty() && parts.iter().all(|(_, part)| field(part));
+    if every(|part| part.components.is_some()) {
+        merged.stamp("components", graph);
+        merged.components = Some(parts.iter().flat_map(|(_, part)| part.components.clone().unwrap()).collect());
+    }
+    if every(|part| part.closeness.is_some()) {
+        merged.stamp("closeness", graph);
+        let mut closeness: Vec<(usize, f64)> = parts.iter().flat_map(|(_, part)| part.closeness.clone().unwrap()).collect();
+        closeness.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        merged.closeness = Some(closeness);
+    }
+    if every(|part| part.eccentricities.is_some()) {
+        merged.stamp("eccentricities", graph);
+        merged.eccentricities = Some(parts.iter().flat_map(|(_, part)| part.eccentricities.clone().unwrap()).collect());
+    }
+    if every(|part| part.clustering.is_some()) {
+        merged.stamp("clustering", graph);
+        merged.clustering = Some(parts.iter().flat_map(|(_, part)| part.clustering.clone().unwrap()).collect());
+    }
+    if every(|part| part.communities.is_some()) {
+        merged.stamp("communities", graph);
+        merged.communities = Some(match parts {
+            [(_, only)] => only.communities.clone().unwrap(), //Nothing to renumber, so a connected graph keeps label_propagation's ids
+            _ => {
+                let mut communities = Partition::new();
+                let mut next = 0;
+                for (_, part) in parts {
+                    let partition = part.communities.as_ref().unwrap();
+                    let mut ids: Vec<usize> = partition.values().copied().collect();
+                    ids.sort();
+                    ids.dedup();
+                    let renumbered: HashMap<usize, usize> = ids.iter().enumerate().map(|(i, &id)| (id, next + i)).collect();
+                    communities.extend(partition.iter().map(|(&node, id)| (node, renumbered[id])));
+                    next += ids.len();
+                }
+                communities
+            }
+        });
+    }
+    merged
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labels::InternalId;
+    use crate::analysis::{average_distance, closeness_centrality, closeness_centrality_approx};
+    use std::cell::Cell;
+
+    fn small_graph() -> Graph { //Triangle graph
+        Graph::from_edges([(0, 1), (1, 2), (2, 0)])
+    }
+
+    #[test]
+    fn test_progress_reports_each_analysis() { //1000 nodes: every analysis reports once past 500 and at the end, under its own name
+        static CALLS: std::sync::Mutex<Vec<(String, usize, usize)>> = std::sync::Mutex::new(Vec::new());
+        let graph = barabasi_albert(1_000, 2, 1).unwrap();
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.parallelism = ParallelismConfig::sequential(); //Batches of 64 sources then finish in order, so the distance sweeps cross 500 at 512
+        results.progress = Some(|analysis, done, total| CALLS.lock().unwrap().push((analysis.to_string(), done, total)));
+        results.ensure_closeness(&graph);
+        results.ensure_stats(&graph);
+        results.ensure_similar_pairs(&graph, 5);
+        let mut calls = CALLS.lock().unwrap().clone();
+        calls.sort(); //Sweeps run in parallel, so calls may arrive out of order
+        let expected: Vec<(String, usize, usize)> = [("closeness", 512), ("distances", 512), ("similar pairs", 500)].iter().flat_map(|&(name, first)| [(name.to_string(), first, 1_000), (name.to_string(), 1_000, 1_000)]).collect();
+        assert_eq!(calls, expected);
+    }
+
+    #[test]
+    fn test_closeness_computed_once() { //The injected algorithm should only run on the first request
+        let graph = small_graph();
+        let calls = Cell::new(0);
+        let counting = |g: &Graph| {
+            calls.set(calls.get() + 1);
+            closeness_centrality(g)
+        };
+        let mut results = AnalysisResults::new();
+        results.ensure_closeness_with(&graph, counting);
+        results.ensure_closeness_with(&graph, counting);
+        results.ensure_closeness(&graph);
+        assert_eq!(calls.get(), 1);
+        assert_eq!(results.closeness.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_stale_results_are_detected_and_refreshed() { //Closeness computed, then an edge added: it is reported stale until refresh recomputes it
+        let mut graph = small_graph();
+        graph.add_edge(InternalId(2), InternalId(3));
+        let mut results = AnalysisResults::new();
+        let before = results.ensure_closeness(&graph).to_vec();
+        results.ensure_degrees(&graph);
+        assert!(results.stale(&graph).is_empty());
+
+        graph.add_edge(InternalId(3), InternalId(0));
+        assert_eq!(results.stale(&graph), vec!["closeness", "degrees"]);
+        assert_eq!(results.check_fresh(&graph, StalePolicy::Warn).unwrap(), vec!["closeness", "degrees"]);
+        assert!(matches!(results.check_fresh(&graph, StalePolicy::Refuse), Err(GraphError::InvalidParameter(_))));
+        assert_eq!(results.ensure_closeness(&graph), &before[..]); //Still the old values until refreshed
+
+        results.refresh(&graph);
+        assert_ne!(results.ensure_closeness(&graph), &before[..]);
+        assert!(results.check_fresh(&graph, StalePolicy::Refuse).unwrap().is_empty());
+        assert!(graph.remove_edge(InternalId(0), InternalId(2)));
+        assert_eq!(results.stale(&graph), vec!["closeness"]); //Degrees were dropped by the refresh and not recomputed
+    }
+
+    #[test]
+    fn test_roles_reuse_stored_betweenness() { //Role classification should not recompute betweenness that is already stored
+        let graph = small_graph();
+        let calls = Cell::new(0);
+        let mut results = AnalysisResults::new();
+        results.ensure_betweenness_with(&graph, |g| {
+            calls.set(calls.get() + 1);
+            betweenness_centrality(g)
+        });
+        results.ensure_roles(&graph, &RoleOptions::default());
+        results.ensure_betweenness_with(&graph, |g| {
+            calls.set(calls.get() + 1);
+            betweenness_centrality(g)
+        });
+        assert_eq!(calls.get(), 1);
+        assert_eq!(results.roles.as_ref().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_cross_community_pairs_kept_apart() { //Cross-community pairs go in their own field, so they neither hide nor replace the overall similar pairs
+        let graph = Graph::from_edges([(0, 1), (0, 2), (1, 2), (3, 4), (3, 5), (4, 5), (2, 6), (3, 6)]);
+        let mut results = AnalysisResults::new();
+        results.communities = Some([(0, 0), (1, 0), (2, 0), (6, 0), (3, 1), (4, 1), (5, 1)].into_iter().collect());
+        let cross = results.ensure_cross_community_pairs(&graph, 3).to_vec();
+        assert!(results.similar_pairs.is_none());
+        let partition = results.communities.clone().unwrap();
+        assert!(!cross.is_empty() && cross.iter().all(|&((u, v), _)| partition[&u] != partition[&v]));
+        let all = results.ensure_similar_pairs(&graph, 3).to_vec();
+        assert_eq!(all, most_similar_pairs_with(&graph, &SimilarPairsOptions::top(3)));
+        assert_eq!(results.ensure_similar_pairs(&graph, 1), &all[..1]); //A smaller top_n reads the stored rows, a larger one ranks again
+        let more = most_similar_pairs_with(&graph, &SimilarPairsOptions::top(10));
+        assert!(more.len() > 3);
+        assert_eq!(results.ensure_similar_pairs(&graph, 10), &more[..]);
+        assert_eq!(results.cross_community_pairs.as_deref(), Some(&cross[..]));
+        assert_eq!(results.stale(&graph), Vec::<&str>::new());
+        let mut text = Vec::new();
+        crate::report::write_text(&results, &graph, StalePolicy::Warn, &mut text, &crate::report::NumberFormat::default()).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("Top Jaccard Similarities") && text.contains("Most Similar Pairs Across Communities"));
+    }
+
+    #[test]
+    fn test_verify_against_matching_and_wrong_expectations() {
+        let graph = small_graph();
+        let matching = ExpectedStats::from_toml_str("nodes = 3\nedges = 3\naverage_clustering = 1.0\ndiameter = 1\n").unwrap();
+        let checks = verify_against(&graph, &matching);
+        assert_eq!(checks.len(), 4);
+        assert!(checks.iter().all(|c| c.passed));
+
+        let wrong = ExpectedStats::from_toml_str("nodes = 3\nedges = 4\naverage_clustering = 0.9\n[tolerance]\naverage_clustering = 0.05\n").unwrap();
+        let checks = verify_against(&graph, &wrong);
+        let failed: Vec<&str> = checks.iter().filter(|c| !c.passed).map(|c| c.name).collect();
+        assert_eq!(failed, vec!["edges", "average_clustering"]);
+        assert!(matches!(ExpectedStats::from_toml_str("nodes = \"many\""), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_run_batch_isolates_bad_files() { //Two good edge lists and one malformed file: the bad one gets an error row, the others are analyzed
+        let dir = std::env::temp_dir().join(format!("fga_batch_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.edges"), "0 1\n1 2\n2 0\n").unwrap();
+        fs::write(dir.join("b.edges"), "0 1\n1 2\n2 3\n").unwrap();
+        fs::write(dir.join("c.edges"), "0 1\nx y\n").unwrap();
+        fs::write(dir.join("notes.txt"), "not an edge list").unwrap();
+
+        let config = BatchConfig { extension: Some(String::from("edges")), ..BatchConfig::default() };
+        let rows = run_batch(dir.to_str().unwrap(), &config).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<String> = rows.iter().map(|(p, _, _)| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(names, vec!["a.edges", "b.edges", "c.edges"]);
+        let (_, triangle, triangle_summary) = &rows[0];
+        assert_eq!((triangle.num_nodes, triangle.num_edges), (3, 3));
+        assert!((triangle_summary.density - 1.0).abs() < 0.0001);
+        assert!((triangle_summary.average_clustering - 1.0).abs() < 0.0001);
+        let (_, path, path_summary) = &rows[1];
+        assert!((path.average_distance - 5.0 / 3.0).abs() < 0.0001);
+        assert!(path_summary.error.is_none());
+        assert!(rows[2].2.error.as_ref().unwrap().contains("line 2"));
+    }
+
+    #[test]
+    fn test_growth_report_rows_and_deltas() { //Path 0-1-2, then a triangle plus a stray edge, then the two pieces joined
+        let dir = std::env::temp_dir().join(format!("fga_growth_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let snapshots = ["0 1\n1 2\n", "0 1\n1 2\n2 0\n3 4\n", "0 1\n1 2\n2 0\n3 4\n2 3\n"];
+        let paths: Vec<PathBuf> = snapshots
+            .iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let path = dir.join(format!("t{}.txt", i));
+                fs::write(&path, text).unwrap();
+                path
+            })
+            .collect();
+        let report = growth_report(&paths, &GrowthOptions::default()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let summary: Vec<(usize, usize, f64, f64, f64)> = report.rows.iter().map(|r| (r.num_nodes, r.num_edges, r.average_degree, r.clustering, r.giant_fraction)).collect();
+        assert_eq!(summary[0], (3, 2, 4.0 / 3.0, 0.0, 1.0));
+        assert_eq!(summary[1], (5, 4, 1.6, 1.0, 0.6));
+        assert_eq!((summary[2].0, summary[2].1, summary[2].4), (5, 5, 1.0));
+        assert!((summary[2].3 - 0.5).abs() < 1e-9); //The triangle closes 3 of the 6 wedges (one at each of 0, 1, 3 and three at node 2)
+        let deltas = report.deltas();
+        assert_eq!(deltas.len(), 2);
+        assert_eq!((deltas[0].nodes, deltas[0].edges), (2, 2));
+        assert!((deltas[1].giant_fraction - 0.4).abs() < 1e-9);
+        assert!(report.rows.iter().all(|r| !r.clustering_sampled));
+    }
+
+    #[test]
+    fn test_stress_stops_after_budget() { //Fake clock: the analyses take 1s, 2s, 4s, ... so with a 3s budget the third size is the last
+        let config = StressConfig {
+            start_nodes: 10,
+            max_nodes: 1000,
+            m: 2,
+            budget: Duration::from_secs(3),
+            analyses: vec![StressAnalysis::Clustering, StressAnalysis::Communities],
+            ..StressConfig::default()
+        };
+        let mut ticks = vec![0, 1, 1, 3, 3, 7].into_iter().map(Duration::from_secs);
+        let rows = stress_with_clock(&config, &mut || ticks.next().unwrap()).unwrap();
+        let summary: Vec<(usize, u64, bool)> = rows.iter().map(|r| (r.nodes, r.elapsed.as_secs(), r.over_budget)).collect();
+        assert_eq!(summary, vec![(10, 1, false), (20, 2, false), (40, 4, true)]);
+        assert!(rows.iter().all(|r| r.valid && r.edges == 2 * (r.nodes - 2)));
+        assert!(rows[2].memory_bytes > rows[1].memory_bytes);
+
+        let small = StressConfig { max_nodes: 20, ..config };
+        assert_eq!(stress(&small).unwrap().len(), 2); //Real clock, tiny graphs: stops at max_nodes
+        assert!(matches!(StressAnalysis::from_name("pagerank"), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_plan_by_size_and_overrides() { //Exact everything on small graphs, cheaper variants past each threshold, and explicit choices always win
+        let all = [(PlannedAnalysis::Closeness, None), (PlannedAnalysis::Diameter, None), (PlannedAnalysis::SimilarPairs, None)];
+        let variants = |nodes: usize, requested: &[(PlannedAnalysis, Option<Variant>)]| {
+            let chosen = plan(GraphSize { num_nodes: nodes, num_edges: 10 * nodes }, requested);
+            [PlannedAnalysis::Closeness, PlannedAnalysis::Diameter, PlannedAnalysis::SimilarPairs].map(|a| chosen.variant(a))
+        };
+        let (exact, approx, skip) = (Some(Variant::Exact), Some(Variant::Approximate), Some(Variant::Skip));
+        assert_eq!(variants(4_039, &all), [exact, exact, exact]);
+        assert_eq!(variants(15_000, &all), [exact, approx, exact]);
+        assert_eq!(variants(30_000, &all), [exact, approx, skip]);
+        assert_eq!(variants(5_000_000, &all), [approx, approx, skip]);
+        assert_eq!(variants(4_039, &all[..1]), [exact, None, None]);
+
+        let gated = [(PlannedAnalysis::Distances, None), (PlannedAnalysis::Betweenness, None)]; //Distances and betweenness also switch to sampling on edge count alone
+        let sized = |nodes: usize, edges: usize| {
+            let chosen = plan(GraphSize { num_nodes: nodes, num_edges: edges }, &gated);
+            [PlannedAnalysis::Distances, PlannedAnalysis::Betweenness].map(|a| chosen.variant(a))
+        };
+        assert_eq!(sized(4_039, 88_234), [exact, exact]);
+        assert_eq!(sized(30_000, 300_000), [exact, approx]);
+        assert_eq!(sized(10_000, 1_000_000), [exact, approx]);
+        assert_eq!(sized(10_000, 5_000_000), [approx, approx]);
+        assert_eq!(sized(100_000, 100_000), [approx, approx]);
+        assert_eq!(plan(GraphSize { num_nodes: 10_000, num_edges: 5_000_000 }, &gated).to_string().lines().next(), Some("distances: sampled (5000000 edges > 2000000)"));
+
+        let forced = [(PlannedAnalysis::Closeness, exact), (PlannedAnalysis::Diameter, exact), (PlannedAnalysis::SimilarPairs, exact)];
+        assert_eq!(variants(5_000_000, &forced), [exact, exact, exact]);
+        assert_eq!(variants(5_000_000, &[(PlannedAnalysis::SimilarPairs, approx)])[2], approx);
+        let forced = [(PlannedAnalysis::Closeness, approx), (PlannedAnalysis::Diameter, skip), (PlannedAnalysis::SimilarPairs, skip)];
+        assert_eq!(variants(100, &forced), [approx, skip, skip]);
+        assert_eq!(plan(GraphSize { num_nodes: 100, num_edges: 500 }, &forced).to_string().lines().next(), Some("closeness: sampled (chosen explicitly)"));
+
+        let graph = barabasi_albert(300, 2, 1).unwrap(); //Running a plan fills exactly the chosen results
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.ensure_planned(&graph, &plan(GraphSize::from(&graph), &forced), 5);
+        assert_eq!(results.closeness.as_ref().map(Vec::len), Some(300));
+        assert!(results.eccentricities.is_none() && results.diameter_bound.is_none() && results.similar_pairs.is_none());
+        let graph = barabasi_albert(1_500, 2, 1).unwrap(); //More nodes than sampled sources, so sampling really happens
+        let sampled = [(PlannedAnalysis::Distances, approx), (PlannedAnalysis::Betweenness, approx)];
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.ensure_planned(&graph, &plan(GraphSize::from(&graph), &sampled), 5);
+        let stats = results.stats.as_ref().unwrap();
+        assert_eq!(stats.distance_sources, Some(SAMPLED_DISTANCE_SOURCES));
+        assert!((stats.average_distance - average_distance(&graph)).abs() < 0.1);
+        assert_eq!(results.betweenness.as_ref().map(Vec::len), Some(1_500));
+        let mut results = AnalysisResults::for_graph(&graph); //Exact distances run first, but the sampled closeness the plan chose is what gets stored
+        results.ensure_planned(&graph, &plan(GraphSize::from(&graph), &[(PlannedAnalysis::Distances, exact), (PlannedAnalysis::Closeness, approx)]), 5);
+        assert_eq!(results.closeness, Some(closeness_centrality_approx(&graph, SAMPLED_CLOSENESS_PIVOTS, COMMUNITY_SEED).scores));
+        assert_ne!(results.closeness, Some(closeness_centrality(&graph)));
+        let mut results = AnalysisResults::for_graph(&graph); //Approximate similar pairs come from the LSH candidates, scored exactly
+        results.ensure_planned(&graph, &plan(GraphSize::from(&graph), &[(PlannedAnalysis::SimilarPairs, approx)]), 5);
+        assert_eq!(results.similar_pairs.as_deref(), Some(&most_similar_pairs_approx(&graph, 5, SIMILAR_PAIRS_HASHES, SIMILAR_PAIRS_BANDS, COMMUNITY_SEED).unwrap()[..]));
+        assert!(matches!(Variant::from_name("fast"), Err(GraphError::InvalidParameter(_))));
+    }
+
+    #[test]
+    fn test_per_component_and_merge() { //Three components analyzed apart must merge into what the whole graph gives; a connected graph gives the non-decomposed results
+        let graph = Graph::from_edges(&[(0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (10, 11), (11, 12), (12, 13), (13, 10), (20, 21), (21, 22)]);
+        let analyses = [ComponentAnalysis::Closeness, ComponentAnalysis::Diameter, ComponentAnalysis::Clustering, ComponentAnalysis::Communities];
+        let parts = per_component(&graph, &analyses);
+        assert_eq!(parts.iter().map(|(id, part)| (*id, part.components.as_ref().unwrap().len())).collect::<Vec<_>>(), vec![(0, 5), (1, 4), (2, 3)]);
+        assert!(parts[1].1.closeness.as_ref().unwrap().iter().all(|&(node, score)| (10..14).contains(&node) && score == 0.75)); //Square: 1 + 1 + 2 hops to the others
+        assert_eq!(parts[2].1.eccentricities.as_ref().unwrap()[&21], 1);
+        assert_eq!(parts[0].1.clustering.as_ref().unwrap()[&2], 1.0 / 3.0);
+
+        let merged = merge_components(&graph, &parts);
+        let mut whole = AnalysisResults::for_graph(&graph);
+        assert_eq!(merged.closeness.as_ref(), Some(&closeness_centrality(&graph)));
+        assert_eq!(merged.eccentricities.as_ref(), Some(whole.ensure_eccentricities(&graph)));
+        assert_eq!(merged.eccentricities.as_ref().unwrap().values().max(), Some(&diameter(&graph)));
+        assert_eq!(merged.clustering.as_ref(), Some(whole.ensure_clustering(&graph)));
+        assert!((merged.clustering.as_ref().unwrap().values().sum::<f64>() / 12.0 - average_clustering(&graph)).abs() < 1e-12);
+        assert_eq!(merged.components.as_ref(), Some(whole.ensure_components(&graph)));
+        let communities = merged.communities.as_ref().unwrap();
+        let components = merged.components.as_ref().unwrap();
+        assert!(graph.adjacency().keys().all(|a| graph.adjacency().keys().all(|b| communities[a] != communities[b] || components[a] == components[b])), "a community spans two components");
+        assert_eq!(merged.stale(&graph), Vec::<&str>::new());
+
+        let connected = barabasi_albert(80, 2, 5).unwrap();
+        let parts = per_component_with(&connected, &analyses, &ParallelismConfig::sequential());
+        assert_eq!(parts.len(), 1);
+        let merged = merge_components(&connected, &parts);
+        let mut whole = AnalysisResults::for_graph(&connected);
+        assert_eq!(merged.closeness.as_ref(), Some(&whole.ensure_closeness(&connected).to_vec()));
+        assert_eq!(merged.communities.as_ref(), Some(whole.ensure_communities(&connected)));
+        assert_eq!(merged.eccentricities.as_ref(), Some(whole.ensure_eccentricities(&connected)));
+    }
+}
@@ -0,0 +1,1363 @@
+//Module: report.rs
+//Here we turn an AnalysisResults container into text, Markdown, JSON and CSV output
+//All numbers go through NumberFormat, so every writer uses the same precision for the same kind of metric
+//When a Provenance is given, files say what produced them: "# key: value" lines above a CSV header, a "meta" object in JSON, a <path>.meta.json sidecar otherwise
+//Writers given an AnalysisResults first check it against the graph: with StalePolicy::Refuse stale results are an error, with Warn the output says which ones are stale
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use crate::analysis::{degree_histogram, density, ChurnStats, Recommendation, Role};
+use crate::community::{community_sizes, Partition};
+use crate::error::GraphError;
+use crate::graph::{DotStyle, Graph};
+use crate::labels::NodeId;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use crate::pipeline::{AnalysisResults, AnalysisSummary, GraphStats, GrowthReport, Section, StalePolicy};
+use crate::labels::InternalId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricFamily { //Groups of metrics that share a number format
+    Centrality, //closeness and other scores between 0 and 1
+    Betweenness, //unbounded path counts
+    Similarity,
+    Distance,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberFormat { //Decimal places per metric family, plus the magnitude above which numbers switch to scientific notation
+    pub centrality_decimals: usize,
+    pub betweenness_decimals: usize,
+    pub similarity_decimals: usize,
+    pub distance_decimals: usize,
+    pub scientific_threshold: f64, //Values with |x| >= this are written like 3.917e6 (f64::INFINITY = never)
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        Self {
+            centrality_decimals: 4,
+            betweenness_decimals: 1,
+            similarity_decimals: 3,
+            distance_decimals: 2,
+            scientific_threshold: f64::INFINITY,
+        }
+    }
+}
+
+impl NumberFormat {
+    pub fn with_precision(decimals: usize) -> Self { //Same number of decimals for every family (what --precision sets)
+        Self {
+            centrality_decimals: decimals,
+            betweenness_decimals: decimals,
+            similarity_decimals: decimals,
+            distance_decimals: decimals,
+            ..Self::default()
+        }
+    }
+
+    pub fn decimals(&self, family: MetricFamily) -> usize {
+        match family {
+            MetricFamily::Centrality => self.centrality_decimals,
+            MetricFamily::Betweenness => self.betweenness_decimals,
+            MetricFamily::Similarity => self.similarity_decimals,
+            MetricFamily::Distance => self.distance_decimals,
+        }
+    }
+
+    // Formats a value for the given family. Rust's formatting never depends on the system locale,
+    // so the decimal separator is always "." and there are no thousands separators
+    pub fn format(&self, family: MetricFamily, value: f64) -> String {
+        let decimals = self.decimals(family);
+        if value.is_finite() && value.abs() >= self.scientific_threshold {
+            format!("{:.*e}", decimals, value)
+        } else {
+            format!("{:.*}", decimals, value)
+        }
+    }
+
+    fn json(&self, family: MetricFamily, value: f64) -> String { //JSON has no NaN or infinity, so those become null
+        if value.is_finite() { self.format(family, value) } else { String::from("null") }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance { //Where a result file came from, so it can still be traced back weeks later
+    pub crate_version: String,
+    pub fingerprint: Option<u64>, //Graph::fingerprint of the analyzed graph (None for runs over several graphs, e.g. batch or growth)
+    pub input: String,            //Input file(s) as given
+    pub options: String,          //Command-line options of the run
+    pub seed: Option<u64>,        //Random seed the results depend on
+    pub timestamp: u64,           //Seconds since the Unix epoch when the file was written
+    pub wall_time: f64,           //Seconds from the start of the run until the file was written
+    started: Option<Instant>,     //None for provenance read back from a file
+}
+
+impl Provenance {
+    pub fn new(input: &str, options: &str, seed: Option<u64>, started: Instant) -> Self {
+        Self {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            fingerprint: None,
+            input: input.to_string(),
+            options: options.to_string(),
+            seed,
+            timestamp: 0,
+            wall_time: 0.0,
+            started: Some(started),
+        }
+        .stamped()
+    }
+
+    pub fn for_graph(self, graph: &Graph) -> Self { //Same provenance, recording which graph was analyzed
+        Self { fingerprint: Some(graph.fingerprint()), ..self }
+    }
+
+    fn stamped(&self) -> Self { //Copy with the timestamp and wall time of right now (unchanged for provenance read from a file)
+        let mut stamped = self.clone();
+        if let Some(started) = self.started {
+            stamped.timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            stamped.wall_time = started.elapsed().as_secs_f64();
+        }
+        stamped
+    }
+
+    fn entries(&self) -> [(&'static str, String); 7] { //Key and raw value of every field, in file order ("" for a missing fingerprint or seed)
+        [
+            ("crate_version", self.crate_version.clone()),
+            ("fingerprint", self.fingerprint.map_or(String::new(), |f| format!("{:016x}", f))),
+            ("input", self.input.clone()),
+            ("options", self.options.clone()),
+            ("seed", self.seed.map_or(String::new(), |s| s.to_string())),
+            ("timestamp", self.timestamp.to_string()),
+            ("wall_time", format!("{:.3}", self.wall_time)),
+        ]
+    }
+
+    fn to_json(&self) -> String { //Flat JSON object; the fingerprint is a hex string since JSON numbers cannot hold every u64
+        let fields: Vec<String> = self
+            .stamped()
+            .entries()
+            .into_iter()
+            .map(|(key, value)| match key {
+                "seed" | "timestamp" | "wall_time" => format!("\"{}\": {}", key, if value.is_empty() { "null" } else { &value }),
+                "fingerprint" if value.is_empty() => format!("\"{}\": null", key),
+                _ => format!("\"{}\": {}", key, json_string(&value)),
+            })
+            .collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+
+    fn csv_comment(&self) -> String { //"# key: value" lines, read back by Provenance::read and skipped by CSV readers set to ignore '#' comments
+        self.stamped().entries().iter().map(|(key, value)| format!("# {}: {}\n", key, value.replace('\n', " "))).collect()
+    }
+
+    pub fn write_sidecar(&self, path: &str) -> Result<(), GraphError> { //Writes <path>.meta.json, for outputs that cannot hold comments (Markdown, DOT)
+        std::fs::write(format!("{}.meta.json", path), format!("{}\n", self.to_json()))?;
+        Ok(())
+    }
+
+    pub fn read(path: &str) -> Result<Self, GraphError> { //Provenance of a result file: its <path>.meta.json sidecar, else the comment lines of a CSV or the "meta" object of a JSON report
+        let sidecar = format!("{}.meta.json", path);
+        let text = std::fs::read_to_string(if Path::new(&sidecar).exists() { &sidecar } else { path })?;
+        let entries = if text.starts_with('#') {
+            text.lines()
+                .map_while(|line| line.strip_prefix("# "))
+                .filter_map(|line| line.split_once(": ").map(|(k, v)| (k.to_string(), v.to_string())))
+                .collect()
+        } else {
+            let json = parse_json(&text);
+            let object = json.as_ref().map(|json| json.get("meta").unwrap_or(json)); //A JSON report, or a sidecar holding just the object
+            object.and_then(JsonValue::flat_fields).ok_or_else(|| GraphError::InvalidParameter(format!("{} has no readable provenance", path)))?
+        };
+        Self::from_entries(&entries).ok_or_else(|| GraphError::InvalidParameter(format!("{} has no readable provenance", path)))
+    }
+
+    fn from_entries(entries: &HashMap<String, String>) -> Option<Self> {
+        let optional = |key: &str| entries.get(key).filter(|v| !v.is_empty() && *v != "null");
+        Some(Self {
+            crate_version: entries.get("crate_version")?.clone(),
+            fingerprint: optional("fingerprint").map(|f| u64::from_str_radix(f, 16)).transpose().ok()?,
+            input: entries.get("input")?.clone(),
+            options: entries.get("options")?.clone(),
+            seed: optional("seed").map(|s| s.parse()).transpose().ok()?,
+            timestamp: entries.get("timestamp")?.parse().ok()?,
+            wall_time: entries.get("wall_time")?.parse().ok()?,
+            started: None,
+        })
+    }
+}
+
+fn json_string(value: &str) -> String { //Quoted JSON string with quotes, backslashes and control characters escaped
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum JsonValue { //Just enough JSON for reading back the files written here (numbers kept as written)
+    Null,
+    Number(String),
+    Text(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn number<T: std::str::FromStr>(&self) -> Option<T> {
+        match self {
+            JsonValue::Number(raw) => raw.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn float(&self) -> Option<f64> { //null reads back as NaN: the writers put null where a float was not finite
+        if *self == JsonValue::Null { Some(f64::NAN) } else { self.number() }
+    }
+
+    fn flat_fields(&self) -> Option<HashMap<String, String>> { //An object's string, number and null fields as text (strings unescaped, the rest as written); nested values are left out
+        let JsonValue::Object(fields) = self else { return None };
+        let text = |value: &JsonValue| match value {
+            JsonValue::Null => Some(String::from("null")),
+            JsonValue::Number(raw) | JsonValue::Text(raw) => Some(raw.clone()),
+            JsonValue::Array(_) | JsonValue::Object(_) => None,
+        };
+        Some(fields.iter().filter_map(|(key, value)| Some((key.clone(), text(value)?))).collect())
+    }
+}
+
+fn parse_json(text: &str) -> Option<JsonValue> { //Parses one JSON value (the whole text, surrounding whitespace aside)
+    let mut chars = text.chars().peekable();
+    let value = parse_json_value(&mut chars)?;
+    chars.all(char::is_whitespace).then_some(value)
+}
+
+fn parse_json_value(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<JsonValue> {
+    while chars.peek()?.is_whitespace() {
+        chars.next();
+    }
+    match chars.next()? {
+        '"' => {
+            let mut value = String::new();
+            loop {
+                match chars.next()? {
+                    '"' => return Some(JsonValue::Text(value)),
+                    '\\' => match chars.next()? {
+                        'n' => value.push('\n'),
+                        'u' => value.push(char::from_u32(u32::from_str_radix(&chars.by_ref().take(4).collect::<String>(), 16).ok()?)?),
+                        c => value.push(c),
+                    },
+                    c => value.push(c),
+                }
+            }
+        }
+        open @ ('[' | '{') => {
+            let close = if open == '[' { ']' } else { '}' };
+            let (mut items, mut fields) = (Vec::new(), Vec::new());
+            loop {
+                while chars.peek()?.is_whitespace() {
+                    chars.next();
+                }
+                if chars.peek() == Some(&close) {
+                    chars.next();
+                    return Some(if open == '[' { JsonValue::Array(items) } else { JsonValue::Object(fields) });
+                }
+                if open == '[' {
+                    items.push(parse_json_value(chars)?);
+                } else {
+                    let JsonValue::Text(key) = parse_json_value(chars)? else { return None };
+                    chars.find(|c| !c.is_whitespace()).filter(|&c| c == ':')?;
+                    fields.push((key, parse_json_value(chars)?));
+                }
+                match chars.find(|c| !c.is_whitespace())? {
+                    ',' => {}
+                    c if c == close => return Some(if open == '[' { JsonValue::Array(items) } else { JsonValue::Object(fields) }),
+                    _ => return None,
+                }
+            }
+        }
+        first => {
+            let mut raw = String::from(first);
+            while chars.peek().is_some_and(|c| !matches!(c, ',' | ']' | '}') && !c.is_whitespace()) {
+                raw.push(chars.next()?);
+            }
+            match raw.as_str() {
+                "null" => Some(JsonValue::Null),
+                _ if raw.parse::<f64>().is_ok() => Some(JsonValue::Number(raw)),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn csv_writer(path: impl AsRef<Path>, provenance: Option<&Provenance>, delimiter: u8) -> Result<csv::Writer<File>, GraphError> { //CSV writer whose file starts with the provenance comment lines, if any
+    results_csv_writer(path, provenance, &[], delimiter)
+}
+
+fn results_csv_writer(path: impl AsRef<Path>, provenance: Option<&Provenance>, stale: &[&str], delimiter: u8) -> Result<csv::Writer<File>, GraphError> { //csv_writer plus a "# stale: ..." comment line when some results are stale
+    let mut file = File::create(path)?;
+    if let Some(provenance) = provenance {
+        file.write_all(provenance.csv_comment().as_bytes())?;
+    }
+    if !stale.is_empty() {
+        writeln!(file, "# stale: {}", stale.join(", "))?;
+    }
+    Ok(csv::WriterBuilder::new().delimiter(delimiter).from_writer(file))
+}
+
+fn write_stale_warnings(stale: &[&str], prefix: &str, out: &mut impl Write) -> Result<(), GraphError> { //One warning line per stale result
+    for name in stale {
+        writeln!(out, "{}Warning: {} was computed before the graph changed", prefix, name)?;
+    }
+    Ok(())
+}
+
+fn degree_counts(degrees: &[(usize, usize)]) -> Vec<(usize, usize)> { //(degree, number of nodes with it) sorted by degree, like analysis::degree_histogram but from stored degrees
+    let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+    for &(_, degree) in degrees {
+        *counts.entry(degree).or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
+fn community_label(partition: &Partition, node: usize) -> String { //Community id as text, or "-" for nodes without one
+    partition.get(&node).map_or(String::from("-"), |c| c.to_string())
+}
+
+fn sorted_entries<V: Copy>(map: &HashMap<usize, V>) -> Vec<(usize, V)> { //Map entries in node id order, so output is the same on every run
+    let mut entries: Vec<(usize, V)> = map.iter().map(|(&k, &v)| (k, v)).collect();
+    entries.sort_by_key(|&(k, _)| k);
+    entries
+}
+
+const ROLES: [Role; 4] = [Role::Hub, Role::Broker, Role::Peripheral, Role::Ordinary];
+
+pub fn write_text(results: &AnalysisResults, graph: &Graph, policy: StalePolicy, out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //Writes a text report of every analysis that has been computed so far (missing ones are skipped)
+    write_stale_warnings(&results.check_fresh(graph, policy)?, "", out)?;
+    if let Some(stats) = &results.stats {
+        writeln!(out, "Loaded {} nodes and {} edges.", stats.num_nodes, stats.num_edges)?;
+        if stats.excluded_nodes > 0 {
+            writeln!(out, "({} nodes excluded by the node lists)", stats.excluded_nodes)?;
+        }
+    }
+    for section in Section::REPORT_ORDER {
+        write_section_lines(results, section, out, format)?;
+    }
+    Ok(())
+}
+
+pub fn write_section(results: &AnalysisResults, graph: &Graph, policy: StalePolicy, section: Section, out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //One section of the text report (nothing if its analyses have not run), e.g. to print it as soon as it is ready
+    write_stale_warnings(&results.check_fresh(graph, policy)?, "", out)?;
+    write_section_lines(results, section, out, format)
+}
+
+fn write_section_lines(results: &AnalysisResults, section: Section, out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //write_section without the freshness check, so write_text warns only once
+    match section {
+        Section::Degrees => if let Some(degrees) = &results.degrees {
+            writeln!(out, "\nDegree Distribution:")?;
+            for (degree, count) in degree_counts(degrees).into_iter().take(10) {
+                writeln!(out, "Degree {:>4}: {} nodes", degree, count)?;
+            }
+            writeln!(out, "_____________")?;
+        }
+        Section::Distances => if let Some(stats) = &results.stats {
+            writeln!(out, "\nAverage Distance (Six Degrees): {}", format.format(MetricFamily::Distance, stats.average_distance))?;
+            writeln!(out, "Median Distance: {}", format.format(MetricFamily::Distance, stats.median_distance))?;
+            if let Some(sources) = stats.distance_sources {
+                writeln!(out, "(estimated from BFS runs out of {} sampled sources)", sources)?;
+            }
+            if let Some(eccentricities) = &results.eccentricities { //The worst case next to the average
+                writeln!(out, "Diameter (longest shortest path): {}", eccentricities.values().max().unwrap_or(&0))?;
+            } else if let Some(bound) = results.diameter_bound {
+                writeln!(out, "Diameter (double-sweep lower bound): at least {}", bound)?;
+            }
+            if let Some(cap) = results.hub_exclusion {
+                writeln!(out, "({} hubs with more than {} friends excluded from distances and closeness)", results.hubs_excluded, cap)?;
+            }
+            if let Some(components) = &results.components { //Pairs in different components are left out of the average, so say how split the graph is
+                let count = components.values().max().map_or(0, |&c| c + 1);
+                let largest = components.values().filter(|&&c| c == 0).count();
+                writeln!(out, "Connected components: {} (largest has {} nodes)", count, largest)?;
+            }
+            writeln!(out, "_____________")?;
+        }
+        Section::Closeness => if let Some(closeness) = &results.closeness {
+            writeln!(out, "\nTop 5 Closeness Centrality Nodes:")?;
+            for &(node, centrality) in closeness.iter().take(5) {
+                writeln!(out, "Node {:>4}: Closeness Centrality {}", results.name(node), format.format(MetricFamily::Centrality, centrality))?;
+            }
+            if results.isolated_excluded > 0 {
+                writeln!(out, "({} isolated nodes excluded)", results.isolated_excluded)?;
+            }
+            writeln!(out, "_____________")?;
+        }
+        Section::Betweenness => if let Some(betweenness) = &results.betweenness {
+            writeln!(out, "\nTop 5 Betweenness Centrality Nodes:")?;
+            for &(node, centrality) in betweenness.iter().take(5) {
+                writeln!(out, "Node {:>4}: Betweenness Centrality {}", results.name(node), format.format(MetricFamily::Betweenness, centrality))?;
+            }
+            writeln!(out, "_____________")?;
+        }
+        Section::SimilarPairs => if let Some(pairs) = &results.similar_pairs {
+            writeln!(out, "\nTop Jaccard Similarities (Most Similar Friend Pairs):")?;
+            write_pair_lines(results, pairs, out, format)?;
+            writeln!(out, "_____________")?;
+        }
+        Section::Communities => if let Some(partition) = &results.communities {
+            let sizes = community_sizes(partition);
+            writeln!(out, "\nCommunities: {} found", sizes.len())?;
+            for (community, size) in sizes.iter().take(5) {
+                writeln!(out, "Community {:>4}: {} members", community, size)?;
+            }
+            writeln!(out, "_____________")?;
+        }
+        Section::CrossCommunityPairs => if let Some(pairs) = &results.cross_community_pairs {
+            writeln!(out, "\nMost Similar Pairs Across Communities:")?;
+            write_pair_lines(results, pairs, out, format)?;
+            writeln!(out, "_____________")?;
+        }
+        Section::Roles => if let Some(roles) = &results.roles {
+            writeln!(out, "\nNode Roles:")?;
+            for role in ROLES {
+                let count = roles.values().filter(|&&r| r == role).count();
+                writeln!(out, "{:>10}: {} nodes", role.as_str(), count)?;
+            }
+            writeln!(out, "_____________")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_pair_lines(results: &AnalysisResults, pairs: &[((usize, usize), f64)], out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //One line per similar pair, with each node's community when communities have been detected
+    for &((u, v), sim) in pairs {
+        let sim = format.format(MetricFamily::Similarity, sim);
+        match &results.communities {
+            Some(p) => writeln!(out, "Nodes {} (community {}) & {} (community {}) → Similarity: {}", results.name(u), community_label(p, u), results.name(v), community_label(p, v), sim)?,
+            None => writeln!(out, "Nodes {} & {} → Similarity: {}", results.name(u), results.name(v), sim)?,
+        }
+    }
+    Ok(())
+}
+
+pub fn write_markdown(results: &AnalysisResults, graph: &Graph, policy: StalePolicy, out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //Same sections as the text report, as Markdown tables
+    let stale = results.check_fresh(graph, policy)?;
+    writeln!(out, "# Graph Analysis Report")?;
+    if !stale.is_empty() {
+        writeln!(out)?;
+        write_stale_warnings(&stale, "> ", out)?;
+    }
+    if let Some(stats) = &results.stats {
+        writeln!(out, "\n| Nodes | Edges | Average distance |\n|---|---|---|")?;
+        writeln!(out, "| {} | {} | {} |", stats.num_nodes, stats.num_edges, format.format(MetricFamily::Distance, stats.average_distance))?;
+    }
+    if let Some(degrees) = &results.degrees {
+        writeln!(out, "\n## Degrees\n\n| Degree | Nodes |\n|---|---|")?;
+        for (degree, count) in degree_counts(degrees).into_iter().take(10) {
+            writeln!(out, "| {} | {} |", degree, count)?;
+        }
+    }
+    if let Some(closeness) = &results.closeness {
+        writeln!(out, "\n## Top Closeness Centrality\n\n| Node | Closeness |\n|---|---|")?;
+        for &(node, value) in closeness.iter().take(5) {
+            writeln!(out, "| {} | {} |", results.name(node), format.format(MetricFamily::Centrality, value))?;
+        }
+    }
+    if let Some(betweenness) = &results.betweenness {
+        writeln!(out, "\n## Top Betweenness Centrality\n\n| Node | Betweenness |\n|---|---|")?;
+        for &(node, value) in betweenness.iter().take(5) {
+            writeln!(out, "| {} | {} |", results.name(node), format.format(MetricFamily::Betweenness, value))?;
+        }
+    }
+    if let Some(pairs) = &results.similar_pairs {
+        writeln!(out, "\n## Most Similar Pairs\n\n| Node A | Node B | Similarity |\n|---|---|---|")?;
+        for &((u, v), sim) in pairs {
+            writeln!(out, "| {} | {} | {} |", results.name(u), results.name(v), format.format(MetricFamily::Similarity, sim))?;
+        }
+    }
+    if let Some(partition) = &results.communities {
+        writeln!(out, "\n## Largest Communities\n\n| Community | Members |\n|---|---|")?;
+        for (community, size) in community_sizes(partition).iter().take(5) {
+            writeln!(out, "| {} | {} |", community, size)?;
+        }
+    }
+    if let Some(roles) = &results.roles {
+        writeln!(out, "\n## Node Roles\n\n| Role | Nodes |\n|---|---|")?;
+        for role in ROLES {
+            writeln!(out, "| {} | {} |", role.as_str(), roles.values().filter(|&&r| r == role).count())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_json(results: &AnalysisResults, graph: &Graph, policy: StalePolicy, out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //Writes every stored result in full as one JSON object (per-node lists are sorted by node id, printed as the input file's ids)
+    let stale = results.check_fresh(graph, policy)?;
+    let mut sections: Vec<String> = Vec::new();
+    if let Some(provenance) = &results.provenance {
+        sections.push(format!("\"meta\": {}", provenance.to_json()));
+    }
+    if !stale.is_empty() { //Only present when some result is out of date
+        let names: Vec<String> = stale.iter().map(|name| json_string(name)).collect();
+        sections.push(format!("\"stale\": [{}]", names.join(", ")));
+    }
+    if let Some(stats) = &results.stats {
+        let excluded = if stats.excluded_nodes > 0 { format!(", \"excluded_nodes\": {}", stats.excluded_nodes) } else { String::new() }; //Only present for scoped runs
+        sections.push(format!(
+            "\"stats\": {{\"num_nodes\": {}, \"num_edges\": {}, \"average_distance\": {}{}}}",
+            stats.num_nodes, stats.num_edges, format.json(MetricFamily::Distance, stats.average_distance), excluded
+        ));
+    }
+    if let Some(degrees) = &results.degrees {
+        let items: Vec<String> = degrees.iter().map(|&(n, d)| format!("{{\"node\": {}, \"degree\": {}}}", results.label(n), d)).collect();
+        sections.push(format!("\"degrees\": [{}]", items.join(", ")));
+    }
+    for (name, scores, family) in [
+        ("closeness", &results.closeness, MetricFamily::Centrality),
+        ("betweenness", &results.betweenness, MetricFamily::Betweenness),
+    ] {
+        if let Some(scores) = scores {
+            let mut scores = scores.clone();
+            scores.sort_by_key(|&(n, _)| n);
+            let items: Vec<String> = scores.iter().map(|&(n, v)| format!("{{\"node\": {}, \"value\": {}}}", results.label(n), format.json(family, v))).collect();
+            sections.push(format!("\"{}\": [{}]", name, items.join(", ")));
+        }
+    }
+    if let Some(pairs) = &results.similar_pairs {
+        let items: Vec<String> = pairs
+            .iter()
+            .map(|&((u, v), s)| format!("{{\"u\": {}, \"v\": {}, \"similarity\": {}}}", results.label(u), results.label(v), format.json(MetricFamily::Similarity, s)))
+            .collect();
+        sections.push(format!("\"similar_pairs\": [{}]", items.join(", ")));
+    }
+    if let Some(partition) = &results.communities {
+        let items: Vec<String> = sorted_entries(partition).iter().map(|(n, c)| format!("{{\"node\": {}, \"community\": {}}}", results.label(*n), c)).collect();
+        sections.push(format!("\"communities\": [{}]", items.join(", ")));
+    }
+    if let Some(diversity) = &results.diversity {
+        let items: Vec<String> = sorted_entries(diversity).iter().map(|(n, d)| format!("{{\"node\": {}, \"diversity\": {}}}", results.label(*n), d)).collect();
+        sections.push(format!("\"diversity\": [{}]", items.join(", ")));
+    }
+    if let Some(two_hop) = &results.two_hop {
+        let items: Vec<String> = sorted_entries(two_hop).iter().map(|(n, r)| format!("{{\"node\": {}, \"two_hop_reach\": {}}}", results.label(*n), r)).collect();
+        sections.push(format!("\"two_hop\": [{}]", items.join(", ")));
+    }
+    if let Some(roles) = &results.roles {
+        let items: Vec<String> = sorted_entries(roles).iter().map(|(n, r)| format!("{{\"node\": {}, \"role\": \"{}\"}}", results.label(*n), r.as_str())).collect();
+        sections.push(format!("\"roles\": [{}]", items.join(", ")));
+    }
+    writeln!(out, "{{{}}}", sections.join(", "))?;
+    Ok(())
+}
+
+pub fn write_node_features_csv(results: &AnalysisResults, graph: &Graph, policy: StalePolicy, path: &str, format: &NumberFormat) -> Result<(), GraphError> { //Writes one row per node (sorted by id) with its degree plus a column for every per-node result computed so far
+    let stale = results.check_fresh(graph, policy)?;
+    let closeness: Option<HashMap<usize, f64>> = results.closeness.as_ref().map(|c| c.iter().copied().collect());
+    let betweenness: Option<HashMap<usize, f64>> = results.betweenness.as_ref().map(|b| b.iter().copied().collect());
+    let mut nodes: Vec<usize> = graph.adjacency().keys().copied().collect();
+    nodes.sort();
+
+    let mut header = vec!["node", "degree"];
+    if closeness.is_some() { header.push("closeness"); }
+    if betweenness.is_some() { header.push("betweenness"); }
+    if results.roles.is_some() { header.push("role"); }
+    if results.communities.is_some() { header.push("community"); }
+    if results.components.is_some() { header.push("component"); }
+    if results.diversity.is_some() { header.push("diversity"); }
+    if results.two_hop.is_some() { header.push("two_hop_reach"); }
+    if results.distance_profiles.is_some() { header.extend(["at_distance_1", "at_distance_2", "median_distance"]); } //The profile columns most useful as features
+
+    let mut writer = results_csv_writer(path, results.provenance.as_ref(), &stale, b',')?;
+    writer.write_record(&header)?;
+    for node in nodes {
+        let mut record = vec![results.label(node).to_string(), graph.adjacency()[&node].len().to_string()];
+        if let Some(c) = &closeness { record.push(format.format(MetricFamily::Centrality, c.get(&node).copied().unwrap_or(0.0))); }
+        if let Some(b) = &betweenness { record.push(format.format(MetricFamily::Betweenness, b.get(&node).copied().unwrap_or(0.0))); }
+        if let Some(r) = &results.roles { record.push(r.get(&node).map_or("", |r| r.as_str()).to_string()); }
+        if let Some(p) = &results.communities { record.push(p.get(&node).map_or(String::new(), |c| c.to_string())); }
+        if let Some(c) = &results.components { record.push(c.get(&node).map_or(String::new(), |c| c.to_string())); }
+        if let Some(d) = &results.diversity { record.push(d.get(&node).copied().unwrap_or(0).to_string()); }
+        if let Some(t) = &results.two_hop { record.push(t.get(&node).copied().unwrap_or(0).to_string()); }
+        if let Some(profiles) = &results.distance_profiles {
+            let profile = profiles.get(&node);
+            let at = |d: usize| profile.and_then(|p| p.counts.get(d - 1)).map_or(String::new(), |c| c.to_string()); //Empty if the profiles stop before depth d
+            record.extend([at(1), at(2), profile.and_then(|p| p.median).map_or(String::new(), |m| m.to_string())]);
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_batch_csv(path: &str, rows: &[(PathBuf, GraphStats, AnalysisSummary)], format: &NumberFormat, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes one row per analyzed file; rows for files that failed only have the file name and the error
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["file", "nodes", "edges", "density", "average_distance", "clustering", "modularity", "error"])?;
+    for (file, stats, summary) in rows {
+        let name = snapshot_name(file);
+        let record = match &summary.error {
+            Some(error) => vec![name, String::new(), String::new(), String::new(), String::new(), String::new(), String::new(), error.clone()],
+            None => vec![
+                name,
+                stats.num_nodes.to_string(),
+                stats.num_edges.to_string(),
+                format.format(MetricFamily::Similarity, summary.density),
+                format.format(MetricFamily::Distance, stats.average_distance),
+                format.format(MetricFamily::Similarity, summary.average_clustering),
+                format.format(MetricFamily::Similarity, summary.modularity),
+                String::new(),
+            ],
+        };
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_recommendations_csv(path: &str, recommendations: &[Recommendation], graph: &Graph, format: &NumberFormat, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes node,rank,candidate,score,mutual_count rows, with node ids as they appear in the graph's input file
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["node", "rank", "candidate", "score", "mutual_count"])?;
+    for r in recommendations {
+        writer.write_record([
+            graph.original_id(InternalId(r.node)).to_string(),
+            r.rank.to_string(),
+            graph.original_id(InternalId(r.candidate)).to_string(),
+            format.format(MetricFamily::Similarity, r.score),
+            r.mutual_count.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_degree_histogram_csv(graph: &Graph, path: &str, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes degree,count rows sorted by degree, ready for a log-log plot
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["degree", "count"])?;
+    for (degree, count) in degree_histogram(graph) {
+        writer.write_record([degree.to_string(), count.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_distance_distribution_csv<T: ToString>(path: &str, distribution: &BTreeMap<usize, T>, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes distance,pairs rows by increasing distance (exact counts or sampled estimates), for the hop plot
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["distance", "pairs"])?;
+    for (distance, pairs) in distribution {
+        writer.write_record([distance.to_string(), pairs.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_walks(path: &str, walks: &[Vec<usize>], graph: &Graph) -> Result<(), GraphError> { //One walk per line, its nodes' original ids separated by spaces: the sentence format word2vec and gensim read directly (so no provenance comment)
+    let mut writer = BufWriter::new(File::create(path)?);
+    for walk in walks {
+        let words: Vec<String> = walk.iter().map(|&node| graph.original_id(InternalId(node)).to_string()).collect();
+        writeln!(writer, "{}", words.join(" "))?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_robustness_csv(path: &str, curves: &[(&str, &[(f64, f64)])], provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes strategy,fraction_removed,giant_component rows, one per step of each robustness_profile curve
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["strategy", "fraction_removed", "giant_component"])?;
+    for (name, curve) in curves {
+        for (fraction, giant) in curve.iter() {
+            writer.write_record([name.to_string(), fraction.to_string(), giant.to_string()])?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_centrality_csv(path: &str, scores: &[(usize, f64)], graph: &Graph, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes node,score rows in the given order, scores at full precision (they read back to the same f64)
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["node", "score"])?;
+    for &(node, score) in scores {
+        writer.write_record([graph.original_id(InternalId(node)).to_string(), score.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_pairs_csv(path: &str, pairs: &[((usize, usize), f64)], graph: &Graph, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes u,v,score rows in the given order, scores at full precision
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["u", "v", "score"])?;
+    for &((u, v), score) in pairs {
+        writer.write_record([graph.original_id(InternalId(u)).to_string(), graph.original_id(InternalId(v)).to_string(), score.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankedEntry { //One row of a ranking: rank counts from 1, node is an internal id
+    pub rank: usize,
+    pub node: usize,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankedMetric<'a> { //A ranking as the analyses return it (best first), read a page at a time so a million-node ranking never has to be printed or copied whole
+    pub name: &'a str,
+    scores: &'a [(usize, f64)],
+}
+
+impl<'a> RankedMetric<'a> {
+    pub fn new(name: &'a str, scores: &'a [(usize, f64)]) -> Self {
+        Self { name, scores }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub fn page(&self, offset: usize, len: usize) -> &'a [(usize, f64)] { //Rows offset..offset + len; shorter at the end, empty past it
+        let start = offset.min(self.scores.len());
+        &self.scores[start..start.saturating_add(len).min(self.scores.len())]
+    }
+
+    pub fn entries(&self, offset: usize, len: usize) -> impl Iterator<Item = RankedEntry> + 'a { //The same page with each row's rank in the whole ranking
+        self.page(offset, len).iter().enumerate().map(move |(i, &(node, score))| RankedEntry { rank: offset + i + 1, node, score })
+    }
+}
+
+pub fn write_ranked_jsonl(path: &str, metric: &str, entries: impl IntoIterator<Item = RankedEntry>, graph: &Graph) -> Result<usize, GraphError> { //One JSON object per line, e.g. {"rank": 1, "node": 107, "metric": "closeness", "score": 0.4597...},
+    //written as the entries come so tools can read the ranking incrementally. Scores at full precision (NaN and infinity as null); returns the number of lines
+    let mut writer = std::io::BufWriter::new(File::create(path)?);
+    let metric = json_string(metric);
+    let mut lines = 0;
+    for entry in entries {
+        let score = if entry.score.is_finite() { entry.score.to_string() } else { String::from("null") };
+        writeln!(writer, "{{\"rank\": {}, \"node\": {}, \"metric\": {}, \"score\": {}}}", entry.rank, graph.original_id(InternalId(entry.node)), metric, score)?;
+        lines += 1;
+    }
+    writer.flush()?;
+    Ok(lines)
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnalysisReport { //The headline numbers of a run, by input-file id, for tools that want them without parsing the text report
+    pub average_distance: f64,
+    pub top_closeness: Vec<(NodeId, f64)>, //Best first
+    pub top_pairs: Vec<((NodeId, NodeId), f64)>, //Most similar first
+    pub timings: Vec<(String, f64)>, //Seconds per stage with --timing (see Timings::seconds); left out of the JSON when empty
+}
+
+impl AnalysisReport {
+    pub fn from_results(results: &AnalysisResults, top_k: usize) -> Self { //The top_k closeness nodes and similar pairs of whatever has been computed (missing results give 0.0 and empty lists)
+        Self {
+            average_distance: results.stats.as_ref().map_or(0.0, |s| s.average_distance),
+            top_closeness: results.closeness.iter().flatten().take(top_k).map(|&(n, c)| (results.label(n), c)).collect(),
+            top_pairs: results.similar_pairs.iter().flatten().take(top_k).map(|&((u, v), s)| ((results.label(u), results.label(v)), s)).collect(),
+            timings: Vec::new(),
+        }
+    }
+
+    pub fn to_json(&self, provenance: Option<&Provenance>) -> String { //One JSON object, floats at full precision (NaN and infinity as null), led by a "meta" object when provenance is given
+        let float = |value: f64| if value.is_finite() { value.to_string() } else { String::from("null") };
+        let mut sections = Vec::new();
+        if let Some(provenance) = provenance {
+            sections.push(format!("\"meta\": {}", provenance.to_json()));
+        }
+        sections.push(format!("\"average_distance\": {}", float(self.average_distance)));
+        let closeness: Vec<String> = self.top_closeness.iter().map(|&(n, c)| format!("{{\"node\": {}, \"closeness\": {}}}", n, float(c))).collect();
+        sections.push(format!("\"top_closeness\": [{}]", closeness.join(", ")));
+        let pairs: Vec<String> = self.top_pairs.iter().map(|&((u, v), s)| format!("{{\"u\": {}, \"v\": {}, \"similarity\": {}}}", u, v, float(s))).collect();
+        sections.push(format!("\"top_pairs\": [{}]", pairs.join(", ")));
+        if !self.timings.is_empty() {
+            let stages: Vec<String> = self.timings.iter().map(|(stage, seconds)| format!("{{\"stage\": {}, \"seconds\": {}}}", json_string(stage), float(*seconds))).collect();
+            sections.push(format!("\"timings\": [{}]", stages.join(", ")));
+        }
+        format!("{{{}}}", sections.join(", "))
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, GraphError> { //Reads back what to_json wrote (the "meta" object is ignored; see Provenance::read)
+        let invalid = || GraphError::InvalidParameter(String::from("not an analysis report (expected average_distance, top_closeness and top_pairs)"));
+        let json = parse_json(text).ok_or_else(invalid)?;
+        let list = |key: &str| match json.get(key) {
+            Some(JsonValue::Array(items)) => Some(items),
+            _ => None,
+        };
+        let id = |item: &JsonValue, key: &str| item.get(key)?.number().map(NodeId);
+        let top_closeness = list("top_closeness").ok_or_else(invalid)?.iter().map(|item| Some((id(item, "node")?, item.get("closeness")?.float()?))).collect::<Option<_>>();
+        let top_pairs = list("top_pairs").ok_or_else(invalid)?.iter().map(|item| Some(((id(item, "u")?, id(item, "v")?), item.get("similarity")?.float()?))).collect::<Option<_>>();
+        let stage = |item: &JsonValue| match item.get("stage")? {
+            JsonValue::Text(name) => Some((name.clone(), item.get("seconds")?.float()?)),
+            _ => None,
+        };
+        let timings = list("timings").map_or(Some(Vec::new()), |items| items.iter().map(stage).collect()); //Optional: only --timing runs write it
+        Ok(Self {
+            average_distance: json.get("average_distance").and_then(JsonValue::float).ok_or_else(invalid)?,
+            top_closeness: top_closeness.ok_or_else(invalid)?,
+            top_pairs: top_pairs.ok_or_else(invalid)?,
+            timings: timings.ok_or_else(invalid)?,
+        })
+    }
+}
+
+pub fn write_report_json(path: &str, report: &AnalysisReport, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes AnalysisReport::to_json to a file
+    std::fs::write(path, format!("{}\n", report.to_json(provenance)))?;
+    Ok(())
+}
+
+pub fn read_report_json(path: &str) -> Result<AnalysisReport, GraphError> {
+    AnalysisReport::from_json(&std::fs::read_to_string(path)?)
+}
+
+pub fn write_churn_csv(path: &str, churn: &HashMap<usize, ChurnStats>, format: &NumberFormat, provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes node,similarity,gained,lost rows sorted by node id; the gained/lost columns are counts
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record(["node", "similarity", "gained", "lost"])?;
+    let mut nodes: Vec<&usize> = churn.keys().collect();
+    nodes.sort();
+    for node in nodes {
+        let stats = &churn[node];
+        writer.write_record([node.to_string(), format.format(MetricFamily::Similarity, stats.similarity), stats.gained.len().to_string(), stats.lost.len().to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Tsv,
+    Csv,
+}
+
+const WIDE_COLUMNS: [(&str, &str); 9] = [ //Column name and type written to the schema sidecar, in file order
+    ("node", "int64"),
+    ("degree", "int64"),
+    ("closeness", "float64"),
+    ("betweenness", "float64"),
+    ("role", "string"),
+    ("community", "int64"),
+    ("component", "int64"),
+    ("diversity", "int64"),
+    ("two_hop_reach", "int64"),
+];
+
+// Writes one row per node with every per-node metric as a column, for dataframe tools (Polars, pandas, R)
+// Unlike write_node_features_csv the columns never change: metrics that were not computed (or have no value for a node) are written as NA.
+// A sidecar file "<path>.schema" lists each column with its type, one "name<TAB>type" line per column.
+// With provenance (or stale results) the file starts with "#" comment lines, e.g. polars.read_csv(path, comment_prefix="#") skips them
+pub fn write_wide_metrics(path: &str, results: &AnalysisResults, graph: &Graph, policy: StalePolicy, table: TableFormat, format: &NumberFormat) -> Result<(), GraphError> {
+    let stale = results.check_fresh(graph, policy)?;
+    let closeness: HashMap<usize, f64> = results.closeness.iter().flatten().copied().collect();
+    let betweenness: HashMap<usize, f64> = results.betweenness.iter().flatten().copied().collect();
+    let degrees: HashMap<usize, usize> = results.degrees.iter().flatten().copied().collect();
+    let mut nodes: Vec<usize> = degrees.keys().chain(closeness.keys()).chain(betweenness.keys()).copied().collect();
+    for map in [&results.communities, &results.components, &results.diversity, &results.two_hop].into_iter().flatten() {
+        nodes.extend(map.keys());
+    }
+    nodes.extend(results.roles.iter().flat_map(|r| r.keys()));
+    nodes.sort();
+    nodes.dedup();
+
+    let na = || String::from("NA");
+    let delimiter = match table {
+        TableFormat::Tsv => b'\t',
+        TableFormat::Csv => b',',
+    };
+    let mut writer = results_csv_writer(path, results.provenance.as_ref(), &stale, delimiter)?;
+    writer.write_record(WIDE_COLUMNS.iter().map(|(name, _)| name))?;
+    for node in nodes {
+        let count = |map: &Option<HashMap<usize, usize>>| map.as_ref().and_then(|m| m.get(&node)).map_or_else(na, |v| v.to_string());
+        writer.write_record([
+            results.label(node).to_string(),
+            degrees.get(&node).map_or_else(na, |d| d.to_string()),
+            closeness.get(&node).map_or_else(na, |&c| format.format(MetricFamily::Centrality, c)),
+            betweenness.get(&node).map_or_else(na, |&b| format.format(MetricFamily::Betweenness, b)),
+            results.roles.as_ref().and_then(|r| r.get(&node)).map_or_else(na, |r| r.as_str().to_string()),
+            count(&results.communities),
+            count(&results.components),
+            count(&results.diversity),
+            count(&results.two_hop),
+        ])?;
+    }
+    writer.flush()?;
+
+    let schema: String = WIDE_COLUMNS.iter().map(|(name, kind)| format!("{}\t{}\n", name, kind)).collect();
+    std::fs::write(format!("{}.schema", path), schema)?;
+    Ok(())
+}
+
+pub fn write_dot(path: &str, graph: &Graph, highlight: &[usize], provenance: Option<&Provenance>) -> Result<(), GraphError> { //Writes the graph in Graphviz DOT format (see Graph::to_dot), labeled with the input file's ids; highlighted nodes are filled in
+    graph.write_dot_with(path, &DotStyle { highlight, ..DotStyle::default() })?;
+    if let Some(provenance) = provenance {
+        provenance.write_sidecar(path)?;
+    }
+    Ok(())
+}
+
+pub fn export_communities(dir: &str, graph: &Graph, partition: &Partition, min_size: usize, format: &NumberFormat, provenance: Option<&Provenance>) -> Result<usize, GraphError> { //Writes each community with at least min_size members as community_<id>.txt (its induced subgraph, same edge-list format as the input) plus manifest.csv. Returns how many were written
+    std::fs::create_dir_all(dir)?;
+    let mut members: HashMap<usize, HashSet<usize>> = HashMap::new();
+    for (&node, &community) in partition {
+        if graph.adjacency().contains_key(&node) { //Partitions read from files can name nodes the graph does not have
+            members.entry(community).or_default().insert(node);
+        }
+    }
+    let mut communities: Vec<(usize, HashSet<usize>)> = members.into_iter().filter(|(_, m)| m.len() >= min_size).collect();
+    communities.sort_by_key(|&(id, _)| id);
+
+    let mut manifest = csv_writer(Path::new(dir).join("manifest.csv"), provenance, b',')?;
+    manifest.write_record(["community", "size", "edges", "density", "file"])?;
+    for (id, nodes) in &communities {
+        let subgraph = graph.induced_subgraph(nodes); //Keeps the id map, so the files use the ids from the original input
+        let file = format!("community_{}.txt", id);
+        subgraph.write_edge_list(&Path::new(dir).join(&file).to_string_lossy())?;
+        manifest.write_record([
+            id.to_string(),
+            nodes.len().to_string(),
+            subgraph.num_edges().to_string(),
+            format.format(MetricFamily::Centrality, density(&subgraph)),
+            file,
+        ])?;
+    }
+    manifest.flush()?;
+    Ok(communities.len())
+}
+
+fn snapshot_name(path: &std::path::Path) -> String {
+    path.file_name().map_or(path.to_string_lossy(), |n| n.to_string_lossy()).into_owned()
+}
+
+pub fn write_growth_csv(report: &GrowthReport, path: &str, format: &NumberFormat, provenance: Option<&Provenance>) -> Result<(), GraphError> { //One row per snapshot; the delta columns compare with the previous snapshot and are empty on the first row
+    let mut writer = csv_writer(path, provenance, b',')?;
+    writer.write_record([
+        "snapshot", "nodes", "edges", "average_degree", "clustering", "clustering_sampled", "giant_fraction",
+        "delta_nodes", "delta_edges", "delta_average_degree", "delta_clustering", "delta_giant_fraction",
+    ])?;
+    let deltas = report.deltas();
+    for (i, row) in report.rows.iter().enumerate() {
+        let mut record = vec![
+            snapshot_name(&row.path),
+            row.num_nodes.to_string(),
+            row.num_edges.to_string(),
+            format.format(MetricFamily::Distance, row.average_degree),
+            format.format(MetricFamily::Similarity, row.clustering),
+            row.clustering_sampled.to_string(),
+            format.format(MetricFamily::Similarity, row.giant_fraction),
+        ];
+        match i.checked_sub(1).map(|j| &deltas[j]) {
+            Some(d) => record.extend([
+                d.nodes.to_string(),
+                d.edges.to_string(),
+                format.format(MetricFamily::Distance, d.average_degree),
+                format.format(MetricFamily::Similarity, d.clustering),
+                format.format(MetricFamily::Similarity, d.giant_fraction),
+            ]),
+            None => record.extend(std::iter::repeat_n(String::new(), 5)),
+        }
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn write_growth_markdown(report: &GrowthReport, out: &mut impl Write, format: &NumberFormat) -> Result<(), GraphError> { //Snapshot table followed by a table of changes between consecutive snapshots (sampled clustering is marked with ~)
+    writeln!(out, "# Growth Report\n\n| Snapshot | Nodes | Edges | Average degree | Clustering | Giant component |\n|---|---|---|---|---|---|")?;
+    for row in &report.rows {
+        writeln!(
+            out,
+            "| {} | {} | {} | {} | {}{} | {} |",
+            snapshot_name(&row.path),
+            row.num_nodes,
+            row.num_edges,
+            format.format(MetricFamily::Distance, row.average_degree),
+            if row.clustering_sampled { "~" } else { "" },
+            format.format(MetricFamily::Similarity, row.clustering),
+            format.format(MetricFamily::Similarity, row.giant_fraction)
+        )?;
+    }
+    let deltas = report.deltas();
+    if !deltas.is_empty() {
+        writeln!(out, "\n## Changes\n\n| From | To | Nodes | Edges | Average degree | Clustering | Giant component |\n|---|---|---|---|---|---|---|")?;
+        for (pair, d) in report.rows.windows(2).zip(&deltas) {
+            writeln!(
+                out,
+                "| {} | {} | {:+} | {:+} | {} | {} | {} |",
+                snapshot_name(&pair[0].path),
+                snapshot_name(&pair[1].path),
+                d.nodes,
+                d.edges,
+                format.format(MetricFamily::Distance, d.average_degree),
+                format.format(MetricFamily::Similarity, d.clustering),
+                format.format(MetricFamily::Similarity, d.giant_fraction)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_text(results: &AnalysisResults, format: &NumberFormat) -> String {
+        let mut out = Vec::new();
+        write_text(results, &Graph::new(), StalePolicy::Warn, &mut out, format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_report_reads_from_container() { //The report should print whatever is stored, even values no algorithm would produce
+        let mut results = AnalysisResults::new();
+        results.closeness = Some(vec![(42, 0.1234)]);
+        let report = render_text(&results, &NumberFormat::default());
+        assert!(report.contains("Node   42: Closeness Centrality 0.1234"));
+        assert!(!report.contains("Degree Distribution"));
+    }
+
+    #[test]
+    fn test_precision_separates_ties() { //Two nodes that look tied at 3 decimals are distinguishable at 6
+        let mut results = AnalysisResults::new();
+        results.closeness = Some(vec![(1, 0.393_641), (2, 0.393_639)]);
+        let low = render_text(&results, &NumberFormat::with_precision(3));
+        assert!(low.contains("Node    1: Closeness Centrality 0.394"));
+        assert!(low.contains("Node    2: Closeness Centrality 0.394"));
+        let high = render_text(&results, &NumberFormat::with_precision(6));
+        assert!(high.contains("Node    1: Closeness Centrality 0.393641"));
+        assert!(high.contains("Node    2: Closeness Centrality 0.393639"));
+    }
+
+    #[test]
+    fn test_scientific_threshold() {
+        let format = NumberFormat { scientific_threshold: 1e6, ..NumberFormat::with_precision(2) };
+        assert_eq!(format.format(MetricFamily::Betweenness, 3_916_560.1), "3.92e6");
+        assert_eq!(format.format(MetricFamily::Betweenness, 12.345), "12.35");
+    }
+
+    #[test]
+    fn test_json_and_markdown_use_format() {
+        let mut results = AnalysisResults::new();
+        results.similar_pairs = Some(vec![((1, 2), 2.0 / 3.0)]);
+        let mut json = Vec::new();
+        write_json(&results, &Graph::new(), StalePolicy::Warn, &mut json, &NumberFormat::with_precision(2)).unwrap();
+        assert_eq!(String::from_utf8(json).unwrap(), "{\"similar_pairs\": [{\"u\": 1, \"v\": 2, \"similarity\": 0.67}]}\n");
+        let mut markdown = Vec::new();
+        write_markdown(&results, &Graph::new(), StalePolicy::Warn, &mut markdown, &NumberFormat::with_precision(4)).unwrap();
+        assert!(String::from_utf8(markdown).unwrap().contains("| 1 | 2 | 0.6667 |"));
+    }
+
+    #[test]
+    fn test_writers_check_freshness() { //Closeness computed before an edge was added: Refuse fails, Warn says so in every format, and recomputing clears it
+        let mut graph = Graph::from_edges([(0, 1), (1, 2)]);
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.ensure_closeness(&graph);
+        graph.add_edge(InternalId(2), InternalId(0));
+        let mut out = Vec::new();
+        assert!(matches!(write_text(&results, &graph, StalePolicy::Refuse, &mut out, &NumberFormat::default()), Err(GraphError::InvalidParameter(_))));
+        assert!(matches!(write_json(&results, &graph, StalePolicy::Refuse, &mut out, &NumberFormat::default()), Err(GraphError::InvalidParameter(_))));
+        assert!(out.is_empty());
+
+        write_text(&results, &graph, StalePolicy::Warn, &mut out, &NumberFormat::default()).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("Warning: closeness was computed before the graph changed\n"));
+        let mut markdown = Vec::new();
+        write_markdown(&results, &graph, StalePolicy::Warn, &mut markdown, &NumberFormat::default()).unwrap();
+        assert!(String::from_utf8(markdown).unwrap().contains("> Warning: closeness was computed before the graph changed"));
+        let mut json = Vec::new();
+        write_json(&results, &graph, StalePolicy::Warn, &mut json, &NumberFormat::default()).unwrap();
+        assert!(String::from_utf8(json).unwrap().starts_with("{\"stale\": [\"closeness\"], "));
+        let path = std::env::temp_dir().join(format!("fga_stale_features_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_node_features_csv(&results, &graph, StalePolicy::Warn, path, &NumberFormat::default()).unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().starts_with("# stale: closeness\nnode,degree,closeness\n"));
+
+        results.refresh(&graph);
+        results.ensure_closeness(&graph);
+        write_node_features_csv(&results, &graph, StalePolicy::Refuse, path, &NumberFormat::default()).unwrap();
+        assert!(std::fs::read_to_string(path).unwrap().starts_with("node,degree,closeness\n"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_node_features_csv_columns() { //Only computed results become columns
+        let graph = Graph::from_edges([(0, 1), (1, 2), (2, 0)]);
+        let mut results = AnalysisResults::new();
+        results.ensure_communities(&graph);
+        results.ensure_diversity(&graph);
+        results.ensure_closeness(&graph);
+        results.ensure_two_hop(&graph);
+        let path = std::env::temp_dir().join(format!("fga_features_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_node_features_csv(&results, &graph, StalePolicy::Warn, path, &NumberFormat::with_precision(2)).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(contents, "node,degree,closeness,community,diversity,two_hop_reach\n0,2,1.00,0,1,0\n1,2,1.00,0,1,0\n2,2,1.00,0,1,0\n");
+
+        results.ensure_distance_profiles(&graph, 2); //Adds the three profile columns
+        write_node_features_csv(&results, &graph, StalePolicy::Warn, path, &NumberFormat::with_precision(2)).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(contents.starts_with("node,degree,closeness,community,diversity,two_hop_reach,at_distance_1,at_distance_2,median_distance\n0,2,1.00,0,1,0,2,0,1\n"));
+    }
+
+    #[test]
+    fn test_export_communities() { //Two 4-cliques joined by 3-4, plus node 8 hanging off 7 in a community of its own that min_size 2 skips
+        let mut graph = Graph::new();
+        for clique in [[0, 1, 2, 3], [4, 5, 6, 7]] {
+            for i in 0..4 {
+                for j in i + 1..4 {
+                    graph.add_edge(InternalId(clique[i]), InternalId(clique[j]));
+                }
+            }
+        }
+        graph.add_edge(InternalId(3), InternalId(4));
+        graph.add_edge(InternalId(7), InternalId(8));
+        let partition: Partition = (0..=8).map(|n| (n, if n == 8 { 2 } else { n / 4 })).collect();
+        let dir = std::env::temp_dir().join(format!("fga_export_communities_{}", std::process::id()));
+        let written = export_communities(dir.to_str().unwrap(), &graph, &partition, 2, &NumberFormat::with_precision(2), None).unwrap();
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        let (first, second, manifest) = (read("community_0.txt"), read("community_1.txt"), read("manifest.csv"));
+        let skipped = dir.join("community_2.txt").exists();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written, 2);
+        assert!(!skipped);
+        assert_eq!(first, "0 1\n0 2\n0 3\n1 2\n1 3\n2 3\n");
+        assert_eq!(second, "4 5\n4 6\n4 7\n5 6\n5 7\n6 7\n");
+        assert_eq!(manifest, "community,size,edges,density,file\n0,4,6,1.00,community_0.txt\n1,4,6,1.00,community_1.txt\n");
+    }
+
+    #[test]
+    fn test_reports_show_original_ids() { //A graph loaded from huge ids is stored compactly but every writer prints the ids from the file
+        let path = std::env::temp_dir().join(format!("fga_report_huge_ids_{}.txt", std::process::id()));
+        std::fs::write(&path, "5000000001 5000000002\n5000000002 5000000003\n").unwrap();
+        let graph = Graph::load_from_file(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(graph.adjacency().keys().all(|&n| n < 3));
+
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.ensure_degrees(&graph);
+        results.ensure_betweenness(&graph);
+        let text = render_text(&results, &NumberFormat::default());
+        assert!(text.contains("Degree    1: 2 nodes\nDegree    2: 1 nodes"));
+        assert!(text.contains("Node 5000000002: Betweenness Centrality 1.0"));
+        let mut json = Vec::new();
+        write_json(&results, &graph, StalePolicy::Warn, &mut json, &NumberFormat::default()).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.contains("{\"node\": 5000000001, \"degree\": 1}"));
+        assert!(!json.contains("\"node\": 0,"));
+    }
+
+    #[test]
+    fn test_growth_writers() { //Deltas appear from the second snapshot on, with signs in Markdown
+        use crate::pipeline::GrowthRow;
+        let row = |name: &str, nodes: usize, edges: usize| GrowthRow {
+            path: PathBuf::from(name),
+            num_nodes: nodes,
+            num_edges: edges,
+            average_degree: 2.0 * edges as f64 / nodes as f64,
+            clustering: 0.5,
+            clustering_sampled: false,
+            giant_fraction: 1.0,
+        };
+        let report = GrowthReport { rows: vec![row("a.txt", 4, 4), row("b.txt", 5, 10)] };
+        let mut out = Vec::new();
+        write_growth_markdown(&report, &mut out, &NumberFormat::default()).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("| a.txt | b.txt | +1 | +6 | 2.00 | 0.000 | 0.000 |"));
+
+        let path = std::env::temp_dir().join(format!("fga_growth_{}.csv", std::process::id()));
+        write_growth_csv(&report, path.to_str().unwrap(), &NumberFormat::default(), None).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[1], "a.txt,4,4,2.00,0.500,false,1.000,,,,,");
+        assert_eq!(lines[2], "b.txt,5,10,4.00,0.500,false,1.000,1,6,2.00,0.000,0.000");
+    }
+
+    #[test]
+    fn test_report_json_and_csv_round_trip() { //Floats that need all 17 digits, a huge id and a provenance "meta" object all survive a write and read
+        let report = AnalysisReport {
+            average_distance: 0.1 + 0.2,
+            top_closeness: vec![(NodeId(5_000_000_001), 1.0 / 3.0), (NodeId(7), 1e-300)],
+            top_pairs: vec![((NodeId(1), NodeId(2)), 2.0 / 3.0)],
+            timings: vec![(String::from("loading"), 0.125), (String::from("closeness \"exact\""), 1.0 / 7.0)],
+        };
+        let path = std::env::temp_dir().join(format!("fga_report_round_trip_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let provenance = Provenance::new("graph.txt", "--out-dir x", Some(1), Instant::now());
+        write_report_json(path, &report, Some(&provenance)).unwrap();
+        assert_eq!(read_report_json(path).unwrap(), report);
+        assert_eq!(Provenance::read(path).unwrap().input, "graph.txt");
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(AnalysisReport::from_json(&AnalysisReport::default().to_json(None)).unwrap(), AnalysisReport::default());
+        let missing = AnalysisReport { average_distance: f64::NAN, ..AnalysisReport::default() };
+        assert!(AnalysisReport::from_json(&missing.to_json(None)).unwrap().average_distance.is_nan());
+        assert!(AnalysisReport::from_json("{\"average_distance\": 1.5}").is_err());
+
+        let mut graph = Graph::new();
+        graph.add_edge(InternalId(1), InternalId(2));
+        let path = std::env::temp_dir().join(format!("fga_centrality_{}.csv", std::process::id()));
+        write_centrality_csv(path.to_str().unwrap(), &[(2, 1.0 / 3.0), (1, 0.1 + 0.2)], &graph, None).unwrap();
+        let rows: Vec<f64> = csv::Reader::from_path(&path).unwrap().records().map(|r| r.unwrap()[1].parse().unwrap()).collect();
+        assert_eq!(rows, vec![1.0 / 3.0, 0.1 + 0.2]);
+        write_pairs_csv(path.to_str().unwrap(), &[((1, 2), 2.0 / 3.0)], &graph, None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), format!("u,v,score\n1,2,{}\n", 2.0 / 3.0));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_ranked_pages_and_jsonl() { //Pages of 3 over 8 rows end with a short page and then nothing; joined they are the ranking, and the JSONL file has one parseable object per row
+        let scores: Vec<(usize, f64)> = (0..8).map(|i| (10 + i, 1.0 / (i + 1) as f64)).collect();
+        let ranked = RankedMetric::new("closeness", &scores);
+        assert_eq!((ranked.page(0, 3), ranked.page(6, 3), ranked.page(8, 3), ranked.page(100, usize::MAX)), (&scores[..3], &scores[6..], &[][..], &[][..]));
+        let joined: Vec<(usize, f64)> = (0..ranked.len()).step_by(3).flat_map(|offset| ranked.page(offset, 3).to_vec()).collect();
+        assert_eq!(joined, scores);
+        assert_eq!(ranked.entries(3, 2).collect::<Vec<_>>(), vec![RankedEntry { rank: 4, node: 13, score: 0.25 }, RankedEntry { rank: 5, node: 14, score: 0.2 }]);
+
+        let graph = Graph::from_edges(&[(1 << 40, 1 << 41)]); //Compacted ids 0 and 1 are written as the file's ids
+        let path = std::env::temp_dir().join(format!("fga_ranked_{}.jsonl", std::process::id()));
+        let path = path.to_str().unwrap();
+        let two = [(1, 1.0 / 3.0), (0, f64::NAN)];
+        assert_eq!(write_ranked_jsonl(path, "page\"rank", RankedMetric::new("", &two).entries(0, 10), &graph).unwrap(), 2);
+        let text = std::fs::read_to_string(path).unwrap();
+        let lines: Vec<JsonValue> = text.lines().map(|line| parse_json(line).expect(line)).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].get("rank").unwrap().number(), lines[0].get("node").unwrap().number()), (Some(1usize), Some(1usize << 41)));
+        assert_eq!(lines[0].get("score").unwrap().float(), Some(1.0 / 3.0));
+        assert_eq!(lines[1].get("metric"), Some(&JsonValue::Text(String::from("page\"rank"))));
+        assert!(lines[1].get("score").unwrap().float().unwrap().is_nan());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_walks() { //One line per walk with the file's ids, so word2vec sees the same tokens as the input edge list
+        let graph = Graph::from_edges([(1 << 40, 5), (5, 1 << 41)]);
+        let walks = crate::walks::generate_random_walks(&graph, 2, 4, 1);
+        let path = std::env::temp_dir().join(format!("fga_walks_{}.txt", std::process::id()));
+        write_walks(path.to_str().unwrap(), &walks, &graph).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let read: Vec<Vec<usize>> = text.lines().map(|line| line.split(' ').map(|word| graph.internal_id(NodeId(word.parse().unwrap())).unwrap().index()).collect()).collect();
+        assert_eq!(read, walks);
+        assert!(text.lines().all(|line| line.split(' ').count() == 4) && text.contains(&(1usize << 41).to_string()));
+    }
+
+    #[test]
+    fn test_degree_histogram_csv() { //Star with 4 leaves: a header, then one row per degree present; the hop plot is written the same way
+        let mut graph = Graph::new();
+        for leaf in 1..=4 {
+            graph.add_edge(InternalId(0), InternalId(leaf));
+        }
+        let path = std::env::temp_dir().join(format!("fga_degree_histogram_{}.csv", std::process::id()));
+        write_degree_histogram_csv(&graph, path.to_str().unwrap(), None).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(csv, "degree,count\n1,4\n4,1\n");
+        write_distance_distribution_csv(path.to_str().unwrap(), &BTreeMap::from([(1, 4), (2, 6)]), None).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "distance,pairs\n1,4\n2,6\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_write_dot() { //Each node once (highlighted ones filled), each edge once with the smaller id first
+        let mut graph = Graph::new();
+        for (u, v) in [(2, 1), (1, 0)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let path = std::env::temp_dir().join(format!("fga_dot_{}.dot", std::process::id()));
+        write_dot(path.to_str().unwrap(), &graph, &[0, 2], None).unwrap();
+        let dot = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(dot, "graph G {\n  0 [style=filled, fillcolor=gold];\n  1;\n  2 [style=filled, fillcolor=gold];\n  0 -- 1;\n  1 -- 2;\n}\n");
+    }
+
+    #[test]
+    fn test_wide_metrics_keep_columns_aligned() { //Only degrees and betweenness computed: every row still has all 9 columns, with NA in the others
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (1, 2)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.ensure_degrees(&graph);
+        results.ensure_betweenness(&graph);
+        let path = std::env::temp_dir().join(format!("fga_wide_{}.tsv", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_wide_metrics(path, &results, &graph, StalePolicy::Warn, TableFormat::Tsv, &NumberFormat::default()).unwrap();
+        let contents = std::fs::read_to_string(path).unwrap();
+        let schema = std::fs::read_to_string(format!("{}.schema", path)).unwrap();
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(format!("{}.schema", path)).unwrap();
+
+        let rows: Vec<Vec<&str>> = contents.lines().map(|l| l.split('\t').collect()).collect();
+        assert_eq!(rows.len(), 4);
+        assert!(rows.iter().all(|r| r.len() == 9));
+        assert_eq!(rows[0][3], "betweenness");
+        assert_eq!(rows[2], vec!["1", "2", "NA", "1.0", "NA", "NA", "NA", "NA", "NA"]);
+        assert_eq!(schema.lines().count(), 9);
+        assert!(schema.starts_with("node\tint64\ndegree\tint64\ncloseness\tfloat64\n"));
+    }
+
+    #[test]
+    fn test_provenance_in_every_format() { //CSV comment lines, the JSON "meta" object and the Markdown / DOT sidecars all read back with the loaded graph's fingerprint
+        let mut graph = Graph::new();
+        for (u, v) in [(0, 1), (1, 2), (2, 0), (2, 3)] {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        let mut results = AnalysisResults::for_graph(&graph);
+        results.record_provenance(&graph, "data/\"odd\" name.txt", "--threads 2", Instant::now());
+        results.ensure_closeness(&graph);
+        let base = std::env::temp_dir().join(format!("fga_provenance_{}", std::process::id()));
+        let path = |extension: &str| format!("{}.{}", base.display(), extension);
+        let check = |file: &str| {
+            let provenance = Provenance::read(file).unwrap();
+            assert_eq!(provenance.fingerprint, Some(graph.fingerprint()));
+            assert_eq!((provenance.input.as_str(), provenance.options.as_str(), provenance.seed), ("data/\"odd\" name.txt", "--threads 2", Some(crate::pipeline::COMMUNITY_SEED)));
+            assert_eq!(provenance.crate_version, env!("CARGO_PKG_VERSION"));
+            assert!(provenance.timestamp > 0 && provenance.wall_time >= 0.0);
+        };
+
+        write_node_features_csv(&results, &graph, StalePolicy::Warn, &path("csv"), &NumberFormat::default()).unwrap();
+        check(&path("csv"));
+        let mut reader = csv::ReaderBuilder::new().comment(Some(b'#')).from_path(path("csv")).unwrap(); //Still a plain CSV to readers that skip comments
+        assert_eq!(reader.headers().unwrap(), vec!["node", "degree", "closeness"]);
+        assert_eq!(reader.records().count(), 4);
+        write_wide_metrics(&path("tsv"), &results, &graph, StalePolicy::Warn, TableFormat::Tsv, &NumberFormat::default()).unwrap();
+        check(&path("tsv"));
+
+        let mut json = Vec::new();
+        write_json(&results, &graph, StalePolicy::Warn, &mut json, &NumberFormat::default()).unwrap();
+        std::fs::write(path("json"), &json).unwrap();
+        check(&path("json"));
+        assert!(String::from_utf8(json).unwrap().contains("\"closeness\": [{\"node\": 0"));
+
+        let mut markdown = Vec::new();
+        write_markdown(&results, &graph, StalePolicy::Warn, &mut markdown, &NumberFormat::default()).unwrap();
+        std::fs::write(path("md"), &markdown).unwrap();
+        results.provenance.as_ref().unwrap().write_sidecar(&path("md")).unwrap();
+        check(&path("md"));
+        write_dot(&path("dot"), &graph, &[], results.provenance.as_ref()).unwrap();
+        check(&path("dot"));
+
+        results.provenance = None; //Without provenance the files are exactly as before
+        write_node_features_csv(&results, &graph, StalePolicy::Warn, &path("csv"), &NumberFormat::default()).unwrap();
+        assert!(std::fs::read_to_string(path("csv")).unwrap().starts_with("node,"));
+        assert!(Provenance::read(&path("csv")).is_err());
+        for extension in ["csv", "tsv", "tsv.schema", "json", "md", "md.meta.json", "dot", "dot.meta.json"] {
+            std::fs::remove_file(path(extension)).unwrap();
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//Module: sketch.rs
+//Here we keep HyperLogLog, a fixed-size sketch that estimates how many distinct items were added to it
+//Two sketches merge by taking the larger value of each register, so neighborhood sizes can be estimated by merging sketches along edges
+//(two-hop reach here, and any later ball-growing estimator such as HyperBall) without ever building the neighbor sets themselves
+use crate::error::GraphError;
+
+pub const MIN_PRECISION: u8 = 4;
+pub const MAX_PRECISION: u8 = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog { //2^precision one-byte registers; register i holds the longest run of leading zeros (+1) seen among hashes routed to it
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new(precision: u8) -> Result<Self, GraphError> { //Relative error of the estimate is about 1.04 / sqrt(2^precision), e.g. 1.6% at precision 12
+        if !(MIN_PRECISION..=MAX_PRECISION).contains(&precision) {
+            return Err(GraphError::InvalidParameter(format!("HyperLogLog precision must be between {} and {}, got {}", MIN_PRECISION, MAX_PRECISION, precision)));
+        }
+        Ok(Self { precision, registers: vec![0; 1 << precision] })
+    }
+
+    pub fn relative_error(precision: u8) -> f64 { //Standard error of the estimate relative to the true count
+        1.04 / ((1usize << precision) as f64).sqrt()
+    }
+
+    pub fn memory_bytes(&self) -> usize { //Size of the registers; independent of how many items were added
+        self.registers.len()
+    }
+
+    pub fn insert(&mut self, item: usize) {
+        let hash = mix(item as u64);
+        let index = (hash >> (64 - self.precision)) as usize; //Top bits pick the register...
+        let rest = hash << self.precision | 1 << (self.precision - 1); //...the remaining bits give the run length (the guard bit caps it)
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    pub fn merge(&mut self, other: &HyperLogLog) { //Afterwards self estimates the size of the union of both sets (both sketches must share a precision)
+        debug_assert_eq!(self.precision, other.precision);
+        for (mine, theirs) in self.registers.iter_mut().zip(&other.registers) {
+            if *theirs > *mine {
+                *mine = *theirs;
+            }
+        }
+    }
+
+    pub fn estimate(&self) -> f64 { //Estimated number of distinct items inserted (merged sketches included)
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let harmonic: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / harmonic;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 { //Small counts: linear counting on the empty registers is far more accurate
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+}
+
+pub(crate) fn mix(x: u64) -> u64 { //SplitMix64 finalizer: spreads consecutive node ids over the whole 64-bit range, the same on every run
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimates_within_error_and_merges_as_union() { //Counts from tiny to large stay within 3 standard errors; merging overlapping sets estimates the union
+        for count in [10, 1000, 100_000] {
+            let mut sketch = HyperLogLog::new(12).unwrap();
+            for item in 0..count {
+                sketch.insert(item);
+                sketch.insert(item); //Duplicates never change the estimate
+            }
+            let error = (sketch.estimate() - count as f64).abs() / count as f64;
+            assert!(error < 3.0 * HyperLogLog::relative_error(12), "count {}: relative error {}", count, error);
+        }
+        let (mut a, mut b) = (HyperLogLog::new(10).unwrap(), HyperLogLog::new(10).unwrap());
+        (0..3000).for_each(|i| a.insert(i));
+        (2000..5000).for_each(|i| b.insert(i));
+        a.merge(&b);
+        assert!((a.estimate() - 5000.0).abs() / 5000.0 < 3.0 * HyperLogLog::relative_error(10));
+        assert_eq!(a.memory_bytes(), 1024);
+        assert!(HyperLogLog::new(3).is_err());
+    }
+}
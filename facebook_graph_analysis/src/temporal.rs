@@ -0,0 +1,118 @@
+//Module: temporal.rs
+//Here we define TemporalGraph, an undirected graph whose edges are contacts at a point in time ("u v t" lines, e.g. when two people messaged)
+//The same pair may be in contact many times. As a GraphRead it is the static graph of everyone who was ever in contact, so the usual analyses
+//still run on it; analysis::temporal_reachability is the one that respects the timestamps
+use std::collections::{hash_map::Entry, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use crate::error::GraphError;
+use crate::graph::{parse_numbers, GraphRead};
+
+#[derive(Debug, Default, Clone)]
+pub struct TemporalGraph { //Every neighbor maps to the times of its contacts with the node (sorted), stored in both directions
+    pub adj_list: HashMap<usize, HashMap<usize, Vec<u64>>>,
+    pub num_nodes: usize,
+    pub num_edges: usize, //Contacts, so a pair in touch three times counts three
+}
+
+impl TemporalGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node: usize) { //Adds a node with no contacts (does nothing if it already exists)
+        if let Entry::Vacant(entry) = self.adj_list.entry(node) {
+            entry.insert(HashMap::new());
+            self.num_nodes += 1;
+        }
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize, time: u64) { //Records a contact between u and v at time. Self-loops only add the node; a repeated contact at the same time is ignored
+        self.add_node(u);
+        self.add_node(v);
+        if u == v {
+            return;
+        }
+        let times = self.adj_list.get_mut(&u).unwrap().entry(v).or_default();
+        let Err(position) = times.binary_search(&time) else { return };
+        times.insert(position, time);
+        let times = self.adj_list.get_mut(&v).unwrap().entry(u).or_default();
+        times.insert(times.binary_search(&time).unwrap_err(), time);
+        self.num_edges += 1;
+    }
+
+    pub fn load_from_file(path: &str) -> Result<Self, GraphError> { //Reads "u v t" lines with whole-number times; a line without exactly three numbers is a Parse error, blank lines are skipped
+        let reader = BufReader::new(File::open(path)?);
+        let mut graph = TemporalGraph::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            match parse_numbers(&line, index)?[..] {
+                [u, v, time] => graph.add_edge(u, v, time as u64),
+                [] => {}
+                _ => return Err(GraphError::Parse { line: index + 1, content: line }),
+            }
+        }
+        if graph.num_nodes == 0 {
+            return Err(GraphError::EmptyFile(path.to_string()));
+        }
+        Ok(graph)
+    }
+
+    pub fn contact_times(&self, u: usize, v: usize) -> &[u64] { //Times u and v were in contact, earliest first (empty if never)
+        self.adj_list.get(&u).and_then(|n| n.get(&v)).map_or(&[], |times| times.as_slice())
+    }
+
+    pub fn contacts_by_time(&self) -> Vec<(u64, usize, usize)> { //Every contact once as (time, smaller id, larger id), in time order (ties by ids)
+        let mut contacts: Vec<(u64, usize, usize)> = self
+            .adj_list
+            .iter()
+            .flat_map(|(&u, neighbors)| neighbors.iter().filter(move |(&v, _)| u < v).flat_map(move |(&v, times)| times.iter().map(move |&t| (t, u, v))))
+            .collect();
+        contacts.sort();
+        contacts
+    }
+}
+
+impl GraphRead for TemporalGraph { //The static projection: u and v are friends if they were ever in contact
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.adj_list.keys().copied())
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self.adj_list.get(&node) {
+            Some(neighbors) => Box::new(neighbors.keys().copied()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.adj_list.contains_key(&node)
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool {
+        !self.contact_times(u, v).is_empty()
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.adj_list.get(&node).map_or(0, |n| n.len())
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_contacts() { //Repeated contacts are kept in time order and counted, an exact repeat is not; two columns are a Parse error
+        let path = std::env::temp_dir().join(format!("fga_temporal_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1 9\n1 0 4\n\n0 1 9\n1 2 6\n").unwrap();
+        let graph = TemporalGraph::load_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!((graph.num_nodes, graph.num_edges, graph.edge_count()), (3, 3, 2));
+        assert_eq!(graph.contact_times(1, 0), &[4, 9]);
+        assert_eq!(graph.contacts_by_time(), vec![(4, 0, 1), (6, 1, 2), (9, 0, 1)]);
+        std::fs::write(&path, "0 1 9\n1 2\n").unwrap();
+        assert!(matches!(TemporalGraph::load_from_file(path.to_str().unwrap()), Err(GraphError::Parse { line: 2, .. })));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+//Module: traversal.rs
+//Here we keep the breadth-first search loop the analyses share. Bfs walks a graph as an iterator of (node, depth) pairs in BFS order,
+//and bfs_visit hands each node with its depth and BFS parent to a callback that can stop the walk early (e.g. past depth k or once a target is found).
+//A node's neighbors are only queued when the walk moves past it, so stopping early also skips expanding the node it stopped on
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::ops::ControlFlow;
+use crate::graph::GraphRead;
+
+pub struct Bfs<'a, G: GraphRead + ?Sized> {
+    graph: &'a G,
+    parents: HashMap<usize, Option<usize>>, //Every node discovered so far and the node it was reached from (None for the start)
+    queue: VecDeque<(usize, usize)>,        //(node, depth) discovered but not yet yielded
+    last: Option<(usize, usize)>,           //The node yielded last, expanded on the next call to next()
+}
+
+impl<'a, G: GraphRead + ?Sized> Bfs<'a, G> {
+    pub fn new(graph: &'a G, start: usize) -> Self { //Starts at start (depth 0); an unknown start node yields nothing
+        let mut bfs = Bfs { graph, parents: HashMap::new(), queue: VecDeque::new(), last: None };
+        if graph.contains_node(start) {
+            bfs.parents.insert(start, None);
+            bfs.queue.push_back((start, 0));
+        }
+        bfs
+    }
+
+    pub fn parent(&self, node: usize) -> Option<usize> { //The node a yielded (or already discovered) node was first reached from; None for the start and for undiscovered nodes
+        self.parents.get(&node).copied().flatten()
+    }
+}
+
+impl<G: GraphRead + ?Sized> Iterator for Bfs<'_, G> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> { //Depths never decrease; ties come in neighbor order
+        if let Some((node, depth)) = self.last.take() {
+            for neighbor in self.graph.neighbors(node) {
+                if let Entry::Vacant(slot) = self.parents.entry(neighbor) {
+                    slot.insert(Some(node));
+                    self.queue.push_back((neighbor, depth + 1));
+                }
+            }
+        }
+        self.last = self.queue.pop_front();
+        self.last
+    }
+}
+
+pub fn bfs_visit<G: GraphRead + ?Sized, B>(graph: &G, start: usize, mut visit: impl FnMut(usize, usize, Option<usize>) -> ControlFlow<B>) -> Option<B> { //Calls visit(node, depth, parent) in BFS order until it breaks; returns the break value, or None once every reachable node was visited
+    let mut bfs = Bfs::new(graph, start);
+    while let Some((node, depth)) = bfs.next() {
+        if let ControlFlow::Break(value) = visit(node, depth, bfs.parent(node)) {
+            return Some(value);
+        }
+    }
+    None
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Graph;
+
+    #[test]
+    fn test_bfs_order_and_early_stop() { //Depths never decrease and each parent is one hop closer; breaking past depth 1 visits exactly the start and its friends
+        let graph = crate::generate::watts_strogatz(60, 4, 0.2, 9).unwrap();
+        let mut bfs = Bfs::new(&graph, 0);
+        let mut order = Vec::new();
+        while let Some((node, depth)) = bfs.next() {
+            if let Some(parent) = bfs.parent(node) {
+                assert!(graph.has_edge(parent, node) && order.contains(&(parent, depth - 1)));
+            }
+            order.push((node, depth));
+        }
+        assert_eq!(order.len(), 60);
+        assert!(order.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(order[0], (0, 0));
+
+        let mut seen = vec![0];
+        let stopped_at = bfs_visit(&graph, 0, |node, depth, _| {
+            if depth > 1 {
+                return ControlFlow::Break(node);
+            }
+            if depth == 1 {
+                seen.push(node);
+            }
+            ControlFlow::Continue(())
+        });
+        let mut expected: Vec<usize> = std::iter::once(0).chain(graph.neighbors(0)).collect();
+        seen.sort();
+        expected.sort();
+        assert_eq!(seen, expected);
+        assert!(stopped_at.is_some_and(|node| !expected.contains(&node)));
+        assert_eq!((Bfs::new(&graph, 999).next(), bfs_visit(&Graph::new(), 0, |_, _, _| ControlFlow::Break(()))), (None, None));
+    }
+}
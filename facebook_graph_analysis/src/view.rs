@@ -0,0 +1,167 @@
+//Module: view.rs
+//Here we define GraphView: a filtered, read-only look at a Graph that keeps only the nodes matching a predicate
+//EdgeOverlay is the opposite: a Graph plus a few hypothetical edges, used for "what if" questions
+//Nothing is copied: neighbors are filtered on the fly, so a view of a huge graph costs almost no memory
+use std::collections::HashSet;
+use crate::graph::{Graph, GraphRead};
+use crate::labels::InternalId;
+
+pub struct GraphView<'a> {
+    base: &'a Graph,
+    keep: Box<dyn Fn(usize) -> bool + Sync + 'a>, //Returns true for nodes that are part of the view (Sync so parallel analyses can share the view)
+}
+
+impl<'a> GraphView<'a> {
+    pub fn new(base: &'a Graph, keep: impl Fn(usize) -> bool + Sync + 'a) -> Self { //View of the nodes for which `keep` returns true (and the edges between them)
+        Self { base, keep: Box::new(keep) }
+    }
+
+    pub fn degree_range(base: &'a Graph, min_degree: usize, max_degree: usize) -> Self { //Nodes whose degree in the full graph is between min_degree and max_degree (inclusive)
+        Self::new(base, move |node| (min_degree..=max_degree).contains(&base.adjacency().get(&node).map_or(0, |n| n.len())))
+    }
+
+    pub fn of_nodes(base: &'a Graph, members: &'a HashSet<usize>) -> Self { //Nodes that belong to the given set
+        Self::new(base, move |node| members.contains(&node))
+    }
+
+    pub fn to_graph(&self) -> Graph { //Materializes the view as an independent Graph (only needed when a copy is really wanted)
+        let mut graph = Graph::new();
+        for node in self.nodes() {
+            graph.add_node(InternalId(node));
+            self.neighbors(node).for_each(|friend| _ = graph.add_edge(InternalId(node), InternalId(friend)));
+        }
+        graph
+    }
+}
+
+impl GraphRead for GraphView<'_> {
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.base.adjacency().keys().copied().filter(move |&n| (self.keep)(n)))
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        if !self.contains_node(node) {
+            return Box::new(std::iter::empty());
+        }
+        Box::new(self.base.neighbors(node).filter(move |&n| (self.keep)(n)))
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.base.contains_node(node) && (self.keep)(node)
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.contains_node(u) && self.contains_node(v) && self.base.has_edge(u, v)
+    }
+
+    fn for_each_neighbor(&self, node: usize, mut visit: impl FnMut(usize)) {
+        if self.contains_node(node) {
+            self.base.for_each_neighbor(node, |n| if (self.keep)(n) { visit(n) });
+        }
+    }
+}
+
+pub struct EdgeOverlay<'a> { //A Graph plus a few extra edges that only exist in the overlay ("what if these two became friends"), again without copying the base
+    base: &'a Graph,
+    extra: Vec<(usize, usize)>,
+}
+
+impl<'a> EdgeOverlay<'a> {
+    pub fn new(base: &'a Graph, extra: &[(usize, usize)]) -> Self { //Self-loops and edges the base already has are ignored
+        let mut added: Vec<(usize, usize)> = Vec::new();
+        for &(u, v) in extra {
+            let edge = (u.min(v), u.max(v));
+            if u != v && !base.has_edge(u, v) && !added.contains(&edge) {
+                added.push(edge);
+            }
+        }
+        Self { base, extra: added }
+    }
+
+    fn extra_neighbors(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.extra.iter().filter_map(move |&(u, v)| if u == node { Some(v) } else if v == node { Some(u) } else { None })
+    }
+}
+
+impl GraphRead for EdgeOverlay<'_> {
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> { //Endpoints of extra edges that the base lacks are new nodes
+        let mut new_nodes: Vec<usize> = self.extra.iter().flat_map(|&(u, v)| [u, v]).filter(|n| !self.base.contains_node(*n)).collect();
+        new_nodes.sort();
+        new_nodes.dedup();
+        Box::new(self.base.adjacency().keys().copied().chain(new_nodes))
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.base.neighbors(node).chain(self.extra_neighbors(node)))
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.base.contains_node(node) || self.extra_neighbors(node).next().is_some()
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.base.has_edge(u, v) || self.extra.contains(&(u.min(v), u.max(v)))
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{bfs_distances, jaccard_similarity};
+
+    fn wheel_graph() -> Graph { //Hub 0 connected to a ring 1-2-3-4-5-1, plus a pendant 6 on node 1
+        let mut graph = Graph::new();
+        let mut edges: Vec<(usize, usize)> = (1..=5).map(|i| (0, i)).collect();
+        edges.extend([(1, 2), (2, 3), (3, 4), (4, 5), (5, 1), (1, 6)]);
+        for (u, v) in edges {
+            graph.add_edge(InternalId(u), InternalId(v));
+        }
+        graph
+    }
+
+    #[test]
+    fn test_view_matches_materialized_subgraph() { //Degrees 2..=4 keep the ring nodes but drop the hub (degree 5) and the pendant (degree 1)
+        let graph = wheel_graph();
+        let view = GraphView::degree_range(&graph, 2, 4);
+
+        let mut subgraph = Graph::new(); //Built by hand: the ring 1-2-3-4-5-1
+        for (u, v) in [(1, 2), (2, 3), (3, 4), (4, 5), (5, 1)] {
+            subgraph.add_edge(InternalId(u), InternalId(v));
+        }
+
+        assert_eq!(view.node_count(), 5);
+        assert_eq!(view.edge_count(), 5);
+        for node in 1..=5 {
+            assert_eq!(bfs_distances(&view, InternalId(node)), bfs_distances(&subgraph, InternalId(node)));
+            for other in 1..=5 {
+                assert_eq!(jaccard_similarity(&view, InternalId(node), InternalId(other)), jaccard_similarity(&subgraph, InternalId(node), InternalId(other)));
+            }
+        }
+        assert_eq!(view.to_graph().adjacency(), subgraph.adjacency());
+    }
+
+    #[test]
+    fn test_membership_view() { //Nodes outside the set are invisible, even as neighbors
+        let graph = wheel_graph();
+        let members = HashSet::from([0, 1, 6]);
+        let view = GraphView::of_nodes(&graph, &members);
+        assert_eq!(view.degree(1), 2);
+        assert!(!view.contains_node(2));
+        assert!(!view.has_edge(1, 2));
+        assert_eq!(bfs_distances(&view, InternalId(6)).len(), 3);
+    }
+
+    #[test]
+    fn test_edge_overlay() { //The overlay adds 6-3 (and a brand new node 9 on 6) while the base stays untouched
+        let graph = wheel_graph();
+        let overlay = EdgeOverlay::new(&graph, &[(6, 3), (3, 6), (1, 2), (6, 9)]);
+        assert!(overlay.has_edge(3, 6) && overlay.has_edge(1, 2));
+        assert!(!graph.has_edge(3, 6));
+        assert_eq!(overlay.degree(6), 3);
+        assert_eq!(overlay.node_count(), 8);
+        assert_eq!(overlay.edge_count(), graph.num_edges() + 2);
+        assert_eq!(bfs_distances(&overlay, InternalId(6))[&3], 1);
+        assert_eq!(bfs_distances(&overlay, InternalId(9))[&4], 3);
+    }
+}
@@ -0,0 +1,100 @@
+//Module: walks.rs
+//Here we generate random walk sequences to feed embedding tools (DeepWalk, node2vec, word2vec on the walks as sentences)
+//generate_random_walks steps to a uniformly random friend each time; generate_biased_walks is node2vec's second-order walk, where the step
+//also depends on the node the walk just came from (return parameter p, in-out parameter q). Walks start from every node that has at least one friend:
+//isolated nodes have nowhere to go, so they get no walks at all (not one-node walks). Each walk holds exactly walk_length nodes, internal ids,
+//and the same seed always gives the same walks (see report::write_walks to save them with the input file's ids)
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use crate::error::GraphError;
+use crate::graph::{Graph, GraphRead};
+use crate::labels::InternalId;
+
+pub fn generate_random_walks(graph: &Graph, walks_per_node: usize, walk_length: usize, seed: u64) -> Vec<Vec<usize>> { //walks_per_node uniform walks from each non-isolated node, in rounds: one walk from every node (by id) per round
+    walks_from_every_node(graph, walks_per_node, walk_length, seed, |_, friends, rng| friends[rng.gen_range(0..friends.len())])
+}
+
+pub fn generate_biased_walks(graph: &Graph, walks_per_node: usize, walk_length: usize, p: f64, q: f64, seed: u64) -> Result<Vec<Vec<usize>>, GraphError> { //node2vec walks: coming from t, the next node x is picked with weight 1/p if x is t,
+    //1 if x is a friend of t and 1/q otherwise (the first step is uniform). Low p keeps walks local, low q pushes them outward; p = q = 1 is the uniform walk
+    for (name, value) in [("return parameter p", p), ("in-out parameter q", q)] {
+        if !(value.is_finite() && value > 0.0) {
+            return Err(GraphError::InvalidParameter(format!("{} must be a positive number, got {}", name, value)));
+        }
+    }
+    Ok(walks_from_every_node(graph, walks_per_node, walk_length, seed, |previous, friends, rng| {
+        let Some(previous) = previous else {
+            return friends[rng.gen_range(0..friends.len())];
+        };
+        let weight = |next: usize| {
+            if next == previous {
+                1.0 / p
+            } else if graph.has_edge(next, previous) {
+                1.0
+            } else {
+                1.0 / q
+            }
+        };
+        let mut target = rng.gen::<f64>() * friends.iter().map(|&next| weight(next)).sum::<f64>();
+        for &next in friends {
+            target -= weight(next);
+            if target < 0.0 {
+                return next;
+            }
+        }
+        friends[friends.len() - 1] //Only reached through rounding in the sum
+    }))
+}
+
+fn walks_from_every_node(graph: &Graph, walks_per_node: usize, walk_length: usize, seed: u64, mut step: impl FnMut(Option<usize>, &[usize], &mut StdRng) -> usize) -> Vec<Vec<usize>> { //Shared loop: step(previous node, friends of the current node in id order, rng) picks the next node
+    if walk_length == 0 {
+        return Vec::new();
+    }
+    let mut starts: Vec<usize> = graph.adjacency().iter().filter(|(_, friends)| !friends.is_empty()).map(|(&node, _)| node).collect();
+    starts.sort();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut walks = Vec::with_capacity(walks_per_node * starts.len());
+    for _ in 0..walks_per_node {
+        for &start in &starts {
+            let mut walk = Vec::with_capacity(walk_length);
+            walk.push(start);
+            while walk.len() < walk_length {
+                let previous = walk.len().checked_sub(2).map(|i| walk[i]);
+                let next = step(previous, graph.sorted_neighbors(InternalId(walk[walk.len() - 1])), &mut rng);
+                walk.push(next);
+            }
+            walks.push(walk);
+        }
+    }
+    walks
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walks_follow_edges_and_repeat_per_seed() { //Uniform and biased walks: right count and length, every step an edge, the isolated node skipped, the same seed giving the same walks
+        let mut graph = crate::generate::watts_strogatz(40, 4, 0.2, 5).unwrap();
+        graph.add_node(InternalId(99));
+        let uniform = generate_random_walks(&graph, 3, 12, 7);
+        let biased = generate_biased_walks(&graph, 3, 12, 0.5, 2.0, 7).unwrap();
+        for walks in [&uniform, &biased] {
+            assert_eq!(walks.len(), 3 * 40);
+            for walk in walks {
+                assert_eq!(walk.len(), 12);
+                assert_ne!(walk[0], 99);
+                assert!(walk.windows(2).all(|step| graph.has_edge(step[0], step[1])), "{:?}", walk);
+            }
+        }
+        assert_eq!(generate_random_walks(&graph, 3, 12, 7), uniform);
+        assert_eq!(generate_biased_walks(&graph, 3, 12, 0.5, 2.0, 7).unwrap(), biased);
+        assert_ne!(generate_random_walks(&graph, 3, 12, 8), uniform);
+        assert!(generate_random_walks(&graph, 3, 0, 7).is_empty());
+        assert!(matches!(generate_biased_walks(&graph, 1, 5, 0.0, 1.0, 7), Err(GraphError::InvalidParameter(_))));
+
+        let path = Graph::from_edges([(0, 1), (1, 2), (2, 3)]); //With a tiny p a walk from 1 to 2 almost always steps straight back
+        let from_1_to_2: Vec<Vec<usize>> = generate_biased_walks(&path, 200, 3, 1e-6, 1.0, 3).unwrap().into_iter().filter(|walk| walk[..2] == [1, 2]).collect();
+        assert!(!from_1_to_2.is_empty() && from_1_to_2.iter().all(|walk| walk[2] == 1));
+    }
+}
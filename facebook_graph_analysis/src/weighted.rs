@@ -0,0 +1,151 @@
+//Module: weighted.rs
+//Here we define WeightedGraph, an undirected graph whose edges carry a numeric weight (for example a similarity score)
+use std::collections::{hash_map::Entry, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use crate::error::GraphError;
+use crate::graph::GraphRead;
+
+#[derive(Debug, Default, Clone)]
+pub struct WeightedGraph { //Adjacency list where every neighbor maps to the weight of the edge, stored in both directions
+    pub adj_list: HashMap<usize, HashMap<usize, f64>>,
+    pub num_nodes: usize,
+    pub num_edges: usize,
+}
+
+impl WeightedGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node: usize) { //Adds a node with no edges (does nothing if it already exists)
+        if let Entry::Vacant(entry) = self.adj_list.entry(node) {
+            entry.insert(HashMap::new());
+            self.num_nodes += 1;
+        }
+    }
+
+    pub fn add_edge(&mut self, u: usize, v: usize, weight: f64) { //Adds an undirected edge, or updates its weight if it already exists
+        self.add_node(u);
+        self.add_node(v);
+        if self.adj_list.get_mut(&u).unwrap().insert(v, weight).is_none() {
+            self.num_edges += 1;
+        }
+        self.adj_list.get_mut(&v).unwrap().insert(u, weight);
+    }
+
+    pub fn load_from_file(path: &str) -> Result<Self, GraphError> { //Reads "u v weight" lines; a missing weight means 1.0, so plain edge lists (like the Facebook file) load too. Negative or non-numeric weights are Parse errors
+        let reader = BufReader::new(File::open(path)?);
+        let mut graph = WeightedGraph::new();
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let bad = || GraphError::Parse { line: index + 1, content: line.clone() };
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if tokens.is_empty() {
+                continue;
+            }
+            let (u, v, weight) = match tokens[..] {
+                [u, v] => (u, v, "1.0"),
+                [u, v, w] => (u, v, w),
+                _ => return Err(bad()),
+            };
+            let weight: f64 = weight.parse().map_err(|_| bad())?;
+            if !weight.is_finite() || weight < 0.0 {
+                return Err(bad());
+            }
+            graph.add_edge(u.parse().map_err(|_| bad())?, v.parse().map_err(|_| bad())?, weight);
+        }
+        if graph.num_nodes == 0 {
+            return Err(GraphError::EmptyFile(path.to_string()));
+        }
+        Ok(graph)
+    }
+
+    pub fn weight(&self, u: usize, v: usize) -> Option<f64> { //Weight of the edge between u and v, if there is one
+        self.adj_list.get(&u).and_then(|n| n.get(&v)).copied()
+    }
+
+    pub fn strengths(&self) -> Vec<(usize, f64)> { //Weighted counterpart of Graph::all_degrees: every node with the total weight of its edges, strongest first (ties by node id)
+        let mut strengths: Vec<(usize, f64)> = self.adj_list.iter().map(|(&node, neighbors)| (node, neighbors.values().sum())).collect();
+        strengths.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap().then(a.0.cmp(&b.0)));
+        strengths
+    }
+
+    pub fn pretty(&self) -> String { //Graph::pretty with each friend's edge weight in parentheses, e.g. "1: 0 (2.5) 2 (1)"
+        let mut nodes: Vec<&usize> = self.adj_list.keys().collect();
+        nodes.sort();
+        let mut out = String::new();
+        for node in nodes {
+            let mut friends: Vec<(&usize, &f64)> = self.adj_list[node].iter().collect();
+            friends.sort_by_key(|(friend, _)| **friend);
+            out.push_str(&format!("{}:", node));
+            friends.iter().for_each(|(friend, weight)| out.push_str(&format!(" {} ({})", friend, weight)));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl GraphRead for WeightedGraph { //Unweighted analyses (BFS, Jaccard, ...) simply ignore the weights
+    fn nodes(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        Box::new(self.adj_list.keys().copied())
+    }
+
+    fn neighbors(&self, node: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match self.adj_list.get(&node) {
+            Some(neighbors) => Box::new(neighbors.keys().copied()),
+            None => Box::new(std::iter::empty()),
+        }
+    }
+
+    fn contains_node(&self, node: usize) -> bool {
+        self.adj_list.contains_key(&node)
+    }
+
+    fn has_edge(&self, u: usize, v: usize) -> bool {
+        self.weight(u, v).is_some()
+    }
+
+    fn for_each_neighbor(&self, node: usize, visit: impl FnMut(usize)) {
+        self.adj_list.get(&node).into_iter().flat_map(|n| n.keys()).copied().for_each(visit);
+    }
+
+    fn degree(&self, node: usize) -> usize {
+        self.adj_list.get(&node).map_or(0, |n| n.len())
+    }
+
+    fn edge_weight(&self, u: usize, v: usize) -> f64 {
+        self.weight(u, v).unwrap_or(0.0)
+    }
+}
+
+//TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_edge_counts() { //Re-adding an edge updates the weight without double counting
+        let mut graph = WeightedGraph::new();
+        graph.add_edge(1, 2, 0.5);
+        graph.add_edge(2, 1, 0.75);
+        graph.add_node(3);
+        assert_eq!(graph.num_nodes, 3);
+        assert_eq!(graph.num_edges, 1);
+        assert_eq!(graph.strengths(), vec![(1, 0.75), (2, 0.75), (3, 0.0)]);
+        assert_eq!(graph.weight(1, 2), Some(0.75));
+        assert_eq!(graph.edge_count(), 1);
+        assert_eq!(graph.pretty(), "1: 2 (0.75)\n2: 1 (0.75)\n3:\n");
+    }
+
+    #[test]
+    fn test_load_weights() { //Two columns default to weight 1.0; a negative weight is rejected with its line
+        let path = std::env::temp_dir().join(format!("fga_weighted_{}.txt", std::process::id()));
+        std::fs::write(&path, "0 1 2.5\n1 2\n").unwrap();
+        let graph = WeightedGraph::load_from_file(path.to_str().unwrap()).unwrap();
+        assert_eq!((graph.weight(0, 1), graph.weight(2, 1)), (Some(2.5), Some(1.0)));
+        std::fs::write(&path, "0 1 2.5\n1 2 -1\n").unwrap();
+        assert!(matches!(WeightedGraph::load_from_file(path.to_str().unwrap()), Err(GraphError::Parse { line: 2, .. })));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,42 @@
+//Integration test: the programs in examples/ are compiled into this test and run on the 200-node fixture
+//They only use the public API, so this also fails if something they need stops being exported
+use facebook_graph_analysis::error::GraphError;
+
+#[allow(dead_code)] //Each example's main is not called here
+#[path = "../examples/recommend.rs"]
+mod recommend;
+#[allow(dead_code)]
+#[path = "../examples/communities.rs"]
+mod communities;
+#[allow(dead_code)]
+#[path = "../examples/compare_snapshots.rs"]
+mod compare_snapshots;
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/snapshot_200.txt");
+const MESSY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/messy_edges.txt");
+
+fn run(example: fn(&[String], &mut Vec<u8>) -> Result<(), GraphError>, args: &[&str]) -> Result<String, GraphError> {
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    let mut out = Vec::new();
+    example(&args, &mut out)?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn test_examples_run_on_fixture() { //Each example prints its summary (and communities writes a partition covering every node); bad arguments are errors, not panics
+    let text = run(recommend::run, &[FIXTURE, "0", "3"]).unwrap();
+    assert!(text.starts_with("Node 0 has ") && text.lines().count() <= 4, "{}", text);
+    assert!(matches!(run(recommend::run, &[FIXTURE, "100000"]), Err(GraphError::UnknownNode(100_000))));
+    assert!(matches!(run(recommend::run, &[FIXTURE]), Err(GraphError::InvalidParameter(_))));
+
+    let partition = std::env::temp_dir().join(format!("fga_example_partition_{}.txt", std::process::id()));
+    let text = run(communities::run, &[FIXTURE, partition.to_str().unwrap()]).unwrap();
+    assert!(text.contains("communities, modularity") && text.contains("Wrote the partition"), "{}", text);
+    assert_eq!(std::fs::read_to_string(&partition).unwrap().lines().count(), 200);
+    std::fs::remove_file(&partition).unwrap();
+
+    let text = run(compare_snapshots::run, &[FIXTURE, FIXTURE, "2"]).unwrap();
+    assert!(text.starts_with("Node Jaccard 1.0000, edge Jaccard 1.0000"), "{}", text);
+    let text = run(compare_snapshots::run, &[MESSY, FIXTURE, "2"]).unwrap();
+    assert_eq!(text.lines().count(), 4, "{}", text);
+}
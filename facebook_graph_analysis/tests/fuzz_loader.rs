@@ -0,0 +1,76 @@
+//Integration test: malformed input thrown at graph::fuzz_load, the loader's fuzz target
+//Every corpus file in tests/fixtures/fuzz has a pinned outcome; then a bounded loop of seeded random inputs checks that nothing panics
+//and that every graph that does load passes Graph::validate (fuzz_load checks that itself, so Ok is enough)
+use std::panic;
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::graph::fuzz_load;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/fuzz");
+
+fn outcome(result: &Result<(usize, usize), GraphError>) -> String { //"ok N M" (nodes, edges) or the error kind with its line
+    match result {
+        Ok((nodes, edges)) => format!("ok {} {}", nodes, edges),
+        Err(GraphError::Parse { line, .. }) => format!("parse {}", line),
+        Err(GraphError::Io(_)) => String::from("io"),
+        Err(GraphError::EmptyFile(_)) => String::from("empty"),
+        Err(other) => format!("unexpected {:?}", other),
+    }
+}
+
+fn load(bytes: &[u8]) -> Result<(usize, usize), GraphError> {
+    fuzz_load(bytes).map(|graph| (graph.num_nodes(), graph.num_edges()))
+}
+
+#[test]
+fn test_corpus_outcomes() { //Bad numbers and separators are Parse errors on their line, bad UTF-8 is an I/O error, odd whitespace and huge ids load fine
+    let expected = [
+        ("blank.txt", "empty"),
+        ("byte_order_mark.txt", "parse 1"),
+        ("huge_token.txt", "parse 2"),
+        ("invalid_utf8.txt", "io"),
+        ("long_line.txt", "ok 3 2"), //The 3000-id line is skipped like any line without exactly two ids
+        ("max_ids.txt", "ok 3 2"),
+        ("mixed_separators.txt", "parse 1"),
+        ("negative.txt", "parse 2"),
+        ("null_bytes.txt", "parse 2"),
+        ("only_self_loops.txt", "ok 2 0"),
+        ("whitespace_separators.txt", "ok 4 3"),
+    ];
+    let mut files: Vec<String> = std::fs::read_dir(CORPUS).unwrap().map(|e| e.unwrap().file_name().to_string_lossy().into_owned()).collect();
+    files.sort();
+    assert_eq!(files, expected.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>(), "every corpus file needs a pinned outcome");
+    for (name, want) in expected {
+        let bytes = std::fs::read(format!("{}/{}", CORPUS, name)).unwrap();
+        assert_eq!(outcome(&load(&bytes)), want, "{}", name);
+    }
+    let mut long = b"0 1\n".to_vec(); //One line of a million digits, built here rather than checked in
+    long.extend(std::iter::repeat_n(b'7', 1_000_000));
+    assert_eq!(outcome(&load(&long)), "parse 2");
+}
+
+#[test]
+fn test_random_bytes_never_panic() { //3000 seeded inputs drawn mostly from edge-list characters, so many of them load and the rest fail in varied places
+    const ALPHABET: &[u8] = b"0123456789012345678901234567890123456789           \n\n\n\n\t\r";
+    const JUNK: &[u8] = b"-,;#\x00\xff\xc3e+.x";
+    let mut rng = StdRng::seed_from_u64(2024);
+    let (mut loaded, mut rejected) = (0, 0);
+    for case in 0..3000 {
+        let len = rng.gen_range(0..200);
+        let bytes: Vec<u8> = (0..len)
+            .map(|_| match rng.gen_range(0..1000) {
+                0..=2 => rng.gen(),
+                3..=7 => JUNK[rng.gen_range(0..JUNK.len())],
+                _ => ALPHABET[rng.gen_range(0..ALPHABET.len())],
+            })
+            .collect();
+        match panic::catch_unwind(|| load(&bytes)) {
+            Ok(Ok(_)) => loaded += 1,
+            Ok(Err(GraphError::Corrupt(problem))) => panic!("case {}: loaded an inconsistent graph ({}) from {:?}", case, problem, bytes),
+            Ok(Err(_)) => rejected += 1,
+            Err(_) => panic!("case {}: the loader panicked on {:?}", case, bytes),
+        }
+    }
+    assert!(loaded > 100 && rejected > 100, "{} loaded, {} rejected", loaded, rejected);
+}
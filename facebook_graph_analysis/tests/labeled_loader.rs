@@ -0,0 +1,55 @@
+//Integration test: labels files with quoted and escaped labels, both ways of handling one label on several ids, and edge lists keyed by strings
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::graph::GraphRead;
+use facebook_graph_analysis::analysis::closeness_centrality;
+use facebook_graph_analysis::labels::{split_quoted, DuplicateLabelPolicy, LabeledGraph, NodeId};
+
+#[test]
+fn test_quoted_labels_and_duplicate_policies() {
+    assert_eq!(split_quoted(r#"7 "Ada  Lovelace""#), Some(vec![String::from("7"), String::from("Ada  Lovelace")]));
+    assert_eq!(split_quoted(r#"8 "the \"Duke\"" \\ a\ b """#), Some(["8", "the \"Duke\"", "\\", "a b", ""].map(String::from).to_vec()));
+    assert_eq!((split_quoted(r#"9 "open"#), split_quoted("9 x\\")), (None, None));
+
+    let dir = std::env::temp_dir().join(format!("fga_labels_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (edges, labels) = (dir.join("edges.txt"), dir.join("labels.txt"));
+    std::fs::write(&edges, "0 1\n1 2\n3 4\n5 4\n").unwrap();
+    std::fs::write(&labels, "# id label\n0 \"Ada Lovelace\"\n1 \"Grace \\\"Amazing\\\" Hopper\"\n3 Alan\n1 \"Grace \\\"Amazing\\\" Hopper\"\n\n5 \"Ada Lovelace\"\n0\t\"Ada Lovelace\"\n9 Alan\n").unwrap();
+    let (edges, labels) = (edges.to_string_lossy().into_owned(), labels.to_string_lossy().into_owned());
+
+    let named = LabeledGraph::load_with_labels(&edges, &labels, DuplicateLabelPolicy::Merge).unwrap();
+    assert_eq!(named.labels(), ["Ada Lovelace", "Grace \"Amazing\" Hopper", "2", "Alan", "4"]); //2 and 4 have no label and keep their number
+    assert_eq!(named.duplicates, vec![(String::from("Ada Lovelace"), vec![NodeId(0), NodeId(5)]), (String::from("Alan"), vec![NodeId(3), NodeId(9)])]);
+    assert_eq!((named.graph.num_nodes(), named.graph.num_edges()), (5, 4)); //5 is the same node as 0 (bringing the edge to 4); 9 has no edges
+    assert!(named.graph.has_edge(named.node_index("Ada Lovelace").unwrap(), named.node_index("4").unwrap()) && named.node_index("5").is_none());
+
+    match LabeledGraph::load_with_labels(&edges, &labels, DuplicateLabelPolicy::Error) {
+        Err(GraphError::Parse { line, content }) => assert_eq!((line, content.as_str()), (7, "5 \"Ada Lovelace\"")),
+        other => panic!("expected a Parse error, got {:?}", other),
+    }
+    std::fs::write(&labels, "1 Grace\n1 Ada\n").unwrap();
+    assert!(matches!(LabeledGraph::load_with_labels(&edges, &labels, DuplicateLabelPolicy::Merge), Err(GraphError::Parse { line: 2, .. })));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_string_node_ids() { //Labels are numbered in order of first appearance, a repeated label is the same node, and results translate back to labels
+    let path = std::env::temp_dir().join(format!("fga_string_ids_{}.txt", std::process::id()));
+    std::fs::write(&path, "# follower followee\nalice bob\nbob carol\n\"dan the man\" alice\nbob alice\ncarol carol\nerin dan\\ the\\ man\n").unwrap();
+    let labeled = LabeledGraph::load(&path.to_string_lossy()).unwrap();
+    assert_eq!(labeled.labels(), ["alice", "bob", "carol", "dan the man", "erin"]);
+    assert_eq!((labeled.graph.num_nodes(), labeled.graph.num_edges()), (5, 4)); //"bob alice" repeats an edge and "carol carol" only the node
+    for (index, label) in labeled.labels().iter().enumerate() {
+        assert_eq!((labeled.node_index(label), labeled.node_label(index)), (Some(index), Some(label.as_str())));
+    }
+    assert_eq!((labeled.node_index("mallory"), labeled.node_label(5)), (None, None));
+    assert!(labeled.graph.has_edge(labeled.node_index("erin").unwrap(), labeled.node_index("dan the man").unwrap()));
+    let (top, _) = closeness_centrality(&labeled.graph)[0];
+    assert_eq!(labeled.node_label(top), Some("alice"));
+
+    std::fs::write(&path, "alice bob\nalice bob carol\n").unwrap();
+    assert!(matches!(LabeledGraph::load(&path.to_string_lossy()), Err(GraphError::Parse { line: 2, .. })));
+    std::fs::write(&path, "# nothing\n").unwrap();
+    assert!(matches!(LabeledGraph::load(&path.to_string_lossy()), Err(GraphError::EmptyFile(_))));
+    std::fs::remove_file(&path).unwrap();
+}
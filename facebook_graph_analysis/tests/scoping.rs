@@ -0,0 +1,63 @@
+//Integration test: a run scoped with node lists (--exclude-nodes / --include-only) must never mention an excluded id
+//Runs the pipeline on the 200-node fixture with a few ids excluded, writes every report and CSV, and scans them for those ids
+use std::collections::HashSet;
+use facebook_graph_analysis::analysis::{recommendations_for_all, SimilarityMetric};
+use facebook_graph_analysis::graph::Graph;
+use facebook_graph_analysis::labels::{read_node_list, NodeId};
+use facebook_graph_analysis::pipeline::{AnalysisResults, StalePolicy};
+use facebook_graph_analysis::report::{self, NumberFormat};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/snapshot_200.txt");
+const EXCLUDED: [&str; 3] = ["150", "177", "199"]; //Larger than any degree, reach or community id the fixture produces, so a bare match can only be a node id
+
+fn integer_tokens(text: &str) -> HashSet<&str> { //Whole numbers in the text; decimals such as 150.25 stay one token and never match an id
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.')).filter(|t| !t.is_empty()).collect()
+}
+
+#[test]
+fn test_excluded_nodes_never_appear_in_outputs() {
+    let dir = std::env::temp_dir().join(format!("fga_scoping_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let list = dir.join("exclude.txt");
+    std::fs::write(&list, "# people who opted out\n150\n\n177 # duplicate account\n199\n").unwrap();
+    let exclude = read_node_list(&list.to_string_lossy()).unwrap();
+    assert_eq!(exclude.len(), 3);
+
+    let (graph, excluded) = Graph::load_from_file(FIXTURE).unwrap().restrict_to(None, &exclude);
+    assert_eq!((graph.num_nodes(), excluded), (197, 3));
+    let mut results = AnalysisResults::for_graph(&graph);
+    results.excluded_nodes = excluded;
+    results.ensure_all(&graph, 10);
+    assert_eq!(results.stats.as_ref().unwrap().excluded_nodes, 3);
+
+    let format = NumberFormat::default();
+    let mut artifacts = Vec::new();
+    for writer in [report::write_text, report::write_markdown, report::write_json] {
+        let mut out = Vec::new();
+        writer(&results, &graph, StalePolicy::Refuse, &mut out, &format).unwrap();
+        artifacts.push(String::from_utf8(out).unwrap());
+    }
+    let features = dir.join("features.csv");
+    report::write_node_features_csv(&results, &graph, StalePolicy::Refuse, &features.to_string_lossy(), &format).unwrap();
+    let recommendations = dir.join("recommendations.csv");
+    report::write_recommendations_csv(&recommendations.to_string_lossy(), &recommendations_for_all(&graph, 5, 1, SimilarityMetric::Jaccard), &graph, &format, None).unwrap();
+    artifacts.push(std::fs::read_to_string(&features).unwrap());
+    artifacts.push(std::fs::read_to_string(&recommendations).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    for text in &artifacts {
+        let tokens = integer_tokens(text);
+        for id in EXCLUDED {
+            assert!(!tokens.contains(id), "excluded node {} appears in an output:\n{}", id, text);
+        }
+    }
+}
+
+#[test]
+fn test_include_only_keeps_listed_nodes() { //An allow list keeps exactly the listed nodes that exist, with the edges among them
+    let graph = Graph::load_from_file(FIXTURE).unwrap();
+    let allowed: HashSet<NodeId> = (0..20).chain([5000]).map(NodeId).collect();
+    let (scoped, excluded) = graph.restrict_to(Some(&allowed), &HashSet::from([NodeId(3)]));
+    assert_eq!((scoped.num_nodes(), excluded), (19, 181));
+    assert!(scoped.adjacency().values().flatten().all(|n| *n < 20 && *n != 3));
+}
@@ -0,0 +1,107 @@
+//Integration test: every public analysis on the empty graph and on a one-node graph (what a file holding a single self-loop loads as)
+//Checks the conventions documented at the top of analysis.rs: empty or one-entry per-node results, 0.0 averages, 0 counts, empty pair lists
+use std::collections::HashMap;
+use facebook_graph_analysis::analysis::*;
+use facebook_graph_analysis::community::{greedy_modularity, label_propagation, louvain, modularity};
+use facebook_graph_analysis::error::GraphError;
+use facebook_graph_analysis::graph::Graph;
+use facebook_graph_analysis::pipeline::AnalysisResults;
+use facebook_graph_analysis::labels::InternalId;
+
+fn one_node_graph() -> Graph { //Loaded from "5 5": the self-loop is dropped but node 5 stays
+    let path = std::env::temp_dir().join(format!("fga_self_loop_{}.txt", std::process::id()));
+    std::fs::write(&path, "5 5\n").unwrap();
+    let graph = Graph::load_from_file(&path.to_string_lossy()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!((graph.num_nodes(), graph.num_edges()), (1, 0));
+    assert!(graph.adjacency()[&5].is_empty());
+    graph
+}
+
+fn assert_scalars_are_zero(graph: &Graph) { //Averages over pairs or edges, and counts
+    assert_eq!(average_distance(graph), 0.0);
+    assert_eq!(average_distance_weighted(graph), 0.0);
+    assert_eq!(median_distance(graph), 0.0);
+    assert_eq!(average_distance_sampled(graph, 5, 1).mean, 0.0);
+    assert_eq!(trimmed_average_distance(graph, 0.1).unwrap(), 0.0);
+    assert!(distance_histogram(graph).is_empty());
+    assert_eq!(density(graph), 0.0);
+    assert_eq!(average_clustering(graph), 0.0);
+    assert_eq!(transitivity(graph).unwrap(), 0.0);
+    assert_eq!(transitivity_sampled(graph, 100, 1).wedges_sampled, 0);
+    assert_eq!(connected_pair_fraction(graph), 0.0);
+    assert_eq!(count_triangles(graph), 0);
+    assert_eq!((diameter(graph), diameter_double_sweep(graph), radius(graph).unwrap()), (0, 0, 0));
+    assert!(degree_assortativity(graph).is_nan());
+    assert_eq!(modularity(graph, &HashMap::new()), 0.0);
+}
+
+fn assert_pair_lists_are_empty(graph: &Graph) {
+    assert!(most_similar_pairs(graph, 5).is_empty());
+    assert!(most_similar_pairs_by_lift(graph, 5).is_empty());
+    assert!(co_neighbor_counts(graph, 1).is_empty());
+    assert!(recommendations_for_all(graph, 3, 0, SimilarityMetric::Jaccard).is_empty());
+    assert_eq!(similarity_graph(graph, 0.1, SimilarityMetric::Jaccard, &SimilarityGraphOptions::default()).unwrap().0.num_edges, 0);
+    assert!(bridges(graph).is_empty() && articulation_points(graph).is_empty());
+}
+
+#[test]
+fn test_empty_graph() {
+    let graph = Graph::new();
+    assert_scalars_are_zero(&graph);
+    assert_pair_lists_are_empty(&graph);
+    assert!(closeness_centrality(&graph).is_empty());
+    assert!(closeness_centrality_weighted(&graph).is_empty());
+    assert!(harmonic_centrality(&graph).is_empty());
+    assert!(betweenness_centrality(&graph).is_empty());
+    assert!(pagerank(&graph, 0.85, PAGERANK_MAX_ITERATIONS, 1e-9).unwrap().is_empty());
+    assert!(closeness_centrality_approx(&graph, 10, 1).scores.is_empty());
+    assert!(local_clustering(&graph).is_empty() && triangles_per_node(&graph).is_empty());
+    assert!(k_core_decomposition(&graph).is_empty() && eccentricities(&graph).is_empty());
+    assert!(two_hop_reach(&graph).is_empty() && two_hop_reach_approx(&graph, 8).unwrap().is_empty());
+    assert!(distance_profiles(&graph, 3).is_empty());
+    assert!(structural_equivalence_classes(&graph).is_empty());
+    assert!(classify_roles(&graph, &RoleOptions::default()).is_empty());
+    assert!(connected_components(&graph).is_empty());
+    assert_eq!((num_components(&graph), is_connected(&graph), giant_component_fraction(&graph)), (0, true, 0.0));
+    assert!(label_propagation(&graph, 1).is_empty());
+    assert_eq!(louvain(&graph, 1.0), (HashMap::new(), 0.0));
+    assert_eq!(greedy_modularity(&graph).1, vec![0.0]);
+    assert!(matches!(closeness_of(&graph, InternalId(0)), Err(GraphError::UnknownNode(0))));
+    assert!(matches!(local_clustering_of(&graph, InternalId(0)), Err(GraphError::UnknownNode(0))));
+
+    let mut results = AnalysisResults::for_graph(&graph);
+    results.ensure_all(&graph, 5);
+    assert_eq!(results.stats.unwrap().average_distance, 0.0);
+}
+
+#[test]
+fn test_one_node_graph() {
+    let graph = one_node_graph();
+    let node = *graph.adjacency().keys().next().unwrap();
+    assert_scalars_are_zero(&graph);
+    assert_pair_lists_are_empty(&graph);
+    assert_eq!(closeness_centrality(&graph), vec![(node, 0.0)]);
+    assert_eq!(closeness_centrality_weighted(&graph), vec![(node, 0.0)]);
+    assert_eq!(harmonic_centrality(&graph), vec![(node, 0.0)]);
+    assert_eq!(betweenness_centrality(&graph), vec![(node, 0.0)]);
+    assert_eq!(pagerank(&graph, 0.85, PAGERANK_MAX_ITERATIONS, 1e-9).unwrap(), vec![(node, 1.0)]); //The whole (unit) rank sits on the only node
+    assert_eq!(closeness_of(&graph, InternalId(node)).unwrap(), 0.0);
+    assert_eq!(local_clustering_of(&graph, InternalId(node)).unwrap(), 0.0);
+    assert_eq!(triangles_per_node(&graph)[&node], 0);
+    assert_eq!(k_core_decomposition(&graph)[&node], 0);
+    assert_eq!(eccentricities(&graph)[&node], 0);
+    assert_eq!(two_hop_reach(&graph)[&node], 0);
+    assert_eq!(two_hop_reach_approx(&graph, 8).unwrap()[&node], 0.0);
+    assert_eq!(distance_profiles(&graph, 3)[&node].median, None);
+    assert_eq!(structural_equivalence_classes(&graph), vec![vec![node]]);
+    assert_eq!(connected_components(&graph), vec![vec![node]]);
+    assert_eq!((num_components(&graph), is_connected(&graph), giant_component_fraction(&graph)), (1, true, 1.0));
+    assert_eq!(louvain(&graph, 1.0).0[&node], 0);
+    assert_eq!(label_propagation(&graph, 1)[&node], 0);
+
+    let mut results = AnalysisResults::for_graph(&graph);
+    results.ensure_all(&graph, 5);
+    assert_eq!(results.stats.unwrap().average_distance, 0.0);
+    assert_eq!(results.closeness.unwrap(), vec![(node, 0.0)]);
+}
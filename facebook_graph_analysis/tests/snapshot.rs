@@ -0,0 +1,38 @@
+//Integration test: runs the whole pipeline on a fixed 200-node graph and compares the JSON report with a committed golden file
+//Any change to a metric, a tie-break or the number formatting makes this fail, so refactors cannot silently change results
+//If a change is intended, regenerate the golden file with:
+//    UPDATE_GOLDEN=1 cargo test --test snapshot
+//and commit the updated tests/golden/snapshot_200.json together with the change
+use facebook_graph_analysis::graph::Graph;
+use facebook_graph_analysis::parallel::ParallelismConfig;
+use facebook_graph_analysis::pipeline::{AnalysisResults, StalePolicy};
+use facebook_graph_analysis::report::{self, NumberFormat};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/snapshot_200.txt"); //Watts–Strogatz graph: generate --model ws --nodes 200 --k 6 --beta 0.15 --seed 7
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/snapshot_200.json");
+
+fn render(parallelism: ParallelismConfig) -> String { //Full pipeline report as JSON text
+    let graph = Graph::load_from_file(FIXTURE).unwrap();
+    let mut results = AnalysisResults::for_graph(&graph);
+    results.parallelism = parallelism;
+    results.ensure_all(&graph, 10);
+    let mut out = Vec::new();
+    report::write_json(&results, &graph, StalePolicy::Refuse, &mut out, &NumberFormat::default()).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_pipeline_matches_golden_report() {
+    let report = render(ParallelismConfig::default());
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(GOLDEN, &report).unwrap();
+        return;
+    }
+    let golden = std::fs::read_to_string(GOLDEN).expect("golden file missing, run with UPDATE_GOLDEN=1 to create it");
+    assert!(report == golden, "pipeline output drifted from {} (rerun with UPDATE_GOLDEN=1 if the change is intended)", GOLDEN);
+}
+
+#[test]
+fn test_thread_count_does_not_change_report() { //Sequential and parallel runs must produce byte-identical reports
+    assert_eq!(render(ParallelismConfig::sequential()), render(ParallelismConfig::with_threads(4)));
+}
@@ -0,0 +1,87 @@
+//Integration test: run_scheduled runs the cheap analyses first and hands each finished section to the caller before the slow ones start
+//A slow betweenness task is injected; when it begins, the degree and distance sections must already have been flushed to the writer
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use facebook_graph_analysis::generate::barabasi_albert;
+use facebook_graph_analysis::pipeline::{plan, planned_tasks, AnalysisResults, GraphSize, PlannedAnalysis, Section, StalePolicy, Task, Variant};
+use facebook_graph_analysis::report::{write_section, write_text, NumberFormat};
+
+#[derive(Clone, Default)]
+struct FlushLog { //Writer that only makes bytes visible in `flushed` once flush is called, like a buffered stdout
+    pending: Rc<RefCell<Vec<u8>>>,
+    flushed: Rc<RefCell<Vec<u8>>>,
+}
+
+impl Write for FlushLog {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.pending.borrow_mut().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.flushed.borrow_mut().append(&mut self.pending.borrow_mut());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_cheap_sections_are_flushed_before_slow_ones_finish() {
+    let graph = barabasi_albert(60, 2, 3).unwrap();
+    let plan = plan(GraphSize::from(&graph), &[(PlannedAnalysis::Closeness, None), (PlannedAnalysis::Diameter, Some(Variant::Approximate)), (PlannedAnalysis::SimilarPairs, None)]); //Exact eccentricities would cost about as much as betweenness
+    let log = FlushLog::default();
+    let seen_by_slow_task = Rc::new(RefCell::new(String::new()));
+
+    let mut tasks: Vec<Task> = planned_tasks(&plan, 5).into_iter().filter(|t| t.section != Section::Betweenness).collect();
+    let (flushed, seen) = (log.flushed.clone(), seen_by_slow_task.clone());
+    tasks.push(Task {
+        section: Section::Betweenness,
+        run: Box::new(move |results, graph| {
+            *seen.borrow_mut() = String::from_utf8(flushed.borrow().clone()).unwrap(); //What a reader of the output has at the moment the slow analysis starts
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            results.ensure_betweenness(graph);
+        }),
+    });
+
+    let mut results = AnalysisResults::for_graph(&graph);
+    let mut order = Vec::new();
+    let mut out = log.clone();
+    results
+        .run_scheduled(&graph, &plan, tasks, |results, section| {
+            order.push(section);
+            write_section(results, &graph, StalePolicy::Refuse, section, &mut out, &NumberFormat::default())?;
+            out.flush()?;
+            Ok(())
+        })
+        .unwrap();
+
+    assert_eq!(order, vec![Section::Degrees, Section::SimilarPairs, Section::Distances, Section::Closeness, Section::Betweenness, Section::Roles]);
+    let seen = seen_by_slow_task.borrow();
+    assert!(seen.contains("Degree Distribution") && seen.contains("Average Distance") && seen.contains("Top 5 Closeness"));
+    assert!(!seen.contains("Betweenness"));
+
+    //The consolidated report still lists every section in report order
+    let mut report = Vec::new();
+    write_text(&results, &graph, StalePolicy::Refuse, &mut report, &NumberFormat::default()).unwrap();
+    let report = String::from_utf8(report).unwrap();
+    let positions: Vec<usize> = ["Degree Distribution", "Average Distance", "Top 5 Closeness", "Top 5 Betweenness", "Top Jaccard", "Node Roles"].iter().map(|h| report.find(h).unwrap()).collect();
+    assert!(positions.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn test_priority_overrides_cost_order() { //Sections named in plan.priority run first, in the given order; the rest stay cheapest first
+    let graph = barabasi_albert(40, 2, 1).unwrap();
+    let mut plan = plan(GraphSize::from(&graph), &[(PlannedAnalysis::Closeness, None)]);
+    plan.priority = vec![Section::Roles, Section::Betweenness];
+    let mut order = Vec::new();
+    let mut results = AnalysisResults::for_graph(&graph);
+    results.run_scheduled(&graph, &plan, planned_tasks(&plan, 5), |_, section| {
+        order.push(section);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(order, vec![Section::Roles, Section::Betweenness, Section::Degrees, Section::Distances, Section::Closeness]);
+    assert!(results.roles.is_some() && results.betweenness.is_some());
+    assert_eq!(Section::from_name("similarity").unwrap(), Section::SimilarPairs);
+    assert!(Section::from_name("everything").is_err());
+}